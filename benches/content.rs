@@ -0,0 +1,51 @@
+#![cfg_attr(feature = "nightly", feature(test))]
+#![cfg(feature = "nightly")]
+extern crate test;
+
+use lopdf::content::{Content, Operation};
+use lopdf::Object;
+
+fn operations() -> Content {
+    let operations = (0..10_000)
+        .map(|i| match i % 4 {
+            0 => Operation::new("re", vec![i.into(), 10.into(), 20.5.into(), 30.into()]),
+            1 => Operation::new("Tf", vec!["F1".into(), 12.into()]),
+            2 => Operation::new("Tj", vec![Object::string_literal("Hello (World)")]),
+            _ => Operation::new("f", vec![]),
+        })
+        .collect();
+    Content { operations }
+}
+
+#[bench]
+fn bench_content_encode(b: &mut test::Bencher) {
+    let content = operations();
+    b.iter(|| content.encode().unwrap())
+}
+
+#[bench]
+fn bench_content_encode_into(b: &mut test::Bencher) {
+    let content = operations();
+    let mut buffer = Vec::new();
+    b.iter(|| {
+        buffer.clear();
+        content.encode_into(&mut buffer).unwrap();
+    })
+}
+
+/// Parsing and encoding again a content mostly of operations of two operands or less, with the occasional marked
+/// content property list.
+#[bench]
+fn bench_content_decode_encode(b: &mut test::Bencher) {
+    let operations = (0..10_000)
+        .map(|i| match i % 5 {
+            0 => Operation::new("cm", vec![1.into(), 0.into(), 0.into(), 1.into(), i.into(), 5.into()]),
+            1 => Operation::new("Td", vec![10.into(), i.into()]),
+            2 => Operation::new("Tj", vec![Object::string_literal("Hello")]),
+            3 => Operation::new("BDC", vec!["Span".into(), lopdf::dictionary! { "MCID" => i }.into()]),
+            _ => Operation::new("EMC", vec![]),
+        })
+        .collect();
+    let data = Content { operations }.encode().unwrap();
+    b.iter(|| Content::decode(&data).unwrap().encode().unwrap())
+}
@@ -1,4 +1,5 @@
-#![feature(test)]
+#![cfg_attr(feature = "nightly", feature(test))]
+#![cfg(feature = "nightly")]
 extern crate test;
 use test::Bencher;
 
@@ -52,3 +53,15 @@ fn bench_boolean_write(b: &mut test::Bencher) {
         doc.save_to(&mut buf).unwrap();
     })
 }
+
+#[bench]
+fn bench_literal_string_write(b: &mut test::Bencher) {
+    // A megabyte of text with parentheses, backslashes and line ends to escape.
+    let text = b"(Hello) \\World\r\n)(".repeat(1 << 16);
+    let mut doc = lopdf::Document::new();
+    doc.add_object(Object::String(text, lopdf::StringFormat::Literal));
+    b.iter(|| {
+        let mut buf = ::std::io::Cursor::new(Vec::<u8>::new());
+        doc.save_to(&mut buf).unwrap();
+    })
+}
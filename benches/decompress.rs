@@ -0,0 +1,60 @@
+#![cfg_attr(feature = "nightly", feature(test))]
+#![cfg(feature = "nightly")]
+use std::fs::File;
+use std::io::{Cursor, Read};
+
+extern crate test;
+use lopdf::{Document, Object, Stream};
+
+fn load_example() -> Document {
+    let mut buffer = Vec::new();
+    File::open("assets/example.pdf")
+        .unwrap()
+        .read_to_end(&mut buffer)
+        .unwrap();
+    Document::load_from(Cursor::new(&buffer)).unwrap()
+}
+
+#[bench]
+fn bench_decompress_serial(b: &mut test::test::Bencher) {
+    let doc = load_example();
+
+    b.iter(|| {
+        let mut doc = doc.clone();
+        for object in doc.objects.values_mut() {
+            if let Object::Stream(ref mut stream) = *object {
+                stream.decompress();
+            }
+        }
+    })
+}
+
+/// With the `parallel` feature, this decodes the streams on several threads.
+#[bench]
+fn bench_decompress(b: &mut test::test::Bencher) {
+    let doc = load_example();
+
+    b.iter(|| {
+        let mut doc = doc.clone();
+        doc.decompress();
+    })
+}
+
+/// Small streams, as `/ToUnicode` maps are, decoded one after the other with the codecs of the thread.
+#[bench]
+fn bench_decompress_small_streams(b: &mut test::test::Bencher) {
+    let streams: Vec<Stream> = (0..1000)
+        .map(|i| {
+            let map = format!("<{:04X}> <{:04X}>\n", i, i + 0x41).repeat(20);
+            let mut stream = Stream::new(lopdf::dictionary! {}, map.into_bytes());
+            stream.compress().unwrap();
+            stream
+        })
+        .collect();
+
+    b.iter(|| {
+        for stream in &streams {
+            test::black_box(stream.decompressed_content().unwrap());
+        }
+    })
+}
@@ -0,0 +1,37 @@
+#![cfg_attr(feature = "nightly", feature(test))]
+#![cfg(feature = "nightly")]
+use std::fs::File;
+use std::io::{Cursor, Read};
+
+extern crate test;
+use lopdf::Document;
+
+fn load_example() -> Document {
+    let mut buffer = Vec::new();
+    File::open("assets/example.pdf")
+        .unwrap()
+        .read_to_end(&mut buffer)
+        .unwrap();
+    Document::load_from(Cursor::new(&buffer)).unwrap()
+}
+
+#[bench]
+fn bench_extract_text_serial(b: &mut test::test::Bencher) {
+    let doc = load_example();
+    let pages: Vec<u32> = doc.get_pages().keys().copied().collect();
+
+    b.iter(|| {
+        for &page in &pages {
+            let _ = doc.extract_text(&[page]);
+        }
+    })
+}
+
+/// With the `parallel` feature, this extracts the pages on several threads.
+#[bench]
+fn bench_extract_text_pages(b: &mut test::test::Bencher) {
+    let doc = load_example();
+    let pages: Vec<u32> = doc.get_pages().keys().copied().collect();
+
+    b.iter(|| doc.extract_text_pages(&pages))
+}
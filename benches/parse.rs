@@ -1,4 +1,5 @@
-#![feature(test)]
+#![cfg_attr(feature = "nightly", feature(test))]
+#![cfg(feature = "nightly")]
 use std::fs::File;
 use std::io::{Cursor, Read};
 
@@ -17,3 +18,18 @@ fn bench_load(b: &mut test::test::Bencher) {
         Document::load_from(Cursor::new(&buffer)).unwrap();
     })
 }
+
+#[bench]
+fn bench_load_many_objects(b: &mut test::test::Bencher) {
+    // A synthetic document of 300,000 small objects.
+    let mut doc = Document::with_version("1.5");
+    for i in 0..300_000i64 {
+        doc.add_object(lopdf::dictionary! { "Type" => "Annot", "Index" => i });
+    }
+    let mut buffer = Vec::new();
+    doc.save_to(&mut buffer).unwrap();
+
+    b.iter(|| {
+        Document::load_from(Cursor::new(&buffer)).unwrap();
+    })
+}
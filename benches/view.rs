@@ -0,0 +1,136 @@
+#![cfg_attr(feature = "nightly", feature(test))]
+#![cfg(all(feature = "nightly", feature = "borrowed"))]
+use std::fs::File;
+use std::io::{Cursor, Read};
+
+extern crate test;
+use lopdf::{dictionary, Document, DocumentView, ObjectId, Stream};
+
+fn read_example() -> Vec<u8> {
+    let mut buffer = Vec::new();
+    File::open("assets/example.pdf")
+        .unwrap()
+        .read_to_end(&mut buffer)
+        .unwrap();
+    buffer
+}
+
+#[bench]
+fn bench_extract_text_owned(b: &mut test::test::Bencher) {
+    let buffer = read_example();
+
+    b.iter(|| {
+        let doc = Document::load_from(Cursor::new(&buffer)).unwrap();
+        let pages: Vec<u32> = doc.get_pages().keys().copied().collect();
+        doc.extract_text(&pages)
+    })
+}
+
+#[bench]
+fn bench_extract_text_view(b: &mut test::test::Bencher) {
+    let buffer = read_example();
+
+    b.iter(|| {
+        let view = DocumentView::load(&buffer).unwrap();
+        let pages: Vec<u32> = view.get_pages().keys().copied().collect();
+        view.extract_text(&pages)
+    })
+}
+
+/// A file of many small objects in object streams, as word processors export them, with the xref stream giving
+/// the container of each object.
+fn object_stream_file(streams: u32, objects_per_stream: u32) -> (Vec<u8>, Vec<ObjectId>) {
+    let mut file = b"%PDF-1.5\n".to_vec();
+    let mut entries = vec![(0u8, 0u32, 0xFFFFu16)];
+    let mut ids = Vec::new();
+    let mut next_id = streams + 1;
+    for container in 1..=streams {
+        let mut header = String::new();
+        let mut objects = Vec::new();
+        for index in 0..objects_per_stream {
+            let id = next_id;
+            next_id += 1;
+            header.push_str(&format!("{} {} ", id, objects.len()));
+            objects.extend_from_slice(
+                format!(
+                    "<< /Type /StructElem /S /P /K {} /Pg 3 0 R /A << /O /Layout /SpaceAfter 6 >> /T (p{}) >>\n",
+                    index, id
+                )
+                .as_bytes(),
+            );
+            entries.push((2, container, index as u16));
+            ids.push((id, 0));
+        }
+        let content = [header.as_bytes(), &objects].concat();
+        let mut stream = Stream::new(
+            dictionary! { "Type" => "ObjStm", "N" => objects_per_stream as i64, "First" => header.len() as i64 },
+            content,
+        );
+        stream.compress().unwrap();
+        entries.insert(container as usize, (1, file.len() as u32, 0));
+        file.extend_from_slice(
+            format!(
+                "{} 0 obj\n<< /Type /ObjStm /N {} /First {} /Filter /FlateDecode /Length {} >>\nstream\n",
+                container,
+                objects_per_stream,
+                header.len(),
+                stream.content().len()
+            )
+            .as_bytes(),
+        );
+        file.extend_from_slice(stream.content());
+        file.extend_from_slice(b"\nendstream\nendobj\n");
+    }
+
+    let xref_id = next_id;
+    let xref_offset = file.len() as u32;
+    entries.push((1, xref_offset, 0));
+    let mut xref = Vec::new();
+    for (kind, value, index) in entries {
+        xref.push(kind);
+        xref.extend_from_slice(&value.to_be_bytes());
+        xref.extend_from_slice(&index.to_be_bytes());
+    }
+    file.extend_from_slice(
+        format!(
+            "{} 0 obj\n<< /Type /XRef /Size {} /W [1 4 2] /Root {} 0 R /Length {} >>\nstream\n",
+            xref_id,
+            xref_id + 1,
+            streams + 1,
+            xref.len()
+        )
+        .as_bytes(),
+    );
+    file.extend_from_slice(&xref);
+    file.extend_from_slice(format!("\nendstream\nendobj\nstartxref\n{}\n%%EOF\n", xref_offset).as_bytes());
+    (file, ids)
+}
+
+#[bench]
+fn bench_view_object_stream_objects(b: &mut test::test::Bencher) {
+    let (file, ids) = object_stream_file(50, 200);
+
+    b.iter(|| {
+        let view = DocumentView::load(&file).unwrap();
+        ids.iter().filter(|&&id| view.get_object(id).is_ok()).count()
+    })
+}
+
+/// With the `parallel` feature, this decodes the object streams on several threads first.
+#[bench]
+fn bench_view_preloaded_object_stream_objects(b: &mut test::test::Bencher) {
+    let (file, ids) = object_stream_file(50, 200);
+
+    b.iter(|| {
+        let view = DocumentView::load(&file).unwrap();
+        view.preload_object_streams();
+        ids.iter().filter(|&&id| view.get_object(id).is_ok()).count()
+    })
+}
+
+#[bench]
+fn bench_load_object_stream_objects(b: &mut test::test::Bencher) {
+    let (file, _) = object_stream_file(50, 200);
+
+    b.iter(|| Document::load_mem(&file).unwrap().objects.len())
+}
@@ -0,0 +1,77 @@
+//! Write a page whose background is a vertical gradient, with a title over it and a square filled through a
+//! shading pattern with a radial gradient, which is not extended past its circle and so paints a disk.
+//!
+//! Usage: `cargo run --example gradient -- gradient.pdf`
+
+use std::env;
+use std::process;
+
+use lopdf::content::{Content, Operation};
+use lopdf::geometry::IDENTITY_MATRIX;
+use lopdf::{BuiltinFont, Color, Document, DocumentBuilder, Object, PageBuilder};
+
+fn page(background: Vec<Operation>) -> lopdf::Result<Document> {
+    let mut doc = DocumentBuilder::new()
+        .page(
+            PageBuilder::a4()
+                .operations(background)
+                .text(72.0, 760.0, BuiltinFont::HelveticaBold, 32.0, "Gradients")
+                .build(),
+        )
+        .build()?;
+    let page_id = doc.page_iter().next().expect("one page");
+    let sky = doc.add_axial_shading(
+        Color::Rgb(0.1, 0.2, 0.5),
+        Color::Rgb(0.9, 0.95, 1.0),
+        [0.0, 842.0, 0.0, 0.0],
+        [true, true],
+    )?;
+    let sun = doc.add_radial_shading(
+        Color::Rgb(1.0, 1.0, 0.8),
+        Color::Rgb(1.0, 0.5, 0.0),
+        [297.0, 421.0, 0.0, 297.0, 421.0, 120.0],
+        [false, false],
+    )?;
+    let pattern = doc.add_shading_pattern(sun, IDENTITY_MATRIX);
+    doc.add_shading(page_id, "Sky", sky)?;
+    doc.add_pattern(page_id, "Sun", pattern)?;
+    Ok(doc)
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 2 {
+        eprintln!("Usage: {} <gradient.pdf>", args[0]);
+        process::exit(2);
+    }
+
+    // Painting a shading fills the whole clipping region, clipped here to the page.
+    let mut background = Content { operations: vec![] };
+    let rect = [0.0, 0.0, 595.0, 842.0]
+        .iter()
+        .map(|&value: &f64| Object::from(value))
+        .collect();
+    background.operations.push(Operation::new("re", rect));
+    background.operations.push(Operation::new("W", vec![]));
+    background.operations.push(Operation::new("n", vec![]));
+    background.paint_shading("Sky");
+    background.set_fill_pattern("Sun");
+    let square = [177.0, 301.0, 240.0, 240.0]
+        .iter()
+        .map(|&value: &f64| Object::from(value))
+        .collect();
+    background.operations.push(Operation::new("re", square));
+    background.operations.push(Operation::new("f", vec![]));
+
+    let mut doc = match page(background.operations) {
+        Ok(doc) => doc,
+        Err(err) => {
+            eprintln!("Cannot make the page: {}", err);
+            process::exit(1);
+        }
+    };
+    if let Err(err) = doc.save(&args[1]) {
+        eprintln!("Cannot write {}: {}", args[1], err);
+        process::exit(1);
+    }
+}
@@ -1,52 +1,19 @@
-#[macro_use]
-extern crate lopdf;
+use std::collections::{BTreeMap, BTreeSet};
 
-use std::collections::BTreeMap;
-
-use lopdf::content::{Content, Operation};
-use lopdf::{Bookmark, Document, Object, ObjectId, Stream};
+use lopdf::{Bookmark, BuiltinFont, DestinationFit, Document, DocumentBuilder, Object, ObjectId, PageBuilder};
 
 pub fn generate_fake_document() -> Document {
-    let mut doc = Document::with_version("1.5");
-    let pages_id = doc.new_object_id();
-    let font_id = doc.add_object(dictionary! {
-        "Type" => "Font",
-        "Subtype" => "Type1",
-        "BaseFont" => "Courier",
-    });
-    let resources_id = doc.add_object(dictionary! {
-        "Font" => dictionary! {
-            "F1" => font_id,
-        },
-    });
-    let content = Content {
-        operations: vec![
-            Operation::new("BT", vec![]),
-            Operation::new("Tf", vec!["F1".into(), 48.into()]),
-            Operation::new("Td", vec![100.into(), 600.into()]),
-            Operation::new("Tj", vec![Object::string_literal("Hello World!")]),
-            Operation::new("ET", vec![]),
-        ],
-    };
-    let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
-    let page_id = doc.add_object(dictionary! {
-        "Type" => "Page",
-        "Parent" => pages_id,
-        "Contents" => content_id,
-        "Resources" => resources_id,
-        "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
-    });
-    let pages = dictionary! {
-        "Type" => "Pages",
-        "Kids" => vec![page_id.into()],
-        "Count" => 1,
-    };
-    doc.objects.insert(pages_id, Object::Dictionary(pages));
-    let catalog_id = doc.add_object(dictionary! {
-        "Type" => "Catalog",
-        "Pages" => pages_id,
-    });
-    doc.trailer.set("Root", catalog_id);
+    let mut doc = DocumentBuilder::new()
+        .page(
+            PageBuilder::a4()
+                .text(100.0, 600.0, BuiltinFont::Courier, 48.0, "Hello World!")
+                .build(),
+        )
+        .build()
+        .unwrap();
+    let page_id = doc.page_iter().next().unwrap();
+    doc.add_named_destination("start", page_id, DestinationFit::Fit)
+        .unwrap();
 
     doc
 }
@@ -66,6 +33,7 @@ fn main() {
     // Collect all Documents Objects grouped by a map
     let mut documents_pages = BTreeMap::new();
     let mut documents_objects = BTreeMap::new();
+    let mut documents_destinations = BTreeMap::new();
     let mut document = Document::with_version("1.5");
 
     for mut doc in documents {
@@ -74,13 +42,30 @@ fn main() {
 
         max_id = doc.max_id + 1;
 
+        // Rename the named destinations already used by a previous Document, along with the links to them
+        let names: BTreeSet<Vec<u8>> = doc.named_destinations().into_keys().collect();
+        for name in names {
+            let mut new_name = name.clone();
+            let mut suffix = 1;
+            while documents_destinations.contains_key(&new_name)
+                || (new_name != name && doc.resolve_destination(&new_name).is_some())
+            {
+                suffix += 1;
+                new_name = [&name[..], format!("_{}", suffix).as_bytes()].concat();
+            }
+            if new_name != name {
+                doc.rename_named_destination(&name, &new_name).unwrap();
+            }
+        }
+        documents_destinations.extend(doc.named_destinations());
+
         documents_pages.extend(
             doc.get_pages()
-                .into_iter()
-                .map(|(_, object_id)| {
+                .into_values()
+                .map(|object_id| {
                     if !first {
                         let bookmark =
-                            Bookmark::new(String::from(format!("Page_{}", pagenum)), [0.0, 0.0, 1.0], 0, object_id);
+                            Bookmark::new(format!("Page_{}", pagenum), [0.0, 0.0, 1.0], 0, object_id);
                         document.add_bookmark(bookmark, None);
                         first = true;
                         pagenum += 1;
@@ -181,8 +166,8 @@ fn main() {
         dictionary.set(
             "Kids",
             documents_pages
-                .into_iter()
-                .map(|(object_id, _)| Object::Reference(object_id))
+                .into_keys()
+                .map(Object::Reference)
                 .collect::<Vec<_>>(),
         );
 
@@ -194,6 +179,7 @@ fn main() {
         let mut dictionary = dictionary.clone();
         dictionary.set("Pages", pages_object.0);
         dictionary.remove(b"Outlines"); // Outlines not supported in merged PDFs
+        dictionary.remove(b"Dests"); // Named destinations of all Documents are added below
 
         document
             .objects
@@ -202,6 +188,13 @@ fn main() {
 
     document.trailer.set("Root", catalog_object.0);
 
+    // Collect the named destinations of all Documents in a single name tree
+    let destinations = documents_destinations
+        .into_iter()
+        .map(|(name, destination)| (name, destination.to_object()))
+        .collect();
+    document.set_names("Dests", destinations).unwrap();
+
     // Update the max internal ID as wasn't updated before due to direct objects insertion
     document.max_id = document.objects.len() as u32;
 
@@ -213,10 +206,8 @@ fn main() {
 
     //Set all bookmarks to the PDF Object tree then set the Outlines to the Bookmark content map.
     if let Some(n) = document.build_outline() {
-        if let Ok(x) = document.get_object_mut(catalog_object.0) {
-            if let Object::Dictionary(ref mut dict) = x {
-                dict.set("Outlines", Object::Reference(n));
-            }
+        if let Ok(Object::Dictionary(ref mut dict)) = document.get_object_mut(catalog_object.0) {
+            dict.set("Outlines", Object::Reference(n));
         }
     }
 
@@ -0,0 +1,114 @@
+//! Build the pages of a document on several threads: each worker numbers the objects of its pages from a block of
+//! ids reserved for it, and the main thread adds them to the document and links the pages into the page tree.
+//! The content is built once on one thread and once on all of them, to compare the times.
+//!
+//! Usage: `cargo run --release --example parallel_pages -- output.pdf [pages]`
+
+use std::env;
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Document, IdBlock, Object, ObjectId, Stream};
+
+/// A page of curves, compressed, as a lot of drawing costs.
+fn page(pages_id: ObjectId, number: usize, ids: &mut IdBlock) -> Vec<(ObjectId, Object)> {
+    let mut operations = Vec::new();
+    for line in 0..4000 {
+        let t = (number * 4000 + line) as f64 / 50.0;
+        let (x, y) = (
+            297.0 + 250.0 * t.sin() * (t / 7.0).cos(),
+            421.0 + 350.0 * t.cos() * (t / 11.0).sin(),
+        );
+        operations.push(Operation::new(
+            if line == 0 { "m" } else { "l" },
+            vec![x.into(), y.into()],
+        ));
+    }
+    operations.push(Operation::new("S", vec![]));
+    let mut stream = Stream::new(dictionary! {}, Content { operations }.encode().unwrap());
+    stream.compress().unwrap();
+
+    let (page_id, content_id) = (ids.next_id().unwrap(), ids.next_id().unwrap());
+    let page = dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+        "Contents" => content_id,
+    };
+    vec![(page_id, page.into()), (content_id, stream.into())]
+}
+
+/// Build the pages on a number of threads, each a range of them, and add them to the document in order.
+fn build(pages: usize, workers: usize) -> (Document, Duration) {
+    let mut doc = Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+    let allocator = doc.id_allocator();
+    let per_worker = pages.div_ceil(workers);
+    // The blocks are reserved in order here, so the ids do not depend on which worker runs first.
+    let blocks: Vec<(usize, IdBlock)> = (0..workers)
+        .map(|worker| (worker * per_worker, allocator.reserve(2 * per_worker as u32)))
+        .collect();
+
+    let start = Instant::now();
+    let prepared: Vec<Vec<(ObjectId, Object)>> = thread::scope(|scope| {
+        let handles: Vec<_> = blocks
+            .into_iter()
+            .map(|(first, mut ids)| {
+                scope.spawn(move || {
+                    (first..pages.min(first + per_worker))
+                        .flat_map(|number| page(pages_id, number, &mut ids))
+                        .collect()
+                })
+            })
+            .collect();
+        handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+    });
+    let elapsed = start.elapsed();
+
+    let mut kids = Vec::new();
+    for objects in prepared {
+        kids.extend(objects.iter().step_by(2).map(|(id, _)| Object::from(*id)));
+        doc.insert_prepared(objects).unwrap();
+    }
+    doc.objects.insert(
+        pages_id,
+        dictionary! { "Type" => "Pages", "Count" => kids.len() as i64, "Kids" => kids }.into(),
+    );
+    let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+    doc.trailer.set("Root", catalog_id);
+    (doc, elapsed)
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 2 && args.len() != 3 {
+        eprintln!("Usage: {} <output.pdf> [pages]", args[0]);
+        process::exit(2);
+    }
+    let pages = match args.get(2).map(|pages| pages.parse::<usize>()) {
+        None => 64,
+        Some(Ok(pages)) if pages > 0 => pages,
+        Some(_) => {
+            eprintln!("Invalid number of pages: {}", args[2]);
+            process::exit(2);
+        }
+    };
+    let workers = thread::available_parallelism().map_or(1, |workers| workers.get());
+
+    let (_, serial) = build(pages, 1);
+    let (mut doc, parallel) = build(pages, workers);
+    println!("{} pages on 1 thread: {:?}", pages, serial);
+    println!(
+        "{} pages on {} threads: {:?}, {:.1} times faster",
+        pages,
+        workers,
+        parallel,
+        serial.as_secs_f64() / parallel.as_secs_f64()
+    );
+    if let Err(err) = doc.save(&args[1]) {
+        eprintln!("Cannot write {}: {}", args[1], err);
+        process::exit(1);
+    }
+}
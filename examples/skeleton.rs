@@ -0,0 +1,43 @@
+//! Write the structure of a PDF file without the data of its streams: pages, outlines, annotations, forms and the
+//! dictionaries of fonts, with empty content streams and without images. The streams are dropped as they are
+//! read, so loading a large file takes little more memory than the file itself.
+//!
+//! Usage: `cargo run --example skeleton -- input.pdf skeleton.pdf`
+
+use std::env;
+use std::process;
+
+use lopdf::{Document, ObjectAction, ParseOptions};
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 3 {
+        eprintln!("Usage: {} <input.pdf> <skeleton.pdf>", args[0]);
+        process::exit(2);
+    }
+
+    let options = ParseOptions {
+        object_filter: Some(Box::new(|_id, dict| {
+            let subtype = dict.get(b"Subtype").and_then(|subtype| subtype.as_name()).ok();
+            match subtype {
+                // Images are not structure, not even their dictionaries.
+                Some(b"Image") => ObjectAction::Drop,
+                _ if dict.has(b"Length") => ObjectAction::SkipContent,
+                _ => ObjectAction::Keep,
+            }
+        })),
+        ..ParseOptions::default()
+    };
+    let mut doc = match Document::load_with_options(&args[1], &options) {
+        Ok(doc) => doc,
+        Err(err) => {
+            eprintln!("Cannot read {}: {}", args[1], err);
+            process::exit(1);
+        }
+    };
+    println!("{} pages, {} objects", doc.get_pages().len(), doc.objects.len());
+    if let Err(err) = doc.save(&args[2]) {
+        eprintln!("Cannot write {}: {}", args[2], err);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,78 @@
+//! Add a watermark to every page of a PDF document, from JavaScript:
+//!
+//! ```js
+//! import init, { watermark } from "./pkg/lopdf_wasm_watermark.js";
+//!
+//! await init();
+//! const input = new Uint8Array(await file.arrayBuffer());
+//! const output = watermark(input, "DRAFT");
+//! ```
+//!
+//! Built with `wasm-pack build --target web examples/wasm`. The document is loaded from memory and saved to
+//! memory, as there are no files, and its clock asks JavaScript for the time, as there is no system clock.
+
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, xobject, Clock, Document, Object};
+use wasm_bindgen::prelude::*;
+
+/// The document with `text` across each page in light grey, half transparent.
+#[wasm_bindgen]
+pub fn watermark(pdf: &[u8], text: &str) -> Result<Vec<u8>, JsError> {
+    let mut doc = Document::load_mem(pdf)?;
+    doc.clock = Clock::new(|| {
+        let millis = js_sys::Date::now() as i128;
+        time::OffsetDateTime::from_unix_timestamp_nanos(millis * 1_000_000).ok()
+    });
+
+    let font_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Helvetica-Bold",
+    });
+    let state_id = doc.add_object(dictionary! {
+        "Type" => "ExtGState",
+        "ca" => 0.5,
+    });
+    let content = Content {
+        operations: vec![
+            Operation::new("gs", vec!["GS".into()]),
+            Operation::new("g", vec![0.75.into()]),
+            Operation::new("BT", vec![]),
+            Operation::new("Tf", vec!["F".into(), 72.into()]),
+            // Rotated by 45 degrees, from the lower left of the page.
+            Operation::new(
+                "Tm",
+                vec![
+                    0.7071.into(),
+                    0.7071.into(),
+                    (-0.7071).into(),
+                    0.7071.into(),
+                    150.into(),
+                    150.into(),
+                ],
+            ),
+            Operation::new("Tj", vec![Object::string_literal(text)]),
+            Operation::new("ET", vec![]),
+        ],
+    };
+
+    for (_, page_id) in doc.get_pages() {
+        let mut form = xobject::form(
+            vec![0.0, 0.0, 842.0, 842.0],
+            vec![1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+            content.encode()?,
+        );
+        form.dict.set(
+            "Resources",
+            dictionary! {
+                "Font" => dictionary! { "F" => font_id },
+                "ExtGState" => dictionary! { "GS" => state_id },
+            },
+        );
+        doc.insert_form_object(page_id, form)?;
+    }
+
+    let mut output = Vec::new();
+    doc.save_to(&mut output)?;
+    Ok(output)
+}
@@ -0,0 +1,110 @@
+/*
+ * A C interface to lopdf.
+ *
+ * Every function returns an lopdf_status and keeps the message of its error in the handle it was given, for
+ * lopdf_last_error. A handle may be shared between threads but is used by one call at a time: a call made while
+ * another is running on the same handle returns LOPDF_STATUS_BUSY instead of racing with it. Different handles are
+ * independent and may be used at the same time.
+ */
+
+#ifndef LOPDF_H
+#define LOPDF_H
+
+/* Generated with cbindgen from src/lib.rs: cbindgen --config cbindgen.toml --output include/lopdf.h */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// The result of a call.
+typedef enum lopdf_status {
+  LOPDF_STATUS_OK = 0,
+  // A pointer was null, or the handle holds no document as it failed to load.
+  LOPDF_STATUS_INVALID_ARGUMENT = 1,
+  // The data is not a PDF document that could be loaded.
+  LOPDF_STATUS_PARSE = 2,
+  // The document has no page of that number.
+  LOPDF_STATUS_PAGE_NOT_FOUND = 3,
+  // The text could not be extracted, the pages appended or the document saved.
+  LOPDF_STATUS_FAILED = 4,
+  // Another call is using the handle.
+  LOPDF_STATUS_BUSY = 5,
+  // lopdf panicked, during this call or an earlier one on the handle. The handle may only be freed.
+  LOPDF_STATUS_PANIC = 6,
+} lopdf_status;
+
+// A document, and the message of the last error of a call on it.
+typedef struct lopdf_doc lopdf_doc;
+
+// Load a document from `len` bytes at `data`. A handle is stored in `out` even if loading fails, for the message
+// of the error, and is to be freed with [`lopdf_free_doc`].
+//
+// # Safety
+//
+// `data` points to `len` readable bytes, and `out` to a writable pointer.
+enum lopdf_status lopdf_load(const uint8_t *data, size_t len, struct lopdf_doc **out);
+
+// Store the number of pages of the document in `out`.
+//
+// # Safety
+//
+// `doc` is a handle from [`lopdf_load`] and `out` points to a writable `size_t`.
+enum lopdf_status lopdf_page_count(struct lopdf_doc *doc, size_t *out);
+
+// Store the text of a page, numbered from 1, in `out` as a UTF-8 string without its NUL bytes, to be freed with
+// [`lopdf_free_string`].
+//
+// # Safety
+//
+// `doc` is a handle from [`lopdf_load`] and `out` points to a writable pointer.
+enum lopdf_status lopdf_extract_text(struct lopdf_doc *doc, uint32_t page, char **out);
+
+// Append the pages of `other` to those of `doc`, leaving `other` as it is. The outline, named destinations and
+// form fields of `other` are not kept.
+//
+// # Safety
+//
+// `doc` and `other` are handles from [`lopdf_load`].
+enum lopdf_status lopdf_merge(struct lopdf_doc *doc, struct lopdf_doc *other);
+
+// Save the document to a buffer, stored in `out` and its length in `len`, to be freed with
+// [`lopdf_free_bytes`].
+//
+// # Safety
+//
+// `doc` is a handle from [`lopdf_load`], `out` points to a writable pointer and `len` to a writable `size_t`.
+enum lopdf_status lopdf_save(struct lopdf_doc *doc, uint8_t **out, size_t *len);
+
+// The message of the error of the last call on the handle, or null if it succeeded or another call is using the
+// handle. The message belongs to the handle and is valid until its next call.
+//
+// # Safety
+//
+// `doc` is a handle from [`lopdf_load`], or null.
+const char *lopdf_last_error(const struct lopdf_doc *doc);
+
+// Free a handle, once the call running on it, if any, has returned.
+//
+// # Safety
+//
+// `doc` is a handle from [`lopdf_load`], or null. No call may start on it once this is called, and it may not be
+// used afterwards.
+void lopdf_free_doc(struct lopdf_doc *doc);
+
+// Free a string from [`lopdf_extract_text`].
+//
+// # Safety
+//
+// `text` is a string from [`lopdf_extract_text`] not yet freed, or null.
+void lopdf_free_string(char *text);
+
+// Free a buffer from [`lopdf_save`].
+//
+// # Safety
+//
+// `data` and `len` are a buffer and its length from [`lopdf_save`] not yet freed, or `data` is null.
+void lopdf_free_bytes(uint8_t *data, size_t len);
+
+#endif /* LOPDF_H */
@@ -0,0 +1,468 @@
+//! A C interface to lopdf: documents are loaded from memory into handles, their pages counted, the text of a page
+//! extracted, the pages of another document appended and the document saved back to memory. `include/lopdf.h`
+//! declares it, as `cbindgen --config cbindgen.toml --output include/lopdf.h` writes it.
+//!
+//! Each function returns a [`LopdfStatus`], and keeps the message of its error in the handle, for
+//! [`lopdf_last_error`]. A handle may be shared between threads but is used by one call at a time: a call made
+//! while another is running on the same handle returns [`LopdfStatus::Busy`] instead of racing with it.
+
+use lopdf::{Document, Object, ObjectId};
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+use std::sync::{Mutex, MutexGuard, TryLockError};
+
+/// How deep inherited page attributes are looked for, as page trees may have cycles.
+const MAX_DEPTH: usize = 64;
+
+/// The result of a call.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LopdfStatus {
+    Ok = 0,
+    /// A pointer was null, or the handle holds no document as it failed to load.
+    InvalidArgument = 1,
+    /// The data is not a PDF document that could be loaded.
+    Parse = 2,
+    /// The document has no page of that number.
+    PageNotFound = 3,
+    /// The text could not be extracted, the pages appended or the document saved.
+    Failed = 4,
+    /// Another call is using the handle.
+    Busy = 5,
+    /// lopdf panicked, during this call or an earlier one on the handle. The handle may only be freed.
+    Panic = 6,
+}
+
+/// A document, and the message of the last error of a call on it.
+#[derive(Default)]
+pub struct LopdfDoc {
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    document: Option<Document>,
+    last_error: Option<CString>,
+    /// Whether a call panicked, leaving the document as it was when it did.
+    panicked: bool,
+}
+
+struct Failure(LopdfStatus, String);
+
+impl State {
+    fn document(&mut self) -> Result<&mut Document, Failure> {
+        self.document
+            .as_mut()
+            .ok_or_else(|| Failure(LopdfStatus::InvalidArgument, "the document failed to load".to_string()))
+    }
+}
+
+fn failed(err: lopdf::Error) -> Failure {
+    Failure(LopdfStatus::Failed, err.to_string())
+}
+
+/// Hold the state of a handle for a call, or tell whether another call holds it.
+unsafe fn lock<'a>(doc: *const LopdfDoc) -> Result<MutexGuard<'a, State>, LopdfStatus> {
+    match (*doc).state.try_lock() {
+        Ok(state) if state.panicked => Err(LopdfStatus::Panic),
+        Ok(state) => Ok(state),
+        Err(TryLockError::WouldBlock) => Err(LopdfStatus::Busy),
+        // Calls catch the panics of lopdf, so the lock is only poisoned by one of their own.
+        Err(TryLockError::Poisoned(_)) => Err(LopdfStatus::Panic),
+    }
+}
+
+/// Run a call on a handle, holding it for the time of the call and keeping the message of its error.
+unsafe fn call(doc: *mut LopdfDoc, f: impl FnOnce(&mut State) -> Result<(), Failure>) -> LopdfStatus {
+    if doc.is_null() {
+        return LopdfStatus::InvalidArgument;
+    }
+    let mut state = match lock(doc) {
+        Ok(state) => state,
+        Err(status) => return status,
+    };
+    let (status, message) = match panic::catch_unwind(AssertUnwindSafe(|| f(&mut state))) {
+        Ok(Ok(())) => (LopdfStatus::Ok, None),
+        Ok(Err(Failure(status, message))) => (status, Some(message)),
+        Err(_) => {
+            state.panicked = true;
+            (LopdfStatus::Panic, Some("lopdf panicked".to_string()))
+        }
+    };
+    // Messages have no NUL bytes but those of the text of a document, which are dropped.
+    state.last_error = message.map(|message| CString::new(message.replace('\0', "")).unwrap_or_default());
+    status
+}
+
+/// Load a document from `len` bytes at `data`. A handle is stored in `out` even if loading fails, for the message
+/// of the error, and is to be freed with [`lopdf_free_doc`].
+///
+/// # Safety
+///
+/// `data` points to `len` readable bytes, and `out` to a writable pointer.
+#[no_mangle]
+pub unsafe extern "C" fn lopdf_load(data: *const u8, len: usize, out: *mut *mut LopdfDoc) -> LopdfStatus {
+    if out.is_null() || (data.is_null() && len > 0) {
+        return LopdfStatus::InvalidArgument;
+    }
+    let data = if len == 0 {
+        &[][..]
+    } else {
+        slice::from_raw_parts(data, len)
+    };
+    let doc = Box::into_raw(Box::default());
+    *out = doc;
+    call(doc, |state| {
+        let document = Document::load_mem(data).map_err(|err| Failure(LopdfStatus::Parse, err.to_string()))?;
+        state.document = Some(document);
+        Ok(())
+    })
+}
+
+/// Store the number of pages of the document in `out`.
+///
+/// # Safety
+///
+/// `doc` is a handle from [`lopdf_load`] and `out` points to a writable `size_t`.
+#[no_mangle]
+pub unsafe extern "C" fn lopdf_page_count(doc: *mut LopdfDoc, out: *mut usize) -> LopdfStatus {
+    if out.is_null() {
+        return LopdfStatus::InvalidArgument;
+    }
+    call(doc, |state| {
+        *out = state.document()?.get_pages().len();
+        Ok(())
+    })
+}
+
+/// Store the text of a page, numbered from 1, in `out` as a UTF-8 string without its NUL bytes, to be freed with
+/// [`lopdf_free_string`].
+///
+/// # Safety
+///
+/// `doc` is a handle from [`lopdf_load`] and `out` points to a writable pointer.
+#[no_mangle]
+pub unsafe extern "C" fn lopdf_extract_text(doc: *mut LopdfDoc, page: u32, out: *mut *mut c_char) -> LopdfStatus {
+    if out.is_null() {
+        return LopdfStatus::InvalidArgument;
+    }
+    call(doc, |state| {
+        let document = state.document()?;
+        if !document.get_pages().contains_key(&page) {
+            return Err(Failure(
+                LopdfStatus::PageNotFound,
+                format!("the document has no page {}", page),
+            ));
+        }
+        let text = document.extract_text(&[page]).map_err(failed)?;
+        let text = CString::new(text.replace('\0', "")).unwrap_or_default();
+        *out = text.into_raw();
+        Ok(())
+    })
+}
+
+/// Append the pages of `other` to those of `doc`, leaving `other` as it is. The outline, named destinations and
+/// form fields of `other` are not kept.
+///
+/// # Safety
+///
+/// `doc` and `other` are handles from [`lopdf_load`].
+#[no_mangle]
+pub unsafe extern "C" fn lopdf_merge(doc: *mut LopdfDoc, other: *mut LopdfDoc) -> LopdfStatus {
+    if other.is_null() || other == doc {
+        return LopdfStatus::InvalidArgument;
+    }
+    call(doc, |state| {
+        let other = lock(other).map_err(|status| match status {
+            LopdfStatus::Busy => Failure(status, "the other document is in use".to_string()),
+            _ => Failure(status, "lopdf panicked on the other document".to_string()),
+        })?;
+        match &other.document {
+            Some(appended) => append_pages(state.document()?, appended).map_err(failed),
+            None => Err(Failure(
+                LopdfStatus::InvalidArgument,
+                "the other document failed to load".to_string(),
+            )),
+        }
+    })
+}
+
+/// Save the document to a buffer, stored in `out` and its length in `len`, to be freed with
+/// [`lopdf_free_bytes`].
+///
+/// # Safety
+///
+/// `doc` is a handle from [`lopdf_load`], `out` points to a writable pointer and `len` to a writable `size_t`.
+#[no_mangle]
+pub unsafe extern "C" fn lopdf_save(doc: *mut LopdfDoc, out: *mut *mut u8, len: *mut usize) -> LopdfStatus {
+    if out.is_null() || len.is_null() {
+        return LopdfStatus::InvalidArgument;
+    }
+    call(doc, |state| {
+        let mut buffer = Vec::new();
+        state
+            .document()?
+            .save_to(&mut buffer)
+            .map_err(|err| failed(err.into()))?;
+        let buffer = buffer.into_boxed_slice();
+        *len = buffer.len();
+        *out = Box::into_raw(buffer) as *mut u8;
+        Ok(())
+    })
+}
+
+/// The message of the error of the last call on the handle, or null if it succeeded or another call is using the
+/// handle. The message belongs to the handle and is valid until its next call.
+///
+/// # Safety
+///
+/// `doc` is a handle from [`lopdf_load`], or null.
+#[no_mangle]
+pub unsafe extern "C" fn lopdf_last_error(doc: *const LopdfDoc) -> *const c_char {
+    if doc.is_null() {
+        return ptr::null();
+    }
+    // The message of a panic is kept with the handle, which is locked to no other call afterwards.
+    let state = match (*doc).state.try_lock() {
+        Ok(state) => state,
+        Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+        Err(TryLockError::WouldBlock) => return ptr::null(),
+    };
+    match &state.last_error {
+        Some(message) => message.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// Free a handle, once the call running on it, if any, has returned.
+///
+/// # Safety
+///
+/// `doc` is a handle from [`lopdf_load`], or null. No call may start on it once this is called, and it may not be
+/// used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn lopdf_free_doc(doc: *mut LopdfDoc) {
+    if !doc.is_null() {
+        drop((*doc).state.lock());
+        drop(Box::from_raw(doc));
+    }
+}
+
+/// Free a string from [`lopdf_extract_text`].
+///
+/// # Safety
+///
+/// `text` is a string from [`lopdf_extract_text`] not yet freed, or null.
+#[no_mangle]
+pub unsafe extern "C" fn lopdf_free_string(text: *mut c_char) {
+    if !text.is_null() {
+        drop(CString::from_raw(text));
+    }
+}
+
+/// Free a buffer from [`lopdf_save`].
+///
+/// # Safety
+///
+/// `data` and `len` are a buffer and its length from [`lopdf_save`] not yet freed, or `data` is null.
+#[no_mangle]
+pub unsafe extern "C" fn lopdf_free_bytes(data: *mut u8, len: usize) {
+    if !data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(data, len)));
+    }
+}
+
+/// Append the pages of `other` under the root page tree node of `document`, with the attributes they inherit set
+/// in their own dictionaries. All objects of `other` but its catalog and page tree nodes are copied.
+fn append_pages(document: &mut Document, other: &Document) -> lopdf::Result<()> {
+    let pages_id = document.catalog()?.get(b"Pages")?.as_reference()?;
+    let mut other = other.clone();
+    other.renumber_objects_with(document.max_id + 1);
+    let catalog_id = other.trailer.get(b"Root")?.as_reference()?;
+
+    let page_ids: Vec<ObjectId> = other.get_pages().into_values().collect();
+    for &page_id in &page_ids {
+        let mut page = other.get_dictionary(page_id)?.clone();
+        let mut ancestor_id = page.get(b"Parent").and_then(Object::as_reference).ok();
+        for _ in 0..MAX_DEPTH {
+            let ancestor = match ancestor_id.map(|id| other.get_dictionary(id)) {
+                Some(Ok(ancestor)) => ancestor,
+                _ => break,
+            };
+            for key in [&b"Resources"[..], b"MediaBox", b"CropBox", b"Rotate"] {
+                if !page.has(key) {
+                    if let Ok(value) = ancestor.get(key) {
+                        page.set(key, value.clone());
+                    }
+                }
+            }
+            ancestor_id = ancestor.get(b"Parent").and_then(Object::as_reference).ok();
+        }
+        page.set("Parent", pages_id);
+        other.objects.insert(page_id, Object::Dictionary(page));
+    }
+    other
+        .objects
+        .retain(|&id, object| id != catalog_id && !object.as_dict().is_ok_and(|dict| dict.type_is(b"Pages")));
+
+    document.max_id = document.max_id.max(other.max_id);
+    document.objects.extend(other.objects);
+    let pages = document.get_object_mut(pages_id)?.as_dict_mut()?;
+    let count = pages.get(b"Count").and_then(Object::as_i64).unwrap_or(0) + page_ids.len() as i64;
+    pages.set("Count", count);
+    pages
+        .get_mut(b"Kids")?
+        .as_array_mut()?
+        .extend(page_ids.into_iter().map(Object::Reference));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::content::{Content, Operation};
+    use lopdf::{dictionary, Stream};
+    use std::ffi::CStr;
+
+    fn document_bytes(texts: &[&str]) -> Vec<u8> {
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let font_id = doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Courier",
+        });
+        let mut kids = Vec::new();
+        for text in texts {
+            let content = Content {
+                operations: vec![
+                    Operation::new("BT", vec![]),
+                    Operation::new("Tf", vec!["F1".into(), 48.into()]),
+                    Operation::new("Td", vec![100.into(), 600.into()]),
+                    Operation::new("Tj", vec![Object::string_literal(*text)]),
+                    Operation::new("ET", vec![]),
+                ],
+            };
+            let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+            kids.push(Object::Reference(doc.add_object(dictionary! {
+                "Type" => "Page",
+                "Parent" => pages_id,
+                "Contents" => content_id,
+            })));
+        }
+        // The resources and media box are inherited from the root page tree node.
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Count" => kids.len() as i64,
+                "Kids" => kids,
+                "Resources" => dictionary! { "Font" => dictionary! { "F1" => font_id } },
+                "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        doc.trailer.set("Root", catalog_id);
+        let mut bytes = Vec::new();
+        doc.save_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    unsafe fn load(bytes: &[u8]) -> *mut LopdfDoc {
+        let mut doc = ptr::null_mut();
+        assert_eq!(lopdf_load(bytes.as_ptr(), bytes.len(), &mut doc), LopdfStatus::Ok);
+        doc
+    }
+
+    unsafe fn text(doc: *mut LopdfDoc, page: u32) -> String {
+        let mut text = ptr::null_mut();
+        assert_eq!(lopdf_extract_text(doc, page, &mut text), LopdfStatus::Ok);
+        let string = CStr::from_ptr(text).to_str().unwrap().trim().to_string();
+        lopdf_free_string(text);
+        string
+    }
+
+    #[test]
+    fn load_merge_and_save() {
+        unsafe {
+            let doc = load(&document_bytes(&["First", "Second"]));
+            let other = load(&document_bytes(&["Third"]));
+            assert_eq!(lopdf_merge(doc, other), LopdfStatus::Ok);
+            assert_eq!(lopdf_merge(doc, doc), LopdfStatus::InvalidArgument);
+            lopdf_free_doc(other);
+
+            let (mut data, mut len) = (ptr::null_mut(), 0);
+            assert_eq!(lopdf_save(doc, &mut data, &mut len), LopdfStatus::Ok);
+            lopdf_free_doc(doc);
+            let saved = load(slice::from_raw_parts(data, len));
+            lopdf_free_bytes(data, len);
+
+            let mut count = 0;
+            assert_eq!(lopdf_page_count(saved, &mut count), LopdfStatus::Ok);
+            assert_eq!(count, 3);
+            assert_eq!(text(saved, 1), "First");
+            assert_eq!(text(saved, 3), "Third");
+            lopdf_free_doc(saved);
+        }
+    }
+
+    #[test]
+    fn errors_kept_per_handle() {
+        unsafe {
+            let mut broken = ptr::null_mut();
+            let garbage = b"not a PDF document";
+            assert_eq!(
+                lopdf_load(garbage.as_ptr(), garbage.len(), &mut broken),
+                LopdfStatus::Parse
+            );
+            assert!(!lopdf_last_error(broken).is_null());
+            let mut count = 0;
+            assert_eq!(lopdf_page_count(broken, &mut count), LopdfStatus::InvalidArgument);
+
+            let doc = load(&document_bytes(&["Only"]));
+            assert!(lopdf_last_error(doc).is_null());
+            let mut text = ptr::null_mut();
+            assert_eq!(lopdf_extract_text(doc, 2, &mut text), LopdfStatus::PageNotFound);
+            let message = CStr::from_ptr(lopdf_last_error(doc)).to_str().unwrap();
+            assert_eq!(message, "the document has no page 2");
+            assert_eq!(lopdf_merge(doc, broken), LopdfStatus::InvalidArgument);
+
+            // A handle in use by another call is not entered.
+            let state = (*doc).state.lock().unwrap();
+            assert_eq!(lopdf_page_count(doc, &mut count), LopdfStatus::Busy);
+            assert!(lopdf_last_error(doc).is_null());
+            drop(state);
+            assert_eq!(lopdf_page_count(doc, &mut count), LopdfStatus::Ok);
+            assert!(lopdf_last_error(doc).is_null());
+
+            // Nor is one left by a panic.
+            (*doc).state.lock().unwrap().panicked = true;
+            assert_eq!(lopdf_page_count(doc, &mut count), LopdfStatus::Panic);
+            assert_eq!(lopdf_merge(broken, doc), LopdfStatus::Panic);
+            lopdf_free_doc(doc);
+            lopdf_free_doc(broken);
+        }
+    }
+
+    #[test]
+    fn header_declares_every_function() {
+        let header = include_str!("../include/lopdf.h");
+        for name in [
+            "lopdf_load(",
+            "lopdf_page_count(",
+            "lopdf_extract_text(",
+            "lopdf_merge(",
+            "lopdf_save(",
+            "lopdf_last_error(",
+            "lopdf_free_doc(",
+            "lopdf_free_string(",
+            "lopdf_free_bytes(",
+            "LOPDF_STATUS_PANIC = 6",
+        ] {
+            assert!(header.contains(name), "{} is not declared", name);
+        }
+    }
+}
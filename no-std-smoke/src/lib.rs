@@ -0,0 +1,52 @@
+//! Writes a page with lopdf built without its `std` feature, as on targets with `alloc` only.
+#![no_std]
+
+extern crate alloc;
+
+use alloc::vec;
+use alloc::vec::Vec;
+use lopdf::content::{Content, Operation};
+use lopdf::writer::{CountingWrite, Writer};
+use lopdf::xref::Xref;
+use lopdf::{dictionary, Object, Stream};
+
+/// The objects of a page showing some text, and their cross-reference table.
+pub fn write_page() -> lopdf::Result<Vec<u8>> {
+    let content = Content {
+        operations: vec![
+            Operation::new("BT", vec![]),
+            Operation::new("Tf", vec!["F1".into(), 24.into()]),
+            Operation::new("Td", vec![100.into(), 600.into()]),
+            Operation::new("Tj", vec![Object::string_literal("Hello without std")]),
+            Operation::new("ET", vec![]),
+        ],
+    };
+    let contents = Stream::new(dictionary! {}, content.encode()?);
+    let page = dictionary! {
+        "Type" => "Page",
+        "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+        "Contents" => (1, 0),
+    };
+
+    let mut buffer = Vec::new();
+    let mut target = CountingWrite {
+        inner: &mut buffer,
+        bytes_written: 0,
+    };
+    let mut xref = Xref::new(3);
+    Writer::write_indirect_object(&mut target, (1, 0), &contents.into(), &mut xref, None)?;
+    Writer::write_indirect_object(&mut target, (2, 0), &page.into(), &mut xref, None)?;
+    Writer::write_xref(&mut target, &xref)?;
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn page_written() {
+        let data = super::write_page().unwrap();
+        assert!(data.starts_with(b"1 0 obj<</Length 50>>stream\nBT\n/F1 24 Tf\n"));
+        assert!(data.windows(17).any(|window| window == b"/Contents 1 0 R>>"));
+        assert!(data.ends_with(b"0000000000 65535 f \n0000000000 00000 n \n0000000095 00000 n \n"));
+    }
+}
@@ -0,0 +1,278 @@
+use crate::structure::{StructElement, StructKid, StructTree};
+use crate::{Dictionary, Document, Object, ObjectId, Result};
+
+/// The `/MarkInfo` of the catalog, from [`Document::mark_info`]. Entries that are missing or of the wrong type
+/// are `None`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MarkInfo {
+    /// Whether the document is tagged.
+    pub marked: Option<bool>,
+    /// Whether the structure elements have user properties.
+    pub user_properties: Option<bool>,
+    /// Whether the tags may be wrong, as when the document was tagged by a conversion heuristic.
+    pub suspects: Option<bool>,
+}
+
+/// A kind of problem with the accessibility of a document found by [`Document::accessibility_report`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessibilityIssueKind {
+    /// The catalog has no `/MarkInfo`.
+    MissingMarkInfo,
+    /// `/Marked` of `/MarkInfo` is not true.
+    NotMarked,
+    /// `/Suspects` of `/MarkInfo` is true.
+    Suspects,
+    /// The catalog has no `/StructTreeRoot`.
+    MissingStructTree,
+    /// The catalog has no `/Lang`.
+    MissingLanguage,
+    /// A page does not set its tab order to that of the structure, `/Tabs /S`.
+    TabOrder,
+    /// A figure has neither `/Alt` nor `/ActualText`.
+    FigureWithoutAlt,
+    /// The document information dictionary has no title.
+    MissingTitle,
+    /// `/DisplayDocTitle` of the viewer preferences is not true.
+    TitleNotDisplayed,
+}
+
+/// A problem with the accessibility of a document, with the object to fix.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessibilityIssue {
+    pub kind: AccessibilityIssueKind,
+    /// The object to fix, or the closest indirect object holding it.
+    pub object: Option<ObjectId>,
+    /// What was found, such as the number of a page or the type of a figure.
+    pub detail: String,
+}
+
+impl Document {
+    /// The `/MarkInfo` of the catalog.
+    pub fn mark_info(&self) -> MarkInfo {
+        let mark_info = match self
+            .catalog()
+            .and_then(|catalog| catalog.get_deref(b"MarkInfo", self))
+            .and_then(Object::as_dict)
+        {
+            Ok(mark_info) => mark_info,
+            Err(_) => return MarkInfo::default(),
+        };
+        let flag = |key: &[u8]| mark_info.get_deref(key, self).and_then(Object::as_bool).ok();
+        MarkInfo {
+            marked: flag(b"Marked"),
+            user_properties: flag(b"UserProperties"),
+            suspects: flag(b"Suspects"),
+        }
+    }
+
+    /// Set the entries of the `/MarkInfo` of the catalog that are not `None`, keeping the others. The dictionary
+    /// is added if the catalog has none.
+    pub fn set_mark_info(&mut self, mark_info: MarkInfo) -> Result<()> {
+        let mark_info_id = match self.catalog()?.get(b"MarkInfo") {
+            Ok(Object::Reference(id)) if self.get_dictionary(*id).is_ok() => Some(*id),
+            Ok(Object::Dictionary(_)) => None,
+            _ => {
+                self.catalog_mut()?.set("MarkInfo", Dictionary::new());
+                None
+            }
+        };
+        let dict = match mark_info_id {
+            Some(id) => self.get_object_mut(id)?.as_dict_mut()?,
+            None => self.catalog_mut()?.get_mut(b"MarkInfo")?.as_dict_mut()?,
+        };
+        let flags = [
+            ("Marked", mark_info.marked),
+            ("UserProperties", mark_info.user_properties),
+            ("Suspects", mark_info.suspects),
+        ];
+        for (key, flag) in flags {
+            if let Some(flag) = flag {
+                dict.set(key, flag);
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the state of the document that PDF/UA requires: that it is marked as tagged, without suspect tags,
+    /// and has a structure tree, a language and a title shown by viewers; that pages follow the structure for
+    /// their tab order; and that figures have alternate text.
+    ///
+    /// This is no validation: the tags themselves and the content are not checked.
+    pub fn accessibility_report(&self) -> Vec<AccessibilityIssue> {
+        let mut issues = Vec::new();
+        let mut issue = |kind, object, detail: &str| {
+            issues.push(AccessibilityIssue {
+                kind,
+                object,
+                detail: detail.to_string(),
+            })
+        };
+        let catalog_id = self.trailer.get(b"Root").and_then(Object::as_reference).ok();
+        let catalog = self.catalog().ok();
+        let holder = |key: &[u8]| {
+            catalog
+                .and_then(|catalog| catalog.get(key).and_then(Object::as_reference).ok())
+                .or(catalog_id)
+        };
+
+        let mark_info = self.mark_info();
+        if catalog.is_none_or(|catalog| !catalog.has(b"MarkInfo")) {
+            issue(AccessibilityIssueKind::MissingMarkInfo, catalog_id, "");
+        } else if mark_info.marked != Some(true) {
+            issue(AccessibilityIssueKind::NotMarked, holder(b"MarkInfo"), "");
+        }
+        if mark_info.suspects == Some(true) {
+            issue(AccessibilityIssueKind::Suspects, holder(b"MarkInfo"), "");
+        }
+        match self.structure_tree() {
+            Some(tree) => {
+                for element in &tree.children {
+                    figures_without_alt(&tree, element, None, &mut |object, struct_type| {
+                        issue(AccessibilityIssueKind::FigureWithoutAlt, object, struct_type)
+                    });
+                }
+            }
+            None => issue(AccessibilityIssueKind::MissingStructTree, catalog_id, ""),
+        }
+        if self.language().is_none_or(|language| language.is_empty()) {
+            issue(AccessibilityIssueKind::MissingLanguage, catalog_id, "");
+        }
+
+        for (number, page_id) in self.get_pages() {
+            let tabs = self
+                .get_dictionary(page_id)
+                .and_then(|page| page.get_deref(b"Tabs", self))
+                .and_then(Object::as_name);
+            if tabs.ok() != Some(b"S") {
+                issue(
+                    AccessibilityIssueKind::TabOrder,
+                    Some(page_id),
+                    &format!("page {}", number),
+                );
+            }
+        }
+
+        if self.metadata().title.is_none_or(|title| title.trim().is_empty()) {
+            let info_id = self.trailer.get(b"Info").and_then(Object::as_reference).ok();
+            issue(AccessibilityIssueKind::MissingTitle, info_id, "");
+        }
+        if self.viewer_preferences().display_doc_title != Some(true) {
+            issue(
+                AccessibilityIssueKind::TitleNotDisplayed,
+                holder(b"ViewerPreferences"),
+                "",
+            );
+        }
+        issues
+    }
+}
+
+/// Report the figures among an element and its descendants that have neither `/Alt` nor `/ActualText`, with the
+/// closest indirect element holding them.
+fn figures_without_alt(
+    tree: &StructTree, element: &StructElement, holder: Option<ObjectId>,
+    report: &mut dyn FnMut(Option<ObjectId>, &str),
+) {
+    let holder = element.id.or(holder);
+    if tree.standard_type(&element.struct_type) == "Figure" && element.alt.is_none() && element.actual_text.is_none() {
+        report(holder, &element.struct_type);
+    }
+    for kid in &element.kids {
+        if let StructKid::Element(kid) = kid {
+            figures_without_alt(tree, kid, holder, report);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::creator::tests::create_document;
+
+    fn kinds(doc: &Document) -> Vec<AccessibilityIssueKind> {
+        doc.accessibility_report().into_iter().map(|issue| issue.kind).collect()
+    }
+
+    #[test]
+    fn set_mark_info() {
+        let mut doc = create_document();
+        assert_eq!(doc.mark_info(), MarkInfo::default());
+        let mark_info_id = doc.add_object(dictionary! { "Suspects" => true });
+        doc.catalog_mut().unwrap().set("MarkInfo", mark_info_id);
+        doc.set_mark_info(MarkInfo {
+            marked: Some(true),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(
+            doc.mark_info(),
+            MarkInfo {
+                marked: Some(true),
+                user_properties: None,
+                suspects: Some(true),
+            }
+        );
+        assert!(doc.get_dictionary(mark_info_id).unwrap().has(b"Marked"));
+    }
+
+    #[test]
+    fn report_accessibility() {
+        use AccessibilityIssueKind::*;
+        let mut doc = create_document();
+        let page_id = doc.page_iter().next().unwrap();
+        let catalog_id = doc.trailer.get(b"Root").and_then(Object::as_reference).unwrap();
+        let report = doc.accessibility_report();
+        assert_eq!(
+            report.iter().map(|issue| issue.kind).collect::<Vec<_>>(),
+            vec![
+                MissingMarkInfo,
+                MissingStructTree,
+                MissingLanguage,
+                TabOrder,
+                TitleNotDisplayed
+            ]
+        );
+        assert_eq!(report[0].object, Some(catalog_id));
+        assert_eq!(report[3].object, Some(page_id));
+        assert_eq!(report[3].detail, "page 1");
+
+        let figure = doc.add_object(dictionary! { "S" => "Image", "Pg" => page_id, "K" => 0 });
+        let described = doc.add_object(dictionary! { "S" => "Figure", "Alt" => Object::string_literal("A cat") });
+        let root = doc.add_object(dictionary! {
+            "Type" => "StructTreeRoot",
+            "RoleMap" => dictionary! { "Image" => "Figure" },
+            "K" => vec![figure.into(), described.into()],
+        });
+        doc.catalog_mut().unwrap().set("StructTreeRoot", root);
+        doc.prepare_for_accessibility("en-GB").unwrap();
+        doc.set_mark_info(MarkInfo {
+            suspects: Some(true),
+            ..Default::default()
+        })
+        .unwrap();
+        doc.get_object_mut(page_id)
+            .unwrap()
+            .as_dict_mut()
+            .unwrap()
+            .set("Tabs", "S");
+        let report = doc.accessibility_report();
+        assert_eq!(
+            report.iter().map(|issue| issue.kind).collect::<Vec<_>>(),
+            vec![Suspects, FigureWithoutAlt]
+        );
+        assert_eq!(report[1].object, Some(figure));
+        assert_eq!(report[1].detail, "Image");
+
+        doc.set_mark_info(MarkInfo {
+            suspects: Some(false),
+            ..Default::default()
+        })
+        .unwrap();
+        doc.get_object_mut(figure)
+            .unwrap()
+            .as_dict_mut()
+            .unwrap()
+            .set("Alt", Object::string_literal("A dog"));
+        assert!(kinds(&doc).is_empty());
+    }
+}
@@ -0,0 +1,824 @@
+use crate::annotations::AnnotationSubtype;
+use crate::destinations::{Destination, DestinationFit};
+use crate::encodings::decode_text_string;
+use crate::{Dictionary, Document, Error, Object, ObjectId, Result, Stream};
+use log::warn;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Kind of action that a security review is interested in, from the `/S` entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActionKind {
+    JavaScript,
+    Launch,
+    SubmitForm,
+    ImportData,
+    Uri,
+}
+
+impl ActionKind {
+    fn from_name(name: &[u8]) -> Option<ActionKind> {
+        match name {
+            b"JavaScript" => Some(ActionKind::JavaScript),
+            b"Launch" => Some(ActionKind::Launch),
+            b"SubmitForm" => Some(ActionKind::SubmitForm),
+            b"ImportData" => Some(ActionKind::ImportData),
+            b"URI" => Some(ActionKind::Uri),
+            _ => None,
+        }
+    }
+}
+
+/// Where an action is triggered from. Actions of a `/Next` chain have the location of the first action.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ActionLocation {
+    /// An entry of the `/JavaScript` name tree, run when the document is opened.
+    DocumentJavaScript(String),
+    OpenAction,
+    /// An additional action of the document, by trigger.
+    Document(Vec<u8>),
+    /// An additional action of a page, by trigger.
+    Page {
+        page: ObjectId,
+        trigger: Vec<u8>,
+    },
+    /// The action of an annotation, or one of its additional actions by trigger.
+    Annotation {
+        page: ObjectId,
+        annotation: Option<ObjectId>,
+        trigger: Option<Vec<u8>>,
+    },
+    /// An additional action of a form field, by trigger.
+    Field {
+        field: ObjectId,
+        trigger: Vec<u8>,
+    },
+    Outline(ObjectId),
+    /// An action found in an object that is not one of the places above, such as an appearance dictionary.
+    Other(ObjectId),
+}
+
+/// An action found by [`Document::actions_report`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActionEntry {
+    pub kind: ActionKind,
+    pub location: ActionLocation,
+    /// Position in the `/Next` chain, 0 for the action that is triggered first.
+    pub depth: usize,
+    /// The action, if it is an indirect object.
+    pub id: Option<ObjectId>,
+    /// The script of a JavaScript action, the URI of a URI action, or the file or URL of the other actions.
+    pub target: Option<String>,
+}
+
+/// An action run when the document or a page is opened or closed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DocAction {
+    /// Go to a page of the document, given by a destination or a `GoTo` action.
+    GoTo(Destination),
+    /// Any other action, by its `/S` type, such as `JavaScript`, or a `GoTo` action to a missing destination.
+    Other {
+        action_type: Vec<u8>,
+        /// The action, if it is an indirect object.
+        id: Option<ObjectId>,
+    },
+}
+
+/// The open action to set with [`Document::set_open_action`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum OpenAction {
+    /// Open the document at a page, by page number starting at 1.
+    GotoPage { page: u32, fit: DestinationFit },
+    /// Open the document the way the viewer chooses.
+    None,
+}
+
+/// Trigger of a page additional action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageTrigger {
+    /// `/O`, when the page is opened.
+    Open,
+    /// `/C`, when the page is closed.
+    Close,
+}
+
+impl PageTrigger {
+    fn key(self) -> &'static str {
+        match self {
+            PageTrigger::Open => "O",
+            PageTrigger::Close => "C",
+        }
+    }
+}
+
+/// Which actions [`Document::sanitize`] removes, and whether it removes embedded files.
+#[derive(Debug, Clone, Default)]
+pub struct SanitizePolicy {
+    pub javascript: bool,
+    pub launch: bool,
+    pub submit_form: bool,
+    pub import_data: bool,
+    pub uri: bool,
+    pub embedded_files: bool,
+}
+
+impl SanitizePolicy {
+    /// Remove all actions that run code, open files or reach the network, and all embedded files.
+    pub fn all() -> SanitizePolicy {
+        SanitizePolicy {
+            javascript: true,
+            launch: true,
+            submit_form: true,
+            import_data: true,
+            uri: true,
+            embedded_files: true,
+        }
+    }
+
+    fn selects(&self, kind: ActionKind) -> bool {
+        match kind {
+            ActionKind::JavaScript => self.javascript,
+            ActionKind::Launch => self.launch,
+            ActionKind::SubmitForm => self.submit_form,
+            ActionKind::ImportData => self.import_data,
+            ActionKind::Uri => self.uri,
+        }
+    }
+
+    fn selects_action(&self, dict: &Dictionary) -> bool {
+        action_kind(dict).is_some_and(|kind| self.selects(kind))
+    }
+}
+
+/// What [`Document::sanitize`] removed.
+#[derive(Debug, Clone, Default)]
+pub struct SanitizeReport {
+    pub actions: Vec<ActionEntry>,
+    pub embedded_files: usize,
+}
+
+fn action_kind(dict: &Dictionary) -> Option<ActionKind> {
+    ActionKind::from_name(dict.get(b"S").and_then(Object::as_name).ok()?)
+}
+
+fn action_target(doc: &Document, dict: &Dictionary, kind: ActionKind) -> Option<String> {
+    // File specifications are either a string or a dictionary with the name in `/UF` or `/F`.
+    let file_name = |object: &Object| match doc.dereference(object).ok()?.1 {
+        Object::String(name, _) => Some(decode_text_string(name)),
+        Object::Dictionary(spec) => spec
+            .get_deref(b"UF", doc)
+            .or_else(|_| spec.get_deref(b"F", doc))
+            .and_then(Object::as_str)
+            .ok()
+            .map(decode_text_string),
+        _ => None,
+    };
+    match kind {
+        ActionKind::JavaScript => match dict.get_deref(b"JS", doc).ok()? {
+            Object::String(script, _) => Some(decode_text_string(script)),
+            Object::Stream(stream) => {
+                let script = match stream.filters() {
+                    Ok(filters) if !filters.is_empty() => stream.decompressed_content().ok()?,
+                    _ => stream.content.to_vec(),
+                };
+                Some(decode_text_string(&script))
+            }
+            _ => None,
+        },
+        ActionKind::Uri => dict
+            .get_deref(b"URI", doc)
+            .and_then(Object::as_str)
+            .ok()
+            .map(|uri| String::from_utf8_lossy(uri).into_owned()),
+        ActionKind::Launch => dict.get(b"F").ok().and_then(file_name).or_else(|| {
+            let windows = dict.get_deref(b"Win", doc).and_then(Object::as_dict).ok()?;
+            file_name(windows.get(b"F").ok()?)
+        }),
+        ActionKind::SubmitForm | ActionKind::ImportData => dict.get(b"F").ok().and_then(file_name),
+    }
+}
+
+/// The entries of a `/Next` entry, which is a single action or an array of actions.
+fn next_actions(dict: &Dictionary) -> Vec<Object> {
+    match dict.get(b"Next") {
+        Ok(Object::Array(actions)) => actions.clone(),
+        Ok(action) => vec![action.clone()],
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Collects actions, identifying dictionaries by address so that each one is reported once.
+struct ActionCollector<'a> {
+    doc: &'a Document,
+    seen: BTreeSet<*const Dictionary>,
+    entries: Vec<ActionEntry>,
+}
+
+impl<'a> ActionCollector<'a> {
+    fn action(&mut self, object: &'a Object, location: &ActionLocation, depth: usize) {
+        let (id, dict) = match self.doc.dereference(object) {
+            Ok((id, Object::Dictionary(dict))) => (id, dict),
+            _ => return,
+        };
+        if !self.seen.insert(dict) {
+            return;
+        }
+        if let Some(kind) = action_kind(dict) {
+            self.entries.push(ActionEntry {
+                kind,
+                location: location.clone(),
+                depth,
+                id,
+                target: action_target(self.doc, dict, kind),
+            });
+        }
+        match dict.get(b"Next") {
+            Ok(Object::Array(actions)) => {
+                for action in actions {
+                    self.action(action, location, depth + 1);
+                }
+            }
+            Ok(action) => self.action(action, location, depth + 1),
+            Err(_) => {}
+        }
+    }
+
+    fn additional_actions<L: Fn(Vec<u8>) -> ActionLocation>(&mut self, holder: &'a Dictionary, location: L) {
+        if let Ok(actions) = holder.get_deref(b"AA", self.doc).and_then(Object::as_dict) {
+            for (trigger, action) in actions {
+                self.action(action, &location(trigger.clone()), 0);
+            }
+        }
+    }
+
+    fn fields(&mut self, fields: &'a [Object], visited: &mut BTreeSet<ObjectId>) {
+        for field in fields {
+            let field_id = match field.as_reference() {
+                Ok(id) if visited.insert(id) => id,
+                _ => continue,
+            };
+            if let Ok(dict) = self.doc.get_dictionary(field_id) {
+                self.additional_actions(dict, |trigger| ActionLocation::Field {
+                    field: field_id,
+                    trigger,
+                });
+                if let Ok(kids) = dict.get_deref(b"Kids", self.doc).and_then(Object::as_array) {
+                    self.fields(kids, visited);
+                }
+            }
+        }
+    }
+
+    /// Report the actions of dictionaries that were not reached from the places actions are expected in.
+    fn other(&mut self, holder: ObjectId, object: &'a Object, top: bool) {
+        let dict = match object {
+            Object::Dictionary(dict) => dict,
+            Object::Stream(stream) => {
+                for (_, value) in stream.dict.iter() {
+                    self.other(holder, value, false);
+                }
+                return;
+            }
+            Object::Array(items) => {
+                for item in items {
+                    self.other(holder, item, false);
+                }
+                return;
+            }
+            _ => return,
+        };
+        if let Some(kind) = action_kind(dict) {
+            if self.seen.insert(dict) {
+                self.entries.push(ActionEntry {
+                    kind,
+                    location: ActionLocation::Other(holder),
+                    depth: 0,
+                    id: Some(holder).filter(|_| top),
+                    target: action_target(self.doc, dict, kind),
+                });
+            }
+        }
+        for (_, value) in dict.iter() {
+            self.other(holder, value, false);
+        }
+    }
+}
+
+/// Removes the selected actions from the objects of a document.
+struct ActionStripper<'p> {
+    selects: &'p dyn Fn(&Dictionary) -> bool,
+    /// Indirect objects that are selected actions, with the actions that follow them.
+    selected: BTreeMap<ObjectId, Vec<Object>>,
+    /// Indirect objects that are additional-actions dictionaries.
+    additional: BTreeSet<ObjectId>,
+}
+
+impl ActionStripper<'_> {
+    /// Replace the selected actions of a list by the actions that follow them.
+    fn splice(&self, actions: Vec<Object>, visited: &mut BTreeSet<ObjectId>) -> Vec<Object> {
+        let mut kept = Vec::new();
+        for action in actions {
+            match action {
+                Object::Reference(id) if self.selected.contains_key(&id) => {
+                    if visited.insert(id) {
+                        kept.extend(self.splice(self.selected[&id].clone(), visited));
+                    }
+                }
+                Object::Dictionary(ref dict) if (self.selects)(dict) => {
+                    kept.extend(self.splice(next_actions(dict), visited));
+                }
+                action => kept.push(action),
+            }
+        }
+        kept
+    }
+
+    /// A single action running the given actions in order, if possible.
+    fn chain(actions: Vec<Object>) -> Option<Object> {
+        let mut actions = actions.into_iter();
+        let first = actions.next()?;
+        let rest: Vec<Object> = actions.collect();
+        if rest.is_empty() {
+            return Some(first);
+        }
+        match first {
+            Object::Dictionary(mut dict) => {
+                let mut next = next_actions(&dict);
+                next.extend(rest);
+                dict.set("Next", next);
+                Some(Object::Dictionary(dict))
+            }
+            first => {
+                warn!("Dropping {} actions that followed a removed action", rest.len());
+                Some(first)
+            }
+        }
+    }
+
+    fn dictionary(&self, dict: &mut Dictionary, additional: bool) {
+        let keys: Vec<Vec<u8>> = dict.iter().map(|(key, _)| key.clone()).collect();
+        for key in keys {
+            let value = dict.get(&key).unwrap().clone();
+            match key.as_slice() {
+                b"Next" => match self.splice(next_actions(dict), &mut BTreeSet::new()) {
+                    next if next.is_empty() => {
+                        dict.remove(b"Next");
+                    }
+                    mut next if next.len() == 1 => dict.set("Next", next.remove(0)),
+                    next => dict.set("Next", next),
+                },
+                b"A" | b"OpenAction" => self.trigger(dict, &key, value),
+                _ if additional => self.trigger(dict, &key, value),
+                b"AA" => {
+                    if let Object::Dictionary(mut actions) = value {
+                        self.dictionary(&mut actions, true);
+                        if actions.is_empty() {
+                            dict.remove(b"AA");
+                        } else {
+                            dict.set("AA", actions);
+                        }
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+            if let Ok(value) = dict.get_mut(&key) {
+                self.object(value);
+            }
+        }
+    }
+
+    fn trigger(&self, dict: &mut Dictionary, key: &[u8], action: Object) {
+        match Self::chain(self.splice(vec![action], &mut BTreeSet::new())) {
+            Some(action) => dict.set(key.to_vec(), action),
+            None => {
+                dict.remove(key);
+            }
+        }
+    }
+
+    fn object(&self, object: &mut Object) {
+        match object {
+            Object::Dictionary(dict) if (self.selects)(dict) => *object = Object::Null,
+            Object::Dictionary(dict) => self.dictionary(dict, false),
+            Object::Stream(stream) => self.dictionary(&mut stream.dict, false),
+            Object::Array(items) => {
+                for item in items {
+                    self.object(item);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Document {
+    /// The JavaScript, launch, form submission, data import and URI actions of the document, with where they are
+    /// triggered from.
+    ///
+    /// Actions are found from the `/JavaScript` name tree, the open action and additional actions of the document,
+    /// the additional actions of pages, the actions of annotations, the additional actions of form fields and the
+    /// outline, following `/Next` chains. Actions anywhere else, such as inside appearance dictionaries, are
+    /// reported last with [`ActionLocation::Other`].
+    pub fn actions_report(&self) -> Vec<ActionEntry> {
+        let mut collector = ActionCollector {
+            doc: self,
+            seen: BTreeSet::new(),
+            entries: Vec::new(),
+        };
+        for (name, action) in self.borrowed_names(b"JavaScript") {
+            collector.action(
+                action,
+                &ActionLocation::DocumentJavaScript(decode_text_string(&name)),
+                0,
+            );
+        }
+
+        if let Ok(catalog) = self.catalog() {
+            if let Ok(action) = catalog.get(b"OpenAction") {
+                collector.action(action, &ActionLocation::OpenAction, 0);
+            }
+            collector.additional_actions(catalog, ActionLocation::Document);
+        }
+
+        for page_id in self.page_iter() {
+            let page = match self.get_dictionary(page_id) {
+                Ok(page) => page,
+                Err(_) => continue,
+            };
+            collector.additional_actions(page, |trigger| ActionLocation::Page { page: page_id, trigger });
+            let annotations: Vec<&Object> = match page.get(b"Annots") {
+                Ok(annots) => match self.dereference(annots) {
+                    Ok((_, Object::Array(annots))) => annots.iter().collect(),
+                    _ => vec![annots],
+                },
+                Err(_) => Vec::new(),
+            };
+            for annotation in annotations {
+                let (annotation_id, dict) = match self.dereference(annotation) {
+                    Ok((id, Object::Dictionary(dict))) => (id, dict),
+                    _ => continue,
+                };
+                if let Ok(action) = dict.get(b"A") {
+                    let location = ActionLocation::Annotation {
+                        page: page_id,
+                        annotation: annotation_id,
+                        trigger: None,
+                    };
+                    collector.action(action, &location, 0);
+                }
+                collector.additional_actions(dict, |trigger| ActionLocation::Annotation {
+                    page: page_id,
+                    annotation: annotation_id,
+                    trigger: Some(trigger),
+                });
+            }
+        }
+
+        if let Some((_, acro_form)) = self.acro_form() {
+            if let Ok(fields) = acro_form.get_deref(b"Fields", self).and_then(Object::as_array) {
+                collector.fields(fields, &mut BTreeSet::new());
+            }
+        }
+
+        let outlines = self
+            .catalog()
+            .and_then(|catalog| catalog.get_deref(b"Outlines", self))
+            .and_then(Object::as_dict);
+        let mut items: Vec<ObjectId> = outlines
+            .and_then(|outlines| outlines.get(b"First"))
+            .and_then(Object::as_reference)
+            .into_iter()
+            .collect();
+        let mut visited = BTreeSet::new();
+        while let Some(item_id) = items.pop() {
+            let item = match self.get_dictionary(item_id) {
+                Ok(item) if visited.insert(item_id) => item,
+                _ => continue,
+            };
+            if let Ok(action) = item.get(b"A") {
+                collector.action(action, &ActionLocation::Outline(item_id), 0);
+            }
+            for key in [&b"Next"[..], b"First"] {
+                items.extend(item.get(key).and_then(Object::as_reference));
+            }
+        }
+
+        for (&id, object) in &self.objects {
+            collector.other(id, object, true);
+        }
+        collector.entries
+    }
+
+    /// Remove the selected kinds of actions from the document, and its embedded files if requested.
+    ///
+    /// Removed actions in a `/Next` chain are replaced by the actions that follow them, triggers and
+    /// additional-actions dictionaries left without actions are removed, and selected actions found anywhere else
+    /// are replaced by `null`. The objects that are no longer referenced are left for
+    /// [`Document::prune_objects`].
+    pub fn sanitize(&mut self, policy: SanitizePolicy) -> Result<SanitizeReport> {
+        let actions: Vec<ActionEntry> = self
+            .actions_report()
+            .into_iter()
+            .filter(|entry| policy.selects(entry.kind))
+            .collect();
+
+        if policy.javascript {
+            let scripts: BTreeMap<Vec<u8>, Object> = self
+                .names(b"JavaScript")
+                .into_iter()
+                .filter(|(_, action)| match self.dereference(action) {
+                    Ok((_, Object::Dictionary(action))) => !policy.selects_action(action),
+                    _ => true,
+                })
+                .collect();
+            if self.catalog()?.has(b"Names") {
+                self.set_names("JavaScript", scripts)?;
+            }
+        }
+
+        self.strip_actions(&|dict| policy.selects_action(dict));
+
+        let mut embedded_files = 0;
+        if policy.embedded_files {
+            embedded_files = self.attachments().len();
+            if self.catalog()?.has(b"Names") {
+                self.set_names("EmbeddedFiles", BTreeMap::new())?;
+            }
+            let root_id = self.trailer.get(b"Root")?.as_reference()?;
+            self.get_object_mut(root_id)?.as_dict_mut()?.remove(b"AF");
+            self.remove_annotations(None, |annotation| {
+                annotation.subtype == AnnotationSubtype::FileAttachment
+            });
+        }
+        Ok(SanitizeReport {
+            actions,
+            embedded_files,
+        })
+    }
+
+    /// Remove the selected actions as [`Document::sanitize`] does, taking them out of the `/Next` chains they are
+    /// in and replacing those found anywhere else by `null`.
+    pub(crate) fn strip_actions(&mut self, selects: &dyn Fn(&Dictionary) -> bool) {
+        let mut stripper = ActionStripper {
+            selects,
+            selected: BTreeMap::new(),
+            additional: BTreeSet::new(),
+        };
+        for (&id, object) in &self.objects {
+            match object {
+                Object::Dictionary(dict) if selects(dict) => {
+                    stripper.selected.insert(id, next_actions(dict));
+                }
+                Object::Dictionary(dict) | Object::Stream(Stream { dict, .. }) => {
+                    stripper
+                        .additional
+                        .extend(dict.get(b"AA").and_then(Object::as_reference));
+                }
+                _ => {}
+            }
+        }
+        let ids: Vec<ObjectId> = self.objects.keys().copied().collect();
+        for id in ids {
+            let object = self.objects.get_mut(&id).unwrap();
+            if stripper.selected.contains_key(&id) {
+                *object = Object::Null;
+            } else if let Object::Dictionary(dict) = object {
+                stripper.dictionary(dict, stripper.additional.contains(&id));
+            } else {
+                stripper.object(object);
+            }
+        }
+    }
+
+    /// The `/OpenAction` of the document.
+    pub fn open_action(&self) -> Option<DocAction> {
+        let action = self.catalog().ok()?.get(b"OpenAction").ok()?;
+        self.doc_action(action)
+    }
+
+    /// Set or remove the `/OpenAction` of the document.
+    pub fn set_open_action(&mut self, action: OpenAction) -> Result<()> {
+        let root_id = self.trailer.get(b"Root")?.as_reference()?;
+        match action {
+            OpenAction::GotoPage { page, fit } => {
+                let page_id = *self.get_pages().get(&page).ok_or(Error::PageNumberNotFound(page))?;
+                let catalog = self.get_object_mut(root_id)?.as_dict_mut()?;
+                catalog.set("OpenAction", fit.to_object(page_id));
+            }
+            OpenAction::None => {
+                self.get_object_mut(root_id)?.as_dict_mut()?.remove(b"OpenAction");
+            }
+        }
+        Ok(())
+    }
+
+    /// The additional action of a page for a trigger.
+    pub fn page_action(&self, page_id: ObjectId, trigger: PageTrigger) -> Option<DocAction> {
+        let actions = self
+            .get_dictionary(page_id)
+            .and_then(|page| page.get_deref(b"AA", self))
+            .and_then(Object::as_dict)
+            .ok()?;
+        self.doc_action(actions.get(trigger.key().as_bytes()).ok()?)
+    }
+
+    /// Set the additional action of a page for a trigger, or remove it with `None`. The additional-actions
+    /// dictionary is removed once it is empty.
+    pub fn set_page_action(
+        &mut self, page_id: ObjectId, trigger: PageTrigger, action: Option<Dictionary>,
+    ) -> Result<()> {
+        let actions_id = self
+            .get_dictionary(page_id)?
+            .get(b"AA")
+            .and_then(Object::as_reference)
+            .ok();
+        let actions = match actions_id {
+            Some(actions_id) => self.get_object_mut(actions_id)?.as_dict_mut()?,
+            None => {
+                let page = self.get_object_mut(page_id)?.as_dict_mut()?;
+                if page.get(b"AA").and_then(Object::as_dict).is_err() {
+                    page.set("AA", Dictionary::new());
+                }
+                page.get_mut(b"AA")?.as_dict_mut()?
+            }
+        };
+        match action {
+            Some(action) => actions.set(trigger.key(), action),
+            None => {
+                actions.remove(trigger.key().as_bytes());
+            }
+        }
+        if actions.is_empty() {
+            self.get_object_mut(page_id)?.as_dict_mut()?.remove(b"AA");
+        }
+        Ok(())
+    }
+
+    fn doc_action(&self, action: &Object) -> Option<DocAction> {
+        let (id, action) = self.dereference(action).ok()?;
+        let dict = match action {
+            Object::Dictionary(dict) => dict,
+            dest => return Destination::from_object(self, dest).map(DocAction::GoTo),
+        };
+        let action_type = dict.get(b"S").and_then(Object::as_name).ok()?;
+        let dest = match dict.get(b"D") {
+            Ok(dest) if action_type == b"GoTo" => self.destination_from_object(dest, &self.named_destinations()),
+            _ => None,
+        };
+        Some(match dest {
+            Some(dest) => DocAction::GoTo(dest),
+            None => DocAction::Other {
+                action_type: action_type.to_vec(),
+                id,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::creator::tests::create_document;
+    use crate::geometry::Rect;
+    use crate::AttachmentOptions;
+
+    #[test]
+    fn report_and_strip_actions() {
+        let mut doc = create_document();
+        let page_id = doc.page_iter().next().unwrap();
+        let script = |js: &str| dictionary! { "S" => "JavaScript", "JS" => Object::string_literal(js) };
+        let uri = |uri: &str| dictionary! { "S" => "URI", "URI" => Object::string_literal(uri) };
+
+        let mut scripts = BTreeMap::new();
+        scripts.insert(b"init".to_vec(), doc.add_object(script("app.alert(1)")).into());
+        doc.set_names("JavaScript", scripts).unwrap();
+        doc.attach_file("payload.exe", b"MZ".to_vec(), &AttachmentOptions::default())
+            .unwrap();
+        let mut open_action = script("this.print()");
+        open_action.set("Next", uri("https://example.com/open"));
+        let root_id = doc.trailer.get(b"Root").unwrap().as_reference().unwrap();
+        let catalog = doc.get_object_mut(root_id).and_then(Object::as_dict_mut).unwrap();
+        catalog.set("OpenAction", open_action);
+        let page = doc.get_object_mut(page_id).and_then(Object::as_dict_mut).unwrap();
+        page.set(
+            "AA",
+            dictionary! { "O" => dictionary! { "S" => "Launch", "F" => Object::string_literal("calc.exe") } },
+        );
+
+        let chained_id = doc.add_object(script("submit()"));
+        let mut link_action = uri("https://example.com");
+        link_action.set("Next", vec![chained_id.into()]);
+        let on = doc.add_object(Stream::new(dictionary! { "S" => "JavaScript" }, Vec::new()));
+        let link_id = doc
+            .add_annotation(
+                page_id,
+                dictionary! {
+                    "Subtype" => "Link",
+                    "Rect" => Rect::new(0.0, 0.0, 10.0, 10.0),
+                    "A" => link_action,
+                    "AP" => dictionary! { "N" => dictionary! { "On" => on, "Off" => script("hidden()") } },
+                },
+            )
+            .unwrap();
+
+        let report = doc.actions_report();
+        let found: Vec<(ActionKind, usize, Option<String>)> = report
+            .iter()
+            .map(|entry| (entry.kind, entry.depth, entry.target.clone()))
+            .collect();
+        let target = |target: &str| Some(target.to_string());
+        assert_eq!(
+            found,
+            vec![
+                (ActionKind::JavaScript, 0, target("app.alert(1)")),
+                (ActionKind::JavaScript, 0, target("this.print()")),
+                (ActionKind::Uri, 1, target("https://example.com/open")),
+                (ActionKind::Launch, 0, target("calc.exe")),
+                (ActionKind::Uri, 0, target("https://example.com")),
+                (ActionKind::JavaScript, 1, target("submit()")),
+                (ActionKind::JavaScript, 0, target("hidden()")),
+            ]
+        );
+        assert_eq!(report[1].location, ActionLocation::OpenAction);
+        assert_eq!(report[5].id, Some(chained_id));
+        assert_eq!(
+            report[5].location,
+            ActionLocation::Annotation {
+                page: page_id,
+                annotation: Some(link_id),
+                trigger: None
+            }
+        );
+        assert_eq!(report[6].location, ActionLocation::Other(link_id));
+
+        let policy = SanitizePolicy {
+            javascript: true,
+            launch: true,
+            embedded_files: true,
+            ..Default::default()
+        };
+        let stripped = doc.sanitize(policy).unwrap();
+        assert_eq!(stripped.actions.len(), 5);
+        assert_eq!(stripped.embedded_files, 1);
+
+        let remaining: Vec<(ActionKind, ActionLocation)> = doc
+            .actions_report()
+            .into_iter()
+            .map(|entry| (entry.kind, entry.location))
+            .collect();
+        let link_location = ActionLocation::Annotation {
+            page: page_id,
+            annotation: Some(link_id),
+            trigger: None,
+        };
+        assert_eq!(
+            remaining,
+            vec![
+                (ActionKind::Uri, ActionLocation::OpenAction),
+                (ActionKind::Uri, link_location)
+            ]
+        );
+        assert!(!doc.catalog().unwrap().has(b"Names"));
+        assert!(!doc.get_dictionary(page_id).unwrap().has(b"AA"));
+        let link = doc.get_dictionary(link_id).unwrap();
+        assert!(!link.get(b"A").and_then(Object::as_dict).unwrap().has(b"Next"));
+        assert!(matches!(doc.get_object(chained_id), Ok(Object::Null)));
+    }
+
+    #[test]
+    fn open_and_page_actions() {
+        let mut doc = create_document();
+        let page_id = doc.page_iter().next().unwrap();
+        assert_eq!(doc.open_action(), None);
+        let fit = DestinationFit::FitH { top: None };
+        doc.set_open_action(OpenAction::GotoPage { page: 1, fit }).unwrap();
+        assert_eq!(doc.open_action(), Some(DocAction::GoTo(Destination::new(page_id, fit))));
+        assert!(doc.set_open_action(OpenAction::GotoPage { page: 2, fit }).is_err());
+
+        doc.add_named_destination("start", page_id, DestinationFit::Fit)
+            .unwrap();
+        let root_id = doc.trailer.get(b"Root").unwrap().as_reference().unwrap();
+        let catalog = doc.get_object_mut(root_id).and_then(Object::as_dict_mut).unwrap();
+        catalog.set(
+            "OpenAction",
+            dictionary! { "S" => "GoTo", "D" => Object::string_literal("start") },
+        );
+        assert_eq!(
+            doc.open_action(),
+            Some(DocAction::GoTo(Destination::new(page_id, DestinationFit::Fit)))
+        );
+        doc.set_open_action(OpenAction::None).unwrap();
+        assert!(!doc.catalog().unwrap().has(b"OpenAction"));
+
+        let script = dictionary! { "S" => "JavaScript", "JS" => Object::string_literal("app.beep()") };
+        doc.set_page_action(page_id, PageTrigger::Open, Some(script)).unwrap();
+        let other = DocAction::Other {
+            action_type: b"JavaScript".to_vec(),
+            id: None,
+        };
+        assert_eq!(doc.page_action(page_id, PageTrigger::Open), Some(other));
+        assert_eq!(doc.page_action(page_id, PageTrigger::Close), None);
+        doc.set_page_action(page_id, PageTrigger::Open, None).unwrap();
+        assert!(!doc.get_dictionary(page_id).unwrap().has(b"AA"));
+    }
+}
@@ -0,0 +1,1837 @@
+use crate::content::{Content, Operation};
+use crate::destinations::DestinationFit;
+use crate::encodings::{decode_text_string, encode_text_string};
+use crate::forms::appearance::{ellipse, wrap_text, DefaultAppearance, FieldFont, TextFont};
+use crate::geometry::{Quad, Rect};
+use crate::truetype::TrueTypeFont;
+use crate::{Color, Dictionary, Document, Error, Object, ObjectId, Result, Stream, StringFormat};
+use bitflags::bitflags;
+use log::warn;
+use std::collections::BTreeSet;
+
+/// Annotation type, from the `/Subtype` entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnnotationSubtype {
+    Text,
+    Link,
+    FreeText,
+    Line,
+    Square,
+    Circle,
+    Polygon,
+    PolyLine,
+    Highlight,
+    Underline,
+    Squiggly,
+    StrikeOut,
+    Stamp,
+    Caret,
+    Ink,
+    Popup,
+    FileAttachment,
+    Sound,
+    Movie,
+    Widget,
+    Screen,
+    PrinterMark,
+    TrapNet,
+    Watermark,
+    ThreeD,
+    Redact,
+    /// A subtype not listed above, or a missing `/Subtype`.
+    Unknown(Vec<u8>),
+}
+
+impl AnnotationSubtype {
+    pub fn from_name(name: &[u8]) -> AnnotationSubtype {
+        use AnnotationSubtype::*;
+        match name {
+            b"Text" => Text,
+            b"Link" => Link,
+            b"FreeText" => FreeText,
+            b"Line" => Line,
+            b"Square" => Square,
+            b"Circle" => Circle,
+            b"Polygon" => Polygon,
+            b"PolyLine" => PolyLine,
+            b"Highlight" => Highlight,
+            b"Underline" => Underline,
+            b"Squiggly" => Squiggly,
+            b"StrikeOut" => StrikeOut,
+            b"Stamp" => Stamp,
+            b"Caret" => Caret,
+            b"Ink" => Ink,
+            b"Popup" => Popup,
+            b"FileAttachment" => FileAttachment,
+            b"Sound" => Sound,
+            b"Movie" => Movie,
+            b"Widget" => Widget,
+            b"Screen" => Screen,
+            b"PrinterMark" => PrinterMark,
+            b"TrapNet" => TrapNet,
+            b"Watermark" => Watermark,
+            b"3D" => ThreeD,
+            b"Redact" => Redact,
+            _ => Unknown(name.to_vec()),
+        }
+    }
+
+    /// Markup annotations are the ones that can carry an author, replies and a popup.
+    pub fn is_markup(&self) -> bool {
+        use AnnotationSubtype::*;
+        !matches!(
+            self,
+            Link | Popup | Movie | Widget | Screen | PrinterMark | TrapNet | Watermark | ThreeD | Unknown(_)
+        )
+    }
+}
+
+bitflags! {
+    /// Annotation flags, from the `/F` entry.
+    pub struct AnnotationFlags: u32 {
+        const INVISIBLE = 1;
+        const HIDDEN = 1 << 1;
+        const PRINT = 1 << 2;
+        const NO_ZOOM = 1 << 3;
+        const NO_ROTATE = 1 << 4;
+        const NO_VIEW = 1 << 5;
+        const READ_ONLY = 1 << 6;
+        const LOCKED = 1 << 7;
+        const TOGGLE_NO_VIEW = 1 << 8;
+        const LOCKED_CONTENTS = 1 << 9;
+    }
+}
+
+/// An entry of an appearance dictionary (`/AP`).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Appearance {
+    Stream(ObjectId),
+    /// One stream per appearance state, selected by the `/AS` entry of the annotation.
+    States(Vec<(Vec<u8>, ObjectId)>),
+}
+
+impl Appearance {
+    /// The stream used for the given appearance state.
+    pub fn stream(&self, state: Option<&[u8]>) -> Option<ObjectId> {
+        match self {
+            Appearance::Stream(id) => Some(*id),
+            Appearance::States(states) => {
+                let state = state?;
+                states.iter().find(|(name, _)| name == state).map(|&(_, id)| id)
+            }
+        }
+    }
+}
+
+/// Typed view of an annotation dictionary.
+#[derive(Debug, Clone)]
+pub struct Annotation {
+    /// `None` for an annotation dictionary stored directly in the `/Annots` array.
+    pub id: Option<ObjectId>,
+    pub subtype: AnnotationSubtype,
+    pub rect: Option<Rect>,
+    pub contents: Option<String>,
+    pub flags: AnnotationFlags,
+    /// The `/M` entry, which is usually but not necessarily a date.
+    pub modified: Option<String>,
+    pub appearance_state: Option<Vec<u8>>,
+    pub normal_appearance: Option<Appearance>,
+    pub rollover_appearance: Option<Appearance>,
+    pub down_appearance: Option<Appearance>,
+    /// The `/C` entry: the color of the border, of the icon or of the title bar of a pop-up, depending on the type.
+    pub color: Option<Color>,
+    /// The `/IC` entry: the color the interior of squares, circles and line endings, among others, is filled with.
+    pub interior_color: Option<Color>,
+    /// The `/T` entry of markup annotations.
+    pub author: Option<String>,
+    pub quad_points: Vec<Quad>,
+    /// The annotation this one is a reply to.
+    pub in_reply_to: Option<ObjectId>,
+}
+
+impl Annotation {
+    pub(crate) fn from_dict(doc: &Document, id: Option<ObjectId>, dict: &Dictionary) -> Annotation {
+        let text = |key: &[u8]| {
+            let object = doc.dereference(dict.get(key).ok()?).ok()?.1;
+            object.as_str().ok().map(decode_text_string)
+        };
+        let subtype = match dict.get(b"Subtype").and_then(Object::as_name) {
+            Ok(name) => AnnotationSubtype::from_name(name),
+            Err(_) => AnnotationSubtype::Unknown(Vec::new()),
+        };
+        let appearances = dict
+            .get(b"AP")
+            .ok()
+            .and_then(|ap| doc.dereference(ap).ok())
+            .and_then(|(_, ap)| ap.as_dict().ok());
+        let appearance = |key: &[u8]| appearance(doc, appearances?.get(key).ok()?);
+        let color = |key: &[u8]| Color::from_object(doc.dereference(dict.get(key).ok()?).ok()?.1);
+
+        let mut annotation = Annotation {
+            id,
+            rect: dict
+                .get(b"Rect")
+                .ok()
+                .and_then(|rect| Rect::from_object(doc.dereference(rect).ok()?.1).ok()),
+            contents: text(b"Contents"),
+            flags: dict
+                .get(b"F")
+                .and_then(Object::as_i64)
+                .map_or(AnnotationFlags::empty(), |flags| {
+                    AnnotationFlags::from_bits_truncate(flags as u32)
+                }),
+            modified: text(b"M"),
+            appearance_state: dict.get(b"AS").and_then(Object::as_name).ok().map(<[u8]>::to_vec),
+            normal_appearance: appearance(b"N"),
+            rollover_appearance: appearance(b"R"),
+            down_appearance: appearance(b"D"),
+            color: color(b"C"),
+            interior_color: color(b"IC"),
+            author: None,
+            quad_points: Vec::new(),
+            in_reply_to: None,
+            subtype,
+        };
+        if annotation.subtype.is_markup() {
+            annotation.author = text(b"T");
+            annotation.in_reply_to = dict.get(b"IRT").and_then(Object::as_reference).ok();
+            if let Some(points) = dict.get(b"QuadPoints").ok().and_then(|points| {
+                let points = doc.dereference(points).ok()?.1.as_array().ok()?;
+                points
+                    .iter()
+                    .map(|point| point.as_float().ok())
+                    .collect::<Option<Vec<_>>>()
+            }) {
+                annotation.quad_points = points.chunks_exact(8).filter_map(Quad::from_points).collect();
+            }
+        }
+        annotation
+    }
+
+    /// The `/M` entry as a date.
+    #[cfg(feature = "chrono_time")]
+    pub fn modified_date(&self) -> Option<chrono::DateTime<chrono::Local>> {
+        Object::string_literal(self.modified.as_ref()?.as_str()).as_datetime()
+    }
+
+    /// The `/M` entry as a date.
+    #[cfg(not(feature = "chrono_time"))]
+    pub fn modified_date(&self) -> Option<time::OffsetDateTime> {
+        Object::string_literal(self.modified.as_ref()?.as_str()).as_datetime()
+    }
+
+    /// The normal appearance stream for the current appearance state.
+    pub fn normal_appearance_stream(&self) -> Option<ObjectId> {
+        self.normal_appearance
+            .as_ref()?
+            .stream(self.appearance_state.as_deref())
+    }
+}
+
+/// Read an appearance dictionary entry, which is a stream or a dictionary of streams.
+fn appearance(doc: &Document, object: &Object) -> Option<Appearance> {
+    match object {
+        Object::Reference(id) => match doc.get_object(*id).ok()? {
+            Object::Stream(_) => Some(Appearance::Stream(*id)),
+            Object::Dictionary(states) => Some(appearance_states(states)),
+            _ => None,
+        },
+        Object::Dictionary(states) => Some(appearance_states(states)),
+        _ => None,
+    }
+}
+
+fn appearance_states(states: &Dictionary) -> Appearance {
+    Appearance::States(
+        states
+            .iter()
+            .filter_map(|(name, stream)| Some((name.clone(), stream.as_reference().ok()?)))
+            .collect(),
+    )
+}
+
+/// Where a link annotation leads.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinkTarget {
+    Uri(String),
+    /// A page of the document, by page number starting at 1.
+    Page {
+        page: u32,
+        fit: DestinationFit,
+    },
+    /// A named destination of the document.
+    Named(Vec<u8>),
+}
+
+/// Kind of text markup annotation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarkupKind {
+    Highlight,
+    Underline,
+    StrikeOut,
+    Squiggly,
+}
+
+impl MarkupKind {
+    fn subtype(self) -> &'static str {
+        match self {
+            MarkupKind::Highlight => "Highlight",
+            MarkupKind::Underline => "Underline",
+            MarkupKind::StrikeOut => "StrikeOut",
+            MarkupKind::Squiggly => "Squiggly",
+        }
+    }
+}
+
+/// Options for [`Document::add_markup`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarkupOptions {
+    pub color: Color,
+    pub opacity: f64,
+    pub author: Option<String>,
+    pub contents: Option<String>,
+}
+
+impl Default for MarkupOptions {
+    fn default() -> Self {
+        MarkupOptions {
+            color: Color::Rgb(1.0, 1.0, 0.0),
+            opacity: 1.0,
+            author: None,
+            contents: None,
+        }
+    }
+}
+
+/// Order in which keyboard navigation visits the annotations of a page, from the `/Tabs` entry.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TabOrder {
+    /// The order of the structure tree.
+    Structure,
+    /// Row by row, from the top.
+    Row,
+    /// Column by column, from the left.
+    Column,
+    /// The given annotations first, in that order, then the other annotations of the page in their current order.
+    /// The `/Annots` array is rearranged accordingly, and navigation follows it.
+    Manual(Vec<ObjectId>),
+}
+
+/// Font of the text of a free text annotation.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FreeTextFont {
+    /// One of the standard 14 fonts, such as `Helvetica` or `Times-Roman`, which only shows WinAnsiEncoding text.
+    Standard(String),
+    /// The data of a TrueType font file, embedded whole, for text in any script the font covers.
+    TrueType(Vec<u8>),
+}
+
+/// Shape drawn at the end of a line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    None,
+    OpenArrow,
+    ClosedArrow,
+    /// A short line across the end.
+    Butt,
+}
+
+impl LineEnding {
+    fn name(self) -> &'static str {
+        match self {
+            LineEnding::None => "None",
+            LineEnding::OpenArrow => "OpenArrow",
+            LineEnding::ClosedArrow => "ClosedArrow",
+            LineEnding::Butt => "Butt",
+        }
+    }
+}
+
+/// Line from a free text annotation to the point it refers to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Callout {
+    /// The point referred to, where the ending is drawn.
+    pub start: (f64, f64),
+    /// Optional point where the line bends.
+    pub knee: Option<(f64, f64)>,
+    /// The point where the line meets the text box.
+    pub end: (f64, f64),
+    pub ending: LineEnding,
+}
+
+/// Options for [`Document::add_free_text`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FreeTextOptions {
+    pub font: FreeTextFont,
+    pub font_size: f64,
+    pub text_color: Color,
+    /// The border and callout line are not drawn without a color.
+    pub border_color: Option<Color>,
+    pub border_width: f64,
+    pub fill_color: Option<Color>,
+    /// Text alignment: 0 for left, 1 for centered and 2 for right.
+    pub quadding: i64,
+    pub callout: Option<Callout>,
+    pub author: Option<String>,
+}
+
+impl Default for FreeTextOptions {
+    fn default() -> Self {
+        FreeTextOptions {
+            font: FreeTextFont::Standard("Helvetica".to_string()),
+            font_size: 12.0,
+            text_color: Color::Rgb(0.0, 0.0, 0.0),
+            border_color: Some(Color::Rgb(0.0, 0.0, 0.0)),
+            border_width: 1.0,
+            fill_color: None,
+            quadding: 0,
+            callout: None,
+            author: None,
+        }
+    }
+}
+
+/// Which device annotations are flattened for, as flags can show an annotation on screen and not in print.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlattenTarget {
+    Screen,
+    Print,
+}
+
+/// Options for [`Document::flatten_annotations`].
+#[derive(Debug, Clone)]
+pub struct AnnotationFlattenOptions {
+    pub target: FlattenTarget,
+    /// Draw a simple appearance for `Square`, `Circle`, `Line` and text markup annotations without an appearance
+    /// stream, instead of leaving them in place.
+    pub generate_appearances: bool,
+}
+
+impl Default for AnnotationFlattenOptions {
+    fn default() -> Self {
+        AnnotationFlattenOptions {
+            target: FlattenTarget::Print,
+            generate_appearances: true,
+        }
+    }
+}
+
+/// Outcome of [`Document::flatten_annotations`].
+#[derive(Debug, Clone, Default)]
+pub struct AnnotationFlattenReport {
+    /// Number of annotations drawn into the page content and removed.
+    pub flattened: usize,
+    /// Number of annotations removed without being drawn, as they are not shown on the target.
+    pub hidden: usize,
+    /// Annotations left in place, as they have nothing to draw.
+    pub skipped: Vec<Annotation>,
+}
+
+impl Document {
+    /// The annotations of a page, skipping entries of `/Annots` which are not dictionaries.
+    pub fn annotations(&self, page_id: ObjectId) -> Vec<Annotation> {
+        let annots = match self.get_dictionary(page_id).and_then(|page| page.get(b"Annots")) {
+            Ok(annots) => annots,
+            Err(_) => return Vec::new(),
+        };
+        let annots = match self.dereference(annots) {
+            Ok((_, Object::Array(annots))) => annots.as_slice(),
+            // A single annotation rather than an array.
+            Ok((_, Object::Dictionary(_))) => std::slice::from_ref(annots),
+            _ => return Vec::new(),
+        };
+        annots
+            .iter()
+            .filter_map(|annot| match self.dereference(annot) {
+                Ok((id, Object::Dictionary(dict))) => Some(Annotation::from_dict(self, id, dict)),
+                _ => {
+                    warn!(
+                        "Skipping annotation {:?} of page {:?} which is not a dictionary",
+                        annot, page_id
+                    );
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Add an annotation dictionary as a new object and append it to the `/Annots` of a page.
+    ///
+    /// The `/Type` and `/P` entries are filled in if missing.
+    pub fn add_annotation(&mut self, page_id: ObjectId, mut annotation: Dictionary) -> Result<ObjectId> {
+        self.get_dictionary(page_id)?;
+        if !annotation.has(b"Type") {
+            annotation.set("Type", "Annot");
+        }
+        if !annotation.has(b"P") {
+            annotation.set("P", page_id);
+        }
+        let annotation_id = self.add_object(annotation);
+
+        let page = self.get_object_mut(page_id).and_then(Object::as_dict_mut)?;
+        let annots = page.get(b"Annots").ok().cloned();
+        match annots {
+            None | Some(Object::Null) => page.set("Annots", vec![annotation_id.into()]),
+            Some(Object::Array(_)) => page.get_mut(b"Annots")?.as_array_mut()?.push(annotation_id.into()),
+            Some(Object::Reference(annots_id)) => match self.get_object_mut(annots_id) {
+                Ok(Object::Array(array)) => array.push(annotation_id.into()),
+                // A single annotation rather than an array.
+                _ => {
+                    let page = self.get_object_mut(page_id).and_then(Object::as_dict_mut)?;
+                    page.set("Annots", vec![annots_id.into(), annotation_id.into()]);
+                }
+            },
+            Some(other) => return Err(Error::type_mismatch("Array", other.enum_variant()).at_key(b"Annots")),
+        }
+        Ok(annotation_id)
+    }
+
+    /// Set the tab order of a page.
+    pub fn set_tab_order(&mut self, page_id: ObjectId, order: TabOrder) -> Result<()> {
+        let tabs = match order {
+            TabOrder::Structure => "S",
+            TabOrder::Row => "R",
+            TabOrder::Column => "C",
+            TabOrder::Manual(ids) => {
+                let page = self.get_dictionary(page_id)?;
+                let (annots_id, mut annots) = match page.get(b"Annots") {
+                    Ok(Object::Array(annots)) => (None, annots.clone()),
+                    Ok(Object::Reference(id)) => match self.get_object(*id)? {
+                        Object::Array(annots) => (Some(*id), annots.clone()),
+                        // A single annotation rather than an array.
+                        _ => (None, vec![Object::Reference(*id)]),
+                    },
+                    _ => (None, Vec::new()),
+                };
+                let mut ordered = Vec::with_capacity(annots.len());
+                for id in ids {
+                    match annots.iter().position(|annot| annot.as_reference().ok() == Some(id)) {
+                        Some(index) => ordered.push(annots.remove(index)),
+                        None if ordered.iter().any(|annot| annot.as_reference().ok() == Some(id)) => {}
+                        None => return Err(Error::not_found(id)),
+                    }
+                }
+                ordered.extend(annots);
+                match annots_id {
+                    Some(annots_id) => *self.get_object_mut(annots_id)? = Object::Array(ordered),
+                    None => self.get_object_mut(page_id)?.as_dict_mut()?.set("Annots", ordered),
+                }
+                // Annotation array order, from PDF 2.0.
+                "A"
+            }
+        };
+        self.get_object_mut(page_id)?.as_dict_mut()?.set("Tabs", tabs);
+        Ok(())
+    }
+
+    /// The annotations of a page from top to bottom, and from left to right in each row, to build a
+    /// [`TabOrder::Manual`] order from. Annotations rather above than below the middle of another belong to an
+    /// earlier row. Annotations without a rectangle come last.
+    pub fn annots_in_visual_order(&self, page_id: ObjectId) -> Vec<ObjectId> {
+        let mut placed = Vec::new();
+        let mut unplaced = Vec::new();
+        for annotation in self.annotations(page_id) {
+            match (annotation.id, annotation.rect) {
+                (Some(id), Some(rect)) => placed.push((id, rect)),
+                (Some(id), None) => unplaced.push(id),
+                _ => {}
+            }
+        }
+        placed.sort_by(|(_, a), (_, b)| b.ury.partial_cmp(&a.ury).unwrap_or(std::cmp::Ordering::Equal));
+        let mut rows: Vec<Vec<(ObjectId, Rect)>> = Vec::new();
+        for (id, rect) in placed {
+            match rows.last_mut() {
+                // Compared with the first, topmost annotation of the row.
+                Some(row) if rect.ury > (row[0].1.lly + row[0].1.ury) / 2.0 => row.push((id, rect)),
+                _ => rows.push(vec![(id, rect)]),
+            }
+        }
+        let mut order = Vec::new();
+        for mut row in rows {
+            row.sort_by(|(_, a), (_, b)| a.llx.partial_cmp(&b.llx).unwrap_or(std::cmp::Ordering::Equal));
+            order.extend(row.into_iter().map(|(id, _)| id));
+        }
+        order.extend(unplaced);
+        order
+    }
+
+    /// Remove an annotation from the `/Annots` of a page, keeping the annotation object itself.
+    ///
+    /// Returns whether the page listed the annotation.
+    pub fn remove_annotation(&mut self, page_id: ObjectId, annotation_id: ObjectId) -> Result<bool> {
+        let page = self.get_object_mut(page_id).and_then(Object::as_dict_mut)?;
+        let annots = page.get(b"Annots").ok().cloned();
+        let annots = match annots {
+            Some(Object::Array(_)) => page.get_mut(b"Annots")?,
+            Some(Object::Reference(id)) if id == annotation_id => {
+                page.remove(b"Annots");
+                return Ok(true);
+            }
+            Some(Object::Reference(id)) => self.get_object_mut(id)?,
+            _ => return Ok(false),
+        };
+        match annots {
+            Object::Array(annots) => {
+                let count = annots.len();
+                annots.retain(|annot| annot.as_reference().ok() != Some(annotation_id));
+                Ok(annots.len() < count)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Remove the annotations of a page, or of all pages, that match a predicate, with their popups, and detach
+    /// removed widgets from their form fields. Returns the number of annotations removed.
+    ///
+    /// The annotation objects are left for [`Document::prune_objects`] to delete.
+    pub fn remove_annotations<F: Fn(&Annotation) -> bool>(&mut self, page_id: Option<ObjectId>, predicate: F) -> usize {
+        let scope: BTreeSet<ObjectId> = match page_id {
+            Some(page_id) => std::iter::once(page_id).collect(),
+            None => self.page_iter().collect(),
+        };
+        let mut removed = BTreeSet::new();
+        for &page_id in &scope {
+            for annotation in self.annotations(page_id) {
+                if let Some(id) = annotation.id.filter(|_| predicate(&annotation)) {
+                    removed.insert(id);
+                }
+            }
+        }
+
+        // Popups go with their markup annotation, found from either side of the link.
+        let pages: Vec<ObjectId> = self.page_iter().collect();
+        let reference = |doc: &Document, id: ObjectId, key: &[u8]| {
+            doc.get_dictionary(id)
+                .and_then(|dict| dict.get(key))
+                .and_then(Object::as_reference)
+                .ok()
+        };
+        let mut popups: Vec<ObjectId> = removed.iter().filter_map(|&id| reference(self, id, b"Popup")).collect();
+        for &page_id in &pages {
+            for annotation in self.annotations(page_id) {
+                let id = match annotation.id {
+                    Some(id) if annotation.subtype == AnnotationSubtype::Popup => id,
+                    _ => continue,
+                };
+                if reference(self, id, b"Parent").is_some_and(|parent| removed.contains(&parent)) {
+                    popups.push(id);
+                }
+            }
+        }
+        removed.extend(popups);
+        // Markup annotations that only lose their popup should not refer to it anymore.
+        for &id in &removed {
+            if let Some(parent) = reference(self, id, b"Parent").filter(|parent| !removed.contains(parent)) {
+                if reference(self, parent, b"Popup") == Some(id) {
+                    if let Ok(Object::Dictionary(parent)) = self.get_object_mut(parent) {
+                        parent.remove(b"Popup");
+                    }
+                }
+            }
+        }
+
+        // Remove from every page, as an annotation can wrongly be listed by more than one.
+        let mut direct = 0;
+        for page_id in pages {
+            let annots = match self.get_dictionary(page_id).and_then(|page| page.get(b"Annots")) {
+                Ok(annots) => annots.clone(),
+                Err(_) => continue,
+            };
+            let (holder, entries) = match annots {
+                Object::Array(entries) => (None, entries),
+                Object::Reference(id) => match self.get_object(id) {
+                    Ok(Object::Array(entries)) => (Some(id), entries.clone()),
+                    _ => {
+                        if removed.contains(&id) {
+                            if let Ok(Object::Dictionary(page)) = self.get_object_mut(page_id) {
+                                page.remove(b"Annots");
+                            }
+                        }
+                        continue;
+                    }
+                },
+                _ => continue,
+            };
+            let count = entries.len();
+            let kept: Vec<Object> = entries
+                .into_iter()
+                .filter(|entry| match entry {
+                    Object::Reference(id) => !removed.contains(id),
+                    Object::Dictionary(dict) if scope.contains(&page_id) => {
+                        let remove = predicate(&Annotation::from_dict(self, None, dict));
+                        direct += remove as usize;
+                        !remove
+                    }
+                    _ => true,
+                })
+                .collect();
+            if kept.len() == count {
+                continue;
+            }
+            match holder {
+                Some(id) => {
+                    if let Ok(entries) = self.get_object_mut(id) {
+                        *entries = Object::Array(kept);
+                    }
+                }
+                None => {
+                    if let Ok(Object::Dictionary(page)) = self.get_object_mut(page_id) {
+                        page.set("Annots", kept);
+                    }
+                }
+            }
+        }
+
+        if self.acro_form().is_some() {
+            for &id in &removed {
+                let is_widget = self
+                    .get_dictionary(id)
+                    .and_then(|dict| dict.get(b"Subtype"))
+                    .and_then(Object::as_name)
+                    .is_ok_and(|subtype| subtype == b"Widget");
+                if is_widget {
+                    if let Err(err) = self.remove_field(id) {
+                        warn!("Could not detach widget {:?} from its form field: {}", id, err);
+                    }
+                }
+            }
+        }
+        removed.len() + direct
+    }
+
+    /// Add a link annotation covering `rect` on a page, without a visible border.
+    pub fn add_link(&mut self, page_id: ObjectId, rect: Rect, target: LinkTarget) -> Result<ObjectId> {
+        let mut link = dictionary! {
+            "Type" => "Annot",
+            "Subtype" => "Link",
+            "Rect" => rect,
+            "Border" => vec![0.into(), 0.into(), 0.into()],
+        };
+        match target {
+            LinkTarget::Uri(uri) => link.set(
+                "A",
+                dictionary! {
+                    "S" => "URI",
+                    "URI" => Object::String(percent_encode_uri(&uri).into_bytes(), StringFormat::Literal),
+                },
+            ),
+            LinkTarget::Page { page, fit } => {
+                let target_id = *self.get_pages().get(&page).ok_or(Error::PageNumberNotFound(page))?;
+                link.set("Dest", fit.to_object(target_id));
+            }
+            LinkTarget::Named(name) => link.set("Dest", Object::String(name, StringFormat::Literal)),
+        }
+        self.add_annotation(page_id, link)
+    }
+
+    /// Add a text markup annotation over the given quads, typically the areas of some text, with an appearance stream.
+    pub fn add_markup(
+        &mut self, page_id: ObjectId, kind: MarkupKind, quads: &[Quad], options: &MarkupOptions,
+    ) -> Result<ObjectId> {
+        let rect = quads
+            .iter()
+            .map(Quad::bounding_rect)
+            .fold(None, |bounds: Option<Rect>, rect| {
+                Some(bounds.map_or(rect, |bounds| bounds.union(&rect)))
+            })
+            .ok_or_else(|| Error::Syntax("Markup annotation without quads".to_string()))?;
+        let appearance_id = self.add_object(markup_appearance(kind, quads, rect, options)?);
+
+        let mut markup = dictionary! {
+            "Type" => "Annot",
+            "Subtype" => kind.subtype(),
+            "Rect" => rect,
+            "QuadPoints" => quads.iter().flat_map(|quad| quad.to_points().to_vec()).map(Object::Real).collect::<Vec<_>>(),
+            "C" => options.color.to_object(),
+            "CA" => options.opacity,
+            "F" => AnnotationFlags::PRINT.bits() as i64,
+            "AP" => dictionary! { "N" => appearance_id },
+        };
+        if let Some(now) = self.clock.now() {
+            markup.set("M", now);
+        }
+        if let Some(author) = &options.author {
+            markup.set("T", Object::String(encode_text_string(author), StringFormat::Literal));
+        }
+        if let Some(contents) = &options.contents {
+            markup.set(
+                "Contents",
+                Object::String(encode_text_string(contents), StringFormat::Literal),
+            );
+        }
+        self.add_annotation(page_id, markup)
+    }
+
+    /// Add a free text annotation showing `text` in the text box `rect`, with an appearance stream laying out the
+    /// text, so that it shows the same in every viewer. Lines are wrapped to the width of the box, and text that
+    /// does not fit is clipped.
+    ///
+    /// The annotation rectangle grows to include the callout line, if any.
+    pub fn add_free_text(
+        &mut self, page_id: ObjectId, rect: Rect, text: &str, options: &FreeTextOptions,
+    ) -> Result<ObjectId> {
+        self.get_dictionary(page_id)?;
+        let (font_id, operations) = match &options.font {
+            FreeTextFont::Standard(name) => {
+                let mut font = dictionary! {
+                    "Type" => "Font",
+                    "Subtype" => "Type1",
+                    "BaseFont" => name.as_str(),
+                };
+                if name != "Symbol" && name != "ZapfDingbats" {
+                    font.set("Encoding", "WinAnsiEncoding");
+                }
+                let operations = free_text_operations(&FieldFont::new(self, &font), rect, text, options);
+                (self.add_object(font), operations)
+            }
+            FreeTextFont::TrueType(data) => {
+                let font = TrueTypeFont::parse(data.clone())?;
+                let operations = free_text_operations(&font, rect, text, options);
+                (self.embed_truetype(&font, text)?, operations)
+            }
+        };
+
+        let mut bounds = rect;
+        let mut callout_points = Vec::new();
+        if let Some(callout) = &options.callout {
+            let reach = line_ending_size(options.border_width);
+            callout_points.push(callout.start);
+            callout_points.extend(callout.knee);
+            callout_points.push(callout.end);
+            for &(x, y) in &callout_points {
+                bounds = bounds.union(&Rect::new(x - reach, y - reach, x + reach, y + reach));
+            }
+        }
+
+        let form = dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Form",
+            "BBox" => bounds,
+            "Resources" => dictionary! { "Font" => dictionary! { "F1" => font_id } },
+        };
+        let appearance_id = self.add_object(Stream::new(form, Content { operations }.encode()?));
+
+        let mut default_appearance = format!(
+            "/F1 {} Tf {}",
+            options.font_size,
+            color_setting(&options.text_color, false)
+        );
+        if let Some(color) = &options.border_color {
+            default_appearance.push(' ');
+            default_appearance.push_str(&color_setting(color, true));
+        }
+        let mut annotation = dictionary! {
+            "Type" => "Annot",
+            "Subtype" => "FreeText",
+            "Rect" => bounds,
+            "Contents" => Object::String(encode_text_string(text), StringFormat::Literal),
+            "DA" => Object::string_literal(default_appearance),
+            "Q" => options.quadding,
+            "BS" => dictionary! { "W" => options.border_width },
+            "F" => AnnotationFlags::PRINT.bits() as i64,
+            "AP" => dictionary! { "N" => appearance_id },
+        };
+        if let Some(now) = self.clock.now() {
+            annotation.set("M", now);
+        }
+        if let Some(color) = options.fill_color {
+            annotation.set("C", color.to_object());
+        }
+        if let Some(callout) = &options.callout {
+            annotation.set("IT", "FreeTextCallout");
+            annotation.set(
+                "CL",
+                callout_points
+                    .iter()
+                    .flat_map(|&(x, y)| vec![Object::Real(x), Object::Real(y)])
+                    .collect::<Vec<_>>(),
+            );
+            annotation.set("LE", callout.ending.name());
+            annotation.set(
+                "RD",
+                vec![
+                    (rect.llx - bounds.llx).into(),
+                    (rect.lly - bounds.lly).into(),
+                    (bounds.urx - rect.urx).into(),
+                    (bounds.ury - rect.ury).into(),
+                ],
+            );
+        }
+        if let Some(author) = &options.author {
+            annotation.set("T", Object::String(encode_text_string(author), StringFormat::Literal));
+        }
+        self.add_annotation(page_id, annotation)
+    }
+
+    /// Draw the normal appearance of the annotations of a page that match a filter into the page content, and remove
+    /// them with their popups. Widgets are left to [`Document::flatten_form`], and popups are not drawn.
+    ///
+    /// The appearances are fitted to the annotation rectangles the same way as for form fields. Annotations hidden
+    /// on the target are removed without being drawn.
+    pub fn flatten_annotations<F: Fn(&Annotation) -> bool>(
+        &mut self, page_id: ObjectId, filter: F, options: &AnnotationFlattenOptions,
+    ) -> Result<AnnotationFlattenReport> {
+        let mut report = AnnotationFlattenReport::default();
+        let mut operations = Vec::new();
+        let mut removed = BTreeSet::new();
+        for annotation in self.annotations(page_id) {
+            if matches!(annotation.subtype, AnnotationSubtype::Widget | AnnotationSubtype::Popup)
+                || !filter(&annotation)
+            {
+                continue;
+            }
+            // Annotations stored directly in `/Annots` cannot be told apart reliably, so they are left alone.
+            let id = match annotation.id {
+                Some(id) => id,
+                None => {
+                    report.skipped.push(annotation);
+                    continue;
+                }
+            };
+            let flags = annotation.flags;
+            let hidden = flags.contains(AnnotationFlags::HIDDEN)
+                || match options.target {
+                    FlattenTarget::Screen => flags.contains(AnnotationFlags::NO_VIEW),
+                    FlattenTarget::Print => !flags.contains(AnnotationFlags::PRINT),
+                };
+            if hidden {
+                report.hidden += 1;
+                removed.insert(id);
+                continue;
+            }
+            let form_id = match self.appearance_xobject(&annotation)? {
+                Some(form_id) => Some(form_id),
+                None if options.generate_appearances => self
+                    .generated_appearance(id, &annotation)?
+                    .map(|form| self.add_object(form)),
+                None => None,
+            };
+            match (form_id, annotation.rect) {
+                (Some(form_id), Some(rect)) => {
+                    operations.extend(self.appearance_operations(page_id, rect, form_id)?);
+                    report.flattened += 1;
+                    removed.insert(id);
+                }
+                _ => report.skipped.push(annotation),
+            }
+        }
+        if !operations.is_empty() {
+            self.append_isolated_content(page_id, Content { operations }.encode()?)?;
+        }
+        self.remove_annotations(Some(page_id), |annotation| {
+            annotation.id.is_some_and(|id| removed.contains(&id))
+        });
+        Ok(report)
+    }
+
+    /// Draw the appearances of the annotations of a page that match a filter again, to fit their current rectangle
+    /// after it changed, as viewers stretch appearances to the rectangle. The appearances of text fields, choice
+    /// fields and buttons are made from their value and states, those of free text annotations from their text and
+    /// default appearance, and those of `Square`, `Circle`, `Line` and text markup annotations from their colors
+    /// and geometry; every appearance state is drawn again. Other annotations are left alone.
+    ///
+    /// Returns the number of annotations drawn again.
+    pub fn regenerate_annotation_appearances<F: Fn(&Annotation) -> bool>(
+        &mut self, page_id: ObjectId, filter: F,
+    ) -> Result<usize> {
+        let mut regenerated = 0;
+        for annotation in self.annotations(page_id) {
+            let id = match (annotation.id, annotation.rect) {
+                (Some(id), Some(_)) if filter(&annotation) => id,
+                _ => continue,
+            };
+            let stream = match annotation.subtype {
+                AnnotationSubtype::Widget => {
+                    if self.regenerate_widget_appearance(id)? {
+                        regenerated += 1;
+                    }
+                    continue;
+                }
+                AnnotationSubtype::FreeText => self.regenerated_free_text(id, &annotation)?,
+                _ => self.generated_appearance(id, &annotation)?,
+            };
+            if let Some(stream) = stream {
+                let stream_id = self.add_object(stream);
+                let normal = match annotation.normal_appearance {
+                    Some(Appearance::States(ref states)) if !states.is_empty() => Object::Dictionary(
+                        states
+                            .iter()
+                            .map(|(state, _)| (state.clone(), Object::Reference(stream_id)))
+                            .collect(),
+                    ),
+                    _ => stream_id.into(),
+                };
+                self.get_object_mut(id)?
+                    .as_dict_mut()?
+                    .set("AP", dictionary! { "N" => normal });
+                regenerated += 1;
+            }
+        }
+        Ok(regenerated)
+    }
+
+    /// Lay out the text of a free text annotation again in its rectangle, with the font of its appearance and the
+    /// size and colors of its default appearance string. Callouts and composite fonts are not drawn again.
+    fn regenerated_free_text(&self, id: ObjectId, annotation: &Annotation) -> Result<Option<Stream>> {
+        let (dict, rect) = match annotation.rect {
+            Some(rect) => (self.get_dictionary(id)?, rect),
+            None => return Ok(None),
+        };
+        let font_resource = annotation
+            .normal_appearance_stream()
+            .and_then(|stream_id| self.get_object(stream_id).and_then(Object::as_stream).ok())
+            .and_then(|stream| stream.dict.get_deref(b"Resources", self).and_then(Object::as_dict).ok())
+            .and_then(|resources| resources.get_deref(b"Font", self).and_then(Object::as_dict).ok())
+            .and_then(|fonts| fonts.get(b"F1").ok().cloned());
+        let font_resource = match font_resource {
+            Some(font_resource) if !dict.has(b"CL") => font_resource,
+            _ => return Ok(None),
+        };
+        let font = self.dereference(&font_resource)?.1.as_dict()?;
+        if font.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Type0") {
+            return Ok(None);
+        }
+
+        let default_appearance = dict.get_deref(b"DA", self).and_then(Object::as_str).unwrap_or(b"");
+        let parsed = DefaultAppearance::parse(default_appearance);
+        let options = FreeTextOptions {
+            font_size: if parsed.size > 0.0 { parsed.size } else { 12.0 },
+            text_color: parsed.color,
+            border_color: parsed.stroke_color,
+            border_width: dict
+                .get_deref(b"BS", self)
+                .and_then(Object::as_dict)
+                .and_then(|style| style.get_deref(b"W", self))
+                .and_then(Object::as_float)
+                .unwrap_or(1.0),
+            fill_color: annotation.color,
+            quadding: dict.get_deref(b"Q", self).and_then(Object::as_i64).unwrap_or(0),
+            ..FreeTextOptions::default()
+        };
+        let text = annotation.contents.clone().unwrap_or_default();
+        let operations = free_text_operations(&FieldFont::new(self, font), rect, &text, &options);
+        let form = dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Form",
+            "BBox" => rect,
+            "Resources" => dictionary! { "Font" => dictionary! { "F1" => font_resource } },
+        };
+        Ok(Some(Stream::new(form, Content { operations }.encode()?)))
+    }
+
+    /// A minimal appearance for the common annotation types, drawn from their colors, border width and geometry.
+    fn generated_appearance(&self, id: ObjectId, annotation: &Annotation) -> Result<Option<Stream>> {
+        let rect = match annotation.rect {
+            Some(rect) => rect,
+            None => return Ok(None),
+        };
+        let dict = self.get_dictionary(id)?;
+        let numbers = |key: &[u8]| -> Vec<f64> {
+            dict.get_deref(key, self)
+                .and_then(Object::as_array)
+                .map(|array| array.iter().filter_map(|value| value.as_float().ok()).collect())
+                .unwrap_or_default()
+        };
+        let width = dict
+            .get_deref(b"BS", self)
+            .and_then(Object::as_dict)
+            .and_then(|style| style.get_deref(b"W", self))
+            .and_then(Object::as_float)
+            .ok()
+            .or_else(|| numbers(b"Border").get(2).copied())
+            .unwrap_or(1.0);
+
+        let mut operations = Vec::new();
+        let markup = match annotation.subtype {
+            AnnotationSubtype::Highlight => Some(MarkupKind::Highlight),
+            AnnotationSubtype::Underline => Some(MarkupKind::Underline),
+            AnnotationSubtype::StrikeOut => Some(MarkupKind::StrikeOut),
+            AnnotationSubtype::Squiggly => Some(MarkupKind::Squiggly),
+            _ => None,
+        };
+        if let Some(kind) = markup {
+            let quads = if annotation.quad_points.is_empty() {
+                vec![Quad::from(rect)]
+            } else {
+                annotation.quad_points.clone()
+            };
+            let mut options = MarkupOptions::default();
+            if let Some(color) = annotation.color {
+                options.color = color;
+            }
+            if let Ok(opacity) = dict.get_deref(b"CA", self).and_then(Object::as_float) {
+                options.opacity = opacity;
+            }
+            return markup_appearance(kind, &quads, rect, &options).map(Some);
+        }
+        match annotation.subtype {
+            AnnotationSubtype::Square | AnnotationSubtype::Circle => {
+                let interior = annotation.interior_color.map(|color| color.fill_operation());
+                let stroke = annotation.color.filter(|_| width > 0.0).map(|color| color.stroke_operation());
+                let painting = match (&stroke, &interior) {
+                    (Some(_), Some(_)) => "B",
+                    (Some(_), None) => "S",
+                    (None, Some(_)) => "f",
+                    (None, None) => return Ok(None),
+                };
+                operations.extend(stroke);
+                operations.extend(interior);
+                operations.push(Operation::new("w", vec![width.into()]));
+                let inset = width / 2.0;
+                if annotation.subtype == AnnotationSubtype::Circle {
+                    ellipse(
+                        &mut operations,
+                        (rect.llx + rect.urx) / 2.0,
+                        (rect.lly + rect.ury) / 2.0,
+                        (rect.width() / 2.0 - inset).max(0.0),
+                        (rect.height() / 2.0 - inset).max(0.0),
+                    );
+                } else {
+                    operations.push(Operation::new(
+                        "re",
+                        vec![
+                            (rect.llx + inset).into(),
+                            (rect.lly + inset).into(),
+                            (rect.width() - width).max(0.0).into(),
+                            (rect.height() - width).max(0.0).into(),
+                        ],
+                    ));
+                }
+                operations.push(Operation::new(painting, vec![]));
+            }
+            AnnotationSubtype::Line => {
+                let line = numbers(b"L");
+                let stroke = match annotation.color.map(|color| color.stroke_operation()) {
+                    Some(stroke) if line.len() == 4 && width > 0.0 => stroke,
+                    _ => return Ok(None),
+                };
+                operations.push(stroke);
+                operations.push(Operation::new("w", vec![width.into()]));
+                operations.push(Operation::new("m", vec![line[0].into(), line[1].into()]));
+                operations.push(Operation::new("l", vec![line[2].into(), line[3].into()]));
+                operations.push(Operation::new("S", vec![]));
+            }
+            _ => return Ok(None),
+        }
+        let form = dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Form",
+            "BBox" => rect,
+        };
+        Ok(Some(Stream::new(form, Content { operations }.encode()?)))
+    }
+}
+
+/// Draw text markup as a form XObject whose bounding box is the annotation rectangle.
+fn markup_appearance(kind: MarkupKind, quads: &[Quad], rect: Rect, options: &MarkupOptions) -> Result<Stream> {
+    let mut operations = vec![Operation::new("gs", vec!["GS0".into()])];
+    if kind == MarkupKind::Highlight {
+        operations.push(options.color.fill_operation());
+    } else {
+        operations.push(options.color.stroke_operation());
+    }
+    for quad in quads {
+        // Positions within the quad, along its baseline and towards its upper edge, both from 0 to 1.
+        let point = |along: f64, up: f64| {
+            let x = quad.lower_left.0
+                + along * (quad.lower_right.0 - quad.lower_left.0)
+                + up * (quad.upper_left.0 - quad.lower_left.0);
+            let y = quad.lower_left.1
+                + along * (quad.lower_right.1 - quad.lower_left.1)
+                + up * (quad.upper_left.1 - quad.lower_left.1);
+            vec![Object::Real(x), Object::Real(y)]
+        };
+        let distance = |(x1, y1): (f64, f64), (x2, y2): (f64, f64)| (x2 - x1).hypot(y2 - y1);
+        let height = distance(quad.lower_left, quad.upper_left);
+        let width = distance(quad.lower_left, quad.lower_right);
+        let line_width = Operation::new("w", vec![(height / 14.0).max(0.5).into()]);
+        match kind {
+            MarkupKind::Highlight => {
+                operations.push(Operation::new("m", point(0.0, 0.0)));
+                operations.push(Operation::new("l", point(1.0, 0.0)));
+                operations.push(Operation::new("l", point(1.0, 1.0)));
+                operations.push(Operation::new("l", point(0.0, 1.0)));
+                operations.push(Operation::new("h", vec![]));
+                operations.push(Operation::new("f", vec![]));
+            }
+            MarkupKind::Underline | MarkupKind::StrikeOut => {
+                let up = if kind == MarkupKind::Underline { 0.1 } else { 0.45 };
+                operations.push(line_width);
+                operations.push(Operation::new("m", point(0.0, up)));
+                operations.push(Operation::new("l", point(1.0, up)));
+                operations.push(Operation::new("S", vec![]));
+            }
+            MarkupKind::Squiggly => {
+                // Zigzag with a period of a third of the height, within the lower part of the quad.
+                let segments = if height > 0.0 {
+                    ((width / (height / 6.0)).ceil() as usize).max(1)
+                } else {
+                    1
+                };
+                operations.push(line_width);
+                operations.push(Operation::new("m", point(0.0, 0.05)));
+                for i in 1..=segments {
+                    let up = if i % 2 == 1 { 0.15 } else { 0.05 };
+                    operations.push(Operation::new("l", point(i as f64 / segments as f64, up)));
+                }
+                operations.push(Operation::new("S", vec![]));
+            }
+        }
+    }
+
+    let mut graphics_state = dictionary! {
+        "CA" => options.opacity,
+        "ca" => options.opacity,
+    };
+    let mut form = dictionary! {
+        "Type" => "XObject",
+        "Subtype" => "Form",
+        "BBox" => rect,
+    };
+    if kind == MarkupKind::Highlight {
+        // Multiply keeps the text underneath readable instead of painting over it.
+        graphics_state.set("BM", "Multiply");
+        form.set("Group", dictionary! { "S" => "Transparency" });
+    }
+    form.set(
+        "Resources",
+        dictionary! { "ExtGState" => dictionary! { "GS0" => graphics_state } },
+    );
+    Ok(Stream::new(form, Content { operations }.encode()?))
+}
+
+/// The operands and operator setting a color in a default appearance string, like `0 0 1 rg`.
+fn color_setting(color: &Color, stroke: bool) -> String {
+    let mut setting = String::new();
+    for component in color.clamped().components() {
+        setting.push_str(&format!("{} ", component));
+    }
+    setting.push_str(color.operator(stroke));
+    setting
+}
+
+/// Length of the sides of line endings drawn with lines of the given width.
+fn line_ending_size(width: f64) -> f64 {
+    6.0 + 3.0 * width
+}
+
+/// Draw the box, callout line and text of a free text annotation, in default user space.
+fn free_text_operations<F: TextFont>(font: &F, rect: Rect, text: &str, options: &FreeTextOptions) -> Vec<Operation> {
+    let width = options.border_width.max(0.0);
+    let stroke = options.border_color.filter(|_| width > 0.0);
+    let mut operations = vec![Operation::new("q", vec![])];
+    if let Some(fill) = options.fill_color {
+        operations.push(fill.fill_operation());
+        operations.push(Operation::new(
+            "re",
+            vec![
+                rect.llx.into(),
+                rect.lly.into(),
+                rect.width().into(),
+                rect.height().into(),
+            ],
+        ));
+        operations.push(Operation::new("f", vec![]));
+    }
+    if let Some(color) = stroke {
+        operations.push(color.stroke_operation());
+        operations.push(Operation::new("w", vec![width.into()]));
+        let inset = width / 2.0;
+        operations.push(Operation::new(
+            "re",
+            vec![
+                (rect.llx + inset).into(),
+                (rect.lly + inset).into(),
+                (rect.width() - width).max(0.0).into(),
+                (rect.height() - width).max(0.0).into(),
+            ],
+        ));
+        operations.push(Operation::new("S", vec![]));
+        if let Some(callout) = &options.callout {
+            let mut points = vec![callout.start];
+            points.extend(callout.knee);
+            points.push(callout.end);
+            operations.push(Operation::new("m", vec![points[0].0.into(), points[0].1.into()]));
+            for &(x, y) in &points[1..] {
+                operations.push(Operation::new("l", vec![x.into(), y.into()]));
+            }
+            operations.push(Operation::new("S", vec![]));
+            line_ending(&mut operations, callout.ending, points[0], points[1], width);
+        }
+    }
+
+    // Text is clipped to the box inside the border, with some padding.
+    let inset = width + 2.0;
+    let inner_width = (rect.width() - 2.0 * inset).max(0.0);
+    operations.push(Operation::new(
+        "re",
+        vec![
+            (rect.llx + width).into(),
+            (rect.lly + width).into(),
+            (rect.width() - 2.0 * width).max(0.0).into(),
+            (rect.height() - 2.0 * width).max(0.0).into(),
+        ],
+    ));
+    operations.push(Operation::new("W", vec![]));
+    operations.push(Operation::new("n", vec![]));
+    let size = options.font_size;
+    operations.push(Operation::new("BT", vec![]));
+    operations.push(Operation::new("Tf", vec!["F1".into(), size.into()]));
+    operations.push(options.text_color.fill_operation());
+    let mut y = rect.ury - inset - size * font.ascent() / 1000.0;
+    for line in wrap_text(font, text, if size > 0.0 { inner_width / size } else { 0.0 }) {
+        let free = inner_width - font.width(&line) * size;
+        let x = rect.llx
+            + inset
+            + match options.quadding {
+                1 => free / 2.0,
+                2 => free,
+                _ => 0.0,
+            };
+        operations.push(Operation::new(
+            "Tm",
+            vec![1.into(), 0.into(), 0.into(), 1.into(), x.into(), y.into()],
+        ));
+        operations.push(Operation::new(
+            "Tj",
+            vec![Object::String(line, StringFormat::Hexadecimal)],
+        ));
+        y -= size * 1.15;
+    }
+    operations.push(Operation::new("ET", vec![]));
+    operations.push(Operation::new("Q", vec![]));
+    operations
+}
+
+/// Draw a line ending at `point`, for a line coming from `from`.
+fn line_ending(operations: &mut Vec<Operation>, ending: LineEnding, point: (f64, f64), from: (f64, f64), width: f64) {
+    let length = (from.0 - point.0).hypot(from.1 - point.1);
+    if ending == LineEnding::None || length == 0.0 {
+        return;
+    }
+    let size = line_ending_size(width);
+    let (dx, dy) = ((from.0 - point.0) / length, (from.1 - point.1) / length);
+    // Points at the given angle from the line, in radians, and the given distance from its end.
+    let side = |angle: f64, distance: f64| {
+        let (sin, cos) = angle.sin_cos();
+        vec![
+            Object::Real(point.0 + distance * (dx * cos - dy * sin)),
+            Object::Real(point.1 + distance * (dx * sin + dy * cos)),
+        ]
+    };
+    let tip = vec![Object::Real(point.0), Object::Real(point.1)];
+    match ending {
+        LineEnding::OpenArrow | LineEnding::ClosedArrow => {
+            let degrees = std::f64::consts::PI / 6.0;
+            operations.push(Operation::new("m", side(degrees, size)));
+            operations.push(Operation::new("l", tip));
+            operations.push(Operation::new("l", side(-degrees, size)));
+            operations.push(Operation::new(
+                if ending == LineEnding::ClosedArrow { "s" } else { "S" },
+                vec![],
+            ));
+        }
+        LineEnding::Butt => {
+            let right_angle = std::f64::consts::FRAC_PI_2;
+            operations.push(Operation::new("m", side(right_angle, size / 2.0)));
+            operations.push(Operation::new("l", side(-right_angle, size / 2.0)));
+            operations.push(Operation::new("S", vec![]));
+        }
+        LineEnding::None => {}
+    }
+}
+
+/// URI actions hold 7-bit ASCII, so percent-encode other bytes of the UTF-8 form, as well as spaces.
+fn percent_encode_uri(uri: &str) -> String {
+    let mut encoded = String::with_capacity(uri.len());
+    for &byte in uri.as_bytes() {
+        if byte.is_ascii_graphic() {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::creator::tests::create_document;
+    use crate::Stream;
+
+    #[test]
+    fn read_typed_annotations() {
+        let mut doc = create_document();
+        let page_id = doc.page_iter().next().unwrap();
+        let normal_id = doc.add_object(Stream::new(dictionary! {}, Vec::new()));
+        let on_id = doc.add_object(Stream::new(dictionary! {}, Vec::new()));
+        let highlight_id = doc.add_object(dictionary! {
+            "Type" => "Annot",
+            "Subtype" => "Highlight",
+            "Rect" => vec![100.into(), 700.into(), 10.into(), 720.into()],
+            "Contents" => Object::String(b"\xFE\xFF\x00H\x00i\x4E\x2D".to_vec(), StringFormat::Hexadecimal),
+            "F" => 4 | 128,
+            "M" => Object::string_literal("D:20240102030405+01'00'"),
+            "T" => Object::string_literal(&b"Caf\xE9"[..]),
+            "QuadPoints" => vec![10, 720, 100, 720, 10, 700, 100, 700].into_iter().map(Object::from).collect::<Vec<_>>(),
+            "AS" => "On",
+            "AP" => dictionary! {
+                "N" => normal_id,
+                "D" => dictionary! { "On" => on_id, "Off" => Object::Null },
+            },
+        });
+        let reply_id = doc.add_object(dictionary! {
+            "Subtype" => "Text",
+            "IRT" => highlight_id,
+        });
+        let annots_id = doc.add_object(vec![
+            highlight_id.into(),
+            Object::Integer(3),
+            Object::Reference((999, 0)),
+            reply_id.into(),
+            dictionary! { "Subtype" => "Link", "T" => Object::string_literal("ignored") }.into(),
+        ]);
+        doc.get_object_mut(page_id)
+            .and_then(Object::as_dict_mut)
+            .unwrap()
+            .set("Annots", annots_id);
+
+        let annotations = doc.annotations(page_id);
+        assert_eq!(annotations.len(), 3);
+        let highlight = &annotations[0];
+        assert_eq!(highlight.id, Some(highlight_id));
+        assert_eq!(highlight.subtype, AnnotationSubtype::Highlight);
+        assert_eq!(highlight.rect, Some(Rect::new(10.0, 700.0, 100.0, 720.0)));
+        assert_eq!(highlight.contents.as_deref(), Some("Hi中"));
+        assert_eq!(highlight.flags, AnnotationFlags::PRINT | AnnotationFlags::LOCKED);
+        assert_eq!(highlight.author.as_deref(), Some("Café"));
+        assert_eq!(
+            highlight.quad_points,
+            vec![Quad::from(Rect::new(10.0, 700.0, 100.0, 720.0))]
+        );
+        assert_eq!(highlight.normal_appearance_stream(), Some(normal_id));
+        assert_eq!(highlight.rollover_appearance, None);
+        assert_eq!(
+            highlight.down_appearance.as_ref().unwrap().stream(Some(b"On")),
+            Some(on_id)
+        );
+        assert!(highlight.modified_date().is_some());
+
+        assert_eq!(annotations[1].in_reply_to, Some(highlight_id));
+        assert_eq!(annotations[1].flags, AnnotationFlags::empty());
+        assert_eq!(annotations[2].id, None);
+        assert_eq!(annotations[2].author, None);
+    }
+
+    #[test]
+    fn add_uri_and_page_links() {
+        let mut doc = create_document();
+        let page_id = doc.page_iter().next().unwrap();
+        let rect = Rect::new(10.0, 10.0, 110.0, 30.0);
+        let uri = LinkTarget::Uri("https://example.com/grüße ok".to_string());
+        let uri_id = doc.add_link(page_id, rect, uri).unwrap();
+        let fit = DestinationFit::FitH { top: Some(700.0) };
+        let page_link_id = doc.add_link(page_id, rect, LinkTarget::Page { page: 1, fit }).unwrap();
+
+        let page = doc.get_dictionary(page_id).unwrap();
+        let annots = page.get(b"Annots").and_then(Object::as_array).unwrap();
+        assert_eq!(annots.len(), 2);
+        assert_eq!(annots[1].as_reference().unwrap(), page_link_id);
+
+        let link = doc.get_dictionary(uri_id).unwrap();
+        let action = link.get(b"A").and_then(Object::as_dict).unwrap();
+        assert_eq!(
+            action.get(b"URI").and_then(Object::as_str).unwrap(),
+            b"https://example.com/gr%C3%BC%C3%9Fe%20ok"
+        );
+        let dest = doc
+            .get_dictionary(page_link_id)
+            .unwrap()
+            .get(b"Dest")
+            .and_then(Object::as_array)
+            .unwrap();
+        assert_eq!(dest[0].as_reference().unwrap(), page_id);
+        assert_eq!(dest[1].as_name_str().unwrap(), "FitH");
+
+        let target = LinkTarget::Page {
+            page: 5,
+            fit: DestinationFit::Fit,
+        };
+        assert!(doc.add_link(page_id, rect, target).is_err());
+    }
+
+    #[test]
+    fn remove_links_and_popups() {
+        let mut doc = crate::forms::tests::form_document();
+        let page_id = doc.page_iter().next().unwrap();
+        let rect = Rect::new(10.0, 10.0, 110.0, 30.0);
+        let link_id = doc
+            .add_link(page_id, rect, LinkTarget::Uri("https://example.com".to_string()))
+            .unwrap();
+        let note_id = doc
+            .add_annotation(page_id, dictionary! { "Subtype" => "Text" })
+            .unwrap();
+        let popup_id = doc
+            .add_annotation(page_id, dictionary! { "Subtype" => "Popup", "Parent" => note_id })
+            .unwrap();
+        doc.get_object_mut(note_id)
+            .and_then(Object::as_dict_mut)
+            .unwrap()
+            .set("Popup", popup_id);
+        // Move the annotations behind a reference, and wrongly list the link on a second page as well.
+        let annots = doc.get_dictionary(page_id).unwrap().get(b"Annots").unwrap().clone();
+        let annots_id = doc.add_object(annots);
+        let page = doc.get_object_mut(page_id).and_then(Object::as_dict_mut).unwrap();
+        page.set("Annots", annots_id);
+        let second_id = doc.add_object(dictionary! { "Type" => "Page", "Annots" => vec![link_id.into()] });
+        let pages_id = doc
+            .get_dictionary(page_id)
+            .unwrap()
+            .get(b"Parent")
+            .and_then(Object::as_reference)
+            .unwrap();
+        let pages = doc.get_object_mut(pages_id).and_then(Object::as_dict_mut).unwrap();
+        pages
+            .get_mut(b"Kids")
+            .and_then(Object::as_array_mut)
+            .unwrap()
+            .push(second_id.into());
+        pages.set("Count", 2);
+
+        let is_link = |annotation: &Annotation| annotation.subtype == AnnotationSubtype::Link;
+        assert_eq!(doc.remove_annotations(Some(page_id), is_link), 1);
+        assert!(doc.annotations(second_id).is_empty());
+        assert_eq!(
+            doc.remove_annotations(None, |annotation| annotation.id == Some(note_id)),
+            2
+        );
+        assert_eq!(doc.annotations(page_id).len(), 6);
+
+        // Removing the widgets of a radio group removes the group from the form.
+        let size = doc.form_fields().remove(3);
+        let widgets: Vec<ObjectId> = size.widgets.iter().map(|widget| widget.id).collect();
+        let removed = doc.remove_annotations(None, |annotation| annotation.id.is_some_and(|id| widgets.contains(&id)));
+        assert_eq!(removed, 2);
+        let names: Vec<String> = doc.form_fields().into_iter().map(|field| field.name).collect();
+        assert_eq!(names, vec!["name", "address", "agree", "color"]);
+        assert_eq!(doc.annotations(page_id).len(), 4);
+    }
+
+    #[test]
+    fn add_highlight_over_text() {
+        let mut doc = create_document();
+        let page_id = doc.page_iter().next().unwrap();
+        // "World" in "Hello World!", shown at (100, 600) in 48 point Courier with glyphs 28.8 points wide.
+        let quad = Quad::from(Rect::new(272.8, 590.0, 416.8, 640.0));
+        let options = MarkupOptions {
+            author: Some("Reviewer".to_string()),
+            contents: Some("Check this".to_string()),
+            opacity: 0.5,
+            ..Default::default()
+        };
+        let id = doc
+            .add_markup(page_id, MarkupKind::Highlight, &[quad], &options)
+            .unwrap();
+        let underline = Quad::from(Rect::new(100.0, 590.0, 200.0, 640.0));
+        doc.add_markup(page_id, MarkupKind::Squiggly, &[underline, quad], &Default::default())
+            .unwrap();
+        assert!(doc.add_markup(page_id, MarkupKind::Underline, &[], &options).is_err());
+
+        let annotations = doc.annotations(page_id);
+        assert_eq!(annotations.len(), 2);
+        let highlight = &annotations[0];
+        assert_eq!(highlight.id, Some(id));
+        assert_eq!(highlight.subtype, AnnotationSubtype::Highlight);
+        assert_eq!(highlight.quad_points, vec![quad]);
+        assert_eq!(highlight.author.as_deref(), Some("Reviewer"));
+        let points = doc.get_dictionary(id).unwrap().get(b"QuadPoints").unwrap();
+        let points: Vec<f64> = points
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p.as_float().unwrap())
+            .collect();
+        assert_eq!(points, vec![272.8, 640.0, 416.8, 640.0, 272.8, 590.0, 416.8, 590.0]);
+
+        for annotation in &annotations {
+            let appearance = doc.get_object(annotation.normal_appearance_stream().unwrap());
+            let appearance = appearance.and_then(Object::as_stream).unwrap();
+            let bbox = Rect::from_object(appearance.dict.get(b"BBox").unwrap()).unwrap();
+            assert_eq!(Some(bbox), annotation.rect);
+        }
+        assert_eq!(annotations[1].rect, Some(Rect::new(100.0, 590.0, 416.8, 640.0)));
+        let appearance = doc.get_object(highlight.normal_appearance_stream().unwrap()).unwrap();
+        let resources = appearance
+            .as_stream()
+            .unwrap()
+            .dict
+            .get(b"Resources")
+            .and_then(Object::as_dict)
+            .unwrap();
+        let graphics_state = resources.get(b"ExtGState").and_then(Object::as_dict).unwrap();
+        let graphics_state = graphics_state.get(b"GS0").and_then(Object::as_dict).unwrap();
+        assert_eq!(
+            graphics_state.get(b"BM").and_then(Object::as_name_str).unwrap(),
+            "Multiply"
+        );
+    }
+
+    #[test]
+    fn flatten_markup_and_shapes() {
+        let mut doc = create_document();
+        let page_id = doc.page_iter().next().unwrap();
+        let quad = Quad::from(Rect::new(272.8, 590.0, 416.8, 640.0));
+        doc.add_markup(page_id, MarkupKind::Highlight, &[quad], &Default::default())
+            .unwrap();
+        let print = AnnotationFlags::PRINT.bits() as i64;
+        let square_id = doc
+            .add_annotation(
+                page_id,
+                dictionary! {
+                    "Subtype" => "Square",
+                    "Rect" => Rect::new(50.0, 50.0, 150.0, 100.0),
+                    "C" => vec![1.into(), 0.into(), 0.into()],
+                    "IC" => vec![0.9.into()],
+                    "BS" => dictionary! { "W" => 2 },
+                    "F" => print,
+                },
+            )
+            .unwrap();
+        let popup_id = doc
+            .add_annotation(
+                page_id,
+                dictionary! { "Subtype" => "Popup", "Rect" => Rect::new(0.0, 0.0, 1.0, 1.0), "Parent" => square_id },
+            )
+            .unwrap();
+        doc.get_object_mut(square_id)
+            .and_then(Object::as_dict_mut)
+            .unwrap()
+            .set("Popup", popup_id);
+        let screen_only = dictionary! {
+            "Subtype" => "Line",
+            "Rect" => Rect::new(0.0, 0.0, 100.0, 100.0),
+            "L" => vec![0.into(), 0.into(), 100.into(), 100.into()],
+            "C" => vec![0.into()],
+        };
+        doc.add_annotation(page_id, screen_only).unwrap();
+        let note_id = doc
+            .add_annotation(
+                page_id,
+                dictionary! { "Subtype" => "Text", "Rect" => Rect::new(0.0, 0.0, 20.0, 20.0), "F" => print },
+            )
+            .unwrap();
+
+        let report = doc
+            .flatten_annotations(page_id, |_| true, &AnnotationFlattenOptions::default())
+            .unwrap();
+        assert_eq!(report.flattened, 2);
+        assert_eq!(report.hidden, 1);
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.skipped[0].id, Some(note_id));
+        let remaining: Vec<Option<ObjectId>> = doc.annotations(page_id).into_iter().map(|a| a.id).collect();
+        assert_eq!(remaining, vec![Some(note_id)]);
+
+        let page = doc.get_dictionary(page_id).unwrap();
+        let contents = page.get(b"Contents").and_then(Object::as_array).unwrap();
+        let flattened = doc
+            .get_object(contents[contents.len() - 1].as_reference().unwrap())
+            .and_then(Object::as_stream)
+            .unwrap();
+        let flattened = String::from_utf8_lossy(&flattened.content);
+        assert_eq!(flattened.matches(" Do").count(), 2);
+        let xobjects = page
+            .get_deref(b"Resources", &doc)
+            .and_then(Object::as_dict)
+            .and_then(|resources| resources.get(b"XObject"))
+            .and_then(Object::as_dict)
+            .unwrap();
+        let square = xobjects
+            .iter()
+            .map(|(_, form)| {
+                doc.get_object(form.as_reference().unwrap())
+                    .and_then(Object::as_stream)
+                    .unwrap()
+            })
+            .find(|form| form.content.ends_with(b"B\n"))
+            .unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&square.content),
+            "1 0 0 RG\n0.9 g\n2 w\n51 51 98 48 re\nB\n"
+        );
+    }
+
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    #[test]
+    fn add_free_text_with_appearance() {
+        let mut doc = create_document();
+        let page_id = doc.page_iter().next().unwrap();
+        let rect = Rect::new(100.0, 600.0, 160.0, 660.0);
+        let options = FreeTextOptions {
+            fill_color: Some(Color::Rgb(1.0, 1.0, 0.8)),
+            quadding: 1,
+            callout: Some(Callout {
+                start: (50.0, 500.0),
+                knee: Some((80.0, 620.0)),
+                end: (100.0, 620.0),
+                ending: LineEnding::OpenArrow,
+            }),
+            ..FreeTextOptions::default()
+        };
+        let id = doc.add_free_text(page_id, rect, "Hello world", &options).unwrap();
+        let annotation = doc.get_dictionary(id).unwrap();
+        assert_eq!(
+            annotation.get(b"Subtype").and_then(Object::as_name).unwrap(),
+            b"FreeText"
+        );
+        assert_eq!(
+            annotation.get(b"DA").and_then(Object::as_str).unwrap(),
+            b"/F1 12 Tf 0 0 0 rg 0 0 0 RG"
+        );
+        assert_eq!(
+            Annotation::from_dict(&doc, Some(id), annotation).color,
+            Some(Color::Rgb(1.0, 1.0, 0.8))
+        );
+        assert_eq!(annotation.get(b"CL").and_then(Object::as_array).unwrap().len(), 6);
+        assert_eq!(annotation.get(b"LE").and_then(Object::as_name).unwrap(), b"OpenArrow");
+        let bounds = Rect::from_object(annotation.get(b"Rect").unwrap()).unwrap();
+        assert_eq!(bounds, Rect::new(41.0, 491.0, 160.0, 660.0));
+        let appearance = annotation.get(b"AP").and_then(Object::as_dict).unwrap();
+        let form = doc
+            .get_object(appearance.get(b"N").unwrap().as_reference().unwrap())
+            .and_then(Object::as_stream)
+            .unwrap();
+        let font = form.dict.get(b"Resources").and_then(Object::as_dict).unwrap();
+        let font_id = font.get(b"Font").and_then(Object::as_dict).unwrap().get(b"F1").unwrap();
+        let font = doc.get_dictionary(font_id.as_reference().unwrap()).unwrap();
+        assert_eq!(font.get(b"BaseFont").and_then(Object::as_name).unwrap(), b"Helvetica");
+        // The text does not fit on one line of the 54 point wide text area.
+        let content = Content::decode(&form.content).unwrap();
+        let lines: Vec<&[u8]> = content
+            .operations
+            .iter()
+            .filter(|operation| operation.operator == "Tj")
+            .map(|operation| operation.operands[0].as_str().unwrap())
+            .collect();
+        assert_eq!(lines, vec![&b"Hello"[..], b"world"]);
+
+        let options = FreeTextOptions {
+            font: FreeTextFont::TrueType(crate::truetype::tests::test_font()),
+            text_color: Color::Cmyk(0.0, 1.0, 1.0, 0.2),
+            border_color: None,
+            ..FreeTextOptions::default()
+        };
+        let id = doc.add_free_text(page_id, rect, "ЖA", &options).unwrap();
+        let default_appearance = doc.get_dictionary(id).unwrap().get(b"DA").and_then(Object::as_str).unwrap();
+        assert_eq!(default_appearance, b"/F1 12 Tf 0 1 1 0.2 k");
+        let annotation = Annotation::from_dict(&doc, Some(id), doc.get_dictionary(id).unwrap());
+        assert_eq!(annotation.rect, Some(rect));
+        assert_eq!(annotation.contents.as_deref(), Some("ЖA"));
+        let form_id = annotation.normal_appearance_stream().unwrap();
+        let form = doc.get_object(form_id).and_then(Object::as_stream).unwrap();
+        let content = Content::decode(&form.content).unwrap();
+        let text = content
+            .operations
+            .iter()
+            .find(|operation| operation.operator == "Tj")
+            .unwrap();
+        assert_eq!(text.operands[0].as_str().unwrap(), [0, 2, 0, 1]);
+    }
+
+    #[test]
+    fn set_tab_order_from_visual_order() {
+        let mut doc = create_document();
+        let page_id = doc.page_iter().next().unwrap();
+        let mut add = |llx: f64, lly: f64| {
+            let field = dictionary! { "Subtype" => "Widget", "Rect" => Rect::new(llx, lly, llx + 100.0, lly + 20.0) };
+            doc.add_annotation(page_id, field).unwrap()
+        };
+        let bottom = add(50.0, 100.0);
+        let top_right = add(200.0, 705.0);
+        let top_left = add(50.0, 700.0);
+        let middle = add(50.0, 400.0);
+        let popup = doc
+            .add_annotation(page_id, dictionary! { "Subtype" => "Popup" })
+            .unwrap();
+
+        let order = doc.annots_in_visual_order(page_id);
+        assert_eq!(order, vec![top_left, top_right, middle, bottom, popup]);
+        doc.set_tab_order(page_id, TabOrder::Manual(order[..3].to_vec()))
+            .unwrap();
+        let ids: Vec<ObjectId> = doc
+            .annotations(page_id)
+            .iter()
+            .filter_map(|annotation| annotation.id)
+            .collect();
+        assert_eq!(ids, vec![top_left, top_right, middle, bottom, popup]);
+        let page = doc.get_dictionary(page_id).unwrap();
+        assert_eq!(page.get(b"Tabs").and_then(Object::as_name).unwrap(), b"A");
+
+        let unknown = TabOrder::Manual(vec![(9999, 0)]);
+        assert!(matches!(
+            doc.set_tab_order(page_id, unknown),
+            Err(Error::ObjectNotFound { id: Some((9999, 0)), .. })
+        ));
+        doc.set_tab_order(page_id, TabOrder::Column).unwrap();
+        let page = doc.get_dictionary(page_id).unwrap();
+        assert_eq!(page.get(b"Tabs").and_then(Object::as_name).unwrap(), b"C");
+    }
+
+    #[test]
+    fn regenerate_appearances_for_new_rectangles() {
+        let mut doc = crate::forms::tests::form_document();
+        let page_id = doc.page_iter().next().unwrap();
+        let bordered = |subtype: &str| {
+            dictionary! {
+                "Subtype" => subtype,
+                "Rect" => Rect::new(300.0, 300.0, 400.0, 350.0),
+                "C" => vec![1.into(), 0.into(), 0.into()],
+            }
+        };
+        for subtype in &["Square", "Circle", "Highlight"] {
+            doc.add_annotation(page_id, bordered(subtype)).unwrap();
+        }
+        let rect = Rect::new(300.0, 400.0, 400.0, 450.0);
+        doc.add_free_text(page_id, rect, "Hello world", &FreeTextOptions::default())
+            .unwrap();
+        doc.add_annotation(page_id, dictionary! { "Subtype" => "Text", "Rect" => rect })
+            .unwrap();
+
+        // The rectangles turn a quarter, as when a page is rotated.
+        for annotation in doc.annotations(page_id) {
+            let old = annotation.rect.unwrap();
+            let rect = Rect::new(old.llx, old.lly, old.llx + old.height(), old.lly + old.width());
+            doc.get_object_mut(annotation.id.unwrap())
+                .and_then(Object::as_dict_mut)
+                .unwrap()
+                .set("Rect", rect);
+        }
+        assert_eq!(doc.regenerate_annotation_appearances(page_id, |_| true).unwrap(), 10);
+
+        let aspect = |rect: Rect| rect.width() / rect.height();
+        for annotation in doc.annotations(page_id) {
+            let streams = match annotation.normal_appearance {
+                Some(Appearance::Stream(id)) => vec![id],
+                Some(Appearance::States(ref states)) => states.iter().map(|&(_, id)| id).collect(),
+                None => {
+                    assert_eq!(annotation.subtype, AnnotationSubtype::Text);
+                    continue;
+                }
+            };
+            for id in streams {
+                let form = doc.get_object(id).and_then(Object::as_stream).unwrap();
+                let bbox = Rect::from_object(form.dict.get(b"BBox").unwrap()).unwrap();
+                assert!((aspect(bbox) - aspect(annotation.rect.unwrap())).abs() < 1e-9);
+            }
+        }
+        // Buttons keep both of their states.
+        let agree = doc.annotations(page_id)[2].clone();
+        match agree.normal_appearance {
+            Some(Appearance::States(states)) => {
+                let names: Vec<&[u8]> = states.iter().map(|(name, _)| name.as_slice()).collect();
+                assert_eq!(names, [&b"Yes"[..], b"Off"]);
+            }
+            _ => panic!("the checkbox has no appearance states"),
+        }
+    }
+}
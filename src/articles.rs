@@ -0,0 +1,328 @@
+use std::collections::BTreeSet;
+
+use crate::encodings::decode_text_string;
+use crate::geometry::Rect;
+use crate::{Document, Object, ObjectId};
+
+/// An article thread of the catalog `/Threads`, from [`Document::article_threads`].
+#[derive(Debug, Clone)]
+pub struct ArticleThread {
+    /// The thread dictionary.
+    pub id: ObjectId,
+    /// `/Title` of the thread information dictionary `/I`.
+    pub title: Option<String>,
+    /// The beads in reading order, from the first one, `/F`.
+    pub beads: Vec<ArticleBead>,
+}
+
+/// A bead of an article thread: the area of a page that a part of the article flows through.
+#[derive(Debug, Clone)]
+pub struct ArticleBead {
+    pub id: ObjectId,
+    /// `/P`, the page of the bead.
+    pub page: Option<ObjectId>,
+    /// `/R`, the area on the page.
+    pub rect: Option<Rect>,
+}
+
+/// How a page is shown in a presentation, from [`Document::page_transition`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageTransition {
+    /// `/S` of `/Trans`, such as `Split`, `Wipe` or `Dissolve`; `R`, a plain replacement, when it is missing.
+    pub style: String,
+    /// `/D` of `/Trans`, how long the transition takes in seconds, 1 by default.
+    pub duration: f64,
+    /// `/Dur` of the page, how long it is shown in seconds before the viewer advances to the next page.
+    pub display_duration: Option<f64>,
+}
+
+/// What [`Document::strip_presentation_features`] removed.
+#[derive(Debug, Clone, Default)]
+pub struct PresentationReport {
+    /// Pages that had a `/Trans` or a `/Dur`.
+    pub pages: Vec<ObjectId>,
+    pub threads: usize,
+    pub beads: usize,
+}
+
+impl Document {
+    /// The article threads of the document, each with its beads in reading order.
+    pub fn article_threads(&self) -> Vec<ArticleThread> {
+        self.thread_ids()
+            .into_iter()
+            .filter_map(|id| {
+                let thread = self.get_dictionary(id).ok()?;
+                let title = thread
+                    .get_deref(b"I", self)
+                    .and_then(Object::as_dict)
+                    .and_then(|info| info.get_deref(b"Title", self))
+                    .and_then(Object::as_str)
+                    .ok()
+                    .map(decode_text_string);
+                let beads = self
+                    .thread_beads(id)
+                    .into_iter()
+                    .map(|bead_id| {
+                        let bead = self.get_dictionary(bead_id).ok();
+                        ArticleBead {
+                            id: bead_id,
+                            page: bead.and_then(|bead| bead.get(b"P").and_then(Object::as_reference).ok()),
+                            rect: bead.and_then(|bead| bead.get_deref(b"R", self).and_then(Rect::from_object).ok()),
+                        }
+                    })
+                    .collect();
+                Some(ArticleThread { id, title, beads })
+            })
+            .collect()
+    }
+
+    /// The transition and display duration of a page, if it has either.
+    pub fn page_transition(&self, page_id: ObjectId) -> Option<PageTransition> {
+        let page = self.get_dictionary(page_id).ok()?;
+        let transition = page.get_deref(b"Trans", self).and_then(Object::as_dict).ok();
+        let display_duration = page.get_deref(b"Dur", self).and_then(Object::as_float).ok();
+        if transition.is_none() && display_duration.is_none() {
+            return None;
+        }
+        let style = transition
+            .and_then(|transition| transition.get(b"S").and_then(Object::as_name_str).ok())
+            .unwrap_or("R");
+        let duration = transition
+            .and_then(|transition| transition.get(b"D").and_then(Object::as_float).ok())
+            .unwrap_or(1.0);
+        Some(PageTransition {
+            style: style.to_string(),
+            duration,
+            display_duration,
+        })
+    }
+
+    /// Remove the article threads with their beads, and the transitions and display durations of the pages, which
+    /// only presentation viewers use.
+    pub fn strip_presentation_features(&mut self) -> PresentationReport {
+        let mut report = PresentationReport::default();
+        for thread_id in self.thread_ids() {
+            for bead_id in self.thread_beads(thread_id) {
+                self.objects.remove(&bead_id);
+                report.beads += 1;
+            }
+            self.objects.remove(&thread_id);
+            report.threads += 1;
+        }
+        if self.catalog().is_ok_and(|catalog| catalog.has(b"Threads")) {
+            if let Ok(catalog) = self.catalog_mut() {
+                catalog.remove(b"Threads");
+            }
+        }
+
+        for page_id in self.page_iter().collect::<Vec<_>>() {
+            let (presentation, beads) = match self.get_dictionary(page_id) {
+                Ok(page) => (page.has(b"Trans") || page.has(b"Dur"), page.has(b"B")),
+                Err(_) => continue,
+            };
+            if !presentation && !beads {
+                continue;
+            }
+            if let Ok(page) = self.get_object_mut(page_id).and_then(Object::as_dict_mut) {
+                for key in [&b"Trans"[..], b"Dur", b"B"] {
+                    page.remove(key);
+                }
+            }
+            if presentation {
+                report.pages.push(page_id);
+            }
+        }
+        report
+    }
+
+    /// Take the beads on pages about to be deleted out of their threads, linking up the beads left, and remove the
+    /// threads that have none left.
+    pub(crate) fn unlink_page_beads(&mut self, pages: &BTreeSet<ObjectId>) {
+        let mut emptied = BTreeSet::new();
+        for thread_id in self.thread_ids() {
+            let on_deleted_page = |bead_id: &ObjectId| {
+                self.get_dictionary(*bead_id)
+                    .and_then(|bead| bead.get(b"P"))
+                    .and_then(Object::as_reference)
+                    .is_ok_and(|page_id| pages.contains(&page_id))
+            };
+            let (removed, kept): (Vec<ObjectId>, Vec<ObjectId>) =
+                self.thread_beads(thread_id).into_iter().partition(on_deleted_page);
+            if removed.is_empty() {
+                continue;
+            }
+            for bead_id in &removed {
+                self.objects.remove(bead_id);
+            }
+            if kept.is_empty() {
+                self.objects.remove(&thread_id);
+                emptied.insert(thread_id);
+                continue;
+            }
+
+            // The beads form a ring: the last is followed by the first, and only the first has to name the thread.
+            for (index, &bead_id) in kept.iter().enumerate() {
+                let next = kept[(index + 1) % kept.len()];
+                let previous = kept[(index + kept.len() - 1) % kept.len()];
+                if let Ok(bead) = self.get_object_mut(bead_id).and_then(Object::as_dict_mut) {
+                    bead.set("N", next);
+                    bead.set("V", previous);
+                    if index == 0 {
+                        bead.set("T", thread_id);
+                    }
+                }
+            }
+            if let Ok(thread) = self.get_object_mut(thread_id).and_then(Object::as_dict_mut) {
+                thread.set("F", kept[0]);
+            }
+        }
+
+        if emptied.is_empty() {
+            return;
+        }
+        let threads_id = self
+            .catalog()
+            .and_then(|catalog| catalog.get(b"Threads"))
+            .and_then(Object::as_reference);
+        let threads = match threads_id {
+            Ok(id) => self.get_object_mut(id),
+            Err(_) => self.catalog_mut().and_then(|catalog| catalog.get_mut(b"Threads")),
+        };
+        if let Ok(Object::Array(threads)) = threads {
+            threads.retain(|thread| !matches!(thread, Object::Reference(id) if emptied.contains(id)));
+        }
+    }
+
+    fn thread_ids(&self) -> Vec<ObjectId> {
+        self.catalog()
+            .and_then(|catalog| catalog.get_deref(b"Threads", self))
+            .and_then(Object::as_array)
+            .map(|threads| threads.iter().filter_map(|thread| thread.as_reference().ok()).collect())
+            .unwrap_or_default()
+    }
+
+    /// The beads of a thread, following `/N` from the first one until it comes back to a bead already seen, as the
+    /// last one links to the first.
+    fn thread_beads(&self, thread_id: ObjectId) -> Vec<ObjectId> {
+        let mut beads = Vec::new();
+        let mut seen = BTreeSet::new();
+        let mut next = self
+            .get_dictionary(thread_id)
+            .and_then(|thread| thread.get(b"F"))
+            .and_then(Object::as_reference)
+            .ok();
+        while let Some(bead_id) = next {
+            let bead = match self.get_dictionary(bead_id) {
+                Ok(bead) if seen.insert(bead_id) => bead,
+                _ => break,
+            };
+            beads.push(bead_id);
+            next = bead.get(b"N").and_then(Object::as_reference).ok();
+        }
+        beads
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DocumentBuilder, PageBuilder};
+
+    /// Three pages with an article thread of three beads over the first two, and one of a bead on the first page.
+    fn threaded_document() -> (Document, ObjectId, [ObjectId; 3]) {
+        let mut doc = DocumentBuilder::new()
+            .page(PageBuilder::a4().build())
+            .page(PageBuilder::a4().build())
+            .page(PageBuilder::a4().build())
+            .build()
+            .unwrap();
+        let pages: Vec<ObjectId> = doc.page_iter().collect();
+        let thread = doc.new_object_id();
+        let beads = [doc.new_object_id(), doc.new_object_id(), doc.new_object_id()];
+        let bead_pages = [pages[0], pages[1], pages[1]];
+        for index in 0..3 {
+            let mut bead = dictionary! {
+                "Type" => "Bead",
+                "N" => beads[(index + 1) % 3],
+                "V" => beads[(index + 2) % 3],
+                "P" => bead_pages[index],
+                "R" => Rect::new(72.0, 72.0, 300.0, 770.0),
+            };
+            if index == 0 {
+                bead.set("T", thread);
+            }
+            doc.objects.insert(beads[index], bead.into());
+        }
+        doc.objects.insert(
+            thread,
+            dictionary! {
+                "Type" => "Thread",
+                "F" => beads[0],
+                "I" => dictionary! { "Title" => Object::string_literal("Lead story") },
+            }
+            .into(),
+        );
+        let (short, short_bead) = (doc.new_object_id(), doc.new_object_id());
+        let bead = dictionary! { "T" => short, "N" => short_bead, "V" => short_bead, "P" => pages[0] };
+        doc.objects.insert(short_bead, bead.into());
+        doc.objects.insert(short, dictionary! { "F" => short_bead }.into());
+        doc.catalog_mut()
+            .unwrap()
+            .set("Threads", vec![thread.into(), short.into()]);
+
+        let page = doc.get_object_mut(pages[0]).and_then(Object::as_dict_mut).unwrap();
+        page.set("B", vec![beads[0].into(), short_bead.into()]);
+        let page = doc.get_object_mut(pages[1]).and_then(Object::as_dict_mut).unwrap();
+        page.set("B", vec![beads[1].into(), beads[2].into()]);
+        page.set("Trans", dictionary! { "S" => "Dissolve", "D" => 0.5 });
+        page.set("Dur", 3);
+        (doc, thread, beads)
+    }
+
+    #[test]
+    fn deleting_a_page_relinks_the_beads_left() {
+        let (mut doc, thread, beads) = threaded_document();
+        let threads = doc.article_threads();
+        assert_eq!(threads.len(), 2);
+        assert_eq!(threads[0].title.as_deref(), Some("Lead story"));
+        let ids: Vec<ObjectId> = threads[0].beads.iter().map(|bead| bead.id).collect();
+        assert_eq!(ids, beads);
+        let second_page = doc.page_iter().nth(1).unwrap();
+        assert_eq!(
+            doc.page_transition(second_page),
+            Some(PageTransition {
+                style: "Dissolve".to_string(),
+                duration: 0.5,
+                display_duration: Some(3.0),
+            })
+        );
+        assert!(doc.page_transition(doc.page_iter().next().unwrap()).is_none());
+
+        doc.delete_pages(&[1]);
+        let threads = doc.article_threads();
+        assert_eq!(threads.len(), 1);
+        let ids: Vec<ObjectId> = threads[0].beads.iter().map(|bead| bead.id).collect();
+        assert_eq!(ids, [beads[1], beads[2]]);
+        assert!(!doc.objects.contains_key(&beads[0]));
+        let first = doc.get_dictionary(beads[1]).unwrap();
+        assert_eq!(first.get(b"T").and_then(Object::as_reference).unwrap(), thread);
+        assert_eq!(first.get(b"V").and_then(Object::as_reference).unwrap(), beads[2]);
+        let last = doc.get_dictionary(beads[2]).unwrap();
+        assert_eq!(last.get(b"N").and_then(Object::as_reference).unwrap(), beads[1]);
+        assert_eq!(last.get(b"V").and_then(Object::as_reference).unwrap(), beads[1]);
+    }
+
+    #[test]
+    fn strip_threads_and_transitions() {
+        let (mut doc, _, beads) = threaded_document();
+        let second_page = doc.page_iter().nth(1).unwrap();
+        let report = doc.strip_presentation_features();
+        assert_eq!(report.pages, [second_page]);
+        assert_eq!((report.threads, report.beads), (2, 4));
+        assert!(doc.article_threads().is_empty());
+        assert!(!doc.catalog().unwrap().has(b"Threads"));
+        assert!(doc.page_transition(second_page).is_none());
+        assert!(!doc.get_dictionary(second_page).unwrap().has(b"B"));
+        assert!(beads.iter().all(|bead| !doc.objects.contains_key(bead)));
+    }
+}
@@ -0,0 +1,65 @@
+#![cfg(all(feature = "async", any(feature = "pom_parser", feature = "nom_parser")))]
+
+//! Loading with the IO of `tokio`, for services that must not block their tasks while a file is read. Parsing
+//! itself takes the CPU for as long as it does with the other loading methods, on the task that loads unless
+//! [`ParseOptions::blocking_parse`] moves it to the blocking threads of the runtime.
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::path::Path;
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::{Document, Error, ParseOptions, Phase, Result};
+
+impl Document {
+    /// Load a PDF document from a specified file path, reading it with `tokio`.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    #[inline]
+    pub async fn load_async<P: AsRef<Path>>(path: P) -> Result<Document> {
+        Self::load_async_with_options(path, ParseOptions::default()).await
+    }
+
+    /// Load a PDF document from a specified file path, reading it with `tokio`, with options.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub async fn load_async_with_options<P: AsRef<Path>>(path: P, options: ParseOptions) -> Result<Document> {
+        let buffer = tokio::fs::read(path)
+            .await
+            .map_err(|err| Error::from(err).in_phase(Phase::Read))?;
+        Self::parse_async(buffer, options).await
+    }
+
+    /// Load a PDF document from an asynchronous source.
+    #[inline]
+    pub async fn load_from_async<R: AsyncRead + Unpin>(source: R) -> Result<Document> {
+        Self::load_from_async_with_options(source, ParseOptions::default()).await
+    }
+
+    /// Load a PDF document from an asynchronous source, with options.
+    pub async fn load_from_async_with_options<R: AsyncRead + Unpin>(
+        mut source: R, options: ParseOptions,
+    ) -> Result<Document> {
+        let mut buffer = Vec::new();
+        source
+            .read_to_end(&mut buffer)
+            .await
+            .map_err(|err| Error::from(err).in_phase(Phase::Read))?;
+        Self::parse_async(buffer, options).await
+    }
+
+    /// Parse a buffer read, on the current task or on a blocking thread. The options are moved along, their
+    /// callbacks being `Send`.
+    async fn parse_async(buffer: Vec<u8>, options: ParseOptions) -> Result<Document> {
+        if !options.blocking_parse {
+            return Document::load_mem_with_options(&buffer, &options);
+        }
+        let parse = tokio::task::spawn_blocking(move || Document::load_mem_with_options(&buffer, &options));
+        match parse.await {
+            Ok(result) => result,
+            // A panic of the parser is the caller's, as it would be without the blocking thread.
+            Err(err) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+            Err(err) => {
+                Err(Error::from(std::io::Error::new(std::io::ErrorKind::Interrupted, err)).in_phase(Phase::Read))
+            }
+        }
+    }
+}
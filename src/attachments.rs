@@ -0,0 +1,328 @@
+use crate::annotations::AnnotationSubtype;
+use crate::digest::md5;
+use crate::encodings::{decode_text_string, encode_text_string};
+use crate::portfolio::{collection_item, CollectionValue};
+use crate::{Dictionary, Document, Object, ObjectId, Result, Stream, StringFormat};
+use log::warn;
+use std::collections::BTreeMap;
+
+/// Where an attachment is found in the document.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttachmentLocation {
+    /// An entry of the `/EmbeddedFiles` name tree, by key.
+    EmbeddedFiles(Vec<u8>),
+    /// A file attachment annotation of a page.
+    Annotation { page: ObjectId, annotation: ObjectId },
+}
+
+/// An embedded file, as listed by [`Document::attachments`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attachment {
+    pub location: AttachmentLocation,
+    /// The file specification, if it is an indirect object.
+    pub file_spec: Option<ObjectId>,
+    /// The embedded file stream.
+    pub stream: ObjectId,
+    /// The file name, from `/UF` or else `/F`.
+    pub filename: String,
+    pub description: Option<String>,
+    /// MIME type, from the `/Subtype` of the stream.
+    pub mime_type: Option<String>,
+    /// Uncompressed size in bytes, from the `/Params` of the stream.
+    pub size: Option<i64>,
+    pub created: Option<String>,
+    pub modified: Option<String>,
+    /// MD5 digest of the uncompressed data.
+    pub checksum: Option<Vec<u8>>,
+    /// Relationship of an associated file with the document, such as `Source` or `Data`.
+    pub relationship: Option<String>,
+    /// The collection item of a file of a portfolio, `/CI`, by field key.
+    pub collection_item: BTreeMap<String, CollectionValue>,
+}
+
+impl Attachment {
+    fn from_file_spec(doc: &Document, location: AttachmentLocation, file_spec: &Object) -> Option<Attachment> {
+        let (file_spec_id, spec) = doc.dereference(file_spec).ok()?;
+        let spec = spec.as_dict().ok()?;
+        let files = spec.get_deref(b"EF", doc).and_then(Object::as_dict).ok()?;
+        let stream_id = files
+            .get(b"UF")
+            .or_else(|_| files.get(b"F"))
+            .and_then(Object::as_reference)
+            .ok()?;
+        let stream = doc.get_object(stream_id).and_then(Object::as_stream).ok()?;
+        let text = |dict: &Dictionary, key: &[u8]| {
+            dict.get_deref(key, doc)
+                .and_then(Object::as_str)
+                .ok()
+                .map(decode_text_string)
+        };
+        let params = stream.dict.get_deref(b"Params", doc).and_then(Object::as_dict).ok();
+        let param = |key: &[u8]| params.and_then(|params| params.get_deref(key, doc).ok());
+        Some(Attachment {
+            location,
+            file_spec: file_spec_id,
+            stream: stream_id,
+            filename: text(spec, b"UF").or_else(|| text(spec, b"F")).unwrap_or_default(),
+            description: text(spec, b"Desc"),
+            mime_type: stream
+                .dict
+                .get(b"Subtype")
+                .and_then(Object::as_name)
+                .ok()
+                .map(|name| String::from_utf8_lossy(name).into_owned()),
+            size: param(b"Size").and_then(|size| size.as_i64().ok()),
+            created: param(b"CreationDate")
+                .and_then(|date| date.as_str().ok())
+                .map(|date| String::from_utf8_lossy(date).into_owned()),
+            modified: param(b"ModDate")
+                .and_then(|date| date.as_str().ok())
+                .map(|date| String::from_utf8_lossy(date).into_owned()),
+            checksum: param(b"CheckSum").and_then(|sum| sum.as_str().ok()).map(<[u8]>::to_vec),
+            relationship: spec
+                .get(b"AFRelationship")
+                .and_then(Object::as_name)
+                .ok()
+                .map(|name| String::from_utf8_lossy(name).into_owned()),
+            collection_item: collection_item(doc, spec),
+        })
+    }
+
+    /// The content of the file, decoded from the embedded file stream.
+    pub fn data(&self, doc: &Document) -> Result<Vec<u8>> {
+        let stream = doc.get_object(self.stream)?.as_stream()?;
+        if stream.filters().map_or(true, |filters| filters.is_empty()) {
+            Ok(stream.content.to_vec())
+        } else {
+            stream.decompressed_content()
+        }
+    }
+
+    /// The modification date of the file.
+    #[cfg(feature = "chrono_time")]
+    pub fn modified_date(&self) -> Option<chrono::DateTime<chrono::Local>> {
+        Object::string_literal(self.modified.as_ref()?.as_str()).as_datetime()
+    }
+
+    /// The modification date of the file.
+    #[cfg(not(feature = "chrono_time"))]
+    pub fn modified_date(&self) -> Option<time::OffsetDateTime> {
+        Object::string_literal(self.modified.as_ref()?.as_str()).as_datetime()
+    }
+}
+
+/// Options for [`Document::attach_file`].
+#[derive(Debug, Clone, Default)]
+pub struct AttachmentOptions {
+    pub description: Option<String>,
+    pub mime_type: Option<String>,
+    /// Add the MD5 digest of the data to the stream parameters.
+    pub checksum: bool,
+    /// Also list the file in the `/AF` associated files of the catalog with this relationship, such as `Source`,
+    /// `Data`, `Alternative`, `Supplement` or `Unspecified`, as PDF/A-3 requires for embedded files.
+    pub relationship: Option<String>,
+}
+
+impl Document {
+    /// The files embedded in the document, from the `/EmbeddedFiles` name tree and then from the file attachment
+    /// annotations of the pages. File specifications that do not embed their file are skipped.
+    pub fn attachments(&self) -> Vec<Attachment> {
+        let mut attachments: Vec<Attachment> = self
+            .names(b"EmbeddedFiles")
+            .into_iter()
+            .filter_map(|(key, file_spec)| {
+                let location = AttachmentLocation::EmbeddedFiles(key);
+                let attachment = Attachment::from_file_spec(self, location.clone(), &file_spec);
+                if attachment.is_none() {
+                    warn!("Skipping embedded file {:?} without data", location);
+                }
+                attachment
+            })
+            .collect();
+        for page in self.page_iter() {
+            for annotation in self.annotations(page) {
+                let annotation_id = match annotation.id {
+                    Some(id) if annotation.subtype == AnnotationSubtype::FileAttachment => id,
+                    _ => continue,
+                };
+                let file_spec = match self.get_dictionary(annotation_id).and_then(|annot| annot.get(b"FS")) {
+                    Ok(file_spec) => file_spec,
+                    Err(_) => continue,
+                };
+                let location = AttachmentLocation::Annotation {
+                    page,
+                    annotation: annotation_id,
+                };
+                attachments.extend(Attachment::from_file_spec(self, location, file_spec));
+            }
+        }
+        attachments
+    }
+
+    /// Embed a file under the given name in the `/EmbeddedFiles` name tree, replacing any file with the same name,
+    /// and return the id of its file specification.
+    pub fn attach_file(&mut self, name: &str, data: Vec<u8>, options: &AttachmentOptions) -> Result<ObjectId> {
+        let mut params = dictionary! { "Size" => data.len() as i64 };
+        if let Some(now) = self.clock.now() {
+            params.set("ModDate", now);
+        }
+        if options.checksum {
+            params.set(
+                "CheckSum",
+                Object::String(md5(&data).to_vec(), StringFormat::Hexadecimal),
+            );
+        }
+        let mut stream = Stream::new(
+            dictionary! {
+                "Type" => "EmbeddedFile",
+                "Params" => params,
+            },
+            data,
+        );
+        if let Some(mime_type) = &options.mime_type {
+            stream.dict.set("Subtype", Object::Name(mime_type.clone().into_bytes()));
+        }
+        stream.compress()?;
+        let stream_id = self.add_object(stream);
+
+        let filename = Object::String(encode_text_string(name), StringFormat::Literal);
+        let mut file_spec = dictionary! {
+            "Type" => "Filespec",
+            "F" => filename.clone(),
+            "UF" => filename.clone(),
+            "EF" => dictionary! { "F" => stream_id, "UF" => stream_id },
+        };
+        if let Some(description) = &options.description {
+            file_spec.set(
+                "Desc",
+                Object::String(encode_text_string(description), StringFormat::Literal),
+            );
+        }
+        if let Some(relationship) = &options.relationship {
+            file_spec.set("AFRelationship", Object::Name(relationship.clone().into_bytes()));
+        }
+        let file_spec_id = self.add_object(file_spec);
+
+        self.remove_attachment(name)?;
+        let mut entries = self.names(b"EmbeddedFiles");
+        entries.insert(encode_text_string(name), file_spec_id.into());
+        self.set_names("EmbeddedFiles", entries)?;
+
+        if options.relationship.is_some() {
+            let root_id = self.trailer.get(b"Root")?.as_reference()?;
+            let associated = match self.catalog()?.get(b"AF") {
+                Ok(Object::Reference(id)) => self.get_object_mut(*id)?.as_array_mut()?,
+                _ => {
+                    let catalog = self.get_object_mut(root_id)?.as_dict_mut()?;
+                    if catalog.get(b"AF").and_then(Object::as_array).is_err() {
+                        catalog.set("AF", Vec::<Object>::new());
+                    }
+                    catalog.get_mut(b"AF")?.as_array_mut()?
+                }
+            };
+            associated.push(file_spec_id.into());
+        }
+        Ok(file_spec_id)
+    }
+
+    /// Remove a file from the `/EmbeddedFiles` name tree, and from the `/AF` associated files of the catalog.
+    /// Returns `false` if there is no file with this name.
+    ///
+    /// The objects of the file are left for [`Document::prune_objects`].
+    pub fn remove_attachment(&mut self, name: &str) -> Result<bool> {
+        let mut entries = self.names(b"EmbeddedFiles");
+        let key = match entries.keys().find(|key| decode_text_string(key) == name) {
+            Some(key) => key.clone(),
+            None => return Ok(false),
+        };
+        let file_spec_id = entries.remove(&key).and_then(|file_spec| file_spec.as_reference().ok());
+        self.set_names("EmbeddedFiles", entries)?;
+
+        if let Some(file_spec_id) = file_spec_id {
+            let root_id = self.trailer.get(b"Root")?.as_reference()?;
+            let associated = match self.catalog()?.get(b"AF") {
+                Ok(Object::Reference(id)) => Some(self.get_object_mut(*id)?.as_array_mut()?),
+                Ok(Object::Array(_)) => Some(
+                    self.get_object_mut(root_id)?
+                        .as_dict_mut()?
+                        .get_mut(b"AF")?
+                        .as_array_mut()?,
+                ),
+                _ => None,
+            };
+            if let Some(associated) = associated {
+                associated.retain(|file_spec| file_spec.as_reference().ok() != Some(file_spec_id));
+                if associated.is_empty() {
+                    self.get_object_mut(root_id)?.as_dict_mut()?.remove(b"AF");
+                }
+            }
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::creator::tests::create_document;
+    use crate::geometry::Rect;
+
+    #[test]
+    fn attach_list_and_remove_files() {
+        let mut doc = create_document();
+        let data = b"id,value\n1,2\n".repeat(20);
+        let options = AttachmentOptions {
+            description: Some("Source data".to_string()),
+            mime_type: Some("text/csv".to_string()),
+            checksum: true,
+            relationship: Some("Source".to_string()),
+        };
+        let csv_id = doc.attach_file("données.csv", data.clone(), &options).unwrap();
+        doc.attach_file("notes.txt", b"Notes".to_vec(), &AttachmentOptions::default())
+            .unwrap();
+
+        let page_id = doc.page_iter().next().unwrap();
+        let note_spec = doc
+            .get_dictionary(doc.attachments()[1].file_spec.unwrap())
+            .unwrap()
+            .clone();
+        doc.add_annotation(
+            page_id,
+            dictionary! {
+                "Type" => "Annot",
+                "Subtype" => "FileAttachment",
+                "Rect" => Rect::new(0.0, 0.0, 10.0, 10.0),
+                "FS" => note_spec,
+            },
+        )
+        .unwrap();
+
+        let attachments = doc.attachments();
+        assert_eq!(attachments.len(), 3);
+        let csv = &attachments[0];
+        assert_eq!(
+            csv.location,
+            AttachmentLocation::EmbeddedFiles(encode_text_string("données.csv"))
+        );
+        assert_eq!(csv.filename, "données.csv");
+        assert_eq!(csv.description.as_deref(), Some("Source data"));
+        assert_eq!(csv.mime_type.as_deref(), Some("text/csv"));
+        assert_eq!(csv.size, Some(data.len() as i64));
+        assert_eq!(csv.checksum.as_deref(), Some(&md5(&data)[..]));
+        assert_eq!(csv.relationship.as_deref(), Some("Source"));
+        assert!(csv.modified_date().is_some());
+        assert_eq!(csv.data(&doc).unwrap(), data);
+        assert!(matches!(attachments[2].location, AttachmentLocation::Annotation { page, .. } if page == page_id));
+        assert_eq!(attachments[2].data(&doc).unwrap(), b"Notes");
+
+        let catalog = doc.catalog().unwrap();
+        let associated = catalog.get(b"AF").and_then(Object::as_array).unwrap();
+        assert_eq!(associated[0].as_reference().unwrap(), csv_id);
+
+        assert!(doc.remove_attachment("données.csv").unwrap());
+        assert!(!doc.remove_attachment("données.csv").unwrap());
+        assert!(!doc.catalog().unwrap().has(b"AF"));
+        let names: Vec<String> = doc.attachments().into_iter().map(|a| a.filename).collect();
+        assert_eq!(names, vec!["notes.txt", "notes.txt"]);
+    }
+}
@@ -1,5 +1,9 @@
-use super::{Dictionary, Document, Object, ObjectId};
-use std::collections::HashMap;
+use super::{Color, Dictionary, Document, Object, ObjectId, Result, StringFormat};
+use crate::destinations::Destination;
+use crate::encodings::{decode_text_string, encode_text_string};
+use bitflags::bitflags;
+use log::warn;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 #[derive(Debug, Clone)]
 pub struct Bookmark {
@@ -8,25 +12,97 @@ pub struct Bookmark {
     pub title: String,
     /// 0, 1 for italic, 2 for bold, 3 for italic bold
     pub format: u32,
-    /// R,G,B
-    pub color: [f32; 3],
+    /// Converted to RGB, the only color space of outline items.
+    pub color: Color,
     pub page: ObjectId,
     pub id: u32,
 }
 
 impl Bookmark {
-    pub fn new(title: String, color: [f32; 3], format: u32, page: ObjectId) -> Bookmark {
+    pub fn new(title: String, color: impl Into<Color>, format: u32, page: ObjectId) -> Bookmark {
         Bookmark {
             children: Vec::new(),
             title,
             format,
-            color,
+            color: color.into(),
             page,
             id: 0,
         }
     }
 }
 
+bitflags! {
+    /// Style of the title of an outline item, from the `/F` entry.
+    pub struct OutlineFlags: u32 {
+        const ITALIC = 1;
+        const BOLD = 1 << 1;
+    }
+}
+
+/// An item of the document outline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutlineItem {
+    pub title: String,
+    /// The page the item leads to and how to display it, if it leads to a page of the document.
+    pub destination: Option<Destination>,
+    /// Color of the title, converted to RGB, the only color space of outline items.
+    pub color: Color,
+    pub flags: OutlineFlags,
+    /// Whether the children are shown.
+    pub open: bool,
+    pub children: Vec<OutlineItem>,
+}
+
+impl OutlineItem {
+    pub fn new(title: String, destination: Option<Destination>) -> OutlineItem {
+        OutlineItem {
+            title,
+            destination,
+            color: Color::Rgb(0.0, 0.0, 0.0),
+            flags: OutlineFlags::empty(),
+            open: false,
+            children: Vec::new(),
+        }
+    }
+
+    /// The number of descendants shown when the item is open: its children and the items shown by its open children.
+    fn visible_descendants(&self) -> i64 {
+        self.children
+            .iter()
+            .map(|child| 1 + if child.open { child.visible_descendants() } else { 0 })
+            .sum()
+    }
+}
+
+/// Options for [`Document::set_outline_with`].
+#[derive(Debug, Clone, Default)]
+pub struct OutlineOptions {
+    /// Write destinations as named destinations instead of explicit arrays. Existing names are reused for the same
+    /// destination and new names are added to the `/Dests` name tree.
+    pub named_destinations: bool,
+}
+
+/// The named destinations known while writing an outline, and the ones to add.
+struct OutlineNames {
+    existing: BTreeMap<Vec<u8>, Destination>,
+    added: BTreeMap<Vec<u8>, Destination>,
+}
+
+impl OutlineNames {
+    fn name(&mut self, destination: Destination) -> Vec<u8> {
+        let known = self.existing.iter().chain(self.added.iter());
+        if let Some((name, _)) = known.into_iter().find(|(_, known)| **known == destination) {
+            return name.clone();
+        }
+        let name = (self.existing.len() + self.added.len() + 1..)
+            .map(|n| format!("outline{}", n).into_bytes())
+            .find(|name| !self.existing.contains_key(name) && !self.added.contains_key(name))
+            .unwrap();
+        self.added.insert(name.clone(), destination);
+        name
+    }
+}
+
 impl Document {
     pub fn add_bookmark(&mut self, mut bookmark: Bookmark, parent: Option<u32>) -> u32 {
         self.max_bookmark_id += 1;
@@ -69,14 +145,7 @@ impl Document {
             child.set("Title", Object::string_literal(bookmark.title.clone()));
             child.set("A", info_id);
             child.set("F", Object::Integer(bookmark.format.into()));
-            child.set(
-                "C",
-                vec![
-                    bookmark.color[0].into(),
-                    bookmark.color[1].into(),
-                    bookmark.color[2].into(),
-                ],
-            );
+            child.set("C", Color::from(bookmark.color.to_rgb()).to_object());
 
             if first.is_none() {
                 first = Some(id);
@@ -141,4 +210,332 @@ impl Document {
 
         None
     }
+
+    /// The items of the document outline, or `None` if the document has no outline.
+    ///
+    /// Items are read by following the `/First` and `/Next` entries, or `/Last` and `/Prev` when there is no
+    /// `/First` entry. Counts are only used to know whether items are open. An item reached twice ends the list it
+    /// is found in, so that cycles are broken.
+    pub fn outline(&self) -> Option<Vec<OutlineItem>> {
+        let outlines = self
+            .catalog()
+            .and_then(|catalog| catalog.get_deref(b"Outlines", self))
+            .and_then(Object::as_dict)
+            .ok()?;
+        let named = self.named_destinations();
+        let mut visited = BTreeSet::new();
+        Some(self.outline_items(outlines, &named, &mut visited))
+    }
+
+    fn outline_items(
+        &self, parent: &Dictionary, named: &BTreeMap<Vec<u8>, Destination>, visited: &mut BTreeSet<ObjectId>,
+    ) -> Vec<OutlineItem> {
+        let (mut current, next_key) = match parent.get(b"First").and_then(Object::as_reference) {
+            Ok(first) => (Some(first), &b"Next"[..]),
+            Err(_) => (parent.get(b"Last").and_then(Object::as_reference).ok(), &b"Prev"[..]),
+        };
+        let mut items = Vec::new();
+        while let Some(id) = current {
+            if !visited.insert(id) {
+                warn!("Outline item {:?} is reached twice", id);
+                break;
+            }
+            match self.get_dictionary(id) {
+                Ok(item) => {
+                    items.push(item);
+                    current = item.get(next_key).and_then(Object::as_reference).ok();
+                }
+                Err(err) => {
+                    warn!("Skipping outline item {:?}: {}", id, err);
+                    break;
+                }
+            }
+        }
+        if next_key == b"Prev" {
+            items.reverse();
+        }
+        items
+            .into_iter()
+            .map(|item| self.outline_item(item, named, visited))
+            .collect()
+    }
+
+    fn outline_item(
+        &self, item: &Dictionary, named: &BTreeMap<Vec<u8>, Destination>, visited: &mut BTreeSet<ObjectId>,
+    ) -> OutlineItem {
+        let destination = match item.get(b"Dest") {
+            Ok(dest) => self.destination_from_object(dest, named),
+            Err(_) => item
+                .get_deref(b"A", self)
+                .and_then(Object::as_dict)
+                .ok()
+                .filter(|action| action.get(b"S").and_then(Object::as_name).ok() == Some(b"GoTo"))
+                .and_then(|action| action.get(b"D").ok())
+                .and_then(|dest| self.destination_from_object(dest, named)),
+        };
+        let color = item
+            .get_deref(b"C", self)
+            .ok()
+            .and_then(Color::from_object)
+            .filter(|color| matches!(color, Color::Rgb(..)))
+            .unwrap_or(Color::Rgb(0.0, 0.0, 0.0));
+        OutlineItem {
+            title: item
+                .get_deref(b"Title", self)
+                .and_then(Object::as_str)
+                .map(decode_text_string)
+                .unwrap_or_default(),
+            destination,
+            color,
+            flags: OutlineFlags::from_bits_truncate(
+                item.get_deref(b"F", self).and_then(Object::as_i64).unwrap_or(0) as u32
+            ),
+            open: item.get_deref(b"Count", self).and_then(Object::as_i64).unwrap_or(0) > 0,
+            children: self.outline_items(item, named, visited),
+        }
+    }
+
+    /// Replace the document outline with the given items, writing destinations as explicit arrays.
+    pub fn set_outline(&mut self, items: Vec<OutlineItem>) -> Result<()> {
+        self.set_outline_with(items, &OutlineOptions::default())
+    }
+
+    /// Replace the document outline with the given items. The outline is removed when there are no items.
+    ///
+    /// The objects of the previous outline are left for [`Document::prune_objects`].
+    pub fn set_outline_with(&mut self, items: Vec<OutlineItem>, options: &OutlineOptions) -> Result<()> {
+        let root_id = self.trailer.get(b"Root")?.as_reference()?;
+        if items.is_empty() {
+            self.get_object_mut(root_id)?.as_dict_mut()?.remove(b"Outlines");
+            return Ok(());
+        }
+
+        let mut names = if options.named_destinations {
+            Some(OutlineNames {
+                existing: self.named_destinations(),
+                added: BTreeMap::new(),
+            })
+        } else {
+            None
+        };
+        let outlines_id = self.new_object_id();
+        let (first, last) = self.write_outline_items(outlines_id, &items, &mut names);
+        let visible: i64 = items
+            .iter()
+            .map(|item| 1 + if item.open { item.visible_descendants() } else { 0 })
+            .sum();
+        self.objects.insert(
+            outlines_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Outlines",
+                "First" => first,
+                "Last" => last,
+                "Count" => visible,
+            }),
+        );
+
+        if let Some(names) = names.filter(|names| !names.added.is_empty()) {
+            let mut entries = self.names(b"Dests");
+            entries.extend(names.added.into_iter().map(|(name, dest)| (name, dest.to_object())));
+            self.set_names("Dests", entries)?;
+        }
+        self.get_object_mut(root_id)?
+            .as_dict_mut()?
+            .set("Outlines", outlines_id);
+        Ok(())
+    }
+
+    /// Add linked outline items under a parent, which must not be empty, and return the first and last ones.
+    fn write_outline_items(
+        &mut self, parent_id: ObjectId, items: &[OutlineItem], names: &mut Option<OutlineNames>,
+    ) -> (ObjectId, ObjectId) {
+        let ids: Vec<ObjectId> = items.iter().map(|_| self.new_object_id()).collect();
+        for (index, item) in items.iter().enumerate() {
+            let mut dict = dictionary! {
+                "Title" => Object::String(encode_text_string(&item.title), StringFormat::Literal),
+                "Parent" => parent_id,
+            };
+            if index > 0 {
+                dict.set("Prev", ids[index - 1]);
+            }
+            if let Some(&next) = ids.get(index + 1) {
+                dict.set("Next", next);
+            }
+            if !item.children.is_empty() {
+                let (first, last) = self.write_outline_items(ids[index], &item.children, names);
+                dict.set("First", first);
+                dict.set("Last", last);
+                let count = item.visible_descendants();
+                dict.set("Count", if item.open { count } else { -count });
+            }
+            if let Some(destination) = item.destination {
+                match names {
+                    Some(names) => dict.set("Dest", Object::String(names.name(destination), StringFormat::Literal)),
+                    None => dict.set("Dest", destination.to_object()),
+                }
+            }
+            let color = item.color.to_rgb();
+            if color != [0.0; 3] {
+                dict.set("C", Color::from(color).to_object());
+            }
+            if !item.flags.is_empty() {
+                dict.set("F", item.flags.bits() as i64);
+            }
+            self.objects.insert(ids[index], Object::Dictionary(dict));
+        }
+        (ids[0], ids[ids.len() - 1])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::creator::tests::create_document;
+    use crate::destinations::DestinationFit;
+    use crate::StringFormat;
+
+    #[test]
+    fn read_outline() {
+        let mut doc = create_document();
+        let page_id = doc.page_iter().next().unwrap();
+        doc.add_named_destination("intro", page_id, DestinationFit::FitH { top: Some(500.0) })
+            .unwrap();
+        let outlines_id = doc.new_object_id();
+        let (chapter_id, section_id, appendix_id) = (doc.new_object_id(), doc.new_object_id(), doc.new_object_id());
+        doc.objects.insert(
+            outlines_id,
+            dictionary! { "First" => chapter_id, "Last" => appendix_id, "Count" => 3 }.into(),
+        );
+        doc.objects.insert(
+            chapter_id,
+            dictionary! {
+                "Title" => Object::String(b"\xFE\xFF\x00C\x00h\x00\xE2".to_vec(), StringFormat::Literal),
+                "Parent" => outlines_id,
+                "Next" => appendix_id,
+                "First" => section_id,
+                "Last" => section_id,
+                "Count" => -1,
+                "Dest" => Object::string_literal("intro"),
+                "C" => vec![1.into(), 0.into(), 0.into()],
+                "F" => 3,
+            }
+            .into(),
+        );
+        doc.objects.insert(
+            section_id,
+            dictionary! {
+                "Title" => Object::string_literal("Section"),
+                "Parent" => chapter_id,
+                "A" => dictionary! { "S" => "GoTo", "D" => vec![page_id.into(), "Fit".into()] },
+            }
+            .into(),
+        );
+        // The appendix links back to the chapter, which must not loop.
+        doc.objects.insert(
+            appendix_id,
+            dictionary! {
+                "Title" => Object::string_literal("Appendix"),
+                "Parent" => outlines_id,
+                "Prev" => chapter_id,
+                "Next" => chapter_id,
+                "A" => dictionary! { "S" => "URI", "URI" => Object::string_literal("https://example.com") },
+            }
+            .into(),
+        );
+        let root_id = doc.trailer.get(b"Root").unwrap().as_reference().unwrap();
+        doc.get_object_mut(root_id)
+            .and_then(Object::as_dict_mut)
+            .unwrap()
+            .set("Outlines", outlines_id);
+
+        let outline = doc.outline().unwrap();
+        assert_eq!(outline.len(), 2);
+        let chapter = &outline[0];
+        assert_eq!(chapter.title, "Ch\u{e2}");
+        assert_eq!(
+            chapter.destination,
+            Some(Destination::new(page_id, DestinationFit::FitH { top: Some(500.0) }))
+        );
+        assert_eq!(chapter.color, Color::Rgb(1.0, 0.0, 0.0));
+        assert_eq!(chapter.flags, OutlineFlags::ITALIC | OutlineFlags::BOLD);
+        assert!(!chapter.open);
+        assert_eq!(chapter.children.len(), 1);
+        assert_eq!(chapter.children[0].title, "Section");
+        assert_eq!(
+            chapter.children[0].destination,
+            Some(Destination::new(page_id, DestinationFit::Fit))
+        );
+        assert_eq!(outline[1].title, "Appendix");
+        assert_eq!(outline[1].destination, None);
+        assert!(outline[1].children.is_empty());
+
+        // Without a first item, the items are read backwards from the last one.
+        doc.get_object_mut(outlines_id)
+            .and_then(Object::as_dict_mut)
+            .unwrap()
+            .remove(b"First");
+        let titles: Vec<String> = doc.outline().unwrap().into_iter().map(|item| item.title).collect();
+        assert_eq!(titles, vec!["Ch\u{e2}", "Appendix"]);
+    }
+
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    #[test]
+    fn add_chapter_to_loaded_outline() {
+        let mut doc = create_document();
+        let page_id = doc.page_iter().next().unwrap();
+        let mut preface = OutlineItem::new(
+            "Préface".to_string(),
+            Some(Destination::new(page_id, DestinationFit::Fit)),
+        );
+        preface.flags = OutlineFlags::BOLD;
+        let mut part = OutlineItem::new("Part I".to_string(), None);
+        part.open = true;
+        part.color = Color::Rgb(0.0, 0.0, 1.0);
+        let mut chapter = OutlineItem::new("Chapter 1".to_string(), None);
+        chapter.children.push(OutlineItem::new("Section 1.1".to_string(), None));
+        part.children.push(chapter);
+        doc.set_outline(vec![preface, part]).unwrap();
+
+        let mut buffer = Vec::new();
+        doc.save_to(&mut buffer).unwrap();
+        let mut doc = Document::load_mem(&buffer).unwrap();
+        let mut outline = doc.outline().unwrap();
+        assert_eq!(outline[0].title, "Préface");
+        assert_eq!(outline[1].children[0].children[0].title, "Section 1.1");
+
+        let top = DestinationFit::Xyz {
+            left: Some(0.0),
+            top: Some(792.0),
+            zoom: None,
+        };
+        let mut new_chapter = OutlineItem::new("Chapter 2 — ∑".to_string(), Some(Destination::new(page_id, top)));
+        new_chapter.flags = OutlineFlags::ITALIC;
+        outline[1].children.push(new_chapter);
+        let options = OutlineOptions {
+            named_destinations: true,
+        };
+        doc.set_outline_with(outline.clone(), &options).unwrap();
+        assert_eq!(doc.outline().unwrap(), outline);
+
+        // Part I is open and shows its two chapters; the first chapter is closed on one section.
+        let catalog = doc.catalog().unwrap();
+        let outlines = doc
+            .get_dictionary(catalog.get(b"Outlines").unwrap().as_reference().unwrap())
+            .unwrap();
+        assert_eq!(outlines.get(b"Count").unwrap().as_i64().unwrap(), 4);
+        let part = doc
+            .get_dictionary(outlines.get(b"Last").unwrap().as_reference().unwrap())
+            .unwrap();
+        assert_eq!(part.get(b"Count").unwrap().as_i64().unwrap(), 2);
+        let chapter = doc
+            .get_dictionary(part.get(b"First").unwrap().as_reference().unwrap())
+            .unwrap();
+        assert_eq!(chapter.get(b"Count").unwrap().as_i64().unwrap(), -1);
+        let new_chapter = doc
+            .get_dictionary(part.get(b"Last").unwrap().as_reference().unwrap())
+            .unwrap();
+        assert_eq!(new_chapter.get(b"Title").unwrap().as_str().unwrap()[..2], [0xFE, 0xFF]);
+        let name = new_chapter.get(b"Dest").unwrap().as_str().unwrap();
+        assert_eq!(doc.resolve_destination(name), Some((page_id, top)));
+    }
 }
@@ -0,0 +1,409 @@
+//! Documents made of pages of text and images, without wiring their objects by hand.
+//!
+//! ```
+//! use lopdf::{BuiltinFont, DocumentBuilder, PageBuilder};
+//!
+//! let mut doc = DocumentBuilder::new()
+//!     .title("Greetings")
+//!     .page(
+//!         PageBuilder::a4()
+//!             .text(72.0, 720.0, BuiltinFont::Helvetica, 24.0, "Hello World!")
+//!             .text(72.0, 690.0, BuiltinFont::TimesItalic, 12.0, "Made with lopdf.")
+//!             .build(),
+//!     )
+//!     .build()
+//!     .unwrap();
+//! // The document can be edited further as any other.
+//! assert_eq!(doc.get_pages().len(), 1);
+//! doc.save_to(&mut Vec::new()).unwrap();
+//! ```
+
+use crate::content::{Content, Operation};
+use crate::{Dictionary, Document, DocumentMetadata, Object, ObjectId, Result, Stream};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
+/// The standard 14 fonts, which viewers have and documents need not embed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BuiltinFont {
+    TimesRoman,
+    TimesBold,
+    TimesItalic,
+    TimesBoldItalic,
+    Helvetica,
+    HelveticaBold,
+    HelveticaOblique,
+    HelveticaBoldOblique,
+    Courier,
+    CourierBold,
+    CourierOblique,
+    CourierBoldOblique,
+    Symbol,
+    ZapfDingbats,
+}
+
+impl BuiltinFont {
+    /// The `/BaseFont` name of the font.
+    pub fn base_font(self) -> &'static str {
+        match self {
+            BuiltinFont::TimesRoman => "Times-Roman",
+            BuiltinFont::TimesBold => "Times-Bold",
+            BuiltinFont::TimesItalic => "Times-Italic",
+            BuiltinFont::TimesBoldItalic => "Times-BoldItalic",
+            BuiltinFont::Helvetica => "Helvetica",
+            BuiltinFont::HelveticaBold => "Helvetica-Bold",
+            BuiltinFont::HelveticaOblique => "Helvetica-Oblique",
+            BuiltinFont::HelveticaBoldOblique => "Helvetica-BoldOblique",
+            BuiltinFont::Courier => "Courier",
+            BuiltinFont::CourierBold => "Courier-Bold",
+            BuiltinFont::CourierOblique => "Courier-Oblique",
+            BuiltinFont::CourierBoldOblique => "Courier-BoldOblique",
+            BuiltinFont::Symbol => "Symbol",
+            BuiltinFont::ZapfDingbats => "ZapfDingbats",
+        }
+    }
+
+    /// Whether the font has its own encoding rather than WinAnsiEncoding.
+    fn is_symbolic(self) -> bool {
+        matches!(self, BuiltinFont::Symbol | BuiltinFont::ZapfDingbats)
+    }
+
+    /// The codes of text in the font. Text fonts show the characters of WinAnsiEncoding, leaving out the others;
+    /// the symbolic fonts take characters up to U+00FF as the codes of their own encoding.
+    pub(crate) fn encode(self, text: &str) -> Vec<u8> {
+        if self.is_symbolic() {
+            text.chars().filter_map(|ch| u8::try_from(u32::from(ch)).ok()).collect()
+        } else {
+            Document::encode_text(Some("WinAnsiEncoding"), text)
+        }
+    }
+
+    pub(crate) fn dictionary(self) -> Dictionary {
+        let mut font = dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => self.base_font(),
+        };
+        if !self.is_symbolic() {
+            font.set("Encoding", "WinAnsiEncoding");
+        }
+        font
+    }
+}
+
+/// Something drawn on a page, in the order it was added.
+#[derive(Debug, Clone)]
+enum Item {
+    Text {
+        x: f64,
+        y: f64,
+        font: BuiltinFont,
+        size: f64,
+        text: String,
+    },
+    Image {
+        image: Stream,
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+    },
+    Operations(Vec<Operation>),
+}
+
+/// A page made by a [`PageBuilder`], to be added to a [`DocumentBuilder`].
+#[derive(Debug, Clone)]
+pub struct Page {
+    media_box: [f64; 4],
+    items: Vec<Item>,
+}
+
+/// A page of text and images, in PDF units of 1/72 inch from the bottom left corner.
+#[derive(Debug, Clone)]
+pub struct PageBuilder {
+    page: Page,
+}
+
+impl PageBuilder {
+    /// An empty page of a size in points.
+    pub fn new(width: f64, height: f64) -> PageBuilder {
+        PageBuilder {
+            page: Page {
+                media_box: [0.0, 0.0, width, height],
+                items: Vec::new(),
+            },
+        }
+    }
+
+    /// An A4 page, 595 by 842 points.
+    pub fn a4() -> PageBuilder {
+        PageBuilder::new(595.0, 842.0)
+    }
+
+    /// A US Letter page, 612 by 792 points.
+    pub fn letter() -> PageBuilder {
+        PageBuilder::new(612.0, 792.0)
+    }
+
+    /// A line of text starting at a point of its baseline.
+    pub fn text(mut self, x: f64, y: f64, font: BuiltinFont, size: f64, text: &str) -> PageBuilder {
+        self.page.items.push(Item::Text {
+            x,
+            y,
+            font,
+            size,
+            text: text.to_string(),
+        });
+        self
+    }
+
+    /// An image XObject, such as [`xobject::jp2_image`](crate::xobject::jp2_image) and, with the `embed_image`
+    /// feature, `xobject::image` make, scaled to a rectangle whose bottom left corner is at a point.
+    pub fn image(mut self, image: Stream, x: f64, y: f64, width: f64, height: f64) -> PageBuilder {
+        self.page.items.push(Item::Image {
+            image,
+            x,
+            y,
+            width,
+            height,
+        });
+        self
+    }
+
+    /// Content operations drawn as they are, in a graphics state of their own. Resources they use have to be added
+    /// to the page of the document built.
+    pub fn operations(mut self, operations: Vec<Operation>) -> PageBuilder {
+        self.page.items.push(Item::Operations(operations));
+        self
+    }
+
+    pub fn build(self) -> Page {
+        self.page
+    }
+}
+
+/// A document of pages, with its page tree, resources and document information made when it is built.
+#[derive(Debug, Clone)]
+pub struct DocumentBuilder {
+    version: String,
+    metadata: DocumentMetadata,
+    pages: Vec<Page>,
+}
+
+impl Default for DocumentBuilder {
+    fn default() -> DocumentBuilder {
+        DocumentBuilder::new()
+    }
+}
+
+impl DocumentBuilder {
+    /// A PDF 1.5 document without pages.
+    pub fn new() -> DocumentBuilder {
+        DocumentBuilder {
+            version: "1.5".to_string(),
+            metadata: DocumentMetadata::default(),
+            pages: Vec::new(),
+        }
+    }
+
+    /// The PDF version of the header.
+    pub fn version(mut self, version: &str) -> DocumentBuilder {
+        self.version = version.to_string();
+        self
+    }
+
+    pub fn title(mut self, title: &str) -> DocumentBuilder {
+        self.metadata.title = Some(title.to_string());
+        self
+    }
+
+    pub fn author(mut self, author: &str) -> DocumentBuilder {
+        self.metadata.author = Some(author.to_string());
+        self
+    }
+
+    pub fn subject(mut self, subject: &str) -> DocumentBuilder {
+        self.metadata.subject = Some(subject.to_string());
+        self
+    }
+
+    /// The application making the document.
+    pub fn creator(mut self, creator: &str) -> DocumentBuilder {
+        self.metadata.creator = Some(creator.to_string());
+        self
+    }
+
+    /// Every entry of the document information dictionary, replacing those set before.
+    pub fn metadata(mut self, metadata: DocumentMetadata) -> DocumentBuilder {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Add a page after the others.
+    pub fn page(mut self, page: Page) -> DocumentBuilder {
+        self.pages.push(page);
+        self
+    }
+
+    /// Make the document. Each font is one object shared by the pages using it; each page has its own resources
+    /// and a compressed content stream. The document information dictionary is only written if an entry is set.
+    pub fn build(self) -> Result<Document> {
+        let mut doc = Document::with_version(self.version.as_str());
+        let pages_id = doc.new_object_id();
+        let mut fonts: BTreeMap<BuiltinFont, (String, ObjectId)> = BTreeMap::new();
+        let mut kids = Vec::with_capacity(self.pages.len());
+        for page in self.pages {
+            let mut page_fonts = Dictionary::new();
+            let mut xobjects = Dictionary::new();
+            let mut operations = Vec::new();
+            for item in page.items {
+                match item {
+                    Item::Text {
+                        x,
+                        y,
+                        font,
+                        size,
+                        text,
+                    } => {
+                        let count = fonts.len();
+                        let (name, font_id) = fonts
+                            .entry(font)
+                            .or_insert_with(|| (format!("F{}", count + 1), doc.add_object(font.dictionary())));
+                        page_fonts.set(name.as_str(), *font_id);
+                        operations.extend(vec![
+                            Operation::new("BT", vec![]),
+                            Operation::new("Tf", vec![Object::from(name.as_str()), size.into()]),
+                            Operation::new("Td", vec![x.into(), y.into()]),
+                            Operation::new("Tj", vec![Object::string_literal(font.encode(&text))]),
+                            Operation::new("ET", vec![]),
+                        ]);
+                    }
+                    Item::Image {
+                        image,
+                        x,
+                        y,
+                        width,
+                        height,
+                    } => {
+                        let name = format!("Im{}", xobjects.len() + 1);
+                        xobjects.set(name.as_str(), doc.add_object(image));
+                        operations.extend(vec![
+                            Operation::new("q", vec![]),
+                            Operation::new(
+                                "cm",
+                                vec![width.into(), 0.into(), 0.into(), height.into(), x.into(), y.into()],
+                            ),
+                            Operation::new("Do", vec![Object::from(name.as_str())]),
+                            Operation::new("Q", vec![]),
+                        ]);
+                    }
+                    Item::Operations(raw) => {
+                        operations.push(Operation::new("q", vec![]));
+                        operations.extend(raw);
+                        operations.push(Operation::new("Q", vec![]));
+                    }
+                }
+            }
+
+            let mut content = Stream::new(Dictionary::new(), Content { operations }.encode()?);
+            content.compress()?;
+            let mut resources = Dictionary::new();
+            if !page_fonts.is_empty() {
+                resources.set("Font", page_fonts);
+            }
+            if !xobjects.is_empty() {
+                resources.set("XObject", xobjects);
+            }
+            let [left, bottom, right, top] = page.media_box;
+            let content_id = doc.add_object(content);
+            let page_id = doc.add_object(dictionary! {
+                "Type" => "Page",
+                "Parent" => pages_id,
+                "MediaBox" => vec![left.into(), bottom.into(), right.into(), top.into()],
+                "Resources" => resources,
+                "Contents" => content_id,
+            });
+            kids.push(Object::Reference(page_id));
+        }
+
+        let count = kids.len() as i64;
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => kids,
+                "Count" => count,
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        doc.trailer.set("Root", catalog_id);
+        if self.metadata != DocumentMetadata::default() {
+            doc.set_metadata(&self.metadata)?;
+        }
+        Ok(doc)
+    }
+}
+
+#[cfg(all(test, any(feature = "pom_parser", feature = "nom_parser")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages_share_fonts() {
+        let image = Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Image",
+                "Width" => 1,
+                "Height" => 1,
+                "ColorSpace" => "DeviceGray",
+                "BitsPerComponent" => 8,
+            },
+            vec![128],
+        );
+        let mut doc = DocumentBuilder::new()
+            .title("Builder")
+            .page(
+                PageBuilder::a4()
+                    .text(72.0, 720.0, BuiltinFont::Helvetica, 12.0, "Hello café")
+                    .image(image, 72.0, 600.0, 100.0, 100.0)
+                    .build(),
+            )
+            .page(
+                PageBuilder::letter()
+                    .text(72.0, 720.0, BuiltinFont::Helvetica, 12.0, "Second")
+                    .text(72.0, 700.0, BuiltinFont::CourierBold, 10.0, "page")
+                    .build(),
+            )
+            .build()
+            .unwrap();
+
+        let mut saved = Vec::new();
+        doc.save_to(&mut saved).unwrap();
+        let doc = Document::load_mem(&saved).unwrap();
+        assert_eq!(doc.metadata().title.as_deref(), Some("Builder"));
+        let pages = doc.get_pages();
+        assert_eq!(pages.len(), 2);
+        assert_eq!(doc.extract_text(&[1]).unwrap(), "Hello café\n");
+        assert_eq!(doc.extract_text(&[2]).unwrap(), "Second\npage\n");
+
+        // One Helvetica object for both pages.
+        let font_id = |page: u32| {
+            doc.get_dictionary(pages[&page])
+                .and_then(|page| page.get_deref(b"Resources", &doc))
+                .and_then(Object::as_dict)
+                .and_then(|resources| resources.get(b"Font"))
+                .and_then(Object::as_dict)
+                .and_then(|fonts| fonts.get(b"F1"))
+                .and_then(Object::as_reference)
+                .unwrap()
+        };
+        assert_eq!(font_id(1), font_id(2));
+        assert_eq!(doc.get_page_fonts(pages[&2]).len(), 2);
+        let images = doc.extract_images(pages[&1], &Default::default()).unwrap();
+        assert_eq!((images[0].width, images[0].data.as_slice()), (1, &[128][..]));
+    }
+}
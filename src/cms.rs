@@ -0,0 +1,355 @@
+#![cfg(feature = "crypto")]
+
+//! Just enough DER (and BER) parsing of CMS signed-data (RFC 5652) to check the signatures of a document.
+
+use crate::signatures::DigestAlgorithm;
+
+const SIGNED_DATA: &str = "1.2.840.113549.1.7.2";
+const MESSAGE_DIGEST: &str = "1.2.840.113549.1.9.4";
+const SIGNING_TIME: &str = "1.2.840.113549.1.9.5";
+const TST_INFO: &str = "1.2.840.113549.1.9.16.1.4";
+
+/// What a detached CMS signature says about itself, without checking the signature value or certificates.
+#[derive(Debug, Clone)]
+pub struct CmsSignature {
+    pub digest_algorithm: DigestAlgorithm,
+    /// The message digest signed attribute, which must equal the digest of the signed byte ranges.
+    pub message_digest: Option<Vec<u8>>,
+    /// Whether [`message_digest`](CmsSignature::message_digest) matches the byte ranges of the document;
+    /// `None` without the attribute or without the ranges.
+    pub digest_matches: Option<bool>,
+    /// Subject of the signer certificate, such as `C=GB, O=Example, CN=Jane Doe`.
+    pub signer: Option<String>,
+    /// The signing time signed attribute, as a PDF date string.
+    pub signing_time: Option<String>,
+    /// What a time-stamp token, as found in document timestamps, certifies.
+    pub timestamp: Option<TimestampInfo>,
+}
+
+/// The time-stamp token info (RFC 3161) signed by a time-stamping authority.
+#[derive(Debug, Clone)]
+pub struct TimestampInfo {
+    /// The algorithm of the digest that was time-stamped.
+    pub digest_algorithm: DigestAlgorithm,
+    /// The digest that was time-stamped, which must equal that of the signed byte ranges.
+    pub message_imprint: Vec<u8>,
+    /// When the token was issued, as a PDF date string.
+    pub time: String,
+}
+
+impl TimestampInfo {
+    /// When the token was issued.
+    #[cfg(feature = "chrono_time")]
+    pub fn date(&self) -> Option<chrono::DateTime<chrono::Local>> {
+        crate::Object::string_literal(self.time.as_str()).as_datetime()
+    }
+
+    /// When the token was issued.
+    #[cfg(not(feature = "chrono_time"))]
+    pub fn date(&self) -> Option<time::OffsetDateTime> {
+        crate::Object::string_literal(self.time.as_str()).as_datetime()
+    }
+
+    /// Parse the DER encoded TSTInfo structure.
+    fn parse(data: &[u8]) -> Option<TimestampInfo> {
+        let mut tst_info = Elements(Elements(data).expect(0x30)?);
+        tst_info.expect(0x02)?;
+        tst_info.expect(0x06)?;
+        let mut message_imprint = Elements(tst_info.expect(0x30)?);
+        let digest_algorithm = digest_algorithm(&oid(Elements(message_imprint.expect(0x30)?).expect(0x06)?))?;
+        let message_imprint = message_imprint.expect(0x04)?.to_vec();
+        tst_info.expect(0x02)?;
+        let time = pdf_date(0x18, tst_info.expect(0x18)?)?;
+        Some(TimestampInfo {
+            digest_algorithm,
+            message_imprint,
+            time,
+        })
+    }
+}
+
+impl CmsSignature {
+    /// The signing time claimed by the signer.
+    #[cfg(feature = "chrono_time")]
+    pub fn signing_date(&self) -> Option<chrono::DateTime<chrono::Local>> {
+        crate::Object::string_literal(self.signing_time.as_ref()?.as_str()).as_datetime()
+    }
+
+    /// The signing time claimed by the signer.
+    #[cfg(not(feature = "chrono_time"))]
+    pub fn signing_date(&self) -> Option<time::OffsetDateTime> {
+        crate::Object::string_literal(self.signing_time.as_ref()?.as_str()).as_datetime()
+    }
+
+    /// Parse a DER encoded ContentInfo of signed data, as found in `/Contents`, followed by any padding.
+    pub fn parse(data: &[u8]) -> Option<CmsSignature> {
+        let (tag, content_info, _) = element(data)?;
+        if tag != 0x30 {
+            return None;
+        }
+        let mut content_info = Elements(content_info);
+        if oid(content_info.expect(0x06)?) != SIGNED_DATA {
+            return None;
+        }
+        let (_, signed_data, _) = element(content_info.expect(0xA0)?)?;
+        let mut signed_data = Elements(signed_data);
+        signed_data.expect(0x02)?;
+        signed_data.expect(0x31)?;
+        let mut content = Elements(signed_data.expect(0x30)?);
+        let timestamp = if oid(content.expect(0x06)?) == TST_INFO {
+            let (_, tst_info, _) = element(content.expect(0xA0)?)?;
+            TimestampInfo::parse(tst_info)
+        } else {
+            None
+        };
+        let mut certificates = Vec::new();
+        let mut signer_infos = None;
+        for (tag, content, _) in signed_data {
+            match tag {
+                0xA0 => certificates = Elements(content).map(|(_, certificate, _)| certificate).collect(),
+                0x31 => signer_infos = Some(content),
+                _ => {}
+            }
+        }
+        let (_, signer_info, _) = element(signer_infos?)?;
+        let mut signer_info = Elements(signer_info);
+        signer_info.expect(0x02)?;
+        let (sid_tag, sid, _) = signer_info.next()?;
+        let digest_algorithm = digest_algorithm(&oid(Elements(signer_info.expect(0x30)?).expect(0x06)?))?;
+
+        let mut message_digest = None;
+        let mut signing_time = None;
+        if let Some((0xA0, attributes, _)) = signer_info.next() {
+            for (_, attribute, _) in Elements(attributes) {
+                let mut attribute = Elements(attribute);
+                let attribute_type = oid(attribute.expect(0x06)?);
+                let value = attribute.expect(0x31).and_then(|values| element(values));
+                match (attribute_type.as_str(), value) {
+                    (MESSAGE_DIGEST, Some((0x04, digest, _))) => message_digest = Some(digest.to_vec()),
+                    (SIGNING_TIME, Some((tag, time, _))) => signing_time = pdf_date(tag, time),
+                    _ => {}
+                }
+            }
+        }
+
+        // The signer is identified by the issuer and serial number of its certificate, or by a key identifier
+        // that is looked for in the extensions, which are not parsed; the first certificate is taken then.
+        let signer_id = if sid_tag == 0x30 {
+            let mut sid = Elements(sid);
+            Some((sid.next()?.2, sid.expect(0x02)?))
+        } else {
+            None
+        };
+        let certificate = certificates
+            .iter()
+            .filter_map(|certificate| Certificate::parse(certificate))
+            .find(|certificate| signer_id.is_none_or(|id| id == (certificate.issuer, certificate.serial)))
+            .or_else(|| {
+                certificates
+                    .first()
+                    .and_then(|certificate| Certificate::parse(certificate))
+            });
+        Some(CmsSignature {
+            digest_algorithm,
+            message_digest,
+            digest_matches: None,
+            signer: certificate.and_then(|certificate| name(certificate.subject)),
+            signing_time,
+            timestamp,
+        })
+    }
+}
+
+/// The fields of an X.509 certificate needed to find and name the signer.
+struct Certificate<'a> {
+    serial: &'a [u8],
+    /// The whole encoded name, for comparison.
+    issuer: &'a [u8],
+    subject: &'a [u8],
+}
+
+impl<'a> Certificate<'a> {
+    fn parse(certificate: &'a [u8]) -> Option<Certificate<'a>> {
+        let mut tbs = Elements(Elements(certificate).expect(0x30)?);
+        let (mut tag, mut serial, _) = tbs.next()?;
+        if tag == 0xA0 {
+            let next = tbs.next()?;
+            tag = next.0;
+            serial = next.1;
+        }
+        if tag != 0x02 {
+            return None;
+        }
+        tbs.expect(0x30)?;
+        let issuer = tbs.next()?.2;
+        tbs.expect(0x30)?;
+        let subject = tbs.next()?.1;
+        Some(Certificate {
+            serial,
+            issuer,
+            subject,
+        })
+    }
+}
+
+/// The children of a constructed element.
+struct Elements<'a>(&'a [u8]);
+
+impl<'a> Elements<'a> {
+    /// The content of the next element, if it has the given tag.
+    fn expect(&mut self, tag: u8) -> Option<&'a [u8]> {
+        match self.next()? {
+            (found, content, _) if found == tag => Some(content),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> Iterator for Elements<'a> {
+    /// Tag, content and the whole encoding of an element.
+    type Item = (u8, &'a [u8], &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.0.starts_with(&[0, 0]) {
+            return None;
+        }
+        let (tag, content, rest) = element(self.0)?;
+        let whole = &self.0[..self.0.len() - rest.len()];
+        self.0 = rest;
+        Some((tag, content, whole))
+    }
+}
+
+/// Split the first element off, as tag, content and what follows. Only single byte tags are supported, which
+/// is all CMS uses; the indefinite lengths of BER, which some signers produce, are followed.
+fn element(data: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, data) = data.split_first()?;
+    let (&length, data) = data.split_first()?;
+    if length == 0x80 {
+        let mut children = Elements(data);
+        while children.next().is_some() {}
+        let content_length = data.len() - children.0.len();
+        let rest = children.0.get(2..)?;
+        return Some((tag, &data[..content_length], rest));
+    }
+    let (length, data) = if length < 0x80 {
+        (length as usize, data)
+    } else {
+        let size = (length & 0x7F) as usize;
+        if size > 4 {
+            return None;
+        }
+        let bytes = data.get(..size)?;
+        let length = bytes.iter().fold(0, |length, &byte| length << 8 | byte as usize);
+        (length, &data[size..])
+    };
+    if length > data.len() {
+        return None;
+    }
+    Some((tag, &data[..length], &data[length..]))
+}
+
+/// Dotted decimal form of an object identifier.
+fn oid(content: &[u8]) -> String {
+    let mut arcs = Vec::new();
+    let mut value: u64 = 0;
+    for &byte in content {
+        value = value << 7 | u64::from(byte & 0x7F);
+        if byte & 0x80 == 0 {
+            if arcs.is_empty() {
+                let first = (value / 40).min(2);
+                arcs.push(first);
+                arcs.push(value - first * 40);
+            } else {
+                arcs.push(value);
+            }
+            value = 0;
+        }
+    }
+    arcs.iter().map(u64::to_string).collect::<Vec<_>>().join(".")
+}
+
+fn digest_algorithm(oid: &str) -> Option<DigestAlgorithm> {
+    match oid {
+        "1.2.840.113549.2.5" => Some(DigestAlgorithm::Md5),
+        "1.3.14.3.2.26" => Some(DigestAlgorithm::Sha1),
+        "2.16.840.1.101.3.4.2.1" => Some(DigestAlgorithm::Sha256),
+        "2.16.840.1.101.3.4.2.2" => Some(DigestAlgorithm::Sha384),
+        "2.16.840.1.101.3.4.2.3" => Some(DigestAlgorithm::Sha512),
+        _ => None,
+    }
+}
+
+/// A distinguished name, with its attributes in the order encoded.
+fn name(content: &[u8]) -> Option<String> {
+    let mut parts = Vec::new();
+    for (_, rdn, _) in Elements(content) {
+        for (_, attribute, _) in Elements(rdn) {
+            let mut attribute = Elements(attribute);
+            let attribute_type = oid(attribute.expect(0x06)?);
+            let (tag, value, _) = attribute.next()?;
+            let value = match tag {
+                // BMPString
+                0x1E => {
+                    let code_units: Vec<u16> = value
+                        .chunks_exact(2)
+                        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                        .collect();
+                    String::from_utf16_lossy(&code_units)
+                }
+                // TeletexString, taken as Latin-1
+                0x14 => value.iter().map(|&byte| char::from(byte)).collect(),
+                _ => String::from_utf8_lossy(value).into_owned(),
+            };
+            let key = match attribute_type.as_str() {
+                "2.5.4.3" => "CN",
+                "2.5.4.6" => "C",
+                "2.5.4.7" => "L",
+                "2.5.4.8" => "ST",
+                "2.5.4.10" => "O",
+                "2.5.4.11" => "OU",
+                "1.2.840.113549.1.9.1" => "E",
+                other => other,
+            };
+            parts.push(format!("{}={}", key, value));
+        }
+    }
+    Some(parts.join(", "))
+}
+
+/// A UTCTime or GeneralizedTime as a PDF date string.
+fn pdf_date(tag: u8, time: &[u8]) -> Option<String> {
+    let time = std::str::from_utf8(time).ok()?;
+    let time = match tag {
+        0x17 => {
+            let year: u32 = time.get(..2)?.parse().ok()?;
+            format!("{}{}", if year < 50 { "20" } else { "19" }, time)
+        }
+        0x18 => time.to_string(),
+        _ => return None,
+    };
+    Some(format!("D:{}", time.split('.').next()?.trim_end_matches('Z')) + "Z")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_der_elements() {
+        assert_eq!(
+            oid(&[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x07, 0x02]),
+            SIGNED_DATA
+        );
+        // An indefinite length sequence holding an integer, followed by a null.
+        let data = [0x30, 0x80, 0x02, 0x01, 0x05, 0x00, 0x00, 0x05, 0x00];
+        let (tag, content, rest) = element(&data).unwrap();
+        assert_eq!((tag, content, rest), (0x30, &data[2..5], &data[7..]));
+        assert_eq!(pdf_date(0x17, b"240102030405Z").unwrap(), "D:20240102030405Z");
+        let name_der = [
+            0x31, 0x0B, 0x30, 0x09, 0x06, 0x03, 0x55, 0x04, 0x06, 0x13, 0x02, b'G', b'B', 0x31, 0x0D, 0x30, 0x0B, 0x06,
+            0x03, 0x55, 0x04, 0x03, 0x0C, 0x04, b'J', b'a', b'n', b'e',
+        ];
+        assert_eq!(name(&name_der).unwrap(), "C=GB, CN=Jane");
+    }
+}
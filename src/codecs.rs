@@ -0,0 +1,268 @@
+use crate::filters::png::{self, FilterType};
+use crate::{Dictionary, Error, Object, Result};
+use flate2::{Decompress, FlushDecompress, Status};
+use log::warn;
+use std::cell::{Cell, RefCell};
+use std::convert::TryFrom;
+use std::io;
+use weezl::{decode::Decoder, BitOrder};
+
+/// Spare buffers kept for the output of filters, and the largest capacity kept, so that decoding a large stream
+/// does not hold its memory afterwards.
+const SPARE_BUFFERS: usize = 4;
+const SPARE_CAPACITY: usize = 1 << 20;
+
+/// The state of the decoding filters, kept from one stream to the next: the flate and LZW decompressors and spare
+/// buffers for the output of filters and predictors. Decoding then takes little more than one allocation for the
+/// decoded content, which counts when decoding tens of thousands of small streams such as cross-reference streams
+/// and `/ToUnicode` maps.
+///
+/// Each thread has its own, which [`Stream::decompressed_content`](crate::Stream::decompressed_content), the
+/// reader and image extraction use. A caller may hold its own and pass it to the `_with` methods of
+/// [`Stream`](crate::Stream).
+#[derive(Default)]
+pub struct Codecs {
+    inflater: Cell<Option<Decompress>>,
+    lzw: Cell<Option<(bool, Decoder)>>,
+    buffers: RefCell<Vec<Vec<u8>>>,
+}
+
+impl Codecs {
+    pub fn new() -> Codecs {
+        Codecs::default()
+    }
+
+    /// Run a closure with the codecs of the current thread.
+    pub fn with<R>(f: impl FnOnce(&Codecs) -> R) -> R {
+        thread_local! {
+            static CODECS: Codecs = Codecs::new();
+        }
+        CODECS.with(f)
+    }
+
+    /// The data of a stream decoded by its filters, as [`Stream::decompressed_content`] returns it.
+    pub(crate) fn decode(&self, dict: &Dictionary, content: &[u8]) -> Result<Vec<u8>> {
+        self.decode_buffer(dict, content).map(|buffer| self.detach(buffer))
+    }
+
+    /// Call a closure with the decoded data of a stream, which is then kept for decoding the next one.
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    pub(crate) fn decode_with<R>(&self, dict: &Dictionary, content: &[u8], f: impl FnOnce(&[u8]) -> R) -> Result<R> {
+        let buffer = self.decode_buffer(dict, content)?;
+        let result = f(&buffer);
+        self.recycle(buffer);
+        Ok(result)
+    }
+
+    fn decode_buffer(&self, dict: &Dictionary, content: &[u8]) -> Result<Vec<u8>> {
+        let params = dict.lookup(b"DecodeParms").and_then(|params| params.as_dict().ok());
+        let filters = match dict.get(b"Filter")? {
+            Object::Array(filters) => filters.as_slice(),
+            filter => std::slice::from_ref(filter),
+        };
+        if let Some(filter) = filters.iter().find(|filter| filter.as_name().is_err()) {
+            return Err(Error::type_mismatch("Name", filter.enum_variant()).at_key(b"Filter"));
+        }
+
+        if dict.lookup(b"Subtype").and_then(|subtype| subtype.as_name().ok()) == Some(b"Image") {
+            return Err(Error::Unimplemented("decoding images as streams"));
+        }
+
+        let span = span!(
+            TRACE,
+            "decompress",
+            filters = filters.len(),
+            bytes = content.len(),
+            decoded_bytes = tracing::field::Empty
+        );
+        // Filters are in decoding order.
+        let mut output: Option<Vec<u8>> = None;
+        for filter in filters {
+            let input = output.as_deref().unwrap_or(content);
+            let decoded = match filter.as_name()? {
+                b"FlateDecode" => self.flate_decode(input, params)?,
+                b"LZWDecode" => self.lzw_decode(input, params)?,
+                _ => return Err(Error::Unimplemented("stream filter")),
+            };
+            if let Some(previous) = output.replace(decoded) {
+                self.recycle(previous);
+            }
+        }
+        let output = output.ok_or_else(|| Error::type_mismatch("Name", "empty Array").at_key(b"Filter"))?;
+        span.record("decoded_bytes", output.len());
+        Ok(output)
+    }
+
+    /// Inflate data and undo its predictor. The output is one of the spare buffers, to be given back with
+    /// [`Codecs::recycle`] or [`Codecs::detach`].
+    pub(crate) fn flate_decode(&self, input: &[u8], params: Option<&Dictionary>) -> Result<Vec<u8>> {
+        let mut output = self.buffer();
+        if !input.is_empty() {
+            let mut inflater = self.inflater.take().unwrap_or_else(|| Decompress::new(true));
+            output.reserve(input.len() * 2);
+            loop {
+                let (read, written) = (inflater.total_in(), inflater.total_out());
+                let status = inflater.decompress_vec(&input[read as usize..], &mut output, FlushDecompress::None);
+                let progress = inflater.total_in() != read || inflater.total_out() != written;
+                match status {
+                    Ok(Status::StreamEnd) => break,
+                    Ok(_) if output.len() == output.capacity() => output.reserve(output.capacity()),
+                    // The data is truncated, and what could be decoded is kept.
+                    Ok(_) if !progress => {
+                        repair!(decoded_bytes = output.len(), "truncated flate data decoded as far as it goes");
+                        break;
+                    }
+                    Ok(_) => {}
+                    Err(err) => {
+                        repair!(decoded_bytes = output.len(), error = %err, "invalid flate data decoded up to the error");
+                        warn!("{}", err);
+                        break;
+                    }
+                }
+            }
+            inflater.reset(true);
+            self.inflater.set(Some(inflater));
+        }
+        self.predict(&mut output, params)?;
+        Ok(output)
+    }
+
+    /// Decode LZW data and undo its predictor, into one of the spare buffers.
+    pub(crate) fn lzw_decode(&self, input: &[u8], params: Option<&Dictionary>) -> Result<Vec<u8>> {
+        const MIN_BITS: u8 = 9;
+
+        let early_change = params
+            .and_then(|p| p.lookup(b"EarlyChange"))
+            .and_then(|p| Object::as_i64(p).ok())
+            .map(|v| v != 0)
+            .unwrap_or(true);
+
+        let mut decoder = match self.lzw.take() {
+            Some((kept_early_change, mut decoder)) if kept_early_change == early_change => {
+                decoder.reset();
+                decoder
+            }
+            _ if early_change => Decoder::with_tiff_size_switch(BitOrder::Msb, MIN_BITS - 1),
+            _ => Decoder::new(BitOrder::Msb, MIN_BITS - 1),
+        };
+        let mut output = self.buffer();
+        if let Err(err) = decoder.into_vec(&mut output).decode_all(input).status {
+            repair!(decoded_bytes = output.len(), error = %err, "invalid LZW data decoded up to the error");
+            warn!("{}", err);
+        }
+        self.lzw.set(Some((early_change, decoder)));
+        self.predict(&mut output, params)?;
+        Ok(output)
+    }
+
+    /// Undo the PNG predictors in place, removing the filter type byte of each row.
+    fn predict(&self, data: &mut Vec<u8>, params: Option<&Dictionary>) -> Result<()> {
+        let params = match params {
+            Some(params) => params,
+            None => return Ok(()),
+        };
+        let number = |key: &[u8], default: i64| params.lookup(key).and_then(|n| n.as_i64().ok()).unwrap_or(default);
+        let predictor = number(b"Predictor", 1);
+        if !(10..=15).contains(&predictor) {
+            return Ok(());
+        }
+        let pixels_per_row = number(b"Columns", 1) as usize;
+        let colors = number(b"Colors", 1) as usize;
+        let bits = number(b"BitsPerComponent", 8) as usize;
+        let bytes_per_pixel = colors * bits / 8;
+        let row_len = bytes_per_pixel * pixels_per_row;
+
+        if !data.len().is_multiple_of(row_len + 1) {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated PNG row").into());
+        }
+        let rows = data.len() / (row_len + 1);
+        let mut zeros = self.buffer();
+        zeros.resize(row_len, 0);
+        for row in 0..rows {
+            let start = row * (row_len + 1);
+            let filter = FilterType::try_from(data[start]).map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid PNG filter type ({})", data[start]),
+                )
+            })?;
+            // Each row moves back by one byte per row above it, after the row above has been decoded.
+            data.copy_within(start + 1..start + 1 + row_len, row * row_len);
+            let (decoded, current) = data.split_at_mut(row * row_len);
+            let previous = match row {
+                0 => &zeros[..],
+                _ => &decoded[(row - 1) * row_len..],
+            };
+            png::decode_row(filter, bytes_per_pixel, previous, &mut current[..row_len]);
+        }
+        data.truncate(rows * row_len);
+        self.recycle(zeros);
+        Ok(())
+    }
+
+    /// A spare buffer, empty.
+    fn buffer(&self) -> Vec<u8> {
+        self.buffers.borrow_mut().pop().unwrap_or_default()
+    }
+
+    /// Keep a buffer for the output of later filters.
+    pub(crate) fn recycle(&self, mut buffer: Vec<u8>) {
+        let mut buffers = self.buffers.borrow_mut();
+        if buffer.capacity() <= SPARE_CAPACITY && buffers.len() < SPARE_BUFFERS {
+            buffer.clear();
+            buffers.push(buffer);
+        }
+    }
+
+    /// The data of a buffer with no more capacity than it needs, the buffer being kept if it is small enough.
+    pub(crate) fn detach(&self, buffer: Vec<u8>) -> Vec<u8> {
+        if buffer.capacity() > SPARE_CAPACITY {
+            return buffer;
+        }
+        let data = buffer.to_vec();
+        self.recycle(buffer);
+        data
+    }
+}
+
+#[cfg(all(test, any(feature = "pom_parser", feature = "nom_parser")))]
+mod tests {
+    use super::*;
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    fn deflate(data: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn codecs_reused_between_streams() {
+        let codecs = Codecs::new();
+        let flate = dictionary! { "Filter" => "FlateDecode" };
+        for i in 0..10 {
+            let data = format!("{} 0 0 {} re f ", i, i * 3).repeat(i + 1);
+            assert_eq!(
+                codecs.decode(&flate, &deflate(data.as_bytes())).unwrap(),
+                data.as_bytes()
+            );
+        }
+        // A truncated stream decodes to what it holds.
+        let data: Vec<u8> = (0..10_000).map(|i| (i * i % 251) as u8).collect();
+        let compressed = deflate(&data);
+        let truncated = codecs.decode(&flate, &compressed[..compressed.len() / 2]).unwrap();
+        assert!(!truncated.is_empty() && data.starts_with(&truncated));
+
+        // Rows of two bytes, with the Sub, Up and Paeth predictors.
+        let predicted = dictionary! {
+            "Filter" => "FlateDecode",
+            "DecodeParms" => dictionary! { "Predictor" => 12, "Columns" => 2 },
+        };
+        let rows = [1, 5, 1, 2, 1, 1, 4, 0, 0];
+        let decoded = codecs.decode_with(&predicted, &deflate(&rows), <[u8]>::to_vec).unwrap();
+        assert_eq!(decoded, [5, 6, 6, 7, 6, 7]);
+        assert!(codecs.decode(&predicted, &deflate(&rows[..8])).is_err());
+    }
+}
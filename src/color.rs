@@ -0,0 +1,214 @@
+use crate::content::Operation;
+use crate::Object;
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// A color in one of the device color spaces, with components between 0 and 1.
+///
+/// Colors are written with their components clamped to that range, as the operators setting them and the arrays of
+/// annotation `/C` and `/IC` entries, which tell the color space by their number of components.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Color {
+    Gray(f32),
+    Rgb(f32, f32, f32),
+    Cmyk(f32, f32, f32, f32),
+}
+
+impl Color {
+    pub const BLACK: Color = Color::Gray(0.0);
+    pub const WHITE: Color = Color::Gray(1.0);
+
+    /// The color with the given gray, RGB or CMYK components.
+    pub fn from_components(components: &[f32]) -> Option<Color> {
+        match *components {
+            [gray] => Some(Color::Gray(gray)),
+            [r, g, b] => Some(Color::Rgb(r, g, b)),
+            [c, m, y, k] => Some(Color::Cmyk(c, m, y, k)),
+            _ => None,
+        }
+    }
+
+    /// The color of an array of one, three or four numbers, as in annotation `/C` and `/IC` entries, or `None` for
+    /// an empty array, which is transparent, and for anything else.
+    pub fn from_object(object: &Object) -> Option<Color> {
+        let components = object
+            .as_array()
+            .ok()?
+            .iter()
+            .map(|component| component.as_float().ok().map(|component| component as f32))
+            .collect::<Option<Vec<f32>>>()?;
+        Color::from_components(&components)
+    }
+
+    pub fn components(&self) -> Vec<f32> {
+        match *self {
+            Color::Gray(gray) => vec![gray],
+            Color::Rgb(r, g, b) => vec![r, g, b],
+            Color::Cmyk(c, m, y, k) => vec![c, m, y, k],
+        }
+    }
+
+    /// Whether all components are between 0 and 1.
+    pub fn is_valid(&self) -> bool {
+        self.components()
+            .iter()
+            .all(|component| (0.0..=1.0).contains(component))
+    }
+
+    /// The color with its components brought between 0 and 1, and those that are not numbers set to 0.
+    pub fn clamped(&self) -> Color {
+        let clamp = |component: f32| {
+            if component.is_nan() {
+                0.0
+            } else {
+                component.clamp(0.0, 1.0)
+            }
+        };
+        match *self {
+            Color::Gray(gray) => Color::Gray(clamp(gray)),
+            Color::Rgb(r, g, b) => Color::Rgb(clamp(r), clamp(g), clamp(b)),
+            Color::Cmyk(c, m, y, k) => Color::Cmyk(clamp(c), clamp(m), clamp(y), clamp(k)),
+        }
+    }
+
+    /// The gray level of the color, with the conversions of the PDF specification.
+    pub fn to_gray(&self) -> f32 {
+        match self.clamped() {
+            Color::Gray(gray) => gray,
+            Color::Rgb(r, g, b) => 0.3 * r + 0.59 * g + 0.11 * b,
+            Color::Cmyk(c, m, y, k) => 1.0 - (0.3 * c + 0.59 * m + 0.11 * y + k).min(1.0),
+        }
+    }
+
+    /// The RGB components of the color, with the conversions of the PDF specification.
+    pub fn to_rgb(&self) -> [f32; 3] {
+        match self.clamped() {
+            Color::Gray(gray) => [gray; 3],
+            Color::Rgb(r, g, b) => [r, g, b],
+            Color::Cmyk(c, m, y, k) => [1.0 - (c + k).min(1.0), 1.0 - (m + k).min(1.0), 1.0 - (y + k).min(1.0)],
+        }
+    }
+
+    /// The CMYK components of the color, with the conversions of the PDF specification, which take all of the
+    /// black the color has out of the other components.
+    pub fn to_cmyk(&self) -> [f32; 4] {
+        match self.clamped() {
+            Color::Gray(gray) => [0.0, 0.0, 0.0, 1.0 - gray],
+            Color::Rgb(r, g, b) => {
+                let (c, m, y) = (1.0 - r, 1.0 - g, 1.0 - b);
+                let k = c.min(m).min(y);
+                [c - k, m - k, y - k, k]
+            }
+            Color::Cmyk(c, m, y, k) => [c, m, y, k],
+        }
+    }
+
+    /// The operator setting the color for filling, or for stroking: `g`, `rg` or `k`, or `G`, `RG` or `K`.
+    pub fn operator(&self, stroke: bool) -> &'static str {
+        match (self, stroke) {
+            (Color::Gray(_), false) => "g",
+            (Color::Gray(_), true) => "G",
+            (Color::Rgb(..), false) => "rg",
+            (Color::Rgb(..), true) => "RG",
+            (Color::Cmyk(..), false) => "k",
+            (Color::Cmyk(..), true) => "K",
+        }
+    }
+
+    /// The operation setting the color for filling.
+    pub fn fill_operation(&self) -> Operation {
+        Operation::new(self.operator(false), self.operands())
+    }
+
+    /// The operation setting the color for stroking.
+    pub fn stroke_operation(&self) -> Operation {
+        Operation::new(self.operator(true), self.operands())
+    }
+
+    /// The array of the components of the color, for annotation `/C` and `/IC` entries.
+    pub fn to_object(&self) -> Object {
+        Object::Array(self.operands())
+    }
+
+    /// The clamped components as reals.
+    fn operands(&self) -> Vec<Object> {
+        self.clamped()
+            .components()
+            .into_iter()
+            .map(|component| Object::Real(widened(component)))
+            .collect()
+    }
+}
+
+/// A component as the real written as short as the component is: widening it exactly would write 0.8 as
+/// 0.800000011920929.
+pub(crate) fn widened(component: f32) -> f64 {
+    component.to_string().parse().unwrap_or(0.0)
+}
+
+impl From<[f32; 3]> for Color {
+    fn from([r, g, b]: [f32; 3]) -> Self {
+        Color::Rgb(r, g, b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_colors() {
+        assert_eq!(Color::Gray(0.25).to_rgb(), [0.25; 3]);
+        assert_eq!(Color::Gray(0.25).to_cmyk(), [0.0, 0.0, 0.0, 0.75]);
+        assert_eq!(Color::Rgb(1.0, 0.5, 0.0).to_cmyk(), [0.0, 0.5, 1.0, 0.0]);
+        assert_eq!(Color::Cmyk(0.0, 0.5, 1.0, 0.0).to_rgb(), [1.0, 0.5, 0.0]);
+        assert_eq!(Color::Cmyk(0.5, 0.0, 0.0, 0.75).to_rgb(), [0.0, 0.25, 0.25]);
+        assert!((Color::Rgb(1.0, 1.0, 1.0).to_gray() - 1.0).abs() < 1e-6);
+        assert_eq!(Color::Cmyk(0.0, 0.0, 0.0, 1.0).to_gray(), 0.0);
+
+        let out_of_range = Color::Rgb(1.5, -0.2, f32::NAN);
+        assert!(!out_of_range.is_valid());
+        assert_eq!(out_of_range.clamped(), Color::Rgb(1.0, 0.0, 0.0));
+        assert!(out_of_range.clamped().is_valid());
+    }
+
+    #[test]
+    fn write_and_read_colors() {
+        let table = [
+            (Color::Gray(0.5), "g", "G"),
+            (Color::Rgb(0.8, 0.1, 0.0), "rg", "RG"),
+            (Color::Cmyk(0.0, 0.2, 1.0, 0.3), "k", "K"),
+        ];
+        for (color, fill, stroke) in table {
+            let operation = color.fill_operation();
+            assert_eq!(operation.operator, fill);
+            let operands: Vec<f64> = operation.operands.iter().map(|c| c.as_float().unwrap()).collect();
+            let expected: Vec<f64> = color
+                .components()
+                .iter()
+                .map(|c| c.to_string().parse().unwrap())
+                .collect();
+            assert_eq!(operands, expected);
+            assert_eq!(color.stroke_operation().operator, stroke);
+            assert_eq!(Color::from_object(&color.to_object()), Some(color));
+        }
+        assert_eq!(
+            Color::Rgb(0.8, 0.1, 2.0).to_object().as_array().unwrap()[0]
+                .as_float()
+                .unwrap(),
+            0.8
+        );
+        assert_eq!(
+            Color::Rgb(0.8, 0.1, 2.0).to_object().as_array().unwrap()[2]
+                .as_float()
+                .unwrap(),
+            1.0
+        );
+
+        assert_eq!(Color::from_object(&Object::Array(vec![])), None);
+        assert_eq!(Color::from_object(&Object::Array(vec![0.into(), 1.into()])), None);
+        assert_eq!(Color::from_object(&Object::Array(vec![1.into()])), Some(Color::WHITE));
+        assert_eq!(Color::from_object(&Object::Integer(1)), None);
+    }
+}
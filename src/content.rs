@@ -1,19 +1,31 @@
 use super::Object;
-use crate::writer::Writer;
+use crate::color::Color;
+use crate::writer::{CountingWrite, Writer};
 use crate::Result;
-use std::io::Write;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use smallvec::SmallVec;
+
+/// The operands of an operation, held without allocating up to two, as for the most frequent text operators `Tf`,
+/// `Td` and `Tj`. Inline room for more would make every operation larger, which slows parsing more than the
+/// allocations it saves.
+///
+/// They were a `Vec`, which a [`SmallVec`] reads and changes as, but a `Vec` now takes `.into()` to be assigned
+/// to them.
+pub type Operands = SmallVec<[Object; 2]>;
 
 #[derive(Debug, Clone)]
 pub struct Operation {
     pub operator: String,
-    pub operands: Vec<Object>,
+    pub operands: Operands,
 }
 
 impl Operation {
     pub fn new(operator: &str, operands: Vec<Object>) -> Operation {
         Operation {
             operator: operator.to_string(),
-            operands,
+            operands: operands.into(),
         }
     }
 }
@@ -23,22 +35,99 @@ pub struct Content<Operations: AsRef<[Operation]> = Vec<Operation>> {
     pub operations: Operations,
 }
 
+impl Content<Vec<Operation>> {
+    /// Set the fill color and the device color space of its components, with `g`, `rg` or `k`.
+    pub fn set_fill_color(&mut self, color: Color) {
+        self.operations.push(color.fill_operation());
+    }
+
+    /// Set the stroke color and the device color space of its components, with `G`, `RG` or `K`.
+    pub fn set_stroke_color(&mut self, color: Color) {
+        self.operations.push(color.stroke_operation());
+    }
+
+    /// Paint a shading of the `/Shading` resources over the clipping path, `sh`.
+    pub fn paint_shading(&mut self, name: &str) {
+        self.operations.push(Operation::new("sh", vec![Object::from(name)]));
+    }
+
+    /// Fill with a pattern of the `/Pattern` resources, such as a shading pattern: the Pattern color space, `cs`,
+    /// and the pattern as the fill color, `scn`.
+    pub fn set_fill_pattern(&mut self, name: &str) {
+        self.operations.push(Operation::new("cs", vec![Object::from("Pattern")]));
+        self.operations.push(Operation::new("scn", vec![Object::from(name)]));
+    }
+}
+
 impl<Operations: AsRef<[Operation]>> Content<Operations> {
     /// Encode content operations.
     pub fn encode(&self) -> Result<Vec<u8>> {
-        let mut inner_buffer = vec![];
-        let mut buffer = super::writer::CountingWrite {
-            inner: &mut inner_buffer,
-            bytes_written: 0
+        let mut buffer = Vec::new();
+        self.encode_into(&mut buffer)?;
+        Ok(buffer)
+    }
+
+    /// Encode content operations at the end of a buffer, so that one buffer can be reused to encode many contents.
+    pub fn encode_into(&self, buffer: &mut Vec<u8>) -> Result<()> {
+        let operations = self.operations.as_ref();
+        // Most operations are a few numbers and an operator.
+        buffer.reserve(operations.len() * 16);
+        let write_object = |buffer: &mut Vec<u8>, object: &Object| {
+            let start = buffer.len();
+            Writer::write_object(
+                &mut CountingWrite {
+                    inner: buffer,
+                    bytes_written: start as u64,
+                },
+                object,
+                None,
+                None,
+            )
+            .map(|_| ())
         };
-        for operation in self.operations.as_ref() {
+        for operation in operations {
+            // An inline image, parsed with its dictionary and data as the stream operand of `BI`.
+            if let ("BI", [Object::Stream(image)]) = (operation.operator.as_str(), operation.operands.as_slice()) {
+                buffer.extend_from_slice(b"BI\n");
+                for (key, value) in image.dict.iter() {
+                    write_object(buffer, &Object::Name(key.clone()))?;
+                    buffer.push(b' ');
+                    write_object(buffer, value)?;
+                    buffer.push(b'\n');
+                }
+                buffer.extend_from_slice(b"ID\n");
+                buffer.extend_from_slice(&image.content);
+                buffer.extend_from_slice(b"\nEI\n");
+                continue;
+            }
             for operand in &operation.operands {
-                Writer::write_object(&mut buffer, operand, None, None)?;
-                buffer.write_all(b" ")?;
+                write_object(buffer, operand)?;
+                buffer.push(b' ');
             }
-            buffer.write_all(operation.operator.as_bytes())?;
-            buffer.write_all(b"\n")?;
+            buffer.extend_from_slice(operation.operator.as_bytes());
+            buffer.push(b'\n');
         }
-        Ok(inner_buffer)
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_into_buffer() {
+        let content = Content {
+            operations: vec![
+                Operation::new("BT", vec![]),
+                Operation::new("Tf", vec!["F1".into(), 12.into()]),
+                Operation::new("Tj", vec![Object::string_literal("Hello")]),
+                Operation::new("ET", vec![]),
+            ],
+        };
+        let mut buffer = b"q\n".to_vec();
+        content.encode_into(&mut buffer).unwrap();
+        assert_eq!(buffer, b"q\nBT\n/F1 12 Tf\n(Hello) Tj\nET\n");
+        assert_eq!(buffer[2..], content.encode().unwrap()[..]);
     }
 }
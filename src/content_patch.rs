@@ -0,0 +1,574 @@
+//! Differences between content streams as edits of their operations, and pages that share the content of another.
+
+use crate::content::{Content, Operation};
+use crate::Object;
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+use crate::{
+    document::fingerprint, geometry::Rect, ContentDecodeOptions, Dictionary, Document, ObjectId, Result, Stream,
+};
+
+/// Reals, and integers compared with reals, that differ by no more than this are taken as the same, as coordinates
+/// written again with another precision.
+const REAL_TOLERANCE: f64 = 1e-3;
+
+/// The largest number of operations deleted and inserted that the differences are looked for in detail for:
+/// beyond it, the operations between the start and the end the contents share are replaced whole.
+const MAX_EDIT_COST: usize = 1024;
+
+/// An edit of a run of the operations of a content, at the index of the first one.
+#[derive(Debug, Clone)]
+pub enum ContentEdit {
+    /// Insert operations before the one at `at`, or after the last if `at` is the number of operations.
+    Insert { at: usize, operations: Vec<Operation> },
+    /// Delete `count` operations.
+    Delete { at: usize, count: usize },
+    /// Replace `count` operations with others.
+    Replace {
+        at: usize,
+        count: usize,
+        operations: Vec<Operation>,
+    },
+}
+
+impl ContentEdit {
+    /// The index of the first operation edited, the number of operations removed and the operations inserted.
+    fn parts(&self) -> (usize, usize, &[Operation]) {
+        match self {
+            ContentEdit::Insert { at, operations } => (*at, 0, operations),
+            ContentEdit::Delete { at, count } => (*at, *count, &[]),
+            ContentEdit::Replace { at, count, operations } => (*at, *count, operations),
+        }
+    }
+}
+
+/// The edits turning the operations of a content into those of another, from [`Content::diff`], in the order of
+/// the operations they edit.
+#[derive(Debug, Clone, Default)]
+pub struct ContentPatch {
+    pub edits: Vec<ContentEdit>,
+}
+
+impl ContentPatch {
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+
+    /// The content edited. Edits out of order, overlapping the edits before them or past the end of the content
+    /// are applied after the edits before them, or at the end.
+    pub fn apply(&self, base: &Content) -> Content {
+        let base = &base.operations;
+        let mut operations = Vec::with_capacity(base.len());
+        let mut next = 0;
+        for edit in &self.edits {
+            let (at, count, inserted) = edit.parts();
+            let at = at.clamp(next, base.len());
+            operations.extend_from_slice(&base[next..at]);
+            operations.extend_from_slice(inserted);
+            next = at.saturating_add(count).min(base.len());
+        }
+        operations.extend_from_slice(&base[next..]);
+        Content { operations }
+    }
+}
+
+impl Content<Vec<Operation>> {
+    /// The edits of the operations of `base` that give those of `derived`, as few as the Myers difference
+    /// algorithm finds. Operations are compared by their operator and operands, reals within a tolerance of
+    /// coordinate jitter, so that the operations kept are those of the base, which may differ that much from the
+    /// derived content.
+    pub fn diff(base: &Content, derived: &Content) -> ContentPatch {
+        let (base, derived) = (&base.operations[..], &derived.operations[..]);
+        let prefix = base
+            .iter()
+            .zip(derived)
+            .take_while(|(a, b)| same_operation(a, b))
+            .count();
+        let suffix = base[prefix..]
+            .iter()
+            .rev()
+            .zip(derived[prefix..].iter().rev())
+            .take_while(|(a, b)| same_operation(a, b))
+            .count();
+        let (base_middle, derived_middle) = (
+            &base[prefix..base.len() - suffix],
+            &derived[prefix..derived.len() - suffix],
+        );
+        let steps = edit_steps(base_middle, derived_middle).unwrap_or_else(|| {
+            let mut steps = vec![Step::Delete; base_middle.len()];
+            steps.extend((0..derived_middle.len()).map(|_| Step::Insert));
+            steps
+        });
+
+        // Runs of deletions and insertions between the operations kept.
+        let mut edits = Vec::new();
+        let (mut a, mut b) = (0, 0);
+        let mut steps = steps.into_iter().peekable();
+        while let Some(step) = steps.next() {
+            if step == Step::Keep {
+                a += 1;
+                b += 1;
+                continue;
+            }
+            let (at, from) = (a, b);
+            let mut step = Some(step);
+            while let Some(current) = step.filter(|&step| step != Step::Keep) {
+                match current {
+                    Step::Delete => a += 1,
+                    _ => b += 1,
+                }
+                step = steps.next_if(|&step| step != Step::Keep);
+            }
+            let (count, operations) = (a - at, derived_middle[from..b].to_vec());
+            let at = prefix + at;
+            edits.push(match (count, operations.is_empty()) {
+                (0, _) => ContentEdit::Insert { at, operations },
+                (_, true) => ContentEdit::Delete { at, count },
+                _ => ContentEdit::Replace { at, count, operations },
+            });
+        }
+        ContentPatch { edits }
+    }
+}
+
+/// Operators whose effect on the graphics state outlasts the operation, outside any `q` and `Q`.
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+const STATE_OPERATORS: [&str; 28] = [
+    "cm", "w", "J", "j", "M", "d", "ri", "i", "gs", "CS", "cs", "SC", "SCN", "sc", "scn", "G", "g", "RG", "rg", "K",
+    "k", "Tc", "Tw", "Tz", "TL", "Tf", "Tr", "Ts",
+];
+
+/// A page whose content other pages draw as a form XObject, with the pages that do.
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+struct Template {
+    content: Content,
+    resources: Dictionary,
+    fingerprint: [u8; 16],
+    /// The pages, the template included, with the operations they draw before and after it.
+    pages: Vec<(ObjectId, Vec<Operation>, Vec<Operation>)>,
+}
+
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+impl Document {
+    /// Store the content that pages share with a page before them as a form XObject that each of them draws,
+    /// between operations of its own, so that the content is written once.
+    ///
+    /// A page shares the content of an earlier one when it has the same resources and its operations are those of
+    /// the earlier page, as [`Content::diff`] compares them, with operations added before or after them, which make
+    /// no more than `1 - threshold` of its operations. The shared content is drawn with the graphics state its
+    /// page starts with, and the state it leaves is set again for the operations after it; content that clips
+    /// outside `q` and `Q`, is not balanced or cannot be parsed is not shared. Returns the number of pages drawing
+    /// a shared form XObject; their previous content streams are left for [`Document::prune_objects`].
+    pub fn deduplicate_page_content(&mut self, threshold: f64) -> Result<usize> {
+        let mut templates: Vec<Template> = Vec::new();
+        let pages: Vec<ObjectId> = self.page_iter().collect();
+        for page_id in pages {
+            let data = match self.get_page_content(page_id) {
+                Ok(data) => data,
+                Err(_) => continue,
+            };
+            let content = match Content::decode_with(&data, &ContentDecodeOptions { strict: true }) {
+                Ok(decoded) if !decoded.content.operations.is_empty() => decoded.content,
+                _ => continue,
+            };
+            let resources = self.effective_page_resources(page_id);
+            let resources_fingerprint = fingerprint(&Object::Dictionary(resources.clone()));
+            let shared = templates
+                .iter_mut()
+                .filter(|template| template.fingerprint == resources_fingerprint)
+                .find_map(|template| Some((template.overlay(&content, threshold)?, template)));
+            match shared {
+                Some(((before, after), template)) => template.pages.push((page_id, before, after)),
+                None if shareable(&content.operations) => templates.push(Template {
+                    content,
+                    resources,
+                    fingerprint: resources_fingerprint,
+                    pages: vec![(page_id, Vec::new(), Vec::new())],
+                }),
+                None => {}
+            }
+        }
+
+        let mut count = 0;
+        for template in templates.into_iter().filter(|template| template.pages.len() > 1) {
+            let bounds = template
+                .pages
+                .iter()
+                .filter_map(|&(page_id, _, _)| self.get_page_media_box(page_id))
+                .reduce(|bounds, media_box| bounds.union(&media_box))
+                .unwrap_or(Rect::new(0.0, 0.0, 612.0, 792.0));
+            let form = dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Form",
+                "BBox" => bounds,
+                "Resources" => template.resources.clone(),
+            };
+            let form_id = self.add_object(Stream::new(form, template.content.encode()?));
+            let state = template.final_state();
+            for (page_id, before, after) in template.pages {
+                let name = self.add_page_xobject(page_id, form_id)?;
+                let mut operations = before;
+                operations.push(Operation::new("Do", vec![Object::Name(name)]));
+                if !after.is_empty() {
+                    operations.extend(state.iter().cloned());
+                    operations.extend(after);
+                }
+                let content_id = self.add_object(Stream::new(Dictionary::new(), Content { operations }.encode()?));
+                self.get_object_mut(page_id)?.as_dict_mut()?.set("Contents", content_id);
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// The resources of a page, or those it inherits.
+    fn effective_page_resources(&self, page_id: ObjectId) -> Dictionary {
+        let mut node = self.get_dictionary(page_id).ok();
+        // Bounded, as page trees may have cycles.
+        for _ in 0..64 {
+            let dict = match node {
+                Some(dict) => dict,
+                None => break,
+            };
+            if let Ok(resources) = dict.get_deref(b"Resources", self).and_then(Object::as_dict) {
+                return resources.clone();
+            }
+            node = dict
+                .get(b"Parent")
+                .and_then(Object::as_reference)
+                .and_then(|id| self.get_dictionary(id))
+                .ok();
+        }
+        Dictionary::new()
+    }
+}
+
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+impl Template {
+    /// The operations a content draws before and after the content of the template, if it has no others and they
+    /// can be drawn around a form XObject of it: the ones before leave no text object open and do not change the
+    /// transformation matrix, which the bounding box of the form would otherwise not follow.
+    fn overlay(&self, content: &Content, threshold: f64) -> Option<(Vec<Operation>, Vec<Operation>)> {
+        let shared = self.content.operations.len();
+        if (shared as f64) < threshold * content.operations.len() as f64 {
+            return None;
+        }
+        let (mut before, mut after) = (Vec::new(), Vec::new());
+        for edit in Content::diff(&self.content, content).edits {
+            match edit {
+                ContentEdit::Insert { at: 0, operations } => before = operations,
+                ContentEdit::Insert { at, operations } if at == shared => after = operations,
+                _ => return None,
+            }
+        }
+        let mut in_text = false;
+        for operation in &before {
+            match operation.operator.as_str() {
+                "BT" => in_text = true,
+                "ET" => in_text = false,
+                "cm" => return None,
+                _ => {}
+            }
+        }
+        (!in_text).then_some((before, after))
+    }
+
+    /// The operations of the template that change the graphics state it leaves.
+    fn final_state(&self) -> Vec<Operation> {
+        let mut depth = 0usize;
+        let mut state = Vec::new();
+        for operation in &self.content.operations {
+            match operation.operator.as_str() {
+                "q" => depth += 1,
+                "Q" => depth = depth.saturating_sub(1),
+                operator if depth == 0 && STATE_OPERATORS.contains(&operator) => state.push(operation.clone()),
+                _ => {}
+            }
+        }
+        state
+    }
+}
+
+/// Whether content can be drawn as a form XObject: its `q` and `Q`, text objects and marked-content sequences are
+/// balanced, and it does not clip outside `q` and `Q`, which the state set again after it would not say.
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+fn shareable(operations: &[Operation]) -> bool {
+    let (mut depth, mut marked, mut in_text) = (0usize, 0usize, false);
+    for operation in operations {
+        match operation.operator.as_str() {
+            "q" => depth += 1,
+            "Q" if depth == 0 => return false,
+            "Q" => depth -= 1,
+            "BT" if in_text => return false,
+            "BT" => in_text = true,
+            "ET" if !in_text => return false,
+            "ET" => in_text = false,
+            "BMC" | "BDC" => marked += 1,
+            "EMC" if marked == 0 => return false,
+            "EMC" => marked -= 1,
+            "W" | "W*" if depth == 0 => return false,
+            _ => {}
+        }
+    }
+    depth == 0 && marked == 0 && !in_text
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Keep,
+    Delete,
+    Insert,
+}
+
+/// The steps of the shortest edit script from `a` to `b`, or `None` when it edits more than [`MAX_EDIT_COST`]
+/// operations.
+fn edit_steps(a: &[Operation], b: &[Operation]) -> Option<Vec<Step>> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let limit = (n + m).min(MAX_EDIT_COST as isize);
+    // The furthest index in `a` reached on each diagonal `k = x - y`, offset by `limit + 1`.
+    let offset = limit + 1;
+    let mut furthest = vec![0isize; 2 * offset as usize + 1];
+    // The furthest indices before each step `d`, on the diagonals from `-d` to `d`.
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    let mut found = None;
+    'search: for d in 0..=limit {
+        trace.push(furthest[(offset - d) as usize..=(offset + d) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let index = (offset + k) as usize;
+            let mut x = if k == -d || (k != d && furthest[index - 1] < furthest[index + 1]) {
+                furthest[index + 1]
+            } else {
+                furthest[index - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && same_operation(&a[x as usize], &b[y as usize]) {
+                x += 1;
+                y += 1;
+            }
+            furthest[index] = x;
+            if x >= n && y >= m {
+                found = Some(d);
+                break 'search;
+            }
+        }
+    }
+
+    let mut steps = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for d in (0..=found?).rev() {
+        let before = &trace[d as usize];
+        let at = |k: isize| before[(k + d) as usize];
+        let k = x - y;
+        let previous = if d == 0 {
+            0
+        } else if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let previous_x = if d == 0 { 0 } else { at(previous) };
+        let previous_y = previous_x - previous;
+        while x > previous_x && y > previous_y {
+            steps.push(Step::Keep);
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            steps.push(if x == previous_x { Step::Insert } else { Step::Delete });
+        }
+        x = previous_x;
+        y = previous_y;
+    }
+    steps.reverse();
+    Some(steps)
+}
+
+fn same_operation(a: &Operation, b: &Operation) -> bool {
+    a.operator == b.operator
+        && a.operands.len() == b.operands.len()
+        && a.operands.iter().zip(b.operands.iter()).all(|(a, b)| same_object(a, b))
+}
+
+fn same_object(a: &Object, b: &Object) -> bool {
+    match (a, b) {
+        (Object::Integer(a), Object::Integer(b)) => a == b,
+        (Object::Integer(_), Object::Real(_)) | (Object::Real(_), Object::Integer(_) | Object::Real(_)) => a
+            .as_float()
+            .ok()
+            .zip(b.as_float().ok())
+            .is_some_and(|(a, b)| (a - b).abs() <= REAL_TOLERANCE),
+        (Object::Null, Object::Null) => true,
+        (Object::Boolean(a), Object::Boolean(b)) => a == b,
+        (Object::Name(a), Object::Name(b)) => a == b,
+        (Object::String(a, _), Object::String(b, _)) => a == b,
+        (Object::Reference(a), Object::Reference(b)) => a == b,
+        (Object::Array(a), Object::Array(b)) => a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same_object(a, b)),
+        (Object::Dictionary(a), Object::Dictionary(b)) => same_dictionary(a, b),
+        (Object::Stream(a), Object::Stream(b)) => same_dictionary(&a.dict, &b.dict) && a.content == b.content,
+        _ => false,
+    }
+}
+
+fn same_dictionary(a: &crate::Dictionary, b: &crate::Dictionary) -> bool {
+    a.len() == b.len() && a.iter().all(|(key, a)| b.get(key).is_ok_and(|b| same_object(a, b)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    use crate::creator::tests::create_document;
+
+    fn operators(content: &Content) -> Vec<&str> {
+        content
+            .operations
+            .iter()
+            .map(|operation| operation.operator.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn diff_and_apply_contents() {
+        let operation = |operator: &str, operands: Vec<Object>| Operation::new(operator, operands);
+        let base = Content {
+            operations: vec![
+                operation("q", vec![]),
+                operation("m", vec![10.into(), 20.into()]),
+                operation("l", vec![30.into(), 40.into()]),
+                operation("S", vec![]),
+                operation("Q", vec![]),
+                operation("BT", vec![]),
+                operation("Tj", vec![Object::string_literal("One")]),
+                operation("ET", vec![]),
+            ],
+        };
+        // The coordinates written again with another precision, a deleted stroke, a replaced string and an
+        // inserted operation at the end.
+        let derived = Content {
+            operations: vec![
+                operation("q", vec![]),
+                operation("m", vec![10.0004.into(), Object::Real(20.0)]),
+                operation("l", vec![30.into(), 40.into()]),
+                operation("Q", vec![]),
+                operation("BT", vec![]),
+                operation("Tj", vec![Object::string_literal("Two")]),
+                operation("ET", vec![]),
+                operation("f", vec![]),
+            ],
+        };
+        let patch = Content::diff(&base, &derived);
+        let edits: Vec<_> = patch
+            .edits
+            .iter()
+            .map(ContentEdit::parts)
+            .map(|(at, count, inserted)| {
+                (
+                    at,
+                    count,
+                    inserted
+                        .iter()
+                        .map(|operation| operation.operator.as_str())
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect();
+        assert_eq!(edits, [(3, 1, vec![]), (6, 1, vec!["Tj"]), (8, 0, vec!["f"])]);
+        assert!(matches!(patch.edits[0], ContentEdit::Delete { at: 3, count: 1 }));
+        assert!(matches!(patch.edits[1], ContentEdit::Replace { at: 6, count: 1, .. }));
+        assert!(matches!(patch.edits[2], ContentEdit::Insert { at: 8, .. }));
+
+        let applied = patch.apply(&base);
+        assert_eq!(applied.encode().unwrap(), {
+            let mut expected = derived.clone();
+            // Operations kept are those of the base.
+            expected.operations[1] = base.operations[1].clone();
+            expected.encode().unwrap()
+        });
+        assert!(Content::diff(&base, &base).is_empty());
+        assert_eq!(
+            operators(&Content::diff(&Content { operations: vec![] }, &derived).apply(&Content { operations: vec![] })),
+            operators(&derived)
+        );
+        assert!(Content::diff(&derived, &Content { operations: vec![] })
+            .apply(&derived)
+            .operations
+            .is_empty());
+    }
+
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    #[test]
+    fn deduplicate_pages_sharing_content() {
+        let mut doc = create_document();
+        let pages_id = doc.catalog().unwrap().get(b"Pages").unwrap().as_reference().unwrap();
+        let template = b"q 0 0 1 rg 0 0 595 80 re f Q BT /F1 12 Tf 72 40 Td (Letterhead) Tj ET 0.5 g".to_vec();
+        let mut add_page = |data: &[u8]| {
+            let content_id = doc.add_object(Stream::new(Dictionary::new(), data.to_vec()));
+            let page_id =
+                doc.add_object(dictionary! { "Type" => "Page", "Parent" => pages_id, "Contents" => content_id });
+            let pages = doc.get_object_mut(pages_id).unwrap().as_dict_mut().unwrap();
+            pages
+                .get_mut(b"Kids")
+                .unwrap()
+                .as_array_mut()
+                .unwrap()
+                .push(page_id.into());
+            let count = pages.get(b"Count").unwrap().as_i64().unwrap();
+            pages.set("Count", count + 1);
+            page_id
+        };
+        let first = add_page(&template);
+        let mut second = template.clone();
+        second.extend_from_slice(b" BT /F1 10 Tf 72 700 Td (Page two) Tj ET");
+        let second = add_page(&second);
+        let mut third = b"0 0 1 RG ".to_vec();
+        third.extend_from_slice(&template);
+        let third = add_page(&third);
+        // Mostly content of its own.
+        let mut fourth = template.clone();
+        fourth.extend_from_slice(&b" 0 0 m 1 1 l S".repeat(10));
+        let fourth = add_page(&fourth);
+
+        assert_eq!(doc.deduplicate_page_content(0.5).unwrap(), 3);
+        let form_of = |doc: &Document, page_id| {
+            let content = doc.get_and_decode_page_content(page_id).unwrap();
+            let name = content
+                .operations
+                .iter()
+                .find(|operation| operation.operator == "Do")
+                .map(|operation| operation.operands[0].as_name().unwrap().to_vec())?;
+            let resources = doc
+                .get_dictionary(page_id)
+                .unwrap()
+                .get_deref(b"Resources", doc)
+                .unwrap()
+                .as_dict()
+                .unwrap();
+            let form_id = resources
+                .get(b"XObject")
+                .unwrap()
+                .as_dict()
+                .unwrap()
+                .get(&name)
+                .unwrap()
+                .as_reference()
+                .unwrap();
+            Some((form_id, content))
+        };
+        let (form_id, content) = form_of(&doc, first).unwrap();
+        assert_eq!(operators(&content), ["Do"]);
+        let form = doc.get_object(form_id).unwrap().as_stream().unwrap();
+        assert_eq!(
+            Content::decode(&form.content).unwrap().encode().unwrap(),
+            Content::decode(&template).unwrap().encode().unwrap()
+        );
+        assert!(form.dict.get(b"Resources").unwrap().as_dict().unwrap().has(b"Font"));
+
+        let (second_form, content) = form_of(&doc, second).unwrap();
+        assert_eq!(second_form, form_id);
+        // The font and the color the shared content leaves are set again for the text after it.
+        assert_eq!(operators(&content), ["Do", "Tf", "g", "BT", "Tf", "Td", "Tj", "ET"]);
+        let (third_form, content) = form_of(&doc, third).unwrap();
+        assert_eq!(third_form, form_id);
+        assert_eq!(operators(&content), ["RG", "Do"]);
+        assert!(form_of(&doc, fourth).is_none());
+    }
+}
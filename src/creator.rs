@@ -11,14 +11,12 @@ impl Document {
 
     /// Create an object ID.
     pub fn new_object_id(&mut self) -> ObjectId {
-        self.max_id += 1;
-        (self.max_id, 0)
+        self.allocate_id()
     }
 
     /// Add PDF object into document's object list.
     pub fn add_object<T: Into<Object>>(&mut self, object: T) -> ObjectId {
-        self.max_id += 1;
-        let id = (self.max_id, 0);
+        let id = self.allocate_id();
         self.objects.insert(id, object.into());
         id
     }
@@ -44,9 +42,9 @@ impl Document {
     fn get_or_create_resources_mut(&mut self, page_id: ObjectId) -> Result<&mut Object> {
         let page = self.get_object_mut(page_id).and_then(Object::as_dict_mut)?;
         if page.has(b"Resources") {
-            if let Ok(_res_id) = page.get(b"Resources").and_then(Object::as_reference) {
+            if let Ok(res_id) = page.get(b"Resources").and_then(Object::as_reference) {
                 // self.get_object_mut(res_id)
-                Err(Error::ObjectNotFound)
+                Err(Error::not_found(res_id))
             } else {
                 page.get_mut(b"Resources")
             }
@@ -70,47 +68,105 @@ impl Document {
         }
     }
 
-    pub fn add_xobject<N: Into<Vec<u8>>>(
-        &mut self,
-        page_id: ObjectId,
-        xobject_name: N,
-        xobject_id: ObjectId,
-    ) -> Result<()> {
-        if let Ok(resources) = self
-            .get_or_create_resources(page_id)
-            .and_then(Object::as_dict_mut)
-        {
-            if !resources.has(b"XObject") {
-                resources.set("XObject", Dictionary::new());
+    /// Give a page resources of its own, so that adding resources for it leaves other pages alone, and return
+    /// their object. Resources that the page inherits, or that other pages, page tree nodes or form XObjects also
+    /// use, are copied with their subdictionaries; the fonts, images and other objects they name stay shared.
+    pub fn isolate_page_resources(&mut self, page_id: ObjectId) -> Result<ObjectId> {
+        let mut resources = match self.get_dictionary(page_id)?.get(b"Resources") {
+            Ok(Object::Reference(id)) => {
+                let id = *id;
+                let resources = self.get_dictionary(id)?;
+                let shared = self.objects.iter().any(|(&user, object)| {
+                    let dict = match object {
+                        Object::Dictionary(dict) => dict,
+                        Object::Stream(stream) => &stream.dict,
+                        _ => return false,
+                    };
+                    user != page_id && dict.get(b"Resources").and_then(Object::as_reference).ok() == Some(id)
+                });
+                let shared_categories = resources
+                    .iter()
+                    .any(|(_, category)| self.is_dictionary_reference(category));
+                if !shared && !shared_categories {
+                    return Ok(id);
+                }
+                resources.clone()
+            }
+            Ok(Object::Dictionary(resources)) => resources.clone(),
+            _ => {
+                let mut inherited = Dictionary::new();
+                let mut node = self.get_dictionary(page_id)?;
+                // Bounded, as page trees may have cycles.
+                for _ in 0..64 {
+                    if let Ok(resources) = node.get_deref(b"Resources", self).and_then(Object::as_dict) {
+                        inherited = resources.clone();
+                        break;
+                    }
+                    match node.get(b"Parent").and_then(Object::as_reference) {
+                        Ok(parent) => node = self.get_dictionary(parent)?,
+                        Err(_) => break,
+                    }
+                }
+                inherited
+            }
+        };
+        for (_, category) in resources.iter_mut() {
+            if let Object::Reference(id) = *category {
+                if let Ok(copy @ Object::Dictionary(_)) = self.get_object(id) {
+                    *category = copy.clone();
+                }
             }
-            let xobjects = resources
-                .get_mut(b"XObject")
-                .and_then(Object::as_dict_mut)?;
-            xobjects.set(xobject_name, Object::Reference(xobject_id));
         }
-        Ok(())
+        let id = self.add_object(resources);
+        self.get_object_mut(page_id)?.as_dict_mut()?.set("Resources", id);
+        Ok(id)
+    }
+
+    fn is_dictionary_reference(&self, object: &Object) -> bool {
+        object
+            .as_reference()
+            .is_ok_and(|id| matches!(self.get_object(id), Ok(Object::Dictionary(_))))
+    }
+
+    pub fn add_xobject<N: Into<Vec<u8>>>(
+        &mut self, page_id: ObjectId, xobject_name: N, xobject_id: ObjectId,
+    ) -> Result<()> {
+        self.add_resource(page_id, "XObject", xobject_name.into(), xobject_id)
     }
 
     pub fn add_graphics_state<N: Into<Vec<u8>>>(
-        &mut self,
-        page_id: ObjectId,
-        gs_name: N,
-        gs_id: ObjectId,
+        &mut self, page_id: ObjectId, gs_name: N, gs_id: ObjectId,
     ) -> Result<()> {
-        if let Ok(resources) = self
-            .get_or_create_resources(page_id)
-            .and_then(Object::as_dict_mut)
-        {
-            if !resources.has(b"ExtGState") {
-                resources.set("ExtGState", Dictionary::new());
-            }
-            let states = resources
-                .get_mut(b"ExtGState")
-                .and_then(Object::as_dict_mut)?;
-            states.set(gs_name, Object::Reference(gs_id));
+        self.add_resource(page_id, "ExtGState", gs_name.into(), gs_id)
+    }
+
+    /// Add a shading to the `/Shading` resources of a page, for [`Content::paint_shading`](crate::content::Content).
+    pub fn add_shading<N: Into<Vec<u8>>>(&mut self, page_id: ObjectId, name: N, shading_id: ObjectId) -> Result<()> {
+        self.add_resource(page_id, "Shading", name.into(), shading_id)
+    }
+
+    /// Add a pattern to the `/Pattern` resources of a page, for
+    /// [`Content::set_fill_pattern`](crate::content::Content).
+    pub fn add_pattern<N: Into<Vec<u8>>>(&mut self, page_id: ObjectId, name: N, pattern_id: ObjectId) -> Result<()> {
+        self.add_resource(page_id, "Pattern", name.into(), pattern_id)
+    }
+
+    /// Add a resource to the resources of a page, which are [isolated](Document::isolate_page_resources) first.
+    fn add_resource(&mut self, page_id: ObjectId, category: &str, name: Vec<u8>, id: ObjectId) -> Result<()> {
+        let resources = self.page_resources_mut(page_id)?;
+        if !resources.has(category.as_bytes()) {
+            resources.set(category, Dictionary::new());
         }
+        let category = resources.get_mut(category.as_bytes()).and_then(Object::as_dict_mut)?;
+        category.set(name, Object::Reference(id));
         Ok(())
     }
+
+    /// Add JPEG 2000 data, either a JP2 file or a raw codestream, as an image XObject.
+    pub fn add_jp2_image(&mut self, data: Vec<u8>) -> Result<ObjectId> {
+        let image = crate::xobject::jp2_image(data)?;
+        Ok(self.add_object(image))
+    }
 }
 
 #[cfg(test)]
@@ -183,6 +239,16 @@ pub mod tests {
         doc
     }
 
+    /// Load a document with its file, for incremental updates.
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    pub fn load_keeping_source(buffer: &[u8]) -> Document {
+        let options = crate::ParseOptions {
+            keep_source: true,
+            ..crate::ParseOptions::default()
+        };
+        Document::load_mem_with_options(buffer, &options).unwrap()
+    }
+
     /// Save a document
     pub fn save_document(filename: &String, doc: &mut Document) {
         let res = doc.save(filename);
@@ -208,6 +274,77 @@ pub mod tests {
         }
     }
 
+    #[test]
+    fn isolate_shared_page_resources() {
+        let mut doc = create_document();
+        let first = doc.page_iter().next().unwrap();
+        let pages_id = doc
+            .get_dictionary(first)
+            .unwrap()
+            .get(b"Parent")
+            .unwrap()
+            .as_reference()
+            .unwrap();
+        let shared_id = doc
+            .get_dictionary(pages_id)
+            .unwrap()
+            .get(b"Resources")
+            .unwrap()
+            .as_reference()
+            .unwrap();
+        let mut pages = vec![first.into()];
+        for _ in 0..2 {
+            let content_id = doc.add_object(Stream::new(dictionary! {}, b"BT /F1 12 Tf (Hi) Tj ET".to_vec()));
+            pages.push(
+                doc.add_object(dictionary! {
+                    "Type" => "Page",
+                    "Parent" => pages_id,
+                    "Contents" => content_id,
+                    "Resources" => shared_id,
+                })
+                .into(),
+            );
+        }
+        let pages_dict = doc.get_object_mut(pages_id).unwrap().as_dict_mut().unwrap();
+        pages_dict.set("Count", 3);
+        pages_dict.set("Kids", pages);
+        let [_, second, third] = [1, 2, 3].map(|number| doc.get_pages()[&number]);
+
+        // The font of the inherited resources stays shared.
+        let font_id = doc
+            .get_dictionary(shared_id)
+            .unwrap()
+            .get(b"Font")
+            .and_then(Object::as_dict)
+            .unwrap();
+        let font_id = font_id.get(b"F1").and_then(Object::as_reference).unwrap();
+        let copy_id = doc.isolate_page_resources(first).unwrap();
+        assert_ne!(copy_id, shared_id);
+        assert_eq!(doc.isolate_page_resources(first).unwrap(), copy_id);
+        let header = [crate::PlacedText::new(72.0, 800.0, 10.0, "Header")];
+        doc.append_text(
+            first,
+            &header,
+            crate::FontChoice::Builtin(crate::BuiltinFont::Helvetica),
+        )
+        .unwrap();
+        assert_eq!(doc.get_page_fonts(first).len(), 2);
+        assert_eq!(doc.get_page_fonts(second).len(), 1);
+        let copied_font = doc
+            .get_dictionary(copy_id)
+            .unwrap()
+            .get(b"Font")
+            .and_then(Object::as_dict)
+            .unwrap();
+        assert_eq!(copied_font.get(b"F1").and_then(Object::as_reference).unwrap(), font_id);
+
+        // Pages referring to the resources of their page tree node get copies as well.
+        doc.add_xobject(second, "Im1", font_id).unwrap();
+        assert!(doc.get_dictionary(shared_id).unwrap().get(b"XObject").is_err());
+        assert!(doc.get_page_fonts(third).contains_key(&b"F1"[..]));
+        assert_ne!(doc.isolate_page_resources(third).unwrap(), shared_id);
+    }
+
     #[test]
     fn create_document_creates_document() {
         let filename = String::from("test_1_create.pdf");
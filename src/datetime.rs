@@ -2,8 +2,55 @@ use super::Object;
 #[cfg(feature = "chrono_time")]
 use chrono::prelude::*;
 
+use time::macros::format_description;
 use time::OffsetDateTime;
-use time::Time;
+
+/// The date type that [`Object::as_datetime`] returns and that converts into a PDF date string.
+#[cfg(feature = "chrono_time")]
+pub type PdfDate = DateTime<Local>;
+/// The date type that [`Object::as_datetime`] returns and that converts into a PDF date string.
+#[cfg(not(feature = "chrono_time"))]
+pub type PdfDate = OffsetDateTime;
+
+/// Where the current time comes from, for the dates lopdf sets itself: the `/ModDate` set when saving and the
+/// modification dates of new annotations, signatures, attachments and page-piece data, all of which are left
+/// out when the clock gives no time.
+///
+/// The default is the system clock, which `wasm32-unknown-unknown` does not have: there, give a clock that asks
+/// the host, such as one calling `js_sys::Date::now`.
+#[derive(Debug, Clone, Copy)]
+pub struct Clock(fn() -> Option<OffsetDateTime>);
+
+impl Clock {
+    /// A clock reading the time from a function.
+    pub fn new(now: fn() -> Option<OffsetDateTime>) -> Clock {
+        Clock(now)
+    }
+
+    /// The system clock, except on `wasm32-unknown-unknown` where it gives no time.
+    pub fn system() -> Clock {
+        if cfg!(all(target_arch = "wasm32", target_os = "unknown")) {
+            Clock::none()
+        } else {
+            Clock(|| Some(OffsetDateTime::now_utc()))
+        }
+    }
+
+    /// A clock that gives no time, so that no dates are set.
+    pub fn none() -> Clock {
+        Clock(|| None)
+    }
+
+    pub fn now(&self) -> Option<OffsetDateTime> {
+        (self.0)()
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Clock {
+        Clock::system()
+    }
+}
 
 #[cfg(feature = "chrono_time")]
 impl From<DateTime<Local>> for Object {
@@ -15,6 +62,7 @@ impl From<DateTime<Local>> for Object {
 }
 
 // Find the last `:` and turn it into an `'` to account for PDF weirdness
+#[cfg(feature = "chrono_time")]
 fn convert_utc_offset(bytes: &mut [u8]) {
     let mut index = bytes.len();
     while let Some(last) = bytes[..index].last_mut() {
@@ -33,27 +81,13 @@ impl From<DateTime<Utc>> for Object {
     }
 }
 
-impl From<Time> for Object {
-    fn from(date: Time) -> Self {
-        // can only fail if the TIME_FMT_ENCODE_STR would be invalid
-        Object::string_literal(format!("D:{}", date.format("%Y%m%d%H%M%SZ")).into_bytes())
-    }
-}
-
 impl From<OffsetDateTime> for Object {
     fn from(date: OffsetDateTime) -> Self {
-        // can only fail if the TIME_FMT_ENCODE_STR would be invalid
-        Object::string_literal({
-            // D:%Y%m%d%H%M%S:%z'
-            //
-            // UTC offset in the form +HHMM or -HHMM (empty string if the the object is naive).
-            let timezone = date.format("%z");
-            let timezone_str_start = date.format("%Y%m%d%H%M%S");
-            let mut timezone_str =
-                format!("D:{}{}:{}'", timezone_str_start, &timezone[..3], &timezone[3..]).into_bytes();
-            convert_utc_offset(&mut timezone_str);
-            timezone_str
-        })
+        let format = format_description!(
+            "D:[year][month][day][hour][minute][second][offset_hour sign:mandatory]'[offset_minute]'"
+        );
+        // can only fail if the format would need more than an OffsetDateTime has
+        Object::string_literal(date.format(&format).unwrap())
     }
 }
 
@@ -75,9 +109,9 @@ impl Object {
     }
 
     #[cfg(feature = "chrono_time")]
-    pub fn as_datetime(&self) -> Option<DateTime<Local>> {
+    pub fn as_datetime(&self) -> Option<PdfDate> {
         let text = self.datetime_string()?;
-        let from_date = |date| FixedOffset::east(0).from_utc_date(&date).and_hms(0, 0, 0);
+        let from_date = |date: NaiveDate| FixedOffset::east_opt(0).unwrap().from_utc_datetime(&date.and_time(NaiveTime::MIN));
         DateTime::parse_from_str(&text, "%Y%m%d%H%M%S%#z")
             .or_else(|_| DateTime::parse_from_str(&text, "%Y%m%d%H%M%#z"))
             .or_else(|_| NaiveDate::parse_from_str(&text, "%Y%m%d").map(from_date))
@@ -90,10 +124,11 @@ impl Object {
     /// are set to 0 since they aren't available in the PDF time format. They could,
     /// however, be calculated manually
     #[cfg(not(feature = "chrono_time"))]
-    pub fn as_datetime(&self) -> Option<OffsetDateTime> {
-        const TIME_FMT_DECODE_STR: &str = "%Y%m%d%H%M%S%z";
+    pub fn as_datetime(&self) -> Option<PdfDate> {
+        let format =
+            format_description!("[year][month][day][hour][minute][second][offset_hour sign:mandatory][offset_minute]");
         let text = self.datetime_string()?;
-        OffsetDateTime::parse(&text, TIME_FMT_DECODE_STR).ok()
+        OffsetDateTime::parse(&text, &format).ok()
     }
 }
 
@@ -146,3 +181,14 @@ fn parse_datetime() {
     assert_eq!(time2.time().minute(), time.time().minute());
     assert_eq!(time2.time().second(), time.time().second());
 }
+
+#[cfg(not(feature = "chrono_time"))]
+#[test]
+fn format_datetime() {
+    let time = time::OffsetDateTime::from_unix_timestamp(1_700_000_000)
+        .unwrap()
+        .to_offset(time::macros::offset!(-8));
+    let text: Object = time.into();
+    assert_eq!(text.as_str().unwrap(), b"D:20231114141320-08'00'");
+    assert_eq!(text.as_datetime(), Some(time));
+}
@@ -0,0 +1,702 @@
+use crate::geometry::Rect;
+use crate::{Dictionary, Document, Object, ObjectId, Result};
+use log::warn;
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+
+/// How a destination page is displayed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DestinationFit {
+    /// Put the given coordinates at the upper-left corner of the window and zoom by the given factor;
+    /// `None` keeps the current value.
+    Xyz {
+        left: Option<f64>,
+        top: Option<f64>,
+        zoom: Option<f64>,
+    },
+    /// Fit the whole page in the window.
+    Fit,
+    /// Fit the width of the page in the window, with the given coordinate at the top of the window.
+    FitH { top: Option<f64> },
+    /// Fit the height of the page in the window, with the given coordinate at the left edge of the window.
+    FitV { left: Option<f64> },
+    /// Fit the given rectangle of the page in the window.
+    FitR(Rect),
+    /// Fit the bounding box of the page contents in the window.
+    FitB,
+    /// Fit the width of the bounding box of the page contents in the window.
+    FitBH { top: Option<f64> },
+    /// Fit the height of the bounding box of the page contents in the window.
+    FitBV { left: Option<f64> },
+}
+
+impl DestinationFit {
+    /// Build the explicit destination array for a page.
+    pub fn to_object(&self, page_id: ObjectId) -> Object {
+        fn optional(value: Option<f64>) -> Object {
+            value.map_or(Object::Null, Object::Real)
+        }
+        let mut array = vec![page_id.into()];
+        match *self {
+            DestinationFit::Xyz { left, top, zoom } => {
+                array.push("XYZ".into());
+                array.extend(vec![optional(left), optional(top), optional(zoom)]);
+            }
+            DestinationFit::Fit => array.push("Fit".into()),
+            DestinationFit::FitH { top } => array.extend(vec!["FitH".into(), optional(top)]),
+            DestinationFit::FitV { left } => array.extend(vec!["FitV".into(), optional(left)]),
+            DestinationFit::FitR(rect) => {
+                array.push("FitR".into());
+                array.extend(vec![rect.llx.into(), rect.lly.into(), rect.urx.into(), rect.ury.into()]);
+            }
+            DestinationFit::FitB => array.push("FitB".into()),
+            DestinationFit::FitBH { top } => array.extend(vec!["FitBH".into(), optional(top)]),
+            DestinationFit::FitBV { left } => array.extend(vec!["FitBV".into(), optional(left)]),
+        }
+        Object::Array(array)
+    }
+
+    /// Parse the part of an explicit destination array after the page.
+    pub fn from_array(array: &[Object]) -> Option<DestinationFit> {
+        let (kind, operands) = array.split_first()?;
+        // Missing operands, like null ones, keep the current value.
+        let optional = |index: usize| operands.get(index).and_then(|value| value.as_float().ok());
+        let fit = match kind.as_name().ok()? {
+            b"XYZ" => DestinationFit::Xyz {
+                left: optional(0),
+                top: optional(1),
+                zoom: optional(2).filter(|&zoom| zoom != 0.0),
+            },
+            b"Fit" => DestinationFit::Fit,
+            b"FitH" => DestinationFit::FitH { top: optional(0) },
+            b"FitV" => DestinationFit::FitV { left: optional(0) },
+            b"FitR" => DestinationFit::FitR(Rect::new(optional(0)?, optional(1)?, optional(2)?, optional(3)?)),
+            b"FitB" => DestinationFit::FitB,
+            b"FitBH" => DestinationFit::FitBH { top: optional(0) },
+            b"FitBV" => DestinationFit::FitBV { left: optional(0) },
+            _ => return None,
+        };
+        Some(fit)
+    }
+}
+
+/// What [`Document::remap_destinations`] does with the destinations of pages mapped to no page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DestinationPolicy {
+    /// Remove them: outline items keep their title and children without a destination, links are removed from
+    /// their pages, `GoTo` actions are taken out of the `/Next` chains they are in and named destinations are
+    /// removed.
+    Drop,
+    /// Go to the closest page that is kept, in the order of the pages, the following one when two are as close.
+    NearestSurviving,
+    /// Go to the first page that is kept.
+    DocumentStart,
+}
+
+/// A page of the document and how to display it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Destination {
+    pub page: ObjectId,
+    pub fit: DestinationFit,
+}
+
+impl Destination {
+    pub fn new(page: ObjectId, fit: DestinationFit) -> Destination {
+        Destination { page, fit }
+    }
+
+    /// Parse an explicit destination, given as an array or as a dictionary with a `/D` entry.
+    ///
+    /// Destinations in other documents, which give a page number instead of a page object, are not supported.
+    pub fn from_object(doc: &Document, object: &Object) -> Option<Destination> {
+        let (_, object) = doc.dereference(object).ok()?;
+        let array = match object {
+            Object::Dictionary(dict) => dict.get_deref(b"D", doc).and_then(Object::as_array).ok()?,
+            object => object.as_array().ok()?,
+        };
+        let (page, fit) = array.split_first()?;
+        Some(Destination {
+            page: page.as_reference().ok()?,
+            fit: DestinationFit::from_array(fit)?,
+        })
+    }
+
+    pub fn to_object(&self) -> Object {
+        self.fit.to_object(self.page)
+    }
+}
+
+impl Document {
+    /// The named destinations of the document, from both the `/Dests` dictionary of the catalog and the `/Dests`
+    /// name tree. A name defined in both refers to the destination of the name tree.
+    pub fn named_destinations(&self) -> BTreeMap<Vec<u8>, Destination> {
+        let mut entries: BTreeMap<Vec<u8>, Object> = self
+            .catalog()
+            .and_then(|catalog| catalog.get_deref(b"Dests", self))
+            .and_then(Object::as_dict)
+            .map(|dests| dests.iter().map(|(name, dest)| (name.clone(), dest.clone())).collect())
+            .unwrap_or_default();
+        entries.extend(self.names(b"Dests"));
+        entries
+            .into_iter()
+            .filter_map(|(name, dest)| match Destination::from_object(self, &dest) {
+                Some(dest) => Some((name, dest)),
+                None => {
+                    warn!("Skipping invalid named destination {}", String::from_utf8_lossy(&name));
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// The page and display of a named destination.
+    pub fn resolve_destination(&self, name: &[u8]) -> Option<(ObjectId, DestinationFit)> {
+        let dest = self.named_destinations().remove(name)?;
+        Some((dest.page, dest.fit))
+    }
+
+    /// Add a named destination to the `/Dests` name tree, replacing any destination with the same name.
+    pub fn add_named_destination<K: Into<Vec<u8>>>(
+        &mut self, name: K, page_id: ObjectId, fit: DestinationFit,
+    ) -> Result<()> {
+        let name = name.into();
+        self.remove_legacy_destination(&name)?;
+        let mut entries = self.names(b"Dests");
+        entries.insert(name, fit.to_object(page_id));
+        self.set_names("Dests", entries)
+    }
+
+    /// Rename a named destination, and update the links, outline items and actions of the document that refer to
+    /// it. Any destination already named `new` is replaced. Returns `false` if there is no destination named `old`.
+    pub fn rename_named_destination(&mut self, old: &[u8], new: &[u8]) -> Result<bool> {
+        let dest = match self.remove_legacy_destination(old)? {
+            Some(dest) => Some(dest),
+            None => {
+                let mut entries = self.names(b"Dests");
+                let dest = entries.remove(old);
+                if dest.is_some() {
+                    self.set_names("Dests", entries)?;
+                }
+                dest
+            }
+        };
+        let dest = match dest {
+            Some(dest) => dest,
+            None => return Ok(false),
+        };
+        self.remove_legacy_destination(new)?;
+        let mut entries = self.names(b"Dests");
+        entries.insert(new.to_vec(), dest);
+        self.set_names("Dests", entries)?;
+
+        for object in self.objects.values_mut() {
+            rename_destination_references(object, old, new);
+        }
+        Ok(true)
+    }
+
+    /// Make the destinations of the document go to the pages that pages map to, as when pages are replaced, moved
+    /// or deleted: explicit destinations in outline items, links, `GoTo` actions, including those of `/Next` chains,
+    /// the open action and named destinations. Destinations of pages mapped to `None` are handled by the policy,
+    /// and go to the whole page they are retargeted to; without any page left they are dropped. Pages that are not
+    /// in the mapping are left as they are, and destinations giving a page by its index, which only destinations
+    /// in other documents should, are made to refer to the page.
+    pub fn remap_destinations(
+        &mut self, mapping: &BTreeMap<ObjectId, Option<ObjectId>>, policy: DestinationPolicy,
+    ) -> Result<()> {
+        let pages: Vec<ObjectId> = self.page_iter().collect();
+        let survives = |page: &ObjectId| mapping.get(page).is_none_or(Option::is_some);
+        let target = |page: &ObjectId| mapping.get(page).copied().flatten().unwrap_or(*page);
+        let mut targets = BTreeMap::new();
+        for (page, mapped) in mapping {
+            let retargeted = match (mapped, policy) {
+                (Some(mapped), _) => Some((*mapped, false)),
+                (None, DestinationPolicy::Drop) => None,
+                (None, DestinationPolicy::NearestSurviving) => {
+                    let index = pages.iter().position(|id| id == page);
+                    let nearest = index.and_then(|index| {
+                        (1..pages.len()).find_map(|distance| {
+                            let after = pages.get(index + distance).filter(|id| survives(id));
+                            after.or_else(|| pages.get(index.checked_sub(distance)?).filter(|id| survives(id)))
+                        })
+                    });
+                    nearest
+                        .or_else(|| pages.iter().find(|id| survives(id)))
+                        .map(|id| (target(id), true))
+                }
+                (None, DestinationPolicy::DocumentStart) => {
+                    pages.iter().find(|id| survives(id)).map(|id| (target(id), true))
+                }
+            };
+            targets.insert(*page, retargeted);
+        }
+        let mut remap = DestinationRemap {
+            targets,
+            pages,
+            dropped_objects: BTreeSet::new(),
+            dropped_names: BTreeSet::new(),
+        };
+
+        // Where destinations are dropped, first find the indirect destinations and names that are, and the links
+        // that have a destination.
+        let legacy_dests: Option<ObjectId> = self.catalog()?.get(b"Dests").and_then(Object::as_reference).ok();
+        let mut named: Vec<(Vec<u8>, Object)> = self
+            .catalog()?
+            .get_deref(b"Dests", self)
+            .and_then(Object::as_dict)
+            .map(|dests| dests.iter().map(|(name, dest)| (name.clone(), dest.clone())).collect())
+            .unwrap_or_default();
+        named.extend(self.names(b"Dests"));
+        let mut destination_objects = BTreeSet::new();
+        let mut links = BTreeSet::new();
+        for (&id, object) in &self.objects {
+            if let Object::Dictionary(dict) = object {
+                let is_goto = dict.get(b"S").and_then(Object::as_name).ok() == Some(b"GoTo");
+                let goto_dest = dict.get(b"D").ok().filter(|_| is_goto);
+                let dests = [dict.get(b"Dest").ok(), dict.get(b"OpenAction").ok(), goto_dest];
+                destination_objects.extend(dests.iter().flatten().filter_map(|dest| dest.as_reference().ok()));
+                let is_link = dict.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Link");
+                if is_link && (dict.has(b"Dest") || dict.has(b"A")) {
+                    links.insert(id);
+                }
+            }
+        }
+        destination_objects.extend(named.iter().filter_map(|(_, dest)| dest.as_reference().ok()));
+        destination_objects.retain(|&id| match self.get_object(id) {
+            Ok(Object::Array(_)) => true,
+            Ok(Object::Dictionary(dict)) => !dict.has(b"S"),
+            _ => false,
+        });
+        for &id in &destination_objects {
+            if self.get_object(id).is_ok_and(|dest| remap.drops(dest)) {
+                remap.dropped_objects.insert(id);
+            }
+        }
+        for (name, dest) in &named {
+            if remap.drops(dest) {
+                remap.dropped_names.insert(name.clone());
+            }
+        }
+        if remap.targets.values().any(Option::is_none) {
+            self.strip_actions(&|action| {
+                action.get(b"S").and_then(Object::as_name).ok() == Some(b"GoTo")
+                    && action.get(b"D").is_ok_and(|dest| remap.drops(dest))
+            });
+        }
+
+        let ids: Vec<ObjectId> = self.objects.keys().copied().collect();
+        for id in ids {
+            let object = self.objects.get_mut(&id).unwrap();
+            if destination_objects.contains(&id) {
+                if remap.dropped_objects.contains(&id) {
+                    *object = Object::Null;
+                } else {
+                    remap.destination(object);
+                }
+            } else if Some(id) == legacy_dests {
+                if let Object::Dictionary(dests) = object {
+                    remap.dictionary_of_destinations(dests);
+                }
+            } else {
+                remap.object(object);
+            }
+        }
+        if let Ok(dests) = self.catalog_mut()?.get_mut(b"Dests").and_then(Object::as_dict_mut) {
+            remap.dictionary_of_destinations(dests);
+        }
+        let mut entries = self.names(b"Dests");
+        if !entries.is_empty() {
+            entries.retain(|name, _| !remap.dropped_names.contains(name));
+            for dest in entries.values_mut() {
+                remap.destination(dest);
+            }
+            self.set_names("Dests", entries)?;
+        }
+
+        let dead_links: BTreeSet<ObjectId> = links
+            .into_iter()
+            .filter(|&id| {
+                self.get_dictionary(id)
+                    .is_ok_and(|link| !link.has(b"Dest") && !link.has(b"A"))
+            })
+            .collect();
+        self.remove_annotations(None, |annotation| {
+            annotation.id.is_some_and(|id| dead_links.contains(&id))
+        });
+        Ok(())
+    }
+
+    /// Resolve a destination given either explicitly or by name, as in `/Dest` entries and `GoTo` actions.
+    pub(crate) fn destination_from_object(
+        &self, object: &Object, named: &BTreeMap<Vec<u8>, Destination>,
+    ) -> Option<Destination> {
+        match self.dereference(object).ok()?.1 {
+            Object::String(name, _) | Object::Name(name) => named.get(name).copied(),
+            object => Destination::from_object(self, object),
+        }
+    }
+
+    /// Remove a destination from the `/Dests` dictionary of the catalog, returning it if it was there.
+    fn remove_legacy_destination(&mut self, name: &[u8]) -> Result<Option<Object>> {
+        let dests = match self.catalog()?.get(b"Dests") {
+            Ok(Object::Reference(id)) => self.get_object_mut(*id)?.as_dict_mut()?,
+            Ok(Object::Dictionary(_)) => {
+                let root_id = self.trailer.get(b"Root")?.as_reference()?;
+                self.get_object_mut(root_id)?
+                    .as_dict_mut()?
+                    .get_mut(b"Dests")?
+                    .as_dict_mut()?
+            }
+            _ => return Ok(None),
+        };
+        Ok(dests.remove(name))
+    }
+}
+
+/// Replace the name of a destination in the `/Dest` entries and `GoTo` actions found in an object.
+fn rename_destination_references(object: &mut Object, old: &[u8], new: &[u8]) {
+    fn rename(dest: Option<&mut Object>, old: &[u8], new: &[u8]) {
+        if let Some(Object::String(name, _)) | Some(Object::Name(name)) = dest {
+            if name.as_slice() == old {
+                *name = new.to_vec();
+            }
+        }
+    }
+    fn rename_in_dictionary(dict: &mut Dictionary, old: &[u8], new: &[u8]) {
+        rename(dict.get_mut(b"Dest").ok(), old, new);
+        if dict.get(b"S").and_then(Object::as_name).ok() == Some(b"GoTo") {
+            rename(dict.get_mut(b"D").ok(), old, new);
+        }
+        for (_, value) in dict.iter_mut() {
+            rename_destination_references(value, old, new);
+        }
+    }
+    match object {
+        Object::Dictionary(dict) => rename_in_dictionary(dict, old, new),
+        Object::Stream(stream) => rename_in_dictionary(&mut stream.dict, old, new),
+        Object::Array(array) => {
+            for item in array {
+                rename_destination_references(item, old, new);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The pages that destinations go to after [`Document::remap_destinations`].
+struct DestinationRemap {
+    /// The page that each page of the mapping goes to, and whether it is another page than it maps to; `None` for
+    /// those whose destinations are dropped.
+    targets: BTreeMap<ObjectId, Option<(ObjectId, bool)>>,
+    /// The pages in their order, for destinations giving a page by its index.
+    pages: Vec<ObjectId>,
+    /// The indirect destinations that are dropped.
+    dropped_objects: BTreeSet<ObjectId>,
+    dropped_names: BTreeSet<Vec<u8>>,
+}
+
+impl DestinationRemap {
+    /// The page of an explicit destination array, given by reference or by index.
+    fn page(&self, array: &[Object]) -> Option<ObjectId> {
+        array.get(1)?.as_name().ok()?;
+        match array.first()? {
+            Object::Reference(id) => Some(*id),
+            Object::Integer(index) => self.pages.get(usize::try_from(*index).ok()?).copied(),
+            _ => None,
+        }
+    }
+
+    /// Whether a destination, or a dictionary with a `/D` destination, is dropped.
+    fn drops(&self, dest: &Object) -> bool {
+        match dest {
+            Object::Array(array) => self
+                .page(array)
+                .is_some_and(|page| self.targets.get(&page) == Some(&None)),
+            Object::Reference(id) => self.dropped_objects.contains(id),
+            Object::String(name, _) | Object::Name(name) => self.dropped_names.contains(name),
+            Object::Dictionary(dict) if !dict.has(b"S") => dict.get(b"D").is_ok_and(|dest| self.drops(dest)),
+            _ => false,
+        }
+    }
+
+    /// Make an explicit destination, or a dictionary with a `/D` destination, go to its new page.
+    fn destination(&self, dest: &mut Object) {
+        let array = match dest {
+            Object::Array(array) => array,
+            // Actions, which have a `/D` entry of their own, are not destinations.
+            Object::Dictionary(dict) if !dict.has(b"S") => match dict.get_mut(b"D") {
+                Ok(Object::Array(array)) => array,
+                _ => return,
+            },
+            _ => return,
+        };
+        let page = match self.page(array) {
+            Some(page) => page,
+            None => return,
+        };
+        match self.targets.get(&page) {
+            Some(Some((target, true))) => *array = vec![(*target).into(), "Fit".into()],
+            Some(Some((target, false))) => array[0] = (*target).into(),
+            Some(None) => {}
+            None => array[0] = page.into(),
+        }
+    }
+
+    fn dictionary_of_destinations(&self, dests: &mut Dictionary) {
+        let dropped: Vec<Vec<u8>> = dests
+            .iter()
+            .filter(|(name, dest)| self.dropped_names.contains(*name) || self.drops(dest))
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in dropped {
+            dests.remove(&name);
+        }
+        for (_, dest) in dests.iter_mut() {
+            self.destination(dest);
+        }
+    }
+
+    fn dictionary(&self, dict: &mut Dictionary) {
+        for key in [&b"Dest"[..], b"OpenAction"] {
+            if dict.get(key).is_ok_and(|dest| self.drops(dest)) {
+                dict.remove(key);
+            }
+        }
+        if let Ok(dest) = dict.get_mut(b"Dest") {
+            self.destination(dest);
+        }
+        if let Ok(dest @ Object::Array(_)) = dict.get_mut(b"OpenAction") {
+            self.destination(dest);
+        }
+        if dict.get(b"S").and_then(Object::as_name).ok() == Some(b"GoTo") {
+            if let Ok(dest) = dict.get_mut(b"D") {
+                self.destination(dest);
+            }
+        }
+        for (_, value) in dict.iter_mut() {
+            self.object(value);
+        }
+    }
+
+    fn object(&self, object: &mut Object) {
+        match object {
+            Object::Dictionary(dict) => self.dictionary(dict),
+            Object::Stream(stream) => self.dictionary(&mut stream.dict),
+            Object::Array(items) => {
+                for item in items {
+                    self.object(item);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annotations::LinkTarget;
+    use crate::creator::tests::create_document;
+
+    #[test]
+    fn read_and_add_named_destinations() {
+        let mut doc = create_document();
+        let page_id = doc.page_iter().next().unwrap();
+        let root_id = doc.trailer.get(b"Root").unwrap().as_reference().unwrap();
+        let catalog = doc.get_object_mut(root_id).unwrap().as_dict_mut().unwrap();
+        catalog.set(
+            "Dests",
+            dictionary! {
+                "top" => vec![page_id.into(), "XYZ".into(), Object::Null, 800.into(), 0.into()],
+                "whole" => dictionary! { "D" => vec![page_id.into(), "Fit".into()] },
+                "remote" => vec![0.into(), "Fit".into()],
+            },
+        );
+
+        let dests = doc.named_destinations();
+        assert_eq!(dests.len(), 2);
+        let top = DestinationFit::Xyz {
+            left: None,
+            top: Some(800.0),
+            zoom: None,
+        };
+        assert_eq!(dests[&b"top"[..]], Destination::new(page_id, top));
+        assert_eq!(doc.resolve_destination(b"whole"), Some((page_id, DestinationFit::Fit)));
+
+        let rect = Rect::new(10.0, 20.0, 30.0, 40.0);
+        doc.add_named_destination("box", page_id, DestinationFit::FitR(rect))
+            .unwrap();
+        doc.add_named_destination("whole", page_id, DestinationFit::FitB)
+            .unwrap();
+        assert_eq!(
+            doc.resolve_destination(b"box"),
+            Some((page_id, DestinationFit::FitR(rect)))
+        );
+        assert_eq!(doc.resolve_destination(b"whole"), Some((page_id, DestinationFit::FitB)));
+        assert_eq!(doc.names(b"Dests").len(), 2);
+
+        let link_id = doc.add_link(page_id, rect, LinkTarget::Named(b"top".to_vec())).unwrap();
+        assert!(doc.rename_named_destination(b"top", b"start").unwrap());
+        assert!(!doc.rename_named_destination(b"top", b"start").unwrap());
+        assert_eq!(doc.resolve_destination(b"top"), None);
+        assert_eq!(doc.resolve_destination(b"start"), Some((page_id, top)));
+        let link = doc.get_dictionary(link_id).unwrap();
+        assert_eq!(link.get(b"Dest").and_then(Object::as_str).unwrap(), b"start");
+    }
+
+    /// A document of three pages with destinations going to the second one: an outline item with a `/Dest`, a
+    /// nested one with a `GoTo` action of a page index, one with a chain of actions, two links, named destinations
+    /// and the open action. Returns the pages and the outline items.
+    fn document_with_destinations() -> (Document, Vec<ObjectId>, [ObjectId; 3]) {
+        let mut doc = create_document();
+        let pages_id = doc.catalog().unwrap().get(b"Pages").unwrap().as_reference().unwrap();
+        for _ in 0..2 {
+            let page_id = doc.add_object(dictionary! { "Type" => "Page", "Parent" => pages_id });
+            let pages = doc.get_object_mut(pages_id).unwrap().as_dict_mut().unwrap();
+            pages
+                .get_mut(b"Kids")
+                .unwrap()
+                .as_array_mut()
+                .unwrap()
+                .push(page_id.into());
+        }
+        doc.get_object_mut(pages_id)
+            .unwrap()
+            .as_dict_mut()
+            .unwrap()
+            .set("Count", 3);
+        let pages: Vec<ObjectId> = doc.page_iter().collect();
+
+        let outlines_id = doc.new_object_id();
+        let (first_id, nested_id, chained_id) = (doc.new_object_id(), doc.new_object_id(), doc.new_object_id());
+        let goto_id = doc.add_object(dictionary! { "S" => "GoTo", "D" => vec![pages[1].into(), "Fit".into()] });
+        let uri = dictionary! {
+            "S" => "URI",
+            "URI" => Object::string_literal("https://example.com"),
+            "Next" => vec![goto_id.into(), dictionary! { "S" => "GoTo", "D" => vec![pages[2].into(), "Fit".into()] }.into()],
+        };
+        let top = vec![pages[1].into(), "XYZ".into(), Object::Null, 700.into(), Object::Null];
+        let items = [
+            (
+                outlines_id,
+                dictionary! { "Type" => "Outlines", "First" => first_id, "Last" => chained_id, "Count" => 3 },
+            ),
+            (
+                first_id,
+                dictionary! {
+                    "Title" => Object::string_literal("First"), "Parent" => outlines_id, "Next" => chained_id,
+                    "First" => nested_id, "Last" => nested_id, "Count" => 1, "Dest" => top,
+                },
+            ),
+            (
+                nested_id,
+                dictionary! {
+                    "Title" => Object::string_literal("Nested"), "Parent" => first_id,
+                    "A" => dictionary! { "S" => "GoTo", "D" => vec![1.into(), "Fit".into()] },
+                },
+            ),
+            (
+                chained_id,
+                dictionary! {
+                    "Title" => Object::string_literal("Chained"), "Parent" => outlines_id, "Prev" => first_id, "A" => uri,
+                },
+            ),
+        ];
+        for (id, item) in items.iter().cloned() {
+            doc.objects.insert(id, Object::Dictionary(item));
+        }
+        let catalog = doc.catalog_mut().unwrap();
+        catalog.set("Outlines", outlines_id);
+        catalog.set("OpenAction", vec![pages[1].into(), "Fit".into()]);
+
+        let rect = Rect::new(10.0, 20.0, 30.0, 40.0);
+        let fit = DestinationFit::FitH { top: Some(500.0) };
+        doc.add_named_destination("middle", pages[1], fit).unwrap();
+        doc.add_named_destination("last", pages[2], fit).unwrap();
+        let mut names = doc.names(b"Dests");
+        names.insert(b"first".to_vec(), vec![0.into(), "Fit".into()].into());
+        doc.set_names("Dests", names).unwrap();
+        doc.add_link(pages[0], rect, LinkTarget::Page { page: 2, fit }).unwrap();
+        doc.add_link(pages[0], rect, LinkTarget::Named(b"middle".to_vec()))
+            .unwrap();
+        doc.add_link(pages[0], rect, LinkTarget::Named(b"last".to_vec()))
+            .unwrap();
+        (doc, pages, [first_id, nested_id, chained_id])
+    }
+
+    fn page_of(dest: &Object) -> ObjectId {
+        dest.as_array().unwrap()[0].as_reference().unwrap()
+    }
+
+    #[test]
+    fn drop_destinations_of_deleted_pages() {
+        let (mut doc, pages, [first_id, nested_id, chained_id]) = document_with_destinations();
+        doc.delete_pages(&[2]);
+
+        let first = doc.get_dictionary(first_id).unwrap();
+        assert!(!first.has(b"Dest"));
+        assert_eq!(first.get(b"First").unwrap().as_reference().unwrap(), nested_id);
+        assert!(!doc.get_dictionary(nested_id).unwrap().has(b"A"));
+        let action = doc
+            .get_dictionary(chained_id)
+            .unwrap()
+            .get(b"A")
+            .and_then(Object::as_dict)
+            .unwrap();
+        assert_eq!(action.get(b"S").and_then(Object::as_name).unwrap(), b"URI");
+        let next = action.get(b"Next").and_then(Object::as_dict).unwrap();
+        assert_eq!(page_of(next.get(b"D").unwrap()), pages[2]);
+
+        assert!(!doc.catalog().unwrap().has(b"OpenAction"));
+        let names = doc.names(b"Dests");
+        assert_eq!(names.keys().collect::<Vec<_>>(), [&b"first"[..], b"last"]);
+        assert_eq!(page_of(&names[&b"first"[..]]), pages[0]);
+        let links = doc.annotations(pages[0]);
+        assert_eq!(links.len(), 1);
+        let link = doc.get_dictionary(links[0].id.unwrap()).unwrap();
+        assert_eq!(link.get(b"Dest").and_then(Object::as_str).unwrap(), b"last");
+    }
+
+    #[test]
+    fn retarget_destinations_of_removed_pages() {
+        for (policy, target) in [
+            (DestinationPolicy::NearestSurviving, 2),
+            (DestinationPolicy::DocumentStart, 0),
+        ] {
+            let (mut doc, pages, [first_id, nested_id, chained_id]) = document_with_destinations();
+            let mapping = vec![(pages[1], None), (pages[2], Some(pages[2]))].into_iter().collect();
+            doc.remap_destinations(&mapping, policy).unwrap();
+
+            let whole = |dest: &Object| {
+                let dest = dest.as_array().unwrap();
+                assert_eq!(dest[1].as_name().unwrap(), b"Fit");
+                dest[0].as_reference().unwrap()
+            };
+            let first = doc.get_dictionary(first_id).unwrap();
+            assert_eq!(whole(first.get(b"Dest").unwrap()), pages[target]);
+            let nested = doc
+                .get_dictionary(nested_id)
+                .unwrap()
+                .get(b"A")
+                .and_then(Object::as_dict)
+                .unwrap();
+            assert_eq!(whole(nested.get(b"D").unwrap()), pages[target]);
+            let action = doc
+                .get_dictionary(chained_id)
+                .unwrap()
+                .get(b"A")
+                .and_then(Object::as_dict)
+                .unwrap();
+            let next = action.get(b"Next").and_then(Object::as_array).unwrap();
+            let goto = doc.get_dictionary(next[0].as_reference().unwrap()).unwrap();
+            assert_eq!(whole(goto.get(b"D").unwrap()), pages[target]);
+            assert_eq!(whole(doc.catalog().unwrap().get(b"OpenAction").unwrap()), pages[target]);
+
+            let names = doc.names(b"Dests");
+            assert_eq!(names.len(), 3);
+            assert_eq!(whole(&names[&b"middle"[..]]), pages[target]);
+            assert_eq!(page_of(&names[&b"last"[..]]), pages[2]);
+            assert_eq!(doc.annotations(pages[0]).len(), 3);
+        }
+    }
+}
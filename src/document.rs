@@ -1,13 +1,16 @@
 use super::encodings::{self, bytes_to_string, string_to_bytes};
-use super::{Bookmark, Dictionary, Object, ObjectId};
+use super::{Bookmark, Clock, Dictionary, IdAllocator, KnownName, Object, ObjectId};
+use crate::geometry::Rect;
+use crate::writer::{CountingWrite, Writer};
 use crate::xref::Xref;
-use crate::{Error, Result};
+use crate::{Error, ParseWarning, Result};
 use encoding::all::UTF_16BE;
 use encoding::types::{DecoderTrap, EncoderTrap, Encoding};
 use log::info;
 use std::cmp::max;
 use std::collections::{BTreeMap, HashMap};
 use std::io::Write;
+use std::ops::Range;
 use std::str;
 
 /// PDF document.
@@ -40,6 +43,42 @@ pub struct Document {
     /// used to locate a stored Bookmark so children can be appended to it via its id. Otherwise we
     /// need to do recrusive lookups and returns on the bookmarks internal layout Vec
     pub bookmark_table: HashMap<u32, Bookmark>,
+
+    /// The file the document was loaded from, see [`source`](Document::source).
+    pub(crate) source: Vec<u8>,
+
+    /// Save the same document to the same bytes: the modification date is left as it is, and new file
+    /// identifiers are derived from the objects instead of being random.
+    pub deterministic: bool,
+
+    /// The clock of the dates set when saving and editing.
+    pub clock: Clock,
+
+    /// The objects read with [`ParseOptions::preserve_raw`](crate::ParseOptions::preserve_raw), which saving copies
+    /// from the [`source`](Document::source) while they are not dirty.
+    pub(crate) raw_objects: BTreeMap<ObjectId, RawObject>,
+
+    /// The problems of the file that loading worked around, such as object streams whose offsets are wrong.
+    pub parse_warnings: Vec<ParseWarning>,
+
+    /// The allocator of [`Document::id_allocator`], once there is one.
+    pub(crate) id_allocator: Option<IdAllocator>,
+
+    /// The definitions of the source that are not used, with their revision, see
+    /// [`Document::shadowed_objects`](Document::shadowed_objects).
+    #[cfg_attr(not(any(feature = "pom_parser", feature = "nom_parser")), allow(dead_code))]
+    pub(crate) shadowed: Vec<(ObjectId, usize)>,
+
+    /// Where each revision of the source ends, from the original file.
+    #[cfg_attr(not(any(feature = "pom_parser", feature = "nom_parser")), allow(dead_code))]
+    pub(crate) revision_ends: Vec<usize>,
+}
+
+/// Where an object is in the source of a document, with the digest of its serialization when it was read.
+#[derive(Debug, Clone)]
+pub(crate) struct RawObject {
+    span: Range<usize>,
+    digest: [u8; 16],
 }
 
 impl Document {
@@ -55,11 +94,27 @@ impl Document {
             max_bookmark_id: 0,
             bookmarks: Vec::new(),
             bookmark_table: HashMap::new(),
+            source: Vec::new(),
+            deterministic: false,
+            clock: Clock::system(),
+            raw_objects: BTreeMap::new(),
+            parse_warnings: Vec::new(),
+            id_allocator: None,
+            shadowed: Vec::new(),
+            revision_ends: Vec::new(),
         }
     }
 
     const DEREF_LIMIT: usize = 128;
 
+    /// The file the document was loaded from, which signatures are computed over and incremental updates are
+    /// appended to. It is kept when loading with [`ParseOptions::keep_source`](crate::ParseOptions::keep_source) or
+    /// [`ParseOptions::preserve_raw`](crate::ParseOptions::preserve_raw), or if the document is signed; it is empty
+    /// otherwise, and for documents created in memory.
+    pub fn source(&self) -> &[u8] {
+        &self.source
+    }
+
     fn recrusive_fix_pages(&mut self, bookmarks: &[u32], first: bool) -> ObjectId {
         if !bookmarks.is_empty() {
             for id in bookmarks {
@@ -104,35 +159,91 @@ impl Document {
     /// The object id will be None if the object was not a
     /// reference. Otherwise, it will be the last object id in the
     /// reference chain.
+    ///
+    /// The errors of a missing object and of a reference loop give the ids followed.
     pub fn dereference<'a>(&'a self, mut object: &'a Object) -> Result<(Option<ObjectId>, &'a Object)> {
+        let start = object;
         let mut nb_deref = 0;
         let mut id = None;
 
         while let Ok(ref_id) = object.as_reference() {
             id = Some(ref_id);
-            object = self.objects.get(&ref_id).ok_or(Error::ObjectNotFound)?;
+            object = match self.objects.get(&ref_id) {
+                Some(object) => object,
+                None => return Err(Error::not_found(ref_id).after_references(&self.references(start, nb_deref))),
+            };
 
             nb_deref += 1;
             if nb_deref > Self::DEREF_LIMIT {
-                return Err(Error::ReferenceLimit);
+                return Err(Error::ReferenceLimit {
+                    chain: self.references(start, nb_deref),
+                });
             }
         }
 
         Ok((id, object))
     }
 
+    /// The ids of the first `count` references followed from an object, for errors.
+    fn references<'a>(&'a self, mut object: &'a Object, count: usize) -> Vec<ObjectId> {
+        let mut chain = Vec::with_capacity(count);
+        while let (true, Ok(id)) = (chain.len() < count, object.as_reference()) {
+            chain.push(id);
+            match self.objects.get(&id) {
+                Some(next) => object = next,
+                None => break,
+            }
+        }
+        chain
+    }
+
     /// Get object by object id, will iteratively dereference a referenced object.
     pub fn get_object(&self, id: ObjectId) -> Result<&Object> {
-        let object = self.objects.get(&id).ok_or(Error::ObjectNotFound)?;
-        self.dereference(object).map(|(_, object)| object)
+        let object = self.objects.get(&id).ok_or_else(|| Error::not_found(id))?;
+        self.dereference(object)
+            .map(|(_, object)| object)
+            .map_err(|err| err.after_references(&[id]))
     }
 
     /// Get mutable reference to object by object id, will iteratively dereference a referenced object.
     pub fn get_object_mut(&mut self, id: ObjectId) -> Result<&mut Object> {
-        let object = self.objects.get(&id).ok_or(Error::ObjectNotFound)?;
-        let (ref_id, _) = self.dereference(object)?;
+        let object = self.objects.get(&id).ok_or_else(|| Error::not_found(id))?;
+        let (ref_id, _) = self.dereference(object).map_err(|err| err.after_references(&[id]))?;
+        let id = ref_id.unwrap_or(id);
 
-        Ok(self.objects.get_mut(&ref_id.unwrap_or(id)).unwrap())
+        self.mark_dirty(id);
+        Ok(self.objects.get_mut(&id).unwrap())
+    }
+
+    /// Whether an object is written anew when saving, rather than copied as it was read. Objects are dirty unless
+    /// the document was loaded with [`ParseOptions::preserve_raw`](crate::ParseOptions::preserve_raw), once they are
+    /// borrowed with [`get_object_mut`](Document::get_object_mut) or [`mark_dirty`](Document::mark_dirty), and when
+    /// their serialization changed, which catches changes made through [`objects`](Document::objects).
+    pub fn is_dirty(&self, id: ObjectId) -> bool {
+        self.raw_object(id).is_none()
+    }
+
+    /// Have an object written anew when saving, for a caller that changes the source or objects in a way that
+    /// their serialization does not show.
+    pub fn mark_dirty(&mut self, id: ObjectId) {
+        self.raw_objects.remove(&id);
+    }
+
+    /// Keep where an object read is in the source, to copy it from there while it is not dirty.
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    pub(crate) fn preserve_object(&mut self, id: ObjectId, span: Range<usize>) {
+        if let Some(object) = self.objects.get(&id) {
+            let digest = fingerprint(object);
+            self.raw_objects.insert(id, RawObject { span, digest });
+        }
+    }
+
+    /// The bytes of an object that is not dirty, from its number to its `endobj` keyword.
+    pub(crate) fn raw_object(&self, id: ObjectId) -> Option<&[u8]> {
+        let raw = self.raw_objects.get(&id)?;
+        let object = self.objects.get(&id)?;
+        let bytes = self.source.get(raw.span.clone())?;
+        (fingerprint(object) == raw.digest).then_some(bytes)
     }
 
     /// Get page object_id of the specified object object_id
@@ -153,7 +264,7 @@ impl Document {
             }
         }
 
-        Err(Error::ObjectNotFound)
+        Err(Error::not_found(id))
     }
 
     /// Get dictionary object by id.
@@ -163,7 +274,7 @@ impl Document {
 
     /// Traverse objects from trailer recursively, return all referenced object IDs.
     pub fn traverse_objects<A: Fn(&mut Object)>(&mut self, action: A) -> Vec<ObjectId> {
-        fn traverse_array<A: Fn(&mut Object)>(array: &mut Vec<Object>, action: &A, refs: &mut Vec<ObjectId>) {
+        fn traverse_array<A: Fn(&mut Object)>(array: &mut [Object], action: &A, refs: &mut Vec<ObjectId>) {
             for item in array.iter_mut() {
                 traverse_object(item, action, refs);
             }
@@ -179,10 +290,8 @@ impl Document {
                 Object::Array(ref mut array) => traverse_array(array, action, refs),
                 Object::Dictionary(ref mut dict) => traverse_dictionary(dict, action, refs),
                 Object::Stream(ref mut stream) => traverse_dictionary(&mut stream.dict, action, refs),
-                Object::Reference(id) => {
-                    if !refs.contains(&id) {
-                        refs.push(id);
-                    }
+                Object::Reference(id) if !refs.contains(&id) => {
+                    refs.push(id);
                 }
                 _ => {}
             }
@@ -202,9 +311,16 @@ impl Document {
     /// Get catalog dictionary.
     pub fn catalog(&self) -> Result<&Dictionary> {
         self.trailer
-            .get(b"Root")
-            .and_then(Object::as_reference)
+            .get(b"Root")?
+            .as_reference()
             .and_then(|id| self.get_dictionary(id))
+            .map_err(|err| err.at_key(b"Root"))
+    }
+
+    /// Get mutable catalog dictionary.
+    pub fn catalog_mut(&mut self) -> Result<&mut Dictionary> {
+        let root_id = self.trailer.get(b"Root")?.as_reference().map_err(|err| err.at_key(b"Root"))?;
+        self.get_object_mut(root_id)?.as_dict_mut().map_err(|err| err.at_key(b"Root"))
     }
 
     /// Get page numbers and corresponding object ids.
@@ -278,6 +394,19 @@ impl Document {
         (resource_dict, resource_ids)
     }
 
+    /// Get the media box of a page, inherited from the page tree if the page has none.
+    pub fn get_page_media_box(&self, page_id: ObjectId) -> Option<Rect> {
+        let mut node = self.get_dictionary(page_id).ok()?;
+        // Bounded, as page trees may have cycles.
+        for _ in 0..64 {
+            if let Ok(media_box) = node.get_deref(b"MediaBox", self) {
+                return Rect::from_object(media_box).ok();
+            }
+            node = node.get(b"Parent").and_then(Object::as_reference).and_then(|id| self.get_dictionary(id)).ok()?;
+        }
+        None
+    }
+
     /// Get fonts used by a page.
     pub fn get_page_fonts(&self, page_id: ObjectId) -> BTreeMap<Vec<u8>, &Dictionary> {
         fn collect_fonts_from_resources<'a>(
@@ -350,6 +479,18 @@ impl Default for Document {
     }
 }
 
+/// The digest of an object as it would be written.
+pub(crate) fn fingerprint(object: &Object) -> [u8; 16] {
+    let mut bytes = Vec::new();
+    let mut target = CountingWrite {
+        inner: &mut bytes,
+        bytes_written: 0,
+    };
+    // Writing to memory does not fail.
+    let _ = Writer::write_object(&mut target, object, None, None);
+    crate::digest::md5(&bytes)
+}
+
 struct PageTreeIter<'a> {
     doc: &'a Document,
     stack: Vec<&'a [Object]>,
@@ -405,19 +546,17 @@ impl Iterator for PageTreeIter<'_> {
                 self.kids = Some(new_kids);
 
                 if let Ok(kid_id) = kid.as_reference() {
-                    if let Ok(type_name) = self.doc.get_dictionary(kid_id).and_then(Dictionary::type_name) {
-                        match type_name {
-                            "Page" => {
+                    if let Ok(kid) = self.doc.get_dictionary(kid_id) {
+                        match kid.known_type() {
+                            Some(KnownName::Page) => {
                                 return Some(kid_id);
                             }
-                            "Pages" => {
-                                if self.stack.len() < Self::PAGE_TREE_DEPTH_LIMIT {
-                                    let kids = self.kids.unwrap();
-                                    if !kids.is_empty() {
-                                        self.stack.push(kids);
-                                    }
-                                    self.kids = Self::kids(self.doc, kid_id);
+                            Some(KnownName::Pages) if self.stack.len() < Self::PAGE_TREE_DEPTH_LIMIT => {
+                                let kids = self.kids.unwrap();
+                                if !kids.is_empty() {
+                                    self.stack.push(kids);
                                 }
+                                self.kids = Self::kids(self.doc, kid_id);
                             }
                             _ => {}
                         }
@@ -442,7 +581,7 @@ impl Iterator for PageTreeIter<'_> {
             .chain(self.stack.iter().flat_map(|k| k.iter()))
             .map(|kid| {
                 if let Ok(dict) = kid.as_reference().and_then(|id| self.doc.get_dictionary(id)) {
-                    if let Ok("Pages") = dict.type_name() {
+                    if dict.known_type() == Some(KnownName::Pages) {
                         let count = dict.get_deref(b"Count", self.doc).and_then(Object::as_i64).unwrap_or(0);
                         // Don't let page count go backwards in case of an invalid document.
                         max(0, count) as usize
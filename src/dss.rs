@@ -0,0 +1,199 @@
+use crate::digest::{sha1, sha256};
+use crate::{Dictionary, Document, Object, ObjectId, Result, Stream};
+use std::collections::BTreeMap;
+
+/// DER encoded data that validates the signatures of a document, for [`Document::add_validation_info`]. It is
+/// stored as given, without being parsed.
+#[derive(Debug, Clone, Default)]
+pub struct ValidationMaterials {
+    pub certificates: Vec<Vec<u8>>,
+    pub ocsp_responses: Vec<Vec<u8>>,
+    pub crls: Vec<Vec<u8>>,
+}
+
+/// Keys of the arrays of streams of the Document Security Store, and of its `/VRI` entries.
+const KINDS: [(&str, &str); 3] = [("Certs", "Cert"), ("OCSPs", "OCSP"), ("CRLs", "CRL")];
+
+impl Document {
+    /// Add validation data to the Document Security Store (`/DSS`) of the catalog, for long-term validation of
+    /// the signatures.
+    ///
+    /// Each certificate, OCSP response and CRL not already in the store is added as a stream and listed in
+    /// `/Certs`, `/OCSPs` or `/CRLs`; data already stored, in an earlier call or by another application, is
+    /// found by its content and not added again. All of it is also listed in the `/VRI` entry of each signature
+    /// of the document, keyed by the SHA-1 digest of its `/Contents`.
+    ///
+    /// The document must then be saved with [`Document::save_incremental`] to keep its signatures valid.
+    pub fn add_validation_info(&mut self, materials: ValidationMaterials) -> Result<()> {
+        let root_id = self.trailer.get(b"Root")?.as_reference()?;
+        let (dss_id, mut dss) = match self.catalog()?.get(b"DSS").map(|dss| self.dereference(dss)) {
+            Ok(Ok((id, Object::Dictionary(dss)))) => (id, dss.clone()),
+            _ => (None, Dictionary::new()),
+        };
+
+        let blobs = [materials.certificates, materials.ocsp_responses, materials.crls];
+        let mut added: [Vec<ObjectId>; 3] = Default::default();
+        for ((&(key, _), blobs), added) in KINDS.iter().zip(blobs).zip(added.iter_mut()) {
+            let mut streams: Vec<Object> = dss
+                .get_deref(key.as_bytes(), self)
+                .and_then(Object::as_array)
+                .cloned()
+                .unwrap_or_default();
+            let mut stored: BTreeMap<Vec<u8>, ObjectId> = streams
+                .iter()
+                .filter_map(|stream| {
+                    let id = stream.as_reference().ok()?;
+                    let stream = self.get_object(id).and_then(Object::as_stream).ok()?;
+                    let content = stream.decompressed_content().unwrap_or_else(|_| stream.content.to_vec());
+                    Some((sha256(&content).to_vec(), id))
+                })
+                .collect();
+            for blob in blobs {
+                let hash = sha256(&blob).to_vec();
+                let id = match stored.get(&hash) {
+                    Some(&id) => id,
+                    None => {
+                        let id = self.add_object(Stream::new(Dictionary::new(), blob));
+                        stored.insert(hash, id);
+                        streams.push(id.into());
+                        id
+                    }
+                };
+                if !added.contains(&id) {
+                    added.push(id);
+                }
+            }
+            if !streams.is_empty() {
+                dss.set(key, streams);
+            }
+        }
+
+        let mut vri = dss
+            .get_deref(b"VRI", self)
+            .and_then(Object::as_dict)
+            .cloned()
+            .unwrap_or_default();
+        for signature in self.signatures() {
+            let key: String = sha1(&signature.contents)
+                .iter()
+                .map(|byte| format!("{:02X}", byte))
+                .collect();
+            let mut entry = vri
+                .get_deref(key.as_bytes(), self)
+                .and_then(Object::as_dict)
+                .cloned()
+                .unwrap_or_default();
+            for (&(_, entry_key), added) in KINDS.iter().zip(&added) {
+                let mut streams = entry
+                    .get_deref(entry_key.as_bytes(), self)
+                    .and_then(Object::as_array)
+                    .cloned()
+                    .unwrap_or_default();
+                for &id in added {
+                    if !streams.iter().any(|stream| stream.as_reference().ok() == Some(id)) {
+                        streams.push(id.into());
+                    }
+                }
+                if !streams.is_empty() {
+                    entry.set(entry_key, streams);
+                }
+            }
+            vri.set(key, entry);
+        }
+        if !vri.is_empty() {
+            dss.set("VRI", vri);
+        }
+        dss.set("Type", "DSS");
+
+        match dss_id {
+            Some(id) => {
+                self.objects.insert(id, Object::Dictionary(dss));
+            }
+            None => {
+                let id = self.add_object(dss);
+                self.get_object_mut(root_id)?.as_dict_mut()?.set("DSS", id);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, any(feature = "pom_parser", feature = "nom_parser")))]
+mod tests {
+    use super::*;
+    use crate::creator::tests::create_document;
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    use crate::creator::tests::load_keeping_source;
+    use crate::geometry::Rect;
+    use crate::Signer;
+
+    struct DigestSigner;
+
+    impl Signer for DigestSigner {
+        fn sign(&self, data_to_be_signed: &[u8]) -> Result<Vec<u8>> {
+            Ok(sha256(data_to_be_signed).to_vec())
+        }
+    }
+
+    fn materials(certificates: &[&[u8]], ocsp_responses: &[&[u8]]) -> ValidationMaterials {
+        ValidationMaterials {
+            certificates: certificates.iter().map(|blob| blob.to_vec()).collect(),
+            ocsp_responses: ocsp_responses.iter().map(|blob| blob.to_vec()).collect(),
+            crls: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn add_validation_info_as_incremental_update() {
+        let mut doc = create_document();
+        let page_id = doc.page_iter().next().unwrap();
+        let field = doc
+            .add_signature_field(
+                page_id,
+                "approval",
+                Rect::new(50.0, 50.0, 250.0, 100.0),
+                &Default::default(),
+            )
+            .unwrap();
+        let mut original = Vec::new();
+        doc.save_to(&mut original).unwrap();
+        let mut doc = load_keeping_source(&original);
+        let mut signed = Vec::new();
+        doc.sign(&mut signed, field, &DigestSigner).unwrap();
+        let signature = doc.signatures().remove(0);
+        let vri_key: String = sha1(&signature.contents)
+            .iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect();
+
+        doc.add_validation_info(materials(&[b"certificate", b"issuer"], &[b"ocsp"]))
+            .unwrap();
+        let mut update = Vec::new();
+        doc.save_incremental_to(&mut update).unwrap();
+        let mut doc = Document::load_mem(&update).unwrap();
+        let signature = &doc.signatures()[0];
+        assert!(signature.byte_range_valid);
+        // The signed ranges are unchanged, so the signature still holds their digest.
+        assert_eq!(signature.contents[..32], signature.digest.clone().unwrap()[..]);
+        assert!(update.starts_with(&signed));
+
+        // Data already stored is not added again.
+        doc.add_validation_info(materials(&[b"issuer", b"root"], &[])).unwrap();
+        let dss = doc.catalog().unwrap().get(b"DSS").unwrap().as_reference().unwrap();
+        let dss = doc.get_dictionary(dss).unwrap();
+        assert_eq!(dss.get(b"Certs").and_then(Object::as_array).unwrap().len(), 3);
+        assert_eq!(dss.get(b"OCSPs").and_then(Object::as_array).unwrap().len(), 1);
+        assert!(!dss.has(b"CRLs"));
+        let vri = dss.get(b"VRI").and_then(Object::as_dict).unwrap();
+        assert_eq!(vri.len(), 1);
+        let entry = vri.get(vri_key.as_bytes()).and_then(Object::as_dict).unwrap();
+        assert_eq!(entry.get(b"Cert").and_then(Object::as_array).unwrap().len(), 3);
+        assert_eq!(entry.get(b"OCSP").and_then(Object::as_array).unwrap().len(), 1);
+        let certificate = entry.get(b"Cert").and_then(Object::as_array).unwrap()[2].as_reference();
+        let certificate = doc
+            .get_object(certificate.unwrap())
+            .and_then(Object::as_stream)
+            .unwrap();
+        assert_eq!(*certificate.content, b"root");
+    }
+}
@@ -1555,3 +1555,263 @@ pub const SYMBOL_ENCODING: [Option<u16>; 256] = [
     Some(Glyph::bracerightbt),
     None,
 ];
+
+/// PDFDocEncoding, used for text strings that do not start with a byte order mark.
+pub const PDF_DOC_ENCODING: [Option<u16>; 256] = [
+    None,
+    None,
+    None,
+    None,
+    None,
+    None,
+    None,
+    None,
+    None,
+    Some(0x0009),
+    Some(0x000A),
+    None,
+    Some(0x000C),
+    Some(0x000D),
+    None,
+    None,
+    None,
+    None,
+    None,
+    None,
+    None,
+    None,
+    None,
+    None,
+    Some(0x02D8),
+    Some(0x02C7),
+    Some(0x02C6),
+    Some(0x02D9),
+    Some(0x02DD),
+    Some(0x02DB),
+    Some(0x02DA),
+    Some(0x02DC),
+    Some(0x0020),
+    Some(0x0021),
+    Some(0x0022),
+    Some(0x0023),
+    Some(0x0024),
+    Some(0x0025),
+    Some(0x0026),
+    Some(0x0027),
+    Some(0x0028),
+    Some(0x0029),
+    Some(0x002A),
+    Some(0x002B),
+    Some(0x002C),
+    Some(0x002D),
+    Some(0x002E),
+    Some(0x002F),
+    Some(0x0030),
+    Some(0x0031),
+    Some(0x0032),
+    Some(0x0033),
+    Some(0x0034),
+    Some(0x0035),
+    Some(0x0036),
+    Some(0x0037),
+    Some(0x0038),
+    Some(0x0039),
+    Some(0x003A),
+    Some(0x003B),
+    Some(0x003C),
+    Some(0x003D),
+    Some(0x003E),
+    Some(0x003F),
+    Some(0x0040),
+    Some(0x0041),
+    Some(0x0042),
+    Some(0x0043),
+    Some(0x0044),
+    Some(0x0045),
+    Some(0x0046),
+    Some(0x0047),
+    Some(0x0048),
+    Some(0x0049),
+    Some(0x004A),
+    Some(0x004B),
+    Some(0x004C),
+    Some(0x004D),
+    Some(0x004E),
+    Some(0x004F),
+    Some(0x0050),
+    Some(0x0051),
+    Some(0x0052),
+    Some(0x0053),
+    Some(0x0054),
+    Some(0x0055),
+    Some(0x0056),
+    Some(0x0057),
+    Some(0x0058),
+    Some(0x0059),
+    Some(0x005A),
+    Some(0x005B),
+    Some(0x005C),
+    Some(0x005D),
+    Some(0x005E),
+    Some(0x005F),
+    Some(0x0060),
+    Some(0x0061),
+    Some(0x0062),
+    Some(0x0063),
+    Some(0x0064),
+    Some(0x0065),
+    Some(0x0066),
+    Some(0x0067),
+    Some(0x0068),
+    Some(0x0069),
+    Some(0x006A),
+    Some(0x006B),
+    Some(0x006C),
+    Some(0x006D),
+    Some(0x006E),
+    Some(0x006F),
+    Some(0x0070),
+    Some(0x0071),
+    Some(0x0072),
+    Some(0x0073),
+    Some(0x0074),
+    Some(0x0075),
+    Some(0x0076),
+    Some(0x0077),
+    Some(0x0078),
+    Some(0x0079),
+    Some(0x007A),
+    Some(0x007B),
+    Some(0x007C),
+    Some(0x007D),
+    Some(0x007E),
+    None,
+    Some(0x2022),
+    Some(0x2020),
+    Some(0x2021),
+    Some(0x2026),
+    Some(0x2014),
+    Some(0x2013),
+    Some(0x0192),
+    Some(0x2044),
+    Some(0x2039),
+    Some(0x203A),
+    Some(0x2212),
+    Some(0x2030),
+    Some(0x201E),
+    Some(0x201C),
+    Some(0x201D),
+    Some(0x2018),
+    Some(0x2019),
+    Some(0x201A),
+    Some(0x2122),
+    Some(0xFB01),
+    Some(0xFB02),
+    Some(0x0141),
+    Some(0x0152),
+    Some(0x0160),
+    Some(0x0178),
+    Some(0x017D),
+    Some(0x0131),
+    Some(0x0142),
+    Some(0x0153),
+    Some(0x0161),
+    Some(0x017E),
+    None,
+    Some(0x20AC),
+    Some(0x00A1),
+    Some(0x00A2),
+    Some(0x00A3),
+    Some(0x00A4),
+    Some(0x00A5),
+    Some(0x00A6),
+    Some(0x00A7),
+    Some(0x00A8),
+    Some(0x00A9),
+    Some(0x00AA),
+    Some(0x00AB),
+    Some(0x00AC),
+    None,
+    Some(0x00AE),
+    Some(0x00AF),
+    Some(0x00B0),
+    Some(0x00B1),
+    Some(0x00B2),
+    Some(0x00B3),
+    Some(0x00B4),
+    Some(0x00B5),
+    Some(0x00B6),
+    Some(0x00B7),
+    Some(0x00B8),
+    Some(0x00B9),
+    Some(0x00BA),
+    Some(0x00BB),
+    Some(0x00BC),
+    Some(0x00BD),
+    Some(0x00BE),
+    Some(0x00BF),
+    Some(0x00C0),
+    Some(0x00C1),
+    Some(0x00C2),
+    Some(0x00C3),
+    Some(0x00C4),
+    Some(0x00C5),
+    Some(0x00C6),
+    Some(0x00C7),
+    Some(0x00C8),
+    Some(0x00C9),
+    Some(0x00CA),
+    Some(0x00CB),
+    Some(0x00CC),
+    Some(0x00CD),
+    Some(0x00CE),
+    Some(0x00CF),
+    Some(0x00D0),
+    Some(0x00D1),
+    Some(0x00D2),
+    Some(0x00D3),
+    Some(0x00D4),
+    Some(0x00D5),
+    Some(0x00D6),
+    Some(0x00D7),
+    Some(0x00D8),
+    Some(0x00D9),
+    Some(0x00DA),
+    Some(0x00DB),
+    Some(0x00DC),
+    Some(0x00DD),
+    Some(0x00DE),
+    Some(0x00DF),
+    Some(0x00E0),
+    Some(0x00E1),
+    Some(0x00E2),
+    Some(0x00E3),
+    Some(0x00E4),
+    Some(0x00E5),
+    Some(0x00E6),
+    Some(0x00E7),
+    Some(0x00E8),
+    Some(0x00E9),
+    Some(0x00EA),
+    Some(0x00EB),
+    Some(0x00EC),
+    Some(0x00ED),
+    Some(0x00EE),
+    Some(0x00EF),
+    Some(0x00F0),
+    Some(0x00F1),
+    Some(0x00F2),
+    Some(0x00F3),
+    Some(0x00F4),
+    Some(0x00F5),
+    Some(0x00F6),
+    Some(0x00F7),
+    Some(0x00F8),
+    Some(0x00F9),
+    Some(0x00FA),
+    Some(0x00FB),
+    Some(0x00FC),
+    Some(0x00FD),
+    Some(0x00FE),
+    Some(0x00FF),
+];
@@ -17,3 +17,46 @@ pub fn string_to_bytes(encoding: [Option<u16>; 256], text: &str) -> Vec<u8> {
         .map(|byte| byte as u8)
         .collect()
 }
+
+/// Decode a PDF text string, which is either UTF-16BE or UTF-8 with a byte order mark, or PDFDocEncoding.
+pub fn decode_text_string(bytes: &[u8]) -> String {
+    if let Some(utf16) = bytes.strip_prefix(b"\xFE\xFF") {
+        let code_units = utf16
+            .chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect::<Vec<u16>>();
+        String::from_utf16_lossy(&code_units)
+    } else if let Some(utf8) = bytes.strip_prefix(b"\xEF\xBB\xBF") {
+        String::from_utf8_lossy(utf8).into_owned()
+    } else {
+        bytes_to_string(PDF_DOC_ENCODING, bytes)
+    }
+}
+
+/// Encode a PDF text string, in PDFDocEncoding if possible and as UTF-16BE otherwise.
+pub fn encode_text_string(text: &str) -> Vec<u8> {
+    let bytes = string_to_bytes(PDF_DOC_ENCODING, text);
+    if bytes.len() == text.chars().count() {
+        bytes
+    } else {
+        let mut bytes = vec![0xFE, 0xFF];
+        for code_unit in text.encode_utf16() {
+            bytes.extend_from_slice(&code_unit.to_be_bytes());
+        }
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_string_round_trip() {
+        assert_eq!(decode_text_string(b"Caf\xE9 \x80 \x84"), "Café • —");
+        assert_eq!(decode_text_string(b"\xFE\xFF\x00A\x4E\x2D"), "A中");
+        assert_eq!(decode_text_string(b"\xEF\xBB\xBFok"), "ok");
+        assert_eq!(encode_text_string("Café •"), b"Caf\xE9 \x80");
+        assert_eq!(decode_text_string(&encode_text_string("A中")), "A中");
+    }
+}
@@ -0,0 +1,1059 @@
+//! The standard security handler of password protected documents, with the RC4 and AES ciphers it relies on.
+
+use crate::digest::{md5, sha256, sha384, sha512};
+use crate::file_id::random_bytes;
+use crate::encodings::{string_to_bytes, PDF_DOC_ENCODING};
+use crate::{Dictionary, Document, Error, Object, ObjectId, Permissions, Result, StringFormat};
+use aes::cipher::{BlockCipher, BlockDecrypt, BlockDecryptMut, BlockEncrypt, BlockEncryptMut, InnerIvInit, KeyInit};
+use aes::{Aes128, Aes256};
+use log::warn;
+use rc4::consts::*;
+use rc4::{Rc4, StreamCipher};
+use std::collections::BTreeMap;
+
+/// Padding appended to passwords shorter than 32 bytes by the revision 2 to 4 handlers.
+const PADDING: [u8; 32] = [
+    0x28, 0xBF, 0x4E, 0x5E, 0x4E, 0x75, 0x8A, 0x41, 0x64, 0x00, 0x4E, 0x56, 0xFF, 0xFA, 0x01, 0x08, 0x2E, 0x2E, 0x00,
+    0xB6, 0xD0, 0x68, 0x3E, 0x80, 0x2F, 0x0C, 0xA9, 0xFE, 0x64, 0x53, 0x69, 0x7A,
+];
+
+/// The encryption a document is saved with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncryptionAlgorithm {
+    /// 128 bit RC4 (revision 3), readable by PDF 1.4 viewers but insecure.
+    Rc4,
+    /// 128 bit AES (revision 4), as introduced by PDF 1.6.
+    Aes128,
+    /// 256 bit AES (revision 6) of PDF 2.0.
+    Aes256,
+}
+
+/// Passwords and permissions of [`Document::encrypt`].
+#[derive(Debug, Clone)]
+pub struct EncryptionOptions {
+    /// Password needed to open the document, which may be empty.
+    pub user_password: String,
+    /// Password that lifts the permissions; the user password is used when empty.
+    pub owner_password: String,
+    /// What the document allows with the user password.
+    pub permissions: Permissions,
+    pub algorithm: EncryptionAlgorithm,
+    /// Whether the metadata stream is encrypted as well. Keeping it readable lets search engines index it; RC4
+    /// always encrypts it.
+    pub encrypt_metadata: bool,
+}
+
+impl Default for EncryptionOptions {
+    fn default() -> EncryptionOptions {
+        EncryptionOptions {
+            user_password: String::new(),
+            owner_password: String::new(),
+            permissions: Permissions::all(),
+            algorithm: EncryptionAlgorithm::Aes256,
+            encrypt_metadata: true,
+        }
+    }
+}
+
+/// What the encryption dictionary of a document says, read without a password by
+/// [`Document::encryption_info`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncryptionInfo {
+    /// Name of the security handler, `Standard` for password protection or `Adobe.PubSec` for public-key
+    /// encryption.
+    pub filter: String,
+    pub sub_filter: Option<String>,
+    /// The algorithm, `/V`.
+    pub version: i64,
+    /// Revision of the standard security handler, `/R`.
+    pub revision: Option<i64>,
+    /// Length of the file key in bits.
+    pub key_length: u32,
+    /// Crypt filter method (`/CFM`) of strings: `None`, `V2` for RC4, `AESV2` or `AESV3`.
+    pub string_method: String,
+    /// Crypt filter method of streams.
+    pub stream_method: String,
+    pub encrypt_metadata: bool,
+    /// Whether the empty user password opens the document, so there is no need to ask for a password.
+    pub opens_without_password: bool,
+    /// What the document allows with the user password, for the standard security handler.
+    pub permissions: Option<Permissions>,
+    /// Number of recipients the file key is encrypted for, for public-key encryption.
+    pub recipients: Option<usize>,
+}
+
+/// How the strings or streams of a document are encrypted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CryptMethod {
+    Identity,
+    Rc4,
+    AesV2,
+    AesV3,
+}
+
+/// The file key and crypt filters of a document, once a password has been verified.
+pub(crate) struct SecurityHandler {
+    key: Vec<u8>,
+    filters: BTreeMap<Vec<u8>, CryptMethod>,
+    strings: CryptMethod,
+    streams: CryptMethod,
+    encrypt_metadata: bool,
+    /// The `/P` entry of the encryption dictionary, with all its bits.
+    pub permissions: u32,
+    /// Whether the password verified is the owner password, rather than the user password.
+    pub owner: bool,
+}
+
+impl SecurityHandler {
+    /// Verify `password` against the encryption dictionary, first as the owner password and then as the user
+    /// password, and derive the file key.
+    pub fn new(encrypt: &Dictionary, file_id: &[u8], password: &str) -> Result<SecurityHandler> {
+        match encrypt.get(b"Filter").and_then(Object::as_name)? {
+            b"Standard" => {}
+            b"Adobe.PubSec" => return Err(Error::Unimplemented("public-key security handler (Adobe.PubSec)")),
+            _ => return Err(Error::Unimplemented("security handlers other than the standard one")),
+        }
+        let version = encrypt.get(b"V").and_then(Object::as_i64).unwrap_or(0);
+        let revision = encrypt.get(b"R").and_then(Object::as_i64)?;
+        let owner_hash = encrypt.get(b"O").and_then(Object::as_str)?;
+        let user_hash = encrypt.get(b"U").and_then(Object::as_str)?;
+        let permissions = encrypt.get(b"P").and_then(Object::as_i64)? as i32 as u32;
+        let encrypt_metadata = encrypt
+            .get(b"EncryptMetadata")
+            .and_then(Object::as_bool)
+            .unwrap_or(true);
+
+        let mut filters = BTreeMap::new();
+        let mut key_length = encrypt.get(b"Length").and_then(Object::as_i64).unwrap_or(40);
+        let (strings, streams) = match version {
+            1 | 2 => (CryptMethod::Rc4, CryptMethod::Rc4),
+            4 | 5 => {
+                if let Ok(crypt_filters) = encrypt.get(b"CF").and_then(Object::as_dict) {
+                    for (name, filter) in crypt_filters {
+                        let filter = filter.as_dict()?;
+                        let method = match filter.get(b"CFM").and_then(Object::as_name).unwrap_or(b"None") {
+                            b"V2" => CryptMethod::Rc4,
+                            b"AESV2" => CryptMethod::AesV2,
+                            b"AESV3" => CryptMethod::AesV3,
+                            _ => return Err(Error::Unimplemented("crypt filters without a standard method")),
+                        };
+                        if method == CryptMethod::Rc4 {
+                            if let Ok(length) = filter.get(b"Length").and_then(Object::as_i64) {
+                                // Meant to be in bytes, but often given in bits like the dictionary /Length.
+                                key_length = if length <= 16 { length * 8 } else { length };
+                            }
+                        }
+                        filters.insert(name.clone(), method);
+                    }
+                }
+                let method = |key: &[u8]| match encrypt.get(key).and_then(Object::as_name).unwrap_or(b"Identity") {
+                    b"Identity" => Ok(CryptMethod::Identity),
+                    name => filters.get(name).copied().ok_or_else(|| Error::DictKey {
+                        path: vec![b"CF".to_vec(), name.to_vec()],
+                    }),
+                };
+                (method(b"StrF")?, method(b"StmF")?)
+            }
+            _ => return Err(Error::Unimplemented("this encryption algorithm")),
+        };
+        let key_length = match version {
+            1 => 5,
+            5 => 32,
+            _ if [strings, streams].contains(&CryptMethod::AesV2) => 16,
+            _ => (key_length as usize / 8).clamp(5, 16),
+        };
+        filters.insert(b"Identity".to_vec(), CryptMethod::Identity);
+
+        let (key, owner) = if revision >= 5 {
+            let password = password.as_bytes();
+            let password = &password[..password.len().min(127)];
+            let hash = |salt: &[u8], user: &[u8]| {
+                if revision == 5 {
+                    sha256(&[password, salt, user].concat())
+                } else {
+                    hardened_hash(password, salt, user)
+                }
+            };
+            let (owner_hash, user_hash) = match (owner_hash.get(..48), user_hash.get(..48)) {
+                (Some(owner_hash), Some(user_hash)) => (owner_hash, user_hash),
+                _ => return Err(Error::Syntax("encryption dictionary hashes are too short".to_string())),
+            };
+            let wrapped_key = |name: &[u8], key: [u8; 32]| -> Result<Vec<u8>> {
+                let wrapped = encrypt.get(name).and_then(Object::as_str)?;
+                let wrapped = wrapped
+                    .get(..32)
+                    .ok_or_else(|| Error::Syntax("encryption dictionary keys are too short".to_string()))?;
+                Ok(cbc_decrypt(Aes256::new(&key.into()), &[0; 16], wrapped))
+            };
+            let (key, owner) = if hash(&owner_hash[32..40], user_hash) == owner_hash[..32] {
+                (wrapped_key(b"OE", hash(&owner_hash[40..48], user_hash))?, true)
+            } else if hash(&user_hash[32..40], &[]) == user_hash[..32] {
+                (wrapped_key(b"UE", hash(&user_hash[40..48], &[]))?, false)
+            } else {
+                return Err(Error::InvalidPassword);
+            };
+            check_perms(encrypt, &key, permissions)?;
+            (key, owner)
+        } else {
+            let legacy = LegacyKey {
+                revision,
+                key_length: key_length.min(16),
+                owner_hash,
+                user_hash,
+                permissions,
+                file_id,
+                encrypt_metadata,
+            };
+            let password = string_to_bytes(PDF_DOC_ENCODING, password);
+            if let Some(key) = legacy.check_user(&legacy.user_password(&password)) {
+                (key, true)
+            } else if let Some(key) = legacy.check_user(&password) {
+                (key, false)
+            } else {
+                return Err(Error::InvalidPassword);
+            }
+        };
+        Ok(SecurityHandler {
+            key,
+            filters,
+            strings,
+            streams,
+            encrypt_metadata,
+            permissions,
+            owner,
+        })
+    }
+
+    fn crypt(&self, id: ObjectId, method: CryptMethod, data: &[u8], encrypt: bool) -> Result<Vec<u8>> {
+        Ok(match (method, encrypt) {
+            (CryptMethod::Identity, _) => data.to_vec(),
+            (CryptMethod::Rc4, _) => rc4(&self.object_key(id, false), data),
+            (CryptMethod::AesV2, false) => aes_cbc_decrypt(&self.object_key(id, true), data)?,
+            (CryptMethod::AesV2, true) => aes_cbc_encrypt(&self.object_key(id, true), data)?,
+            (CryptMethod::AesV3, false) => aes_cbc_decrypt(&self.key, data)?,
+            (CryptMethod::AesV3, true) => aes_cbc_encrypt(&self.key, data)?,
+        })
+    }
+
+    /// The key of a single object, for revision 4 and earlier.
+    fn object_key(&self, id: ObjectId, aes: bool) -> Vec<u8> {
+        let mut data = self.key.clone();
+        data.extend_from_slice(&id.0.to_le_bytes()[..3]);
+        data.extend_from_slice(&id.1.to_le_bytes());
+        if aes {
+            data.extend_from_slice(b"sAlT");
+        }
+        md5(&data)[..(self.key.len() + 5).min(16)].to_vec()
+    }
+
+    /// Decrypt the strings and the stream of an object of the document.
+    pub fn decrypt_object(&self, id: ObjectId, object: &mut Object) -> Result<()> {
+        self.crypt_object(id, object, false)
+    }
+
+    /// Encrypt the strings and the stream of an object of the document.
+    pub fn encrypt_object(&self, id: ObjectId, object: &mut Object) -> Result<()> {
+        self.crypt_object(id, object, true)
+    }
+
+    fn crypt_object(&self, id: ObjectId, object: &mut Object, encrypt: bool) -> Result<()> {
+        match object {
+            Object::String(bytes, _) => *bytes = self.crypt(id, self.strings, bytes, encrypt)?,
+            Object::Array(array) => {
+                for item in array {
+                    self.crypt_object(id, item, encrypt)?;
+                }
+            }
+            Object::Dictionary(dict) => self.crypt_dictionary(id, dict, encrypt)?,
+            Object::Stream(stream) => {
+                self.crypt_dictionary(id, &mut stream.dict, encrypt)?;
+                if stream.dict.type_is(b"XRef") || (stream.dict.type_is(b"Metadata") && !self.encrypt_metadata) {
+                    return Ok(());
+                }
+                let method = match crypt_filter(&stream.dict) {
+                    Some(name) => {
+                        let method = self.filters.get(&name).copied().ok_or_else(|| Error::DictKey {
+                            path: vec![b"CF".to_vec(), name.clone()],
+                        })?;
+                        if !encrypt {
+                            remove_crypt_filter(&mut stream.dict);
+                        }
+                        method
+                    }
+                    None => self.streams,
+                };
+                let content = self.crypt(id, method, &stream.content, encrypt)?;
+                stream.set_content(content);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn crypt_dictionary(&self, id: ObjectId, dict: &mut Dictionary, encrypt: bool) -> Result<()> {
+        // The signature value is left as is, since the signed byte ranges are computed over the file itself.
+        let signature = dict.type_is(b"Sig") || dict.type_is(b"DocTimeStamp");
+        for (key, value) in dict.iter_mut() {
+            if !(signature && key == b"Contents") {
+                self.crypt_object(id, value, encrypt)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Cross-check `/P` against its encrypted copy in `/Perms`, which revision 5 and later keep so that the
+/// permissions cannot be changed without the file key.
+fn check_perms(encrypt: &Dictionary, key: &[u8], permissions: u32) -> Result<()> {
+    let perms = match encrypt
+        .get(b"Perms")
+        .and_then(Object::as_str)
+        .map(|perms| perms.get(..16))
+    {
+        Ok(Some(perms)) => perms,
+        _ => {
+            warn!("Encryption dictionary has no /Perms to check /P against");
+            return Ok(());
+        }
+    };
+    let mut block = [0; 16];
+    block.copy_from_slice(perms);
+    Aes256::new_from_slice(key)
+        .map_err(|_| aes_key_error(key))?
+        .decrypt_block((&mut block).into());
+    if &block[9..12] != b"adb" {
+        warn!("Encryption dictionary has an invalid /Perms");
+    } else if block[..4] != permissions.to_le_bytes() {
+        return Err(Error::Syntax(
+            "/P of the encryption dictionary does not match /Perms".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Everything of the encryption dictionary that the revision 2 to 4 key derivation uses.
+struct LegacyKey<'a> {
+    revision: i64,
+    key_length: usize,
+    owner_hash: &'a [u8],
+    user_hash: &'a [u8],
+    permissions: u32,
+    file_id: &'a [u8],
+    encrypt_metadata: bool,
+}
+
+impl LegacyKey<'_> {
+    /// The file key for a user password (algorithm 2).
+    fn file_key(&self, password: &[u8]) -> Vec<u8> {
+        let mut data = pad_password(password).to_vec();
+        data.extend_from_slice(self.owner_hash.get(..32).unwrap_or(self.owner_hash));
+        data.extend_from_slice(&self.permissions.to_le_bytes());
+        data.extend_from_slice(self.file_id);
+        if self.revision >= 4 && !self.encrypt_metadata {
+            data.extend_from_slice(&[0xFF; 4]);
+        }
+        let mut key = md5(&data);
+        if self.revision >= 3 {
+            for _ in 0..50 {
+                key = md5(&key[..self.key_length]);
+            }
+        }
+        key[..self.key_length].to_vec()
+    }
+
+    /// The `/U` value of a file key (algorithms 4 and 5), of which revision 3 and later only compare the first
+    /// 16 bytes.
+    fn user_hash(&self, key: &[u8]) -> Vec<u8> {
+        if self.revision == 2 {
+            rc4(key, &PADDING)
+        } else {
+            let hash = rc4(key, &md5(&[&PADDING[..], self.file_id].concat()));
+            (1..=19).fold(hash, |hash, i| rc4(&xor_key(key, i), &hash))
+        }
+    }
+
+    /// The file key, if `password` is the user password (algorithm 6).
+    fn check_user(&self, password: &[u8]) -> Option<Vec<u8>> {
+        let key = self.file_key(password);
+        let hash = self.user_hash(&key);
+        if self.user_hash.get(..hash.len()) == Some(&hash[..]) {
+            Some(key)
+        } else {
+            None
+        }
+    }
+
+    /// The RC4 key that encrypts the user password into `/O` (algorithm 3).
+    fn owner_key(&self, password: &[u8]) -> Vec<u8> {
+        let mut key = md5(&pad_password(password));
+        if self.revision >= 3 {
+            for _ in 0..50 {
+                key = md5(&key);
+            }
+        }
+        key[..self.key_length].to_vec()
+    }
+
+    /// The `/O` value of a pair of passwords (algorithm 3).
+    fn owner_hash(&self, owner_password: &[u8], user_password: &[u8]) -> Vec<u8> {
+        let key = self.owner_key(owner_password);
+        let hash = rc4(&key, &pad_password(user_password));
+        if self.revision == 2 {
+            hash
+        } else {
+            (1..=19).fold(hash, |hash, i| rc4(&xor_key(&key, i), &hash))
+        }
+    }
+
+    /// The user password that `/O` holds, assuming `password` is the owner password (algorithm 7).
+    fn user_password(&self, password: &[u8]) -> Vec<u8> {
+        let key = self.owner_key(password);
+        let owner_hash = self.owner_hash.get(..32).unwrap_or(self.owner_hash);
+        if self.revision == 2 {
+            rc4(&key, owner_hash)
+        } else {
+            (0..=19)
+                .rev()
+                .fold(owner_hash.to_vec(), |data, i| rc4(&xor_key(&key, i), &data))
+        }
+    }
+}
+
+fn pad_password(password: &[u8]) -> [u8; 32] {
+    let mut padded = PADDING;
+    let len = password.len().min(32);
+    padded.copy_from_slice(&[&password[..len], &PADDING[..32 - len]].concat());
+    padded
+}
+
+fn xor_key(key: &[u8], value: u8) -> Vec<u8> {
+    key.iter().map(|byte| byte ^ value).collect()
+}
+
+/// The hash of revision 6 passwords (algorithm 2.B).
+fn hardened_hash(password: &[u8], salt: &[u8], user: &[u8]) -> [u8; 32] {
+    let mut hash = sha256(&[password, salt, user].concat()).to_vec();
+    let mut round = 0;
+    loop {
+        let block = [password, &hash, user].concat().repeat(64);
+        let encrypted = cbc_encrypt(Aes128::new(hash[..16].into()), &hash[16..32], &block);
+        // The first 16 bytes as a big-endian number modulo 3, which is the sum of the bytes modulo 3.
+        let selector: u32 = encrypted[..16].iter().map(|&byte| u32::from(byte)).sum();
+        hash = match selector % 3 {
+            0 => sha256(&encrypted).to_vec(),
+            1 => sha384(&encrypted).to_vec(),
+            _ => sha512(&encrypted).to_vec(),
+        };
+        round += 1;
+        if round >= 64 && u32::from(encrypted[encrypted.len() - 1]) + 32 <= round {
+            break;
+        }
+    }
+    let mut result = [0; 32];
+    result.copy_from_slice(&hash[..32]);
+    result
+}
+
+/// The name of the crypt filter of a stream, if its first filter is `/Crypt`.
+fn crypt_filter(dict: &Dictionary) -> Option<Vec<u8>> {
+    let first = |object: &Object| match object {
+        Object::Array(array) => array.first().cloned(),
+        object => Some(object.clone()),
+    };
+    if first(dict.get(b"Filter").ok()?)?.as_name().ok()? != b"Crypt" {
+        return None;
+    }
+    let name = dict
+        .get(b"DecodeParms")
+        .ok()
+        .and_then(first)
+        .and_then(|parms| Some(parms.as_dict().ok()?.get(b"Name").ok()?.as_name().ok()?.to_vec()));
+    Some(name.unwrap_or_else(|| b"Identity".to_vec()))
+}
+
+fn remove_crypt_filter(dict: &mut Dictionary) {
+    let remaining = match dict.get(b"Filter") {
+        Ok(Object::Array(filters)) => filters[1..].to_vec(),
+        _ => Vec::new(),
+    };
+    let remaining_parms = match dict.get(b"DecodeParms") {
+        Ok(Object::Array(parms)) => parms.get(1..).map(<[Object]>::to_vec).unwrap_or_default(),
+        _ => Vec::new(),
+    };
+    if remaining.is_empty() {
+        dict.remove(b"Filter");
+    } else {
+        dict.set("Filter", remaining);
+    }
+    if remaining_parms.is_empty() {
+        dict.remove(b"DecodeParms");
+    } else {
+        dict.set("DecodeParms", remaining_parms);
+    }
+}
+
+/// RC4 stream cipher, which encrypts and decrypts alike, with a key of at most 16 bytes, the longest that the
+/// standard security handler derives.
+pub(crate) fn rc4(key: &[u8], data: &[u8]) -> Vec<u8> {
+    fn apply<C: KeyInit + StreamCipher>(key: &[u8], data: &mut [u8]) {
+        C::new(key.into()).apply_keystream(data);
+    }
+    let mut output = data.to_vec();
+    macro_rules! key_sizes {
+        ($($len:literal => $size:ident),*) => {
+            match key.len() {
+                $($len => apply::<Rc4<$size>>(key, &mut output),)*
+                len => unreachable!("RC4 key of {} bytes", len),
+            }
+        };
+    }
+    key_sizes!(
+        1 => U1, 2 => U2, 3 => U3, 4 => U4, 5 => U5, 6 => U6, 7 => U7, 8 => U8,
+        9 => U9, 10 => U10, 11 => U11, 12 => U12, 13 => U13, 14 => U14, 15 => U15, 16 => U16
+    );
+    output
+}
+
+/// Encrypt whole blocks in CBC mode, without padding.
+fn cbc_encrypt<C: BlockCipher + BlockEncryptMut>(cipher: C, iv: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mode = cbc::Encryptor::inner_iv_init(cipher, iv.into());
+    let mut output = data[..data.len() / C::block_size() * C::block_size()].to_vec();
+    for block in output.chunks_exact_mut(C::block_size()) {
+        mode.encrypt_block_mut(block.into());
+    }
+    output
+}
+
+/// Decrypt whole blocks in CBC mode, without removing padding.
+fn cbc_decrypt<C: BlockCipher + BlockDecryptMut>(cipher: C, iv: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mode = cbc::Decryptor::inner_iv_init(cipher, iv.into());
+    let mut output = data[..data.len() / C::block_size() * C::block_size()].to_vec();
+    for block in output.chunks_exact_mut(C::block_size()) {
+        mode.decrypt_block_mut(block.into());
+    }
+    output
+}
+
+/// The error for AES keys that are neither 128 nor 256 bits long, which crypt filters do not derive.
+fn aes_key_error(key: &[u8]) -> Error {
+    Error::Syntax(format!("AES key of {} bytes", key.len()))
+}
+
+/// Encrypt data with AES-CBC and PKCS #5 padding, after a random initialization vector.
+fn aes_cbc_encrypt(key: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+    let iv = random_bytes(16)?;
+    let padding = 16 - data.len() % 16;
+    let mut padded = data.to_vec();
+    padded.resize(data.len() + padding, padding as u8);
+    let mut encrypted = match key.len() {
+        16 => cbc_encrypt(Aes128::new(key.into()), &iv, &padded),
+        32 => cbc_encrypt(Aes256::new(key.into()), &iv, &padded),
+        _ => return Err(aes_key_error(key)),
+    };
+    encrypted.splice(0..0, iv);
+    Ok(encrypted)
+}
+
+/// Decrypt AES-CBC data that starts with its initialization vector and ends with PKCS #5 padding.
+fn aes_cbc_decrypt(key: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+    if data.len() < 16 {
+        return Ok(Vec::new());
+    }
+    let (iv, data) = data.split_at(16);
+    let length = data.len() / 16 * 16;
+    if length != data.len() {
+        warn!("AES encrypted data is not a whole number of blocks");
+    }
+    let mut plain = match key.len() {
+        16 => cbc_decrypt(Aes128::new(key.into()), iv, &data[..length]),
+        32 => cbc_decrypt(Aes256::new(key.into()), iv, &data[..length]),
+        _ => return Err(aes_key_error(key)),
+    };
+    match plain.last() {
+        Some(&pad) if (1..=16).contains(&pad) && plain.ends_with(&vec![pad; pad as usize]) => {
+            plain.truncate(plain.len() - pad as usize)
+        }
+        Some(_) => warn!("AES encrypted data has invalid padding"),
+        None => {}
+    }
+    Ok(plain)
+}
+
+impl Document {
+    /// The permissions of an encrypted document, or `None` if it is not encrypted.
+    pub fn permissions(&self) -> Option<Permissions> {
+        let (_, encrypt) = self.dereference(self.trailer.get(b"Encrypt").ok()?).ok()?;
+        let value = encrypt.as_dict().ok()?.get(b"P").and_then(Object::as_i64).ok()?;
+        Some(Permissions::from_p(value))
+    }
+
+    /// Describe the encryption of the document, or `None` if it is not encrypted.
+    ///
+    /// Encrypted documents load without a password, public-key encrypted ones included; their strings and
+    /// streams stay encrypted, and cannot be read, until [`Document::decrypt`] succeeds. Whether the empty user
+    /// password opens the document is checked against the `/U` and `/O` entries.
+    pub fn encryption_info(&self) -> Option<EncryptionInfo> {
+        let (_, encrypt) = self.dereference(self.trailer.get(b"Encrypt").ok()?).ok()?;
+        let encrypt = encrypt.as_dict().ok()?;
+        let name = |dict: &Dictionary, key: &[u8]| {
+            dict.get(key)
+                .and_then(Object::as_name)
+                .ok()
+                .map(|name| String::from_utf8_lossy(name).into_owned())
+        };
+        let filter = name(encrypt, b"Filter")?;
+        let version = encrypt.get(b"V").and_then(Object::as_i64).unwrap_or(0);
+        let crypt_filters = encrypt.get_deref(b"CF", self).and_then(Object::as_dict).ok();
+        let crypt_filter = |key: &[u8]| {
+            let filter_name = encrypt.get(key).and_then(Object::as_name).unwrap_or(b"Identity");
+            crypt_filters
+                .and_then(|filters| filters.get_deref(filter_name, self).and_then(Object::as_dict).ok())
+                .filter(|_| filter_name != b"Identity")
+        };
+        let method = |key: &[u8]| match version {
+            1..=3 => "V2".to_string(),
+            _ => crypt_filter(key)
+                .and_then(|filter| name(filter, b"CFM"))
+                .unwrap_or_else(|| "None".to_string()),
+        };
+        let key_length = match version {
+            1 => 40,
+            4 => match crypt_filter(b"StmF").map(|filter| filter.get(b"Length").and_then(Object::as_i64)) {
+                // Meant to be in bytes, but often given in bits.
+                Some(Ok(length)) if length <= 16 => length * 8,
+                Some(Ok(length)) => length,
+                _ => 128,
+            },
+            5 => 256,
+            _ => encrypt.get(b"Length").and_then(Object::as_i64).unwrap_or(40),
+        };
+        // Public-key encryption lists the recipients in the crypt filters from version 4 on.
+        let recipients = (filter == "Adobe.PubSec").then(|| {
+            let count = |dict: &Dictionary| match dict.get_deref(b"Recipients", self) {
+                Ok(Object::Array(recipients)) => recipients.len(),
+                Ok(Object::String(..)) => 1,
+                _ => 0,
+            };
+            count(encrypt)
+                + crypt_filters.map_or(0, |filters| {
+                    filters
+                        .iter()
+                        .filter_map(|(_, filter)| self.dereference(filter).ok()?.1.as_dict().ok())
+                        .map(count)
+                        .max()
+                        .unwrap_or(0)
+                })
+        });
+        let standard = filter == "Standard";
+        let file_id = self.file_id().map(|(permanent, _)| permanent).unwrap_or_default();
+        Some(EncryptionInfo {
+            sub_filter: name(encrypt, b"SubFilter"),
+            version,
+            revision: encrypt.get(b"R").and_then(Object::as_i64).ok(),
+            key_length: key_length as u32,
+            string_method: method(b"StrF"),
+            stream_method: method(b"StmF"),
+            encrypt_metadata: encrypt
+                .get(b"EncryptMetadata")
+                .and_then(Object::as_bool)
+                .unwrap_or(true),
+            opens_without_password: standard && SecurityHandler::new(encrypt, &file_id, "").is_ok(),
+            permissions: self.permissions().filter(|_| standard),
+            recipients,
+            filter,
+        })
+    }
+
+    /// Encrypt the strings and streams of the document with the standard security handler, so that it is saved
+    /// password protected. Until it is saved, the document holds encrypted data that the other methods do not
+    /// expect.
+    pub fn encrypt(&mut self, options: &EncryptionOptions) -> Result<()> {
+        if self.trailer.has(b"Encrypt") {
+            return Err(Error::Unimplemented("encrypting an already encrypted document"));
+        }
+        let file_id = self.permanent_file_id();
+
+        let user_password = options.user_password.as_str();
+        let owner_password = if options.owner_password.is_empty() {
+            user_password
+        } else {
+            options.owner_password.as_str()
+        };
+        let p = options.permissions.to_p();
+        let hex = |bytes: Vec<u8>| Object::String(bytes, StringFormat::Hexadecimal);
+        let (encrypt, key, method, encrypt_metadata) = match options.algorithm {
+            EncryptionAlgorithm::Rc4 | EncryptionAlgorithm::Aes128 => {
+                let aes = options.algorithm == EncryptionAlgorithm::Aes128;
+                if aes {
+                    // Fail now rather than when saving, on targets without the random initialization vectors.
+                    random_bytes(0)?;
+                }
+                let encrypt_metadata = options.encrypt_metadata || !aes;
+                let user_password = string_to_bytes(PDF_DOC_ENCODING, user_password);
+                let owner_password = string_to_bytes(PDF_DOC_ENCODING, owner_password);
+                let mut legacy = LegacyKey {
+                    revision: if aes { 4 } else { 3 },
+                    key_length: 16,
+                    owner_hash: &[],
+                    user_hash: &[],
+                    permissions: p as u32,
+                    file_id: &file_id,
+                    encrypt_metadata,
+                };
+                let owner_hash = legacy.owner_hash(&owner_password, &user_password);
+                legacy.owner_hash = &owner_hash;
+                let key = legacy.file_key(&user_password);
+                let mut user_hash = legacy.user_hash(&key);
+                user_hash.resize(32, 0);
+                let mut encrypt = dictionary! {
+                    "Filter" => "Standard",
+                    "V" => if aes { 4 } else { 2 },
+                    "R" => legacy.revision,
+                    "Length" => 128,
+                    "O" => hex(owner_hash.clone()),
+                    "U" => hex(user_hash),
+                    "P" => p,
+                };
+                if aes {
+                    encrypt.set(
+                        "CF",
+                        dictionary! {
+                            "StdCF" => dictionary! { "CFM" => "AESV2", "AuthEvent" => "DocOpen", "Length" => 16 },
+                        },
+                    );
+                    encrypt.set("StmF", "StdCF");
+                    encrypt.set("StrF", "StdCF");
+                    encrypt.set("EncryptMetadata", encrypt_metadata);
+                }
+                let method = if aes { CryptMethod::AesV2 } else { CryptMethod::Rc4 };
+                (encrypt, key, method, encrypt_metadata)
+            }
+            EncryptionAlgorithm::Aes256 => {
+                let truncate = |password: &'_ str| password.as_bytes()[..password.len().min(127)].to_vec();
+                let (user_password, owner_password) = (truncate(user_password), truncate(owner_password));
+                let key = random_bytes(32)?;
+                let salts = random_bytes(32)?;
+                let wrap = |hash: [u8; 32]| cbc_encrypt(Aes256::new(&hash.into()), &[0; 16], &key);
+                let user_hash = [&hardened_hash(&user_password, &salts[..8], &[])[..], &salts[..16]].concat();
+                let user_key = wrap(hardened_hash(&user_password, &salts[8..16], &[]));
+                let owner_hash = [
+                    &hardened_hash(&owner_password, &salts[16..24], &user_hash)[..],
+                    &salts[16..32],
+                ]
+                .concat();
+                let owner_key = wrap(hardened_hash(&owner_password, &salts[24..32], &user_hash));
+                let mut perms = [0; 16];
+                perms[..4].copy_from_slice(&(p as u32).to_le_bytes());
+                perms[4..8].copy_from_slice(&[0xFF; 4]);
+                perms[8] = if options.encrypt_metadata { b'T' } else { b'F' };
+                perms[9..12].copy_from_slice(b"adb");
+                perms[12..].copy_from_slice(&random_bytes(4)?);
+                Aes256::new(key[..].into()).encrypt_block((&mut perms).into());
+                let encrypt = dictionary! {
+                    "Filter" => "Standard",
+                    "V" => 5,
+                    "R" => 6,
+                    "Length" => 256,
+                    "CF" => dictionary! {
+                        "StdCF" => dictionary! { "CFM" => "AESV3", "AuthEvent" => "DocOpen", "Length" => 32 },
+                    },
+                    "StmF" => "StdCF",
+                    "StrF" => "StdCF",
+                    "EncryptMetadata" => options.encrypt_metadata,
+                    "O" => hex(owner_hash),
+                    "U" => hex(user_hash),
+                    "OE" => hex(owner_key),
+                    "UE" => hex(user_key),
+                    "Perms" => hex(perms.to_vec()),
+                    "P" => p,
+                };
+                (encrypt, key, CryptMethod::AesV3, options.encrypt_metadata)
+            }
+        };
+
+        let mut filters = BTreeMap::new();
+        filters.insert(b"StdCF".to_vec(), method);
+        filters.insert(b"Identity".to_vec(), CryptMethod::Identity);
+        let handler = SecurityHandler {
+            key,
+            filters,
+            strings: method,
+            streams: method,
+            encrypt_metadata,
+            permissions: p as u32,
+            owner: true,
+        };
+        for (&id, object) in self.objects.iter_mut() {
+            handler.encrypt_object(id, object)?;
+        }
+        let encrypt_id = self.add_object(encrypt);
+        self.trailer.set("Encrypt", encrypt_id);
+
+        let version = match options.algorithm {
+            EncryptionAlgorithm::Rc4 => "1.4",
+            EncryptionAlgorithm::Aes128 => "1.6",
+            EncryptionAlgorithm::Aes256 => "2.0",
+        };
+        if self.version.as_str() < version {
+            self.version = version.to_string();
+        }
+        Ok(())
+    }
+
+    /// Remove the encryption of the document, so that it is saved without a password.
+    ///
+    /// Fails with [`Error::OwnerPasswordRequired`] if `password` is only the user password and the document
+    /// restricts what its users may do; use [`decrypt_with`](Document::decrypt_with) to decrypt it anyway.
+    pub fn decrypt(&mut self, password: &str) -> Result<()> {
+        self.decrypt_with(password, false)
+    }
+
+    /// Remove the encryption of the document, with the owner or user password. The permissions of the
+    /// document are bypassed with the user password only if `force` is set.
+    pub fn decrypt_with(&mut self, password: &str, force: bool) -> Result<()> {
+        let encrypt = match self.trailer.get(b"Encrypt") {
+            Ok(encrypt) => encrypt,
+            Err(_) => return Ok(()),
+        };
+        let (encrypt_id, encrypt) = self.dereference(encrypt)?;
+        let file_id = self.file_id().map(|(permanent, _)| permanent).unwrap_or_default();
+        let handler = SecurityHandler::new(encrypt.as_dict()?, &file_id, password)?;
+        let permissions = Permissions::from_bits_truncate(handler.permissions);
+        if !handler.owner && !force && !permissions.is_all() {
+            return Err(Error::OwnerPasswordRequired(permissions));
+        }
+
+        for (&id, object) in self.objects.iter_mut() {
+            if Some(id) != encrypt_id {
+                handler.decrypt_object(id, object)?;
+            }
+        }
+        if let Some(encrypt_id) = encrypt_id {
+            self.objects.remove(&encrypt_id);
+        }
+        self.trailer.remove(b"Encrypt");
+        #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+        self.expand_object_streams();
+        Ok(())
+    }
+
+    /// Move the objects of object streams, which the reader leaves packed in encrypted documents, into the
+    /// document.
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    fn expand_object_streams(&mut self) {
+        let stream_ids: Vec<ObjectId> = self
+            .objects
+            .iter()
+            .filter(|(_, object)| matches!(object, Object::Stream(stream) if stream.dict.type_is(b"ObjStm")))
+            .map(|(&id, _)| id)
+            .collect();
+        let mut compressed = Vec::new();
+        for id in stream_ids {
+            if let Some(Object::Stream(stream)) = self.objects.remove(&id) {
+                match crate::object_stream::ObjectStream::new(id, &stream) {
+                    Ok(object_stream) => {
+                        self.parse_warnings.extend(object_stream.warnings);
+                        let xref = &self.reference_table;
+                        compressed.extend(object_stream.objects.into_iter().map(|(object_id, object)| {
+                            (object_id, xref.stream_object_rank(object_id.0, id.0), object)
+                        }));
+                    }
+                    Err(err) => warn!("Could not read object stream {:?}: {}", id, err),
+                }
+            }
+        }
+        // As when loading, the object stream that the entry of an object names is the one it is taken from.
+        compressed.sort_by_key(|(id, rank, _)| (*id, *rank));
+        for (id, rank, object) in compressed {
+            if rank < 3 {
+                self.objects.entry(id).or_insert(object);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(text: &str) -> Vec<u8> {
+        (0..text.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn cipher_test_vectors() {
+        assert_eq!(rc4(b"Key", b"Plaintext"), hex("bbf316e8d940af0ad3"));
+
+        let plain = hex("00112233445566778899aabbccddeeff");
+        for (key, cipher) in [
+            ("000102030405060708090a0b0c0d0e0f", "69c4e0d86a7b0430d8cdb78070b4c55a"),
+            (
+                "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+                "8ea2b7ca516745bfeafc49904b496089",
+            ),
+        ] {
+            // With a zero initialization vector, CBC on a single block is the block cipher itself.
+            let key = hex(key);
+            let crypt = |data: &[u8], encrypt: bool| match (key.len(), encrypt) {
+                (16, true) => cbc_encrypt(Aes128::new(key[..].into()), &[0; 16], data),
+                (16, false) => cbc_decrypt(Aes128::new(key[..].into()), &[0; 16], data),
+                (_, true) => cbc_encrypt(Aes256::new(key[..].into()), &[0; 16], data),
+                (_, false) => cbc_decrypt(Aes256::new(key[..].into()), &[0; 16], data),
+            };
+            assert_eq!(crypt(&plain, true), hex(cipher));
+            assert_eq!(crypt(&hex(cipher), false), plain);
+        }
+
+        let key = hex("2b7e151628aed2a6abf7158809cf4f3c");
+        let iv = hex("000102030405060708090a0b0c0d0e0f");
+        let message = b"Sixteen byte msgAnd then some more.";
+        let mut padded = message.to_vec();
+        padded.extend_from_slice(&[13; 13]);
+        let encrypted = [iv.clone(), cbc_encrypt(Aes128::new(key[..].into()), &iv, &padded)].concat();
+        assert_eq!(aes_cbc_decrypt(&key, &encrypted).unwrap(), message.to_vec());
+    }
+
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    fn assert_decrypted(doc: &mut Document, title: &str) {
+        assert!(!doc.trailer.has(b"Encrypt"));
+        let mut saved = Vec::new();
+        doc.save_to(&mut saved).unwrap();
+        let doc = Document::load_mem(&saved).unwrap();
+        assert!(!doc.trailer.has(b"Encrypt"));
+        let page_id = doc.page_iter().next().unwrap();
+        assert_eq!(
+            doc.get_page_content(page_id).unwrap(),
+            b"BT /F1 24 Tf 72 700 Td (Hello encrypted world) Tj ET"
+        );
+        let info = doc.trailer.get(b"Info").and_then(Object::as_reference).unwrap();
+        let info = doc.get_dictionary(info).unwrap();
+        assert_eq!(info.get(b"Title").and_then(Object::as_str).unwrap(), title.as_bytes());
+    }
+
+    #[test]
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    fn decrypt_documents() {
+        let mut doc = Document::load("assets/encrypted_rc4.pdf").unwrap();
+        doc.decrypt("").unwrap();
+        assert_decrypted(&mut doc, "RC4 document");
+
+        let mut doc = Document::load("assets/encrypted_aes256.pdf").unwrap();
+        assert!(matches!(doc.decrypt("user"), Err(Error::InvalidPassword)));
+        doc.decrypt("usér").unwrap();
+        assert_decrypted(&mut doc, "AES-256 document");
+        let mut doc = Document::load("assets/encrypted_aes256.pdf").unwrap();
+        doc.decrypt("owner").unwrap();
+        assert_decrypted(&mut doc, "AES-256 document");
+    }
+
+    #[test]
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    fn decrypt_with_user_password_and_restricted_permissions() {
+        let mut doc = Document::load("assets/encrypted_aes128.pdf").unwrap();
+        assert!(matches!(doc.decrypt("wrong"), Err(Error::InvalidPassword)));
+        assert!(matches!(
+            doc.decrypt("user"),
+            Err(Error::OwnerPasswordRequired(Permissions::PRINT))
+        ));
+        assert_eq!(doc.permissions(), Some(Permissions::PRINT));
+        assert!(doc.trailer.has(b"Encrypt"));
+        doc.decrypt_with("user", true).unwrap();
+        // With /EncryptMetadata false, the metadata stream was never encrypted.
+        let catalog = doc.catalog().unwrap();
+        let metadata = catalog.get(b"Metadata").and_then(Object::as_reference).unwrap();
+        let metadata = doc.get_object(metadata).and_then(Object::as_stream).unwrap();
+        assert_eq!(*metadata.content, br#"<x:xmpmeta xmlns:x="adobe:ns:meta/"/>"#);
+        assert_decrypted(&mut doc, "AES document");
+
+        let mut doc = Document::load("assets/encrypted_aes128.pdf").unwrap();
+        doc.decrypt("owner").unwrap();
+        assert_decrypted(&mut doc, "AES document");
+    }
+
+    #[test]
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    fn describe_encryption() {
+        let doc = Document::load("assets/encrypted_aes128.pdf").unwrap();
+        let info = doc.encryption_info().unwrap();
+        assert_eq!(
+            info,
+            EncryptionInfo {
+                filter: "Standard".to_string(),
+                sub_filter: None,
+                version: 4,
+                revision: Some(4),
+                key_length: 128,
+                string_method: "AESV2".to_string(),
+                stream_method: "AESV2".to_string(),
+                encrypt_metadata: false,
+                opens_without_password: false,
+                permissions: Some(Permissions::PRINT),
+                recipients: None,
+            }
+        );
+        let mut doc = Document::load("assets/encrypted_rc4.pdf").unwrap();
+        let info = doc.encryption_info().unwrap();
+        assert!(info.opens_without_password);
+        assert_eq!((info.string_method.as_str(), info.revision), ("V2", Some(2)));
+        doc.decrypt("").unwrap();
+        assert!(doc.encryption_info().is_none());
+
+        let mut doc = crate::creator::tests::create_document();
+        let recipients = vec![Object::string_literal("first"), Object::string_literal("second")];
+        let encrypt = doc.add_object(dictionary! {
+            "Filter" => "Adobe.PubSec",
+            "SubFilter" => "adbe.pkcs7.s5",
+            "V" => 5,
+            "CF" => dictionary! {
+                "DefaultCryptFilter" => dictionary! { "CFM" => "AESV3", "Recipients" => recipients },
+            },
+            "StrF" => "DefaultCryptFilter",
+            "StmF" => "DefaultCryptFilter",
+        });
+        doc.trailer.set("Encrypt", encrypt);
+        let info = doc.encryption_info().unwrap();
+        assert_eq!((info.recipients, info.key_length), (Some(2), 256));
+        assert_eq!((info.string_method.as_str(), info.permissions), ("AESV3", None));
+        assert!(matches!(doc.decrypt(""), Err(Error::Unimplemented(_))));
+    }
+
+    #[test]
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    fn encrypt_and_decrypt_documents() {
+        for &algorithm in &[
+            EncryptionAlgorithm::Rc4,
+            EncryptionAlgorithm::Aes128,
+            EncryptionAlgorithm::Aes256,
+        ] {
+            let mut doc = crate::creator::tests::create_document();
+            let page_id = doc.page_iter().next().unwrap();
+            let content = doc.get_page_content(page_id).unwrap();
+            let options = EncryptionOptions {
+                user_password: "user".to_string(),
+                owner_password: "owner".to_string(),
+                permissions: Permissions::PRINT | Permissions::COPY,
+                algorithm,
+                encrypt_metadata: false,
+            };
+            doc.encrypt(&options).unwrap();
+            assert!(matches!(doc.encrypt(&options), Err(Error::Unimplemented(_))));
+            let mut saved = Vec::new();
+            doc.save_to(&mut saved).unwrap();
+
+            let mut doc = Document::load_mem(&saved).unwrap();
+            assert_eq!(doc.permissions(), Some(Permissions::PRINT | Permissions::COPY));
+            assert!(matches!(
+                doc.decrypt("user"),
+                Err(Error::OwnerPasswordRequired(permissions)) if permissions == options.permissions
+            ));
+            assert!(matches!(doc.decrypt("other"), Err(Error::InvalidPassword)));
+            doc.decrypt("owner").unwrap();
+            assert_eq!(doc.permissions(), None);
+            let page_id = doc.page_iter().next().unwrap();
+            assert_eq!(doc.get_page_content(page_id).unwrap(), content);
+        }
+    }
+}
@@ -1,47 +1,296 @@
-use std::fmt;
+use crate::{ObjectId, Permissions};
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
 
+/// An error of lopdf. Those found deep in a document say where: [`Error::context`] gives the object, byte offset,
+/// dictionary keys, references followed and phase of loading or saving that are known.
 #[derive(Debug)]
 pub enum Error {
     ContentDecode,
-    DictKey,
+    /// A required dictionary key was not found: the path of keys to it, from the outermost dictionary given
+    /// context, ends with the missing key when it is known.
+    DictKey { path: KeyPath },
+    /// A form field name that is empty or conflicts with the name of another field.
+    FieldName(String),
+    /// The permanent file identifier of an encrypted document was changed, which its encryption key depends on.
+    FileIdChanged,
     Header,
-    IO(std::io::Error),
+    /// Reading or writing failed, in the phase given when it is known.
+    IO {
+        source: crate::io::Error,
+        phase: Option<Phase>,
+    },
+    /// The password does not open the encrypted document.
+    InvalidPassword,
     ObjectIdMismatch,
-    ObjectNotFound,
+    /// An object was not found: its id when it is known, and the references followed to it, if any.
+    ObjectNotFound {
+        id: Option<ObjectId>,
+        chain: Vec<ObjectId>,
+    },
     Offset(usize),
+    /// The document was opened with its user password, but the action needs the owner password because of the
+    /// permission flags (`/P`) given.
+    OwnerPasswordRequired(Permissions),
     PageNumberNotFound(u32),
+    /// An object could not be parsed: the offset it starts at, and its id when it is known.
     Parse {
         offset: usize,
+        object: Option<ObjectId>,
     },
-    ReferenceLimit,
+    /// Too many references were followed, as in a loop: the ids followed.
+    ReferenceLimit { chain: Vec<ObjectId> },
     BracketLimit,
     Trailer,
-    Type,
+    /// An object does not have the expected type: the names of both types, and the path of dictionary keys to the
+    /// object when it is known.
+    Type {
+        expected: &'static str,
+        found: &'static str,
+        path: KeyPath,
+    },
     UTF8,
     Syntax(String),
+    /// A signature field or signature that cannot be used as requested.
+    Signature(String),
+    /// The action needs the file the document was loaded from, which was not kept with it (see
+    /// `ParseOptions::keep_source`).
+    SourceNotKept,
+    /// A feature of the file that lopdf does not (yet) handle.
+    Unimplemented(&'static str),
     Xref(XrefError),
     #[cfg(feature = "embed_image")]
     Image(image::ImageError),
 }
 
+/// Dictionary keys, each the key of a dictionary in the value of the one before.
+pub type KeyPath = Vec<Vec<u8>>;
+
+/// The phase of loading or saving a document an input or output error happened in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Reading the file.
+    Read,
+    /// Reading a cross-reference table or stream.
+    Xref,
+    /// Reading the trailer.
+    Trailer,
+    /// Reading or decoding an object.
+    Object(ObjectId),
+    /// Writing the document.
+    Write,
+}
+
+/// A problem of a file that loading worked around instead of failing, kept in the `parse_warnings` of the
+/// document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseWarning {
+    pub kind: ParseWarningKind,
+    /// The object with the problem, or the object stream holding it.
+    pub object: Option<ObjectId>,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseWarningKind {
+    /// The offsets of the header of an object stream, or its `/First`, do not match its content, so its objects
+    /// were read one after the other instead, up to the first one that could not be.
+    ObjectStreamOffsets,
+    /// The `/N` of an object stream is not the number of objects in its header, whose pairs are used.
+    ObjectStreamCount,
+    /// The entries of several object numbers lead to definitions of the same object, of which one is used.
+    DuplicateObject,
+    /// A number in a form the syntax does not allow, with several signs or an exponent, or out of the range of
+    /// numbers, was read as viewers read it.
+    Number,
+    /// Content operations stop before the end of their data, in the middle of an operation or at one that cannot be
+    /// parsed; the operations before were kept.
+    TruncatedContent,
+}
+
+/// Where an error happened, as far as it is known, from [`Error::context`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ErrorContext<'a> {
+    /// The object being read or looked for.
+    pub object: Option<ObjectId>,
+    /// The offset in the file.
+    pub offset: Option<usize>,
+    /// The dictionary keys to the value with the error.
+    pub path: &'a [Vec<u8>],
+    /// The references followed.
+    pub references: &'a [ObjectId],
+    pub phase: Option<Phase>,
+}
+
+impl Error {
+    /// A missing dictionary key.
+    pub(crate) fn dict_key(key: &[u8]) -> Error {
+        Error::DictKey { path: vec![key.to_vec()] }
+    }
+
+    /// An object with a type other than the one expected.
+    pub(crate) fn type_mismatch(expected: &'static str, found: &'static str) -> Error {
+        Error::Type {
+            expected,
+            found,
+            path: KeyPath::new(),
+        }
+    }
+
+    #[cfg_attr(not(feature = "std"), allow(dead_code))]
+    pub(crate) fn not_found(id: ObjectId) -> Error {
+        Error::ObjectNotFound {
+            id: Some(id),
+            chain: Vec::new(),
+        }
+    }
+
+    /// The error of a value found under a key of a dictionary: the key is put first in the path of a missing key or
+    /// of an object of the wrong type.
+    pub fn at_key(mut self, key: &[u8]) -> Error {
+        if let Error::DictKey { path } | Error::Type { path, .. } = &mut self {
+            path.insert(0, key.to_vec());
+        }
+        self
+    }
+
+    /// The error of reading or decoding an object, for errors that do not say which object yet.
+    pub fn in_object(self, id: ObjectId) -> Error {
+        match self {
+            Error::Parse { offset, object: None } => Error::Parse {
+                offset,
+                object: Some(id),
+            },
+            Error::IO { source, phase: None } => Error::IO {
+                source,
+                phase: Some(Phase::Object(id)),
+            },
+            err => err,
+        }
+    }
+
+    /// The error in a phase of loading or saving, for input or output errors that do not say so yet.
+    pub fn in_phase(self, phase: Phase) -> Error {
+        match self {
+            Error::IO { source, phase: None } => Error::IO {
+                source,
+                phase: Some(phase),
+            },
+            err => err,
+        }
+    }
+
+    /// The error of resolving a reference after following `chain`, for objects not found.
+    #[cfg_attr(not(feature = "std"), allow(dead_code))]
+    pub(crate) fn after_references(mut self, followed: &[ObjectId]) -> Error {
+        if let Error::ObjectNotFound { chain, .. } | Error::ReferenceLimit { chain } = &mut self {
+            chain.splice(0..0, followed.iter().copied());
+        }
+        self
+    }
+
+    /// Where the error happened, as far as it is known.
+    pub fn context(&self) -> ErrorContext<'_> {
+        let mut context = ErrorContext::default();
+        match self {
+            Error::DictKey { path } | Error::Type { path, .. } => context.path = path,
+            Error::IO { phase, .. } => {
+                context.phase = *phase;
+                if let Some(Phase::Object(id)) = phase {
+                    context.object = Some(*id);
+                }
+            }
+            Error::ObjectNotFound { id, chain } => {
+                context.object = *id;
+                context.references = chain;
+            }
+            Error::ReferenceLimit { chain } => context.references = chain,
+            Error::Offset(offset) => context.offset = Some(*offset),
+            Error::Parse { offset, object } => {
+                context.offset = Some(*offset);
+                context.object = *object;
+            }
+            Error::Xref(_) => context.phase = Some(Phase::Xref),
+            Error::Trailer => context.phase = Some(Phase::Trailer),
+            _ => {}
+        }
+        context
+    }
+}
+
+/// Write a path of keys as `/Root/Pages`.
+fn write_path(f: &mut fmt::Formatter, path: &[Vec<u8>]) -> fmt::Result {
+    for key in path {
+        write!(f, "/{}", String::from_utf8_lossy(key))?;
+    }
+    Ok(())
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Error::ContentDecode => write!(f, "Could not decode content"),
-            Error::DictKey => write!(f, "A required dictionary key was not found"),
+            Error::DictKey { path } if path.is_empty() => write!(f, "A required dictionary key was not found"),
+            Error::DictKey { path } => {
+                write!(f, "A required dictionary key was not found: ")?;
+                write_path(f, path)
+            }
+            Error::FieldName(name) => write!(f, "Invalid form field name: {}", name),
+            Error::FileIdChanged => write!(
+                f,
+                "The permanent file identifier of an encrypted document was changed; it could not be opened"
+            ),
             Error::Header => write!(f, "Invalid file header"),
-            Error::IO(e) => e.fmt(f),
+            Error::IO { source, phase: None } => source.fmt(f),
+            Error::IO {
+                source,
+                phase: Some(phase),
+            } => write!(f, "{} ({})", source, phase),
+            Error::InvalidPassword => write!(f, "Incorrect password for the encrypted document"),
             Error::ObjectIdMismatch => write!(f, "The object id found did not match the requested object"),
-            Error::ObjectNotFound => write!(f, "A required object was not found"),
+            Error::ObjectNotFound { id: None, .. } => write!(f, "A required object was not found"),
+            Error::ObjectNotFound { id: Some(id), chain } => {
+                write!(f, "Object {} {} was not found", id.0, id.1)?;
+                match chain.first() {
+                    Some(first) => write!(f, " (referenced from {} {})", first.0, first.1),
+                    None => Ok(()),
+                }
+            }
             Error::Offset(o) => write!(f, "Invalid file offset: {}", o),
+            Error::OwnerPasswordRequired(p) => {
+                write!(f, "The owner password is required, the user password only grants {}", p)
+            }
             Error::PageNumberNotFound(p) => write!(f, "Page number {} could not be found", p),
-            Error::Parse { offset, .. } => write!(f, "Invalid object at byte {}", offset),
-            Error::ReferenceLimit => write!(f, "Could not dereference an object; possible reference loop"),
+            Error::Parse { offset, object: None } => write!(f, "Invalid object at byte {}", offset),
+            Error::Parse {
+                offset,
+                object: Some(id),
+            } => write!(f, "Invalid object {} {} at byte {}", id.0, id.1, offset),
+            Error::ReferenceLimit { chain } => match chain.first() {
+                Some(first) => write!(
+                    f,
+                    "Could not dereference object {} {}; possible reference loop",
+                    first.0, first.1
+                ),
+                None => write!(f, "Could not dereference an object; possible reference loop"),
+            },
             Error::BracketLimit => write!(f, "Too deep embedding of ()'s."),
             Error::Trailer => write!(f, "Invalid file trailer"),
-            Error::Type => write!(f, "An object does not have the expected type"),
+            Error::Type { expected, found, path } => {
+                write!(f, "Expected {}, found {}", expected, found)?;
+                if !path.is_empty() {
+                    write!(f, " at ")?;
+                    write_path(f, path)?;
+                }
+                Ok(())
+            }
             Error::UTF8 => write!(f, "UTF-8 error"),
             Error::Syntax(msg) => write!(f, "Syntax error: {}", msg),
+            Error::Signature(msg) => write!(f, "Signature error: {}", msg),
+            Error::SourceNotKept => write!(f, "The file the document was loaded from was not kept"),
+            Error::Unimplemented(feature) => write!(f, "Unimplemented: {}", feature),
             Error::Xref(e) => write!(f, "Invalid cross-reference table ({})", e),
             #[cfg(feature = "embed_image")]
             Error::Image(e) => e.fmt(f),
@@ -49,7 +298,30 @@ impl fmt::Display for Error {
     }
 }
 
-impl std::error::Error for Error {}
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Phase::Read => write!(f, "reading the file"),
+            Phase::Xref => write!(f, "reading the cross-reference table"),
+            Phase::Trailer => write!(f, "reading the trailer"),
+            Phase::Object(id) => write!(f, "reading object {} {}", id.0, id.1),
+            Phase::Write => write!(f, "writing the document"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::IO { source, .. } => Some(source),
+            Error::Xref(err) => Some(err),
+            #[cfg(feature = "embed_image")]
+            Error::Image(err) => Some(err),
+            _ => None,
+        }
+    }
+}
 
 #[derive(Debug)]
 pub enum XrefError {
@@ -70,24 +342,28 @@ impl fmt::Display for XrefError {
     }
 }
 
+#[cfg(feature = "std")]
 impl std::error::Error for XrefError {}
 
-pub type Result<T> = std::result::Result<T, Error>;
+pub type Result<T> = core::result::Result<T, Error>;
 
-impl From<std::io::Error> for Error {
-    fn from(err: std::io::Error) -> Self {
-        Error::IO(err)
+impl From<crate::io::Error> for Error {
+    fn from(err: crate::io::Error) -> Self {
+        Error::IO {
+            source: err,
+            phase: None,
+        }
     }
 }
 
-impl From<std::string::FromUtf8Error> for Error {
-    fn from(_err: std::string::FromUtf8Error) -> Self {
+impl From<alloc::string::FromUtf8Error> for Error {
+    fn from(_err: alloc::string::FromUtf8Error) -> Self {
         Error::UTF8
     }
 }
 
-impl From<std::str::Utf8Error> for Error {
-    fn from(_err: std::str::Utf8Error) -> Self {
+impl From<core::str::Utf8Error> for Error {
+    fn from(_err: core::str::Utf8Error) -> Self {
         Error::UTF8
     }
 }
@@ -98,3 +374,35 @@ impl From<image::ImageError> for Error {
         Error::Image(err)
     }
 }
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::{Dictionary, Document, Object};
+
+    #[test]
+    fn missing_object_keeps_references_followed() {
+        let mut doc = Document::with_version("1.5");
+        doc.objects.insert((1, 0), Object::Reference((2, 0)));
+        doc.objects.insert((2, 0), Object::Reference((3, 0)));
+
+        let err = doc.get_object((1, 0)).unwrap_err();
+        let context = err.context();
+        assert_eq!(context.object, Some((3, 0)));
+        assert_eq!(context.references, [(1, 0), (2, 0)]);
+        assert!(err.to_string().contains("1 0"));
+    }
+
+    #[test]
+    fn key_paths_built_from_innermost_key() {
+        let pages = Dictionary::new();
+        let err = pages
+            .get(b"Kids")
+            .unwrap_err()
+            .at_key(b"Pages")
+            .at_key(b"Root");
+        assert_eq!(err.context().path, [b"Root".to_vec(), b"Pages".to_vec(), b"Kids".to_vec()]);
+
+        let err = Object::Integer(1).as_dict().unwrap_err().at_key(b"Root");
+        assert_eq!(err.to_string(), "Expected Dictionary, found Integer at /Root");
+    }
+}
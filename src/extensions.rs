@@ -0,0 +1,311 @@
+use crate::forms::FieldType;
+use crate::{Dictionary, Document, Object, ObjectId, Result};
+
+/// Keys of `/Perms` holding usage rights signatures: `/UR3`, and `/UR` of PDF 1.5.
+const USAGE_RIGHTS: [&[u8]; 2] = [b"UR3", b"UR"];
+
+/// A developer extension declared in the `/Extensions` of the catalog.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeveloperExtension {
+    /// The prefix of the developer, such as `ADBE`.
+    pub prefix: String,
+    /// `/BaseVersion`, the PDF version the extension builds on.
+    pub base_version: Option<String>,
+    /// `/ExtensionLevel`.
+    pub extension_level: Option<i64>,
+    /// `/URL` of the documentation of the extension.
+    pub url: Option<String>,
+}
+
+/// A permission signature of the `/Perms` of the catalog.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PermissionSignature {
+    /// The key of the signature in `/Perms`: `UR3` or `UR` for usage rights, `DocMDP` for a certification.
+    pub key: String,
+    /// The signature dictionary, unless it is stored directly in `/Perms`.
+    pub signature: Option<ObjectId>,
+    /// `/Filter` of the signature dictionary, the handler that created it, such as `Adobe.PPKLite`.
+    pub filter: Option<String>,
+    /// `/SubFilter` of the signature dictionary.
+    pub sub_filter: Option<String>,
+    /// The signature field whose value is the signature, if any.
+    pub field: Option<ObjectId>,
+}
+
+impl PermissionSignature {
+    /// Whether this is a usage rights signature, which an application adds to enable features of a viewer and
+    /// which is invalidated by any change to the document.
+    pub fn is_usage_rights(&self) -> bool {
+        USAGE_RIGHTS.contains(&self.key.as_bytes())
+    }
+}
+
+/// The extensions and permission signatures declared by a document, from [`Document::extensions_report`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExtensionsReport {
+    pub extensions: Vec<DeveloperExtension>,
+    pub permissions: Vec<PermissionSignature>,
+}
+
+impl Document {
+    /// List the developer extensions of `/Extensions` and the permission signatures of `/Perms`, in the catalog.
+    pub fn extensions_report(&self) -> ExtensionsReport {
+        let mut report = ExtensionsReport::default();
+        let catalog = match self.catalog() {
+            Ok(catalog) => catalog,
+            Err(_) => return report,
+        };
+        let name = |dict: &Dictionary, key: &[u8]| {
+            dict.get_deref(key, self)
+                .and_then(Object::as_name_str)
+                .ok()
+                .map(str::to_string)
+        };
+
+        if let Ok(extensions) = catalog.get_deref(b"Extensions", self).and_then(Object::as_dict) {
+            for (prefix, entry) in extensions {
+                let prefix = String::from_utf8_lossy(prefix).into_owned();
+                // PDF 2.0 allows an array of extension dictionaries for a prefix.
+                let entries = match self.dereference(entry) {
+                    Ok((_, Object::Array(entries))) => entries.iter().collect(),
+                    _ => vec![entry],
+                };
+                for entry in entries {
+                    let entry = match self.dereference(entry).and_then(|(_, entry)| entry.as_dict()) {
+                        Ok(entry) => entry,
+                        Err(_) => continue,
+                    };
+                    report.extensions.push(DeveloperExtension {
+                        prefix: prefix.clone(),
+                        base_version: name(entry, b"BaseVersion"),
+                        extension_level: entry.get_deref(b"ExtensionLevel", self).and_then(Object::as_i64).ok(),
+                        url: entry
+                            .get_deref(b"URL", self)
+                            .and_then(Object::as_str)
+                            .ok()
+                            .map(|url| String::from_utf8_lossy(url).into_owned()),
+                    });
+                }
+            }
+        }
+
+        if let Ok(perms) = catalog.get_deref(b"Perms", self).and_then(Object::as_dict) {
+            for (key, value) in perms {
+                let (signature, dict) = match self.dereference(value) {
+                    Ok((id, Object::Dictionary(dict))) => (id, dict),
+                    _ => continue,
+                };
+                report.permissions.push(PermissionSignature {
+                    key: String::from_utf8_lossy(key).into_owned(),
+                    signature,
+                    filter: name(dict, b"Filter"),
+                    sub_filter: name(dict, b"SubFilter"),
+                    field: signature.and_then(|id| self.signature_field(id)),
+                });
+            }
+        }
+        report
+    }
+
+    /// Remove the usage rights signatures (`/UR3` and `/UR`) from the `/Perms` of the catalog, and the signature
+    /// fields that only held them, with their widgets. `/Perms` is removed once empty. Returns whether there was
+    /// a usage rights signature.
+    ///
+    /// Any change to the document invalidates these signatures, and viewers then warn that the features they
+    /// enabled are no longer available. The `/Extensions` they usually come with can be removed with
+    /// [`Document::remove_extensions`]. The document can be saved in full or as an incremental update; the
+    /// objects that are no longer referenced can be deleted with [`Document::prune_objects`].
+    pub fn remove_usage_rights(&mut self) -> Result<bool> {
+        let root_id = self.trailer.get(b"Root")?.as_reference()?;
+        let (perms_id, mut perms) = match self.catalog()?.get(b"Perms").map(|perms| self.dereference(perms)) {
+            Ok(Ok((id, Object::Dictionary(perms)))) => (id, perms.clone()),
+            _ => return Ok(false),
+        };
+
+        let count = perms.len();
+        let mut fields = Vec::new();
+        for key in USAGE_RIGHTS {
+            if let Some(Object::Reference(signature)) = perms.remove(key) {
+                fields.extend(self.signature_field(signature));
+            }
+        }
+        if perms.len() == count {
+            return Ok(false);
+        }
+        for field in fields {
+            let widgets = self
+                .form_fields()
+                .into_iter()
+                .find(|candidate| candidate.id == field)
+                .map(|field| field.widgets)
+                .unwrap_or_default();
+            for widget in widgets {
+                if let Some(page_id) = widget.page {
+                    self.remove_annotation(page_id, widget.id)?;
+                }
+            }
+            self.remove_field(field)?;
+        }
+
+        match perms_id {
+            Some(id) if !perms.is_empty() => {
+                self.objects.insert(id, Object::Dictionary(perms));
+            }
+            _ => {
+                let catalog = self.get_object_mut(root_id)?.as_dict_mut()?;
+                if perms.is_empty() {
+                    catalog.remove(b"Perms");
+                } else {
+                    catalog.set("Perms", perms);
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    /// Remove the developer extension of a prefix, or all of them, from the `/Extensions` of the catalog, which is
+    /// removed once empty. Returns whether an extension was removed.
+    pub fn remove_extensions(&mut self, prefix: Option<&str>) -> Result<bool> {
+        let root_id = self.trailer.get(b"Root")?.as_reference()?;
+        let (extensions_id, mut extensions) = match self
+            .catalog()?
+            .get(b"Extensions")
+            .map(|extensions| self.dereference(extensions))
+        {
+            Ok(Ok((id, Object::Dictionary(extensions)))) => (id, extensions.clone()),
+            _ => return Ok(false),
+        };
+        let removed = match prefix {
+            Some(prefix) => extensions.remove(prefix.as_bytes()).is_some(),
+            None => {
+                let removed = !extensions.is_empty();
+                extensions = Dictionary::new();
+                removed
+            }
+        };
+        match extensions_id {
+            Some(id) if !extensions.is_empty() => {
+                self.objects.insert(id, Object::Dictionary(extensions));
+            }
+            _ => {
+                let catalog = self.get_object_mut(root_id)?.as_dict_mut()?;
+                if extensions.is_empty() {
+                    catalog.remove(b"Extensions");
+                } else {
+                    catalog.set("Extensions", extensions);
+                }
+            }
+        }
+        Ok(removed)
+    }
+
+    /// The signature field whose `/V` is a signature dictionary.
+    fn signature_field(&self, signature: ObjectId) -> Option<ObjectId> {
+        self.form_fields()
+            .into_iter()
+            .filter(|field| field.field_type == FieldType::Signature)
+            .find(|field| {
+                self.get_dictionary(field.id)
+                    .and_then(|field| field.get(b"V"))
+                    .and_then(Object::as_reference)
+                    .ok()
+                    == Some(signature)
+            })
+            .map(|field| field.id)
+    }
+}
+
+#[cfg(all(test, any(feature = "pom_parser", feature = "nom_parser")))]
+mod tests {
+    use super::*;
+    use crate::creator::tests::create_document;
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    use crate::creator::tests::load_keeping_source;
+    use crate::geometry::Rect;
+
+    /// A document enabled for a viewer: an `ADBE` extension and a usage rights signature held by a field.
+    fn usage_rights_document() -> Vec<u8> {
+        let mut doc = create_document();
+        let page_id = doc.page_iter().next().unwrap();
+        let field = doc
+            .add_signature_field(page_id, "rights", Rect::new(0.0, 0.0, 0.0, 0.0), &Default::default())
+            .unwrap();
+        let signature = doc.add_object(dictionary! {
+            "Type" => "Sig",
+            "Filter" => "Adobe.PPKLite",
+            "SubFilter" => "adbe.pkcs7.detached",
+            "Reference" => vec![dictionary! { "TransformMethod" => "UR3" }.into()],
+        });
+        doc.get_object_mut(field)
+            .unwrap()
+            .as_dict_mut()
+            .unwrap()
+            .set("V", signature);
+        let root_id = doc.trailer.get(b"Root").unwrap().as_reference().unwrap();
+        let catalog = doc.get_object_mut(root_id).unwrap().as_dict_mut().unwrap();
+        catalog.set("Perms", dictionary! { "UR3" => signature });
+        catalog.set(
+            "Extensions",
+            dictionary! { "ADBE" => dictionary! { "BaseVersion" => "1.7", "ExtensionLevel" => 8 } },
+        );
+        let mut data = Vec::new();
+        doc.save_to(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn report_and_remove_usage_rights() {
+        let original = usage_rights_document();
+        let mut doc = load_keeping_source(&original);
+        let report = doc.extensions_report();
+        assert_eq!(
+            report.extensions,
+            vec![DeveloperExtension {
+                prefix: "ADBE".to_string(),
+                base_version: Some("1.7".to_string()),
+                extension_level: Some(8),
+                url: None,
+            }]
+        );
+        assert_eq!(report.permissions.len(), 1);
+        let permission = &report.permissions[0];
+        assert!(permission.is_usage_rights());
+        assert_eq!(permission.filter.as_deref(), Some("Adobe.PPKLite"));
+        assert_eq!(permission.field, Some(doc.form_fields()[0].id));
+
+        assert!(doc.remove_usage_rights().unwrap());
+        assert!(!doc.remove_usage_rights().unwrap());
+        assert!(doc.remove_extensions(Some("ADBE")).unwrap());
+        let mut update = Vec::new();
+        doc.save_incremental_to(&mut update).unwrap();
+        assert!(update.starts_with(&original));
+
+        let doc = Document::load_mem(&update).unwrap();
+        assert_eq!(doc.extensions_report(), ExtensionsReport::default());
+        assert!(doc.form_fields().is_empty());
+        let page_id = doc.page_iter().next().unwrap();
+        assert!(doc.annotations(page_id).is_empty());
+    }
+
+    #[test]
+    fn keep_other_permissions() {
+        let mut doc = Document::load_mem(&usage_rights_document()).unwrap();
+        let root_id = doc.trailer.get(b"Root").unwrap().as_reference().unwrap();
+        let certification = doc.add_object(dictionary! { "Type" => "Sig", "Filter" => "Adobe.PPKLite" });
+        let catalog = doc.get_object_mut(root_id).unwrap().as_dict_mut().unwrap();
+        catalog
+            .get_mut(b"Perms")
+            .unwrap()
+            .as_dict_mut()
+            .unwrap()
+            .set("DocMDP", certification);
+
+        assert!(doc.remove_usage_rights().unwrap());
+        assert_eq!(doc.form_fields().len(), 0);
+        let report = doc.extensions_report();
+        assert_eq!(report.extensions.len(), 1);
+        assert_eq!(report.permissions.len(), 1);
+        assert_eq!(report.permissions[0].key, "DocMDP");
+        assert!(!report.permissions[0].is_usage_rights());
+    }
+}
@@ -0,0 +1,217 @@
+use crate::digest::md5;
+use crate::writer::{CountingWrite, Writer};
+use crate::{Document, Error, Object, Result, StringFormat};
+use std::io::Write;
+
+impl Document {
+    /// The file identifier, from the `/ID` entry of the trailer: the permanent identifier given to the file
+    /// when it was created, and the identifier of its current version.
+    pub fn file_id(&self) -> Option<(Vec<u8>, Vec<u8>)> {
+        match self.trailer.get(b"ID").and_then(Object::as_array).map(Vec::as_slice) {
+            Ok([permanent, changing]) => Some((permanent.as_str().ok()?.to_vec(), changing.as_str().ok()?.to_vec())),
+            _ => None,
+        }
+    }
+
+    /// Give the document a new identifier for its current version, and a new permanent identifier too unless
+    /// `keep_first` is set and it has one.
+    ///
+    /// The permanent identifier is part of the encryption key of an encrypted document, which cannot be opened
+    /// once it changes; [`Document::save_incremental`] refuses to save it.
+    pub fn regenerate_file_id(&mut self, keep_first: bool) {
+        let changing = self.new_file_id();
+        let permanent = match self.file_id() {
+            Some((permanent, _)) if keep_first => permanent,
+            _ => changing.clone(),
+        };
+        self.set_file_id(permanent, changing);
+    }
+
+    /// A new identifier: random, or in [deterministic](Document::deterministic) mode, and on targets without
+    /// random bytes, the MD5 digest of the objects of the document. Outside deterministic mode the time of the
+    /// [clock](Document::clock) is mixed in.
+    fn new_file_id(&self) -> Vec<u8> {
+        let mut seed = match random_bytes(16) {
+            Ok(bytes) if !self.deterministic => bytes,
+            _ => {
+                let mut objects = Vec::new();
+                let mut target = CountingWrite {
+                    inner: &mut objects,
+                    bytes_written: 0,
+                };
+                for (id, object) in &self.objects {
+                    // Writing to memory does not fail.
+                    let _ = write!(target, "{} {} obj ", id.0, id.1);
+                    let _ = Writer::write_object(&mut target, object, None, None);
+                }
+                if self.deterministic {
+                    return md5(&objects).to_vec();
+                }
+                objects
+            }
+        };
+        if let Some(now) = self.clock.now() {
+            seed.extend_from_slice(&now.unix_timestamp_nanos().to_le_bytes());
+        }
+        md5(&seed).to_vec()
+    }
+
+    fn set_file_id(&mut self, permanent: Vec<u8>, changing: Vec<u8>) {
+        self.trailer.set(
+            "ID",
+            vec![
+                Object::String(permanent, StringFormat::Hexadecimal),
+                Object::String(changing, StringFormat::Hexadecimal),
+            ],
+        );
+    }
+
+    /// The permanent identifier, which encryption keys are derived from, given to the document if it has none.
+    pub(crate) fn permanent_file_id(&mut self) -> Vec<u8> {
+        if self.file_id().is_none() {
+            self.regenerate_file_id(false);
+        }
+        self.file_id().map(|(permanent, _)| permanent).unwrap_or_default()
+    }
+
+    /// Identify the version of the document about to be saved after the version read from a file, whose
+    /// trailer is given: the permanent identifier is kept and the identifier of the version replaced, unless the
+    /// caller set a new one.
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    pub(crate) fn update_file_id(&mut self, original_trailer: &crate::Dictionary) -> crate::Result<()> {
+        let original = match original_trailer
+            .get(b"ID")
+            .and_then(Object::as_array)
+            .map(Vec::as_slice)
+        {
+            Ok([permanent, changing]) => permanent.as_str().ok().zip(changing.as_str().ok()),
+            _ => None,
+        };
+        let (original_permanent, original_changing) = match original {
+            Some(original) => original,
+            None => {
+                self.permanent_file_id();
+                return Ok(());
+            }
+        };
+        match self.file_id() {
+            Some((permanent, _)) if permanent != original_permanent && original_trailer.has(b"Encrypt") => {
+                Err(crate::Error::FileIdChanged)
+            }
+            Some((_, changing)) if changing != original_changing => Ok(()),
+            _ => {
+                let changing = self.new_file_id();
+                self.set_file_id(original_permanent.to_vec(), changing);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Unpredictable bytes for keys, salts and initialization vectors, from the random number generator of the
+/// operating system.
+///
+/// `wasm32-unknown-unknown` has none, and an error is returned there rather than predictable bytes.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub(crate) fn random_bytes(len: usize) -> Result<Vec<u8>> {
+    let mut bytes = vec![0; len];
+    getrandom::getrandom(&mut bytes).map_err(|err| Error::IO {
+        source: err.into(),
+        phase: None,
+    })?;
+    Ok(bytes)
+}
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub(crate) fn random_bytes(_len: usize) -> Result<Vec<u8>> {
+    Err(Error::Unimplemented("random bytes without an operating system"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::creator::tests::create_document;
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    use crate::creator::tests::load_keeping_source;
+    use crate::Document;
+    #[cfg(feature = "crypto")]
+    use crate::EncryptionOptions;
+
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    #[test]
+    fn file_id_of_saved_documents() {
+        let mut doc = create_document();
+        assert!(doc.file_id().is_none());
+        let mut file = Vec::new();
+        doc.save_to(&mut file).unwrap();
+        let (permanent, changing) = doc.file_id().unwrap();
+        assert_eq!((permanent.len(), &permanent), (16, &changing));
+        // Saving again in full keeps the identifier.
+        let mut copy = Vec::new();
+        doc.save_to(&mut copy).unwrap();
+        assert_eq!(doc.file_id().unwrap(), (permanent.clone(), changing.clone()));
+
+        let mut doc = Document::load_mem(&file).unwrap();
+        assert_eq!(doc.file_id().unwrap(), (permanent.clone(), changing.clone()));
+        doc.regenerate_file_id(true);
+        let (regenerated, new_changing) = doc.file_id().unwrap();
+        assert_eq!(regenerated, permanent);
+        assert_ne!(new_changing, changing);
+        doc.regenerate_file_id(false);
+        assert_ne!(doc.file_id().unwrap().0, permanent);
+    }
+
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    #[test]
+    fn incremental_saves_keep_permanent_file_id() {
+        let mut doc = create_document();
+        let mut file = Vec::new();
+        doc.save_to(&mut file).unwrap();
+        let (permanent, changing) = doc.file_id().unwrap();
+
+        let mut doc = load_keeping_source(&file);
+        doc.trailer.set("Test", true);
+        let mut update = Vec::new();
+        doc.save_incremental_to(&mut update).unwrap();
+        let updated = Document::load_mem(&update).unwrap();
+        let (updated_permanent, updated_changing) = updated.file_id().unwrap();
+        assert_eq!(updated_permanent, permanent);
+        assert_ne!(updated_changing, changing);
+
+        // A new identifier set by the caller is written as is.
+        let mut doc = load_keeping_source(&file);
+        doc.regenerate_file_id(false);
+        let regenerated = doc.file_id().unwrap();
+        let mut update = Vec::new();
+        doc.save_incremental_to(&mut update).unwrap();
+        assert_eq!(Document::load_mem(&update).unwrap().file_id().unwrap(), regenerated);
+    }
+
+    #[cfg(all(feature = "crypto", any(feature = "pom_parser", feature = "nom_parser")))]
+    #[test]
+    fn encrypted_documents_keep_permanent_file_id() {
+        let mut doc = create_document();
+        doc.encrypt(&EncryptionOptions {
+            user_password: "user".to_string(),
+            ..EncryptionOptions::default()
+        })
+        .unwrap();
+        let (permanent, _) = doc.file_id().unwrap();
+        let mut file = Vec::new();
+        doc.save_to(&mut file).unwrap();
+
+        let mut doc = load_keeping_source(&file);
+        doc.regenerate_file_id(true);
+        let mut update = Vec::new();
+        doc.save_incremental_to(&mut update).unwrap();
+        let mut updated = Document::load_mem(&update).unwrap();
+        assert_eq!(updated.file_id().unwrap().0, permanent);
+        updated.decrypt("user").unwrap();
+
+        let mut doc = load_keeping_source(&file);
+        doc.regenerate_file_id(false);
+        assert!(matches!(
+            doc.save_incremental_to(&mut Vec::new()),
+            Err(crate::Error::FileIdChanged)
+        ));
+    }
+}
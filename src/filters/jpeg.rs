@@ -0,0 +1,76 @@
+/// Image properties read from the markers of JPEG (`DCTDecode`) data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JpegInfo {
+    pub width: u16,
+    pub height: u16,
+    pub components: u8,
+    pub bits_per_component: u8,
+    /// The data has an Adobe APP14 marker.
+    pub adobe: bool,
+}
+
+impl JpegInfo {
+    /// Adobe applications store the samples of CMYK images inverted, so a PDF image using this data needs a
+    /// `/Decode [1 0 1 0 1 0 1 0]` array to be painted with the intended colors.
+    pub fn is_inverted_cmyk(&self) -> bool {
+        self.adobe && self.components == 4
+    }
+}
+
+/// Read the frame header and Adobe marker of JPEG data without decoding it.
+///
+/// Returns `None` if the data is not a JPEG image.
+pub fn read_info(data: &[u8]) -> Option<JpegInfo> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let mut adobe = false;
+    let mut pos = 2;
+    loop {
+        // Markers may be preceded by any number of fill bytes.
+        while *data.get(pos)? == 0xFF && *data.get(pos + 1)? == 0xFF {
+            pos += 1;
+        }
+        if data[pos] != 0xFF {
+            return None;
+        }
+        let marker = *data.get(pos + 1)?;
+        pos += 2;
+        if marker == 0x01 || (0xD0..=0xD7).contains(&marker) {
+            continue;
+        }
+        let length = usize::from(u16::from_be_bytes([*data.get(pos)?, *data.get(pos + 1)?]));
+        let segment = data.get(pos + 2..pos + length)?;
+        match marker {
+            0xEE if segment.starts_with(b"Adobe") => adobe = true,
+            // Start of frame, excluding DHT (C4), JPG (C8) and DAC (CC).
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+                if segment.len() < 6 {
+                    return None;
+                }
+                return Some(JpegInfo {
+                    bits_per_component: segment[0],
+                    height: u16::from_be_bytes([segment[1], segment[2]]),
+                    width: u16::from_be_bytes([segment[3], segment[4]]),
+                    components: segment[5],
+                    adobe,
+                });
+            }
+            0xD9 | 0xDA => return None,
+            _ => {}
+        }
+        pos += length;
+    }
+}
+
+#[test]
+fn read_adobe_cmyk_header() {
+    let data = std::fs::read("assets/cmyk_adobe.jpg").unwrap();
+    let info = read_info(&data).unwrap();
+    assert_eq!(
+        (info.width, info.height, info.components, info.bits_per_component),
+        (16, 8, 4, 8)
+    );
+    assert!(info.is_inverted_cmyk());
+    assert_eq!(read_info(b"\x89PNG\r\n"), None);
+}
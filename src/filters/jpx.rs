@@ -0,0 +1,146 @@
+use std::convert::TryFrom;
+
+/// Image properties read from the header of JPEG 2000 (`JPXDecode`) data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JpxInfo {
+    pub width: u32,
+    pub height: u32,
+    pub components: u16,
+    /// Bit depth of the first component.
+    pub bits_per_component: u8,
+}
+
+const JP2_SIGNATURE: &[u8] = b"\x00\x00\x00\x0CjP  \r\n\x87\n";
+const CODESTREAM_START: &[u8] = b"\xFF\x4F\xFF\x51";
+
+/// Read the header of a JP2 file or a raw JPEG 2000 codestream without decoding it.
+///
+/// Returns `None` if the data is not JPEG 2000 data.
+pub fn read_info(data: &[u8]) -> Option<JpxInfo> {
+    if data.starts_with(JP2_SIGNATURE) {
+        read_jp2_info(data)
+    } else if data.starts_with(CODESTREAM_START) {
+        read_codestream_info(data)
+    } else {
+        None
+    }
+}
+
+fn read_jp2_info(data: &[u8]) -> Option<JpxInfo> {
+    let mut image_header = None;
+    let mut bit_depths = None;
+    let mut codestream = None;
+    for (box_type, contents) in boxes(data) {
+        match box_type {
+            b"jp2h" => {
+                for (box_type, contents) in boxes(contents) {
+                    match box_type {
+                        b"ihdr" => image_header = Some(contents),
+                        b"bpcc" => bit_depths = Some(contents),
+                        _ => {}
+                    }
+                }
+            }
+            b"jp2c" => codestream = Some(contents),
+            _ => {}
+        }
+    }
+    match image_header {
+        Some(ihdr) if ihdr.len() >= 11 => {
+            // A depth of 255 means that the components differ and are listed in the bpcc box.
+            let depth = if ihdr[10] == 255 {
+                *bit_depths?.first()?
+            } else {
+                ihdr[10]
+            };
+            Some(JpxInfo {
+                height: u32::from_be_bytes([ihdr[0], ihdr[1], ihdr[2], ihdr[3]]),
+                width: u32::from_be_bytes([ihdr[4], ihdr[5], ihdr[6], ihdr[7]]),
+                components: u16::from_be_bytes([ihdr[8], ihdr[9]]),
+                bits_per_component: (depth & 0x7F) + 1,
+            })
+        }
+        _ => read_codestream_info(codestream?),
+    }
+}
+
+/// Iterate over the boxes of a JP2 file as type and contents.
+fn boxes(mut data: &[u8]) -> impl Iterator<Item = (&[u8], &[u8])> {
+    std::iter::from_fn(move || {
+        let length = data.get(0..4)?;
+        let length = u32::from_be_bytes([length[0], length[1], length[2], length[3]]);
+        let box_type = data.get(4..8)?;
+        let (header, length) = match length {
+            0 => (8, data.len()),
+            1 => {
+                let length = data.get(8..16)?;
+                let mut bytes = [0; 8];
+                bytes.copy_from_slice(length);
+                (16, usize::try_from(u64::from_be_bytes(bytes)).ok()?)
+            }
+            length => (8, length as usize),
+        };
+        let contents = data.get(header..length)?;
+        data = &data[length..];
+        Some((box_type, contents))
+    })
+}
+
+fn read_codestream_info(data: &[u8]) -> Option<JpxInfo> {
+    if !data.starts_with(CODESTREAM_START) {
+        return None;
+    }
+    // The SIZ marker segment directly follows the start of codestream marker.
+    let siz = data.get(6..)?;
+    let number = |offset: usize| -> Option<u32> {
+        let bytes = siz.get(offset..offset + 4)?;
+        Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    };
+    let components = u16::from_be_bytes([*siz.get(34)?, *siz.get(35)?]);
+    Some(JpxInfo {
+        width: number(2)?.checked_sub(number(10)?)?,
+        height: number(6)?.checked_sub(number(14)?)?,
+        components,
+        bits_per_component: (siz.get(36)? & 0x7F) + 1,
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A JPEG 2000 codestream header for an 8-bit RGB image without any tile data.
+    pub fn codestream(width: u32, height: u32) -> Vec<u8> {
+        let mut siz = vec![0xFF, 0x4F, 0xFF, 0x51, 0, 47, 0, 0];
+        for value in &[width, height, 0, 0, width, height, 0, 0] {
+            siz.extend_from_slice(&value.to_be_bytes());
+        }
+        siz.extend_from_slice(&[0, 3]);
+        for _ in 0..3 {
+            siz.extend_from_slice(&[7, 1, 1]);
+        }
+        siz
+    }
+
+    #[test]
+    fn read_jp2_and_codestream_headers() {
+        let codestream = codestream(300, 200);
+        let expected = JpxInfo {
+            width: 300,
+            height: 200,
+            components: 3,
+            bits_per_component: 8,
+        };
+        assert_eq!(read_info(&codestream), Some(expected));
+
+        let mut jp2 = JP2_SIGNATURE.to_vec();
+        jp2.extend_from_slice(b"\x00\x00\x00\x14ftypjp2 \x00\x00\x00\x00jp2 ");
+        jp2.extend_from_slice(b"\x00\x00\x00\x1Ejp2h\x00\x00\x00\x16ihdr");
+        jp2.extend_from_slice(&[0, 0, 0, 200, 0, 0, 1, 44, 0, 3, 7, 7, 0, 0]);
+        jp2.extend_from_slice(&(codestream.len() as u32 + 8).to_be_bytes());
+        jp2.extend_from_slice(b"jp2c");
+        jp2.extend_from_slice(&codestream);
+        assert_eq!(read_info(&jp2), Some(expected));
+        assert_eq!(read_info(b"\xFF\xD8\xFF"), None);
+    }
+}
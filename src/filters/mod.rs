@@ -1 +1,3 @@
+pub mod jpeg;
+pub mod jpx;
 pub mod png;
@@ -0,0 +1,329 @@
+use crate::encodings;
+use crate::{Codecs, Dictionary, Document, Object, ObjectId};
+use std::collections::BTreeMap;
+
+/// The `/ToUnicode` maps of the fonts of some pages, parsed once so that pages can be decoded apart, and
+/// together, without parsing the same map again for each page.
+#[derive(Debug, Default)]
+pub(crate) struct FontCache {
+    to_unicode: BTreeMap<ObjectId, ToUnicodeMap>,
+}
+
+impl FontCache {
+    /// Parse the `/ToUnicode` maps of the fonts of the pages. Maps that cannot be read are left out, and the text
+    /// of their fonts decoded by encoding.
+    pub fn new(doc: &Document, page_ids: &[ObjectId]) -> Self {
+        let mut to_unicode = BTreeMap::new();
+        for &page_id in page_ids {
+            for font in doc.get_page_fonts(page_id).values() {
+                if let Ok(id) = font.get(b"ToUnicode").and_then(Object::as_reference) {
+                    if to_unicode.contains_key(&id) {
+                        continue;
+                    }
+                    if let Ok(stream) = doc.get_object(id).and_then(Object::as_stream) {
+                        let map = match stream.dict.has(b"Filter") {
+                            true => Codecs::with(|codecs| {
+                                codecs.decode_with(&stream.dict, &stream.content, ToUnicodeMap::parse).ok()
+                            }),
+                            false => Some(ToUnicodeMap::parse(&stream.content)),
+                        };
+                        if let Some(map) = map {
+                            to_unicode.insert(id, map);
+                        }
+                    }
+                }
+            }
+        }
+        FontCache { to_unicode }
+    }
+
+    /// Add the `/ToUnicode` map of a stream, given its decoded content.
+    #[cfg(feature = "borrowed")]
+    pub fn insert(&mut self, id: ObjectId, data: &[u8]) {
+        self.to_unicode.insert(id, ToUnicodeMap::parse(data));
+    }
+
+    #[cfg(feature = "borrowed")]
+    pub fn contains(&self, id: ObjectId) -> bool {
+        self.to_unicode.contains_key(&id)
+    }
+
+    /// Decode a string shown with a font: by its `/ToUnicode` map if it has one in the cache, else by its encoding.
+    /// The number of codes that have no text, missing from the map or else from a simple encoding, comes with it.
+    pub fn decode(&self, font: Option<&Dictionary>, bytes: &[u8]) -> (String, usize) {
+        let encoding = font.map(Dictionary::get_font_encoding);
+        let to_unicode = font.and_then(|font| font.get(b"ToUnicode").and_then(Object::as_reference).ok());
+        self.decode_with(encoding, to_unicode, bytes)
+    }
+
+    /// Decode a string shown with a font of an encoding and `/ToUnicode` map, with the number of codes that have
+    /// no text.
+    pub fn decode_with(&self, encoding: Option<&str>, to_unicode: Option<ObjectId>, bytes: &[u8]) -> (String, usize) {
+        match to_unicode.and_then(|id| self.to_unicode.get(&id)) {
+            Some(map) => map.decode(bytes, encoding),
+            None => {
+                let table = match encoding {
+                    None | Some("StandardEncoding") => Some(encodings::STANDARD_ENCODING),
+                    Some("MacRomanEncoding") => Some(encodings::MAC_ROMAN_ENCODING),
+                    Some("MacExpertEncoding") => Some(encodings::MAC_EXPERT_ENCODING),
+                    Some("WinAnsiEncoding") => Some(encodings::WIN_ANSI_ENCODING),
+                    Some(_) => None,
+                };
+                let unmapped = table.map_or(0, |table| {
+                    bytes.iter().filter(|&&byte| table[byte as usize].is_none()).count()
+                });
+                (Document::decode_text(encoding, bytes), unmapped)
+            }
+        }
+    }
+}
+
+/// A `/ToUnicode` CMap: the ranges of its character codes, and the text of the codes.
+#[derive(Debug, Default)]
+struct ToUnicodeMap {
+    /// The code space ranges, as the bytes of their lowest and highest codes.
+    code_space: Vec<(Vec<u8>, Vec<u8>)>,
+    chars: BTreeMap<Vec<u8>, String>,
+    /// `bfrange` mappings to a first text, whose last UTF-16 unit is incremented along the range.
+    ranges: Vec<(Vec<u8>, Vec<u8>, Vec<u16>)>,
+}
+
+impl ToUnicodeMap {
+    /// Parse the `codespacerange`, `bfchar` and `bfrange` sections of a CMap, ignoring everything else.
+    fn parse(data: &[u8]) -> Self {
+        let mut map = ToUnicodeMap::default();
+        let tokens = cmap_tokens(data);
+        let mut section = None;
+        let mut i = 0;
+        while i < tokens.len() {
+            match (&tokens[i], section) {
+                (CMapToken::Keyword(keyword), _) => {
+                    section = match keyword.as_str() {
+                        "begincodespacerange" | "beginbfchar" | "beginbfrange" => Some(keyword.as_str()),
+                        _ => None,
+                    };
+                    i += 1;
+                }
+                (CMapToken::Hex(low), Some("begincodespacerange")) => {
+                    if let Some(CMapToken::Hex(high)) = tokens.get(i + 1) {
+                        map.code_space.push((low.clone(), high.clone()));
+                    }
+                    i += 2;
+                }
+                (CMapToken::Hex(code), Some("beginbfchar")) => {
+                    if let Some(CMapToken::Hex(text)) = tokens.get(i + 1) {
+                        map.chars.insert(code.clone(), utf16_text(&utf16_units(text)));
+                    }
+                    i += 2;
+                }
+                (CMapToken::Hex(low), Some("beginbfrange")) => {
+                    match (tokens.get(i + 1), tokens.get(i + 2)) {
+                        (Some(CMapToken::Hex(high)), Some(CMapToken::Hex(text))) => {
+                            map.ranges.push((low.clone(), high.clone(), utf16_units(text)));
+                        }
+                        (Some(CMapToken::Hex(_)), Some(CMapToken::Array(texts))) => {
+                            let mut code = low.clone();
+                            for text in texts {
+                                map.chars.insert(code.clone(), utf16_text(&utf16_units(text)));
+                                increment(&mut code);
+                            }
+                        }
+                        _ => {}
+                    }
+                    i += 3;
+                }
+                _ => i += 1,
+            }
+        }
+        if map.code_space.is_empty() {
+            // Without code space ranges, the codes are as long as those that are mapped.
+            let length = map
+                .chars
+                .keys()
+                .next()
+                .or_else(|| map.ranges.first().map(|range| &range.0))
+                .map_or(1, Vec::len);
+            map.code_space.push((vec![0; length], vec![0xFF; length]));
+        }
+        map
+    }
+
+    /// Decode a string code by code. Single-byte codes the map has no text for are decoded by the encoding, and
+    /// other codes left out and counted.
+    fn decode(&self, bytes: &[u8], encoding: Option<&str>) -> (String, usize) {
+        let mut text = String::new();
+        let mut unmapped = 0;
+        let mut rest = bytes;
+        while !rest.is_empty() {
+            let code_length = self
+                .code_space
+                .iter()
+                .find(|(low, high)| {
+                    low.len() <= rest.len()
+                        && rest[..low.len()]
+                            .iter()
+                            .zip(low.iter().zip(high))
+                            .all(|(byte, (low, high))| (low..=high).contains(&byte))
+                })
+                .map(|(low, _)| low.len().max(1));
+            let (code, next) = rest.split_at(code_length.unwrap_or(1));
+            match self.lookup(code) {
+                Some(decoded) => text.push_str(&decoded),
+                None if code_length == Some(1) => text.push_str(&Document::decode_text(encoding, code)),
+                None => unmapped += 1,
+            }
+            rest = next;
+        }
+        (text, unmapped)
+    }
+
+    fn lookup(&self, code: &[u8]) -> Option<String> {
+        if let Some(text) = self.chars.get(code) {
+            return Some(text.clone());
+        }
+        let (low, _, first) = self
+            .ranges
+            .iter()
+            .find(|(low, high, _)| low.len() == code.len() && low.as_slice() <= code && code <= high.as_slice())?;
+        let offset = code_value(code) - code_value(low);
+        let mut units = first.clone();
+        let last = units.last_mut()?;
+        *last = last.wrapping_add(offset as u16);
+        Some(utf16_text(&units))
+    }
+}
+
+enum CMapToken {
+    Hex(Vec<u8>),
+    Array(Vec<Vec<u8>>),
+    Keyword(String),
+}
+
+/// The hexadecimal strings, arrays of them and keywords of a CMap. Comments, names, numbers, literal strings and
+/// dictionaries are skipped, as only the mappings are read.
+fn cmap_tokens(data: &[u8]) -> Vec<CMapToken> {
+    let mut tokens = Vec::new();
+    let mut array: Option<Vec<Vec<u8>>> = None;
+    let mut i = 0;
+    while i < data.len() {
+        match data[i] {
+            b'%' => {
+                while i < data.len() && data[i] != b'\n' && data[i] != b'\r' {
+                    i += 1;
+                }
+            }
+            b'<' if data.get(i + 1) == Some(&b'<') => i += 2,
+            b'>' if data.get(i + 1) == Some(&b'>') => i += 2,
+            b'<' => {
+                let end = data[i..]
+                    .iter()
+                    .position(|&byte| byte == b'>')
+                    .map_or(data.len(), |end| i + end);
+                let hex = hex_bytes(&data[i + 1..end]);
+                match array {
+                    Some(ref mut array) => array.push(hex),
+                    None => tokens.push(CMapToken::Hex(hex)),
+                }
+                i = end + 1;
+            }
+            b'[' => {
+                array = Some(Vec::new());
+                i += 1;
+            }
+            b']' => {
+                tokens.extend(array.take().map(CMapToken::Array));
+                i += 1;
+            }
+            b'(' => {
+                let mut depth = 0;
+                while i < data.len() {
+                    match data[i] {
+                        b'\\' => i += 1,
+                        b'(' => depth += 1,
+                        b')' => depth -= 1,
+                        _ => {}
+                    }
+                    i += 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+            }
+            byte if byte.is_ascii_alphabetic() => {
+                let start = i;
+                while i < data.len() && data[i].is_ascii_alphanumeric() {
+                    i += 1;
+                }
+                if array.is_none() {
+                    tokens.push(CMapToken::Keyword(
+                        String::from_utf8_lossy(&data[start..i]).into_owned(),
+                    ));
+                }
+            }
+            b'/' => {
+                i += 1;
+                while i < data.len() && !data[i].is_ascii_whitespace() && !b"/<>[]()%".contains(&data[i]) {
+                    i += 1;
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    tokens
+}
+
+/// The bytes of the digits of a hexadecimal string, a missing last digit being 0.
+fn hex_bytes(digits: &[u8]) -> Vec<u8> {
+    let digits: Vec<u8> = digits
+        .iter()
+        .filter_map(|&digit| char::from(digit).to_digit(16).map(|digit| digit as u8))
+        .collect();
+    digits
+        .chunks(2)
+        .map(|pair| pair[0] << 4 | pair.get(1).copied().unwrap_or(0))
+        .collect()
+}
+
+fn utf16_units(bytes: &[u8]) -> Vec<u16> {
+    bytes
+        .chunks(2)
+        .map(|pair| u16::from(pair[0]) << 8 | u16::from(pair.get(1).copied().unwrap_or(0)))
+        .collect()
+}
+
+fn utf16_text(units: &[u16]) -> String {
+    String::from_utf16_lossy(units)
+}
+
+fn code_value(code: &[u8]) -> u32 {
+    code.iter().fold(0, |value, &byte| value << 8 | u32::from(byte))
+}
+
+fn increment(code: &mut [u8]) {
+    for byte in code.iter_mut().rev() {
+        let (next, overflow) = byte.overflowing_add(1);
+        *byte = next;
+        if !overflow {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_with_to_unicode_map() {
+        let map = ToUnicodeMap::parse(
+            b"%!PS-Adobe-3.0 Resource-CMap\n/CIDInit /ProcSet findresource begin\n12 dict begin\nbegincmap\n\
+              /CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n\
+              1 begincodespacerange <0000> <FFFF> endcodespacerange\n\
+              2 beginbfchar\n<0001> <0048>\n<0002> <D83DDE00>\nendbfchar\n\
+              2 beginbfrange\n<0010> <0019> <0030>\n<0020> <0021> [<0066006C> <0416>]\nendbfrange\n\
+              endcmap\nend\nend\n",
+        );
+        assert_eq!(map.decode(&[0, 1, 0, 0x13, 0, 0x20, 0, 0x21, 0, 2], None), ("H3flЖ😀".to_string(), 0));
+        assert_eq!(map.decode(&[0, 0x30], None), (String::new(), 1));
+    }
+}
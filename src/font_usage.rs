@@ -0,0 +1,383 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::annotations::Appearance;
+use crate::forms::appearance::DefaultAppearance;
+use crate::{Dictionary, Document, Object, ObjectId, Result};
+
+/// What uses a font at a [`FontUseSite`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FontUseKind {
+    /// The resources of a page.
+    Page,
+    /// The resources of a page tree node, which the pages below it inherit.
+    PageTree,
+    /// The resources of a form XObject drawn by a page, an appearance stream or another form XObject.
+    FormXObject,
+    /// The resources of a Type 3 font, whose glyphs the font draws with.
+    Type3Font,
+    /// The resources of an appearance stream of an annotation.
+    Appearance { annotation: ObjectId },
+    /// The default resources (`/DR`) of the interactive form.
+    FormResources,
+    /// The default appearance string (`/DA`) of the interactive form, a field or a widget, which names a font of
+    /// the default resources.
+    DefaultAppearance,
+}
+
+/// A place where a font object is used, found by [`Document::font_usage`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FontUseSite {
+    pub kind: FontUseKind,
+    /// The object with the resources or the default appearance string: the page, page tree node, form XObject,
+    /// Type 3 font or appearance stream, or the field, widget or interactive form, which is the catalog when the
+    /// form is stored in it.
+    pub owner: ObjectId,
+    /// The name of the font in the resources.
+    pub name: Vec<u8>,
+    /// The object holding the reference to the font and the keys leading to it from there, for all but default
+    /// appearance strings.
+    entry: Option<(ObjectId, Vec<Vec<u8>>)>,
+}
+
+/// An object, and the keys leading from it to an entry of it or of its direct dictionaries.
+type Location = (ObjectId, Vec<Vec<u8>>);
+
+/// The walk of the places fonts are used from.
+struct FontWalk<'a> {
+    doc: &'a Document,
+    usage: BTreeMap<ObjectId, Vec<FontUseSite>>,
+    /// The resources to visit, of form XObjects and Type 3 fonts found in other resources.
+    pending: Vec<(ObjectId, FontUseKind)>,
+    /// The form XObjects and Type 3 fonts visited or to visit, which can use one another.
+    seen: BTreeSet<ObjectId>,
+}
+
+impl<'a> FontWalk<'a> {
+    /// Record the fonts of the resources of a page, page tree node, form XObject, Type 3 font or appearance stream.
+    fn resources(&mut self, owner: ObjectId, kind: FontUseKind) {
+        let dict = match self.doc.get_object(owner) {
+            Ok(Object::Dictionary(dict)) => dict,
+            Ok(Object::Stream(stream)) => &stream.dict,
+            _ => return,
+        };
+        if let Some((resources, location)) = entry(self.doc, dict, b"Resources", (owner, Vec::new())) {
+            self.resource_fonts(owner, kind, resources, location);
+        }
+    }
+
+    /// Record the fonts of a resource dictionary, and queue the form XObjects and Type 3 fonts it uses. Returns
+    /// the fonts by name.
+    fn resource_fonts(
+        &mut self, owner: ObjectId, kind: FontUseKind, resources: &'a Dictionary, location: Location,
+    ) -> BTreeMap<Vec<u8>, ObjectId> {
+        let doc = self.doc;
+        let mut fonts_by_name = BTreeMap::new();
+        if let Some((fonts, (holder, path))) = entry(doc, resources, b"Font", location) {
+            for (name, font) in fonts.iter() {
+                let font_id = match font.as_reference() {
+                    Ok(font_id) => font_id,
+                    Err(_) => continue,
+                };
+                let mut font_path = path.clone();
+                font_path.push(name.clone());
+                self.usage.entry(font_id).or_default().push(FontUseSite {
+                    kind: kind.clone(),
+                    owner,
+                    name: name.clone(),
+                    entry: Some((holder, font_path)),
+                });
+                fonts_by_name.insert(name.clone(), font_id);
+                let type3 = doc
+                    .get_dictionary(font_id)
+                    .is_ok_and(|font| font.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Type3"));
+                if type3 && self.seen.insert(font_id) {
+                    self.pending.push((font_id, FontUseKind::Type3Font));
+                }
+            }
+        }
+        if let Ok(xobjects) = resources.get_deref(b"XObject", doc).and_then(Object::as_dict) {
+            for (_, xobject) in xobjects.iter() {
+                let form = xobject.as_reference().ok().filter(|&id| {
+                    doc.get_object(id)
+                        .and_then(Object::as_stream)
+                        .is_ok_and(|stream| stream.dict.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Form"))
+                });
+                if let Some(id) = form {
+                    if self.seen.insert(id) {
+                        self.pending.push((id, FontUseKind::FormXObject));
+                    }
+                }
+            }
+        }
+        fonts_by_name
+    }
+
+    fn visit_pending(&mut self) {
+        while let Some((id, kind)) = self.pending.pop() {
+            self.resources(id, kind);
+        }
+    }
+
+    /// Record the fonts that the default appearance strings of fields and their widgets name.
+    fn default_appearances(&mut self, field_ids: Vec<ObjectId>, fonts: &BTreeMap<Vec<u8>, ObjectId>) {
+        let mut seen = BTreeSet::new();
+        let mut pending = field_ids;
+        while let Some(id) = pending.pop() {
+            let field = match self.doc.get_dictionary(id) {
+                Ok(field) if seen.insert(id) => field,
+                _ => continue,
+            };
+            self.default_appearance(id, field, fonts);
+            if let Ok(kids) = field.get_deref(b"Kids", self.doc).and_then(Object::as_array) {
+                pending.extend(kids.iter().filter_map(|kid| kid.as_reference().ok()));
+            }
+        }
+    }
+
+    fn default_appearance(&mut self, owner: ObjectId, dict: &Dictionary, fonts: &BTreeMap<Vec<u8>, ObjectId>) {
+        let default_appearance = match dict.get_deref(b"DA", self.doc).and_then(Object::as_str) {
+            Ok(default_appearance) => default_appearance,
+            Err(_) => return,
+        };
+        // Without a font operator, the string names no font.
+        if !default_appearance
+            .split(|byte| byte.is_ascii_whitespace())
+            .any(|token| token == b"Tf")
+        {
+            return;
+        }
+        let name = DefaultAppearance::parse(default_appearance).font;
+        if let Some(&font_id) = fonts.get(&name) {
+            self.usage.entry(font_id).or_default().push(FontUseSite {
+                kind: FontUseKind::DefaultAppearance,
+                owner,
+                name,
+                entry: None,
+            });
+        }
+    }
+}
+
+/// The dictionary of an entry of a dictionary at a location, with its own location.
+fn entry<'a>(
+    doc: &'a Document, dict: &'a Dictionary, key: &[u8], (holder, mut path): Location,
+) -> Option<(&'a Dictionary, Location)> {
+    match dict.get(key).ok()? {
+        Object::Reference(id) => Some((doc.get_dictionary(*id).ok()?, (*id, Vec::new()))),
+        Object::Dictionary(entry) => {
+            path.push(key.to_vec());
+            Some((entry, (holder, path)))
+        }
+        _ => None,
+    }
+}
+
+/// Add the references of an object to a list.
+fn references(object: &Object, ids: &mut Vec<ObjectId>) {
+    match object {
+        Object::Reference(id) => ids.push(*id),
+        Object::Array(array) => array.iter().for_each(|item| references(item, ids)),
+        Object::Dictionary(dict) => dict.iter().for_each(|(_, value)| references(value, ids)),
+        Object::Stream(stream) => stream.dict.iter().for_each(|(_, value)| references(value, ids)),
+        _ => {}
+    }
+}
+
+impl Document {
+    /// Every place each font object is used from: the resources of pages and of the page tree nodes they inherit
+    /// from, of the form XObjects and Type 3 fonts these use, and of the appearance streams of annotations, the
+    /// default resources of the interactive form, and the default appearance strings naming fonts of these.
+    pub fn font_usage(&self) -> BTreeMap<ObjectId, Vec<FontUseSite>> {
+        let mut walk = FontWalk {
+            doc: self,
+            usage: BTreeMap::new(),
+            pending: Vec::new(),
+            seen: BTreeSet::new(),
+        };
+        let mut nodes = BTreeSet::new();
+        for page_id in self.page_iter() {
+            walk.resources(page_id, FontUseKind::Page);
+            let mut parent = self
+                .get_dictionary(page_id)
+                .and_then(|page| page.get(b"Parent")?.as_reference());
+            while let Ok(node_id) = parent {
+                if !nodes.insert(node_id) {
+                    break;
+                }
+                walk.resources(node_id, FontUseKind::PageTree);
+                parent = self
+                    .get_dictionary(node_id)
+                    .and_then(|node| node.get(b"Parent")?.as_reference());
+            }
+            for annotation in self.annotations(page_id) {
+                let annotation_id = match annotation.id {
+                    Some(id) => id,
+                    None => continue,
+                };
+                let appearances = [
+                    &annotation.normal_appearance,
+                    &annotation.rollover_appearance,
+                    &annotation.down_appearance,
+                ];
+                for appearance in appearances.iter().copied().flatten() {
+                    let streams = match appearance {
+                        Appearance::Stream(id) => vec![*id],
+                        Appearance::States(states) => states.iter().map(|&(_, id)| id).collect(),
+                    };
+                    for stream_id in streams {
+                        let kind = FontUseKind::Appearance {
+                            annotation: annotation_id,
+                        };
+                        walk.resources(stream_id, kind);
+                    }
+                }
+            }
+        }
+        if let Some((acro_form_id, acro_form)) = self.acro_form() {
+            // A form stored in the catalog is found there.
+            let location = match acro_form_id {
+                Some(id) => Some((id, Vec::new())),
+                None => (self.trailer.get(b"Root").and_then(Object::as_reference).ok())
+                    .map(|catalog_id| (catalog_id, vec![b"AcroForm".to_vec()])),
+            };
+            if let Some(location) = location {
+                let owner = location.0;
+                let fonts = match entry(self, acro_form, b"DR", location) {
+                    Some((resources, location)) => {
+                        walk.resource_fonts(owner, FontUseKind::FormResources, resources, location)
+                    }
+                    None => BTreeMap::new(),
+                };
+                walk.default_appearance(owner, acro_form, &fonts);
+                let fields = acro_form
+                    .get_deref(b"Fields", self)
+                    .and_then(Object::as_array)
+                    .map(|fields| fields.iter().filter_map(|field| field.as_reference().ok()).collect())
+                    .unwrap_or_default();
+                walk.default_appearances(fields, &fonts);
+            }
+        }
+        walk.visit_pending();
+        walk.usage
+    }
+
+    /// Make every resource using a font use another font instead, wherever [`Document::font_usage`] finds it, so
+    /// that the default appearance strings naming the font name the other one. Returns the number of resources
+    /// changed, each shared resource dictionary counting once.
+    pub fn replace_font(&mut self, old: ObjectId, new: ObjectId) -> Result<usize> {
+        self.get_dictionary(new)?;
+        let entries: BTreeSet<(ObjectId, Vec<Vec<u8>>)> = self
+            .font_usage()
+            .remove(&old)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|site| site.entry)
+            .collect();
+        for (holder, path) in &entries {
+            let mut dict = match self.get_object_mut(*holder)? {
+                Object::Dictionary(dict) => dict,
+                Object::Stream(stream) => &mut stream.dict,
+                _ => continue,
+            };
+            let (name, keys) = path.split_last().expect("font entries have a name");
+            for key in keys {
+                dict = dict.get_mut(key)?.as_dict_mut()?;
+            }
+            dict.set(name.clone(), new);
+        }
+        Ok(entries.len())
+    }
+
+    /// The fonts that [`Document::font_usage`] finds, with the objects they refer to.
+    pub(crate) fn used_font_objects(&self) -> BTreeSet<ObjectId> {
+        let mut used = BTreeSet::new();
+        let mut pending: Vec<ObjectId> = self.font_usage().into_keys().collect();
+        while let Some(id) = pending.pop() {
+            if used.insert(id) {
+                if let Ok(object) = self.get_object(id) {
+                    references(object, &mut pending);
+                }
+            }
+        }
+        used
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::forms::tests::form_document;
+    use crate::FieldValue;
+    use std::collections::HashMap;
+
+    #[test]
+    fn font_shared_by_page_and_field_appearance() {
+        let mut doc = form_document();
+        let mut values = HashMap::new();
+        values.insert("name".to_string(), FieldValue::Text("Jane".to_string()));
+        doc.fill_form(&values).unwrap();
+        let (_, acro_form) = doc.acro_form().unwrap();
+        let dr_fonts = acro_form.get(b"DR").and_then(Object::as_dict).unwrap().get(b"Font");
+        let helvetica = dr_fonts
+            .and_then(Object::as_dict)
+            .unwrap()
+            .get(b"Helv")
+            .and_then(Object::as_reference)
+            .unwrap();
+        let page_id = doc.page_iter().next().unwrap();
+        let pages_id = doc
+            .get_dictionary(page_id)
+            .unwrap()
+            .get(b"Parent")
+            .and_then(Object::as_reference)
+            .unwrap();
+        let resources_id = doc
+            .get_dictionary(pages_id)
+            .unwrap()
+            .get(b"Resources")
+            .and_then(Object::as_reference)
+            .unwrap();
+        let fonts = doc.get_object_mut(resources_id).and_then(Object::as_dict_mut).unwrap();
+        fonts
+            .get_mut(b"Font")
+            .and_then(Object::as_dict_mut)
+            .unwrap()
+            .set("F2", helvetica);
+
+        let usage = doc.font_usage();
+        let kinds: Vec<&FontUseKind> = usage[&helvetica].iter().map(|site| &site.kind).collect();
+        let name_id = doc
+            .form_fields()
+            .into_iter()
+            .find(|field| field.name == "name")
+            .unwrap()
+            .id;
+        assert!(kinds.contains(&&FontUseKind::PageTree));
+        assert!(kinds.contains(&&FontUseKind::Appearance { annotation: name_id }));
+        assert!(kinds.contains(&&FontUseKind::FormResources));
+        // The form and the address field name Helv in their default appearance.
+        assert_eq!(
+            kinds
+                .iter()
+                .filter(|&&kind| *kind == FontUseKind::DefaultAppearance)
+                .count(),
+            2
+        );
+
+        // A font of the default resources only is kept.
+        let zapf = doc.add_object(dictionary! { "Type" => "Font", "Subtype" => "Type1", "BaseFont" => "ZapfDingbats" });
+        let acro_form = doc.acro_form_mut().unwrap();
+        let dr = acro_form.get_mut(b"DR").and_then(Object::as_dict_mut).unwrap();
+        dr.get_mut(b"Font")
+            .and_then(Object::as_dict_mut)
+            .unwrap()
+            .set("ZaDb", zapf);
+        assert!(!doc.prune_objects().contains(&zapf));
+
+        let times = doc.add_object(dictionary! { "Type" => "Font", "Subtype" => "Type1", "BaseFont" => "Times-Roman" });
+        assert_eq!(doc.replace_font(helvetica, times).unwrap(), 3);
+        assert!(!doc.font_usage().contains_key(&helvetica));
+        assert_eq!(doc.font_usage()[&times].len(), usage[&helvetica].len());
+        assert!(doc.prune_objects().contains(&helvetica));
+    }
+}
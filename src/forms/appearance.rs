@@ -0,0 +1,563 @@
+use std::convert::TryFrom;
+
+use super::FormField;
+use crate::content::{Content, Operation};
+use crate::geometry::Rect;
+use crate::{Color, Dictionary, Document, Object, Result, Stream};
+
+/// Widths of the printable ASCII characters in Helvetica, in thousandths of the font size.
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556, 556, 556, 556, 556,
+    556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833,
+    722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556,
+    556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334,
+    260, 334, 584,
+];
+
+/// Font size used for automatically sized multiline fields and list boxes.
+const DEFAULT_FONT_SIZE: f64 = 12.0;
+
+/// Highlight color of the selected options in list boxes.
+const SELECTION_COLOR: Color = Color::Rgb(0.6, 0.75, 0.86);
+
+/// The parts of a default appearance string, like `/Helv 12 Tf 0 g`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DefaultAppearance {
+    pub font: Vec<u8>,
+    /// 0 when the text is sized to fit the widget.
+    pub size: f64,
+    pub color: Color,
+    /// The color set with `G`, `RG` or `K`, which free text annotations give their border color with.
+    pub stroke_color: Option<Color>,
+}
+
+impl DefaultAppearance {
+    pub fn parse(default_appearance: &[u8]) -> DefaultAppearance {
+        let mut appearance = DefaultAppearance {
+            font: b"Helv".to_vec(),
+            size: 0.0,
+            color: Color::BLACK,
+            stroke_color: None,
+        };
+        let mut operands: Vec<&[u8]> = Vec::new();
+        for token in default_appearance
+            .split(|byte| byte.is_ascii_whitespace())
+            .filter(|token| !token.is_empty())
+        {
+            let number = |token: &[u8]| std::str::from_utf8(token).ok()?.parse::<f64>().ok();
+            match token {
+                b"Tf" if operands.len() >= 2 => {
+                    if let Some(font) = operands[operands.len() - 2].strip_prefix(b"/") {
+                        appearance.font = font.to_vec();
+                    }
+                    appearance.size = number(operands[operands.len() - 1]).unwrap_or(0.0);
+                }
+                b"g" | b"rg" | b"k" | b"G" | b"RG" | b"K" => {
+                    let count = match token {
+                        b"g" | b"G" => 1,
+                        b"rg" | b"RG" => 3,
+                        _ => 4,
+                    };
+                    if operands.len() >= count {
+                        let color = operands[operands.len() - count..]
+                            .iter()
+                            .map(|&operand| number(operand).map(|component| component as f32));
+                        if let Some(color) = color.collect::<Option<Vec<f32>>>() {
+                            if token[0].is_ascii_lowercase() {
+                                appearance.color = Color::from_components(&color).unwrap_or(Color::BLACK);
+                            } else {
+                                appearance.stroke_color = Color::from_components(&color);
+                            }
+                        }
+                    }
+                }
+                _ if token[0] == b'/' || number(token).is_some() => {
+                    operands.push(token);
+                    continue;
+                }
+                _ => {}
+            }
+            operands.clear();
+        }
+        appearance
+    }
+}
+
+/// A font text can be laid out with, through the codes it shows characters with.
+pub(crate) trait TextFont {
+    fn encode(&self, text: &str) -> Vec<u8>;
+
+    /// Width of encoded text at a font size of 1.
+    fn width(&self, bytes: &[u8]) -> f64;
+
+    /// Number of bytes of each character code.
+    fn code_len(&self) -> usize {
+        1
+    }
+
+    /// Ascent and descent, in thousandths of the font size.
+    fn ascent(&self) -> f64;
+    fn descent(&self) -> f64;
+}
+
+/// Metrics and encoding of a simple font, enough to lay out field text.
+pub(crate) struct FieldFont {
+    first_char: i64,
+    widths: Vec<f64>,
+    /// Width for codes without an entry in `widths`.
+    default_width: f64,
+    encoding: Option<String>,
+    /// Ascent and descent, in thousandths of the font size.
+    ascent: f64,
+    descent: f64,
+}
+
+impl FieldFont {
+    /// Read a font dictionary, with built-in metrics for standard fonts without `/Widths`.
+    pub fn new(doc: &Document, font: &Dictionary) -> FieldFont {
+        let base_font = font.get(b"BaseFont").and_then(Object::as_name).unwrap_or_default();
+        let mut field_font = if base_font.starts_with(b"Courier") {
+            FieldFont {
+                first_char: 32,
+                widths: Vec::new(),
+                default_width: 600.0,
+                encoding: None,
+                ascent: 629.0,
+                descent: -157.0,
+            }
+        } else {
+            // Helvetica, also a reasonable approximation for other fonts without metrics.
+            FieldFont::helvetica()
+        };
+        field_font.encoding = match font.get(b"Encoding").and_then(|encoding| doc.dereference(encoding)) {
+            Ok((_, Object::Name(name))) => Some(String::from_utf8_lossy(name).into_owned()),
+            Ok((_, Object::Dictionary(encoding))) => encoding
+                .get(b"BaseEncoding")
+                .and_then(Object::as_name_str)
+                .ok()
+                .map(str::to_string),
+            _ => None,
+        };
+        let widths = font
+            .get(b"Widths")
+            .and_then(|widths| doc.dereference(widths))
+            .and_then(|(_, widths)| widths.as_array());
+        if let Ok(widths) = widths {
+            field_font.first_char = font.get(b"FirstChar").and_then(Object::as_i64).unwrap_or(0);
+            field_font.widths = widths
+                .iter()
+                .map(|width| {
+                    doc.dereference(width)
+                        .and_then(|(_, width)| width.as_float())
+                        .unwrap_or(0.0)
+                })
+                .collect();
+        }
+        let descriptor = font
+            .get(b"FontDescriptor")
+            .and_then(|descriptor| doc.dereference(descriptor))
+            .and_then(|(_, descriptor)| descriptor.as_dict());
+        if let Ok(descriptor) = descriptor {
+            if let Ok(width) = descriptor.get(b"MissingWidth").and_then(Object::as_float) {
+                field_font.default_width = width;
+            }
+            if let (Ok(ascent), Ok(descent)) = (
+                descriptor.get(b"Ascent").and_then(Object::as_float),
+                descriptor.get(b"Descent").and_then(Object::as_float),
+            ) {
+                if ascent > descent {
+                    field_font.ascent = ascent;
+                    field_font.descent = descent;
+                }
+            }
+        }
+        field_font
+    }
+
+    /// Helvetica in WinAnsiEncoding, the font used when the default appearance names no usable font.
+    pub fn helvetica() -> FieldFont {
+        FieldFont {
+            first_char: 32,
+            widths: HELVETICA_WIDTHS.iter().map(|&width| f64::from(width)).collect(),
+            default_width: 556.0,
+            encoding: Some("WinAnsiEncoding".to_string()),
+            ascent: 718.0,
+            descent: -207.0,
+        }
+    }
+}
+
+impl TextFont for FieldFont {
+    fn encode(&self, text: &str) -> Vec<u8> {
+        Document::encode_text(self.encoding.as_deref(), text)
+    }
+
+    fn width(&self, bytes: &[u8]) -> f64 {
+        let width: f64 = bytes
+            .iter()
+            .map(|&byte| {
+                usize::try_from(i64::from(byte) - self.first_char)
+                    .ok()
+                    .and_then(|index| self.widths.get(index))
+                    .cloned()
+                    .unwrap_or(self.default_width)
+            })
+            .sum();
+        width / 1000.0
+    }
+
+    fn ascent(&self) -> f64 {
+        self.ascent
+    }
+
+    fn descent(&self) -> f64 {
+        self.descent
+    }
+}
+
+/// The text shown in a widget.
+pub(crate) enum FieldContent<'a> {
+    /// A single line, vertically centered.
+    Line(&'a str),
+    /// Text wrapped at word boundaries.
+    Wrapped(&'a str),
+    /// One character per cell of a field divided into the given number of cells.
+    Comb(&'a str, usize),
+    /// One line per option, with the selected ones highlighted.
+    List { options: Vec<String>, selected: Vec<usize> },
+}
+
+/// Build the normal appearance of a text or choice field widget as a form XObject.
+pub(crate) fn text_field_appearance(
+    doc: &Document, field: &FormField, widget: &Dictionary, content: &FieldContent,
+) -> Result<Stream> {
+    let rect = Rect::from_object(doc.dereference(widget.get(b"Rect")?)?.1)?;
+    let (width, height) = (rect.width(), rect.height());
+    let default_appearance = DefaultAppearance::parse(field.default_appearance.as_deref().unwrap_or(b"/Helv 0 Tf 0 g"));
+
+    // Use the font of the default resources of the form, or Helvetica if it is missing.
+    let font_resource = doc
+        .acro_form()
+        .and_then(|(_, acro_form)| doc.dereference(acro_form.get(b"DR").ok()?).ok())
+        .and_then(|(_, resources)| doc.dereference(resources.as_dict().ok()?.get(b"Font").ok()?).ok())
+        .and_then(|(_, fonts)| fonts.as_dict().ok()?.get(&default_appearance.font).ok().cloned());
+    let font_dict = font_resource
+        .as_ref()
+        .and_then(|font| doc.dereference(font).ok())
+        .and_then(|(_, font)| font.as_dict().ok());
+    let (font_resource, font) = match font_dict {
+        Some(font_dict) => (font_resource.clone().unwrap(), FieldFont::new(doc, font_dict)),
+        None => (
+            Object::Dictionary(dictionary! {
+                "Type" => "Font",
+                "Subtype" => "Type1",
+                "BaseFont" => "Helvetica",
+                "Encoding" => "WinAnsiEncoding",
+            }),
+            FieldFont::helvetica(),
+        ),
+    };
+
+    let characteristics = widget
+        .get(b"MK")
+        .and_then(|characteristics| doc.dereference(characteristics))
+        .and_then(|(_, characteristics)| characteristics.as_dict())
+        .ok();
+    let color = |key: &[u8]| Color::from_object(characteristics?.get(key).ok()?);
+    let border_width = widget
+        .get(b"BS")
+        .and_then(|border| doc.dereference(border))
+        .and_then(|(_, border)| border.as_dict())
+        .and_then(|border| border.get(b"W"))
+        .and_then(Object::as_float)
+        .unwrap_or(1.0);
+    let inset = 2.0 * border_width.max(1.0);
+    let (inner_width, inner_height) = ((width - 2.0 * inset).max(0.0), (height - 2.0 * inset).max(0.0));
+
+    let mut operations = Vec::new();
+    if let Some(operation) = color(b"BG").map(|background| background.fill_operation()) {
+        operations.push(operation);
+        operations.push(Operation::new(
+            "re",
+            vec![0.into(), 0.into(), width.into(), height.into()],
+        ));
+        operations.push(Operation::new("f", vec![]));
+    }
+    if let Some(operation) = color(b"BC").map(|border| border.stroke_operation()) {
+        if border_width > 0.0 {
+            let half = border_width / 2.0;
+            operations.push(operation);
+            operations.push(Operation::new("w", vec![border_width.into()]));
+            operations.push(Operation::new(
+                "re",
+                vec![
+                    half.into(),
+                    half.into(),
+                    (width - border_width).into(),
+                    (height - border_width).into(),
+                ],
+            ));
+            operations.push(Operation::new("S", vec![]));
+        }
+    }
+    operations.push(Operation::new("BMC", vec!["Tx".into()]));
+    operations.push(Operation::new("q", vec![]));
+    operations.push(Operation::new(
+        "re",
+        vec![
+            border_width.into(),
+            border_width.into(),
+            (width - 2.0 * border_width).into(),
+            (height - 2.0 * border_width).into(),
+        ],
+    ));
+    operations.push(Operation::new("W", vec![]));
+    operations.push(Operation::new("n", vec![]));
+
+    let em_height = (font.ascent - font.descent) / 1000.0;
+    let align = |line_width: f64| match field.quadding {
+        1 => (width - line_width) / 2.0,
+        2 => width - inset - line_width,
+        _ => inset,
+    };
+    // Lines of encoded text with their positions.
+    let mut lines: Vec<(f64, f64, Vec<u8>)> = Vec::new();
+    let size = match content {
+        FieldContent::Line(text) => {
+            let text = font.encode(text);
+            let text_width = font.width(&text);
+            let mut size = default_appearance.size;
+            if size <= 0.0 {
+                size = inner_height / 1.2;
+                if text_width * size > inner_width && text_width > 0.0 {
+                    size = inner_width / text_width;
+                }
+            }
+            let y = (height - size * em_height) / 2.0 - size * font.descent / 1000.0;
+            lines.push((align(text_width * size), y, text));
+            size
+        }
+        FieldContent::Comb(text, cells) => {
+            let size = if default_appearance.size > 0.0 {
+                default_appearance.size
+            } else {
+                inner_height / 1.2
+            };
+            let cell_width = width / *cells as f64;
+            let y = (height - size * em_height) / 2.0 - size * font.descent / 1000.0;
+            for (i, c) in text.chars().take(*cells).enumerate() {
+                let glyph = font.encode(&c.to_string());
+                let x = cell_width * i as f64 + (cell_width - font.width(&glyph) * size) / 2.0;
+                lines.push((x, y, glyph));
+            }
+            size
+        }
+        FieldContent::Wrapped(text) => {
+            let size = if default_appearance.size > 0.0 {
+                default_appearance.size
+            } else {
+                DEFAULT_FONT_SIZE
+            };
+            let mut y = height - inset - size * font.ascent / 1000.0;
+            for line in wrap_text(&font, text, inner_width / size) {
+                lines.push((align(font.width(&line) * size), y, line));
+                y -= size * 1.15;
+            }
+            size
+        }
+        FieldContent::List { options, selected } => {
+            let size = if default_appearance.size > 0.0 {
+                default_appearance.size
+            } else {
+                DEFAULT_FONT_SIZE
+            };
+            let leading = size * 1.15;
+            let mut top = height - border_width;
+            for (i, option) in options.iter().enumerate() {
+                if selected.contains(&i) {
+                    operations.push(SELECTION_COLOR.fill_operation());
+                    operations.push(Operation::new(
+                        "re",
+                        vec![
+                            border_width.into(),
+                            (top - leading).into(),
+                            (width - 2.0 * border_width).into(),
+                            leading.into(),
+                        ],
+                    ));
+                    operations.push(Operation::new("f", vec![]));
+                }
+                let line = font.encode(option);
+                let y = top - leading + (leading - size * em_height) / 2.0 - size * font.descent / 1000.0;
+                lines.push((align(font.width(&line) * size), y, line));
+                top -= leading;
+            }
+            size
+        }
+    };
+
+    operations.push(Operation::new("BT", vec![]));
+    operations.push(Operation::new(
+        "Tf",
+        vec![Object::Name(default_appearance.font.clone()), size.into()],
+    ));
+    operations.push(default_appearance.color.fill_operation());
+    for (x, y, text) in lines {
+        operations.push(Operation::new(
+            "Tm",
+            vec![1.into(), 0.into(), 0.into(), 1.into(), x.into(), y.into()],
+        ));
+        operations.push(Operation::new("Tj", vec![Object::string_literal(text)]));
+    }
+    operations.push(Operation::new("ET", vec![]));
+    operations.push(Operation::new("Q", vec![]));
+    operations.push(Operation::new("EMC", vec![]));
+
+    let mut fonts = Dictionary::new();
+    fonts.set(default_appearance.font, font_resource);
+    let form = dictionary! {
+        "Type" => "XObject",
+        "Subtype" => "Form",
+        "BBox" => vec![0.into(), 0.into(), width.into(), height.into()],
+        "Resources" => dictionary! { "Font" => fonts },
+    };
+    Ok(Stream::new(form, Content { operations }.encode()?))
+}
+
+/// ZapfDingbats characters drawn in the on state of buttons.
+pub(crate) const CHECK_MARK: &str = "4";
+pub(crate) const BULLET: &str = "l";
+
+/// Build an appearance of a checkbox or radio button widget of the given size, showing a ZapfDingbats character
+/// when on. `font` is the ZapfDingbats font resource and `down` selects the appearance while pressed.
+pub(crate) fn button_appearance(
+    width: f64, height: f64, character: &str, on: bool, down: bool, font: Object,
+) -> Result<Stream> {
+    let round = character == BULLET;
+    let mut operations = Vec::new();
+    let path = |operations: &mut Vec<Operation>, inset: f64| {
+        if round {
+            circle(operations, width / 2.0, height / 2.0, width.min(height) / 2.0 - inset);
+        } else {
+            operations.push(Operation::new(
+                "re",
+                vec![
+                    inset.into(),
+                    inset.into(),
+                    (width - 2.0 * inset).into(),
+                    (height - 2.0 * inset).into(),
+                ],
+            ));
+        }
+    };
+    operations.push(Color::Gray(if down { 0.75 } else { 1.0 }).fill_operation());
+    path(&mut operations, 0.0);
+    operations.push(Operation::new("f", vec![]));
+    operations.push(Operation::new("G", vec![0.into()]));
+    operations.push(Operation::new("w", vec![1.into()]));
+    path(&mut operations, 0.5);
+    operations.push(Operation::new("S", vec![]));
+    if on {
+        // Widths of the check mark and the bullet, and the approximate height of both, in glyph space units.
+        let glyph_width = if round { 0.791 } else { 0.76 };
+        let size = width.min(height) * if round { 0.5 } else { 0.8 };
+        let x = (width - glyph_width * size) / 2.0;
+        let y = (height - 0.7 * size) / 2.0;
+        operations.push(Operation::new("BT", vec![]));
+        operations.push(Operation::new("Tf", vec!["ZaDb".into(), size.into()]));
+        operations.push(Operation::new("g", vec![0.into()]));
+        operations.push(Operation::new("Td", vec![x.into(), y.into()]));
+        operations.push(Operation::new("Tj", vec![Object::string_literal(character)]));
+        operations.push(Operation::new("ET", vec![]));
+    }
+    let form = dictionary! {
+        "Type" => "XObject",
+        "Subtype" => "Form",
+        "BBox" => vec![0.into(), 0.into(), width.into(), height.into()],
+        "Resources" => dictionary! { "Font" => dictionary! { "ZaDb" => font } },
+    };
+    Ok(Stream::new(form, Content { operations }.encode()?))
+}
+
+/// Add a circle made of four Bézier curves to a path.
+fn circle(operations: &mut Vec<Operation>, x: f64, y: f64, radius: f64) {
+    ellipse(operations, x, y, radius, radius);
+}
+
+/// Add the path of an ellipse centered on a point, with the given horizontal and vertical radii.
+pub(crate) fn ellipse(operations: &mut Vec<Operation>, x: f64, y: f64, rx: f64, ry: f64) {
+    // Distance of the control points from the ends of each quarter.
+    let (kx, ky) = (rx * 0.552_284_75, ry * 0.552_284_75);
+    operations.push(Operation::new("m", vec![(x + rx).into(), y.into()]));
+    let quarters = [
+        [x + rx, y + ky, x + kx, y + ry, x, y + ry],
+        [x - kx, y + ry, x - rx, y + ky, x - rx, y],
+        [x - rx, y - ky, x - kx, y - ry, x, y - ry],
+        [x + kx, y - ry, x + rx, y - ky, x + rx, y],
+    ];
+    for quarter in &quarters {
+        operations.push(Operation::new("c", quarter.iter().map(|&value| value.into()).collect()));
+    }
+    operations.push(Operation::new("h", vec![]));
+}
+
+/// Split text into encoded lines no wider than `max_width` at a font size of 1, breaking at spaces where possible.
+pub(crate) fn wrap_text<F: TextFont>(font: &F, text: &str, max_width: f64) -> Vec<Vec<u8>> {
+    let space = font.encode(" ");
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line: Vec<u8> = Vec::new();
+        for word in paragraph.split(' ') {
+            let word = font.encode(word);
+            let mut candidate = line.clone();
+            if !candidate.is_empty() {
+                candidate.extend_from_slice(&space);
+            }
+            candidate.extend_from_slice(&word);
+            if font.width(&candidate) <= max_width || line.is_empty() && font.width(&word) <= max_width {
+                line = candidate;
+                continue;
+            }
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            // Break words that do not fit on a line of their own.
+            for code in word.chunks(font.code_len()) {
+                if !line.is_empty() && font.width(&line) + font.width(code) > max_width {
+                    lines.push(std::mem::take(&mut line));
+                }
+                line.extend_from_slice(code);
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_default_appearance() {
+        let appearance = DefaultAppearance::parse(b"0.2 0.4 0.6 rg /TiRo 9.5 Tf 0.5 G");
+        assert_eq!(
+            appearance,
+            DefaultAppearance {
+                font: b"TiRo".to_vec(),
+                size: 9.5,
+                color: Color::Rgb(0.2, 0.4, 0.6),
+                stroke_color: Some(Color::Gray(0.5)),
+            }
+        );
+        assert_eq!(DefaultAppearance::parse(b"/Helv 0 Tf 0 g").size, 0.0);
+
+        let font = FieldFont::helvetica();
+        assert_eq!(font.width(b"Hi"), 0.944);
+        let lines = wrap_text(&font, "one two three\nfour", 2.5);
+        assert_eq!(
+            lines,
+            vec![b"one".to_vec(), b"two".to_vec(), b"three".to_vec(), b"four".to_vec()]
+        );
+    }
+}
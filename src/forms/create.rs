@@ -0,0 +1,538 @@
+use super::appearance::{button_appearance, text_field_appearance, FieldContent, BULLET, CHECK_MARK};
+use super::{name_text, FieldFlags, FieldType, FieldValue, FillOptions, FormField};
+use crate::annotations::{Annotation, Appearance};
+use crate::encodings::encode_text_string;
+use crate::geometry::Rect;
+use crate::signatures::SIGNATURES_EXIST;
+use crate::{Dictionary, Document, Error, Object, ObjectId, Result, StringFormat, TabOrder};
+
+/// Options for [`Document::add_text_field`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TextFieldOptions {
+    pub value: Option<String>,
+    pub multiline: bool,
+    pub password: bool,
+    pub max_len: Option<u32>,
+    /// Divide the field into `max_len` evenly spaced cells.
+    pub comb: bool,
+    /// Text alignment: 0 for left, 1 for centered and 2 for right.
+    pub quadding: i64,
+    /// 0 sizes the text to fit the field.
+    pub font_size: f64,
+    pub read_only: bool,
+    pub required: bool,
+}
+
+/// Options for [`Document::add_choice_field`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChoiceFieldOptions {
+    /// Export value and display text of each option.
+    pub choices: Vec<(String, String)>,
+    /// A drop-down combo box rather than a list box.
+    pub combo: bool,
+    /// Whether a combo box also accepts text that is not one of the options.
+    pub editable: bool,
+    pub multi_select: bool,
+    /// The export value of the selected option.
+    pub value: Option<String>,
+    /// 0 sizes the text to fit the field.
+    pub font_size: f64,
+    pub read_only: bool,
+    pub required: bool,
+}
+
+/// Options for [`Document::add_signature_field`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SignatureFieldOptions {
+    /// Give the widget an empty rectangle, for a signature that is not shown on the page.
+    pub invisible: bool,
+    /// Show "Unsigned" in a visible widget until the field is signed.
+    pub unsigned_appearance: bool,
+}
+
+impl Document {
+    /// Add a text field with a widget on a page.
+    pub fn add_text_field(
+        &mut self, page_id: ObjectId, name: &str, rect: Rect, options: &TextFieldOptions,
+    ) -> Result<ObjectId> {
+        let mut flags = common_flags(options.read_only, options.required);
+        flags.set(FieldFlags::MULTILINE, options.multiline);
+        flags.set(FieldFlags::PASSWORD, options.password);
+        flags.set(FieldFlags::COMB, options.comb && options.max_len.is_some());
+        let mut field = dictionary! {
+            "FT" => "Tx",
+            "Ff" => flags.bits() as i64,
+            "DA" => Object::string_literal(format!("/Helv {} Tf 0 g", options.font_size)),
+            "Q" => options.quadding,
+        };
+        if let Some(max_len) = options.max_len {
+            field.set("MaxLen", i64::from(max_len));
+        }
+        let id = self.add_field_widget(page_id, name, rect, field)?;
+        let value = FieldValue::Text(options.value.clone().unwrap_or_default());
+        self.fill_created_field(id, &value)?;
+        Ok(id)
+    }
+
+    /// Add a checkbox on a page, with `Yes` as its on state.
+    pub fn add_checkbox(&mut self, page_id: ObjectId, name: &str, rect: Rect, checked: bool) -> Result<ObjectId> {
+        let state = if checked { "Yes" } else { "Off" };
+        let field = dictionary! {
+            "FT" => "Btn",
+            "DA" => Object::string_literal("/ZaDb 0 Tf 0 g"),
+            "V" => state,
+            "AS" => state,
+        };
+        let id = self.add_field_widget(page_id, name, rect, field)?;
+        self.set_button_appearances(id, rect, CHECK_MARK, "Yes")?;
+        Ok(id)
+    }
+
+    /// Add a group of radio buttons on a page, one widget for each rectangle and export value, with none selected.
+    pub fn add_radio_group(&mut self, page_id: ObjectId, name: &str, buttons: Vec<(Rect, String)>) -> Result<ObjectId> {
+        self.check_field_name(name)?;
+        self.get_dictionary(page_id)?;
+        let flags = FieldFlags::RADIO | FieldFlags::NO_TOGGLE_TO_OFF;
+        let group_id = self.add_object(dictionary! {
+            "FT" => "Btn",
+            "Ff" => flags.bits() as i64,
+            "V" => "Off",
+            "Kids" => Vec::<Object>::new(),
+        });
+        self.insert_field(name, group_id)?;
+        for (rect, export) in buttons {
+            let widget = dictionary! {
+                "Subtype" => "Widget",
+                "Rect" => rect,
+                "F" => crate::AnnotationFlags::PRINT.bits() as i64,
+                "Parent" => group_id,
+                "AS" => "Off",
+                "MK" => dictionary! { "BC" => vec![0.into()], "CA" => Object::string_literal(BULLET) },
+            };
+            let widget_id = self.add_annotation(page_id, widget)?;
+            self.field_kids_mut(Some(group_id))?.push(widget_id.into());
+            self.set_button_appearances(widget_id, rect, BULLET, &export)?;
+        }
+        self.default_tab_order(page_id)?;
+        Ok(group_id)
+    }
+
+    /// Add a combo box or list box on a page.
+    pub fn add_choice_field(
+        &mut self, page_id: ObjectId, name: &str, rect: Rect, options: &ChoiceFieldOptions,
+    ) -> Result<ObjectId> {
+        let mut flags = common_flags(options.read_only, options.required);
+        flags.set(FieldFlags::COMBO, options.combo);
+        flags.set(FieldFlags::EDIT, options.combo && options.editable);
+        flags.set(FieldFlags::MULTI_SELECT, !options.combo && options.multi_select);
+        let text = |text: &str| Object::String(encode_text_string(text), StringFormat::Literal);
+        let choices = options
+            .choices
+            .iter()
+            .map(|(export, display)| {
+                if export == display {
+                    text(export)
+                } else {
+                    Object::Array(vec![text(export), text(display)])
+                }
+            })
+            .collect::<Vec<_>>();
+        let field = dictionary! {
+            "FT" => "Ch",
+            "Ff" => flags.bits() as i64,
+            "DA" => Object::string_literal(format!("/Helv {} Tf 0 g", options.font_size)),
+            "Opt" => choices,
+        };
+        let id = self.add_field_widget(page_id, name, rect, field)?;
+        let value = match &options.value {
+            Some(value) => FieldValue::Text(value.clone()),
+            None if options.combo => FieldValue::Text(String::new()),
+            None => FieldValue::Selection(Vec::new()),
+        };
+        self.fill_created_field(id, &value)?;
+        Ok(id)
+    }
+
+    /// Add an empty signature field with a widget on a page, to be signed later.
+    pub fn add_signature_field(
+        &mut self, page_id: ObjectId, name: &str, rect: Rect, options: &SignatureFieldOptions,
+    ) -> Result<ObjectId> {
+        let rect = if options.invisible {
+            Rect::new(0.0, 0.0, 0.0, 0.0)
+        } else {
+            rect
+        };
+        let field = dictionary! {
+            "FT" => "Sig",
+            "DA" => Object::string_literal("/Helv 0 Tf 0 g"),
+        };
+        let id = self.add_field_widget(page_id, name, rect, field)?;
+        self.set_sig_flags(SIGNATURES_EXIST)?;
+        if options.invisible {
+            self.get_object_mut(id)?.as_dict_mut()?.remove(b"MK");
+        } else if options.unsigned_appearance {
+            let field = self
+                .form_fields()
+                .into_iter()
+                .find(|field| field.id == id)
+                .ok_or_else(|| Error::not_found(id))?;
+            let widget = self.get_dictionary(id)?;
+            let appearance = text_field_appearance(self, &field, widget, &FieldContent::Line("Unsigned"))?;
+            let appearance_id = self.add_object(appearance);
+            self.get_object_mut(id)?
+                .as_dict_mut()?
+                .set("AP", dictionary! { "N" => appearance_id });
+        }
+        Ok(id)
+    }
+
+    /// Add a field merged with its only widget.
+    fn add_field_widget(
+        &mut self, page_id: ObjectId, name: &str, rect: Rect, mut field: Dictionary,
+    ) -> Result<ObjectId> {
+        self.check_field_name(name)?;
+        self.get_dictionary(page_id)?;
+        field.set("Subtype", "Widget");
+        field.set("Rect", rect);
+        field.set("F", crate::AnnotationFlags::PRINT.bits() as i64);
+        field.set("MK", dictionary! { "BC" => vec![0.into()] });
+        if field.get(b"FT").and_then(Object::as_name).ok() == Some(b"Btn") {
+            let characteristics = field.get_mut(b"MK")?.as_dict_mut()?;
+            characteristics.set("CA", Object::string_literal(CHECK_MARK));
+        }
+        let id = self.add_annotation(page_id, field)?;
+        self.insert_field(name, id)?;
+        self.default_tab_order(page_id)?;
+        Ok(id)
+    }
+
+    /// Let keyboard navigation visit the fields of a page row by row, unless the page has a tab order already.
+    fn default_tab_order(&mut self, page_id: ObjectId) -> Result<()> {
+        if self.get_dictionary(page_id)?.has(b"Tabs") {
+            return Ok(());
+        }
+        self.set_tab_order(page_id, TabOrder::Row)
+    }
+
+    /// Check that a fully qualified name is well formed and neither taken nor the prefix or extension of a taken name.
+    fn check_field_name(&self, name: &str) -> Result<()> {
+        if name.split('.').any(str::is_empty) {
+            return Err(Error::FieldName(name.to_string()));
+        }
+        let conflicts = |existing: &str| {
+            existing == name
+                || existing.starts_with(name) && existing[name.len()..].starts_with('.')
+                || name.starts_with(existing) && name[existing.len()..].starts_with('.')
+        };
+        if self.form_fields().iter().any(|field| conflicts(&field.name)) {
+            return Err(Error::FieldName(name.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Put a field into the field tree under its fully qualified name, adding missing ancestors.
+    fn insert_field(&mut self, name: &str, field_id: ObjectId) -> Result<()> {
+        self.ensure_acro_form()?;
+        let mut parts: Vec<&str> = name.split('.').collect();
+        let partial_name = parts.pop().unwrap_or_default();
+        let mut parent_id = None;
+        for part in parts {
+            let existing = self.field_kids_mut(parent_id)?.clone().into_iter().find_map(|kid| {
+                let kid_id = kid.as_reference().ok()?;
+                let title = self
+                    .get_dictionary(kid_id)
+                    .ok()?
+                    .get(b"T")
+                    .and_then(Object::as_str)
+                    .ok()?;
+                if crate::encodings::decode_text_string(title) == part {
+                    Some(kid_id)
+                } else {
+                    None
+                }
+            });
+            let node_id = match existing {
+                Some(node_id) => node_id,
+                None => {
+                    let mut node = dictionary! {
+                        "T" => Object::String(encode_text_string(part), StringFormat::Literal),
+                        "Kids" => Vec::<Object>::new(),
+                    };
+                    if let Some(parent_id) = parent_id {
+                        node.set("Parent", parent_id);
+                    }
+                    let node_id = self.add_object(node);
+                    self.field_kids_mut(parent_id)?.push(node_id.into());
+                    node_id
+                }
+            };
+            parent_id = Some(node_id);
+        }
+        let field = self.get_object_mut(field_id)?.as_dict_mut()?;
+        field.set(
+            "T",
+            Object::String(encode_text_string(partial_name), StringFormat::Literal),
+        );
+        if let Some(parent_id) = parent_id {
+            field.set("Parent", parent_id);
+        }
+        self.field_kids_mut(parent_id)?.push(field_id.into());
+        Ok(())
+    }
+
+    /// Create the interactive form if missing, and register Helvetica and ZapfDingbats in its default resources.
+    pub(super) fn ensure_acro_form(&mut self) -> Result<()> {
+        if self.acro_form().is_none() {
+            let acro_form_id = self.add_object(dictionary! { "Fields" => Vec::<Object>::new() });
+            let root_id = self.trailer.get(b"Root")?.as_reference()?;
+            self.get_object_mut(root_id)?
+                .as_dict_mut()?
+                .set("AcroForm", acro_form_id);
+        }
+        let fonts = self
+            .acro_form()
+            .and_then(|(_, acro_form)| {
+                acro_form
+                    .get_deref(b"DR", self)
+                    .ok()?
+                    .as_dict()
+                    .ok()?
+                    .get_deref(b"Font", self)
+                    .ok()?
+                    .as_dict()
+                    .ok()
+            })
+            .cloned()
+            .unwrap_or_default();
+        let mut missing = Vec::new();
+        for &(name, base_font) in &[("Helv", "Helvetica"), ("ZaDb", "ZapfDingbats")] {
+            if !fonts.has(name.as_bytes()) {
+                let mut font = dictionary! {
+                    "Type" => "Font",
+                    "Subtype" => "Type1",
+                    "BaseFont" => base_font,
+                };
+                if name == "Helv" {
+                    font.set("Encoding", "WinAnsiEncoding");
+                }
+                missing.push((name, self.add_object(font)));
+            }
+        }
+        let acro_form = self.acro_form_mut()?;
+        if !acro_form.has(b"DA") {
+            acro_form.set("DA", Object::string_literal("/Helv 0 Tf 0 g"));
+        }
+        if missing.is_empty() {
+            return Ok(());
+        }
+        // Store the default resources directly in the form, so they are not shared with other objects.
+        let resources_ref = acro_form.get(b"DR").and_then(Object::as_reference).ok();
+        let mut resources = match resources_ref {
+            Some(id) => self.get_dictionary(id)?.clone(),
+            None => match self.acro_form_mut()?.get(b"DR") {
+                Ok(Object::Dictionary(resources)) => resources.clone(),
+                _ => Dictionary::new(),
+            },
+        };
+        let mut fonts = fonts;
+        for (name, font_id) in missing {
+            fonts.set(name, font_id);
+        }
+        resources.set("Font", fonts);
+        self.acro_form_mut()?.set("DR", resources);
+        Ok(())
+    }
+
+    /// Give a new field its value and appearance.
+    fn fill_created_field(&mut self, id: ObjectId, value: &FieldValue) -> Result<()> {
+        let field = self
+            .form_fields()
+            .into_iter()
+            .find(|field| field.id == id)
+            .ok_or_else(|| Error::not_found(id))?;
+        match self.fill_field(&field, value, &FillOptions::default())? {
+            Ok(()) => Ok(()),
+            Err(reason) => Err(Error::Syntax(reason)),
+        }
+    }
+
+    /// Draw the appearances of a checkbox or radio button widget again for its current rectangle, keeping its on
+    /// state and the character of its `/MK` entry. Widgets without an on state are left alone.
+    pub(super) fn regenerate_button_appearances(&mut self, field: &FormField, widget_id: ObjectId) -> Result<bool> {
+        let widget = self.get_dictionary(widget_id)?;
+        let rect = match widget.get_deref(b"Rect", self).and_then(Rect::from_object) {
+            Ok(rect) => rect,
+            Err(_) => return Ok(false),
+        };
+        let on_state = match Annotation::from_dict(self, Some(widget_id), widget).normal_appearance {
+            Some(Appearance::States(states)) => {
+                states.into_iter().map(|(state, _)| state).find(|state| state != b"Off")
+            }
+            _ => None,
+        };
+        let on_state = match on_state {
+            Some(state) => name_text(&state),
+            None => return Ok(false),
+        };
+        let character = widget
+            .get_deref(b"MK", self)
+            .and_then(Object::as_dict)
+            .and_then(|characteristics| characteristics.get_deref(b"CA", self))
+            .and_then(Object::as_str)
+            .map(|character| String::from_utf8_lossy(character).into_owned())
+            .unwrap_or_else(|_| match field.field_type {
+                FieldType::Radio => BULLET.to_string(),
+                _ => CHECK_MARK.to_string(),
+            });
+        self.ensure_acro_form()?;
+        self.set_button_appearances(widget_id, rect, &character, &on_state)?;
+        Ok(true)
+    }
+
+    /// Set the normal and down appearances of a checkbox or radio button widget.
+    fn set_button_appearances(
+        &mut self, widget_id: ObjectId, rect: Rect, character: &str, on_state: &str,
+    ) -> Result<()> {
+        let font = self
+            .acro_form()
+            .and_then(|(_, acro_form)| {
+                acro_form
+                    .get_deref(b"DR", self)
+                    .ok()?
+                    .as_dict()
+                    .ok()?
+                    .get_deref(b"Font", self)
+                    .ok()?
+                    .as_dict()
+                    .ok()?
+                    .get(b"ZaDb")
+                    .ok()
+                    .cloned()
+            })
+            .ok_or_else(|| Error::DictKey {
+                path: ["AcroForm", "DR", "Font", "ZaDb"].iter().map(|key| key.as_bytes().to_vec()).collect(),
+            })?;
+        let mut appearances = Dictionary::new();
+        for &(key, down) in &[("N", false), ("D", true)] {
+            let mut states = Dictionary::new();
+            for &(state, on) in &[(on_state, true), ("Off", false)] {
+                let stream = button_appearance(rect.width(), rect.height(), character, on, down, font.clone())?;
+                states.set(state, self.add_object(stream));
+            }
+            appearances.set(key, states);
+        }
+        self.get_object_mut(widget_id)?.as_dict_mut()?.set("AP", appearances);
+        Ok(())
+    }
+}
+
+fn common_flags(read_only: bool, required: bool) -> FieldFlags {
+    let mut flags = FieldFlags::empty();
+    flags.set(FieldFlags::READ_ONLY, read_only);
+    flags.set(FieldFlags::REQUIRED, required);
+    flags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::creator::tests::create_document;
+    use crate::forms::FieldType;
+    use std::collections::HashMap;
+
+    #[test]
+    fn create_form_fields() {
+        let mut doc = create_document();
+        let page_id = doc.page_iter().next().unwrap();
+        let options = TextFieldOptions {
+            value: Some("Jane".to_string()),
+            required: true,
+            ..Default::default()
+        };
+        let name_id = doc
+            .add_text_field(page_id, "person.name", Rect::new(50.0, 700.0, 250.0, 720.0), &options)
+            .unwrap();
+        let notes = TextFieldOptions {
+            multiline: true,
+            ..Default::default()
+        };
+        doc.add_text_field(page_id, "person.notes", Rect::new(50.0, 600.0, 250.0, 680.0), &notes)
+            .unwrap();
+        doc.add_checkbox(page_id, "agree", Rect::new(50.0, 560.0, 62.0, 572.0), true)
+            .unwrap();
+        let buttons = vec![
+            (Rect::new(50.0, 530.0, 62.0, 542.0), "s".to_string()),
+            (Rect::new(70.0, 530.0, 82.0, 542.0), "m".to_string()),
+        ];
+        let size_id = doc.add_radio_group(page_id, "size", buttons).unwrap();
+        let choices = ChoiceFieldOptions {
+            choices: vec![
+                ("r".to_string(), "Red".to_string()),
+                ("g".to_string(), "Green".to_string()),
+            ],
+            combo: true,
+            value: Some("g".to_string()),
+            ..Default::default()
+        };
+        doc.add_choice_field(page_id, "color", Rect::new(50.0, 490.0, 150.0, 510.0), &choices)
+            .unwrap();
+        for name in &["person.name", "person", "person.name.first", "size", "a..b"] {
+            let rect = Rect::new(0.0, 0.0, 10.0, 10.0);
+            let result = doc.add_text_field(page_id, name, rect, &Default::default());
+            assert!(matches!(result, Err(Error::FieldName(_))), "{}", name);
+        }
+
+        let fields = doc.form_fields();
+        let summary: Vec<_> = fields
+            .iter()
+            .map(|field| {
+                (
+                    field.name.as_str(),
+                    field.field_type.clone(),
+                    field.value.as_deref(),
+                    field.widgets.len(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("person.name", FieldType::Text, Some("Jane"), 1),
+                ("person.notes", FieldType::Multiline, Some(""), 1),
+                ("agree", FieldType::Checkbox, Some("Yes"), 1),
+                ("size", FieldType::Radio, Some("Off"), 2),
+                ("color", FieldType::Combo, Some("g"), 1),
+            ]
+        );
+        assert!(fields[0].is_required());
+        assert_eq!(doc.annotations(page_id).len(), 6);
+        let page = doc.get_dictionary(page_id).unwrap();
+        assert_eq!(page.get(b"Tabs").and_then(Object::as_name).unwrap(), b"R");
+        let (_, acro_form) = doc.acro_form().unwrap();
+        let fonts = acro_form
+            .get(b"DR")
+            .and_then(Object::as_dict)
+            .unwrap()
+            .get(b"Font")
+            .and_then(Object::as_dict)
+            .unwrap();
+        assert!(fonts.has(b"Helv") && fonts.has(b"ZaDb"));
+        let roots = acro_form.get(b"Fields").and_then(Object::as_array).unwrap();
+        assert_eq!(roots.len(), 4);
+        let person = doc.get_dictionary(roots[0].as_reference().unwrap()).unwrap();
+        assert_eq!(
+            person.get(b"Kids").and_then(Object::as_array).unwrap()[0]
+                .as_reference()
+                .unwrap(),
+            name_id
+        );
+        let widget = doc.get_dictionary(fields[0].widgets[0].id).unwrap();
+        assert!(widget.get(b"AP").and_then(Object::as_dict).unwrap().has(b"N"));
+
+        let mut values = HashMap::new();
+        values.insert("size".to_string(), FieldValue::Text("m".to_string()));
+        assert_eq!(doc.fill_form(&values).unwrap().filled, vec!["size"]);
+        let size = doc.get_dictionary(size_id).unwrap();
+        assert_eq!(size.get(b"V").and_then(Object::as_name_str).unwrap(), "m");
+    }
+}
@@ -0,0 +1,555 @@
+use super::appearance::{text_field_appearance, FieldContent};
+use super::{name_text, FieldFlags, FieldType, FormField};
+use crate::annotations::Annotation;
+use crate::encodings::encode_text_string;
+use crate::{Document, EditDescription, Object, ObjectId, PolicyVerdict, Result, StringFormat};
+use log::warn;
+use std::collections::HashMap;
+
+/// Value to put into a form field.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldValue {
+    /// Text of a text field, the export value of a radio button or checkbox, or the value of a choice field.
+    Text(String),
+    /// Whether a checkbox is checked.
+    Checked(bool),
+    /// The selected values of a list box allowing multiple selection.
+    Selection(Vec<String>),
+}
+
+/// Options for [`Document::fill_form_with`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FillOptions {
+    /// Set `/NeedAppearances` so that viewers draw the fields, instead of generating appearance streams.
+    pub need_appearances: bool,
+}
+
+/// Outcome of filling a form.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FillReport {
+    /// Names of the fields that were filled.
+    pub filled: Vec<String>,
+    /// Names that match no field of the form.
+    pub unknown: Vec<String>,
+    /// Fields that were left alone, with the reason.
+    pub rejected: Vec<(String, String)>,
+}
+
+impl Document {
+    /// Fill form fields, by fully qualified name, and regenerate the appearances of their widgets.
+    pub fn fill_form(&mut self, values: &HashMap<String, FieldValue>) -> Result<FillReport> {
+        self.fill_form_with(values, &FillOptions::default())
+    }
+
+    pub fn fill_form_with(
+        &mut self, values: &HashMap<String, FieldValue>, options: &FillOptions,
+    ) -> Result<FillReport> {
+        let fields = self.form_fields();
+        let policy = self.modification_policy();
+        let mut report = FillReport::default();
+        let mut names: Vec<&String> = values.keys().collect();
+        names.sort();
+        for name in names {
+            let matching: Vec<&FormField> = fields.iter().filter(|field| &field.name == name).collect();
+            if matching.is_empty() {
+                report.unknown.push(name.clone());
+                continue;
+            }
+            match policy.check(&EditDescription::FillFields(vec![name.clone()])) {
+                PolicyVerdict::Allowed => {}
+                PolicyVerdict::Warn(reason) => warn!("Filling {}: {}", name, reason),
+                PolicyVerdict::Forbidden(reason) => {
+                    report.rejected.push((name.clone(), reason));
+                    continue;
+                }
+            }
+            let mut result = Ok(());
+            for field in matching {
+                result = result.and(self.fill_field(field, &values[name], options)?);
+            }
+            match result {
+                Ok(()) => report.filled.push(name.clone()),
+                Err(reason) => report.rejected.push((name.clone(), reason)),
+            }
+        }
+        if options.need_appearances && !report.filled.is_empty() {
+            self.acro_form_mut()?.set("NeedAppearances", true);
+        }
+        Ok(report)
+    }
+
+    /// Fill one field. The inner error is the reason for rejecting the value.
+    pub(super) fn fill_field(
+        &mut self, field: &FormField, value: &FieldValue, options: &FillOptions,
+    ) -> Result<std::result::Result<(), String>> {
+        let text_value = |text: &str| Object::String(encode_text_string(text), StringFormat::Literal);
+        match (&field.field_type, value) {
+            (FieldType::Text, FieldValue::Text(text))
+            | (FieldType::Multiline, FieldValue::Text(text))
+            | (FieldType::Password, FieldValue::Text(text)) => {
+                let text: String = match field.max_len {
+                    Some(max_len) if max_len >= 0 => text.chars().take(max_len as usize).collect(),
+                    _ => text.clone(),
+                };
+                self.get_object_mut(field.id)?
+                    .as_dict_mut()?
+                    .set("V", text_value(&text));
+                if !options.need_appearances {
+                    let shown = match field.field_type {
+                        FieldType::Password => "*".repeat(text.chars().count()),
+                        _ => text.clone(),
+                    };
+                    let content = match (field.field_type == FieldType::Multiline, field.max_len) {
+                        (true, _) => FieldContent::Wrapped(&shown),
+                        (false, Some(cells)) if field.flags.contains(FieldFlags::COMB) && cells > 0 => {
+                            FieldContent::Comb(&shown, cells as usize)
+                        }
+                        _ => FieldContent::Line(&shown),
+                    };
+                    self.set_widget_appearances(field, &content)?;
+                }
+            }
+            (FieldType::Checkbox, FieldValue::Checked(_)) | (FieldType::Checkbox, FieldValue::Text(_)) => {
+                let states = self.widget_states(field);
+                let has_states = states.iter().any(|states| !states.is_empty());
+                let on_state = match value {
+                    // The on state is often but not always `Yes`.
+                    FieldValue::Checked(true) => states
+                        .iter()
+                        .flatten()
+                        .next()
+                        .cloned()
+                        .unwrap_or_else(|| b"Yes".to_vec()),
+                    FieldValue::Text(export) if export != "Off" => match button_state(field, &states, export) {
+                        Some(state) => state,
+                        None if !has_states => export.as_bytes().to_vec(),
+                        None => return Ok(Err(format!("no widget has the state {}", export))),
+                    },
+                    _ => b"Off".to_vec(),
+                };
+                // Checkboxes sharing an on state are always checked together.
+                self.set_button_state(field, &states, on_state, true)?;
+            }
+            (FieldType::Radio, FieldValue::Text(export)) => {
+                let states = self.widget_states(field);
+                let state = if export == "Off" {
+                    if field.flags.contains(FieldFlags::NO_TOGGLE_TO_OFF) {
+                        return Ok(Err("the radio buttons cannot all be turned off".to_string()));
+                    }
+                    b"Off".to_vec()
+                } else {
+                    match button_state(field, &states, export) {
+                        Some(state) => state,
+                        None => return Ok(Err(format!("no radio button has the value {}", export))),
+                    }
+                };
+                // The flag is RadiosInUnison for radio buttons.
+                let unison = field.flags.contains(FieldFlags::RICH_TEXT);
+                self.set_button_state(field, &states, state, unison)?;
+            }
+            (FieldType::Combo, FieldValue::Text(choice)) | (FieldType::List, FieldValue::Text(choice)) => {
+                self.fill_choice(field, vec![choice.clone()], options)?;
+            }
+            (FieldType::List, FieldValue::Selection(choices)) => {
+                if choices.len() > 1 && !field.flags.contains(FieldFlags::MULTI_SELECT) {
+                    return Ok(Err("the list box does not allow multiple selection".to_string()));
+                }
+                self.fill_choice(field, choices.clone(), options)?;
+            }
+            (FieldType::PushButton, _) | (FieldType::Signature, _) | (FieldType::Unknown(_), _) => {
+                return Ok(Err(format!("fields of type {:?} cannot be filled", field.field_type)));
+            }
+            _ => {
+                return Ok(Err(format!(
+                    "a {:?} field cannot take the value {:?}",
+                    field.field_type, value
+                )))
+            }
+        }
+        Ok(Ok(()))
+    }
+
+    fn fill_choice(&mut self, field: &FormField, choices: Vec<String>, options: &FillOptions) -> Result<()> {
+        let text_value = |text: &str| Object::String(encode_text_string(text), StringFormat::Literal);
+        let value = match choices.as_slice() {
+            [choice] => text_value(choice),
+            choices => Object::Array(choices.iter().map(|choice| text_value(choice)).collect()),
+        };
+        self.get_object_mut(field.id)?.as_dict_mut()?.set("V", value);
+        if options.need_appearances {
+            return Ok(());
+        }
+        if field.field_type == FieldType::Combo {
+            // Show the display text of the selected option, or the value itself for editable combo boxes.
+            let choice = choices.first().cloned().unwrap_or_default();
+            let shown = match field.options.iter().find(|(export, _)| export == &choice) {
+                Some((_, display)) => display.clone(),
+                None => choice,
+            };
+            self.set_widget_appearances(field, &FieldContent::Line(&shown))
+        } else {
+            let content = FieldContent::List {
+                options: field.options.iter().map(|(_, display)| display.clone()).collect(),
+                selected: (field.options.iter().enumerate())
+                    .filter(|(_, (export, _))| choices.contains(export))
+                    .map(|(i, _)| i)
+                    .collect(),
+            };
+            self.set_widget_appearances(field, &content)
+        }
+    }
+
+    /// Replace the appearance of every widget of a field by a newly generated normal appearance.
+    pub(super) fn set_widget_appearances(&mut self, field: &FormField, content: &FieldContent) -> Result<()> {
+        for widget in &field.widgets {
+            let appearance = text_field_appearance(self, field, self.get_dictionary(widget.id)?, content)?;
+            let appearance_id = self.add_object(appearance);
+            let widget = self.get_object_mut(widget.id)?.as_dict_mut()?;
+            widget.set("AP", dictionary! { "N" => appearance_id });
+        }
+        Ok(())
+    }
+
+    /// Draw the appearance of a widget again for its current rectangle: text and choice fields show their value,
+    /// and buttons get appearances for each of their states. Widgets of other fields, or of no field, are left
+    /// alone.
+    pub(crate) fn regenerate_widget_appearance(&mut self, widget_id: ObjectId) -> Result<bool> {
+        let field = self
+            .form_fields()
+            .into_iter()
+            .find(|field| field.widgets.iter().any(|widget| widget.id == widget_id));
+        let field = match field {
+            Some(field) => field,
+            None => return Ok(false),
+        };
+        let value = field.value.clone().unwrap_or_default();
+        let shown = match field.field_type {
+            FieldType::Password => "*".repeat(value.chars().count()),
+            FieldType::Combo => match field.options.iter().find(|(export, _)| export == &value) {
+                Some((_, display)) => display.clone(),
+                None => value.clone(),
+            },
+            _ => value.clone(),
+        };
+        let content = match field.field_type {
+            FieldType::Text | FieldType::Password => match field.max_len {
+                Some(cells) if field.flags.contains(FieldFlags::COMB) && cells > 0 => {
+                    FieldContent::Comb(&shown, cells as usize)
+                }
+                _ => FieldContent::Line(&shown),
+            },
+            FieldType::Multiline => FieldContent::Wrapped(&shown),
+            FieldType::Combo => FieldContent::Line(&shown),
+            // The values of a multiple selection are joined by newlines.
+            FieldType::List => FieldContent::List {
+                options: field.options.iter().map(|(_, display)| display.clone()).collect(),
+                selected: (field.options.iter().enumerate())
+                    .filter(|(_, (export, _))| value.lines().any(|selected| selected == export))
+                    .map(|(i, _)| i)
+                    .collect(),
+            },
+            FieldType::Checkbox | FieldType::Radio => return self.regenerate_button_appearances(&field, widget_id),
+            _ => return Ok(false),
+        };
+        let appearance = text_field_appearance(self, &field, self.get_dictionary(widget_id)?, &content)?;
+        let appearance_id = self.add_object(appearance);
+        let widget = self.get_object_mut(widget_id)?.as_dict_mut()?;
+        widget.set("AP", dictionary! { "N" => appearance_id });
+        Ok(true)
+    }
+
+    /// The on states of the normal appearance of each widget of a button field.
+    fn widget_states(&self, field: &FormField) -> Vec<Vec<Vec<u8>>> {
+        field
+            .widgets
+            .iter()
+            .map(|widget| match self.get_dictionary(widget.id) {
+                Ok(dict) => match Annotation::from_dict(self, Some(widget.id), dict).normal_appearance {
+                    Some(crate::Appearance::States(states)) => states
+                        .into_iter()
+                        .map(|(state, _)| state)
+                        .filter(|state| state != b"Off")
+                        .collect(),
+                    _ => Vec::new(),
+                },
+                Err(_) => Vec::new(),
+            })
+            .collect()
+    }
+
+    /// Set the value of a button field and turn on the widgets having that state: all of them in `unison`, or
+    /// else only the first one.
+    fn set_button_state(
+        &mut self, field: &FormField, states: &[Vec<Vec<u8>>], state: Vec<u8>, unison: bool,
+    ) -> Result<()> {
+        let mut turned_on = false;
+        for (widget, widget_states) in field.widgets.iter().zip(states) {
+            let widget_state = if widget_states.contains(&state) && (unison || !turned_on) {
+                turned_on = true;
+                state.clone()
+            } else {
+                b"Off".to_vec()
+            };
+            self.get_object_mut(widget.id)?
+                .as_dict_mut()?
+                .set("AS", Object::Name(widget_state));
+        }
+        self.get_object_mut(field.id)?
+            .as_dict_mut()?
+            .set("V", Object::Name(state));
+        Ok(())
+    }
+}
+
+/// The on state of a button field for an export value. It is the state of that name, compared as text so that
+/// names with non-ASCII characters match, or with options, the index of the option with that export value.
+fn button_state(field: &FormField, states: &[Vec<Vec<u8>>], export: &str) -> Option<Vec<u8>> {
+    let find = |text: &str| states.iter().flatten().find(|state| name_text(state) == text).cloned();
+    find(export).or_else(|| {
+        let index = field.options.iter().position(|(option, _)| option == export)?;
+        find(&index.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::creator::tests::create_document;
+    use crate::forms::tests::form_document;
+    use crate::{Dictionary, ObjectId, Stream};
+
+    fn value(doc: &Document, field: &FormField) -> Option<String> {
+        doc.form_fields()
+            .into_iter()
+            .find(|other| other.id == field.id)
+            .unwrap()
+            .value
+    }
+
+    #[test]
+    fn fill_form_and_regenerate_appearances() {
+        let mut doc = form_document();
+        let mut values = HashMap::new();
+        values.insert("name".to_string(), FieldValue::Text("Jane Doe-Smith".to_string()));
+        values.insert(
+            "address".to_string(),
+            FieldValue::Text("1 Long Street\nSome Town With A Really Quite Long Name, Somewhere".to_string()),
+        );
+        values.insert("agree".to_string(), FieldValue::Checked(true));
+        values.insert("size".to_string(), FieldValue::Text("m".to_string()));
+        values.insert("color".to_string(), FieldValue::Text("g".to_string()));
+        values.insert("missing".to_string(), FieldValue::Text("x".to_string()));
+        let report = doc.fill_form(&values).unwrap();
+        assert_eq!(report.filled, vec!["address", "agree", "color", "name", "size"]);
+        assert_eq!(report.unknown, vec!["missing"]);
+        assert!(report.rejected.is_empty());
+
+        let fields = doc.form_fields();
+        assert_eq!(value(&doc, &fields[0]).as_deref(), Some("Jane Doe"));
+        assert_eq!(value(&doc, &fields[2]).as_deref(), Some("Yes"));
+        assert_eq!(value(&doc, &fields[3]).as_deref(), Some("m"));
+        let states: Vec<_> = fields[3]
+            .widgets
+            .iter()
+            .map(|widget| {
+                doc.get_dictionary(widget.id)
+                    .unwrap()
+                    .get(b"AS")
+                    .unwrap()
+                    .as_name_str()
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(states, vec!["Off", "m"]);
+
+        for (field, shown) in &[(&fields[0], &b"(Jane Doe) Tj"[..]), (&fields[4], &b"(Green) Tj"[..])] {
+            let widget = doc.get_dictionary(field.widgets[0].id).unwrap();
+            let appearance_id = widget.get(b"AP").and_then(Object::as_dict).unwrap().get(b"N").unwrap();
+            let appearance = doc
+                .get_object(appearance_id.as_reference().unwrap())
+                .and_then(Object::as_stream)
+                .unwrap();
+            let bbox = crate::geometry::Rect::from_object(appearance.dict.get(b"BBox").unwrap()).unwrap();
+            let rect = field.widgets[0].rect.unwrap();
+            assert_eq!((bbox.width(), bbox.height()), (rect.width(), rect.height()));
+            let content = String::from_utf8_lossy(&appearance.content);
+            assert!(content.contains(std::str::from_utf8(shown).unwrap()), "{}", content);
+            assert!(content.contains("/Helv"));
+        }
+        let widget = doc.get_dictionary(fields[1].widgets[0].id).unwrap();
+        let appearance_id = widget.get(b"AP").and_then(Object::as_dict).unwrap().get(b"N").unwrap();
+        let appearance = doc
+            .get_object(appearance_id.as_reference().unwrap())
+            .and_then(Object::as_stream)
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&appearance.content).matches("Tj").count(), 3);
+
+        let mut values = HashMap::new();
+        values.insert("size".to_string(), FieldValue::Text("xl".to_string()));
+        values.insert("agree".to_string(), FieldValue::Text("Yes".to_string()));
+        values.insert("name".to_string(), FieldValue::Checked(false));
+        let report = doc
+            .fill_form_with(&values, &FillOptions { need_appearances: true })
+            .unwrap();
+        assert_eq!(report.filled, vec!["agree"]);
+        assert_eq!(report.rejected.len(), 2);
+        let (_, acro_form) = doc.acro_form().unwrap();
+        assert!(acro_form.get(b"NeedAppearances").and_then(Object::as_bool).unwrap());
+    }
+
+    /// Buttons structured like those written by Acrobat and LibreOffice: an Acrobat radio group `shipping` with a
+    /// UTF-8 state name and a checkbox `newsletter`, a LibreOffice radio group `delivery` in unison with a
+    /// PDFDocEncoding state name, and an Acrobat checkbox `terms` whose non-ASCII export value is an option.
+    fn button_document() -> Document {
+        let mut doc = create_document();
+        let page_id = doc.page_iter().next().unwrap();
+        let mut annots = Vec::new();
+        let mut widget = |doc: &mut Document, mut dict: Dictionary, on: &[u8]| -> ObjectId {
+            let form = dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Form",
+                "BBox" => vec![0.into(), 0.into(), 10.into(), 10.into()],
+            };
+            let on_id = doc.add_object(Stream::new(form.clone(), b"0 g 2 2 6 6 re f".to_vec()));
+            let off_id = doc.add_object(Stream::new(form, Vec::new()));
+            let mut states = Dictionary::new();
+            states.set(on.to_vec(), on_id);
+            states.set("Off", off_id);
+            dict.set("Type", "Annot");
+            dict.set("Subtype", "Widget");
+            dict.set("Rect", vec![0.into(), 0.into(), 10.into(), 10.into()]);
+            dict.set("P", page_id);
+            dict.set("AS", "Off");
+            dict.set("AP", dictionary! { "N" => states });
+            let id = doc.add_object(dict);
+            annots.push(Object::Reference(id));
+            id
+        };
+        let mut group = |doc: &mut Document, name: &str, flags: FieldFlags, states: &[&[u8]]| -> ObjectId {
+            let group_id = doc.new_object_id();
+            let kids: Vec<Object> = states
+                .iter()
+                .map(|state| widget(doc, dictionary! { "Parent" => group_id }, state).into())
+                .collect();
+            doc.objects.insert(
+                group_id,
+                Object::Dictionary(dictionary! {
+                    "T" => Object::string_literal(name),
+                    "FT" => "Btn",
+                    "Ff" => (FieldFlags::RADIO | flags).bits() as i64,
+                    "V" => "Off",
+                    "Kids" => kids,
+                }),
+            );
+            group_id
+        };
+        let shipping_id = group(
+            &mut doc,
+            "shipping",
+            FieldFlags::NO_TOGGLE_TO_OFF,
+            &[b"Standard", b"Express", "Grün".as_bytes()],
+        );
+        let delivery_id = group(
+            &mut doc,
+            "delivery",
+            FieldFlags::RICH_TEXT,
+            &[b"Gr\xFCn", b"Blau", b"Blau"],
+        );
+        let newsletter_id = widget(
+            &mut doc,
+            dictionary! { "T" => Object::string_literal("newsletter"), "FT" => "Btn", "V" => "Off" },
+            b"Yes",
+        );
+        let terms_id = widget(
+            &mut doc,
+            dictionary! {
+                "T" => Object::string_literal("terms"),
+                "FT" => "Btn",
+                "V" => "Off",
+                "Opt" => vec![Object::String(encode_text_string("Accepté"), StringFormat::Literal)],
+            },
+            b"0",
+        );
+
+        doc.get_object_mut(page_id)
+            .and_then(Object::as_dict_mut)
+            .unwrap()
+            .set("Annots", annots);
+        let fields: Vec<Object> = vec![
+            shipping_id.into(),
+            newsletter_id.into(),
+            delivery_id.into(),
+            terms_id.into(),
+        ];
+        let acro_form_id = doc.add_object(dictionary! { "Fields" => fields });
+        let root_id = doc.trailer.get(b"Root").unwrap().as_reference().unwrap();
+        doc.get_object_mut(root_id)
+            .and_then(Object::as_dict_mut)
+            .unwrap()
+            .set("AcroForm", acro_form_id);
+        doc
+    }
+
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    #[test]
+    fn fill_radio_buttons_and_checkboxes() {
+        let mut doc = button_document();
+        let states = |doc: &Document, field: &FormField| -> Vec<Vec<u8>> {
+            field
+                .widgets
+                .iter()
+                .map(|widget| {
+                    let widget = doc.get_dictionary(widget.id).unwrap();
+                    widget.get(b"AS").and_then(Object::as_name).unwrap().to_vec()
+                })
+                .collect()
+        };
+        let fill = |doc: &mut Document, name: &str, value: FieldValue| {
+            let mut values = HashMap::new();
+            values.insert(name.to_string(), value);
+            let report = doc.fill_form(&values).unwrap();
+            report.rejected.into_iter().next().map(|(_, reason)| reason)
+        };
+
+        let fields = doc.form_fields();
+        let (shipping, newsletter, delivery, terms) = (&fields[0], &fields[1], &fields[2], &fields[3]);
+        assert_eq!(fill(&mut doc, "shipping", FieldValue::Text("Grün".to_string())), None);
+        assert_eq!(
+            states(&doc, shipping),
+            vec![b"Off".to_vec(), b"Off".to_vec(), "Grün".as_bytes().to_vec()]
+        );
+        assert!(fill(&mut doc, "shipping", FieldValue::Text("Off".to_string())).is_some());
+        assert!(fill(&mut doc, "shipping", FieldValue::Text("Overnight".to_string())).is_some());
+
+        assert_eq!(fill(&mut doc, "delivery", FieldValue::Text("Grün".to_string())), None);
+        assert_eq!(states(&doc, delivery)[0], b"Gr\xFCn");
+        assert_eq!(fill(&mut doc, "delivery", FieldValue::Text("Blau".to_string())), None);
+        assert_eq!(
+            states(&doc, delivery),
+            vec![b"Off".to_vec(), b"Blau".to_vec(), b"Blau".to_vec()]
+        );
+        assert_eq!(fill(&mut doc, "delivery", FieldValue::Text("Off".to_string())), None);
+        assert_eq!(states(&doc, delivery), vec![b"Off".to_vec(); 3]);
+        assert_eq!(fill(&mut doc, "delivery", FieldValue::Text("Grün".to_string())), None);
+
+        assert_eq!(fill(&mut doc, "newsletter", FieldValue::Checked(true)), None);
+        assert_eq!(states(&doc, newsletter), vec![b"Yes".to_vec()]);
+        assert_eq!(fill(&mut doc, "terms", FieldValue::Text("Accepté".to_string())), None);
+        assert_eq!(states(&doc, terms), vec![b"0".to_vec()]);
+        assert_eq!(fill(&mut doc, "terms", FieldValue::Checked(false)), None);
+        assert_eq!(states(&doc, terms), vec![b"Off".to_vec()]);
+        assert_eq!(fill(&mut doc, "terms", FieldValue::Checked(true)), None);
+        assert_eq!(states(&doc, terms), vec![b"0".to_vec()]);
+
+        // Non-ASCII state names survive saving and loading, whatever their encoding.
+        let mut saved = Vec::new();
+        doc.save_to(&mut saved).unwrap();
+        let doc = Document::load_mem(&saved).unwrap();
+        let values: Vec<Option<String>> = doc.form_fields().into_iter().map(|field| field.value).collect();
+        let expected = ["Grün", "Yes", "Grün", "0"];
+        assert_eq!(
+            values,
+            expected.iter().map(|value| Some(value.to_string())).collect::<Vec<_>>()
+        );
+    }
+}
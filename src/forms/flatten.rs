@@ -0,0 +1,251 @@
+use super::FormField;
+use crate::annotations::{Annotation, AnnotationFlags};
+use crate::content::{Content, Operation};
+use crate::geometry::{transform_point, Matrix, Rect, IDENTITY_MATRIX};
+use crate::{Dictionary, Document, Object, ObjectId, Result, Stream};
+use std::collections::BTreeMap;
+
+impl Document {
+    /// Draw the normal appearance of the widgets of some fields, or of all fields, into page content, and remove
+    /// the fields from the form. Returns the number of fields removed.
+    ///
+    /// Hidden widgets are removed without being drawn. The interactive form is deleted once it has no fields left;
+    /// the objects that are no longer referenced can then be deleted with [`Document::prune_objects`].
+    pub fn flatten_form(&mut self, fields: Option<&[String]>) -> Result<usize> {
+        let selected: Vec<FormField> = self
+            .form_fields()
+            .into_iter()
+            .filter(|field| fields.is_none_or(|names| names.contains(&field.name)))
+            .collect();
+
+        let mut drawn: BTreeMap<ObjectId, Vec<Operation>> = BTreeMap::new();
+        for field in &selected {
+            for widget in &field.widgets {
+                let page_id = match widget.page {
+                    Some(page_id) => page_id,
+                    None => continue,
+                };
+                let annotation = Annotation::from_dict(self, Some(widget.id), self.get_dictionary(widget.id)?);
+                let hidden = annotation
+                    .flags
+                    .intersects(AnnotationFlags::HIDDEN | AnnotationFlags::NO_VIEW);
+                if let Some(rect) = annotation.rect.filter(|_| !hidden) {
+                    if let Some(form_id) = self.appearance_xobject(&annotation)? {
+                        let operations = self.appearance_operations(page_id, rect, form_id)?;
+                        drawn.entry(page_id).or_default().extend(operations);
+                    }
+                }
+                self.remove_annotation(page_id, widget.id)?;
+            }
+        }
+        for (page_id, operations) in drawn {
+            self.append_isolated_content(page_id, Content { operations }.encode()?)?;
+        }
+        for field in &selected {
+            self.remove_field(field.id)?;
+        }
+
+        let no_fields_left = self.acro_form().is_some_and(|(_, acro_form)| {
+            acro_form
+                .get_deref(b"Fields", self)
+                .and_then(Object::as_array)
+                .map_or(true, Vec::is_empty)
+        });
+        if no_fields_left {
+            let root_id = self.trailer.get(b"Root")?.as_reference()?;
+            self.get_object_mut(root_id)?.as_dict_mut()?.remove(b"AcroForm");
+        }
+        Ok(selected.len())
+    }
+
+    /// The normal appearance stream of an annotation, marked as a form XObject, or `None` if there is no stream
+    /// with a bounding box to draw.
+    pub(crate) fn appearance_xobject(&mut self, annotation: &Annotation) -> Result<Option<ObjectId>> {
+        let form_id = match annotation.normal_appearance_stream() {
+            Some(form_id) => form_id,
+            None => return Ok(None),
+        };
+        let form = match self.get_object_mut(form_id) {
+            Ok(Object::Stream(form)) if form.dict.has(b"BBox") => form,
+            _ => return Ok(None),
+        };
+        form.dict.set("Type", "XObject");
+        form.dict.set("Subtype", "Form");
+        Ok(Some(form_id))
+    }
+
+    /// The operations drawing a form XObject of a page over an annotation rectangle.
+    pub(crate) fn appearance_operations(
+        &mut self, page_id: ObjectId, rect: Rect, form_id: ObjectId,
+    ) -> Result<Vec<Operation>> {
+        let matrix = self.appearance_matrix(rect, form_id)?;
+        let name = self.add_page_xobject(page_id, form_id)?;
+        Ok(vec![
+            Operation::new("q", vec![]),
+            Operation::new("cm", matrix.iter().map(|&value| value.into()).collect()),
+            Operation::new("Do", vec![Object::Name(name)]),
+            Operation::new("Q", vec![]),
+        ])
+    }
+
+    /// The `cm` operands that map the bounding box of an appearance, after its own matrix, onto a rectangle.
+    pub(crate) fn appearance_matrix(&self, rect: Rect, form_id: ObjectId) -> Result<Matrix> {
+        let form = self.get_object(form_id)?.as_stream()?;
+        let bbox = Rect::from_object(form.dict.get_deref(b"BBox", self)?)?;
+        let matrix = match form.dict.get_deref(b"Matrix", self).and_then(Object::as_array) {
+            Ok(values) if values.len() == 6 => {
+                let mut matrix = IDENTITY_MATRIX;
+                for (entry, value) in matrix.iter_mut().zip(values) {
+                    *entry = value.as_float()?;
+                }
+                matrix
+            }
+            _ => IDENTITY_MATRIX,
+        };
+        // The transformed bounding box is the smallest rectangle containing the transformed corners.
+        let corners = [
+            (bbox.llx, bbox.lly),
+            (bbox.urx, bbox.lly),
+            (bbox.llx, bbox.ury),
+            (bbox.urx, bbox.ury),
+        ];
+        let (x, y) = transform_point(&matrix, bbox.llx, bbox.lly);
+        let transformed = corners.iter().fold(Rect::new(x, y, x, y), |bounds, &(x, y)| {
+            let (x, y) = transform_point(&matrix, x, y);
+            bounds.union(&Rect::new(x, y, x, y))
+        });
+        let scale = |target: f64, source: f64| if source == 0.0 { 1.0 } else { target / source };
+        let (sx, sy) = (
+            scale(rect.width(), transformed.width()),
+            scale(rect.height(), transformed.height()),
+        );
+        // `Do` applies the matrix of the form itself.
+        Ok([
+            sx,
+            0.0,
+            0.0,
+            sy,
+            rect.llx - transformed.llx * sx,
+            rect.lly - transformed.lly * sy,
+        ])
+    }
+
+    /// Add a form XObject to the resources of a page under a new name.
+    pub(crate) fn add_page_xobject(&mut self, page_id: ObjectId, form_id: ObjectId) -> Result<Vec<u8>> {
+        let resources = self.page_resources_mut(page_id)?;
+        if !resources.has(b"XObject") {
+            resources.set("XObject", Dictionary::new());
+        }
+        let xobjects = resources.get_mut(b"XObject")?.as_dict_mut()?;
+        let mut name = format!("Fm{}", form_id.0).into_bytes();
+        while xobjects.has(&name) {
+            name.push(b'_');
+        }
+        xobjects.set(name.clone(), form_id);
+        Ok(name)
+    }
+
+    /// The resources of a page, made the page's own by [`Document::isolate_page_resources`].
+    pub(crate) fn page_resources_mut(&mut self, page_id: ObjectId) -> Result<&mut Dictionary> {
+        let resources_id = self.isolate_page_resources(page_id)?;
+        self.get_object_mut(resources_id)?.as_dict_mut()
+    }
+
+    /// Append content to a page, isolated from the graphics state left by the existing content.
+    pub(crate) fn append_isolated_content(&mut self, page_id: ObjectId, content: Vec<u8>) -> Result<()> {
+        let existing = match self.get_dictionary(page_id)?.get(b"Contents") {
+            Ok(Object::Array(contents)) => contents.clone(),
+            // An array may also be an object of its own.
+            Ok(Object::Reference(id)) => match self.get_object(*id) {
+                Ok(Object::Array(contents)) => contents.clone(),
+                _ => vec![Object::Reference(*id)],
+            },
+            Ok(contents) => vec![contents.clone()],
+            Err(_) => Vec::new(),
+        };
+        let mut contents = Vec::with_capacity(existing.len() + 2);
+        if !existing.is_empty() {
+            contents.push(self.add_object(Stream::new(Dictionary::new(), b"q\n".to_vec())).into());
+        }
+        let last = if existing.is_empty() {
+            content
+        } else {
+            [&b"Q\n"[..], &content].concat()
+        };
+        contents.extend(existing);
+        contents.push(self.add_object(Stream::new(Dictionary::new(), last)).into());
+        self.get_object_mut(page_id)?.as_dict_mut()?.set("Contents", contents);
+        Ok(())
+    }
+
+    /// Remove a field from its parent, or from the interactive form, and remove ancestors left without kids.
+    pub(crate) fn remove_field(&mut self, field_id: ObjectId) -> Result<()> {
+        let parent_id = self
+            .get_dictionary(field_id)?
+            .get(b"Parent")
+            .and_then(Object::as_reference)
+            .ok();
+        let siblings = self.field_kids_mut(parent_id)?;
+        siblings.retain(|kid| kid.as_reference().ok() != Some(field_id));
+        match parent_id {
+            Some(parent_id) if siblings.is_empty() => self.remove_field(parent_id),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::forms::tests::form_document;
+    use crate::forms::FieldValue;
+    use std::collections::HashMap;
+
+    #[test]
+    fn flatten_filled_form() {
+        let mut doc = form_document();
+        let mut values = HashMap::new();
+        values.insert("name".to_string(), FieldValue::Text("Jane".to_string()));
+        values.insert("size".to_string(), FieldValue::Text("m".to_string()));
+        doc.fill_form(&values).unwrap();
+        let page_id = doc.page_iter().next().unwrap();
+        let fields = doc.form_fields();
+        let name_appearance = Annotation::from_dict(&doc, None, doc.get_dictionary(fields[0].id).unwrap())
+            .normal_appearance_stream()
+            .unwrap();
+
+        assert_eq!(
+            doc.flatten_form(Some(&["name".to_string(), "size".to_string()]))
+                .unwrap(),
+            2
+        );
+        let names: Vec<String> = doc.form_fields().into_iter().map(|field| field.name).collect();
+        assert_eq!(names, vec!["address", "agree", "color"]);
+        assert_eq!(doc.annotations(page_id).len(), 3);
+
+        let page = doc.get_dictionary(page_id).unwrap();
+        let resources = page.get_deref(b"Resources", &doc).and_then(Object::as_dict).unwrap();
+        assert!(resources.get(b"Font").and_then(Object::as_dict).unwrap().has(b"F1"));
+        let xobjects = resources.get(b"XObject").and_then(Object::as_dict).unwrap();
+        assert_eq!(xobjects.len(), 3);
+        let contents = page.get(b"Contents").and_then(Object::as_array).unwrap();
+        assert_eq!(contents.len(), 3);
+        let flattened = doc
+            .get_object(contents[2].as_reference().unwrap())
+            .and_then(Object::as_stream)
+            .unwrap();
+        let flattened = String::from_utf8_lossy(&flattened.content);
+        // The appearance of the name field has a bounding box of the widget size, so it is only moved.
+        let name = format!("/Fm{} Do", name_appearance.0);
+        assert!(
+            flattened.contains(&format!("q\n1 0 0 1 50 700 cm\n{}", name)),
+            "{}",
+            flattened
+        );
+        assert_eq!(flattened.matches(" Do").count(), 3);
+
+        assert_eq!(doc.flatten_form(None).unwrap(), 3);
+        assert!(doc.annotations(page_id).is_empty());
+        assert!(doc.acro_form().is_none());
+    }
+}
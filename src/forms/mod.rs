@@ -0,0 +1,584 @@
+use crate::encodings::decode_text_string;
+use crate::geometry::Rect;
+use crate::{Dictionary, Document, Error, Object, ObjectId, Result};
+use bitflags::bitflags;
+use log::warn;
+use std::collections::{BTreeMap, BTreeSet};
+
+pub(crate) mod appearance;
+mod create;
+mod fill;
+mod flatten;
+pub use self::create::{ChoiceFieldOptions, SignatureFieldOptions, TextFieldOptions};
+pub use self::fill::{FieldValue, FillOptions, FillReport};
+
+bitflags! {
+    /// Field flags, from the `/Ff` entry. Most flags only apply to one field type.
+    pub struct FieldFlags: u32 {
+        const READ_ONLY = 1;
+        const REQUIRED = 1 << 1;
+        const NO_EXPORT = 1 << 2;
+        const MULTILINE = 1 << 12;
+        const PASSWORD = 1 << 13;
+        const NO_TOGGLE_TO_OFF = 1 << 14;
+        const RADIO = 1 << 15;
+        const PUSHBUTTON = 1 << 16;
+        const COMBO = 1 << 17;
+        const EDIT = 1 << 18;
+        const SORT = 1 << 19;
+        const FILE_SELECT = 1 << 20;
+        const MULTI_SELECT = 1 << 21;
+        const DO_NOT_SPELL_CHECK = 1 << 22;
+        const DO_NOT_SCROLL = 1 << 23;
+        const COMB = 1 << 24;
+        /// `RichText` for text fields and `RadiosInUnison` for radio buttons.
+        const RICH_TEXT = 1 << 25;
+        const COMMIT_ON_SEL_CHANGE = 1 << 26;
+    }
+}
+
+/// Field type, from the `/FT` entry and the type-specific flags.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldType {
+    Checkbox,
+    Radio,
+    PushButton,
+    Text,
+    Multiline,
+    Password,
+    Combo,
+    List,
+    Signature,
+    /// A field type not listed above, or a missing `/FT`.
+    Unknown(Vec<u8>),
+}
+
+impl FieldType {
+    fn new(field_type: Option<&[u8]>, flags: FieldFlags) -> FieldType {
+        match field_type {
+            Some(b"Btn") if flags.contains(FieldFlags::PUSHBUTTON) => FieldType::PushButton,
+            Some(b"Btn") if flags.contains(FieldFlags::RADIO) => FieldType::Radio,
+            Some(b"Btn") => FieldType::Checkbox,
+            Some(b"Tx") if flags.contains(FieldFlags::PASSWORD) => FieldType::Password,
+            Some(b"Tx") if flags.contains(FieldFlags::MULTILINE) => FieldType::Multiline,
+            Some(b"Tx") => FieldType::Text,
+            Some(b"Ch") if flags.contains(FieldFlags::COMBO) => FieldType::Combo,
+            Some(b"Ch") => FieldType::List,
+            Some(b"Sig") => FieldType::Signature,
+            other => FieldType::Unknown(other.unwrap_or_default().to_vec()),
+        }
+    }
+}
+
+/// Widget annotation of a form field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Widget {
+    pub id: ObjectId,
+    pub page: Option<ObjectId>,
+    pub rect: Option<Rect>,
+}
+
+/// A terminal form field with its inherited attributes resolved.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormField {
+    pub id: ObjectId,
+    /// Fully qualified name, the partial names of the field and its ancestors joined by periods.
+    pub name: String,
+    pub field_type: FieldType,
+    pub flags: FieldFlags,
+    /// The `/V` entry; the values of a multiple selection are joined by newlines.
+    pub value: Option<String>,
+    pub default_value: Option<String>,
+    /// Export value and display text of the options of a choice field.
+    pub options: Vec<(String, String)>,
+    /// The default appearance string, falling back to the one of the interactive form.
+    pub default_appearance: Option<Vec<u8>>,
+    /// Text alignment: 0 for left, 1 for centered and 2 for right.
+    pub quadding: i64,
+    pub max_len: Option<i64>,
+    pub widgets: Vec<Widget>,
+}
+
+impl FormField {
+    pub fn is_read_only(&self) -> bool {
+        self.flags.contains(FieldFlags::READ_ONLY)
+    }
+
+    pub fn is_required(&self) -> bool {
+        self.flags.contains(FieldFlags::REQUIRED)
+    }
+}
+
+/// Attributes that terminal fields inherit from their ancestors.
+#[derive(Clone, Default)]
+struct Inherited {
+    name: Option<String>,
+    field_type: Option<Vec<u8>>,
+    flags: Option<i64>,
+    value: Option<String>,
+    default_value: Option<String>,
+    default_appearance: Option<Vec<u8>>,
+    quadding: Option<i64>,
+    max_len: Option<i64>,
+}
+
+impl Document {
+    /// The interactive form dictionary of the document, `/Root /AcroForm`.
+    pub fn acro_form(&self) -> Option<(Option<ObjectId>, &Dictionary)> {
+        let acro_form = self.catalog().ok()?.get(b"AcroForm").ok()?;
+        match self.dereference(acro_form).ok()? {
+            (id, Object::Dictionary(acro_form)) => Some((id, acro_form)),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn acro_form_mut(&mut self) -> Result<&mut Dictionary> {
+        match self.acro_form().ok_or_else(|| Error::dict_key(b"AcroForm"))?.0 {
+            Some(id) => self.get_object_mut(id)?.as_dict_mut(),
+            None => {
+                let root_id = self.trailer.get(b"Root")?.as_reference()?;
+                let catalog = self.get_object_mut(root_id)?.as_dict_mut()?;
+                catalog.get_mut(b"AcroForm")?.as_dict_mut()
+            }
+        }
+    }
+
+    /// The kids of a field, or the root fields of the interactive form, created if missing.
+    pub(crate) fn field_kids_mut(&mut self, parent_id: Option<ObjectId>) -> Result<&mut Vec<Object>> {
+        let (key, kids) = match parent_id {
+            Some(parent_id) => (&b"Kids"[..], self.get_dictionary(parent_id)?.get(b"Kids")),
+            None => (&b"Fields"[..], self.acro_form().ok_or_else(|| Error::dict_key(b"AcroForm"))?.1.get(b"Fields")),
+        };
+        if let Ok(kids_id) = kids.and_then(Object::as_reference) {
+            return self.get_object_mut(kids_id)?.as_array_mut();
+        }
+        let holder = match parent_id {
+            Some(parent_id) => self.get_object_mut(parent_id)?.as_dict_mut()?,
+            None => self.acro_form_mut()?,
+        };
+        if !holder.has(key) {
+            holder.set(key, Vec::<Object>::new());
+        }
+        holder.get_mut(key)?.as_array_mut()
+    }
+
+    /// The terminal fields of the interactive form, in the order of its field tree.
+    pub fn form_fields(&self) -> Vec<FormField> {
+        let acro_form = match self.acro_form() {
+            Some((_, acro_form)) => acro_form,
+            None => return Vec::new(),
+        };
+        let inherited = Inherited {
+            default_appearance: acro_form.get(b"DA").and_then(Object::as_str).ok().map(<[u8]>::to_vec),
+            quadding: acro_form.get(b"Q").and_then(Object::as_i64).ok(),
+            ..Default::default()
+        };
+        let widget_pages = self.widget_pages();
+        let mut fields = Vec::new();
+        let mut visited = BTreeSet::new();
+        if let Some(roots) = acro_form
+            .get(b"Fields")
+            .ok()
+            .and_then(|fields| self.dereference(fields).ok())
+        {
+            for root in roots.1.as_array().map(Vec::as_slice).unwrap_or_default() {
+                match root.as_reference() {
+                    Ok(id) => self.collect_fields(id, &inherited, &widget_pages, &mut visited, &mut fields),
+                    Err(_) => warn!("Skipping form field {:?} which is not a reference", root),
+                }
+            }
+        }
+        fields
+    }
+
+    /// Map from widget annotation to the page listing it in its `/Annots`.
+    fn widget_pages(&self) -> BTreeMap<ObjectId, ObjectId> {
+        let mut pages = BTreeMap::new();
+        for page_id in self.page_iter() {
+            for annotation in self.annotations(page_id) {
+                if let Some(id) = annotation.id {
+                    pages.insert(id, page_id);
+                }
+            }
+        }
+        pages
+    }
+
+    fn collect_fields(
+        &self, id: ObjectId, parent: &Inherited, widget_pages: &BTreeMap<ObjectId, ObjectId>,
+        visited: &mut BTreeSet<ObjectId>, fields: &mut Vec<FormField>,
+    ) {
+        if !visited.insert(id) {
+            warn!("Form field {:?} is part of a loop in the field tree", id);
+            return;
+        }
+        let dict = match self.get_dictionary(id) {
+            Ok(dict) => dict,
+            Err(_) => {
+                warn!("Skipping form field {:?} which is not a dictionary", id);
+                return;
+            }
+        };
+        let text = |key: &[u8]| dict.get(key).ok().and_then(|value| field_text(self, value));
+        let mut inherited = parent.clone();
+        if let Some(partial_name) = text(b"T") {
+            inherited.name = Some(match &parent.name {
+                Some(parent_name) => format!("{}.{}", parent_name, partial_name),
+                None => partial_name,
+            });
+        }
+        if let Ok(field_type) = dict.get(b"FT").and_then(Object::as_name) {
+            inherited.field_type = Some(field_type.to_vec());
+        }
+        if let Ok(flags) = dict.get(b"Ff").and_then(Object::as_i64) {
+            inherited.flags = Some(flags);
+        }
+        if dict.has(b"V") {
+            inherited.value = text(b"V");
+        }
+        if dict.has(b"DV") {
+            inherited.default_value = text(b"DV");
+        }
+        if let Ok(default_appearance) = dict.get(b"DA").and_then(Object::as_str) {
+            inherited.default_appearance = Some(default_appearance.to_vec());
+        }
+        if let Ok(quadding) = dict.get(b"Q").and_then(Object::as_i64) {
+            inherited.quadding = Some(quadding);
+        }
+        if let Ok(max_len) = dict.get(b"MaxLen").and_then(Object::as_i64) {
+            inherited.max_len = Some(max_len);
+        }
+
+        // Kids with a partial name are child fields, the others are the widgets of this field.
+        let kids = dict
+            .get(b"Kids")
+            .and_then(|kids| self.dereference(kids))
+            .map(|(_, kids)| kids);
+        let mut widgets = Vec::new();
+        if dict.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Widget") {
+            widgets.push(self.widget(id, dict, widget_pages));
+        }
+        let mut has_child_fields = false;
+        for kid in kids.and_then(Object::as_array).map(Vec::as_slice).unwrap_or_default() {
+            let kid_id = match kid.as_reference() {
+                Ok(kid_id) => kid_id,
+                Err(_) => continue,
+            };
+            match self.get_dictionary(kid_id) {
+                Ok(kid) if kid.has(b"T") => {
+                    has_child_fields = true;
+                    self.collect_fields(kid_id, &inherited, widget_pages, visited, fields);
+                }
+                Ok(kid) => widgets.push(self.widget(kid_id, kid, widget_pages)),
+                Err(_) => warn!("Skipping widget {:?} which is not a dictionary", kid_id),
+            }
+        }
+        if has_child_fields && widgets.is_empty() {
+            return;
+        }
+
+        let flags = FieldFlags::from_bits_truncate(inherited.flags.unwrap_or(0) as u32);
+        let options = dict
+            .get(b"Opt")
+            .ok()
+            .and_then(|options| self.dereference(options).ok())
+            .and_then(|(_, options)| options.as_array().ok())
+            .map(|options| {
+                options
+                    .iter()
+                    .filter_map(|option| choice_option(self, option))
+                    .collect()
+            })
+            .unwrap_or_default();
+        fields.push(FormField {
+            id,
+            name: inherited.name.unwrap_or_default(),
+            field_type: FieldType::new(inherited.field_type.as_deref(), flags),
+            flags,
+            value: inherited.value,
+            default_value: inherited.default_value,
+            options,
+            default_appearance: inherited.default_appearance,
+            quadding: inherited.quadding.unwrap_or(0),
+            max_len: inherited.max_len,
+            widgets,
+        });
+    }
+
+    fn widget(&self, id: ObjectId, dict: &Dictionary, widget_pages: &BTreeMap<ObjectId, ObjectId>) -> Widget {
+        Widget {
+            id,
+            page: widget_pages
+                .get(&id)
+                .cloned()
+                .or_else(|| dict.get(b"P").and_then(Object::as_reference).ok()),
+            rect: dict.get(b"Rect").ok().and_then(|rect| Rect::from_object(rect).ok()),
+        }
+    }
+}
+
+/// Text of a field value, which is a text string, a name for buttons or an array for multiple selections.
+fn field_text(doc: &Document, value: &Object) -> Option<String> {
+    match doc.dereference(value).ok()?.1 {
+        Object::String(bytes, _) => Some(decode_text_string(bytes)),
+        Object::Name(name) => Some(name_text(name)),
+        Object::Array(values) => Some(
+            values
+                .iter()
+                .filter_map(|value| field_text(doc, value))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ),
+        _ => None,
+    }
+}
+
+/// Text of a name, such as a button state, which is UTF-8 unless written by older software in PDFDocEncoding.
+pub(crate) fn name_text(name: &[u8]) -> String {
+    match std::str::from_utf8(name) {
+        Ok(text) => text.to_string(),
+        Err(_) => decode_text_string(name),
+    }
+}
+
+/// An option of a choice field, either a text string or an array of export value and display text.
+fn choice_option(doc: &Document, option: &Object) -> Option<(String, String)> {
+    match doc.dereference(option).ok()?.1 {
+        Object::Array(pair) => match pair.as_slice() {
+            [export, display] => Some((field_text(doc, export)?, field_text(doc, display)?)),
+            _ => None,
+        },
+        option => {
+            let text = field_text(doc, option)?;
+            Some((text.clone(), text))
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::creator::tests::create_document;
+    use crate::Stream;
+
+    /// A document with a form on its first page: a text field `name` limited to 8 characters, a multiline text
+    /// field `address`, a checkbox `agree`, a radio group `size` with the values `s` and `m`, and a combo box
+    /// `color`.
+    pub fn form_document() -> Document {
+        let mut doc = create_document();
+        let page_id = doc.page_iter().next().unwrap();
+        let font_id = doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Helvetica",
+            "Encoding" => "WinAnsiEncoding",
+        });
+        let rect =
+            |llx: i64, lly: i64, urx: i64, ury: i64| Object::from(vec![llx.into(), lly.into(), urx.into(), ury.into()]);
+        let widget = |doc: &mut Document, mut dict: Dictionary, rect: Object| {
+            dict.set("Type", "Annot");
+            dict.set("Subtype", "Widget");
+            dict.set("Rect", rect);
+            dict.set("P", page_id);
+            dict.set("MK", dictionary! { "BC" => vec![0.into()] });
+            doc.add_object(dict)
+        };
+        let button = |doc: &mut Document, dict: Dictionary, rect: Object, on: &str| {
+            let form = dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Form",
+                "BBox" => vec![0.into(), 0.into(), 10.into(), 10.into()],
+            };
+            let on_id = doc.add_object(Stream::new(form.clone(), b"0 g 2 2 6 6 re f".to_vec()));
+            let off_id = doc.add_object(Stream::new(form, Vec::new()));
+            let mut states = Dictionary::new();
+            states.set(on, on_id);
+            states.set("Off", off_id);
+            let id = widget(doc, dict, rect);
+            doc.get_object_mut(id)
+                .and_then(Object::as_dict_mut)
+                .unwrap()
+                .set("AP", dictionary! { "N" => states });
+            id
+        };
+
+        let name_id = widget(
+            &mut doc,
+            dictionary! {
+                "T" => Object::string_literal("name"),
+                "FT" => "Tx",
+                "MaxLen" => 8,
+                "Q" => 1,
+            },
+            rect(50, 700, 250, 720),
+        );
+        let address_id = widget(
+            &mut doc,
+            dictionary! {
+                "T" => Object::string_literal("address"),
+                "FT" => "Tx",
+                "Ff" => FieldFlags::MULTILINE.bits() as i64,
+                "DA" => Object::string_literal("/Helv 10 Tf 0 0 1 rg"),
+            },
+            rect(50, 600, 250, 680),
+        );
+        let agree_id = button(
+            &mut doc,
+            dictionary! {
+                "T" => Object::string_literal("agree"),
+                "FT" => "Btn",
+                "V" => "Off",
+                "AS" => "Off",
+            },
+            rect(50, 560, 60, 570),
+            "Yes",
+        );
+        let size_id = doc.new_object_id();
+        let small_id = button(
+            &mut doc,
+            dictionary! { "Parent" => size_id },
+            rect(50, 530, 60, 540),
+            "s",
+        );
+        let medium_id = button(
+            &mut doc,
+            dictionary! { "Parent" => size_id },
+            rect(70, 530, 80, 540),
+            "m",
+        );
+        doc.objects.insert(
+            size_id,
+            Object::Dictionary(dictionary! {
+                "T" => Object::string_literal("size"),
+                "FT" => "Btn",
+                "Ff" => (FieldFlags::RADIO | FieldFlags::NO_TOGGLE_TO_OFF).bits() as i64,
+                "Kids" => vec![small_id.into(), medium_id.into()],
+            }),
+        );
+        let color_id = widget(
+            &mut doc,
+            dictionary! {
+                "T" => Object::string_literal("color"),
+                "FT" => "Ch",
+                "Ff" => FieldFlags::COMBO.bits() as i64,
+                "Opt" => vec![
+                    vec![Object::string_literal("r"), Object::string_literal("Red")].into(),
+                    vec![Object::string_literal("g"), Object::string_literal("Green")].into(),
+                ],
+            },
+            rect(50, 490, 150, 510),
+        );
+
+        let fields = vec![name_id, address_id, agree_id, size_id, color_id];
+        let annots = vec![name_id, address_id, agree_id, small_id, medium_id, color_id];
+        let page = doc.get_object_mut(page_id).and_then(Object::as_dict_mut).unwrap();
+        page.set("Annots", annots.into_iter().map(Object::from).collect::<Vec<_>>());
+        let acro_form_id = doc.add_object(dictionary! {
+            "Fields" => fields.into_iter().map(Object::from).collect::<Vec<_>>(),
+            "DA" => Object::string_literal("/Helv 0 Tf 0 g"),
+            "DR" => dictionary! { "Font" => dictionary! { "Helv" => font_id } },
+        });
+        let catalog_id = doc.trailer.get(b"Root").and_then(Object::as_reference).unwrap();
+        doc.get_object_mut(catalog_id)
+            .and_then(Object::as_dict_mut)
+            .unwrap()
+            .set("AcroForm", acro_form_id);
+        doc
+    }
+
+    #[test]
+    fn enumerate_form_fields() {
+        let mut doc = create_document();
+        let page_id = doc.page_iter().next().unwrap();
+        let name_id = doc.add_object(dictionary! {
+            "T" => Object::string_literal("name"),
+            "FT" => "Tx",
+            "V" => Object::String(b"\xFE\xFF\x00J\x00o".to_vec(), crate::StringFormat::Hexadecimal),
+            "Subtype" => "Widget",
+            "Rect" => vec![10.into(), 10.into(), 110.into(), 30.into()],
+        });
+        let agree_widget_id = doc.add_object(dictionary! {
+            "Subtype" => "Widget",
+            "Rect" => vec![10.into(), 40.into(), 20.into(), 50.into()],
+            "P" => page_id,
+        });
+        let agree_id = doc.add_object(dictionary! {
+            "T" => Object::string_literal("agree"),
+            "V" => "Yes",
+            "Kids" => vec![agree_widget_id.into()],
+        });
+        let color_id = doc.add_object(dictionary! {
+            "T" => Object::string_literal("color"),
+            "FT" => "Ch",
+            "Ff" => (FieldFlags::COMBO | FieldFlags::REQUIRED).bits() as i64,
+            "Opt" => vec![
+                vec![Object::string_literal("r"), Object::string_literal("Red")].into(),
+                Object::string_literal("Green"),
+                Object::Integer(1),
+            ],
+        });
+        let group_id = doc.add_object(dictionary! {
+            "T" => Object::string_literal("group"),
+            "FT" => "Btn",
+            "Ff" => FieldFlags::READ_ONLY.bits() as i64,
+            "DA" => Object::string_literal("/Helv 0 Tf 0 g"),
+            "Kids" => vec![agree_id.into(), color_id.into()],
+        });
+        for (id, parent) in &[(agree_id, group_id), (color_id, group_id)] {
+            doc.get_object_mut(*id)
+                .and_then(Object::as_dict_mut)
+                .unwrap()
+                .set("Parent", *parent);
+        }
+        let page = doc.get_object_mut(page_id).and_then(Object::as_dict_mut).unwrap();
+        page.set("Annots", vec![name_id.into()]);
+        let acro_form = dictionary! {
+            "Fields" => vec![name_id.into(), group_id.into(), Object::Null],
+            "DA" => Object::string_literal("/Helv 12 Tf 0 g"),
+        };
+        let catalog_id = doc.trailer.get(b"Root").and_then(Object::as_reference).unwrap();
+        doc.get_object_mut(catalog_id)
+            .and_then(Object::as_dict_mut)
+            .unwrap()
+            .set("AcroForm", acro_form);
+
+        let fields = doc.form_fields();
+        assert_eq!(fields.len(), 3);
+        let name = &fields[0];
+        assert_eq!((name.name.as_str(), name.value.as_deref()), ("name", Some("Jo")));
+        assert_eq!(name.field_type, FieldType::Text);
+        assert_eq!(name.default_appearance.as_deref(), Some(&b"/Helv 12 Tf 0 g"[..]));
+        assert_eq!(
+            name.widgets,
+            vec![Widget {
+                id: name_id,
+                page: Some(page_id),
+                rect: Some(Rect::new(10.0, 10.0, 110.0, 30.0)),
+            }]
+        );
+
+        let agree = &fields[1];
+        assert_eq!(
+            (agree.name.as_str(), agree.value.as_deref()),
+            ("group.agree", Some("Yes"))
+        );
+        assert_eq!(agree.field_type, FieldType::Checkbox);
+        assert!(agree.is_read_only() && !agree.is_required());
+        assert_eq!(agree.default_appearance.as_deref(), Some(&b"/Helv 0 Tf 0 g"[..]));
+        assert_eq!(agree.widgets[0].id, agree_widget_id);
+        assert_eq!(agree.widgets[0].page, Some(page_id));
+
+        let color = &fields[2];
+        assert_eq!(color.name, "group.color");
+        assert_eq!(color.field_type, FieldType::Combo);
+        assert!(color.is_required() && !color.is_read_only());
+        assert_eq!(
+            color.options,
+            vec![
+                ("r".to_string(), "Red".to_string()),
+                ("Green".to_string(), "Green".to_string())
+            ]
+        );
+        assert!(color.widgets.is_empty());
+    }
+}
@@ -0,0 +1,722 @@
+//! PDF functions, used by color spaces and shadings to map input values to color components.
+
+use crate::{Dictionary, Document, Error, Object, Result, Stream};
+
+/// Maximum nesting of stitching functions and PostScript procedures.
+const NESTING_LIMIT: usize = 16;
+
+/// Maximum number of inputs of sampled functions, each of which doubles the samples interpolated between.
+const SAMPLED_INPUT_LIMIT: usize = 16;
+
+/// Maximum number of values on the stack of PostScript functions.
+const STACK_LIMIT: usize = 1000;
+
+/// A function object (PDF 32000-1:2008, section 7.10).
+#[derive(Debug, Clone)]
+pub struct Function {
+    domain: Vec<f64>,
+    range: Option<Vec<f64>>,
+    kind: FunctionKind,
+}
+
+#[derive(Debug, Clone)]
+enum FunctionKind {
+    Sampled {
+        size: Vec<usize>,
+        bits_per_sample: u32,
+        encode: Vec<f64>,
+        decode: Vec<f64>,
+        samples: Vec<u32>,
+    },
+    Exponential {
+        c0: Vec<f64>,
+        c1: Vec<f64>,
+        exponent: f64,
+    },
+    Stitching {
+        functions: Vec<Function>,
+        bounds: Vec<f64>,
+        encode: Vec<f64>,
+    },
+    PostScript(Vec<PsOperation>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum PsOperation {
+    Number(f64),
+    Operator(String),
+    If(Vec<PsOperation>),
+    IfElse(Vec<PsOperation>, Vec<PsOperation>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PsValue {
+    Number(f64),
+    Bool(bool),
+}
+
+impl Function {
+    /// Read a function from a function dictionary or stream, following references.
+    pub fn from_object(doc: &Document, object: &Object) -> Result<Function> {
+        Function::parse(doc, object, 0)
+    }
+
+    fn parse(doc: &Document, object: &Object, depth: usize) -> Result<Function> {
+        if depth > NESTING_LIMIT {
+            return Err(Error::Syntax("Functions are nested too deeply".to_string()));
+        }
+        let (_, object) = doc.dereference(object)?;
+        let (dict, stream) = match object {
+            Object::Dictionary(dict) => (dict, None),
+            Object::Stream(stream) => (&stream.dict, Some(stream)),
+            other => return Err(Error::type_mismatch("Dictionary or Stream", other.enum_variant())),
+        };
+        let numbers = |key: &[u8]| -> Result<Option<Vec<f64>>> {
+            match dict.get_deref(key, doc) {
+                Ok(value) => value
+                    .as_array()?
+                    .iter()
+                    .map(|value| doc.dereference(value).and_then(|(_, value)| value.as_float()))
+                    .collect::<Result<Vec<_>>>()
+                    .map(Some)
+                    .map_err(|err| err.at_key(key)),
+                Err(_) => Ok(None),
+            }
+        };
+        let domain = numbers(b"Domain")?.ok_or_else(|| Error::dict_key(b"Domain"))?;
+        let range = numbers(b"Range")?;
+        if domain.is_empty() || domain.len() % 2 != 0 || range.as_ref().is_some_and(|r| r.len() % 2 != 0) {
+            return Err(Error::Syntax("Invalid function domain or range".to_string()));
+        }
+
+        let kind = match dict.get(b"FunctionType").and_then(Object::as_i64)? {
+            0 => {
+                let stream = stream.ok_or_else(|| Error::type_mismatch("Stream", "Dictionary"))?;
+                let range = range.as_ref().ok_or_else(|| Error::dict_key(b"Range"))?;
+                let size = numbers(b"Size")?
+                    .ok_or_else(|| Error::dict_key(b"Size"))?
+                    .into_iter()
+                    .map(|size| size as usize)
+                    .collect::<Vec<_>>();
+                if size.len() * 2 != domain.len() || size.len() > SAMPLED_INPUT_LIMIT || size.contains(&0) {
+                    return Err(Error::Syntax("Invalid sampled function size".to_string()));
+                }
+                let bits_per_sample = dict.get(b"BitsPerSample").and_then(Object::as_i64)? as u32;
+                if !matches!(bits_per_sample, 1 | 2 | 4 | 8 | 12 | 16 | 24 | 32) {
+                    return Err(Error::Syntax(format!("Invalid BitsPerSample {}", bits_per_sample)));
+                }
+                let encode = numbers(b"Encode")?
+                    .unwrap_or_else(|| size.iter().flat_map(|&size| vec![0.0, (size - 1) as f64]).collect());
+                let decode = numbers(b"Decode")?.unwrap_or_else(|| range.clone());
+                if encode.len() != domain.len() || decode.len() != range.len() {
+                    return Err(Error::Syntax("Invalid sampled function encode or decode".to_string()));
+                }
+                let count = size
+                    .iter()
+                    .try_fold(range.len() / 2, |count, &size| count.checked_mul(size))
+                    .ok_or_else(|| Error::Syntax("Sampled function size is too large".to_string()))?;
+                let samples = read_samples(&stream_data(stream)?, bits_per_sample, count)?;
+                FunctionKind::Sampled {
+                    size,
+                    bits_per_sample,
+                    encode,
+                    decode,
+                    samples,
+                }
+            }
+            2 => {
+                let c0 = numbers(b"C0")?.unwrap_or_else(|| vec![0.0]);
+                let c1 = numbers(b"C1")?.unwrap_or_else(|| vec![1.0]);
+                if c0.len() != c1.len() {
+                    return Err(Error::Syntax("C0 and C1 differ in length".to_string()));
+                }
+                let exponent = dict.get(b"N").and_then(Object::as_float)?;
+                FunctionKind::Exponential { c0, c1, exponent }
+            }
+            3 => {
+                let functions = dict
+                    .get_deref(b"Functions", doc)?
+                    .as_array()?
+                    .iter()
+                    .map(|function| Function::parse(doc, function, depth + 1))
+                    .collect::<Result<Vec<_>>>()?;
+                let bounds = numbers(b"Bounds")?.ok_or_else(|| Error::dict_key(b"Bounds"))?;
+                let encode = numbers(b"Encode")?.ok_or_else(|| Error::dict_key(b"Encode"))?;
+                if functions.is_empty() || bounds.len() + 1 != functions.len() || encode.len() != functions.len() * 2 {
+                    return Err(Error::Syntax("Invalid stitching function".to_string()));
+                }
+                FunctionKind::Stitching {
+                    functions,
+                    bounds,
+                    encode,
+                }
+            }
+            4 => {
+                let stream = stream.ok_or_else(|| Error::type_mismatch("Stream", "Dictionary"))?;
+                if range.is_none() {
+                    return Err(Error::dict_key(b"Range"));
+                }
+                FunctionKind::PostScript(parse_postscript(&stream_data(stream)?)?)
+            }
+            function_type => return Err(Error::Syntax(format!("Invalid FunctionType {}", function_type))),
+        };
+        Ok(Function { domain, range, kind })
+    }
+
+    /// An exponential interpolation function of one input from 0 to 1, giving `c0` at 0 and `c1` at 1, linear for
+    /// an exponent of 1.
+    pub fn exponential(c0: Vec<f64>, c1: Vec<f64>, exponent: f64) -> Function {
+        Function {
+            domain: vec![0.0, 1.0],
+            range: None,
+            kind: FunctionKind::Exponential { c0, c1, exponent },
+        }
+    }
+
+    /// The function dictionary, to add to a document. Sampled and PostScript functions, which are streams, are not
+    /// written, nor are stitching functions of them.
+    pub fn to_object(&self) -> Result<Object> {
+        let numbers = |values: &[f64]| Object::Array(values.iter().map(|&value| value.into()).collect());
+        let mut dict = Dictionary::new();
+        match &self.kind {
+            FunctionKind::Exponential { c0, c1, exponent } => {
+                dict.set("FunctionType", 2);
+                dict.set("C0", numbers(c0));
+                dict.set("C1", numbers(c1));
+                dict.set("N", *exponent);
+            }
+            FunctionKind::Stitching {
+                functions,
+                bounds,
+                encode,
+            } => {
+                dict.set("FunctionType", 3);
+                let functions = functions.iter().map(Function::to_object).collect::<Result<Vec<_>>>()?;
+                dict.set("Functions", functions);
+                dict.set("Bounds", numbers(bounds));
+                dict.set("Encode", numbers(encode));
+            }
+            FunctionKind::Sampled { .. } | FunctionKind::PostScript(_) => {
+                return Err(Error::Unimplemented("writing sampled and PostScript functions"))
+            }
+        }
+        dict.set("Domain", numbers(&self.domain));
+        if let Some(range) = &self.range {
+            dict.set("Range", numbers(range));
+        }
+        Ok(dict.into())
+    }
+
+    /// Number of input values.
+    pub fn input_count(&self) -> usize {
+        self.domain.len() / 2
+    }
+
+    /// Number of output values, if it is known without evaluating the function.
+    pub fn output_count(&self) -> Option<usize> {
+        match &self.range {
+            Some(range) => Some(range.len() / 2),
+            None => match &self.kind {
+                FunctionKind::Exponential { c0, .. } => Some(c0.len()),
+                FunctionKind::Stitching { functions, .. } => functions[0].output_count(),
+                _ => None,
+            },
+        }
+    }
+
+    /// Evaluate the function; inputs are clipped to the domain and outputs to the range.
+    pub fn evaluate(&self, input: &[f64]) -> Result<Vec<f64>> {
+        if input.len() != self.input_count() {
+            return Err(Error::Syntax(format!(
+                "Function takes {} inputs, got {}",
+                self.input_count(),
+                input.len()
+            )));
+        }
+        let input = input
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| clip(x, self.domain[i * 2], self.domain[i * 2 + 1]))
+            .collect::<Vec<_>>();
+
+        let mut output = match &self.kind {
+            FunctionKind::Sampled {
+                size,
+                bits_per_sample,
+                encode,
+                decode,
+                samples,
+            } => {
+                let outputs = decode.len() / 2;
+                // Position of the input in the sample table along each dimension.
+                let mut positions = Vec::with_capacity(size.len());
+                for (i, &x) in input.iter().enumerate() {
+                    let e = interpolate(
+                        x,
+                        self.domain[i * 2],
+                        self.domain[i * 2 + 1],
+                        encode[i * 2],
+                        encode[i * 2 + 1],
+                    );
+                    positions.push(clip(e, 0.0, (size[i] - 1) as f64));
+                }
+                // Multilinear interpolation between the surrounding samples.
+                let mut values = vec![0.0; outputs];
+                for corner in 0..1_usize << size.len() {
+                    let mut weight = 1.0;
+                    let mut offset = 0;
+                    let mut stride = 1;
+                    for (i, &position) in positions.iter().enumerate() {
+                        let low = position.floor();
+                        let fraction = position - low;
+                        let index = if corner & (1 << i) != 0 {
+                            weight *= fraction;
+                            (low as usize + 1).min(size[i] - 1)
+                        } else {
+                            weight *= 1.0 - fraction;
+                            low as usize
+                        };
+                        offset += index * stride;
+                        stride *= size[i];
+                    }
+                    if weight == 0.0 {
+                        continue;
+                    }
+                    for (j, value) in values.iter_mut().enumerate() {
+                        *value += weight * f64::from(samples[offset * outputs + j]);
+                    }
+                }
+                let max = ((1_u64 << bits_per_sample) - 1) as f64;
+                values
+                    .iter()
+                    .enumerate()
+                    .map(|(j, &value)| interpolate(value, 0.0, max, decode[j * 2], decode[j * 2 + 1]))
+                    .collect()
+            }
+            FunctionKind::Exponential { c0, c1, exponent } => {
+                let x = input[0].powf(*exponent);
+                c0.iter().zip(c1).map(|(c0, c1)| c0 + x * (c1 - c0)).collect()
+            }
+            FunctionKind::Stitching {
+                functions,
+                bounds,
+                encode,
+            } => {
+                let x = input[0];
+                let k = bounds.iter().take_while(|&&bound| x >= bound).count();
+                let low = if k == 0 { self.domain[0] } else { bounds[k - 1] };
+                let high = if k == bounds.len() { self.domain[1] } else { bounds[k] };
+                functions[k].evaluate(&[interpolate(x, low, high, encode[k * 2], encode[k * 2 + 1])])?
+            }
+            FunctionKind::PostScript(program) => {
+                let mut stack = input.iter().map(|&x| PsValue::Number(x)).collect();
+                execute_postscript(program, &mut stack)?;
+                let outputs = self.range.as_ref().map_or(0, |range| range.len() / 2);
+                if stack.len() < outputs {
+                    return Err(Error::Syntax("PostScript function returned too few values".to_string()));
+                }
+                stack[stack.len() - outputs..]
+                    .iter()
+                    .map(|value| match value {
+                        PsValue::Number(x) => Ok(*x),
+                        PsValue::Bool(_) => Err(Error::Syntax("PostScript function returned a boolean".to_string())),
+                    })
+                    .collect::<Result<Vec<_>>>()?
+            }
+        };
+
+        if let Some(range) = &self.range {
+            for (j, value) in output.iter_mut().enumerate().take(range.len() / 2) {
+                *value = clip(*value, range[j * 2], range[j * 2 + 1]);
+            }
+        }
+        Ok(output)
+    }
+}
+
+fn clip(x: f64, min: f64, max: f64) -> f64 {
+    x.max(min).min(max)
+}
+
+fn interpolate(x: f64, x_min: f64, x_max: f64, y_min: f64, y_max: f64) -> f64 {
+    if x_max == x_min {
+        return y_min;
+    }
+    y_min + (x - x_min) * (y_max - y_min) / (x_max - x_min)
+}
+
+fn stream_data(stream: &Stream) -> Result<Vec<u8>> {
+    if stream.filters().is_ok() {
+        stream.decompressed_content()
+    } else {
+        Ok(stream.content.to_vec())
+    }
+}
+
+/// Read `count` big-endian samples of `bits` bits each.
+fn read_samples(data: &[u8], bits: u32, count: usize) -> Result<Vec<u32>> {
+    if count.checked_mul(bits as usize).is_none_or(|needed| data.len().saturating_mul(8) < needed) {
+        return Err(Error::Syntax("Sampled function data is too short".to_string()));
+    }
+    let mut samples = Vec::with_capacity(count);
+    let mut bit = 0;
+    for _ in 0..count {
+        let mut value = 0_u32;
+        let mut remaining = bits as usize;
+        while remaining > 0 {
+            let byte = data[bit / 8];
+            let offset = bit % 8;
+            let take = remaining.min(8 - offset);
+            let part = (byte >> (8 - offset - take)) & ((1_u16 << take) - 1) as u8;
+            value = (value << take) | u32::from(part);
+            remaining -= take;
+            bit += take;
+        }
+        samples.push(value);
+    }
+    Ok(samples)
+}
+
+fn parse_postscript(data: &[u8]) -> Result<Vec<PsOperation>> {
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let byte = data[pos];
+        if byte.is_ascii_whitespace() {
+            pos += 1;
+        } else if byte == b'%' {
+            while pos < data.len() && data[pos] != b'\n' && data[pos] != b'\r' {
+                pos += 1;
+            }
+        } else if byte == b'{' || byte == b'}' {
+            tokens.push(&data[pos..pos + 1]);
+            pos += 1;
+        } else {
+            let start = pos;
+            while pos < data.len() && !data[pos].is_ascii_whitespace() && !b"{}%".contains(&data[pos]) {
+                pos += 1;
+            }
+            tokens.push(&data[start..pos]);
+        }
+    }
+
+    let mut tokens = tokens.into_iter();
+    if tokens.next() != Some(b"{".as_ref()) {
+        return Err(Error::Syntax("PostScript function must start with {".to_string()));
+    }
+    let program = parse_procedure(&mut tokens, 0)?;
+    if tokens.next().is_some() {
+        return Err(Error::Syntax("Data after PostScript function".to_string()));
+    }
+    Ok(program)
+}
+
+fn parse_procedure<'a, I: Iterator<Item = &'a [u8]>>(tokens: &mut I, depth: usize) -> Result<Vec<PsOperation>> {
+    if depth > NESTING_LIMIT {
+        return Err(Error::Syntax("PostScript procedures are nested too deeply".to_string()));
+    }
+    let mut operations = Vec::new();
+    let mut procedures = Vec::new();
+    loop {
+        let token = tokens
+            .next()
+            .ok_or_else(|| Error::Syntax("Unterminated PostScript procedure".to_string()))?;
+        match token {
+            b"{" => {
+                procedures.push(parse_procedure(tokens, depth + 1)?);
+                continue;
+            }
+            b"}" => break,
+            b"if" if procedures.len() == 1 => operations.push(PsOperation::If(procedures.remove(0))),
+            b"ifelse" if procedures.len() == 2 => {
+                let else_procedure = procedures.pop().unwrap();
+                operations.push(PsOperation::IfElse(procedures.pop().unwrap(), else_procedure))
+            }
+            _ => {
+                let text = String::from_utf8_lossy(token);
+                match text.parse::<f64>() {
+                    Ok(number) => operations.push(PsOperation::Number(number)),
+                    Err(_) if text.bytes().all(|b| b.is_ascii_alphabetic()) => {
+                        operations.push(PsOperation::Operator(text.into_owned()))
+                    }
+                    Err(_) => return Err(Error::Syntax(format!("Invalid PostScript token {}", text))),
+                }
+            }
+        }
+        if !procedures.is_empty() {
+            return Err(Error::Syntax("PostScript procedure without if or ifelse".to_string()));
+        }
+    }
+    if !procedures.is_empty() {
+        return Err(Error::Syntax("PostScript procedure without if or ifelse".to_string()));
+    }
+    Ok(operations)
+}
+
+fn execute_postscript(program: &[PsOperation], stack: &mut Vec<PsValue>) -> Result<()> {
+    fn underflow() -> Error {
+        Error::Syntax("PostScript stack underflow".to_string())
+    }
+    fn pop(stack: &mut Vec<PsValue>) -> Result<PsValue> {
+        stack.pop().ok_or_else(underflow)
+    }
+    fn number(stack: &mut Vec<PsValue>) -> Result<f64> {
+        match pop(stack)? {
+            PsValue::Number(x) => Ok(x),
+            PsValue::Bool(_) => Err(Error::Syntax("PostScript operator expects a number".to_string())),
+        }
+    }
+    fn integer(stack: &mut Vec<PsValue>) -> Result<i64> {
+        number(stack).map(|x| x as i64)
+    }
+
+    for operation in program {
+        let operator = match operation {
+            PsOperation::Number(x) => {
+                stack.push(PsValue::Number(*x));
+                continue;
+            }
+            PsOperation::If(procedure) => {
+                if pop(stack)? == PsValue::Bool(true) {
+                    execute_postscript(procedure, stack)?;
+                }
+                continue;
+            }
+            PsOperation::IfElse(if_procedure, else_procedure) => {
+                let procedure = if pop(stack)? == PsValue::Bool(true) {
+                    if_procedure
+                } else {
+                    else_procedure
+                };
+                execute_postscript(procedure, stack)?;
+                continue;
+            }
+            PsOperation::Operator(operator) => operator.as_str(),
+        };
+        let value = match operator {
+            "abs" => PsValue::Number(number(stack)?.abs()),
+            "neg" => PsValue::Number(-number(stack)?),
+            "ceiling" => PsValue::Number(number(stack)?.ceil()),
+            "floor" => PsValue::Number(number(stack)?.floor()),
+            "round" => PsValue::Number((number(stack)? + 0.5).floor()),
+            "truncate" | "cvi" => PsValue::Number(number(stack)?.trunc()),
+            "cvr" => PsValue::Number(number(stack)?),
+            "sqrt" => PsValue::Number(number(stack)?.sqrt()),
+            "sin" => PsValue::Number(number(stack)?.to_radians().sin()),
+            "cos" => PsValue::Number(number(stack)?.to_radians().cos()),
+            "ln" => PsValue::Number(number(stack)?.ln()),
+            "log" => PsValue::Number(number(stack)?.log10()),
+            "add" | "sub" | "mul" | "div" | "exp" | "atan" => {
+                let b = number(stack)?;
+                let a = number(stack)?;
+                PsValue::Number(match operator {
+                    "add" => a + b,
+                    "sub" => a - b,
+                    "mul" => a * b,
+                    "div" if b == 0.0 => return Err(Error::Syntax("PostScript division by zero".to_string())),
+                    "div" => a / b,
+                    "exp" => a.powf(b),
+                    _ => {
+                        let angle = a.atan2(b).to_degrees();
+                        if angle < 0.0 {
+                            angle + 360.0
+                        } else {
+                            angle
+                        }
+                    }
+                })
+            }
+            "idiv" | "mod" | "bitshift" => {
+                let b = integer(stack)?;
+                let a = integer(stack)?;
+                PsValue::Number(match operator {
+                    "bitshift" if b >= 0 => a << b.min(63),
+                    "bitshift" => a >> (-b).min(63),
+                    _ if b == 0 => return Err(Error::Syntax("PostScript division by zero".to_string())),
+                    "idiv" => a / b,
+                    _ => a % b,
+                } as f64)
+            }
+            "eq" | "ne" => {
+                let b = pop(stack)?;
+                let a = pop(stack)?;
+                PsValue::Bool((a == b) == (operator == "eq"))
+            }
+            "gt" | "ge" | "lt" | "le" => {
+                let b = number(stack)?;
+                let a = number(stack)?;
+                PsValue::Bool(match operator {
+                    "gt" => a > b,
+                    "ge" => a >= b,
+                    "lt" => a < b,
+                    _ => a <= b,
+                })
+            }
+            "and" | "or" | "xor" => {
+                let b = pop(stack)?;
+                let a = pop(stack)?;
+                match (a, b) {
+                    (PsValue::Bool(a), PsValue::Bool(b)) => PsValue::Bool(match operator {
+                        "and" => a & b,
+                        "or" => a | b,
+                        _ => a ^ b,
+                    }),
+                    (PsValue::Number(a), PsValue::Number(b)) => {
+                        let (a, b) = (a as i64, b as i64);
+                        PsValue::Number(match operator {
+                            "and" => a & b,
+                            "or" => a | b,
+                            _ => a ^ b,
+                        } as f64)
+                    }
+                    _ => return Err(Error::Syntax("PostScript operand types differ".to_string())),
+                }
+            }
+            "not" => match pop(stack)? {
+                PsValue::Bool(a) => PsValue::Bool(!a),
+                PsValue::Number(a) => PsValue::Number(!(a as i64) as f64),
+            },
+            "true" => PsValue::Bool(true),
+            "false" => PsValue::Bool(false),
+            "pop" => {
+                pop(stack)?;
+                continue;
+            }
+            "dup" => *stack.last().ok_or_else(underflow)?,
+            "exch" => {
+                let b = pop(stack)?;
+                let a = pop(stack)?;
+                stack.push(b);
+                a
+            }
+            "copy" => {
+                let n = integer(stack)?;
+                if n < 0 || n as usize > stack.len() {
+                    return Err(underflow());
+                }
+                if stack.len() + n as usize > STACK_LIMIT {
+                    return Err(Error::Syntax("PostScript stack overflow".to_string()));
+                }
+                let start = stack.len() - n as usize;
+                stack.extend_from_within(start..);
+                continue;
+            }
+            "index" => {
+                let n = integer(stack)?;
+                if n < 0 || n as usize >= stack.len() {
+                    return Err(underflow());
+                }
+                stack[stack.len() - 1 - n as usize]
+            }
+            "roll" => {
+                let j = integer(stack)?;
+                let n = integer(stack)?;
+                if n < 0 || n as usize > stack.len() {
+                    return Err(underflow());
+                }
+                if n > 0 {
+                    let start = stack.len() - n as usize;
+                    let shift = j.rem_euclid(n) as usize;
+                    stack[start..].rotate_right(shift);
+                }
+                continue;
+            }
+            _ => return Err(Error::Syntax(format!("Unknown PostScript operator {}", operator))),
+        };
+        stack.push(value);
+        if stack.len() > STACK_LIMIT {
+            return Err(Error::Syntax("PostScript stack overflow".to_string()));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate(object: Object, input: &[f64]) -> Vec<f64> {
+        let doc = Document::new();
+        Function::from_object(&doc, &object).unwrap().evaluate(input).unwrap()
+    }
+
+    #[test]
+    fn sampled_and_exponential_functions() {
+        let exponential = dictionary! {
+            "FunctionType" => 2,
+            "Domain" => vec![0.into(), 1.into()],
+            "C0" => vec![1.into(), 0.into()],
+            "C1" => vec![0.into(), 1.into()],
+            "N" => 2,
+        };
+        assert_eq!(evaluate(exponential.into(), &[0.5]), vec![0.75, 0.25]);
+
+        // Two samples per input going from 0 to 255, so the function is linear.
+        let sampled = Stream::new(
+            dictionary! {
+                "FunctionType" => 0,
+                "Domain" => vec![0.into(), 1.into()],
+                "Range" => vec![0.into(), 1.into()],
+                "Size" => vec![2.into()],
+                "BitsPerSample" => 8,
+            },
+            vec![0, 255],
+        );
+        assert_eq!(evaluate(sampled.into(), &[0.25]), vec![0.25]);
+    }
+
+    #[test]
+    fn postscript_function() {
+        let function = Stream::new(
+            dictionary! {
+                "FunctionType" => 4,
+                "Domain" => vec![0.into(), 1.into()],
+                "Range" => vec![0.into(), 1.into(), 0.into(), 1.into()],
+            },
+            b"{ dup 0.5 gt { 1 exch sub } { 2 mul } ifelse 0.25 }".to_vec(),
+        );
+        let function: Object = function.into();
+        assert_eq!(evaluate(function.clone(), &[0.25]), vec![0.5, 0.25]);
+        assert_eq!(evaluate(function, &[0.75]), vec![0.25, 0.25]);
+    }
+
+    #[test]
+    fn reject_hostile_functions() {
+        let doc = Document::new();
+        let sampled = |size: Vec<Object>| {
+            let domain = size.iter().flat_map(|_| vec![0.into(), 1.into()]).collect::<Vec<Object>>();
+            let stream = Stream::new(
+                dictionary! {
+                    "FunctionType" => 0,
+                    "Domain" => domain,
+                    "Range" => vec![0.into(), 1.into()],
+                    "Size" => size,
+                    "BitsPerSample" => 8,
+                },
+                vec![0; 16],
+            );
+            Function::from_object(&doc, &stream.into())
+        };
+        assert!(sampled(vec![1e30.into(), 1e30.into()]).is_err());
+        assert!(sampled(vec![Object::Integer(usize::MAX as i64 / 2); 2]).is_err());
+        assert!(sampled(vec![1.into(); 64]).is_err());
+        assert!(sampled(vec![4.into(), 4.into()]).is_ok());
+
+        // Each copy doubles the stack.
+        let mut program = String::from("{ 1");
+        for n in 0..12 {
+            program.push_str(&format!(" {} copy", 1 << n));
+        }
+        program.push_str(" }");
+        let function = Stream::new(
+            dictionary! {
+                "FunctionType" => 4,
+                "Domain" => vec![0.into(), 1.into()],
+                "Range" => vec![0.into(), 1.into()],
+            },
+            program.into_bytes(),
+        );
+        let function = Function::from_object(&doc, &function.into()).unwrap();
+        assert!(function.evaluate(&[0.5]).is_err());
+    }
+}
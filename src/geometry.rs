@@ -0,0 +1,217 @@
+use crate::{Error, Object, Result};
+
+/// Affine transformation matrix `[a b c d e f]` in the form used by the `cm` operator.
+pub type Matrix = [f64; 6];
+
+/// The identity transformation.
+pub const IDENTITY_MATRIX: Matrix = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+/// Concatenate two matrices so that the result applies `first` and then `second`.
+///
+/// `cm` operands are concatenated as `multiply(&operands, &ctm)`.
+pub fn multiply(first: &Matrix, second: &Matrix) -> Matrix {
+    [
+        first[0] * second[0] + first[1] * second[2],
+        first[0] * second[1] + first[1] * second[3],
+        first[2] * second[0] + first[3] * second[2],
+        first[2] * second[1] + first[3] * second[3],
+        first[4] * second[0] + first[5] * second[2] + second[4],
+        first[4] * second[1] + first[5] * second[3] + second[5],
+    ]
+}
+
+/// Apply the transformation to a point.
+pub fn transform_point(matrix: &Matrix, x: f64, y: f64) -> (f64, f64) {
+    (
+        matrix[0] * x + matrix[2] * y + matrix[4],
+        matrix[1] * x + matrix[3] * y + matrix[5],
+    )
+}
+
+/// The inverse transformation, if the matrix is invertible.
+pub fn invert(matrix: &Matrix) -> Option<Matrix> {
+    let [a, b, c, d, e, f] = *matrix;
+    let determinant = a * d - b * c;
+    if determinant == 0.0 || !determinant.is_finite() {
+        return None;
+    }
+    Some([
+        d / determinant,
+        -b / determinant,
+        -c / determinant,
+        a / determinant,
+        (c * f - d * e) / determinant,
+        (b * e - a * f) / determinant,
+    ])
+}
+
+/// Smallest rectangle containing the transformed rectangle.
+pub fn transform_rect(matrix: &Matrix, rect: &Rect) -> Rect {
+    let (x, y) = transform_point(matrix, rect.llx, rect.lly);
+    [(rect.urx, rect.lly), (rect.llx, rect.ury), (rect.urx, rect.ury)]
+        .iter()
+        .fold(Rect::new(x, y, x, y), |bounds, &(x, y)| {
+            let (x, y) = transform_point(matrix, x, y);
+            bounds.union(&Rect::new(x, y, x, y))
+        })
+}
+
+/// Rectangle given by its lower-left and upper-right corners, as used for `/Rect` and `/MediaBox` entries.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub llx: f64,
+    pub lly: f64,
+    pub urx: f64,
+    pub ury: f64,
+}
+
+impl Rect {
+    /// Create a rectangle from two opposite corners in any order.
+    pub fn new(x1: f64, y1: f64, x2: f64, y2: f64) -> Rect {
+        Rect {
+            llx: x1.min(x2),
+            lly: y1.min(y2),
+            urx: x1.max(x2),
+            ury: y1.max(y2),
+        }
+    }
+
+    /// Read a rectangle from an array of four numbers.
+    pub fn from_object(object: &Object) -> Result<Rect> {
+        match object.as_array()?.as_slice() {
+            [x1, y1, x2, y2] => Ok(Rect::new(
+                x1.as_float()?,
+                y1.as_float()?,
+                x2.as_float()?,
+                y2.as_float()?,
+            )),
+            _ => Err(Error::type_mismatch("Array of 4 numbers", "Array")),
+        }
+    }
+
+    pub fn width(&self) -> f64 {
+        self.urx - self.llx
+    }
+
+    pub fn height(&self) -> f64 {
+        self.ury - self.lly
+    }
+
+    /// Smallest rectangle containing both rectangles.
+    pub fn union(&self, other: &Rect) -> Rect {
+        Rect {
+            llx: self.llx.min(other.llx),
+            lly: self.lly.min(other.lly),
+            urx: self.urx.max(other.urx),
+            ury: self.ury.max(other.ury),
+        }
+    }
+
+    /// Whether the rectangles overlap; rectangles that only touch do not.
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.llx < other.urx && other.llx < self.urx && self.lly < other.ury && other.lly < self.ury
+    }
+
+    /// Whether the other rectangle lies within this one.
+    pub fn contains(&self, other: &Rect) -> bool {
+        self.llx <= other.llx && other.urx <= self.urx && self.lly <= other.lly && other.ury <= self.ury
+    }
+}
+
+impl From<Rect> for Object {
+    fn from(rect: Rect) -> Self {
+        Object::Array(vec![rect.llx.into(), rect.lly.into(), rect.urx.into(), rect.ury.into()])
+    }
+}
+
+/// Quadrilateral given by its four corners, as used for `/QuadPoints` entries.
+///
+/// For text the upper edge runs along the top of the glyphs, so a rotated quad stays attached to its text.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quad {
+    pub upper_left: (f64, f64),
+    pub upper_right: (f64, f64),
+    pub lower_left: (f64, f64),
+    pub lower_right: (f64, f64),
+}
+
+impl Quad {
+    /// Read a quad from eight numbers in `/QuadPoints` order.
+    pub fn from_points(points: &[f64]) -> Option<Quad> {
+        match *points {
+            [x1, y1, x2, y2, x3, y3, x4, y4] => Some(Quad {
+                upper_left: (x1, y1),
+                upper_right: (x2, y2),
+                lower_left: (x3, y3),
+                lower_right: (x4, y4),
+            }),
+            _ => None,
+        }
+    }
+
+    /// The corners in `/QuadPoints` order: upper left, upper right, lower left, lower right.
+    ///
+    /// This is the order viewers expect, even though the specification describes the points counterclockwise.
+    pub fn to_points(&self) -> [f64; 8] {
+        [
+            self.upper_left.0,
+            self.upper_left.1,
+            self.upper_right.0,
+            self.upper_right.1,
+            self.lower_left.0,
+            self.lower_left.1,
+            self.lower_right.0,
+            self.lower_right.1,
+        ]
+    }
+
+    /// Smallest rectangle containing the quad.
+    pub fn bounding_rect(&self) -> Rect {
+        let (x, y) = self.upper_left;
+        [self.upper_right, self.lower_left, self.lower_right]
+            .iter()
+            .fold(Rect::new(x, y, x, y), |rect, &(x, y)| {
+                rect.union(&Rect::new(x, y, x, y))
+            })
+    }
+}
+
+impl From<Rect> for Quad {
+    fn from(rect: Rect) -> Self {
+        Quad {
+            upper_left: (rect.llx, rect.ury),
+            upper_right: (rect.urx, rect.ury),
+            lower_left: (rect.llx, rect.lly),
+            lower_right: (rect.urx, rect.lly),
+        }
+    }
+}
+
+#[test]
+fn concatenate_matrices() {
+    let scale = [2.0, 0.0, 0.0, 3.0, 0.0, 0.0];
+    let translate = [1.0, 0.0, 0.0, 1.0, 10.0, 20.0];
+    let matrix = multiply(&scale, &translate);
+    assert_eq!(transform_point(&matrix, 1.0, 1.0), (12.0, 23.0));
+    assert_eq!(multiply(&matrix, &IDENTITY_MATRIX), matrix);
+}
+
+#[test]
+fn rect_from_array() {
+    let rect = Rect::from_object(&vec![100.into(), 50.into(), 0.into(), 80.5.into()].into()).unwrap();
+    assert_eq!(rect, Rect::new(0.0, 50.0, 100.0, 80.5));
+    assert_eq!((rect.width(), rect.height()), (100.0, 30.5));
+}
+
+#[test]
+fn invert_and_intersect() {
+    let matrix = [0.0, 2.0, -2.0, 0.0, 10.0, 20.0];
+    let inverse = invert(&matrix).unwrap();
+    assert_eq!(transform_point(&inverse, 10.0, 22.0), (1.0, 0.0));
+    assert!(invert(&[1.0, 2.0, 2.0, 4.0, 0.0, 0.0]).is_none());
+    let rect = transform_rect(&matrix, &Rect::new(0.0, 0.0, 1.0, 1.0));
+    assert_eq!(rect, Rect::new(8.0, 20.0, 10.0, 22.0));
+    assert!(rect.intersects(&Rect::new(9.0, 21.0, 30.0, 30.0)));
+    assert!(!rect.intersects(&Rect::new(10.0, 20.0, 30.0, 30.0)));
+    assert!(Rect::new(0.0, 0.0, 10.0, 30.0).contains(&rect));
+}
@@ -0,0 +1,177 @@
+use std::collections::BTreeSet;
+use std::ops::Range;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::{Document, Error, Object, ObjectId, Result};
+
+/// Hands out the object ids of a document to threads building objects for it apart from the document, from
+/// [`Document::id_allocator`]. Clones share the ids, so each thread can have its own.
+///
+/// Ids are handed out in blocks reserved with [`reserve`](IdAllocator::reserve), of consecutive ids that no other
+/// block nor the document itself use. Blocks reserved in the same order get the same ids, so reserving them on
+/// one thread before handing them to the workers makes the ids of a document independent of how the threads are
+/// scheduled.
+#[derive(Debug, Clone)]
+pub struct IdAllocator {
+    state: Arc<AllocatorState>,
+}
+
+#[derive(Debug)]
+struct AllocatorState {
+    /// The first id not handed out yet.
+    next: AtomicU32,
+    /// The blocks handed out, which prepared objects have to be numbered from.
+    blocks: Mutex<Vec<Range<u32>>>,
+}
+
+/// Consecutive object ids reserved for a thread by [`IdAllocator::reserve`].
+#[derive(Debug, Clone)]
+pub struct IdBlock {
+    ids: Range<u32>,
+}
+
+impl IdAllocator {
+    fn new(first: u32) -> IdAllocator {
+        IdAllocator {
+            state: Arc::new(AllocatorState {
+                next: AtomicU32::new(first),
+                blocks: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// Reserve a block of `count` ids.
+    pub fn reserve(&self, count: u32) -> IdBlock {
+        let start = self.state.next.fetch_add(count, Ordering::Relaxed);
+        let ids = start..start + count;
+        self.state.blocks.lock().unwrap().push(ids.clone());
+        IdBlock { ids }
+    }
+
+    /// An id for the document itself, which prepared objects cannot use.
+    fn next_id(&self) -> u32 {
+        self.state.next.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn reserved(&self, id: u32) -> bool {
+        self.state
+            .blocks
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|block| block.contains(&id))
+    }
+
+    fn first_free(&self) -> u32 {
+        self.state.next.load(Ordering::Relaxed)
+    }
+}
+
+impl IdBlock {
+    /// The next id of the block, if it has any left.
+    pub fn next_id(&mut self) -> Option<ObjectId> {
+        self.ids.next().map(|id| (id, 0))
+    }
+
+    /// How many ids are left.
+    pub fn remaining(&self) -> u32 {
+        self.ids.end - self.ids.start
+    }
+}
+
+impl Document {
+    /// The allocator of the ids of the document, made on first use with the ids after
+    /// [`max_id`](Document::max_id). From then on, [`new_object_id`](Document::new_object_id) and
+    /// [`add_object`](Document::add_object) take their ids from it too, so that they never clash with the ids of
+    /// objects that threads are preparing. Renumbering the objects drops it.
+    pub fn id_allocator(&mut self) -> IdAllocator {
+        let max_id = self.max_id;
+        self.id_allocator
+            .get_or_insert_with(|| IdAllocator::new(max_id + 1))
+            .clone()
+    }
+
+    /// Add objects prepared apart from the document, numbered with ids of blocks reserved from its
+    /// [allocator](Document::id_allocator). Nothing is added if an id was not reserved or is used already.
+    pub fn insert_prepared(&mut self, objects: Vec<(ObjectId, Object)>) -> Result<()> {
+        let allocator = self
+            .id_allocator
+            .as_ref()
+            .ok_or_else(|| Error::Syntax("Prepared objects without an id allocator".to_string()))?;
+        let mut ids = BTreeSet::new();
+        for (id, _) in &objects {
+            if !ids.insert(*id) {
+                return Err(Error::Syntax(format!("Object id {:?} is given twice", id)));
+            }
+            if id.1 != 0 || !allocator.reserved(id.0) {
+                return Err(Error::Syntax(format!("Object id {:?} was not reserved", id)));
+            }
+            if self.objects.contains_key(id) {
+                return Err(Error::Syntax(format!("Object id {:?} is used already", id)));
+            }
+        }
+        self.max_id = self.max_id.max(allocator.first_free() - 1);
+        self.objects.extend(objects);
+        Ok(())
+    }
+
+    /// The id after the largest one used, from the allocator if there is one.
+    pub(crate) fn allocate_id(&mut self) -> ObjectId {
+        let id = match self.id_allocator {
+            Some(ref allocator) => allocator.next_id(),
+            None => self.max_id + 1,
+        };
+        self.max_id = self.max_id.max(id);
+        (id, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn prepared_objects_from_threads() {
+        let mut doc = Document::new();
+        let catalog = doc.add_object(dictionary! { "Type" => "Catalog" });
+        let allocator = doc.id_allocator();
+        let blocks: Vec<IdBlock> = (0..4).map(|_| allocator.reserve(10)).collect();
+        // The document keeps numbering its own objects after the blocks.
+        let own = doc.add_object(Object::Null);
+        assert_eq!(own, (42, 0));
+
+        let prepared: Vec<Vec<(ObjectId, Object)>> = thread::scope(|scope| {
+            let workers: Vec<_> = blocks
+                .into_iter()
+                .enumerate()
+                .map(|(worker, mut block)| {
+                    scope.spawn(move || {
+                        let mut objects = Vec::new();
+                        while let Some(id) = block.next_id() {
+                            objects.push((id, Object::Integer(worker as i64)));
+                        }
+                        objects
+                    })
+                })
+                .collect();
+            workers.into_iter().map(|worker| worker.join().unwrap()).collect()
+        });
+        assert_eq!(prepared[0][0].0, (catalog.0 + 1, 0));
+        assert_eq!(prepared[3][9].0, (41, 0));
+        for objects in prepared {
+            doc.insert_prepared(objects).unwrap();
+        }
+        assert_eq!(doc.objects.len(), 42);
+        assert_eq!(doc.max_id, 42);
+
+        assert!(doc.insert_prepared(vec![((2, 0), Object::Null)]).is_err());
+        assert!(doc.insert_prepared(vec![((50, 0), Object::Null)]).is_err());
+        let mut block = allocator.reserve(1);
+        let id = block.next_id().unwrap();
+        assert!(block.next_id().is_none());
+        assert!(doc.insert_prepared(vec![(id, Object::Null), (id, Object::Null)]).is_err());
+        assert!(Document::new().insert_prepared(vec![((1, 0), Object::Null)]).is_err());
+    }
+}
@@ -0,0 +1,1813 @@
+#![cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+
+use crate::annotations::Appearance;
+use crate::content::{Content, Operation};
+use crate::filters::{jpeg, jpx};
+use crate::function::Function;
+use crate::geometry::{multiply, Matrix, IDENTITY_MATRIX};
+use crate::{Codecs, Dictionary, Document, Error, Object, ObjectId, Result, Stream};
+use log::warn;
+use std::collections::{BTreeMap, HashMap};
+
+/// Maximum nesting of Form XObjects followed when looking for image placements.
+const FORM_DEPTH_LIMIT: usize = 32;
+
+/// An image XObject painted on a page.
+#[derive(Debug, Clone)]
+pub struct ImagePlacement {
+    /// Page the image is painted on.
+    pub page_id: ObjectId,
+    /// Id of the image XObject.
+    pub image_id: ObjectId,
+    /// Transformation matrix in effect when the image was painted; it maps the unit square onto user space.
+    pub ctm: Matrix,
+    /// Fill color in effect when the image was painted, approximated as RGB. Stencil masks are painted with it.
+    pub fill_color: [u8; 3],
+}
+
+impl ImagePlacement {
+    /// Width and height in points of the painted image.
+    pub fn rendered_size(&self) -> (f64, f64) {
+        let [a, b, c, d, _, _] = self.ctm;
+        ((a * a + b * b).sqrt(), (c * c + d * d).sqrt())
+    }
+
+    /// Effective horizontal and vertical resolution in pixels per inch for an image of the given pixel size.
+    pub fn effective_dpi(&self, width: u32, height: u32) -> (f64, f64) {
+        let (rendered_width, rendered_height) = self.rendered_size();
+        (
+            f64::from(width) * 72.0 / rendered_width,
+            f64::from(height) * 72.0 / rendered_height,
+        )
+    }
+}
+
+/// Graphics state parameters tracked while looking for image placements.
+#[derive(Debug, Clone, Copy)]
+struct PaintState {
+    ctm: Matrix,
+    fill_color: [u8; 3],
+}
+
+/// The named XObjects and patterns of the resources of a content stream, as looking for image placements needs.
+#[derive(Debug, Clone, Default)]
+struct PaintResources {
+    xobjects: BTreeMap<Vec<u8>, ObjectId>,
+    patterns: BTreeMap<Vec<u8>, ObjectId>,
+}
+
+/// Pixel layout of a decoded image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelFormat {
+    Gray,
+    Rgb,
+    Cmyk,
+    /// Gray followed by alpha.
+    GrayAlpha,
+    /// RGB followed by alpha.
+    Rgba,
+}
+
+impl PixelFormat {
+    /// Number of components per pixel.
+    pub fn components(self) -> usize {
+        match self {
+            PixelFormat::Gray => 1,
+            PixelFormat::GrayAlpha => 2,
+            PixelFormat::Rgb => 3,
+            PixelFormat::Cmyk | PixelFormat::Rgba => 4,
+        }
+    }
+}
+
+/// Image pixels decoded to 8 bits per component, stored row by row without padding.
+#[derive(Debug, Clone)]
+pub struct DecodedImage {
+    pub width: u32,
+    pub height: u32,
+    pub format: PixelFormat,
+    pub pixels: Vec<u8>,
+}
+
+/// Encoding of the data of an [`ExtractedImage`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageDataFormat {
+    /// JPEG file data.
+    Jpeg,
+    /// JPEG 2000 data, either a JP2 file or a raw codestream, as stored in the document.
+    Jp2,
+    /// Pixels with 8 bits per component, stored row by row without padding.
+    Pixels(PixelFormat),
+}
+
+/// An image XObject in a form usable outside of the document.
+#[derive(Debug, Clone)]
+pub struct ExtractedImage {
+    pub id: ObjectId,
+    pub width: u32,
+    pub height: u32,
+    pub format: ImageDataFormat,
+    pub data: Vec<u8>,
+}
+
+/// Settings for [`Document::extract_image`].
+#[derive(Debug, Clone, Default)]
+pub struct ImageExtractionOptions {
+    /// Return gray or RGB pixels for every image, decoding JPEG data and converting CMYK colors, instead of
+    /// passing JPEG data through and returning CMYK pixels as they are.
+    pub decode: bool,
+    /// Combine images having a soft mask, color key mask or stencil mask with their mask into gray or RGB pixels
+    /// with alpha. Stencil masks (`/ImageMask true`) are given the fill color they are painted with.
+    pub apply_mask: bool,
+}
+
+/// How [`Document::deduplicate_images`] decides that two images are the same.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageCompare {
+    /// Identical stream data and dictionaries.
+    EncodedBytes,
+    /// Identical decoded pixels and dictionary entries other than those describing the compression. Images that
+    /// cannot be decoded are compared by their encoded bytes.
+    #[cfg(feature = "embed_image")]
+    DecodedPixels,
+}
+
+impl Document {
+    /// Find every image XObject painted by the content of a page, including those painted from within Form
+    /// XObjects and tiling patterns, and by the appearances of the annotations of the page in each of their states,
+    /// together with the transformation in effect when they are painted. The images of a tiling pattern are found
+    /// once, in the cell at the origin of the pattern space.
+    pub fn image_placements(&self, page_id: ObjectId) -> Result<Vec<ImagePlacement>> {
+        let content = self.get_and_decode_page_content(page_id)?;
+        let resources = self.page_paint_resources(page_id);
+        let state = PaintState {
+            ctm: IDENTITY_MATRIX,
+            fill_color: [0, 0, 0],
+        };
+        let mut placements = Vec::new();
+        let mut forms = Vec::new();
+        self.collect_image_placements(
+            page_id,
+            &content.operations,
+            &resources,
+            state,
+            &mut forms,
+            &mut placements,
+        );
+        for annotation in self.annotations(page_id) {
+            let rect = match annotation.rect {
+                Some(rect) => rect,
+                None => continue,
+            };
+            let appearances = [
+                &annotation.normal_appearance,
+                &annotation.rollover_appearance,
+                &annotation.down_appearance,
+            ];
+            for appearance in appearances.iter().filter_map(|appearance| appearance.as_ref()) {
+                let streams = match appearance {
+                    Appearance::Stream(id) => vec![*id],
+                    Appearance::States(states) => states.iter().map(|(_, id)| *id).collect(),
+                };
+                for form_id in streams {
+                    if let Ok(ctm) = self.appearance_matrix(rect, form_id) {
+                        let state = PaintState { ctm, ..state };
+                        self.collect_form_placements(page_id, form_id, &resources, state, &mut forms, &mut placements);
+                    }
+                }
+            }
+        }
+        Ok(placements)
+    }
+
+    /// Named XObjects and patterns available to a page, honoring resources inherited from the page tree.
+    fn page_paint_resources(&self, page_id: ObjectId) -> PaintResources {
+        let mut paint_resources = PaintResources::default();
+        let (resource_dict, resource_ids) = self.get_page_resources(page_id);
+        let resources = resource_dict
+            .into_iter()
+            .chain(resource_ids.into_iter().filter_map(|id| self.get_dictionary(id).ok()));
+        for resources in resources {
+            self.collect_paint_resources(resources, &mut paint_resources);
+        }
+        paint_resources
+    }
+
+    fn collect_paint_resources(&self, resources: &Dictionary, paint_resources: &mut PaintResources) {
+        self.collect_resource_ids(resources, b"XObject", &mut paint_resources.xobjects);
+        self.collect_resource_ids(resources, b"Pattern", &mut paint_resources.patterns);
+    }
+
+    pub(crate) fn collect_xobjects(&self, resources: &Dictionary, xobjects: &mut BTreeMap<Vec<u8>, ObjectId>) {
+        self.collect_resource_ids(resources, b"XObject", xobjects);
+    }
+
+    fn collect_resource_ids(&self, resources: &Dictionary, category: &[u8], ids: &mut BTreeMap<Vec<u8>, ObjectId>) {
+        if let Ok(dict) = resources.get_deref(category, self).and_then(Object::as_dict) {
+            for (name, value) in dict {
+                if let Ok(id) = value.as_reference() {
+                    ids.entry(name.clone()).or_insert(id);
+                }
+            }
+        }
+    }
+
+    fn collect_image_placements(
+        &self, page_id: ObjectId, operations: &[Operation], resources: &PaintResources, state: PaintState,
+        forms: &mut Vec<ObjectId>, placements: &mut Vec<ImagePlacement>,
+    ) {
+        // Patterns are laid out from the space the content stream starts in, whatever the current matrix.
+        let pattern_state = state;
+        let mut state = state;
+        let mut stack = Vec::new();
+        for operation in operations {
+            match operation.operator.as_str() {
+                "q" => stack.push(state),
+                "Q" => state = stack.pop().unwrap_or(state),
+                "cm" => {
+                    if let Some(matrix) = matrix_from_operands(&operation.operands) {
+                        state.ctm = multiply(&matrix, &state.ctm);
+                    }
+                }
+                "g" | "rg" | "k" | "sc" | "scn" => {
+                    let components = operation
+                        .operands
+                        .iter()
+                        .map(|operand| {
+                            operand
+                                .as_float()
+                                .ok()
+                                .map(|c| (c * 255.0).round().clamp(0.0, 255.0) as u8)
+                        })
+                        .collect::<Option<Vec<_>>>();
+                    match components.as_deref() {
+                        Some(&[gray]) => state.fill_color = [gray; 3],
+                        Some(&[r, g, b]) => state.fill_color = [r, g, b],
+                        Some(cmyk @ &[_, _, _, _]) => state.fill_color.copy_from_slice(&cmyk_to_rgb(cmyk)),
+                        None if operation.operator == "scn" => {
+                            let state = PaintState {
+                                fill_color: state.fill_color,
+                                ..pattern_state
+                            };
+                            self.collect_pattern_placements(
+                                page_id,
+                                &operation.operands,
+                                resources,
+                                state,
+                                forms,
+                                placements,
+                            );
+                        }
+                        // Other color spaces.
+                        _ => {}
+                    }
+                }
+                "SCN" => {
+                    let state = PaintState {
+                        fill_color: state.fill_color,
+                        ..pattern_state
+                    };
+                    self.collect_pattern_placements(page_id, &operation.operands, resources, state, forms, placements);
+                }
+                "Do" => {
+                    let xobject = operation
+                        .operands
+                        .first()
+                        .and_then(|name| name.as_name().ok())
+                        .and_then(|name| resources.xobjects.get(name));
+                    let (xobject_id, stream) = match xobject
+                        .and_then(|&id| self.get_object(id).and_then(Object::as_stream).ok().map(|s| (id, s)))
+                    {
+                        Some(xobject) => xobject,
+                        None => continue,
+                    };
+                    match stream.dict.get(b"Subtype").and_then(Object::as_name) {
+                        Ok(b"Image") => placements.push(ImagePlacement {
+                            page_id,
+                            image_id: xobject_id,
+                            ctm: state.ctm,
+                            fill_color: state.fill_color,
+                        }),
+                        Ok(b"Form") => {
+                            self.collect_form_placements(page_id, xobject_id, resources, state, forms, placements)
+                        }
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Look for images in the tiling pattern that an `scn` or `SCN` operation selects, if it selects one.
+    fn collect_pattern_placements(
+        &self, page_id: ObjectId, operands: &[Object], resources: &PaintResources, state: PaintState,
+        forms: &mut Vec<ObjectId>, placements: &mut Vec<ImagePlacement>,
+    ) {
+        let pattern_id = match operands
+            .last()
+            .and_then(|name| name.as_name().ok())
+            .and_then(|name| resources.patterns.get(name))
+        {
+            Some(&pattern_id) => pattern_id,
+            None => return,
+        };
+        let tiling = self
+            .get_object(pattern_id)
+            .and_then(Object::as_stream)
+            .and_then(|pattern| pattern.dict.get(b"PatternType"))
+            .and_then(Object::as_i64)
+            .ok()
+            == Some(1);
+        if tiling {
+            self.collect_form_placements(page_id, pattern_id, resources, state, forms, placements);
+        }
+    }
+
+    /// Look for images in a form XObject, or in another stream with a `/Matrix` and `/Resources` that is drawn as
+    /// one is: an appearance stream or a tiling pattern.
+    fn collect_form_placements(
+        &self, page_id: ObjectId, form_id: ObjectId, resources: &PaintResources, state: PaintState,
+        forms: &mut Vec<ObjectId>, placements: &mut Vec<ImagePlacement>,
+    ) {
+        if forms.len() >= FORM_DEPTH_LIMIT || forms.contains(&form_id) {
+            return;
+        }
+        let stream = match self.get_object(form_id).and_then(Object::as_stream) {
+            Ok(stream) => stream,
+            Err(_) => return,
+        };
+        let data = stream
+            .decompressed_content()
+            .unwrap_or_else(|_| stream.content.to_vec());
+        let content = match Content::decode(&data) {
+            Ok(content) => content,
+            Err(_) => {
+                warn!("Could not decode content of form {:?}", form_id);
+                return;
+            }
+        };
+        let matrix = stream
+            .dict
+            .get(b"Matrix")
+            .and_then(Object::as_array)
+            .ok()
+            .and_then(|m| matrix_from_operands(m))
+            .unwrap_or(IDENTITY_MATRIX);
+        let form_resources = match stream.dict.get_deref(b"Resources", self).and_then(Object::as_dict) {
+            Ok(form_resources) => {
+                let mut paint_resources = PaintResources::default();
+                self.collect_paint_resources(form_resources, &mut paint_resources);
+                paint_resources
+            }
+            Err(_) => resources.clone(),
+        };
+        forms.push(form_id);
+        self.collect_image_placements(
+            page_id,
+            &content.operations,
+            &form_resources,
+            PaintState {
+                ctm: multiply(&matrix, &state.ctm),
+                ..state
+            },
+            forms,
+            placements,
+        );
+        forms.pop();
+    }
+
+    /// Decode the pixels of an image XObject to 8 bits per component.
+    ///
+    /// Images in Indexed, Separation and DeviceN color spaces are converted to their base or alternate color space,
+    /// which applies their `/Decode` array. Other sample values are returned as stored; the `/Decode` array is not
+    /// applied, so CMYK JPEG images written by Adobe applications come out inverted. JPEG images can only be decoded
+    /// with the `embed_image` feature.
+    pub fn decode_image(&self, image_id: ObjectId) -> Result<DecodedImage> {
+        let stream = self.get_object(image_id).and_then(Object::as_stream)?;
+        if !image_subtype(stream) {
+            return Err(Error::type_mismatch("Image", "other XObject").at_key(b"Subtype"));
+        }
+        let (data, image_filter) = decode_image_filters(stream)?;
+        self.decode_image_data(stream, data, image_filter.as_deref(), false)
+    }
+
+    fn decode_image_data(
+        &self, stream: &Stream, data: Vec<u8>, image_filter: Option<&str>, apply_decode: bool,
+    ) -> Result<DecodedImage> {
+        let width = stream.dict.get(b"Width").and_then(Object::as_i64)? as u32;
+        let height = stream.dict.get(b"Height").and_then(Object::as_i64)? as u32;
+        let is_mask = stream.dict.get(b"ImageMask").and_then(Object::as_bool).unwrap_or(false);
+        let color_space = if is_mask {
+            ImageColorSpace::Device(PixelFormat::Gray)
+        } else {
+            self.image_color_space(stream.dict.get(b"ColorSpace")?, 0)?
+        };
+        let components = color_space.components();
+        let bits = if is_mask {
+            1
+        } else {
+            stream
+                .dict
+                .get(b"BitsPerComponent")
+                .and_then(Object::as_i64)
+                .unwrap_or(8)
+        };
+
+        let mut samples = match image_filter {
+            None => {
+                let is_indexed = matches!(color_space, ImageColorSpace::Indexed { .. });
+                unpack_samples(&data, width, height, components, bits, !is_indexed)?
+            }
+            #[cfg(feature = "embed_image")]
+            Some("DCTDecode") => {
+                let image = decode_jpeg(&data)?;
+                if image.format.components() != components {
+                    return Err(Error::Syntax(
+                        "JPEG data does not match the image color space".to_string(),
+                    ));
+                }
+                image.pixels
+            }
+            #[cfg(not(feature = "embed_image"))]
+            Some("DCTDecode") => return Err(Error::Unimplemented("DCTDecode without the embed_image feature")),
+            Some(_) => return Err(Error::Unimplemented("image filter")),
+        };
+
+        let decode = decode_array(&stream.dict, components);
+        let (format, pixels) = match color_space {
+            ImageColorSpace::Device(format) => {
+                if let Some(decode) = decode.filter(|_| apply_decode) {
+                    self::apply_decode(&mut samples, &decode);
+                }
+                (format, samples)
+            }
+            ImageColorSpace::Indexed { base, hival, lookup } => {
+                if let Some(decode) = decode {
+                    let max = f64::from((1_u32 << bits) - 1);
+                    for index in &mut samples {
+                        let value = decode[0] + f64::from(*index) * (decode[1] - decode[0]) / max;
+                        *index = value.round().clamp(0.0, 255.0) as u8;
+                    }
+                }
+                let base_components = base.components();
+                let mut base_samples = Vec::with_capacity(samples.len() * base_components);
+                for &index in &samples {
+                    let start = usize::from(index).min(hival) * base_components;
+                    match lookup.get(start..start + base_components) {
+                        Some(entry) => base_samples.extend_from_slice(entry),
+                        None => base_samples.resize(base_samples.len() + base_components, 0),
+                    }
+                }
+                base.convert(base_samples)?
+            }
+            color_space => {
+                if let Some(decode) = decode {
+                    self::apply_decode(&mut samples, &decode);
+                }
+                color_space.convert(samples)?
+            }
+        };
+        Ok(DecodedImage {
+            width,
+            height,
+            format,
+            pixels,
+        })
+    }
+
+    /// Extract an image XObject with its `/Decode` array applied.
+    ///
+    /// JPEG data is passed through unless decoding is requested, the image has a mask to apply or its colors would
+    /// not display correctly outside of the document. JPEG 2000 data is always passed through, as it cannot be
+    /// decoded. Stencil masks are given a black fill color.
+    pub fn extract_image(&self, image_id: ObjectId, options: &ImageExtractionOptions) -> Result<ExtractedImage> {
+        self.extract_painted_image(image_id, options, [0, 0, 0])
+    }
+
+    fn extract_painted_image(
+        &self, image_id: ObjectId, options: &ImageExtractionOptions, fill_color: [u8; 3],
+    ) -> Result<ExtractedImage> {
+        let stream = self.get_object(image_id).and_then(Object::as_stream)?;
+        if !image_subtype(stream) {
+            return Err(Error::type_mismatch("Image", "other XObject").at_key(b"Subtype"));
+        }
+        self.extract_image_stream(image_id, stream, options, fill_color)
+    }
+
+    /// Extract an image stream, which may lack the `/Subtype` of an XObject, as thumbnails do.
+    pub(crate) fn extract_image_stream(
+        &self, image_id: ObjectId, stream: &Stream, options: &ImageExtractionOptions, fill_color: [u8; 3],
+    ) -> Result<ExtractedImage> {
+        let (data, image_filter) = decode_image_filters(stream)?;
+        let is_stencil = stream.dict.get(b"ImageMask").and_then(Object::as_bool).unwrap_or(false);
+        let apply_mask = options.apply_mask && (is_stencil || stream.dict.has(b"SMask") || stream.dict.has(b"Mask"));
+
+        if !options.decode
+            && !apply_mask
+            && image_filter.as_deref() == Some("DCTDecode")
+            && self.has_device_colors(stream)
+        {
+            let info = jpeg::read_info(&data).ok_or_else(|| Error::Syntax("Invalid JPEG data".to_string()))?;
+            let components = usize::from(info.components);
+            // Image viewers undo the inversion of Adobe CMYK data, which is what the /Decode array is for.
+            let passthrough = match decode_array(&stream.dict, components) {
+                Some(decode) if info.is_inverted_cmyk() => decode == inverted_decode(components),
+                Some(decode) => decode == default_decode(components),
+                None => !info.is_inverted_cmyk(),
+            };
+            if passthrough {
+                return Ok(ExtractedImage {
+                    id: image_id,
+                    width: u32::from(info.width),
+                    height: u32::from(info.height),
+                    format: ImageDataFormat::Jpeg,
+                    data,
+                });
+            }
+        }
+
+        if image_filter.as_deref() == Some("JPXDecode") {
+            if options.decode || apply_mask {
+                return Err(Error::Unimplemented("decoding JPXDecode images"));
+            }
+            let info = jpx::read_info(&data).ok_or_else(|| Error::Syntax("Invalid JPEG 2000 data".to_string()))?;
+            return Ok(ExtractedImage {
+                id: image_id,
+                width: info.width,
+                height: info.height,
+                format: ImageDataFormat::Jp2,
+                data,
+            });
+        }
+
+        let mut image = self.decode_image_data(stream, data, image_filter.as_deref(), true)?;
+        if apply_mask && is_stencil {
+            // Painted samples are 0 once the /Decode array is applied.
+            let mut pixels = Vec::with_capacity(image.pixels.len() * 4);
+            for &sample in &image.pixels {
+                pixels.extend_from_slice(&fill_color);
+                pixels.push(255 - sample);
+            }
+            image.pixels = pixels;
+            image.format = PixelFormat::Rgba;
+        } else if apply_mask {
+            let (alpha, matte) = self.image_alpha(stream, &image)?;
+            if let Some(matte) = matte.filter(|matte| matte.len() == image.format.components()) {
+                unmultiply_matte(&mut image.pixels, &alpha, &matte);
+            }
+            if image.format == PixelFormat::Cmyk {
+                image.pixels = cmyk_to_rgb(&image.pixels);
+                image.format = PixelFormat::Rgb;
+            }
+            let components = image.format.components();
+            let mut pixels = Vec::with_capacity(image.pixels.len() / components * (components + 1));
+            for (color, &alpha) in image.pixels.chunks_exact(components).zip(&alpha) {
+                pixels.extend_from_slice(color);
+                pixels.push(alpha);
+            }
+            image.pixels = pixels;
+            image.format = if components == 1 {
+                PixelFormat::GrayAlpha
+            } else {
+                PixelFormat::Rgba
+            };
+        } else if options.decode && image.format == PixelFormat::Cmyk {
+            image.pixels = cmyk_to_rgb(&image.pixels);
+            image.format = PixelFormat::Rgb;
+        }
+        Ok(ExtractedImage {
+            id: image_id,
+            width: image.width,
+            height: image.height,
+            format: ImageDataFormat::Pixels(image.format),
+            data: image.pixels,
+        })
+    }
+
+    /// Alpha value of each pixel of a decoded image from its mask, with the `/Matte` color of a soft mask.
+    fn image_alpha(&self, stream: &Stream, image: &DecodedImage) -> Result<(Vec<u8>, Option<Vec<f64>>)> {
+        let dimensions = (image.width, image.height);
+        if let Ok(smask_id) = stream.dict.get(b"SMask").and_then(Object::as_reference) {
+            let smask = self.get_object(smask_id).and_then(Object::as_stream)?;
+            let (data, image_filter) = decode_image_filters(smask)?;
+            let mask = self.decode_image_data(smask, data, image_filter.as_deref(), true)?;
+            if mask.format != PixelFormat::Gray {
+                return Err(Error::Syntax("Soft mask is not a gray image".to_string()));
+            }
+            let matte = smask
+                .dict
+                .get(b"Matte")
+                .and_then(Object::as_array)
+                .ok()
+                .and_then(|matte| matte.iter().map(|c| c.as_float().ok()).collect());
+            return Ok((resize_nearest(&mask, dimensions)?, matte));
+        }
+        match stream.dict.get(b"Mask") {
+            Ok(Object::Reference(mask_id)) => {
+                let mask_stream = self.get_object(*mask_id).and_then(Object::as_stream)?;
+                let (data, image_filter) = decode_image_filters(mask_stream)?;
+                let mut mask = self.decode_image_data(mask_stream, data, image_filter.as_deref(), true)?;
+                // Stencil mask samples of 1 mask out the image once the /Decode array is applied.
+                for sample in &mut mask.pixels {
+                    *sample = 255 - *sample;
+                }
+                Ok((resize_nearest(&mask, dimensions)?, None))
+            }
+            Ok(Object::Array(ranges)) => {
+                let ranges = ranges.iter().map(Object::as_i64).collect::<Result<Vec<_>>>()?;
+                let (data, image_filter) = decode_image_filters(stream)?;
+                if image_filter.is_some() {
+                    return Err(Error::Unimplemented("color key mask of compressed image data"));
+                }
+                let components = self.image_color_space(stream.dict.get(b"ColorSpace")?, 0)?.components();
+                if ranges.len() != components * 2 {
+                    return Err(Error::Syntax(
+                        "Color key mask does not match the color space".to_string(),
+                    ));
+                }
+                let bits = stream.dict.get(b"BitsPerComponent").and_then(Object::as_i64)?;
+                // 16-bit samples are unpacked to their high byte.
+                let shift = if bits == 16 { 8 } else { 0 };
+                let samples = unpack_samples(&data, image.width, image.height, components, bits, false)?;
+                let alpha = samples
+                    .chunks_exact(components)
+                    .map(|color| {
+                        let masked = color.iter().enumerate().all(|(i, &c)| {
+                            (ranges[i * 2] >> shift..=ranges[i * 2 + 1] >> shift).contains(&i64::from(c))
+                        });
+                        if masked {
+                            0
+                        } else {
+                            255
+                        }
+                    })
+                    .collect();
+                Ok((alpha, None))
+            }
+            _ => {
+                // There is a pixel of the image for each alpha value.
+                let pixels = (image.width as usize)
+                    .checked_mul(image.height as usize)
+                    .filter(|&pixels| pixels <= image.pixels.len())
+                    .ok_or_else(|| {
+                        Error::Syntax(format!("Invalid image dimensions {}x{}", image.width, image.height))
+                    })?;
+                Ok((vec![255; pixels], None))
+            }
+        }
+    }
+
+    /// Extract every image painted on a page, skipping those that cannot be extracted.
+    pub fn extract_images(&self, page_id: ObjectId, options: &ImageExtractionOptions) -> Result<Vec<ExtractedImage>> {
+        let mut image_ids = Vec::new();
+        for placement in self.image_placements(page_id)? {
+            if !image_ids.iter().any(|&(id, _)| id == placement.image_id) {
+                image_ids.push((placement.image_id, placement.fill_color));
+            }
+        }
+        let mut images = Vec::with_capacity(image_ids.len());
+        for (image_id, fill_color) in image_ids {
+            match self.extract_painted_image(image_id, options, fill_color) {
+                Ok(image) => images.push(image),
+                Err(err) => warn!("Could not extract image {:?}: {}", image_id, err),
+            }
+        }
+        Ok(images)
+    }
+
+    /// Replace duplicate image XObjects by a single one and remove the duplicates.
+    ///
+    /// All references to a duplicate, such as from resource dictionaries and `/SMask` entries, are changed to
+    /// refer to the image that is kept. Images whose dictionaries differ, for instance in their `/SMask` or
+    /// `/Decode` entries, are never merged. Returns the number of stream bytes saved.
+    pub fn deduplicate_images(&mut self, compare: ImageCompare) -> Result<usize> {
+        let mut saved = 0;
+        // Merging soft masks can make the images using them identical, so repeat until nothing changes.
+        loop {
+            let mut survivors: HashMap<Vec<u8>, ObjectId> = HashMap::new();
+            let mut replace = BTreeMap::new();
+            for (&id, object) in &self.objects {
+                let stream = match object {
+                    Object::Stream(stream) if image_subtype(stream) => stream,
+                    _ => continue,
+                };
+                let key = self.image_key(stream, compare);
+                match survivors.get(&key) {
+                    Some(&survivor) => {
+                        replace.insert(id, survivor);
+                    }
+                    None => {
+                        survivors.insert(key, id);
+                    }
+                }
+            }
+            if replace.is_empty() {
+                return Ok(saved);
+            }
+
+            // Objects that the trailer does not lead to, such as those of pages about to be added elsewhere, are
+            // changed as well, so that no reference to a removed duplicate is left.
+            for object in self.objects.values_mut() {
+                replace_references(object, &replace);
+            }
+            for (_, object) in self.trailer.iter_mut() {
+                replace_references(object, &replace);
+            }
+            for id in replace.keys() {
+                if let Some(Object::Stream(stream)) = self.objects.remove(id) {
+                    saved += stream.content.len();
+                }
+            }
+        }
+    }
+
+    /// Data identifying an image for deduplication.
+    fn image_key(&self, stream: &Stream, compare: ImageCompare) -> Vec<u8> {
+        let mut key = Vec::new();
+        #[cfg(feature = "embed_image")]
+        {
+            if compare == ImageCompare::DecodedPixels {
+                let decoded = decode_image_filters(stream).and_then(|(data, image_filter)| {
+                    self.decode_image_data(stream, data, image_filter.as_deref(), false)
+                });
+                match decoded {
+                    Ok(image) => {
+                        let mut dict = stream.dict.clone();
+                        for encoding_key in &[&b"Length"[..], b"Filter", b"DecodeParms", b"BitsPerComponent"] {
+                            dict.remove(encoding_key);
+                        }
+                        key.push(b'P');
+                        write_canonical(&Object::Dictionary(dict), &mut key);
+                        key.extend_from_slice(format!("{:?}", image.format).as_bytes());
+                        key.extend_from_slice(&image.pixels);
+                        return key;
+                    }
+                    Err(err) => warn!("Comparing undecodable image by its encoded bytes: {}", err),
+                }
+            }
+        }
+        #[cfg(not(feature = "embed_image"))]
+        let _ = compare;
+
+        let mut dict = stream.dict.clone();
+        dict.remove(b"Length");
+        key.push(b'E');
+        write_canonical(&Object::Dictionary(dict), &mut key);
+        key.extend_from_slice(&stream.content);
+        key
+    }
+
+    /// Whether the samples of an image are device colors, rather than palette indices or tints.
+    fn has_device_colors(&self, stream: &Stream) -> bool {
+        match stream.dict.get(b"ColorSpace") {
+            Ok(color_space) => matches!(self.image_color_space(color_space, 0), Ok(ImageColorSpace::Device(_))),
+            Err(_) => false,
+        }
+    }
+
+    fn image_color_space(&self, color_space: &Object, depth: usize) -> Result<ImageColorSpace> {
+        if depth > 4 {
+            return Err(Error::Syntax("Color spaces are nested too deeply".to_string()));
+        }
+        let (_, color_space) = self.dereference(color_space)?;
+        let (family, params) = match color_space {
+            Object::Name(name) => (name.as_slice(), &[][..]),
+            Object::Array(array) => (
+                array.first().ok_or_else(|| Error::type_mismatch("Name", "empty Array"))?.as_name()?,
+                &array[1..],
+            ),
+            other => return Err(Error::type_mismatch("Name or Array", other.enum_variant())),
+        };
+        let param = |index: usize| -> Result<&Object> {
+            let param = params
+                .get(index)
+                .ok_or(Error::Syntax("Missing color space parameter".to_string()))?;
+            self.dereference(param).map(|(_, param)| param)
+        };
+        match family {
+            b"DeviceGray" | b"CalGray" | b"G" => Ok(ImageColorSpace::Device(PixelFormat::Gray)),
+            b"DeviceRGB" | b"CalRGB" | b"RGB" => Ok(ImageColorSpace::Device(PixelFormat::Rgb)),
+            b"DeviceCMYK" | b"CMYK" => Ok(ImageColorSpace::Device(PixelFormat::Cmyk)),
+            b"ICCBased" => {
+                // Without color management, use the alternate color space or a device space with as many components.
+                let profile = param(0)?.as_stream()?;
+                let components = profile.dict.get(b"N").and_then(Object::as_i64).ok();
+                if let Ok(alternate) = profile.dict.get(b"Alternate") {
+                    match self.image_color_space(alternate, depth + 1) {
+                        Ok(alternate) if components.is_none_or(|n| n as usize == alternate.components()) => {
+                            return Ok(alternate)
+                        }
+                        _ => warn!("Ignoring unusable alternate of ICC based color space"),
+                    }
+                }
+                match components {
+                    Some(1) => Ok(ImageColorSpace::Device(PixelFormat::Gray)),
+                    Some(3) => Ok(ImageColorSpace::Device(PixelFormat::Rgb)),
+                    Some(4) => Ok(ImageColorSpace::Device(PixelFormat::Cmyk)),
+                    _ => Err(Error::Unimplemented("ICC profile component count")),
+                }
+            }
+            b"Indexed" | b"I" => {
+                let base = self.image_color_space(param(0)?, depth + 1)?;
+                if matches!(base, ImageColorSpace::Indexed { .. }) {
+                    return Err(Error::Syntax("Indexed color space with an indexed base".to_string()));
+                }
+                let hival = param(1)?.as_i64()?.clamp(0, 255) as usize;
+                let lookup = match param(2)? {
+                    Object::String(bytes, _) => bytes.clone(),
+                    Object::Stream(stream) => decode_image_filters(stream)?.0,
+                    other => return Err(Error::type_mismatch("String or Stream", other.enum_variant())),
+                };
+                Ok(ImageColorSpace::Indexed {
+                    base: Box::new(base),
+                    hival,
+                    lookup,
+                })
+            }
+            b"Separation" | b"DeviceN" => {
+                let components = if family == b"Separation" {
+                    1
+                } else {
+                    param(0)?.as_array()?.len()
+                };
+                let alternate = self.image_color_space(param(1)?, depth + 1)?;
+                if !matches!(alternate, ImageColorSpace::Device(_)) {
+                    return Err(Error::Unimplemented("tint transform to a special color space"));
+                }
+                let tint_transform = Function::from_object(self, param(2)?)?;
+                if tint_transform.input_count() != components
+                    || tint_transform
+                        .output_count()
+                        .is_some_and(|n| n != alternate.components())
+                {
+                    return Err(Error::Syntax(
+                        "Tint transform does not match the color space".to_string(),
+                    ));
+                }
+                Ok(ImageColorSpace::Tinted {
+                    components,
+                    alternate: Box::new(alternate),
+                    tint_transform,
+                })
+            }
+            _ => Err(Error::Unimplemented("image color space")),
+        }
+    }
+}
+
+/// Color space of image samples, resolved as far as needed to convert the samples to device colors.
+enum ImageColorSpace {
+    Device(PixelFormat),
+    Indexed {
+        base: Box<ImageColorSpace>,
+        hival: usize,
+        lookup: Vec<u8>,
+    },
+    /// Separation and DeviceN color spaces.
+    Tinted {
+        components: usize,
+        alternate: Box<ImageColorSpace>,
+        tint_transform: Function,
+    },
+}
+
+impl ImageColorSpace {
+    fn components(&self) -> usize {
+        match self {
+            ImageColorSpace::Device(format) => format.components(),
+            ImageColorSpace::Indexed { .. } => 1,
+            ImageColorSpace::Tinted { components, .. } => *components,
+        }
+    }
+
+    /// Convert samples scaled to 0-255 to device colors.
+    fn convert(&self, samples: Vec<u8>) -> Result<(PixelFormat, Vec<u8>)> {
+        match self {
+            ImageColorSpace::Device(format) => Ok((*format, samples)),
+            ImageColorSpace::Indexed { .. } => Err(Error::Unimplemented("indexed color space as a base")),
+            ImageColorSpace::Tinted {
+                components,
+                alternate,
+                tint_transform,
+            } => {
+                let alternate_components = alternate.components();
+                let mut colors: HashMap<&[u8], Vec<u8>> = HashMap::new();
+                let mut converted = Vec::with_capacity(samples.len() / components * alternate_components);
+                for tint in samples.chunks_exact(*components) {
+                    if let Some(color) = colors.get(tint) {
+                        converted.extend_from_slice(color);
+                        continue;
+                    }
+                    let input = tint.iter().map(|&t| f64::from(t) / 255.0).collect::<Vec<_>>();
+                    let output = tint_transform.evaluate(&input)?;
+                    if output.len() != alternate_components {
+                        return Err(Error::Syntax(
+                            "Tint transform does not match the color space".to_string(),
+                        ));
+                    }
+                    let color = output
+                        .iter()
+                        .map(|&value| (value * 255.0).round().clamp(0.0, 255.0) as u8)
+                        .collect::<Vec<_>>();
+                    converted.extend_from_slice(&color);
+                    colors.insert(tint, color);
+                }
+                alternate.convert(converted)
+            }
+        }
+    }
+}
+
+/// Serialize an object so that equal objects give equal bytes regardless of dictionary key order and string format.
+fn write_canonical(object: &Object, out: &mut Vec<u8>) {
+    fn write_bytes(bytes: &[u8], out: &mut Vec<u8>) {
+        out.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+        out.extend_from_slice(bytes);
+    }
+    match object {
+        Object::Null => out.push(b'n'),
+        Object::Boolean(value) => out.extend_from_slice(if *value { b"t" } else { b"f" }),
+        Object::Integer(value) => {
+            out.push(b'i');
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        Object::Real(value) => {
+            out.push(b'r');
+            out.extend_from_slice(&value.to_bits().to_le_bytes());
+        }
+        Object::Name(name) => {
+            out.push(b'/');
+            write_bytes(name, out);
+        }
+        Object::String(text, _) => {
+            out.push(b'(');
+            write_bytes(text, out);
+        }
+        Object::Array(array) => {
+            out.push(b'[');
+            out.extend_from_slice(&(array.len() as u64).to_le_bytes());
+            for item in array {
+                write_canonical(item, out);
+            }
+        }
+        Object::Dictionary(dict) => {
+            let mut entries = dict.iter().collect::<Vec<_>>();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            out.push(b'<');
+            out.extend_from_slice(&(entries.len() as u64).to_le_bytes());
+            for (key, value) in entries {
+                write_bytes(key, out);
+                write_canonical(value, out);
+            }
+        }
+        Object::Stream(stream) => {
+            out.push(b's');
+            write_canonical(&Object::Dictionary(stream.dict.clone()), out);
+            write_bytes(&stream.content, out);
+        }
+        Object::Reference(id) => {
+            out.push(b'R');
+            out.extend_from_slice(&id.0.to_le_bytes());
+            out.extend_from_slice(&id.1.to_le_bytes());
+        }
+    }
+}
+
+/// Make the references of an object and those of the objects it holds refer to their replacements.
+fn replace_references(object: &mut Object, replace: &BTreeMap<ObjectId, ObjectId>) {
+    match object {
+        Object::Reference(id) => {
+            if let Some(replacement) = replace.get(id) {
+                *id = *replacement;
+            }
+        }
+        Object::Array(array) => {
+            for item in array {
+                replace_references(item, replace);
+            }
+        }
+        Object::Dictionary(dict) | Object::Stream(Stream { dict, .. }) => {
+            for (_, value) in dict.iter_mut() {
+                replace_references(value, replace);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn image_subtype(stream: &Stream) -> bool {
+    stream.dict.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Image")
+}
+
+fn matrix_from_operands(operands: &[Object]) -> Option<Matrix> {
+    if operands.len() != 6 {
+        return None;
+    }
+    let mut matrix = IDENTITY_MATRIX;
+    for (value, operand) in matrix.iter_mut().zip(operands) {
+        *value = operand.as_float().ok()?;
+    }
+    Some(matrix)
+}
+
+/// The `/Decode` array of an image if it has the expected number of values.
+fn decode_array(dict: &Dictionary, components: usize) -> Option<Vec<f64>> {
+    let array = dict.get(b"Decode").and_then(Object::as_array).ok()?;
+    let decode = array
+        .iter()
+        .map(|value| value.as_float().ok())
+        .collect::<Option<Vec<_>>>()?;
+    if decode.len() == components * 2 {
+        Some(decode)
+    } else {
+        warn!("Ignoring /Decode array of length {}", decode.len());
+        None
+    }
+}
+
+fn default_decode(components: usize) -> Vec<f64> {
+    [0.0, 1.0].repeat(components)
+}
+
+fn inverted_decode(components: usize) -> Vec<f64> {
+    [1.0, 0.0].repeat(components)
+}
+
+/// Map 8-bit samples through the per-component ranges of a `/Decode` array.
+fn apply_decode(pixels: &mut [u8], decode: &[f64]) {
+    if decode == default_decode(decode.len() / 2).as_slice() {
+        return;
+    }
+    let components = decode.len() / 2;
+    for (index, sample) in pixels.iter_mut().enumerate() {
+        let component = index % components;
+        let (min, max) = (decode[component * 2], decode[component * 2 + 1]);
+        let value = min + f64::from(*sample) / 255.0 * (max - min);
+        *sample = (value * 255.0).round().clamp(0.0, 255.0) as u8;
+    }
+}
+
+/// Undo the premultiplication of colors with the `/Matte` color of their soft mask.
+fn unmultiply_matte(pixels: &mut [u8], alpha: &[u8], matte: &[f64]) {
+    let components = matte.len();
+    for (color, &alpha) in pixels.chunks_exact_mut(components).zip(alpha) {
+        if alpha == 0 {
+            continue;
+        }
+        for (c, &m) in color.iter_mut().zip(matte) {
+            let m = m * 255.0;
+            let value = m + (f64::from(*c) - m) * 255.0 / f64::from(alpha);
+            *c = value.round().clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+/// Scale a decoded image to the given dimensions by repeating or dropping pixels.
+fn resize_nearest(image: &DecodedImage, dimensions: (u32, u32)) -> Result<Vec<u8>> {
+    let components = image.format.components();
+    let size = |width: u32, height: u32| {
+        (width as usize)
+            .checked_mul(height as usize)
+            .and_then(|pixels| pixels.checked_mul(components))
+            .filter(|&size| size > 0)
+            .ok_or_else(|| Error::Syntax(format!("Invalid image dimensions {}x{}", width, height)))
+    };
+    if image.pixels.len() < size(image.width, image.height)? {
+        return Err(Error::Syntax("Image data is too short for its dimensions".to_string()));
+    }
+    let resized = size(dimensions.0, dimensions.1)?;
+    if (image.width, image.height) == dimensions {
+        return Ok(image.pixels[..resized].to_vec());
+    }
+    let (width, height) = (dimensions.0 as usize, dimensions.1 as usize);
+    let mut pixels = Vec::with_capacity(resized);
+    for y in 0..height {
+        let source_y = y * image.height as usize / height;
+        for x in 0..width {
+            let source_x = x * image.width as usize / width;
+            let offset = (source_y * image.width as usize + source_x) * components;
+            pixels.extend_from_slice(&image.pixels[offset..offset + components]);
+        }
+    }
+    Ok(pixels)
+}
+
+/// Naive conversion of CMYK pixels to RGB, without color management.
+fn cmyk_to_rgb(pixels: &[u8]) -> Vec<u8> {
+    let mut rgb = Vec::with_capacity(pixels.len() / 4 * 3);
+    for cmyk in pixels.chunks_exact(4) {
+        let white = 255 - u32::from(cmyk[3]);
+        for &ink in &cmyk[..3] {
+            rgb.push(((255 - u32::from(ink)) * white / 255) as u8);
+        }
+    }
+    rgb
+}
+
+/// Apply the generic filters of an image stream.
+///
+/// Returns the decoded data and, if the last filter is an image compression filter (such as `DCTDecode`),
+/// its name; the data is then still compressed with that filter.
+pub(crate) fn decode_image_filters(stream: &Stream) -> Result<(Vec<u8>, Option<String>)> {
+    let filters = match stream.filters() {
+        Ok(filters) => filters,
+        Err(Error::DictKey { .. }) => vec![],
+        Err(err) => return Err(err),
+    };
+    let params = stream.dict.get(b"DecodeParms").ok();
+    Codecs::with(|codecs| {
+        let mut data: Option<Vec<u8>> = None;
+        for (index, filter) in filters.iter().enumerate() {
+            let params = match params {
+                Some(Object::Dictionary(dict)) if index == 0 => Some(dict),
+                Some(Object::Array(array)) => array.get(index).and_then(|p| p.as_dict().ok()),
+                _ => None,
+            };
+            let input = data.as_deref().unwrap_or(&stream.content);
+            let decoded = match filter.as_str() {
+                "FlateDecode" | "Fl" => codecs.flate_decode(input, params)?,
+                "LZWDecode" | "LZW" => codecs.lzw_decode(input, params)?,
+                "DCTDecode" | "DCT" | "JPXDecode" | "CCITTFaxDecode" | "CCF" | "JBIG2Decode"
+                    if index == filters.len() - 1 =>
+                {
+                    let name = if filter == "DCT" { "DCTDecode" } else { filter.as_str() };
+                    let data = data.map_or_else(|| stream.content.to_vec(), |data| codecs.detach(data));
+                    return Ok((data, Some(name.to_string())));
+                }
+                _ => return Err(Error::Unimplemented("image filter")),
+            };
+            if let Some(previous) = data.replace(decoded) {
+                codecs.recycle(previous);
+            }
+        }
+        let data = data.map_or_else(|| stream.content.to_vec(), |data| codecs.detach(data));
+        Ok((data, None))
+    })
+}
+
+/// Expand packed samples of `bits` bits into one byte per component, optionally scaling to the 0-255 range.
+fn unpack_samples(data: &[u8], width: u32, height: u32, components: usize, bits: i64, scale: bool) -> Result<Vec<u8>> {
+    let samples_per_row = width as usize * components;
+    let row_bytes = match bits {
+        1 | 2 | 4 | 8 | 16 => (samples_per_row * bits as usize).div_ceil(8),
+        _ => return Err(Error::Syntax(format!("Invalid BitsPerComponent {}", bits))),
+    };
+    let height = height as usize;
+    if data.len() < row_bytes * height {
+        return Err(Error::Syntax("Image data is shorter than its dimensions".to_string()));
+    }
+    if bits == 8 {
+        return Ok(data[..row_bytes * height].to_vec());
+    }
+
+    let mut pixels = Vec::with_capacity(samples_per_row * height);
+    for row in data.chunks(row_bytes).take(height) {
+        match bits {
+            16 => pixels.extend(row.chunks(2).map(|sample| sample[0])),
+            _ => {
+                let bits = bits as usize;
+                let max = (1 << bits) - 1;
+                for index in 0..samples_per_row {
+                    let bit = index * bits;
+                    let value = (row[bit / 8] >> (8 - bits - bit % 8)) & max;
+                    if scale {
+                        pixels.push((u32::from(value) * 255 / u32::from(max)) as u8);
+                    } else {
+                        pixels.push(value);
+                    }
+                }
+            }
+        }
+    }
+    Ok(pixels)
+}
+
+#[cfg(feature = "embed_image")]
+fn decode_jpeg(data: &[u8]) -> Result<DecodedImage> {
+    let mut decoder = jpeg_decoder::Decoder::new(data);
+    let mut pixels = decoder
+        .decode()
+        .map_err(|err| Error::Syntax(format!("Invalid JPEG data: {}", err)))?;
+    let info = decoder
+        .info()
+        .ok_or_else(|| Error::Syntax("Invalid JPEG data".to_string()))?;
+    let format = match info.pixel_format {
+        jpeg_decoder::PixelFormat::L8 => PixelFormat::Gray,
+        jpeg_decoder::PixelFormat::RGB24 => PixelFormat::Rgb,
+        jpeg_decoder::PixelFormat::CMYK32 => PixelFormat::Cmyk,
+    };
+    if jpeg::read_info(data).is_some_and(|info| info.is_inverted_cmyk()) {
+        // The decoder undoes the Adobe inversion, restore the samples as stored.
+        for sample in &mut pixels {
+            *sample = 255 - *sample;
+        }
+    }
+    Ok(DecodedImage {
+        width: u32::from(info.width),
+        height: u32::from(info.height),
+        format,
+        pixels,
+    })
+}
+
+/// Settings for [`Document::optimize_images`].
+#[cfg(feature = "embed_image")]
+#[derive(Debug, Clone)]
+pub struct ImageOptimizationOptions {
+    /// Downsample images whose effective resolution exceeds this many pixels per inch.
+    pub target_dpi: Option<f64>,
+    /// Quality (1-100) used when encoding JPEG images.
+    pub jpeg_quality: u8,
+    /// Also convert losslessly compressed gray and color images to JPEG.
+    pub convert_lossless_to_jpeg: bool,
+}
+
+#[cfg(feature = "embed_image")]
+impl Default for ImageOptimizationOptions {
+    fn default() -> Self {
+        ImageOptimizationOptions {
+            target_dpi: Some(150.0),
+            jpeg_quality: 75,
+            convert_lossless_to_jpeg: false,
+        }
+    }
+}
+
+/// Result of optimizing a single image.
+#[cfg(feature = "embed_image")]
+#[derive(Debug, Clone)]
+pub struct OptimizedImage {
+    pub id: ObjectId,
+    /// Stream length before optimization.
+    pub original_size: usize,
+    /// Stream length after optimization.
+    pub optimized_size: usize,
+    pub original_dimensions: (u32, u32),
+    pub dimensions: (u32, u32),
+}
+
+#[cfg(feature = "embed_image")]
+impl Document {
+    /// Downsample and recompress the images painted on the pages of the document.
+    ///
+    /// The required resolution of an image is derived from the largest size at which it is painted, so an image
+    /// used at several scales keeps enough pixels for the largest one. Soft masks are resampled along with their
+    /// images. Gray and RGB images are handled; images are only replaced when the result is smaller.
+    pub fn optimize_images(&mut self, options: &ImageOptimizationOptions) -> Result<Vec<OptimizedImage>> {
+        // Largest rendered width and height in points of each image.
+        let mut rendered_sizes: BTreeMap<ObjectId, (f64, f64)> = BTreeMap::new();
+        for page_id in self.page_iter() {
+            let placements = match self.image_placements(page_id) {
+                Ok(placements) => placements,
+                Err(err) => {
+                    warn!("Could not analyze images of page {:?}: {}", page_id, err);
+                    continue;
+                }
+            };
+            for placement in placements {
+                let (width, height) = placement.rendered_size();
+                let size = rendered_sizes.entry(placement.image_id).or_insert((0.0, 0.0));
+                *size = (size.0.max(width), size.1.max(height));
+            }
+        }
+
+        let mut report = Vec::new();
+        for (image_id, (rendered_width, rendered_height)) in rendered_sizes {
+            match self.optimize_image(image_id, rendered_width, rendered_height, options) {
+                Ok(Some(optimized)) => report.push(optimized),
+                Ok(None) => {}
+                Err(err) => warn!("Could not optimize image {:?}: {}", image_id, err),
+            }
+        }
+        Ok(report)
+    }
+
+    fn optimize_image(
+        &mut self, image_id: ObjectId, rendered_width: f64, rendered_height: f64, options: &ImageOptimizationOptions,
+    ) -> Result<Option<OptimizedImage>> {
+        let stream = self.get_object(image_id).and_then(Object::as_stream)?;
+        if stream.dict.get(b"ImageMask").and_then(Object::as_bool).unwrap_or(false)
+            || stream.dict.get(b"Mask").and_then(Object::as_reference).is_ok()
+        {
+            // Stencil masks would have to be resampled as well.
+            return Ok(None);
+        }
+        if !self.has_device_colors(stream) {
+            // Keep palettes and spot colors.
+            return Ok(None);
+        }
+        let is_jpeg = decode_image_filters(stream)?.1.as_deref() == Some("DCTDecode");
+        // Lossy compression would change the colors matched by a color key mask.
+        let has_color_key = stream.dict.has(b"Mask");
+        let original_size = stream.content.len();
+        let smask_id = stream.dict.get(b"SMask").and_then(Object::as_reference).ok();
+
+        let image = self.decode_image(image_id)?;
+        if image.format == PixelFormat::Cmyk {
+            return Ok(None);
+        }
+        let original_dimensions = (image.width, image.height);
+        let dimensions = match options.target_dpi {
+            Some(dpi) => {
+                let factor = (rendered_width / 72.0 * dpi / f64::from(image.width))
+                    .max(rendered_height / 72.0 * dpi / f64::from(image.height));
+                if factor < 1.0 {
+                    (
+                        ((f64::from(image.width) * factor).round() as u32).max(1),
+                        ((f64::from(image.height) * factor).round() as u32).max(1),
+                    )
+                } else {
+                    original_dimensions
+                }
+            }
+            None => original_dimensions,
+        };
+        let use_jpeg = !has_color_key && (is_jpeg || options.convert_lossless_to_jpeg);
+        if dimensions == original_dimensions && (is_jpeg || !use_jpeg) {
+            return Ok(None);
+        }
+
+        let pixels = resample(&image, dimensions)?;
+        let mut new_stream = stream.clone();
+        new_stream.dict.set("Width", dimensions.0);
+        new_stream.dict.set("Height", dimensions.1);
+        new_stream.dict.set("BitsPerComponent", 8);
+        if use_jpeg {
+            new_stream.dict.remove(b"DecodeParms");
+            new_stream.dict.set("Filter", "DCTDecode");
+            new_stream.set_content(encode_jpeg(&pixels, dimensions, image.format, options.jpeg_quality)?);
+        } else {
+            new_stream.set_plain_content(pixels);
+            new_stream.compress()?;
+        }
+        let optimized_size = new_stream.content.len();
+        if optimized_size >= original_size {
+            return Ok(None);
+        }
+
+        if let Some(smask_id) = smask_id.filter(|_| dimensions != original_dimensions) {
+            let mask = self.decode_image(smask_id)?;
+            if (mask.width, mask.height) != dimensions {
+                let mut mask_stream = self.get_object(smask_id).and_then(Object::as_stream)?.clone();
+                mask_stream.dict.set("Width", dimensions.0);
+                mask_stream.dict.set("Height", dimensions.1);
+                mask_stream.dict.set("BitsPerComponent", 8);
+                mask_stream.set_plain_content(resample(&mask, dimensions)?);
+                mask_stream.compress()?;
+                self.objects.insert(smask_id, Object::Stream(mask_stream));
+            }
+        }
+        self.objects.insert(image_id, Object::Stream(new_stream));
+
+        Ok(Some(OptimizedImage {
+            id: image_id,
+            original_size,
+            optimized_size,
+            original_dimensions,
+            dimensions,
+        }))
+    }
+}
+
+#[cfg(feature = "embed_image")]
+impl ExtractedImage {
+    /// Write the pixels of the image as a PNG file, converting CMYK colors to RGB.
+    pub fn write_png<W: std::io::Write>(&self, writer: W) -> Result<()> {
+        use image::codecs::png::PngEncoder;
+        use image::ColorType;
+
+        let format = match self.format {
+            ImageDataFormat::Pixels(format) => format,
+            ImageDataFormat::Jpeg | ImageDataFormat::Jp2 => {
+                return Err(Error::type_mismatch("pixels", "encoded image data"))
+            }
+        };
+        let rgb;
+        let (data, color_type) = match format {
+            PixelFormat::Gray => (&self.data, ColorType::L8),
+            PixelFormat::GrayAlpha => (&self.data, ColorType::La8),
+            PixelFormat::Rgb => (&self.data, ColorType::Rgb8),
+            PixelFormat::Rgba => (&self.data, ColorType::Rgba8),
+            PixelFormat::Cmyk => {
+                rgb = cmyk_to_rgb(&self.data);
+                (&rgb, ColorType::Rgb8)
+            }
+        };
+        PngEncoder::new(writer).encode(data, self.width, self.height, color_type)?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "embed_image")]
+fn resample(image: &DecodedImage, dimensions: (u32, u32)) -> Result<Vec<u8>> {
+    use image::imageops::{resize, FilterType};
+    use image::{ImageBuffer, Luma, LumaA, Rgb, Rgba};
+
+    if (image.width, image.height) == dimensions {
+        return Ok(image.pixels.clone());
+    }
+    let invalid = || Error::Syntax("Image data does not match its dimensions".to_string());
+    let (width, height) = dimensions;
+    match image.format {
+        PixelFormat::Gray => {
+            let buffer = ImageBuffer::<Luma<u8>, _>::from_raw(image.width, image.height, image.pixels.as_slice())
+                .ok_or_else(invalid)?;
+            Ok(resize(&buffer, width, height, FilterType::Triangle).into_raw())
+        }
+        PixelFormat::Rgb => {
+            let buffer = ImageBuffer::<Rgb<u8>, _>::from_raw(image.width, image.height, image.pixels.as_slice())
+                .ok_or_else(invalid)?;
+            Ok(resize(&buffer, width, height, FilterType::Triangle).into_raw())
+        }
+        PixelFormat::GrayAlpha => {
+            let buffer = ImageBuffer::<LumaA<u8>, _>::from_raw(image.width, image.height, image.pixels.as_slice())
+                .ok_or_else(invalid)?;
+            Ok(resize(&buffer, width, height, FilterType::Triangle).into_raw())
+        }
+        PixelFormat::Rgba => {
+            let buffer = ImageBuffer::<Rgba<u8>, _>::from_raw(image.width, image.height, image.pixels.as_slice())
+                .ok_or_else(invalid)?;
+            Ok(resize(&buffer, width, height, FilterType::Triangle).into_raw())
+        }
+        PixelFormat::Cmyk => Err(Error::Unimplemented("resampling CMYK images")),
+    }
+}
+
+#[cfg(feature = "embed_image")]
+fn encode_jpeg(pixels: &[u8], dimensions: (u32, u32), format: PixelFormat, quality: u8) -> Result<Vec<u8>> {
+    use image::codecs::jpeg::JpegEncoder;
+    use image::ColorType;
+
+    let color_type = match format {
+        PixelFormat::Gray => ColorType::L8,
+        PixelFormat::Rgb => ColorType::Rgb8,
+        PixelFormat::Cmyk => return Err(Error::Unimplemented("encoding CMYK JPEG images")),
+        PixelFormat::GrayAlpha | PixelFormat::Rgba => {
+            return Err(Error::Unimplemented("encoding JPEG images with alpha"))
+        }
+    };
+    let mut output = Vec::new();
+    JpegEncoder::new_with_quality(&mut output, quality.clamp(1, 100)).encode(
+        pixels,
+        dimensions.0,
+        dimensions.1,
+        color_type,
+    )?;
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Stream;
+
+    /// A single page document painting image `(1, 0)` through the given content.
+    pub fn image_document(image: Stream, content: &str) -> Document {
+        let mut doc = Document::with_version("1.5");
+        let image_id = doc.add_object(image);
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content.as_bytes().to_vec()));
+        let pages_id = doc.new_object_id();
+        let page_id = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "Contents" => content_id,
+            "Resources" => dictionary! {
+                "XObject" => dictionary! { "Im1" => image_id },
+            },
+        });
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => vec![page_id.into()],
+                "Count" => 1,
+                "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        doc.trailer.set("Root", catalog_id);
+        doc
+    }
+
+    fn rgb_image(width: u32, height: u32) -> Stream {
+        let pixels = (0..width * height * 3).map(|i| (i % 251) as u8).collect();
+        Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Image",
+                "Width" => width,
+                "Height" => height,
+                "ColorSpace" => "DeviceRGB",
+                "BitsPerComponent" => 8,
+            },
+            pixels,
+        )
+    }
+
+    #[test]
+    fn placements_follow_transformations() {
+        let doc = image_document(
+            rgb_image(4, 2),
+            "q 2 0 0 2 10 10 cm q 100 0 0 50 0 0 cm /Im1 Do Q Q /Im1 Do",
+        );
+        let page_id = doc.page_iter().next().unwrap();
+        let placements = doc.image_placements(page_id).unwrap();
+        assert_eq!(placements.len(), 2);
+        assert_eq!(placements[0].rendered_size(), (200.0, 100.0));
+        assert_eq!(placements[0].ctm[4..], [10.0, 10.0]);
+        assert_eq!(placements[1].rendered_size(), (1.0, 1.0));
+        assert_eq!(placements[0].effective_dpi(4, 2), (1.44, 1.44));
+    }
+
+    #[test]
+    fn placements_in_patterns_and_appearances() {
+        let mut doc = image_document(rgb_image(4, 2), "2 0 0 2 0 0 cm /Pattern cs /P1 scn 0 0 100 100 re f");
+        let page_id = doc.page_iter().next().unwrap();
+        let pattern_id = doc.add_object(Stream::new(
+            dictionary! {
+                "PatternType" => 1,
+                "PaintType" => 1,
+                "TilingType" => 1,
+                "BBox" => vec![0.into(), 0.into(), 10.into(), 10.into()],
+                "XStep" => 10,
+                "YStep" => 10,
+                "Matrix" => vec![3.into(), 0.into(), 0.into(), 3.into(), 0.into(), 0.into()],
+                "Resources" => dictionary! { "XObject" => dictionary! { "Im1" => (1, 0) } },
+            },
+            b"q 10 0 0 10 0 0 cm /Im1 Do Q".to_vec(),
+        ));
+        let resources = doc.get_object_mut(page_id).unwrap().as_dict_mut().unwrap();
+        let resources = resources.get_mut(b"Resources").and_then(Object::as_dict_mut).unwrap();
+        resources.set("Pattern", dictionary! { "P1" => pattern_id });
+        let appearance_id = doc.add_object(Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Form",
+                "BBox" => vec![0.into(), 0.into(), 20.into(), 10.into()],
+            },
+            b"q 20 0 0 10 0 0 cm /Im1 Do Q".to_vec(),
+        ));
+        doc.add_annotation(
+            page_id,
+            dictionary! {
+                "Subtype" => "Square",
+                "Rect" => vec![100.into(), 100.into(), 300.into(), 200.into()],
+                "AP" => dictionary! { "N" => appearance_id },
+            },
+        )
+        .unwrap();
+
+        let placements = doc.image_placements(page_id).unwrap();
+        assert_eq!(placements.len(), 2);
+        // The pattern space is that of the page, whatever the current matrix when the pattern is selected.
+        assert_eq!(placements[0].rendered_size(), (30.0, 30.0));
+        // The appearance is scaled from its bounding box to the rectangle of the annotation.
+        assert_eq!(placements[1].rendered_size(), (200.0, 100.0));
+        assert_eq!(placements[1].ctm[4..], [100.0, 100.0]);
+    }
+
+    #[test]
+    fn decode_packed_samples() {
+        let mut image = rgb_image(3, 2);
+        image.dict.set("ColorSpace", "DeviceGray");
+        image.dict.set("BitsPerComponent", 1);
+        image.set_content(vec![0b1010_0000, 0b0100_0000]);
+        let doc = image_document(image, "");
+        let decoded = doc.decode_image((1, 0)).unwrap();
+        assert_eq!(decoded.format, PixelFormat::Gray);
+        assert_eq!(decoded.pixels, vec![255, 0, 255, 0, 255, 0]);
+    }
+
+    #[test]
+    fn decode_indexed_and_separation_images() {
+        let mut image = rgb_image(2, 1);
+        let palette = Object::string_literal(vec![255, 0, 0, 0, 0, 255]);
+        image.dict.set(
+            "ColorSpace",
+            vec!["Indexed".into(), "DeviceRGB".into(), 1.into(), palette],
+        );
+        image.set_content(vec![1, 0]);
+        let doc = image_document(image, "");
+        let decoded = doc.decode_image((1, 0)).unwrap();
+        assert_eq!(decoded.format, PixelFormat::Rgb);
+        assert_eq!(decoded.pixels, vec![0, 0, 255, 255, 0, 0]);
+
+        let mut image = rgb_image(2, 1);
+        let tint_transform = dictionary! {
+            "FunctionType" => 2,
+            "Domain" => vec![0.into(), 1.into()],
+            "C0" => vec![0.into(), 0.into(), 0.into(), 0.into()],
+            "C1" => vec![0.into(), 1.into(), 1.into(), 0.into()],
+            "N" => 1,
+        };
+        let color_space = vec![
+            "Separation".into(),
+            "Red".into(),
+            "DeviceCMYK".into(),
+            tint_transform.into(),
+        ];
+        image.dict.set("ColorSpace", color_space);
+        image.set_content(vec![0, 255]);
+        let doc = image_document(image, "");
+        let options = ImageExtractionOptions {
+            decode: true,
+            ..Default::default()
+        };
+        let extracted = doc.extract_image((1, 0), &options).unwrap();
+        assert_eq!(extracted.format, ImageDataFormat::Pixels(PixelFormat::Rgb));
+        assert_eq!(extracted.data, vec![255, 255, 255, 255, 0, 0]);
+    }
+
+    #[test]
+    fn combine_image_with_masks() {
+        let mut doc = image_document(rgb_image(2, 2), "0 0 1 rg /Im1 Do");
+        let smask_id = doc.add_object(Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Image",
+                "Width" => 1,
+                "Height" => 1,
+                "ColorSpace" => "DeviceGray",
+                "BitsPerComponent" => 8,
+            },
+            vec![128],
+        ));
+        let image = doc.get_object_mut((1, 0)).and_then(Object::as_stream_mut).unwrap();
+        image.dict.set("SMask", smask_id);
+        let options = ImageExtractionOptions {
+            apply_mask: true,
+            ..Default::default()
+        };
+        let extracted = doc.extract_image((1, 0), &options).unwrap();
+        assert_eq!(extracted.format, ImageDataFormat::Pixels(PixelFormat::Rgba));
+        // The 1 by 1 soft mask is scaled to the 2 by 2 image.
+        assert_eq!(&extracted.data[..8], &[0, 1, 2, 128, 3, 4, 5, 128]);
+
+        let image = doc.get_object_mut((1, 0)).and_then(Object::as_stream_mut).unwrap();
+        image.dict = dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Image",
+            "Width" => 2,
+            "Height" => 1,
+            "ImageMask" => true,
+        };
+        image.set_content(vec![0b0100_0000]);
+        let page_id = doc.page_iter().next().unwrap();
+        let extracted = doc.extract_images(page_id, &options).unwrap();
+        assert_eq!(extracted[0].data, vec![0, 0, 255, 255, 0, 0, 255, 0]);
+    }
+
+    #[test]
+    fn resize_masks() {
+        let image = |width, height, format, pixels| DecodedImage {
+            width,
+            height,
+            format,
+            pixels,
+        };
+        let mask = |width, height, pixels| image(width, height, PixelFormat::Gray, pixels);
+        assert_eq!(
+            resize_nearest(&mask(1, 2, vec![1, 2]), (2, 2)).unwrap(),
+            vec![1, 1, 2, 2]
+        );
+        assert!(resize_nearest(&mask(2, 2, vec![1, 2]), (2, 2)).is_err());
+        assert!(resize_nearest(&mask(0, 2, vec![]), (2, 2)).is_err());
+        assert!(resize_nearest(&mask(1, 1, vec![1]), (0, 2)).is_err());
+        let rgba = image(1, 1, PixelFormat::Rgba, vec![1; 4]);
+        assert!(resize_nearest(&rgba, (u32::MAX, u32::MAX)).is_err());
+
+        // Without a mask, every pixel is opaque.
+        let doc = Document::with_version("1.5");
+        let stream = Stream::new(Dictionary::new(), vec![]);
+        assert_eq!(doc.image_alpha(&stream, &mask(2, 1, vec![1, 2])).unwrap().0, vec![255, 255]);
+        assert!(doc.image_alpha(&stream, &mask(u32::MAX, u32::MAX, vec![1])).is_err());
+    }
+
+    #[test]
+    fn pass_jpeg_2000_through() {
+        let mut doc = image_document(rgb_image(1, 1), "/Im1 Do");
+        let codestream = crate::filters::jpx::tests::codestream(30, 20);
+        let image_id = doc.add_jp2_image(codestream.clone()).unwrap();
+        let page_id = doc.page_iter().next().unwrap();
+        doc.add_xobject(page_id, "Im1", image_id).unwrap();
+
+        let images = doc.extract_images(page_id, &ImageExtractionOptions::default()).unwrap();
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].format, ImageDataFormat::Jp2);
+        assert_eq!((images[0].width, images[0].height), (30, 20));
+        assert_eq!(images[0].data, codestream);
+        let stream = doc.get_object(image_id).and_then(Object::as_stream).unwrap();
+        assert!(!stream.dict.has(b"ColorSpace"));
+    }
+
+    #[test]
+    fn deduplicate_identical_images() {
+        let mut doc = image_document(rgb_image(2, 2), "/Im1 Do /Im2 Do /Im3 Do");
+        let page_id = doc.page_iter().next().unwrap();
+        let mask = Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Image",
+                "Width" => 2,
+                "Height" => 2,
+                "ColorSpace" => "DeviceGray",
+                "BitsPerComponent" => 8,
+            },
+            vec![0, 64, 128, 255],
+        );
+        let first_mask = doc.add_object(mask.clone());
+        let second_mask = doc.add_object(mask);
+        let mut copy = rgb_image(2, 2);
+        copy.dict.set("SMask", second_mask);
+        let copy_id = doc.add_object(copy);
+        let mut inverted = rgb_image(2, 2);
+        inverted.dict.set("SMask", first_mask);
+        inverted.dict.set(
+            "Decode",
+            vec![1.into(), 0.into(), 1.into(), 0.into(), 1.into(), 0.into()],
+        );
+        let inverted_id = doc.add_object(inverted);
+        doc.get_object_mut((1, 0))
+            .and_then(Object::as_stream_mut)
+            .unwrap()
+            .dict
+            .set("SMask", first_mask);
+        doc.add_xobject(page_id, "Im2", copy_id).unwrap();
+        doc.add_xobject(page_id, "Im3", inverted_id).unwrap();
+        // Resources that nothing refers to yet.
+        let unused_id = doc.add_object(dictionary! { "XObject" => dictionary! { "Im1" => copy_id } });
+
+        // The copy becomes identical once its soft mask is merged.
+        assert_eq!(doc.deduplicate_images(ImageCompare::EncodedBytes).unwrap(), 12 + 4);
+        let placements = doc.image_placements(page_id).unwrap();
+        let ids = placements.iter().map(|p| p.image_id).collect::<Vec<_>>();
+        assert_eq!(ids, vec![(1, 0), (1, 0), inverted_id]);
+        assert!(doc.get_object(second_mask).is_err());
+        assert!(doc.get_object(copy_id).is_err());
+        let unused = doc
+            .get_dictionary(unused_id)
+            .and_then(|dict| dict.get(b"XObject"))
+            .unwrap();
+        assert_eq!(
+            unused
+                .as_dict()
+                .unwrap()
+                .get(b"Im1")
+                .and_then(Object::as_reference)
+                .unwrap(),
+            (1, 0)
+        );
+    }
+
+    #[cfg(feature = "embed_image")]
+    #[test]
+    fn downsample_to_target_resolution() {
+        let mut doc = image_document(
+            rgb_image(400, 200),
+            "q 72 0 0 36 0 0 cm /Im1 Do Q q 144 0 0 72 0 0 cm /Im1 Do Q",
+        );
+        let options = ImageOptimizationOptions {
+            target_dpi: Some(100.0),
+            ..Default::default()
+        };
+        let report = doc.optimize_images(&options).unwrap();
+        assert_eq!(report.len(), 1);
+        // The larger placement of 2 by 1 inches needs 200 by 100 pixels.
+        assert_eq!(report[0].dimensions, (200, 100));
+        assert!(report[0].optimized_size < report[0].original_size);
+        let stream = doc.get_object((1, 0)).and_then(Object::as_stream).unwrap();
+        assert_eq!(stream.dict.get(b"Width").and_then(Object::as_i64).unwrap(), 200);
+    }
+
+    #[cfg(feature = "embed_image")]
+    #[test]
+    fn extract_inverted_cmyk_jpeg() {
+        let image = crate::xobject::image("assets/cmyk_adobe.jpg").unwrap();
+        let doc = image_document(image, "/Im1 Do");
+        let page_id = doc.page_iter().next().unwrap();
+
+        let images = doc.extract_images(page_id, &ImageExtractionOptions::default()).unwrap();
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].format, ImageDataFormat::Jpeg);
+
+        let options = ImageExtractionOptions {
+            decode: true,
+            ..Default::default()
+        };
+        let image = doc.extract_image((1, 0), &options).unwrap();
+        assert_eq!(image.format, ImageDataFormat::Pixels(PixelFormat::Rgb));
+        assert_eq!((image.width, image.height), (16, 8));
+        // The image has vertical bands of cyan, magenta, yellow and black, four pixels wide each.
+        let expected = [[0, 255, 255], [255, 0, 255], [255, 255, 0], [0, 0, 0]];
+        for (band, color) in expected.iter().enumerate() {
+            let offset = (3 * 16 + band * 4 + 1) * 3;
+            for (&value, &expected) in image.data[offset..offset + 3].iter().zip(color) {
+                assert!((i32::from(value) - expected).abs() < 16, "band {}", band);
+            }
+        }
+    }
+}
@@ -0,0 +1,126 @@
+//! The sinks objects are written to. With the `std` feature these are those of `std::io`; without it, a `Write`
+//! trait of the same shape, as `core2` and `embedded-io` have, implemented for vectors, and the error it returns.
+
+#[cfg(feature = "std")]
+pub use std::io::{Error, ErrorKind, Result, Write};
+
+#[cfg(not(feature = "std"))]
+pub use self::alloc_only::{Error, ErrorKind, Result, Write};
+
+#[cfg(not(feature = "std"))]
+mod alloc_only {
+    use alloc::vec::Vec;
+    use core::fmt;
+
+    /// The kinds of [`Error`], those of `std::io::ErrorKind` that writing returns.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[non_exhaustive]
+    pub enum ErrorKind {
+        /// The sink accepted no more bytes.
+        WriteZero,
+        InvalidData,
+        Other,
+    }
+
+    #[derive(Debug)]
+    pub struct Error {
+        kind: ErrorKind,
+        message: &'static str,
+    }
+
+    impl Error {
+        pub fn new(kind: ErrorKind, message: &'static str) -> Error {
+            Error { kind, message }
+        }
+
+        pub fn kind(&self) -> ErrorKind {
+            self.kind
+        }
+    }
+
+    impl fmt::Display for Error {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str(self.message)
+        }
+    }
+
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    /// A sink of bytes, as `std::io::Write` is.
+    pub trait Write {
+        fn write(&mut self, buffer: &[u8]) -> Result<usize>;
+
+        fn flush(&mut self) -> Result<()>;
+
+        fn write_all(&mut self, mut buffer: &[u8]) -> Result<()> {
+            while !buffer.is_empty() {
+                match self.write(buffer)? {
+                    0 => return Err(Error::new(ErrorKind::WriteZero, "failed to write whole buffer")),
+                    written => buffer = &buffer[written..],
+                }
+            }
+            Ok(())
+        }
+
+        /// Write formatted text, which `write!` calls.
+        fn write_fmt(&mut self, arguments: fmt::Arguments<'_>) -> Result<()> {
+            struct Adapter<'a, W: ?Sized> {
+                inner: &'a mut W,
+                error: Option<Error>,
+            }
+
+            impl<W: Write + ?Sized> fmt::Write for Adapter<'_, W> {
+                fn write_str(&mut self, text: &str) -> fmt::Result {
+                    self.inner.write_all(text.as_bytes()).map_err(|err| {
+                        self.error = Some(err);
+                        fmt::Error
+                    })
+                }
+            }
+
+            let mut adapter = Adapter {
+                inner: self,
+                error: None,
+            };
+            fmt::write(&mut adapter, arguments).map_err(|_| {
+                adapter
+                    .error
+                    .unwrap_or_else(|| Error::new(ErrorKind::Other, "formatter error"))
+            })
+        }
+    }
+
+    impl Write for Vec<u8> {
+        fn write(&mut self, buffer: &[u8]) -> Result<usize> {
+            self.extend_from_slice(buffer);
+            Ok(buffer.len())
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn write_all(&mut self, buffer: &[u8]) -> Result<()> {
+            self.extend_from_slice(buffer);
+            Ok(())
+        }
+    }
+
+    impl<W: Write + ?Sized> Write for &mut W {
+        fn write(&mut self, buffer: &[u8]) -> Result<usize> {
+            (**self).write(buffer)
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            (**self).flush()
+        }
+
+        fn write_all(&mut self, buffer: &[u8]) -> Result<()> {
+            (**self).write_all(buffer)
+        }
+
+        fn write_fmt(&mut self, arguments: fmt::Arguments<'_>) -> Result<()> {
+            (**self).write_fmt(arguments)
+        }
+    }
+}
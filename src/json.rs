@@ -0,0 +1,333 @@
+use crate::bookmarks::{OutlineFlags, OutlineItem};
+use crate::destinations::DestinationFit;
+use crate::xmp::pdf_date_to_xmp;
+use crate::content::{Content, Operation};
+use crate::{AttachmentLocation, ContentEdit, ContentPatch, Document, Object, ObjectId, PdfDate, Trapped};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
+impl Document {
+    /// The document outline as JSON: an array of items with their `title`, the `page` number and `fit` of their
+    /// destination, named destinations being resolved, their `color`, whether they are `bold`, `italic` and
+    /// `open`, and their `children`. The array is empty when there is no outline.
+    pub fn export_outline_json(&self) -> Value {
+        let page_numbers = self.page_numbers();
+        let items = self.outline().unwrap_or_default();
+        Value::Array(
+            items
+                .iter()
+                .map(|item| outline_item_json(item, &page_numbers))
+                .collect(),
+        )
+    }
+
+    /// A summary of the document as JSON: its `version`, `metadata`, the size of its `pages` in points, its
+    /// `page_labels` ranges, its `outline` as given by [`Document::export_outline_json`], its `attachments` and
+    /// its `signatures`. Dates are given in ISO 8601.
+    pub fn export_summary_json(&self) -> Value {
+        let metadata = self.metadata();
+        let date = |date: Option<PdfDate>| date.and_then(|date| pdf_date_to_xmp(Object::from(date).as_str().ok()?));
+        let trapped = metadata.trapped.map(|trapped| match trapped {
+            Trapped::True => "True",
+            Trapped::False => "False",
+            Trapped::Unknown => "Unknown",
+        });
+
+        let page_numbers = self.page_numbers();
+        let pages: Vec<Value> = self
+            .get_pages()
+            .into_iter()
+            .map(|(number, page_id)| {
+                let media_box = self.get_page_media_box(page_id);
+                json!({
+                    "number": number,
+                    "width": media_box.map(|media_box| media_box.width()),
+                    "height": media_box.map(|media_box| media_box.height()),
+                })
+            })
+            .collect();
+        let page_labels: Vec<Value> = self
+            .page_labels()
+            .into_iter()
+            .map(|range| {
+                json!({
+                    "first_page": range.first_page,
+                    "first_label": range.label(range.first_page),
+                    "prefix": range.prefix,
+                    "start": range.start,
+                    "style": range.style.map(|style| format!("{:?}", style)),
+                })
+            })
+            .collect();
+        let attachments: Vec<Value> = self
+            .attachments()
+            .into_iter()
+            .map(|attachment| {
+                let page = match attachment.location {
+                    AttachmentLocation::Annotation { page, .. } => page_numbers.get(&page).copied(),
+                    AttachmentLocation::EmbeddedFiles(_) => None,
+                };
+                json!({
+                    "filename": attachment.filename,
+                    "description": attachment.description,
+                    "mime_type": attachment.mime_type,
+                    "size": attachment.size,
+                    "created": attachment.created.and_then(|date| pdf_date_to_xmp(date.as_bytes())),
+                    "modified": attachment.modified.and_then(|date| pdf_date_to_xmp(date.as_bytes())),
+                    "relationship": attachment.relationship,
+                    "page": page,
+                })
+            })
+            .collect();
+        let signatures: Vec<Value> = self
+            .signatures()
+            .into_iter()
+            .map(|signature| {
+                json!({
+                    "name": signature.name,
+                    "sub_filter": signature.sub_filter,
+                    "document_timestamp": signature.document_timestamp,
+                    "signed": signature.modified.and_then(|date| pdf_date_to_xmp(date.as_bytes())),
+                    "byte_range_valid": signature.byte_range_valid,
+                    "covers_whole_file": signature.covers_whole_file,
+                })
+            })
+            .collect();
+
+        json!({
+            "version": self.version,
+            "metadata": {
+                "title": metadata.title,
+                "author": metadata.author,
+                "subject": metadata.subject,
+                "keywords": metadata.keywords,
+                "creator": metadata.creator,
+                "producer": metadata.producer,
+                "creation_date": date(metadata.creation_date),
+                "mod_date": date(metadata.mod_date),
+                "trapped": trapped,
+            },
+            "pages": pages,
+            "page_labels": page_labels,
+            "outline": self.export_outline_json(),
+            "attachments": attachments,
+            "signatures": signatures,
+        })
+    }
+
+    fn page_numbers(&self) -> BTreeMap<ObjectId, u32> {
+        self.get_pages().into_iter().map(|(number, id)| (id, number)).collect()
+    }
+}
+
+impl ContentPatch {
+    /// The patch as JSON: an array of edits with their `op`, `insert`, `delete` or `replace`, the index `at` of the
+    /// first operation they edit, the `count` of operations they remove and the `operations` they insert, each as
+    /// the text it is written as in a content stream, with bytes beyond ASCII as the characters of their code.
+    pub fn to_json(&self) -> Value {
+        let operations = |operations: &[Operation]| -> Vec<Value> {
+            operations
+                .iter()
+                .map(|operation| {
+                    let data = Content {
+                        operations: vec![operation.clone()],
+                    }
+                    .encode()
+                    .unwrap_or_default();
+                    let text: String = data.iter().map(|&byte| byte as char).collect();
+                    Value::String(text.trim_end_matches('\n').to_string())
+                })
+                .collect()
+        };
+        Value::Array(
+            self.edits
+                .iter()
+                .map(|edit| match edit {
+                    ContentEdit::Insert { at, operations: inserted } => {
+                        json!({ "op": "insert", "at": at, "operations": operations(inserted) })
+                    }
+                    ContentEdit::Delete { at, count } => json!({ "op": "delete", "at": at, "count": count }),
+                    ContentEdit::Replace {
+                        at,
+                        count,
+                        operations: inserted,
+                    } => json!({ "op": "replace", "at": at, "count": count, "operations": operations(inserted) }),
+                })
+                .collect(),
+        )
+    }
+
+    /// The patch of JSON given by [`ContentPatch::to_json`].
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    pub fn from_json(value: &Value) -> crate::Result<ContentPatch> {
+        let invalid = |detail: &str| crate::Error::Syntax(format!("invalid content patch: {}", detail));
+        let index = |edit: &Value, key: &str| {
+            edit[key]
+                .as_u64()
+                .map(|index| index as usize)
+                .ok_or_else(|| invalid(&format!("no {}", key)))
+        };
+        let operations = |edit: &Value| -> crate::Result<Vec<Operation>> {
+            let texts = edit["operations"].as_array().ok_or_else(|| invalid("no operations"))?;
+            texts
+                .iter()
+                .map(|text| {
+                    let text = text.as_str().ok_or_else(|| invalid("operation not a string"))?;
+                    let data = text
+                        .chars()
+                        .map(|c| u8::try_from(u32::from(c)).map_err(|_| invalid("character beyond a byte")))
+                        .collect::<crate::Result<Vec<u8>>>()?;
+                    let decoded = Content::decode_with(&data, &crate::ContentDecodeOptions { strict: true })?;
+                    let mut operations = decoded.content.operations;
+                    match operations.len() {
+                        1 => Ok(operations.remove(0)),
+                        _ => Err(invalid("not one operation")),
+                    }
+                })
+                .collect()
+        };
+        let edits = value.as_array().ok_or_else(|| invalid("not an array"))?;
+        let edits = edits
+            .iter()
+            .map(|edit| {
+                let at = index(edit, "at")?;
+                Ok(match edit["op"].as_str() {
+                    Some("insert") => ContentEdit::Insert {
+                        at,
+                        operations: operations(edit)?,
+                    },
+                    Some("delete") => ContentEdit::Delete {
+                        at,
+                        count: index(edit, "count")?,
+                    },
+                    Some("replace") => ContentEdit::Replace {
+                        at,
+                        count: index(edit, "count")?,
+                        operations: operations(edit)?,
+                    },
+                    _ => return Err(invalid("unknown op")),
+                })
+            })
+            .collect::<crate::Result<Vec<ContentEdit>>>()?;
+        Ok(ContentPatch { edits })
+    }
+}
+
+fn outline_item_json(item: &OutlineItem, page_numbers: &BTreeMap<ObjectId, u32>) -> Value {
+    let page = item
+        .destination
+        .and_then(|destination| page_numbers.get(&destination.page).copied());
+    let fit = item.destination.map(|destination| fit_json(destination.fit));
+    let children: Vec<Value> = item
+        .children
+        .iter()
+        .map(|child| outline_item_json(child, page_numbers))
+        .collect();
+    json!({
+        "title": item.title,
+        "page": page,
+        "fit": fit,
+        "color": item.color.to_rgb(),
+        "bold": item.flags.contains(OutlineFlags::BOLD),
+        "italic": item.flags.contains(OutlineFlags::ITALIC),
+        "open": item.open,
+        "children": children,
+    })
+}
+
+fn fit_json(fit: DestinationFit) -> Value {
+    match fit {
+        DestinationFit::Xyz { left, top, zoom } => json!({ "type": "XYZ", "left": left, "top": top, "zoom": zoom }),
+        DestinationFit::Fit => json!({ "type": "Fit" }),
+        DestinationFit::FitH { top } => json!({ "type": "FitH", "top": top }),
+        DestinationFit::FitV { left } => json!({ "type": "FitV", "left": left }),
+        DestinationFit::FitR(rect) => json!({
+            "type": "FitR",
+            "left": rect.llx,
+            "bottom": rect.lly,
+            "right": rect.urx,
+            "top": rect.ury,
+        }),
+        DestinationFit::FitB => json!({ "type": "FitB" }),
+        DestinationFit::FitBH { top } => json!({ "type": "FitBH", "top": top }),
+        DestinationFit::FitBV { left } => json!({ "type": "FitBV", "left": left }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::creator::tests::create_document;
+    use crate::Destination;
+
+    #[test]
+    fn export_outline_and_summary() {
+        let mut doc = create_document();
+        let page_id = doc.page_iter().next().unwrap();
+        let mut chapter = OutlineItem::new(
+            "Chapter".to_string(),
+            Some(Destination::new(
+                page_id,
+                DestinationFit::Xyz {
+                    left: Some(0.0),
+                    top: Some(800.0),
+                    zoom: None,
+                },
+            )),
+        );
+        chapter.flags = OutlineFlags::BOLD;
+        chapter.children.push(OutlineItem::new(
+            "Introduction".to_string(),
+            Some(Destination::new(page_id, DestinationFit::Fit)),
+        ));
+        doc.set_outline_with(
+            vec![chapter],
+            &crate::OutlineOptions {
+                named_destinations: true,
+            },
+        )
+        .unwrap();
+
+        let outline = doc.export_outline_json();
+        assert_eq!(outline[0]["title"], "Chapter");
+        assert_eq!(outline[0]["page"], 1);
+        assert_eq!(outline[0]["bold"], true);
+        assert_eq!(outline[0]["fit"]["type"], "XYZ");
+        assert_eq!(outline[0]["fit"]["zoom"], Value::Null);
+        assert_eq!(outline[0]["children"][0]["fit"], json!({ "type": "Fit" }));
+
+        let summary = doc.export_summary_json();
+        assert_eq!(summary["version"], "1.5");
+        assert_eq!(summary["metadata"]["title"], "Create PDF document example");
+        assert_eq!(
+            summary["pages"],
+            json!([{ "number": 1, "width": 595.0, "height": 842.0 }])
+        );
+        assert_eq!(summary["outline"], outline);
+        assert_eq!(summary["attachments"], json!([]));
+    }
+
+    #[test]
+    fn content_patch_to_and_from_json() {
+        let base = Content::decode(b"BT /F1 12 Tf 72 700 Td (Hello) Tj ET 0 0 m 10 10 l S").unwrap();
+        let derived = Content::decode(b"BT /F1 12 Tf 72 700 Td (Caf\\351) Tj ET 0 0 m S 1 0 0 rg").unwrap();
+        let patch = Content::diff(&base, &derived);
+        let value = patch.to_json();
+        assert_eq!(
+            value,
+            json!([
+                { "op": "replace", "at": 3, "count": 1, "operations": ["(Caf\u{e9}) Tj"] },
+                { "op": "delete", "at": 6, "count": 1 },
+                { "op": "insert", "at": 8, "operations": ["1 0 0 rg"] },
+            ])
+        );
+
+        let read = ContentPatch::from_json(&value).unwrap();
+        assert_eq!(read.apply(&base).encode().unwrap(), derived.encode().unwrap());
+        assert!(ContentPatch::from_json(&json!([{ "op": "insert", "at": 0, "operations": ["q Q"] }])).is_err());
+        let wide = json!([{ "op": "insert", "at": 0, "operations": ["(\u{100}) Tj"] }]);
+        assert!(ContentPatch::from_json(&wide).is_err());
+        assert!(ContentPatch::from_json(&json!([{ "op": "move", "at": 0 }])).is_err());
+    }
+}
@@ -0,0 +1,553 @@
+use crate::encodings::decode_text_string;
+use crate::{Dictionary, Document, Error, Object, ObjectId, Result};
+
+/// An optional content group, or layer, listed in the `/OCProperties` of the catalog.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerInfo {
+    pub id: ObjectId,
+    pub name: String,
+    /// Whether the default configuration lists the group in `/ON`.
+    pub on: bool,
+    /// Whether the default configuration lists the group in `/OFF`.
+    pub off: bool,
+    /// Whether the group is visible by default, from `/ON`, `/OFF` and the `/BaseState` of the default
+    /// configuration.
+    pub visible: bool,
+    /// `/Intent`, such as `View` or `Design`.
+    pub intent: Vec<String>,
+    /// The categories of the `/Usage` dictionary, such as `Print` or `Zoom`.
+    pub usage: Vec<String>,
+}
+
+/// A layer given by its name, or by the id of its optional content group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayerRef<'a> {
+    Name(&'a str),
+    Id(ObjectId),
+}
+
+impl<'a> From<&'a str> for LayerRef<'a> {
+    fn from(name: &'a str) -> Self {
+        LayerRef::Name(name)
+    }
+}
+
+impl From<ObjectId> for LayerRef<'_> {
+    fn from(id: ObjectId) -> Self {
+        LayerRef::Id(id)
+    }
+}
+
+/// What [`Document::remove_layer`] does with the content of the layer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemovalMode {
+    /// Remove the content of the layer, which is then never shown.
+    DeleteContent,
+    /// Keep the content of the layer, which is then always shown.
+    KeepContent,
+}
+
+impl Document {
+    /// The optional content groups of `/OCProperties`, with their state in the default configuration.
+    pub fn layers(&self) -> Vec<LayerInfo> {
+        let properties = match self.optional_content_properties() {
+            Some(properties) => properties,
+            None => return Vec::new(),
+        };
+        let config = properties.get_deref(b"D", self).and_then(Object::as_dict).ok();
+        let listed = |key: &[u8]| -> Vec<ObjectId> {
+            config
+                .and_then(|config| config.get_deref(key, self).and_then(Object::as_array).ok())
+                .map(|ids| ids.iter().filter_map(|id| id.as_reference().ok()).collect())
+                .unwrap_or_default()
+        };
+        let (on, off) = (listed(b"ON"), listed(b"OFF"));
+        let base_on = config
+            .and_then(|config| config.get(b"BaseState").and_then(Object::as_name).ok())
+            .is_none_or(|state| state != b"OFF");
+        let names = |object: Option<&Object>| -> Vec<String> {
+            match object.map(|object| self.dereference(object)) {
+                Some(Ok((_, Object::Name(name)))) => vec![String::from_utf8_lossy(name).into_owned()],
+                Some(Ok((_, Object::Array(names)))) => names
+                    .iter()
+                    .filter_map(|name| name.as_name_str().ok())
+                    .map(str::to_string)
+                    .collect(),
+                _ => Vec::new(),
+            }
+        };
+
+        self.optional_content_groups()
+            .into_iter()
+            .filter_map(|id| {
+                let group = self.get_dictionary(id).ok()?;
+                let usage = group
+                    .get_deref(b"Usage", self)
+                    .and_then(Object::as_dict)
+                    .map(|usage| {
+                        usage
+                            .iter()
+                            .map(|(key, _)| String::from_utf8_lossy(key).into_owned())
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                let (is_on, is_off) = (on.contains(&id), off.contains(&id));
+                Some(LayerInfo {
+                    id,
+                    name: group
+                        .get_deref(b"Name", self)
+                        .and_then(Object::as_str)
+                        .map(decode_text_string)
+                        .unwrap_or_default(),
+                    on: is_on,
+                    off: is_off,
+                    visible: is_on || (base_on && !is_off),
+                    intent: match group.get(b"Intent") {
+                        Ok(intent) => names(Some(intent)),
+                        Err(_) => vec!["View".to_string()],
+                    },
+                    usage,
+                })
+            })
+            .collect()
+    }
+
+    /// Show or hide a layer, or all the layers of a name, by default: the groups are moved to the `/ON` or `/OFF`
+    /// array of the default configuration.
+    pub fn set_layer_visibility<'a, L: Into<LayerRef<'a>>>(&mut self, layer: L, visible: bool) -> Result<()> {
+        let layer = layer.into();
+        let ids: Vec<ObjectId> = self
+            .layers()
+            .into_iter()
+            .filter(|info| match layer {
+                LayerRef::Name(name) => info.name == name,
+                LayerRef::Id(id) => info.id == id,
+            })
+            .map(|info| info.id)
+            .collect();
+        if ids.is_empty() {
+            return Err(Error::ObjectNotFound {
+                id: match layer {
+                    LayerRef::Id(id) => Some(id),
+                    LayerRef::Name(_) => None,
+                },
+                chain: Vec::new(),
+            });
+        }
+        let (add, remove) = if visible { ("ON", "OFF") } else { ("OFF", "ON") };
+        let config = self.default_configuration_mut()?;
+        if let Ok(Object::Array(listed)) = config.get_mut(remove.as_bytes()) {
+            listed.retain(|listed| !listed.as_reference().is_ok_and(|listed| ids.contains(&listed)));
+        }
+        let mut listed = match config.get(add.as_bytes()) {
+            Ok(Object::Array(listed)) => listed.clone(),
+            _ => Vec::new(),
+        };
+        for &id in &ids {
+            if !listed.iter().any(|listed| listed.as_reference().ok() == Some(id)) {
+                listed.push(id.into());
+            }
+        }
+        config.set(add, listed);
+        Ok(())
+    }
+
+    /// Remove an optional content group from `/OCProperties`: from its list of groups, and from the `/ON`,
+    /// `/OFF`, `/Order`, `/RBGroups`, `/Locked` and usage application arrays of its configurations.
+    ///
+    /// With [`RemovalMode::DeleteContent`], the marked content of pages and Form XObjects that belongs to the
+    /// group (`/OC` marked content), the Form and image XObjects painted there that belong to it, and the
+    /// annotations that belong to it are removed. With [`RemovalMode::KeepContent`], the marking is removed and
+    /// the content kept. Content belongs to the group through its `/OC` entry: the group itself, or a membership
+    /// dictionary whose only group it is and that has no visibility expression. Membership dictionaries that also
+    /// depend on other groups are left as they are. The objects that are no longer referenced are left for
+    /// [`Document::prune_objects`].
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    pub fn remove_layer(&mut self, id: ObjectId, mode: RemovalMode) -> Result<()> {
+        if !self.optional_content_groups().contains(&id) {
+            return Err(Error::not_found(id));
+        }
+
+        // Content streams of pages, and of Form XObjects with their resources.
+        let pages: Vec<ObjectId> = self.page_iter().collect();
+        for page_id in pages {
+            let (direct, inherited) = self.get_page_resources(page_id);
+            let mut resources: Vec<Dictionary> = direct.cloned().into_iter().collect();
+            resources.extend(inherited.iter().filter_map(|id| self.get_dictionary(*id).ok()).cloned());
+            let content = self.get_and_decode_page_content(page_id)?;
+            if let Some(content) = self.remove_layer_content(content, &resources, id, mode)? {
+                let content_id = self.add_object(crate::Stream::new(Dictionary::new(), content));
+                self.get_object_mut(page_id)?.as_dict_mut()?.set("Contents", content_id);
+            }
+        }
+        let forms: Vec<ObjectId> = self
+            .objects
+            .iter()
+            .filter(|(_, object)| {
+                object
+                    .as_stream()
+                    .is_ok_and(|stream| stream.dict.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Form"))
+            })
+            .map(|(&id, _)| id)
+            .collect();
+        for form_id in forms {
+            let stream = self.get_object(form_id).and_then(Object::as_stream)?;
+            let resources: Vec<Dictionary> = stream
+                .dict
+                .get_deref(b"Resources", self)
+                .and_then(Object::as_dict)
+                .cloned()
+                .into_iter()
+                .collect();
+            let mut stream = stream.clone();
+            stream.decompress();
+            let content = match stream.decode_content() {
+                Ok(content) => content,
+                Err(_) => continue,
+            };
+            if let Some(content) = self.remove_layer_content(content, &resources, id, mode)? {
+                stream.set_content(content);
+                self.objects.insert(form_id, Object::Stream(stream));
+            }
+        }
+
+        // Annotations, and XObjects that are kept, no longer belong to the group.
+        let targets: Vec<ObjectId> = self
+            .objects
+            .iter()
+            .filter(|(_, object)| {
+                let dict = match object {
+                    Object::Dictionary(dict) => dict,
+                    Object::Stream(stream) => &stream.dict,
+                    _ => return false,
+                };
+                dict.get(b"OC").is_ok_and(|oc| self.belongs_to_layer(oc, id))
+            })
+            .map(|(&id, _)| id)
+            .collect();
+        let mut annotations = Vec::new();
+        for target in targets {
+            let object = self.get_object_mut(target)?;
+            let dict = match object {
+                Object::Stream(stream) => &mut stream.dict,
+                Object::Dictionary(dict) => dict,
+                _ => continue,
+            };
+            if mode == RemovalMode::DeleteContent && dict.has(b"Rect") && dict.has(b"Subtype") {
+                annotations.push(target);
+            } else {
+                dict.remove(b"OC");
+            }
+        }
+        if !annotations.is_empty() {
+            self.remove_annotations(None, |annotation| {
+                annotation.id.is_some_and(|id| annotations.contains(&id))
+            });
+        }
+
+        let mut properties = self.optional_content_properties_mut()?.clone();
+        remove_reference(&mut properties, id);
+        // Configurations that are indirect objects are updated where they are.
+        let mut configurations: Vec<ObjectId> = properties
+            .get(b"D")
+            .and_then(Object::as_reference)
+            .into_iter()
+            .collect();
+        if let Ok(Object::Array(configs)) = properties.get(b"Configs") {
+            configurations.extend(configs.iter().filter_map(|config| config.as_reference().ok()));
+        }
+        *self.optional_content_properties_mut()? = properties;
+        for config in configurations {
+            if let Ok(config) = self.get_object_mut(config).and_then(Object::as_dict_mut) {
+                remove_reference(config, id);
+            }
+        }
+        Ok(())
+    }
+
+    fn optional_content_properties(&self) -> Option<&Dictionary> {
+        self.catalog()
+            .ok()?
+            .get_deref(b"OCProperties", self)
+            .and_then(Object::as_dict)
+            .ok()
+    }
+
+    fn optional_content_groups(&self) -> Vec<ObjectId> {
+        self.optional_content_properties()
+            .and_then(|properties| properties.get_deref(b"OCGs", self).and_then(Object::as_array).ok())
+            .map(|groups| groups.iter().filter_map(|group| group.as_reference().ok()).collect())
+            .unwrap_or_default()
+    }
+
+    fn optional_content_properties_mut(&mut self) -> Result<&mut Dictionary> {
+        let root_id = self.trailer.get(b"Root")?.as_reference()?;
+        match self.get_dictionary(root_id)?.get(b"OCProperties")? {
+            &Object::Reference(id) => self.get_object_mut(id)?.as_dict_mut(),
+            _ => self
+                .get_object_mut(root_id)?
+                .as_dict_mut()?
+                .get_mut(b"OCProperties")?
+                .as_dict_mut(),
+        }
+    }
+
+    /// The default configuration of `/OCProperties`, added if missing.
+    fn default_configuration_mut(&mut self) -> Result<&mut Dictionary> {
+        let properties = self.optional_content_properties_mut()?;
+        match properties.get(b"D") {
+            Ok(&Object::Reference(id)) => return self.get_object_mut(id)?.as_dict_mut(),
+            Ok(Object::Dictionary(_)) => {}
+            _ => properties.set("D", Dictionary::new()),
+        }
+        self.optional_content_properties_mut()?.get_mut(b"D")?.as_dict_mut()
+    }
+
+    /// Whether an `/OC` entry is the group, or a membership dictionary that only depends on the group.
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    fn belongs_to_layer(&self, oc: &Object, layer: ObjectId) -> bool {
+        let (id, oc) = match self.dereference(oc) {
+            Ok(resolved) => resolved,
+            Err(_) => return false,
+        };
+        if id == Some(layer) {
+            return true;
+        }
+        let membership = match oc.as_dict() {
+            Ok(membership) if membership.get(b"Type").and_then(Object::as_name).ok() == Some(b"OCMD") => membership,
+            _ => return false,
+        };
+        if membership.has(b"VE") {
+            return false;
+        }
+        let groups: Vec<Option<ObjectId>> = match membership.get(b"OCGs") {
+            Ok(Object::Array(groups)) => groups.iter().map(|group| group.as_reference().ok()).collect(),
+            Ok(group) => vec![group.as_reference().ok()],
+            Err(_) => Vec::new(),
+        };
+        !groups.is_empty() && groups.iter().all(|&group| group == Some(layer))
+    }
+
+    /// Remove the content of a layer from a content stream, or its marking, returning the encoded content if it
+    /// changed.
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    fn remove_layer_content(
+        &self, content: crate::content::Content<Vec<crate::content::Operation>>, resources: &[Dictionary],
+        layer: ObjectId, mode: RemovalMode,
+    ) -> Result<Option<Vec<u8>>> {
+        let resource = |category: &[u8], name: &[u8]| {
+            resources.iter().find_map(|resources| {
+                let entries = resources.get_deref(category, self).and_then(Object::as_dict).ok()?;
+                entries.get(name).ok()
+            })
+        };
+        let belongs = |operand: Option<&Object>| match operand {
+            Some(Object::Name(name)) => {
+                resource(b"Properties", name).is_some_and(|oc| self.belongs_to_layer(oc, layer))
+            }
+            Some(oc @ Object::Dictionary(_)) => self.belongs_to_layer(oc, layer),
+            _ => false,
+        };
+
+        let mut operations = Vec::with_capacity(content.operations.len());
+        // For each open marked-content sequence, whether it marks the layer.
+        let mut open: Vec<bool> = Vec::new();
+        let mut changed = false;
+        for operation in content.operations {
+            let hidden = open.iter().any(|&marks| marks) && mode == RemovalMode::DeleteContent;
+            match operation.operator.as_str() {
+                "BMC" => open.push(false),
+                "BDC" => {
+                    let marks = operation.operands.first().and_then(|tag| tag.as_name().ok()) == Some(b"OC")
+                        && belongs(operation.operands.get(1));
+                    open.push(marks);
+                    if marks {
+                        changed = true;
+                        continue;
+                    }
+                }
+                "EMC" => {
+                    let marks = open.pop() == Some(true);
+                    if marks {
+                        continue;
+                    }
+                }
+                "Do" if mode == RemovalMode::DeleteContent => {
+                    let painted = operation
+                        .operands
+                        .first()
+                        .and_then(|name| name.as_name().ok())
+                        .and_then(|name| resource(b"XObject", name))
+                        .and_then(|xobject| self.dereference(xobject).ok())
+                        .and_then(|(_, xobject)| xobject.as_stream().ok())
+                        .and_then(|xobject| xobject.dict.get(b"OC").ok());
+                    if painted.is_some_and(|oc| self.belongs_to_layer(oc, layer)) {
+                        changed = true;
+                        continue;
+                    }
+                }
+                _ => {}
+            }
+            if hidden {
+                changed = true;
+            } else {
+                operations.push(operation);
+            }
+        }
+        if !changed {
+            return Ok(None);
+        }
+        crate::content::Content { operations }.encode().map(Some)
+    }
+}
+
+/// Remove the references to an object from the arrays of a dictionary, at any depth.
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+fn remove_reference(dict: &mut Dictionary, id: ObjectId) {
+    for (_, value) in dict.iter_mut() {
+        remove_reference_from(value, id);
+    }
+}
+
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+fn remove_reference_from(object: &mut Object, id: ObjectId) {
+    match object {
+        Object::Array(items) => {
+            items.retain(|item| item.as_reference().ok() != Some(id));
+            for item in items {
+                remove_reference_from(item, id);
+            }
+        }
+        Object::Dictionary(dict) => remove_reference(dict, id),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::{Content, Operation};
+    use crate::creator::tests::create_document;
+    use crate::{Stream, StringFormat};
+
+    /// A page with text on a `Dimensions` layer, an image on a `Notes` layer kept visible through a membership
+    /// dictionary, and a free text annotation on `Dimensions`.
+    fn layered_document() -> (Document, ObjectId, ObjectId, ObjectId) {
+        let mut doc = create_document();
+        let page_id = doc.page_iter().next().unwrap();
+        let name = |name: &str| Object::String(name.as_bytes().to_vec(), StringFormat::Literal);
+        let dimensions = doc.add_object(dictionary! { "Type" => "OCG", "Name" => name("Dimensions") });
+        let notes = doc.add_object(dictionary! {
+            "Type" => "OCG",
+            "Name" => name("Notes"),
+            "Intent" => vec!["View".into(), "Design".into()],
+            "Usage" => dictionary! { "Print" => dictionary! { "PrintState" => "OFF" } },
+        });
+        let either = doc.add_object(dictionary! {
+            "Type" => "OCMD",
+            "OCGs" => vec![dimensions.into(), notes.into()],
+            "VE" => vec!["Or".into(), dimensions.into(), notes.into()],
+        });
+        let image = doc.add_object(Stream::new(
+            dictionary! { "Subtype" => "Image", "Width" => 1, "Height" => 1, "OC" => dimensions },
+            vec![0],
+        ));
+        let annotation = doc.add_object(dictionary! {
+            "Type" => "Annot",
+            "Subtype" => "FreeText",
+            "Rect" => vec![0.into(), 0.into(), 10.into(), 10.into()],
+            "OC" => dimensions,
+        });
+        let content = Content {
+            operations: vec![
+                Operation::new("BDC", vec!["OC".into(), "MC0".into()]),
+                Operation::new("BT", vec![]),
+                Operation::new("Tf", vec!["F1".into(), 12.into()]),
+                Operation::new("Tj", vec![Object::string_literal("12 mm")]),
+                Operation::new("ET", vec![]),
+                Operation::new("EMC", vec![]),
+                Operation::new("BDC", vec!["OC".into(), "MC1".into()]),
+                Operation::new("Do", vec!["Im1".into()]),
+                Operation::new("EMC", vec![]),
+            ],
+        };
+        let content_id = doc.add_object(Stream::new(Dictionary::new(), content.encode().unwrap()));
+        let page = doc.get_object_mut(page_id).unwrap().as_dict_mut().unwrap();
+        page.set("Contents", content_id);
+        page.set("Annots", vec![annotation.into()]);
+        page.set(
+            "Resources",
+            dictionary! {
+                "Font" => dictionary! { "F1" => dictionary! { "Type" => "Font", "BaseFont" => "Courier" } },
+                "Properties" => dictionary! { "MC0" => dimensions, "MC1" => either },
+                "XObject" => dictionary! { "Im1" => image },
+            },
+        );
+        let root_id = doc.trailer.get(b"Root").and_then(Object::as_reference).unwrap();
+        doc.get_object_mut(root_id).unwrap().as_dict_mut().unwrap().set(
+            "OCProperties",
+            dictionary! {
+                "OCGs" => vec![dimensions.into(), notes.into()],
+                "D" => dictionary! {
+                    "OFF" => vec![notes.into()],
+                    "Order" => vec![dimensions.into(), vec![name("Extra"), notes.into()].into()],
+                },
+            },
+        );
+        (doc, dimensions, notes, either)
+    }
+
+    #[test]
+    fn list_layers_and_set_visibility() {
+        let (mut doc, dimensions, notes, _) = layered_document();
+        let layers = doc.layers();
+        assert_eq!(layers.len(), 2);
+        assert_eq!((layers[0].id, layers[0].name.as_str()), (dimensions, "Dimensions"));
+        assert!(layers[0].visible && !layers[0].on && !layers[0].off);
+        assert_eq!(layers[0].intent, vec!["View"]);
+        assert!(!layers[1].visible && layers[1].off);
+        assert_eq!(layers[1].intent, vec!["View", "Design"]);
+        assert_eq!(layers[1].usage, vec!["Print"]);
+
+        doc.set_layer_visibility("Notes", true).unwrap();
+        doc.set_layer_visibility(dimensions, false).unwrap();
+        let layers = doc.layers();
+        assert!(!layers[0].visible && layers[0].off);
+        assert!(layers[1].visible && layers[1].on && !layers[1].off);
+        assert_eq!(doc.layers()[1].id, notes);
+        assert!(doc.set_layer_visibility("Missing", true).is_err());
+    }
+
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    #[test]
+    fn remove_layer_content() {
+        let (mut doc, dimensions, notes, either) = layered_document();
+        let page_id = doc.page_iter().next().unwrap();
+        let mut kept = doc.clone();
+        doc.remove_layer(dimensions, RemovalMode::DeleteContent).unwrap();
+        let content = doc.get_and_decode_page_content(page_id).unwrap();
+        let operators: Vec<&str> = content.operations.iter().map(|op| op.operator.as_str()).collect();
+        // The image is on the layer itself, inside content marked with a membership dictionary.
+        assert_eq!(operators, vec!["BDC", "EMC"]);
+        assert!(doc.annotations(page_id).is_empty());
+        let properties = doc.optional_content_properties().unwrap();
+        let groups = properties.get(b"OCGs").and_then(Object::as_array).unwrap();
+        assert_eq!(groups.len(), 1);
+        let order = properties.get_deref(b"D", &doc).and_then(Object::as_dict).unwrap();
+        assert_eq!(order.get(b"Order").and_then(Object::as_array).unwrap().len(), 1);
+        // The membership dictionary that also depends on another group is left intact.
+        let membership = doc.get_dictionary(either).unwrap();
+        assert_eq!(membership.get(b"VE").and_then(Object::as_array).unwrap().len(), 3);
+        assert_eq!(doc.layers()[0].id, notes);
+
+        kept.remove_layer(dimensions, RemovalMode::KeepContent).unwrap();
+        let content = kept.get_and_decode_page_content(page_id).unwrap();
+        let operators: Vec<&str> = content.operations.iter().map(|op| op.operator.as_str()).collect();
+        assert_eq!(operators, vec!["BT", "Tf", "Tj", "ET", "BDC", "Do", "EMC"]);
+        assert_eq!(kept.annotations(page_id).len(), 1);
+        assert!(!kept
+            .get_dictionary(kept.annotations(page_id)[0].id.unwrap())
+            .unwrap()
+            .has(b"OC"));
+    }
+}
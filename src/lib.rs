@@ -1,29 +1,175 @@
-#[macro_use]
-mod object;
-mod datetime;
-pub use crate::object::{Dictionary, Object, ObjectId, Stream, StringFormat};
+#![cfg_attr(not(feature = "std"), no_std)]
 
-mod document;
-mod object_stream;
-pub mod xref;
-pub use crate::document::Document;
+//! Without the default `std` feature, only the object model, content operations and the writer are built, with
+//! `alloc`, and the writer writes to the [`io::Write`] of this crate.
 
-mod bookmarks;
-pub use crate::bookmarks::Bookmark;
+extern crate alloc;
+
+/// Items that need the standard library.
+macro_rules! with_std {
+    ($($item:item)*) => {
+        $(
+            #[cfg(feature = "std")]
+            $item
+        )*
+    };
+}
+
+#[cfg(feature = "std")]
+#[macro_use]
+mod trace;
+#[macro_use]
+mod object;
+pub use crate::object::{Dictionary, DictionaryIter, DictionaryIterMut, Object, ObjectId, Stream, StringFormat};
+mod color;
+pub use crate::color::Color;
 pub mod content;
-mod creator;
-mod encodings;
 mod error;
-pub mod filters;
-#[cfg(not(feature = "nom_parser"))]
-#[cfg(feature = "pom_parser")]
-mod parser;
-#[cfg(feature = "nom_parser")]
-#[path = "nom_parser.rs"]
-mod parser;
-mod parser_aux;
-mod processor;
-mod reader;
+pub use error::{Error, ErrorContext, KeyPath, ParseWarning, ParseWarningKind, Phase, Result};
+pub mod io;
+mod names;
+pub use crate::names::KnownName;
+mod permissions;
+pub use crate::permissions::Permissions;
+pub use smallvec::SmallVec;
 pub mod writer;
-pub mod xobject;
-pub use error::{Error, Result};
+pub mod xref;
+
+with_std! {
+    mod datetime;
+    pub use crate::datetime::{Clock, PdfDate};
+
+    mod document;
+    mod object_stream;
+    pub use crate::document::Document;
+
+    mod accessibility;
+    pub use crate::accessibility::{AccessibilityIssue, AccessibilityIssueKind, MarkInfo};
+    mod actions;
+    pub use crate::actions::{
+        ActionEntry, ActionKind, ActionLocation, DocAction, OpenAction, PageTrigger, SanitizePolicy, SanitizeReport,
+    };
+    mod annotations;
+    pub use crate::annotations::{
+        Annotation, AnnotationFlags, AnnotationFlattenOptions, AnnotationFlattenReport, AnnotationSubtype, Appearance,
+        Callout, FlattenTarget, FreeTextFont, FreeTextOptions, LineEnding, LinkTarget, MarkupKind, MarkupOptions,
+        TabOrder,
+    };
+    mod articles;
+    pub use crate::articles::{ArticleBead, ArticleThread, PageTransition, PresentationReport};
+    mod asynchronous;
+    mod attachments;
+    pub use crate::attachments::{Attachment, AttachmentLocation, AttachmentOptions};
+    mod bookmarks;
+    pub use crate::bookmarks::{Bookmark, OutlineFlags, OutlineItem, OutlineOptions};
+    mod builder;
+    pub use crate::builder::{BuiltinFont, DocumentBuilder, Page, PageBuilder};
+    mod cms;
+    #[cfg(feature = "crypto")]
+    pub use crate::cms::{CmsSignature, TimestampInfo};
+    mod codecs;
+    pub use crate::codecs::Codecs;
+    mod content_patch;
+    pub use crate::content_patch::{ContentEdit, ContentPatch};
+    mod creator;
+    mod digest;
+    mod dss;
+    pub use crate::dss::ValidationMaterials;
+    mod destinations;
+    pub use crate::destinations::{Destination, DestinationFit, DestinationPolicy};
+    mod encodings;
+    #[cfg(feature = "crypto")]
+    mod encryption;
+    #[cfg(feature = "crypto")]
+    pub use crate::encryption::{EncryptionAlgorithm, EncryptionInfo, EncryptionOptions};
+    mod extensions;
+    pub use crate::extensions::{DeveloperExtension, ExtensionsReport, PermissionSignature};
+    mod file_id;
+    pub mod filters;
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    mod font_cache;
+    mod font_usage;
+    pub use crate::font_usage::{FontUseKind, FontUseSite};
+    mod forms;
+    pub use crate::forms::{
+        ChoiceFieldOptions, FieldFlags, FieldType, FieldValue, FillOptions, FillReport, FormField, SignatureFieldOptions,
+        TextFieldOptions, Widget,
+    };
+    pub mod function;
+    pub mod geometry;
+    mod id_allocator;
+    pub use crate::id_allocator::{IdAllocator, IdBlock};
+    mod images;
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    pub use crate::images::{
+        DecodedImage, ExtractedImage, ImageCompare, ImageDataFormat, ImageExtractionOptions, ImagePlacement, PixelFormat,
+    };
+    #[cfg(all(feature = "embed_image", any(feature = "pom_parser", feature = "nom_parser")))]
+    pub use crate::images::{ImageOptimizationOptions, OptimizedImage};
+    #[cfg(feature = "serde")]
+    mod json;
+    mod layers;
+    pub use crate::layers::{LayerInfo, LayerRef, RemovalMode};
+    mod measurement;
+    pub use crate::measurement::{GeospatialMeasure, Measure, NumberFormat, RectilinearMeasure, Viewport};
+    mod metadata;
+    pub use crate::metadata::{DocumentMetadata, Trapped};
+    mod modification;
+    pub use crate::modification::{
+        DocMdpLevel, EditDescription, FieldLock, FieldLockAction, ModificationPolicy, PolicyVerdict, UsageRights,
+    };
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    mod numbers;
+    #[cfg(not(feature = "nom_parser"))]
+    #[cfg(feature = "pom_parser")]
+    mod parser;
+    #[cfg(feature = "nom_parser")]
+    #[path = "nom_parser.rs"]
+    mod parser;
+    mod output_intents;
+    pub use crate::output_intents::{OutputIntentInfo, OutputIntentSubtype};
+    mod page_labels;
+    pub use crate::page_labels::{PageLabelRange, PageLabelStyle};
+    mod page_text;
+    pub use crate::page_text::{AppendTextOptions, FontChoice, PlacedText};
+    mod parser_aux;
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    pub use crate::parser_aux::{
+        ContentDecodeOptions, DecodedContent, ExtractStats, ExtractedText, ExtractionOptions, TextExtractionOptions,
+        TextOrder, TextSpan,
+    };
+    mod pdfa;
+    pub use crate::pdfa::{PdfaIssue, PdfaIssueKind};
+    mod piece_info;
+    pub use crate::piece_info::{PieceInfo, PieceInfoTarget};
+    mod name_tree;
+    mod portfolio;
+    pub use crate::portfolio::{
+        CollectionField, CollectionFieldType, CollectionInfo, CollectionValue, CollectionView, PortfolioMember,
+    };
+    mod processor;
+    pub use crate::processor::PageResizeOptions;
+    mod reader;
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    pub use crate::reader::{ObjectAction, ObjectFilter, ParseOptions};
+    mod redaction;
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    pub use crate::redaction::{ImageRedaction, RedactionOptions, RedactionReport};
+    mod shading;
+    mod signatures;
+    pub use crate::signatures::{DigestAlgorithm, SignatureInfo, Signer, TimestampAuthority};
+    mod structure;
+    pub use crate::structure::{ParentTreeEntry, StructElement, StructKid, StructTree};
+    mod thumbnails;
+    mod truetype;
+    #[cfg(all(feature = "borrowed", any(feature = "pom_parser", feature = "nom_parser")))]
+    mod view;
+    #[cfg(all(feature = "borrowed", any(feature = "pom_parser", feature = "nom_parser")))]
+    pub use crate::view::{DictionaryRef, DocumentView, ObjectRef, PageContext, StreamRef};
+    mod viewer;
+    pub use crate::viewer::{Direction, Duplex, PageLayout, PageMode, PrintScaling, ViewerPreferences};
+    pub use crate::writer::{SaveHooks, SaveOptions};
+    pub mod xobject;
+    mod xmp;
+    pub use crate::xmp::MetadataSync;
+}
@@ -0,0 +1,397 @@
+use log::warn;
+
+use crate::encodings::decode_text_string;
+use crate::geometry::{transform_point, transform_rect, Matrix, Rect};
+use crate::{Dictionary, Document, Object, ObjectId, Result};
+
+/// A viewport of the `/VP` array of a page: an area of the page with its own measurement scale, as drawings and
+/// maps have.
+#[derive(Debug, Clone)]
+pub struct Viewport {
+    /// `/BBox`, the area on the page.
+    pub bbox: Rect,
+    pub name: Option<String>,
+    /// `/Measure`, how distances in the viewport translate to real-world ones.
+    pub measure: Option<Measure>,
+}
+
+/// A measure dictionary, converting lengths in user space units to the units of what is drawn.
+#[derive(Debug, Clone)]
+pub enum Measure {
+    /// `/Subtype /RL`, a scale such as that of an engineering drawing.
+    Rectilinear(RectilinearMeasure),
+    /// `/Subtype /GEO`, the georeferencing of a map.
+    Geospatial(GeospatialMeasure),
+}
+
+#[derive(Debug, Clone)]
+pub struct RectilinearMeasure {
+    /// `/R`, the scale ratio as shown to the user, such as `1in = 4ft`.
+    pub ratio: String,
+    /// `/X`, the units of distances along the x axis: the first converts from user space units, each of the
+    /// others from the unit before it.
+    pub x: Vec<NumberFormat>,
+    /// `/Y`, the units along the y axis; empty when they are those of the x axis.
+    pub y: Vec<NumberFormat>,
+    /// `/D`, the units of distances in any direction.
+    pub distance: Vec<NumberFormat>,
+    /// `/A`, the units of areas.
+    pub area: Vec<NumberFormat>,
+    /// `/O`, the origin of measurements, in user space.
+    pub origin: Option<(f64, f64)>,
+}
+
+/// A number format dictionary of a measure: a unit and its conversion factor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumberFormat {
+    /// `/U`, the label of the unit.
+    pub unit: String,
+    /// `/C`, the factor converting to this unit.
+    pub factor: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct GeospatialMeasure {
+    /// `/Bounds`, the region of the viewport that is mapped, in the unit square of its bounding box.
+    pub bounds: Vec<(f64, f64)>,
+    /// `/GPTS`, the latitude and longitude of the points of `/LPTS`.
+    pub gpts: Vec<(f64, f64)>,
+    /// `/LPTS`, points in the unit square of the bounding box of the viewport.
+    pub lpts: Vec<(f64, f64)>,
+    /// `/PDU`, the preferred units of distance, area and angle.
+    pub pdu: Vec<String>,
+}
+
+impl Document {
+    /// The viewports of a page, with their measure dictionaries.
+    pub fn page_viewports(&self, page_id: ObjectId) -> Vec<Viewport> {
+        let viewports = self
+            .get_dictionary(page_id)
+            .and_then(|page| page.get_deref(b"VP", self))
+            .and_then(Object::as_array);
+        let viewports = match viewports {
+            Ok(viewports) => viewports,
+            Err(_) => return Vec::new(),
+        };
+        viewports
+            .iter()
+            .filter_map(|viewport| {
+                let viewport = self.dereference(viewport).ok()?.1.as_dict().ok()?;
+                Some(Viewport {
+                    bbox: Rect::from_object(viewport.get_deref(b"BBox", self).ok()?).ok()?,
+                    name: viewport
+                        .get_deref(b"Name", self)
+                        .and_then(Object::as_str)
+                        .ok()
+                        .map(decode_text_string),
+                    measure: viewport
+                        .get_deref(b"Measure", self)
+                        .and_then(Object::as_dict)
+                        .ok()
+                        .map(|measure| self.read_measure(measure)),
+                })
+            })
+            .collect()
+    }
+
+    /// Update the viewports of a page after its content has been transformed by a matrix: their bounding boxes,
+    /// and the conversion factors and points of their measures, so that measurements made on the page do not
+    /// change. Measures that the transformation breaks, as skewing or scaling a rectilinear measure more in one
+    /// direction than the other does, are removed with a warning, and so are viewports that it does not keep
+    /// rectangular.
+    pub fn transform_page_viewports(&mut self, page_id: ObjectId, matrix: &Matrix) -> Result<()> {
+        let (viewports_id, mut viewports) = match self.get_dictionary(page_id)?.get(b"VP") {
+            Ok(Object::Reference(id)) => (Some(*id), self.get_object(*id)?.as_array()?.clone()),
+            Ok(Object::Array(viewports)) => (None, viewports.clone()),
+            _ => return Ok(()),
+        };
+        let axis_aligned = (matrix[1] == 0.0 && matrix[2] == 0.0) || (matrix[0] == 0.0 && matrix[3] == 0.0);
+        if !axis_aligned {
+            warn!(
+                "Removing the viewports of page {:?}, which the transformation skews",
+                page_id
+            );
+            self.get_object_mut(page_id)?.as_dict_mut()?.remove(b"VP");
+            return Ok(());
+        }
+
+        for viewport in viewports.iter_mut() {
+            match *viewport {
+                Object::Reference(id) => {
+                    let mut dict = self.get_dictionary(id)?.clone();
+                    self.transform_viewport(&mut dict, matrix)?;
+                    *self.get_object_mut(id)? = Object::Dictionary(dict);
+                }
+                Object::Dictionary(ref mut dict) => self.transform_viewport(dict, matrix)?,
+                _ => {}
+            }
+        }
+        match viewports_id {
+            Some(id) => *self.get_object_mut(id)? = Object::Array(viewports),
+            None => self.get_object_mut(page_id)?.as_dict_mut()?.set("VP", viewports),
+        }
+        Ok(())
+    }
+
+    fn transform_viewport(&mut self, viewport: &mut Dictionary, matrix: &Matrix) -> Result<()> {
+        let bbox = match viewport.get_deref(b"BBox", self).and_then(Rect::from_object) {
+            Ok(bbox) => bbox,
+            Err(_) => return Ok(()),
+        };
+        let new_bbox = transform_rect(matrix, &bbox);
+        viewport.set("BBox", new_bbox);
+
+        let (measure_id, mut measure) = match viewport.get(b"Measure") {
+            Ok(Object::Reference(id)) => (Some(*id), self.get_dictionary(*id)?.clone()),
+            Ok(Object::Dictionary(measure)) => (None, measure.clone()),
+            _ => return Ok(()),
+        };
+        if !transform_measure(&mut measure, matrix, &bbox, &new_bbox) {
+            warn!("Removing a measure of a viewport that the transformation scales unevenly");
+            viewport.remove(b"Measure");
+            return Ok(());
+        }
+        match measure_id {
+            Some(id) => *self.get_object_mut(id)? = Object::Dictionary(measure),
+            None => viewport.set("Measure", measure),
+        }
+        Ok(())
+    }
+
+    fn read_measure(&self, measure: &Dictionary) -> Measure {
+        let formats = |key: &[u8]| -> Vec<NumberFormat> {
+            let formats = match measure.get_deref(key, self).and_then(Object::as_array) {
+                Ok(formats) => formats,
+                Err(_) => return Vec::new(),
+            };
+            formats
+                .iter()
+                .filter_map(|format| {
+                    let format = self.dereference(format).ok()?.1.as_dict().ok()?;
+                    Some(NumberFormat {
+                        unit: format
+                            .get_deref(b"U", self)
+                            .and_then(Object::as_str)
+                            .map(decode_text_string)
+                            .unwrap_or_default(),
+                        factor: format.get_deref(b"C", self).and_then(Object::as_float).ok()?,
+                    })
+                })
+                .collect()
+        };
+        let points = |key: &[u8]| -> Vec<(f64, f64)> {
+            let numbers = measure
+                .get_deref(key, self)
+                .and_then(Object::as_array)
+                .map(|numbers| {
+                    numbers
+                        .iter()
+                        .filter_map(|number| number.as_float().ok())
+                        .collect::<Vec<f64>>()
+                })
+                .unwrap_or_default();
+            numbers.chunks_exact(2).map(|point| (point[0], point[1])).collect()
+        };
+
+        if measure.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"GEO") {
+            let pdu = measure
+                .get_deref(b"PDU", self)
+                .and_then(Object::as_array)
+                .map(|units| {
+                    units
+                        .iter()
+                        .filter_map(|unit| unit.as_name_str().ok().map(str::to_string))
+                        .collect()
+                })
+                .unwrap_or_default();
+            return Measure::Geospatial(GeospatialMeasure {
+                bounds: points(b"Bounds"),
+                gpts: points(b"GPTS"),
+                lpts: points(b"LPTS"),
+                pdu,
+            });
+        }
+        Measure::Rectilinear(RectilinearMeasure {
+            ratio: measure
+                .get_deref(b"R", self)
+                .and_then(Object::as_str)
+                .map(decode_text_string)
+                .unwrap_or_default(),
+            x: formats(b"X"),
+            y: formats(b"Y"),
+            distance: formats(b"D"),
+            area: formats(b"A"),
+            origin: points(b"O").first().copied(),
+        })
+    }
+}
+
+/// Update a measure of a viewport whose bounding box an axis-aligned transformation moved, returning false if the
+/// measure cannot follow it.
+fn transform_measure(measure: &mut Dictionary, matrix: &Matrix, bbox: &Rect, new_bbox: &Rect) -> bool {
+    if measure.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"GEO") {
+        // The points are in the unit square of the bounding box, which moved with the content.
+        for key in [&b"Bounds"[..], b"LPTS"] {
+            let numbers = match measure.get_mut(key) {
+                Ok(Object::Array(numbers)) => numbers,
+                _ => continue,
+            };
+            let values: Vec<f64> = numbers.iter().filter_map(|number| number.as_float().ok()).collect();
+            if values.len() != numbers.len() {
+                continue;
+            }
+            *numbers = values
+                .chunks_exact(2)
+                .flat_map(|point| {
+                    let x = bbox.llx + point[0] * bbox.width();
+                    let y = bbox.lly + point[1] * bbox.height();
+                    let (x, y) = transform_point(matrix, x, y);
+                    let u = (x - new_bbox.llx) / new_bbox.width();
+                    let v = (y - new_bbox.lly) / new_bbox.height();
+                    vec![u.into(), v.into()]
+                })
+                .collect();
+        }
+        return true;
+    }
+
+    // How much the transformation scales distances along the x and the y axes, and whether it swaps the axes.
+    let (scale_x, scale_y, swapped) = if matrix[1] == 0.0 && matrix[2] == 0.0 {
+        (matrix[0].abs(), matrix[3].abs(), false)
+    } else {
+        (matrix[1].abs(), matrix[2].abs(), true)
+    };
+    if (scale_x - scale_y).abs() > 1e-9 * scale_x.max(scale_y) || scale_x == 0.0 {
+        return false;
+    }
+    let scale = scale_x;
+    for (key, power) in [(&b"X"[..], 1), (b"Y", 1), (b"D", 1), (b"A", 2)] {
+        let first = match measure.get_mut(key) {
+            Ok(Object::Array(formats)) => formats.first_mut(),
+            _ => None,
+        };
+        if let Some(Object::Dictionary(format)) = first {
+            if let Ok(factor) = format.get(b"C").and_then(Object::as_float) {
+                format.set("C", factor / scale.powi(power));
+            }
+        }
+    }
+    if swapped {
+        if let Some(y) = measure.remove(b"Y") {
+            if let Some(x) = measure.remove(b"X") {
+                measure.set("Y", x);
+            }
+            measure.set("X", y);
+        }
+    }
+    if let Ok([x, y]) = measure.get(b"O").and_then(Object::as_array).map(Vec::as_slice) {
+        if let (Ok(x), Ok(y)) = (x.as_float(), y.as_float()) {
+            let (x, y) = transform_point(matrix, x, y);
+            measure.set("O", vec![x.into(), y.into()]);
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DocumentBuilder, PageBuilder};
+
+    fn drawing() -> (Document, ObjectId) {
+        let mut doc = DocumentBuilder::new()
+            .page(PageBuilder::new(1000.0, 500.0).build())
+            .build()
+            .unwrap();
+        let page_id = doc.page_iter().next().unwrap();
+        // 1 inch, 72 units, is 10 feet.
+        let measure = doc.add_object(dictionary! {
+            "Type" => "Measure",
+            "Subtype" => "RL",
+            "R" => Object::string_literal("1 in = 10 ft"),
+            "X" => vec![dictionary! { "U" => Object::string_literal("ft"), "C" => 10.0 / 72.0 }.into()],
+            "D" => vec![dictionary! { "U" => Object::string_literal("ft"), "C" => 10.0 / 72.0 }.into()],
+            "A" => vec![dictionary! { "U" => Object::string_literal("sq ft"), "C" => 100.0 / 5184.0 }.into()],
+        });
+        let map = dictionary! {
+            "Type" => "Measure",
+            "Subtype" => "GEO",
+            "GPTS" => vec![48.0.into(), 2.0.into(), 49.0.into(), 3.0.into()],
+            "LPTS" => vec![0.into(), 0.into(), 1.into(), 1.into()],
+            "PDU" => vec!["KM".into(), "SQKM".into(), "DEG".into()],
+        };
+        let page = doc.get_object_mut(page_id).and_then(Object::as_dict_mut).unwrap();
+        page.set(
+            "VP",
+            vec![
+                dictionary! {
+                    "Type" => "Viewport",
+                    "BBox" => Rect::new(100.0, 100.0, 900.0, 400.0),
+                    "Name" => Object::string_literal("Plan"),
+                    "Measure" => measure,
+                }
+                .into(),
+                dictionary! { "BBox" => Rect::new(0.0, 0.0, 100.0, 100.0), "Measure" => map }.into(),
+            ],
+        );
+        (doc, page_id)
+    }
+
+    #[test]
+    fn resized_drawing_keeps_its_scale() {
+        let (mut doc, page_id) = drawing();
+        let viewports = doc.page_viewports(page_id);
+        assert_eq!(viewports.len(), 2);
+        assert_eq!(viewports[0].name.as_deref(), Some("Plan"));
+        let measure = match viewports[0].measure {
+            Some(Measure::Rectilinear(ref measure)) => measure.clone(),
+            _ => panic!("no rectilinear measure"),
+        };
+        assert_eq!(measure.ratio, "1 in = 10 ft");
+        let wall = 144.0 * measure.x[0].factor;
+
+        doc.resize_pages(&[1], 500.0, 250.0).unwrap();
+        let viewports = doc.page_viewports(page_id);
+        assert_eq!(viewports[0].bbox, Rect::new(50.0, 50.0, 450.0, 200.0));
+        let measure = match viewports[0].measure {
+            Some(Measure::Rectilinear(ref measure)) => measure.clone(),
+            _ => panic!("no rectilinear measure"),
+        };
+        // The wall is drawn half as long, and still measures as long.
+        assert!((72.0 * measure.x[0].factor - wall).abs() < 1e-9);
+        assert!((72.0 * measure.distance[0].factor - wall).abs() < 1e-9);
+        assert!((measure.area[0].factor - 400.0 / 5184.0).abs() < 1e-12);
+        assert_eq!(measure.ratio, "1 in = 10 ft");
+        match viewports[1].measure {
+            Some(Measure::Geospatial(ref map)) => {
+                assert_eq!(map.lpts, [(0.0, 0.0), (1.0, 1.0)]);
+                assert_eq!(map.gpts, [(48.0, 2.0), (49.0, 3.0)]);
+                assert_eq!(map.pdu, ["KM", "SQKM", "DEG"]);
+            }
+            _ => panic!("no geospatial measure"),
+        }
+    }
+
+    #[test]
+    fn rotated_and_uneven_transformations() {
+        let (mut doc, page_id) = drawing();
+        // A quarter turn maps the map points to other corners of its box.
+        doc.transform_page_viewports(page_id, &[0.0, 1.0, -1.0, 0.0, 500.0, 0.0])
+            .unwrap();
+        let viewports = doc.page_viewports(page_id);
+        assert_eq!(viewports[0].bbox, Rect::new(100.0, 100.0, 400.0, 900.0));
+        match viewports[1].measure {
+            Some(Measure::Geospatial(ref map)) => assert_eq!(map.lpts, [(1.0, 0.0), (0.0, 1.0)]),
+            _ => panic!("no geospatial measure"),
+        }
+
+        doc.transform_page_viewports(page_id, &[2.0, 0.0, 0.0, 1.0, 0.0, 0.0])
+            .unwrap();
+        let viewports = doc.page_viewports(page_id);
+        assert!(viewports[0].measure.is_none());
+        assert!(matches!(viewports[1].measure, Some(Measure::Geospatial(_))));
+        doc.transform_page_viewports(page_id, &[1.0, 0.5, 0.0, 1.0, 0.0, 0.0])
+            .unwrap();
+        assert!(doc.page_viewports(page_id).is_empty());
+    }
+}
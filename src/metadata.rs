@@ -0,0 +1,267 @@
+use crate::datetime::PdfDate;
+use crate::encodings::{decode_text_string, encode_text_string};
+use crate::{Dictionary, Document, Object, Result, StringFormat};
+
+/// `/Trapped`: whether trapping information was added to the document, to be used when printing it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trapped {
+    True,
+    False,
+    Unknown,
+}
+
+/// The entries of the document information dictionary (`/Info`), from [`Document::metadata`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DocumentMetadata {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub subject: Option<String>,
+    pub keywords: Option<String>,
+    /// The application that created the original document.
+    pub creator: Option<String>,
+    /// The application that converted the document to PDF, or that last wrote it.
+    pub producer: Option<String>,
+    pub creation_date: Option<PdfDate>,
+    pub mod_date: Option<PdfDate>,
+    pub trapped: Option<Trapped>,
+}
+
+/// Keys of the text strings of the document information dictionary.
+const TEXT_KEYS: [&str; 6] = ["Title", "Author", "Subject", "Keywords", "Creator", "Producer"];
+
+impl DocumentMetadata {
+    fn text_entries(&self) -> [&Option<String>; 6] {
+        [
+            &self.title,
+            &self.author,
+            &self.subject,
+            &self.keywords,
+            &self.creator,
+            &self.producer,
+        ]
+    }
+}
+
+impl Document {
+    /// The document information dictionary, with its text strings decoded and its dates parsed. Entries that are
+    /// missing or of the wrong type are `None`, as is everything when there is no such dictionary.
+    pub fn metadata(&self) -> DocumentMetadata {
+        let info = match self.info() {
+            Some(info) => info,
+            None => return DocumentMetadata::default(),
+        };
+        let text = |key: &str| match info.get_deref(key.as_bytes(), self) {
+            Ok(Object::String(text, _)) => Some(decode_text_string(text)),
+            _ => None,
+        };
+        let date = |key: &[u8]| info.get_deref(key, self).ok().and_then(Object::as_datetime);
+        let trapped = match info.get_deref(b"Trapped", self) {
+            Ok(Object::Name(name)) if name == b"True" => Some(Trapped::True),
+            Ok(Object::Name(name)) if name == b"False" => Some(Trapped::False),
+            Ok(Object::Name(name)) if name == b"Unknown" => Some(Trapped::Unknown),
+            // PDF 1.3 used booleans.
+            Ok(Object::Boolean(true)) => Some(Trapped::True),
+            Ok(Object::Boolean(false)) => Some(Trapped::False),
+            _ => None,
+        };
+        DocumentMetadata {
+            title: text("Title"),
+            author: text("Author"),
+            subject: text("Subject"),
+            keywords: text("Keywords"),
+            creator: text("Creator"),
+            producer: text("Producer"),
+            creation_date: date(b"CreationDate"),
+            mod_date: date(b"ModDate"),
+            trapped,
+        }
+    }
+
+    /// Write the entries of the document information dictionary, removing those that are `None` and keeping
+    /// other entries. The dictionary is added, and referenced from the trailer, if the document has none.
+    ///
+    /// Saving the document sets `/ModDate` to the time of its [clock](Document::clock), unless it is
+    /// [deterministic](Document::deterministic).
+    pub fn set_metadata(&mut self, metadata: &DocumentMetadata) -> Result<()> {
+        let info = self.info_mut()?;
+        for (key, value) in TEXT_KEYS.iter().zip(metadata.text_entries()) {
+            match value {
+                Some(text) => info.set(*key, Object::String(encode_text_string(text), StringFormat::Literal)),
+                None => {
+                    info.remove(key.as_bytes());
+                }
+            }
+        }
+        for (key, date) in [("CreationDate", metadata.creation_date), ("ModDate", metadata.mod_date)] {
+            match date {
+                Some(date) => info.set(key, date),
+                None => {
+                    info.remove(key.as_bytes());
+                }
+            }
+        }
+        match metadata.trapped {
+            Some(trapped) => info.set(
+                "Trapped",
+                match trapped {
+                    Trapped::True => "True",
+                    Trapped::False => "False",
+                    Trapped::Unknown => "Unknown",
+                },
+            ),
+            None => {
+                info.remove(b"Trapped");
+            }
+        }
+        Ok(())
+    }
+
+    /// Set `/ModDate` of the document information dictionary to the time of the clock when saving, if there is
+    /// such a dictionary and the document is not deterministic.
+    pub(crate) fn update_mod_date(&mut self) {
+        if self.deterministic || self.info().is_none() {
+            return;
+        }
+        if let (Some(now), Ok(info)) = (self.clock.now(), self.info_mut()) {
+            info.set("ModDate", now);
+        }
+    }
+
+    pub(crate) fn info(&self) -> Option<&Dictionary> {
+        let info = self.trailer.get(b"Info").ok()?;
+        self.dereference(info).ok()?.1.as_dict().ok()
+    }
+
+    /// The document information dictionary, added as a new object if the trailer has none, or refers to
+    /// something else.
+    pub(crate) fn info_mut(&mut self) -> Result<&mut Dictionary> {
+        let info_id = match self.trailer.get(b"Info").map(|info| self.dereference(info)) {
+            Ok(Ok((Some(id), Object::Dictionary(_)))) => Some(id),
+            Ok(Ok((None, Object::Dictionary(_)))) => None,
+            _ => {
+                let id = self.add_object(Dictionary::new());
+                self.trailer.set("Info", id);
+                Some(id)
+            }
+        };
+        match info_id {
+            Some(id) => self.get_object_mut(id)?.as_dict_mut(),
+            None => self.trailer.get_mut(b"Info")?.as_dict_mut(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::creator::tests::create_document;
+    use crate::Clock;
+
+    #[test]
+    fn set_and_read_metadata() {
+        let mut doc = Document::with_version("1.5");
+        assert_eq!(doc.metadata(), DocumentMetadata::default());
+        let created = Object::string_literal("D:20240229103000+01'00'").as_datetime();
+        let metadata = DocumentMetadata {
+            title: Some("Quarterly report".to_string()),
+            author: Some("Zoë 中".to_string()),
+            creation_date: created,
+            trapped: Some(Trapped::False),
+            ..Default::default()
+        };
+        doc.set_metadata(&metadata).unwrap();
+        let info_id = doc.trailer.get(b"Info").and_then(Object::as_reference).unwrap();
+        let info = doc.get_dictionary(info_id).unwrap();
+        assert_eq!(
+            info.get(b"Title").and_then(Object::as_str).unwrap(),
+            b"Quarterly report"
+        );
+        assert!(info
+            .get(b"Author")
+            .and_then(Object::as_str)
+            .unwrap()
+            .starts_with(b"\xFE\xFF"));
+        assert_eq!(info.get(b"Trapped").and_then(Object::as_name_str).unwrap(), "False");
+        assert_eq!(doc.metadata(), metadata);
+
+        let mut metadata = doc.metadata();
+        metadata.title = None;
+        doc.set_metadata(&metadata).unwrap();
+        assert!(!doc.get_dictionary(info_id).unwrap().has(b"Title"));
+        assert_eq!(doc.metadata().author, metadata.author);
+    }
+
+    #[test]
+    fn metadata_of_unusual_info() {
+        let mut doc = create_document();
+        let info = dictionary! {
+            "Title" => 12,
+            "Author" => Object::string_literal("Caf\u{e9}"),
+            "Subject" => Object::Name(b"junk".to_vec()),
+            "CreationDate" => Object::string_literal("yesterday"),
+            "Trapped" => true,
+        };
+        let info_id = doc.add_object(info);
+        let link_id = doc.add_object(info_id);
+        doc.trailer.set("Info", link_id);
+        let metadata = doc.metadata();
+        assert_eq!(metadata.title, None);
+        assert_eq!(metadata.subject, None);
+        assert_eq!(metadata.creation_date, None);
+        assert_eq!(metadata.trapped, Some(Trapped::True));
+
+        // The dictionary at the end of the chain of references is updated.
+        doc.set_metadata(&DocumentMetadata {
+            title: Some("Fixed".to_string()),
+            ..metadata
+        })
+        .unwrap();
+        assert_eq!(
+            doc.trailer.get(b"Info").and_then(Object::as_reference).unwrap(),
+            link_id
+        );
+        assert!(doc.get_dictionary(info_id).unwrap().has(b"Title"));
+
+        doc.trailer.set("Info", "broken");
+        assert_eq!(doc.metadata(), DocumentMetadata::default());
+        doc.set_metadata(&DocumentMetadata {
+            title: Some("New".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(doc.metadata().title.as_deref(), Some("New"));
+    }
+
+    #[test]
+    fn mod_date_set_on_save() {
+        let mut doc = create_document();
+        assert!(doc.metadata().mod_date.is_none());
+        doc.save_to(&mut Vec::new()).unwrap();
+        assert!(doc.metadata().mod_date.is_some());
+
+        let mut doc = create_document();
+        doc.deterministic = true;
+        let mut copy = doc.clone();
+        let mut first = Vec::new();
+        doc.save_to(&mut first).unwrap();
+        assert!(doc.metadata().mod_date.is_none());
+        let mut second = Vec::new();
+        copy.save_to(&mut second).unwrap();
+        assert_eq!(first, second);
+
+        // The time is that of the clock, and there is none without a clock.
+        let mut doc = create_document();
+        doc.clock = Clock::new(|| time::OffsetDateTime::from_unix_timestamp(1_700_000_000).ok());
+        doc.save_to(&mut Vec::new()).unwrap();
+        let info = doc.info().unwrap();
+        assert_eq!(info.get(b"ModDate").unwrap().as_str().unwrap(), b"D:20231114221320+00'00'");
+        for clock in [Clock::none(), Clock::new(|| None)] {
+            let mut doc = create_document();
+            doc.clock = clock;
+            let mut saved = Vec::new();
+            doc.save_to(&mut saved).unwrap();
+            assert!(doc.metadata().mod_date.is_none());
+            assert!(!saved.windows(7).any(|window| window == b"ModDate"));
+        }
+    }
+}
@@ -0,0 +1,510 @@
+use crate::encodings::decode_text_string;
+use crate::forms::FieldType;
+use crate::writer::{CountingWrite, Writer};
+use crate::{Dictionary, Document, Error, Object, ObjectId, Result};
+use log::warn;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The changes a certification signature allows, the `/P` of its `/DocMDP` transform parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DocMdpLevel {
+    /// `/P 1`: no changes at all.
+    NoChanges,
+    /// `/P 2`, the default: filling in forms, instantiating page templates and signing.
+    FormFilling,
+    /// `/P 3`: also creating, deleting and modifying annotations.
+    Annotations,
+}
+
+/// The fields a `/FieldMDP` transform locks once its signature field is signed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldLockAction {
+    All,
+    Include(Vec<String>),
+    Exclude(Vec<String>),
+}
+
+/// Fields locked by a signature, from the `/FieldMDP` transform of its signature dictionary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldLock {
+    /// Fully qualified name of the signature field.
+    pub signature: String,
+    pub action: FieldLockAction,
+}
+
+impl FieldLock {
+    /// Whether the field of a fully qualified name, or the field it is a descendant of, is locked.
+    pub fn locks(&self, name: &str) -> bool {
+        let listed = |fields: &[String]| {
+            fields.iter().any(|field| {
+                name.strip_prefix(field.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+            })
+        };
+        match &self.action {
+            FieldLockAction::All => true,
+            FieldLockAction::Include(fields) => listed(fields),
+            FieldLockAction::Exclude(fields) => !listed(fields),
+        }
+    }
+}
+
+/// The rights a usage rights signature (`/UR3`, or `/UR`) enables in viewers, by category of its transform
+/// parameters, such as `Create` in `annots` and `FillIn` in `form`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UsageRights {
+    pub document: Vec<String>,
+    pub annots: Vec<String>,
+    pub form: Vec<String>,
+    pub signature: Vec<String>,
+    /// `/EF`, the rights on embedded files.
+    pub embedded_files: Vec<String>,
+    /// `/P`: whether viewers restrict the rights of the document to those given, even when others are usually
+    /// enabled.
+    pub restricted: bool,
+}
+
+/// What the signatures of a document allow to be changed, from [`Document::modification_policy`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModificationPolicy {
+    /// Whether a signature field is signed. Saving in full, rather than as an incremental update, invalidates its
+    /// signature.
+    pub signed: bool,
+    /// The changes a certification signature allows, if the document is certified.
+    pub certification: Option<DocMdpLevel>,
+    pub field_locks: Vec<FieldLock>,
+    /// The rights of a usage rights signature, which any change invalidates.
+    pub usage_rights: Option<UsageRights>,
+}
+
+/// An intended change of a document, for [`Document::check_edit`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditDescription {
+    /// Fill in the fields of fully qualified names.
+    FillFields(Vec<String>),
+    AddAnnotation,
+    AddPages,
+    /// Any other change, such as of the content of pages.
+    Arbitrary,
+}
+
+/// Whether the signatures of a document allow a change, with the reason when they do not or when viewers may
+/// report it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyVerdict {
+    Allowed,
+    /// The change keeps the signatures valid but is reported, or invalidates a usage rights signature.
+    Warn(String),
+    /// The change invalidates a certification, or a field lock, of a signature.
+    Forbidden(String),
+}
+
+impl ModificationPolicy {
+    /// Check an intended change against the policy.
+    pub fn check(&self, edit: &EditDescription) -> PolicyVerdict {
+        if let Some(level) = self.certification {
+            let allowed = match edit {
+                EditDescription::FillFields(_) => level >= DocMdpLevel::FormFilling,
+                EditDescription::AddAnnotation => level >= DocMdpLevel::Annotations,
+                EditDescription::AddPages | EditDescription::Arbitrary => false,
+            };
+            if !allowed {
+                let allows = match level {
+                    DocMdpLevel::NoChanges => "no changes",
+                    DocMdpLevel::FormFilling => "only filling in forms and signing",
+                    DocMdpLevel::Annotations => "only filling in forms, signing and annotating",
+                };
+                return PolicyVerdict::Forbidden(format!("the certification of the document allows {}", allows));
+            }
+        }
+        if let EditDescription::FillFields(names) = edit {
+            for name in names {
+                if let Some(lock) = self.field_locks.iter().find(|lock| lock.locks(name)) {
+                    return PolicyVerdict::Forbidden(format!(
+                        "field {} is locked by signature {}",
+                        name, lock.signature
+                    ));
+                }
+            }
+        }
+        if self.signed && matches!(edit, EditDescription::AddPages | EditDescription::Arbitrary) {
+            return PolicyVerdict::Warn(
+                "viewers report changes to a signed document other than filling in forms, signing and annotating"
+                    .to_string(),
+            );
+        }
+        if self.usage_rights.is_some() {
+            return PolicyVerdict::Warn("any change invalidates the usage rights signature".to_string());
+        }
+        PolicyVerdict::Allowed
+    }
+}
+
+impl Document {
+    /// What the signatures of the signature fields and of the `/Perms` of the catalog allow to be changed, from
+    /// the transforms of their `/Reference` arrays: the `/DocMDP` of a certification, the `/FieldMDP` field locks
+    /// and the usage rights of `/UR3`. The signatures themselves are not verified.
+    pub fn modification_policy(&self) -> ModificationPolicy {
+        let mut policy = ModificationPolicy::default();
+        let mut signatures: Vec<(String, &Dictionary)> = Vec::new();
+        let mut seen = BTreeSet::new();
+        for field in self.form_fields() {
+            if field.field_type != FieldType::Signature {
+                continue;
+            }
+            let value = self.get_dictionary(field.id).and_then(|field| field.get(b"V"));
+            if let Ok((id, Object::Dictionary(signature))) = value.and_then(|value| self.dereference(value)) {
+                policy.signed = true;
+                seen.extend(id);
+                signatures.push((field.name, signature));
+            }
+        }
+        let perms = self
+            .catalog()
+            .and_then(|catalog| catalog.get_deref(b"Perms", self)?.as_dict());
+        for (key, value) in perms.into_iter().flatten() {
+            if let Ok((id, Object::Dictionary(signature))) = self.dereference(value) {
+                if id.is_none_or(|id| seen.insert(id)) {
+                    signatures.push((String::from_utf8_lossy(key).into_owned(), signature));
+                }
+            }
+        }
+
+        for (name, signature) in signatures {
+            let references = signature.get_deref(b"Reference", self).and_then(Object::as_array);
+            for reference in references.into_iter().flatten() {
+                let reference = match self
+                    .dereference(reference)
+                    .and_then(|(_, reference)| reference.as_dict())
+                {
+                    Ok(reference) => reference,
+                    Err(_) => continue,
+                };
+                let empty = Dictionary::new();
+                let params = reference
+                    .get_deref(b"TransformParams", self)
+                    .and_then(Object::as_dict)
+                    .unwrap_or(&empty);
+                match reference.get(b"TransformMethod").and_then(Object::as_name) {
+                    Ok(b"DocMDP") => {
+                        let level = match params.get(b"P").and_then(Object::as_i64) {
+                            Ok(1) => DocMdpLevel::NoChanges,
+                            Ok(3) => DocMdpLevel::Annotations,
+                            _ => DocMdpLevel::FormFilling,
+                        };
+                        // A document has one certification; the most restrictive is taken if there are several.
+                        policy.certification = Some(policy.certification.map_or(level, |other| other.min(level)));
+                    }
+                    Ok(b"FieldMDP") => {
+                        let fields = self.text_strings(params, b"Fields");
+                        let action = match params.get(b"Action").and_then(Object::as_name) {
+                            Ok(b"Include") => FieldLockAction::Include(fields),
+                            Ok(b"Exclude") => FieldLockAction::Exclude(fields),
+                            _ => FieldLockAction::All,
+                        };
+                        policy.field_locks.push(FieldLock {
+                            signature: name.clone(),
+                            action,
+                        });
+                    }
+                    Ok(b"UR3") | Ok(b"UR") => {
+                        policy.usage_rights = Some(UsageRights {
+                            document: self.names_of(params, b"Document"),
+                            annots: self.names_of(params, b"Annots"),
+                            form: self.names_of(params, b"Form"),
+                            signature: self.names_of(params, b"Signature"),
+                            embedded_files: self.names_of(params, b"EF"),
+                            restricted: params.get(b"P").and_then(Object::as_bool).unwrap_or(false),
+                        });
+                    }
+                    _ => {}
+                }
+            }
+        }
+        policy
+    }
+
+    /// Check an intended change against the [modification policy](Document::modification_policy) of the
+    /// document.
+    pub fn check_edit(&self, edit: &EditDescription) -> PolicyVerdict {
+        self.modification_policy().check(edit)
+    }
+
+    /// Refuse an incremental update of the original document making changes its signatures forbid, and warn
+    /// about those they allow but that are reported.
+    #[cfg_attr(not(any(feature = "pom_parser", feature = "nom_parser")), allow(dead_code))]
+    pub(crate) fn check_update(&self, original: &Document) -> Result<()> {
+        let policy = original.modification_policy();
+        if policy == ModificationPolicy::default() {
+            return Ok(());
+        }
+        for edit in self.update_edits(original) {
+            match policy.check(&edit) {
+                PolicyVerdict::Allowed => {}
+                PolicyVerdict::Warn(reason) => warn!("Saving an incremental update: {}", reason),
+                PolicyVerdict::Forbidden(reason) => return Err(Error::Signature(reason)),
+            }
+        }
+        Ok(())
+    }
+
+    /// The changes made to the original document, from the objects that differ from its own.
+    #[cfg_attr(not(any(feature = "pom_parser", feature = "nom_parser")), allow(dead_code))]
+    fn update_edits(&self, original: &Document) -> Vec<EditDescription> {
+        let mut fields = BTreeMap::new();
+        for field in self.form_fields() {
+            for widget in &field.widgets {
+                fields.insert(widget.id, field.name.clone());
+            }
+            fields.insert(field.id, field.name);
+        }
+        let pages: BTreeSet<ObjectId> = self.page_iter().collect();
+        let original_pages: BTreeSet<ObjectId> = original.page_iter().collect();
+        let catalog_id = self.trailer.get(b"Root").and_then(Object::as_reference).ok();
+        let info_id = self.trailer.get(b"Info").and_then(Object::as_reference).ok();
+        let form_id = self
+            .catalog()
+            .and_then(|catalog| catalog.get(b"AcroForm")?.as_reference())
+            .ok();
+
+        let mut filled = BTreeSet::new();
+        let (mut annotated, mut added_pages, mut arbitrary) = (false, false, false);
+        for (id, object) in &self.objects {
+            let unchanged = original
+                .objects
+                .get(id)
+                .is_some_and(|original| serialized(original) == serialized(object));
+            if unchanged {
+                continue;
+            }
+            let dict = match object {
+                Object::Dictionary(dict) => dict,
+                Object::Stream(stream) => &stream.dict,
+                _ => {
+                    arbitrary = true;
+                    continue;
+                }
+            };
+            let subtype = dict.get(b"Subtype").and_then(Object::as_name).ok();
+            if let Some(name) = fields.get(id) {
+                filled.insert(name.clone());
+            } else if pages.contains(id) {
+                let without_annots = |dict: &Dictionary| {
+                    let mut dict = dict.clone();
+                    dict.remove(b"Annots");
+                    serialized(&Object::Dictionary(dict))
+                };
+                match original.get_dictionary(*id) {
+                    Ok(page) if without_annots(page) == without_annots(dict) => annotated = true,
+                    Ok(_) => arbitrary = true,
+                    Err(_) => added_pages = true,
+                }
+            } else if dict.type_is(b"Pages") {
+                if pages.len() > original_pages.len() {
+                    added_pages = true;
+                } else {
+                    arbitrary = true;
+                }
+            } else if dict.type_is(b"Annot") || (subtype.is_some() && dict.has(b"Rect")) {
+                annotated = true;
+            } else {
+                // The dictionaries that filling in forms, signing and saving change or add.
+                let neutral = [catalog_id, info_id, form_id].contains(&Some(*id))
+                    || [
+                        &b"Sig"[..],
+                        b"DocTimeStamp",
+                        b"Font",
+                        b"FontDescriptor",
+                        b"Metadata",
+                        b"XObject",
+                        b"XRef",
+                    ]
+                    .iter()
+                    .any(|name| dict.type_is(name))
+                    || subtype == Some(b"Form");
+                arbitrary |= !neutral;
+            }
+        }
+
+        let mut edits = Vec::new();
+        if !filled.is_empty() {
+            edits.push(EditDescription::FillFields(filled.into_iter().collect()));
+        }
+        if annotated {
+            edits.push(EditDescription::AddAnnotation);
+        }
+        if added_pages {
+            edits.push(EditDescription::AddPages);
+        }
+        if arbitrary {
+            edits.push(EditDescription::Arbitrary);
+        }
+        edits
+    }
+
+    /// The names of an array of names in a dictionary.
+    fn names_of(&self, dict: &Dictionary, key: &[u8]) -> Vec<String> {
+        let names = dict.get_deref(key, self).and_then(Object::as_array);
+        names
+            .into_iter()
+            .flatten()
+            .filter_map(|name| name.as_name_str().ok().map(str::to_string))
+            .collect()
+    }
+
+    /// The text strings of an array of them in a dictionary.
+    fn text_strings(&self, dict: &Dictionary, key: &[u8]) -> Vec<String> {
+        let strings = dict.get_deref(key, self).and_then(Object::as_array);
+        strings
+            .into_iter()
+            .flatten()
+            .filter_map(|string| string.as_str().ok().map(decode_text_string))
+            .collect()
+    }
+}
+
+/// An object as it is written.
+#[cfg_attr(not(any(feature = "pom_parser", feature = "nom_parser")), allow(dead_code))]
+fn serialized(object: &Object) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut target = CountingWrite {
+        inner: &mut bytes,
+        bytes_written: 0,
+    };
+    // Writing to memory does not fail.
+    let _ = Writer::write_object(&mut target, object, None, None);
+    bytes
+}
+
+#[cfg(all(test, any(feature = "pom_parser", feature = "nom_parser")))]
+mod tests {
+    use super::*;
+    use crate::creator::tests::create_document;
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    use crate::creator::tests::load_keeping_source;
+    use crate::geometry::Rect;
+    use crate::{FieldValue, Stream, TextFieldOptions};
+    use std::collections::HashMap;
+
+    /// A document certified with a `/P` of 2, with a signature field locking the field `client.name` and a
+    /// text field `client.address` that is not.
+    fn certified_document() -> Vec<u8> {
+        let mut doc = create_document();
+        let page_id = doc.page_iter().next().unwrap();
+        let rect = Rect::new(10.0, 10.0, 200.0, 30.0);
+        for name in ["client.name", "client.address"] {
+            doc.add_text_field(page_id, name, rect, &TextFieldOptions::default())
+                .unwrap();
+        }
+        let field = doc
+            .add_signature_field(page_id, "approval", rect, &Default::default())
+            .unwrap();
+        let references = vec![
+            dictionary! {
+                "Type" => "SigRef",
+                "TransformMethod" => "DocMDP",
+                "TransformParams" => dictionary! { "Type" => "TransformParams", "P" => 2, "V" => "1.2" },
+            }
+            .into(),
+            dictionary! {
+                "Type" => "SigRef",
+                "TransformMethod" => "FieldMDP",
+                "TransformParams" => dictionary! {
+                    "Action" => "Include",
+                    "Fields" => vec![Object::string_literal("client.name")],
+                },
+            }
+            .into(),
+        ];
+        let signature = doc.add_object(dictionary! {
+            "Type" => "Sig",
+            "Filter" => "Adobe.PPKLite",
+            "Reference" => references,
+        });
+        doc.get_object_mut(field)
+            .unwrap()
+            .as_dict_mut()
+            .unwrap()
+            .set("V", signature);
+        doc.catalog_mut()
+            .unwrap()
+            .set("Perms", dictionary! { "DocMDP" => signature });
+        let mut data = Vec::new();
+        doc.save_to(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn read_and_check_modification_policy() {
+        let doc = Document::load_mem(&certified_document()).unwrap();
+        let policy = doc.modification_policy();
+        assert!(policy.signed);
+        assert_eq!(policy.certification, Some(DocMdpLevel::FormFilling));
+        assert_eq!(
+            policy.field_locks,
+            [FieldLock {
+                signature: "approval".to_string(),
+                action: FieldLockAction::Include(vec!["client.name".to_string()]),
+            }]
+        );
+        assert_eq!(policy.usage_rights, None);
+
+        let fill = |name: &str| EditDescription::FillFields(vec![name.to_string()]);
+        assert_eq!(doc.check_edit(&fill("client.address")), PolicyVerdict::Allowed);
+        assert!(matches!(
+            doc.check_edit(&fill("client.name")),
+            PolicyVerdict::Forbidden(_)
+        ));
+        assert!(matches!(
+            doc.check_edit(&EditDescription::AddAnnotation),
+            PolicyVerdict::Forbidden(_)
+        ));
+        assert!(matches!(
+            doc.check_edit(&EditDescription::Arbitrary),
+            PolicyVerdict::Forbidden(_)
+        ));
+
+        let uncertified = ModificationPolicy {
+            certification: None,
+            usage_rights: Some(UsageRights::default()),
+            ..policy
+        };
+        assert!(matches!(
+            uncertified.check(&fill("client.name.first")),
+            PolicyVerdict::Forbidden(_)
+        ));
+        assert!(matches!(uncertified.check(&fill("client")), PolicyVerdict::Warn(_)));
+        assert!(matches!(
+            uncertified.check(&EditDescription::AddPages),
+            PolicyVerdict::Warn(_)
+        ));
+    }
+
+    #[test]
+    fn fill_and_save_certified_document() {
+        let original = certified_document();
+        let mut doc = load_keeping_source(&original);
+        let values: HashMap<String, FieldValue> = ["client.name", "client.address"]
+            .iter()
+            .map(|name| (name.to_string(), FieldValue::Text("Somewhere".to_string())))
+            .collect();
+        let report = doc.fill_form(&values).unwrap();
+        assert_eq!(report.filled, ["client.address"]);
+        assert_eq!(report.rejected.len(), 1);
+        assert_eq!(report.rejected[0].0, "client.name");
+        let mut update = Vec::new();
+        doc.save_incremental_to(&mut update).unwrap();
+        assert!(update.starts_with(&original));
+
+        // Changing the content of the page is not filling in the form.
+        let page_id = doc.page_iter().next().unwrap();
+        let content_id = doc.add_object(Stream::new(dictionary! {}, b"0 0 10 10 re f".to_vec()));
+        doc.get_object_mut(page_id)
+            .unwrap()
+            .as_dict_mut()
+            .unwrap()
+            .set("Contents", content_id);
+        let mut update = Vec::new();
+        assert!(matches!(doc.save_incremental_to(&mut update), Err(Error::Signature(_))));
+    }
+}
@@ -0,0 +1,232 @@
+use crate::{Dictionary, Document, Object, ObjectId, Result, StringFormat};
+use log::warn;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Maximum number of entries in a leaf, and of kids in an intermediate node, of the name trees built here.
+const NODE_SIZE: usize = 32;
+
+impl Document {
+    /// All entries of a name tree, sorted by key. Values are returned as stored, so they may be references.
+    ///
+    /// Malformed nodes and entries are skipped, and nodes reached twice are only read once.
+    pub fn name_tree_entries(&self, root: &Dictionary) -> BTreeMap<Vec<u8>, Object> {
+        let mut entries = BTreeMap::new();
+        let mut visited = BTreeSet::new();
+        self.collect_name_tree(root, &mut entries, &mut visited);
+        entries.into_iter().map(|(key, value)| (key, value.clone())).collect()
+    }
+
+    fn collect_name_tree<'a>(
+        &'a self, node: &'a Dictionary, entries: &mut BTreeMap<Vec<u8>, &'a Object>, visited: &mut BTreeSet<ObjectId>,
+    ) {
+        if let Ok(names) = node.get_deref(b"Names", self).and_then(Object::as_array) {
+            for pair in names.chunks(2) {
+                match pair {
+                    [Object::String(key, _), value] => {
+                        entries.insert(key.clone(), value);
+                    }
+                    _ => warn!("Skipping malformed name tree entry {:?}", pair),
+                }
+            }
+        }
+        if let Ok(kids) = node.get_deref(b"Kids", self).and_then(Object::as_array) {
+            for kid in kids {
+                let kid = match kid.as_reference() {
+                    Ok(id) if !visited.insert(id) => continue,
+                    _ => self.dereference(kid).and_then(|(_, kid)| kid.as_dict()),
+                };
+                match kid {
+                    Ok(kid) => self.collect_name_tree(kid, entries, visited),
+                    Err(err) => warn!("Skipping name tree node: {}", err),
+                }
+            }
+        }
+    }
+
+    /// All entries of a number tree, sorted by key, like [`Document::name_tree_entries`].
+    pub fn number_tree_entries(&self, root: &Dictionary) -> BTreeMap<i64, Object> {
+        let mut entries = BTreeMap::new();
+        let mut visited = BTreeSet::new();
+        self.collect_number_tree(root, &mut entries, &mut visited);
+        entries.into_iter().map(|(key, value)| (key, value.clone())).collect()
+    }
+
+    fn collect_number_tree<'a>(
+        &'a self, node: &'a Dictionary, entries: &mut BTreeMap<i64, &'a Object>, visited: &mut BTreeSet<ObjectId>,
+    ) {
+        if let Ok(nums) = node.get_deref(b"Nums", self).and_then(Object::as_array) {
+            for pair in nums.chunks(2) {
+                match pair {
+                    [Object::Integer(key), value] => {
+                        entries.insert(*key, value);
+                    }
+                    _ => warn!("Skipping malformed number tree entry {:?}", pair),
+                }
+            }
+        }
+        if let Ok(kids) = node.get_deref(b"Kids", self).and_then(Object::as_array) {
+            for kid in kids {
+                let kid = match kid.as_reference() {
+                    Ok(id) if !visited.insert(id) => continue,
+                    _ => self.dereference(kid).and_then(|(_, kid)| kid.as_dict()),
+                };
+                match kid {
+                    Ok(kid) => self.collect_number_tree(kid, entries, visited),
+                    Err(err) => warn!("Skipping number tree node: {}", err),
+                }
+            }
+        }
+    }
+
+    /// Build a balanced name tree holding the given entries and return its root node.
+    ///
+    /// Leaves and intermediate nodes are added as new objects; the root is left to the caller to store.
+    pub fn build_name_tree(&mut self, entries: BTreeMap<Vec<u8>, Object>) -> Dictionary {
+        fn chunk_size(len: usize) -> usize {
+            len.div_ceil(len.div_ceil(NODE_SIZE))
+        }
+        fn names(entries: &[(Vec<u8>, Object)]) -> Vec<Object> {
+            entries
+                .iter()
+                .flat_map(|(key, value)| vec![Object::String(key.clone(), StringFormat::Literal), value.clone()])
+                .collect()
+        }
+        fn limits(first: &[u8], last: &[u8]) -> Object {
+            Object::Array(vec![
+                Object::String(first.to_vec(), StringFormat::Literal),
+                Object::String(last.to_vec(), StringFormat::Literal),
+            ])
+        }
+
+        let entries: Vec<(Vec<u8>, Object)> = entries.into_iter().collect();
+        if entries.len() <= NODE_SIZE {
+            return dictionary! { "Names" => names(&entries) };
+        }
+        // Each level is built from the whole level below, so all leaves have the same depth.
+        let mut level: Vec<(Vec<u8>, Vec<u8>, ObjectId)> = entries
+            .chunks(chunk_size(entries.len()))
+            .map(|leaf| {
+                let (first, last) = (leaf[0].0.clone(), leaf[leaf.len() - 1].0.clone());
+                let id = self.add_object(dictionary! {
+                    "Limits" => limits(&first, &last),
+                    "Names" => names(leaf),
+                });
+                (first, last, id)
+            })
+            .collect();
+        while level.len() > NODE_SIZE {
+            level = level
+                .chunks(chunk_size(level.len()))
+                .map(|kids| {
+                    let (first, last) = (kids[0].0.clone(), kids[kids.len() - 1].1.clone());
+                    let id = self.add_object(dictionary! {
+                        "Limits" => limits(&first, &last),
+                        "Kids" => kids.iter().map(|kid| Object::Reference(kid.2)).collect::<Vec<_>>(),
+                    });
+                    (first, last, id)
+                })
+                .collect();
+        }
+        dictionary! {
+            "Kids" => level.into_iter().map(|kid| Object::Reference(kid.2)).collect::<Vec<_>>(),
+        }
+    }
+
+    /// The entries of a name tree in the name dictionary of the document, such as `Dests` or `EmbeddedFiles`.
+    pub fn names(&self, category: &[u8]) -> BTreeMap<Vec<u8>, Object> {
+        self.borrowed_names(category)
+            .into_iter()
+            .map(|(key, value)| (key, value.clone()))
+            .collect()
+    }
+
+    /// The entries of a name tree in the name dictionary of the document, borrowed from the tree.
+    pub(crate) fn borrowed_names(&self, category: &[u8]) -> BTreeMap<Vec<u8>, &Object> {
+        let root = self
+            .catalog()
+            .and_then(|catalog| catalog.get_deref(b"Names", self))
+            .and_then(Object::as_dict)
+            .and_then(|names| names.get_deref(category, self))
+            .and_then(Object::as_dict);
+        let mut entries = BTreeMap::new();
+        if let Ok(root) = root {
+            self.collect_name_tree(root, &mut entries, &mut BTreeSet::new());
+        }
+        entries
+    }
+
+    /// Replace a name tree in the name dictionary of the document with a balanced tree holding the given entries.
+    ///
+    /// The tree is removed when there are no entries, and so is the name dictionary once it is empty.
+    pub fn set_names(&mut self, category: &str, entries: BTreeMap<Vec<u8>, Object>) -> Result<()> {
+        let root = if entries.is_empty() {
+            None
+        } else {
+            let root = self.build_name_tree(entries);
+            Some(self.add_object(root))
+        };
+
+        let root_id = self.trailer.get(b"Root")?.as_reference()?;
+        let names_id = self.catalog()?.get(b"Names").and_then(Object::as_reference).ok();
+        let names = match names_id {
+            Some(names_id) => self.get_object_mut(names_id)?.as_dict_mut()?,
+            None => {
+                let catalog = self.get_object_mut(root_id)?.as_dict_mut()?;
+                if !catalog.has(b"Names") {
+                    catalog.set("Names", Dictionary::new());
+                }
+                catalog.get_mut(b"Names")?.as_dict_mut()?
+            }
+        };
+        match root {
+            Some(root) => names.set(category, root),
+            None => {
+                names.remove(category.as_bytes());
+            }
+        }
+        if names.is_empty() {
+            self.get_object_mut(root_id)?.as_dict_mut()?.remove(b"Names");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_balanced_name_tree() {
+        let mut doc = Document::with_version("1.5");
+        let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog" });
+        doc.trailer.set("Root", catalog_id);
+
+        let entries: BTreeMap<Vec<u8>, Object> = (0..2000)
+            .map(|i| (format!("name{:04}", i).into_bytes(), Object::Integer(i)))
+            .collect();
+        doc.set_names("Dests", entries.clone()).unwrap();
+        let read = doc.names(b"Dests");
+        assert!(read.keys().eq(entries.keys()));
+        assert_eq!(read[&b"name1234"[..]].as_i64().unwrap(), 1234);
+
+        // 2000 entries need 63 leaves, under two intermediate nodes.
+        let names = doc.catalog().unwrap().get(b"Names").and_then(Object::as_dict).unwrap();
+        let root = doc
+            .get_dictionary(names.get(b"Dests").unwrap().as_reference().unwrap())
+            .unwrap();
+        assert!(!root.has(b"Limits"));
+        let kids = root.get(b"Kids").and_then(Object::as_array).unwrap();
+        assert_eq!(kids.len(), 2);
+        let first = doc.get_dictionary(kids[0].as_reference().unwrap()).unwrap();
+        let limits = first.get(b"Limits").and_then(Object::as_array).unwrap();
+        assert_eq!(limits[0].as_str().unwrap(), b"name0000");
+        let leaf = first.get(b"Kids").and_then(Object::as_array).unwrap()[0]
+            .as_reference()
+            .unwrap();
+        let leaf = doc.get_dictionary(leaf).unwrap();
+        assert_eq!(leaf.get(b"Names").and_then(Object::as_array).unwrap().len(), 64);
+
+        doc.set_names("Dests", BTreeMap::new()).unwrap();
+        assert!(!doc.catalog().unwrap().has(b"Names"));
+    }
+}
@@ -0,0 +1,87 @@
+#[cfg(feature = "std")]
+use lazy_static::lazy_static;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+macro_rules! known_names {
+    ($($name:ident),* $(,)?) => {
+        /// A name of the PDF specification. Dictionaries keep their `/Type` as one, so that checking their type
+        /// with [`Dictionary::known_type`](crate::Dictionary::known_type) compares two integers rather than two
+        /// strings.
+        ///
+        /// Only the `/Type` values are interned: dictionary keys and other names stay bytes, compared as such.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub enum KnownName {
+            $($name),*
+        }
+
+        impl KnownName {
+            const ALL: &'static [KnownName] = &[$(KnownName::$name),*];
+
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    $(KnownName::$name => stringify!($name)),*
+                }
+            }
+        }
+    };
+}
+
+#[cfg(feature = "std")]
+lazy_static! {
+    static ref KNOWN_NAMES: HashMap<&'static [u8], KnownName> =
+        KnownName::ALL.iter().map(|&name| (name.as_bytes(), name)).collect();
+}
+
+impl KnownName {
+    /// The known name of some bytes, or `None` for names that are not in the table.
+    #[cfg(feature = "std")]
+    pub fn from_bytes(name: &[u8]) -> Option<KnownName> {
+        KNOWN_NAMES.get(name).copied()
+    }
+
+    /// The known name of some bytes, looked up one by one without the hash map of the `std` feature.
+    #[cfg(not(feature = "std"))]
+    pub fn from_bytes(name: &[u8]) -> Option<KnownName> {
+        KnownName::ALL.iter().copied().find(|known| known.as_bytes() == name)
+    }
+
+    pub fn as_bytes(self) -> &'static [u8] {
+        self.as_str().as_bytes()
+    }
+}
+
+known_names! {
+    // Types of dictionaries.
+    Catalog, Pages, Page, Font, FontDescriptor, XObject, ExtGState, Pattern, Annot, Action, Border, ObjStm, XRef,
+    Metadata, Outlines, StructTreeRoot, StructElem, MCR, OBJR, ParentTree, Filespec, EmbeddedFile, Encoding, Group,
+    Mask, OCG, OCMD, Sig, DocTimeStamp, Linearized, Halftone, CMap, Collection, MarkInfo, ViewerPreferences,
+    // Subtypes of fonts, external objects and annotations.
+    Type0, Type1, MMType1, Type3, TrueType, CIDFontType0, CIDFontType2, Image, Form, PS, Link, Widget, Text,
+    FreeText, Popup, Highlight, Underline, StrikeOut, Squiggly, Stamp, Ink, FileAttachment, Redact,
+    // Filters.
+    ASCIIHexDecode, ASCII85Decode, LZWDecode, FlateDecode, RunLengthDecode, CCITTFaxDecode, JBIG2Decode, DCTDecode,
+    JPXDecode, Crypt,
+    // Color spaces.
+    DeviceGray, DeviceRGB, DeviceCMYK, CalGray, CalRGB, Lab, ICCBased, Indexed, Separation, DeviceN,
+    // Common keys.
+    Type, Subtype, Kids, Count, Parent, Resources, MediaBox, CropBox, Rotate, Contents, Annots, Length, Filter,
+    DecodeParms, BaseFont, ToUnicode, Width, Height, BitsPerComponent, ColorSpace, Root, Info, Size, Prev, ID,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_names_round_trip() {
+        assert_eq!(KnownName::from_bytes(b"Page"), Some(KnownName::Page));
+        assert_eq!(
+            KnownName::from_bytes(b"Pages").map(KnownName::as_bytes),
+            Some(&b"Pages"[..])
+        );
+        assert_eq!(KnownName::from_bytes(b"CIDFontType2").unwrap().as_str(), "CIDFontType2");
+        assert_eq!(KnownName::from_bytes(b"page"), None);
+        assert_eq!(KnownName::from_bytes(b"Page#20"), None);
+    }
+}
@@ -1,6 +1,7 @@
 use super::{Dictionary, Object, ObjectId, Stream, StringFormat};
 use crate::content::*;
 use crate::error::XrefError;
+use crate::{numbers, parser_aux};
 use crate::reader::Reader;
 use crate::xref::*;
 use crate::Error;
@@ -8,7 +9,7 @@ use std::str::{self, FromStr};
 
 use nom::branch::alt;
 use nom::bytes::complete::{tag, take, take_while, take_while1, take_while_m_n};
-use nom::character::complete::{digit0, digit1, one_of};
+use nom::character::complete::{digit1, one_of};
 use nom::character::{is_hex_digit, is_oct_digit};
 use nom::combinator::{map, map_opt, map_res, opt, verify};
 use nom::error::{ErrorKind, ParseError};
@@ -86,17 +87,12 @@ fn integer(input: &[u8]) -> NomResult<i64> {
     convert_result(i64::from_str(str::from_utf8(int_input).unwrap()), i, ErrorKind::Digit)
 }
 
-fn real(input: &[u8]) -> NomResult<f64> {
-    let (i, _) = pair(
-        opt(one_of("+-")),
-        alt((
-            map(tuple((digit1, tag(b"."), digit0)), |_| ()),
-            map(pair(tag(b"."), digit1), |_| ()),
-        )),
-    )(input)?;
-
-    let float_input = &input[..input.len() - i.len()];
-    convert_result(f64::from_str(str::from_utf8(float_input).unwrap()), i, ErrorKind::Digit)
+/// An integer or a real, as [`numbers::leading_number`] reads them.
+fn number(input: &[u8]) -> NomResult<Object> {
+    match numbers::leading_number(input) {
+        Some((number, length)) => Ok((&input[length..], number)),
+        None => Err(nom::Err::Error(NomError::from_error_kind(input, ErrorKind::Digit))),
+    }
 }
 
 fn hex_char(input: &[u8]) -> NomResult<u8> {
@@ -301,8 +297,7 @@ fn _direct_objects(input: &[u8]) -> NomResult<Object> {
         null,
         boolean,
         reference,
-        map(real, Object::Real),
-        map(integer, Object::Integer),
+        number,
         map(name, Object::Name),
         map(literal_string, Object::string_literal),
         hexadecimal_string,
@@ -319,24 +314,34 @@ pub fn direct_object(input: &[u8]) -> Option<Object> {
     strip_nom(_direct_object(input))
 }
 
+/// A direct object at the start of the input, with the length of it and of the whitespace after it.
+pub fn leading_direct_object(input: &[u8]) -> Option<(Object, usize)> {
+    let (rest, object) = _direct_object(input).ok()?;
+    Some((object, input.len() - rest.len()))
+}
+
 fn object<'a>(input: &'a [u8], reader: &Reader) -> NomResult<'a, Object> {
     terminated(alt((|input| stream(input, reader), _direct_objects)), space)(input)
 }
 
+/// An indirect object at an offset, with the offset of the end of the object and of the whitespace after it.
 pub fn indirect_object<'a>(
     input: &'a [u8], offset: usize, expected_id: Option<ObjectId>, reader: &Reader,
-) -> crate::Result<(ObjectId, Object)> {
-    let (id, mut object) = _indirect_object(&input[offset..], offset, expected_id, reader)?;
+) -> crate::Result<(ObjectId, Object, usize)> {
+    let (id, mut object, end) = _indirect_object(&input[offset..], offset, expected_id, reader)?;
 
     offset_stream(&mut object, offset);
 
-    Ok((id, object))
+    Ok((id, object, offset + end))
 }
 
 fn _indirect_object<'a>(
     input: &'a [u8], offset: usize, expected_id: Option<ObjectId>, reader: &Reader,
-) -> crate::Result<(ObjectId, Object)> {
-    let (i, object_id) = terminated(object_id, pair(tag(b"obj"), space))(input).map_err(|_| Error::Parse { offset })?;
+) -> crate::Result<(ObjectId, Object, usize)> {
+    let (i, object_id) = terminated(object_id, pair(tag(b"obj"), space))(input).map_err(|_| Error::Parse {
+        offset,
+        object: expected_id,
+    })?;
     if let Some(expected_id) = expected_id {
         if object_id != expected_id {
             return Err(crate::error::Error::ObjectIdMismatch);
@@ -344,12 +349,15 @@ fn _indirect_object<'a>(
     }
 
     let object_offset = input.len() - i.len();
-    let (_, mut object) = terminated(|i| object(i, reader), tuple((space, opt(tag(b"endobj")), space)))(i)
-        .map_err(|_| Error::Parse { offset })?;
+    let (rest, mut object) = terminated(|i| object(i, reader), tuple((space, opt(tag(b"endobj")), space)))(i)
+        .map_err(|_| Error::Parse {
+            offset,
+            object: Some(object_id),
+        })?;
 
     offset_stream(&mut object, object_offset);
 
-    Ok((object_id, object))
+    Ok((object_id, object, input.len() - rest.len()))
 }
 
 pub fn header(input: &[u8]) -> Option<String> {
@@ -364,7 +372,10 @@ pub fn header(input: &[u8]) -> Option<String> {
 }
 
 fn xref(input: &[u8]) -> NomResult<Xref> {
-    let xref_eol = map(alt((tag(b" \r"), tag(b" \n"), tag(b"\r\n"))), |_| ());
+    let xref_eol = map(
+        alt((tag(b" \r"), tag(b" \n"), tag(b"\r\n"), tag(b"\n"), tag(b"\r"))),
+        |_| (),
+    );
     let xref_entry = pair(
         separated_pair(unsigned_int, tag(b" "), unsigned_int),
         delimited(tag(b" "), map(one_of("nf"), |k| k == 'n'), xref_eol),
@@ -404,7 +415,7 @@ pub fn xref_and_trailer(input: &[u8], reader: &Reader) -> crate::Result<(Xref, D
         }),
         (|input| {
             _indirect_object(input, 0, None, reader)
-                .map(|(_, obj)| {
+                .map(|(_, obj, _)| {
                     let res = match obj {
                         Object::Stream(stream) => decode_xref_stream(stream),
                         _ => Err(Error::Xref(XrefError::Parse)),
@@ -429,7 +440,14 @@ pub fn xref_start(input: &[u8]) -> Option<i64> {
 // The following code create parser to parse content stream.
 
 fn content_space(input: &[u8]) -> NomResult<()> {
-    map(take_while(|c| b" \t\r\n".contains(&c)), |_| ())(input)
+    // A comment may end the content without an end of line.
+    map(
+        many0_count(alt((
+            map(take_while1(|c| b" \t\r\n".contains(&c)), |_| ()),
+            map(pair(tag(b"%"), take_while(|c: u8| !b"\r\n".contains(&c))), |_| ()),
+        ))),
+        |_| (),
+    )(input)
 }
 
 fn operator(input: &[u8]) -> NomResult<String> {
@@ -444,8 +462,7 @@ fn operand(input: &[u8]) -> NomResult<Object> {
         alt((
             null,
             boolean,
-            map(real, Object::Real),
-            map(integer, Object::Integer),
+            number,
             map(name, Object::Name),
             map(literal_string, Object::string_literal),
             hexadecimal_string,
@@ -458,20 +475,52 @@ fn operand(input: &[u8]) -> NomResult<Object> {
 
 fn operation(input: &[u8]) -> NomResult<Operation> {
     map(
-        terminated(pair(many0(operand), operator), content_space),
+        terminated(
+            pair(
+                fold_many0(operand, Operands::new(), |mut operands, operand| {
+                    operands.push(operand);
+                    operands
+                }),
+                operator,
+            ),
+            content_space,
+        ),
         |(operands, operator)| Operation { operator, operands },
     )(input)
 }
 
-fn _content(input: &[u8]) -> NomResult<Content<Vec<Operation>>> {
-    preceded(
-        content_space,
-        map(many0(operation), |operations| Content { operations }),
-    )(input)
-}
-
-pub fn content(input: &[u8]) -> Option<Content<Vec<Operation>>> {
-    strip_nom(_content(input))
+/// An inline image, as [`parser_aux::inline_image`] reads it.
+fn inline_image(input: &[u8]) -> NomResult<Operation> {
+    let (data, entries) = delimited(
+        pair(tag(b"BI"), content_space),
+        many0(pair(terminated(name, content_space), operand)),
+        pair(tag(b"ID"), one_of(" \t\r\n\0\x0C")),
+    )(input)?;
+    let (image, length) = parser_aux::inline_image(entries, data).ok_or(nom::Err::Error(()))?;
+    let (rest, _) = content_space(&data[length..])?;
+    Ok((rest, image))
+}
+
+/// The content operations, with the offset parsing stopped at: the end of the input, unless an operation is cut
+/// off or cannot be parsed.
+pub fn content(input: &[u8]) -> (Content<Vec<Operation>>, usize) {
+    let mut operations = Vec::new();
+    let mut rest = content_space(input).map_or(input, |(rest, _)| rest);
+    while !rest.is_empty() {
+        let parsed = if parser_aux::starts_inline_image(rest) {
+            inline_image(rest)
+        } else {
+            operation(rest)
+        };
+        match parsed {
+            Ok((remaining, operation)) => {
+                operations.push(operation);
+                rest = remaining;
+            }
+            Err(_) => break,
+        }
+    }
+    (Content { operations }, input.len() - rest.len())
 }
 
 #[cfg(test)]
@@ -484,7 +533,7 @@ mod tests {
 
     #[test]
     fn parse_real_number() {
-        let real = |i| tstrip(real(i));
+        let real = |i| tstrip(number(i)).and_then(|number| number.as_float().ok());
 
         assert_eq!(real(b"0.12"), Some(0.12));
         assert_eq!(real(b"-.12"), Some(-0.12));
@@ -540,9 +589,10 @@ BT
 [(b) 20 (ut generally tak) 10 (e more space than \\311)] TJ
 T* (encoded streams.) Tj
 		";
-        let content = tstrip(_content(stream));
+        let (content, offset) = content(stream);
         println!("{:?}", content);
-        assert!(content.is_some());
+        assert_eq!(offset, stream.len());
+        assert!(!content.operations.is_empty());
     }
 
     #[test]
@@ -0,0 +1,210 @@
+use crate::{Object, ObjectId, ParseWarning, ParseWarningKind};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::str::{self, FromStr};
+
+thread_local! {
+    /// The numbers read in a form the syntax does not allow while collecting, by their address, as parsers that
+    /// backtrack read some numbers again.
+    static NORMALIZED: RefCell<Option<BTreeMap<usize, String>>> = const { RefCell::new(None) };
+}
+
+/// Run a parser, returning with its result the numbers it read in a form the syntax does not allow, in the order
+/// of the input.
+pub(crate) fn collect_normalized<R>(parse: impl FnOnce() -> R) -> (R, Vec<String>) {
+    let outer = NORMALIZED.with(|normalized| normalized.replace(Some(BTreeMap::new())));
+    let result = parse();
+    let normalized = NORMALIZED.with(|normalized| normalized.replace(outer));
+    (result, normalized.unwrap_or_default().into_values().collect())
+}
+
+/// The warning for a number read in a form the syntax does not allow.
+pub(crate) fn warning(object: Option<ObjectId>, detail: String) -> ParseWarning {
+    ParseWarning {
+        kind: ParseWarningKind::Number,
+        object,
+        detail,
+    }
+}
+
+/// A number at the start of the input, with its length: an integer, or a real with a point or an exponent.
+///
+/// Numbers are read as viewers read them rather than rejected: several signs are taken as one, which is negative
+/// if any is, an exponent is applied, and integers and reals out of range are clamped to the largest ones. A point
+/// without digits on one side of it is allowed by the syntax.
+pub(crate) fn leading_number(input: &[u8]) -> Option<(Object, usize)> {
+    let digits = |from: usize| {
+        input[from.min(input.len())..]
+            .iter()
+            .take_while(|c| c.is_ascii_digit())
+            .count()
+    };
+    let signs = input.iter().take_while(|&&c| c == b'+' || c == b'-').count();
+    let negative = input[..signs].contains(&b'-');
+    let whole = digits(signs);
+    let mut end = signs + whole;
+    let mut real = false;
+    if input.get(end) == Some(&b'.') {
+        let fraction = digits(end + 1);
+        if whole + fraction > 0 {
+            real = true;
+            end += 1 + fraction;
+        }
+    }
+    if end == signs {
+        return None;
+    }
+    let mut exponent = false;
+    if matches!(input.get(end), Some(b'e') | Some(b'E')) {
+        let sign = matches!(input.get(end + 1), Some(b'+') | Some(b'-')) as usize;
+        let digits = digits(end + 1 + sign);
+        if digits > 0 {
+            exponent = true;
+            end += 1 + sign + digits;
+        }
+    }
+
+    let mut reasons = Vec::new();
+    if signs > 1 {
+        reasons.push("with several signs");
+    }
+    if exponent {
+        reasons.push("with an exponent");
+    }
+    // The digits and the rest are ASCII.
+    let text = format!(
+        "{}{}",
+        if negative { "-" } else { "" },
+        str::from_utf8(&input[signs..end]).unwrap()
+    );
+    let (object, value) = if real || exponent {
+        let mut value = f64::from_str(&text).ok()?;
+        if !value.is_finite() {
+            reasons.push("out of range");
+            value = if negative { f64::MIN } else { f64::MAX };
+        }
+        (Object::Real(value), value.to_string())
+    } else {
+        let value = i64::from_str(&text).unwrap_or_else(|_| {
+            reasons.push("out of range");
+            if negative {
+                i64::MIN
+            } else {
+                i64::MAX
+            }
+        });
+        (Object::Integer(value), value.to_string())
+    };
+    if !reasons.is_empty() {
+        let detail = format!(
+            "number {} {} read as {}",
+            String::from_utf8_lossy(&input[..end]),
+            reasons.join(" and "),
+            value
+        );
+        NORMALIZED.with(|normalized| {
+            if let Some(normalized) = normalized.borrow_mut().as_mut() {
+                normalized.entry(input.as_ptr() as usize).or_insert(detail);
+            }
+        });
+    }
+    Some((object, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::Content;
+    use crate::creator::tests::create_document;
+    use crate::Document;
+
+    #[derive(Debug, PartialEq)]
+    enum Number {
+        Integer(i64),
+        Real(f64),
+    }
+
+    type Row<'a> = (&'a [u8], Option<(Number, usize)>, bool);
+
+    #[test]
+    fn read_deviant_numbers() {
+        // The input, the number read with its length, and whether it is read in a form the syntax does not allow.
+        let table: &[Row] = &[
+            (b"42", Some((Number::Integer(42), 2)), false),
+            (b"-17 0", Some((Number::Integer(-17), 3)), false),
+            (b"+.5", Some((Number::Real(0.5), 3)), false),
+            (b"4.", Some((Number::Real(4.0), 2)), false),
+            (b"-.002]", Some((Number::Real(-0.002), 5)), false),
+            (b"0.00000000000000001", Some((Number::Real(1e-17), 19)), false),
+            (b"--5", Some((Number::Integer(-5), 3)), true),
+            (b"+-2.5", Some((Number::Real(-2.5), 5)), true),
+            (b"++3", Some((Number::Integer(3), 3)), true),
+            (b"6.02e23", Some((Number::Real(6.02e23), 7)), true),
+            (b"1E-3 Tc", Some((Number::Real(0.001), 4)), true),
+            (b"5e", Some((Number::Integer(5), 1)), false),
+            (b"99999999999999999999", Some((Number::Integer(i64::MAX), 20)), true),
+            (b"-99999999999999999999", Some((Number::Integer(i64::MIN), 21)), true),
+            (b"1e999", Some((Number::Real(f64::MAX), 5)), true),
+            (b".", None, false),
+            (b"--", None, false),
+            (b"-e5", None, false),
+        ];
+        for (input, expected, normalized) in table {
+            let (number, warnings) = collect_normalized(|| leading_number(input));
+            let number = number.map(|(object, length)| match object {
+                Object::Integer(value) => (Number::Integer(value), length),
+                Object::Real(value) => (Number::Real(value), length),
+                _ => unreachable!(),
+            });
+            assert_eq!(&number, expected, "{:?}", String::from_utf8_lossy(input));
+            assert_eq!(warnings.len(), *normalized as usize, "{:?}", warnings);
+        }
+        assert_eq!(
+            collect_normalized(|| leading_number(b"--5")).1,
+            ["number --5 with several signs read as -5"]
+        );
+        assert_eq!(
+            collect_normalized(|| leading_number(b"99999999999999999999")).1,
+            ["number 99999999999999999999 out of range read as 9223372036854775807"]
+        );
+    }
+
+    #[test]
+    fn load_and_decode_deviant_numbers() {
+        let mut doc = create_document();
+        let id = doc.add_object(dictionary! { "A" => 7777777, "B" => vec![8888888.into(), 5.into()] });
+        let mut buffer = Vec::new();
+        doc.save_to(&mut buffer).unwrap();
+        let replace = |buffer: &mut Vec<u8>, old: &[u8], new: &[u8]| {
+            let position = buffer.windows(old.len()).position(|window| window == old).unwrap();
+            buffer[position..position + old.len()].copy_from_slice(new);
+        };
+        replace(&mut buffer, b"7777777", b"6.02e23");
+        replace(&mut buffer, b"8888888", b"--88888");
+
+        let loaded = Document::load_mem(&buffer).unwrap();
+        let dict = loaded.get_dictionary(id).unwrap();
+        assert_eq!(dict.get(b"A").unwrap().as_float().unwrap(), 6.02e23);
+        assert_eq!(dict.get(b"B").unwrap().as_array().unwrap()[0].as_i64().unwrap(), -88888);
+        let warnings: Vec<_> = loaded
+            .parse_warnings
+            .iter()
+            .filter(|warning| warning.object == Some(id))
+            .collect();
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings.iter().all(|warning| warning.kind == ParseWarningKind::Number));
+
+        let (content, warnings) = Content::decode_with_warnings(b"--1 +-.5 1e2 m 4. 99999999999999999999 l").unwrap();
+        let operands: Vec<f64> = content
+            .operations
+            .iter()
+            .flat_map(|operation| &operation.operands)
+            .map(|operand| operand.as_float().unwrap())
+            .collect();
+        assert_eq!(operands, [-1.0, -0.5, 100.0, 4.0, i64::MAX as f64]);
+        assert_eq!(warnings.len(), 4);
+        assert!(warnings
+            .iter()
+            .all(|warning| warning.kind == ParseWarningKind::Number && warning.object.is_none()));
+    }
+}
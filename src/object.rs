@@ -1,15 +1,200 @@
-use crate::{Document, Error, Result};
-use linked_hash_map::{self, Iter, IterMut, LinkedHashMap};
-use log::warn;
-use std::fmt;
-use std::str;
+use crate::names::KnownName;
+use crate::{Error, Result};
+#[cfg(feature = "std")]
+use crate::{Codecs, Document};
+use alloc::borrow::ToOwned;
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::str;
+#[cfg(feature = "std")]
+use linked_hash_map::{self, LinkedHashMap};
 
 /// Object identifier consists of two parts: object number and generation number.
 pub type ObjectId = (u32, u16);
 
 /// Dictionary object.
 #[derive(Clone, Default)]
-pub struct Dictionary(LinkedHashMap<Vec<u8>, Object>);
+pub struct Dictionary(DictionaryEntries, TypeCache);
+
+/// The `/Type` of a dictionary, interned when it is set so that the type is checked without looking it up.
+#[derive(Debug, Clone, Copy, Default)]
+enum TypeCache {
+    /// No `/Type` name.
+    #[default]
+    Missing,
+    Known(KnownName),
+    /// A `/Type` name that is not a known one, or one that may have been changed in place, to be looked up.
+    Other,
+}
+
+/// The most entries a dictionary keeps in a vector. Most dictionaries have a handful of entries, which are faster
+/// to look up one by one than through a hash map, and take one allocation rather than one each.
+const SMALL_DICTIONARY_LEN: usize = 16;
+
+/// The entries of a dictionary in their order: in a vector while they are few, and in a hash map beyond that. Without
+/// the `std` feature they stay in the vector.
+#[derive(Clone)]
+enum DictionaryEntries {
+    Small(Vec<(Vec<u8>, Object)>),
+    #[cfg(feature = "std")]
+    Large(LinkedHashMap<Vec<u8>, Object>),
+}
+
+impl Default for DictionaryEntries {
+    fn default() -> Self {
+        DictionaryEntries::Small(Vec::new())
+    }
+}
+
+impl DictionaryEntries {
+    fn get(&self, key: &[u8]) -> Option<&Object> {
+        match self {
+            DictionaryEntries::Small(entries) => entries.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+            #[cfg(feature = "std")]
+            DictionaryEntries::Large(map) => map.get(key),
+        }
+    }
+
+    fn get_mut(&mut self, key: &[u8]) -> Option<&mut Object> {
+        match self {
+            DictionaryEntries::Small(entries) => entries
+                .iter_mut()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            #[cfg(feature = "std")]
+            DictionaryEntries::Large(map) => map.get_mut(key),
+        }
+    }
+
+    /// Set an entry, moving it last if the key was already set, as [`LinkedHashMap::insert`] does.
+    fn insert(&mut self, key: Vec<u8>, value: Object) {
+        match self {
+            DictionaryEntries::Small(entries) => {
+                if let Some(index) = entries.iter().position(|(name, _)| *name == key) {
+                    entries.remove(index);
+                } else if entries.len() == SMALL_DICTIONARY_LEN {
+                    #[cfg(feature = "std")]
+                    {
+                        let mut map: LinkedHashMap<Vec<u8>, Object> = entries.drain(..).collect();
+                        map.insert(key, value);
+                        *self = DictionaryEntries::Large(map);
+                        return;
+                    }
+                }
+                entries.push((key, value));
+            }
+            #[cfg(feature = "std")]
+            DictionaryEntries::Large(map) => {
+                map.insert(key, value);
+            }
+        }
+    }
+
+    fn remove(&mut self, key: &[u8]) -> Option<Object> {
+        match self {
+            DictionaryEntries::Small(entries) => {
+                let index = entries.iter().position(|(name, _)| name == key)?;
+                Some(entries.remove(index).1)
+            }
+            #[cfg(feature = "std")]
+            DictionaryEntries::Large(map) => map.remove(key),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            DictionaryEntries::Small(entries) => entries.len(),
+            #[cfg(feature = "std")]
+            DictionaryEntries::Large(map) => map.len(),
+        }
+    }
+}
+
+/// An iterator over the entries of a [`Dictionary`], in their order.
+pub struct DictionaryIter<'a>(DictionaryIterInner<'a>);
+
+enum DictionaryIterInner<'a> {
+    Small(core::slice::Iter<'a, (Vec<u8>, Object)>),
+    #[cfg(feature = "std")]
+    Large(linked_hash_map::Iter<'a, Vec<u8>, Object>),
+}
+
+impl<'a> Iterator for DictionaryIter<'a> {
+    type Item = (&'a Vec<u8>, &'a Object);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.0 {
+            DictionaryIterInner::Small(ref mut iter) => iter.next().map(|(key, value)| (key, value)),
+            #[cfg(feature = "std")]
+            DictionaryIterInner::Large(ref mut iter) => iter.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.0 {
+            DictionaryIterInner::Small(ref iter) => iter.size_hint(),
+            #[cfg(feature = "std")]
+            DictionaryIterInner::Large(ref iter) => iter.size_hint(),
+        }
+    }
+}
+
+impl<'a> DoubleEndedIterator for DictionaryIter<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match self.0 {
+            DictionaryIterInner::Small(ref mut iter) => iter.next_back().map(|(key, value)| (key, value)),
+            #[cfg(feature = "std")]
+            DictionaryIterInner::Large(ref mut iter) => iter.next_back(),
+        }
+    }
+}
+
+impl<'a> ExactSizeIterator for DictionaryIter<'a> {}
+
+/// An iterator over the entries of a [`Dictionary`], in their order, with mutable values.
+pub struct DictionaryIterMut<'a>(DictionaryIterMutInner<'a>);
+
+enum DictionaryIterMutInner<'a> {
+    Small(core::slice::IterMut<'a, (Vec<u8>, Object)>),
+    #[cfg(feature = "std")]
+    Large(linked_hash_map::IterMut<'a, Vec<u8>, Object>),
+}
+
+impl<'a> Iterator for DictionaryIterMut<'a> {
+    type Item = (&'a Vec<u8>, &'a mut Object);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.0 {
+            DictionaryIterMutInner::Small(ref mut iter) => iter.next().map(|(key, value)| (&*key, value)),
+            #[cfg(feature = "std")]
+            DictionaryIterMutInner::Large(ref mut iter) => iter.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.0 {
+            DictionaryIterMutInner::Small(ref iter) => iter.size_hint(),
+            #[cfg(feature = "std")]
+            DictionaryIterMutInner::Large(ref iter) => iter.size_hint(),
+        }
+    }
+}
+
+impl<'a> DoubleEndedIterator for DictionaryIterMut<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match self.0 {
+            DictionaryIterMutInner::Small(ref mut iter) => iter.next_back().map(|(key, value)| (&*key, value)),
+            #[cfg(feature = "std")]
+            DictionaryIterMutInner::Large(ref mut iter) => iter.next_back(),
+        }
+    }
+}
+
+impl<'a> ExactSizeIterator for DictionaryIterMut<'a> {}
 
 /// Stream object
 /// Warning - all streams must be indirect objects, while
@@ -18,8 +203,8 @@ pub struct Dictionary(LinkedHashMap<Vec<u8>, Object>);
 pub struct Stream {
     /// Associated stream dictionary
     pub dict: Dictionary,
-    /// Contents of the stream in bytes
-    pub content: Vec<u8>,
+    /// Contents of the stream in bytes, see [`Stream::content`].
+    pub(crate) content: Arc<Vec<u8>>,
     /// Can the stream be compressed by the `Document::compress()` function?
     /// Font streams may not be compressed, for example
     pub allows_compression: bool,
@@ -28,8 +213,9 @@ pub struct Stream {
 }
 
 /// Basic PDF object types defined in an enum.
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub enum Object {
+    #[default]
     Null,
     Boolean(bool),
     Integer(i64),
@@ -43,18 +229,13 @@ pub enum Object {
 }
 
 /// String objects can be written in two formats.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub enum StringFormat {
+    #[default]
     Literal,
     Hexadecimal,
 }
 
-impl Default for StringFormat {
-    fn default() -> StringFormat {
-        StringFormat::Literal
-    }
-}
-
 impl From<bool> for Object {
     fn from(value: bool) -> Self {
         Object::Boolean(value)
@@ -137,6 +318,22 @@ impl Object {
         Object::String(s.into(), StringFormat::Literal)
     }
 
+    /// The name of the type of the object, as in error messages.
+    pub fn enum_variant(&self) -> &'static str {
+        match *self {
+            Object::Null => "Null",
+            Object::Boolean(_) => "Boolean",
+            Object::Integer(_) => "Integer",
+            Object::Real(_) => "Real",
+            Object::Name(_) => "Name",
+            Object::String(..) => "String",
+            Object::Array(_) => "Array",
+            Object::Dictionary(_) => "Dictionary",
+            Object::Stream(_) => "Stream",
+            Object::Reference(_) => "Reference",
+        }
+    }
+
     pub fn is_null(&self) -> bool {
         matches!(*self, Object::Null)
     }
@@ -144,21 +341,21 @@ impl Object {
     pub fn as_bool(&self) -> Result<bool> {
         match *self {
             Object::Boolean(ref value) => Ok(*value),
-            _ => Err(Error::Type),
+            _ => Err(Error::type_mismatch("Boolean", self.enum_variant())),
         }
     }
 
     pub fn as_i64(&self) -> Result<i64> {
         match *self {
             Object::Integer(ref value) => Ok(*value),
-            _ => Err(Error::Type),
+            _ => Err(Error::type_mismatch("Integer", self.enum_variant())),
         }
     }
 
     pub fn as_f64(&self) -> Result<f64> {
         match *self {
             Object::Real(ref value) => Ok(*value),
-            _ => Err(Error::Type),
+            _ => Err(Error::type_mismatch("Real", self.enum_variant())),
         }
     }
 
@@ -168,14 +365,14 @@ impl Object {
         match *self {
             Object::Integer(ref value) => Ok(*value as f64),
             Object::Real(ref value) => Ok(*value),
-            _ => Err(Error::Type),
+            _ => Err(Error::type_mismatch("Integer or Real", self.enum_variant())),
         }
     }
 
     pub fn as_name(&self) -> Result<&[u8]> {
         match *self {
             Object::Name(ref name) => Ok(name),
-            _ => Err(Error::Type),
+            _ => Err(Error::type_mismatch("Name", self.enum_variant())),
         }
     }
 
@@ -186,63 +383,63 @@ impl Object {
     pub fn as_str(&self) -> Result<&[u8]> {
         match self {
             Object::String(string, _) => Ok(string),
-            _ => Err(Error::Type),
+            _ => Err(Error::type_mismatch("String", self.enum_variant())),
         }
     }
 
     pub fn as_str_mut(&mut self) -> Result<&mut Vec<u8>> {
         match self {
             Object::String(string, _) => Ok(string),
-            _ => Err(Error::Type),
+            _ => Err(Error::type_mismatch("String", self.enum_variant())),
         }
     }
 
     pub fn as_reference(&self) -> Result<ObjectId> {
         match *self {
             Object::Reference(ref id) => Ok(*id),
-            _ => Err(Error::Type),
+            _ => Err(Error::type_mismatch("Reference", self.enum_variant())),
         }
     }
 
     pub fn as_array(&self) -> Result<&Vec<Object>> {
         match *self {
             Object::Array(ref arr) => Ok(arr),
-            _ => Err(Error::Type),
+            _ => Err(Error::type_mismatch("Array", self.enum_variant())),
         }
     }
 
     pub fn as_array_mut(&mut self) -> Result<&mut Vec<Object>> {
         match *self {
             Object::Array(ref mut arr) => Ok(arr),
-            _ => Err(Error::Type),
+            _ => Err(Error::type_mismatch("Array", self.enum_variant())),
         }
     }
 
     pub fn as_dict(&self) -> Result<&Dictionary> {
         match *self {
             Object::Dictionary(ref dict) => Ok(dict),
-            _ => Err(Error::Type),
+            _ => Err(Error::type_mismatch("Dictionary", self.enum_variant())),
         }
     }
 
     pub fn as_dict_mut(&mut self) -> Result<&mut Dictionary> {
         match *self {
             Object::Dictionary(ref mut dict) => Ok(dict),
-            _ => Err(Error::Type),
+            _ => Err(Error::type_mismatch("Dictionary", self.enum_variant())),
         }
     }
 
     pub fn as_stream(&self) -> Result<&Stream> {
         match *self {
             Object::Stream(ref stream) => Ok(stream),
-            _ => Err(Error::Type),
+            _ => Err(Error::type_mismatch("Stream", self.enum_variant())),
         }
     }
 
     pub fn as_stream_mut(&mut self) -> Result<&mut Stream> {
         match *self {
             Object::Stream(ref mut stream) => Ok(stream),
-            _ => Err(Error::Type),
+            _ => Err(Error::type_mismatch("Stream", self.enum_variant())),
         }
     }
 
@@ -250,7 +447,16 @@ impl Object {
         match *self {
             Object::Dictionary(ref dict) => dict.type_name(),
             Object::Stream(ref stream) => stream.dict.type_name(),
-            _ => Err(Error::Type),
+            _ => Err(Error::type_mismatch("Dictionary or Stream", self.enum_variant())),
+        }
+    }
+
+    /// The type of a dictionary or stream, as [`Dictionary::known_type`] gives it.
+    pub fn known_type(&self) -> Option<KnownName> {
+        match *self {
+            Object::Dictionary(ref dict) => dict.known_type(),
+            Object::Stream(ref stream) => stream.dict.known_type(),
+            _ => None,
         }
     }
 }
@@ -283,25 +489,35 @@ impl fmt::Debug for Object {
 
 impl Dictionary {
     pub fn new() -> Dictionary {
-        Dictionary(LinkedHashMap::new())
+        Dictionary(DictionaryEntries::default(), TypeCache::Missing)
     }
 
     pub fn has(&self, key: &[u8]) -> bool {
-        self.0.contains_key(key)
+        self.0.get(key).is_some()
     }
 
+    /// The value of a key, or an [`Error::DictKey`] with the key as its path.
     pub fn get(&self, key: &[u8]) -> Result<&Object> {
-        self.0.get(key).ok_or(Error::DictKey)
+        self.0.get(key).ok_or_else(|| Error::dict_key(key))
+    }
+
+    /// The value of a key, for lookups of optional keys that would not use the error of [`Dictionary::get`].
+    pub(crate) fn lookup(&self, key: &[u8]) -> Option<&Object> {
+        self.0.get(key)
     }
 
     /// Extract object from dictionary, dereferencing the object if it
     /// is a reference.
+    #[cfg(feature = "std")]
     pub fn get_deref<'a>(&'a self, key: &[u8], doc: &'a Document) -> Result<&'a Object> {
         doc.dereference(self.get(key)?).map(|(_, object)| object)
     }
 
     pub fn get_mut(&mut self, key: &[u8]) -> Result<&mut Object> {
-        self.0.get_mut(key).ok_or(Error::DictKey)
+        if key == b"Type" {
+            self.1 = TypeCache::Other;
+        }
+        self.0.get_mut(key).ok_or_else(|| Error::dict_key(key))
     }
 
     pub fn set<K, V>(&mut self, key: K, value: V)
@@ -309,7 +525,15 @@ impl Dictionary {
         K: Into<Vec<u8>>,
         V: Into<Object>,
     {
-        self.0.insert(key.into(), value.into());
+        let key = key.into();
+        let value = value.into();
+        if key == b"Type" {
+            self.1 = match value {
+                Object::Name(ref name) => KnownName::from_bytes(name).map_or(TypeCache::Other, TypeCache::Known),
+                _ => TypeCache::Missing,
+            };
+        }
+        self.0.insert(key, value);
     }
 
     pub fn len(&self) -> usize {
@@ -321,36 +545,70 @@ impl Dictionary {
     }
 
     pub fn remove(&mut self, key: &[u8]) -> Option<Object> {
+        if key == b"Type" {
+            self.1 = TypeCache::Missing;
+        }
         self.0.remove(key)
     }
 
     pub fn type_name(&self) -> Result<&str> {
-        self.get(b"Type")
-            .and_then(Object::as_name_str)
-            .or_else(|_| self.get(b"Linearized").and(Ok("Linearized")))
+        match self.1 {
+            TypeCache::Known(name) => Ok(name.as_str()),
+            TypeCache::Missing if self.has(b"Linearized") => Ok("Linearized"),
+            TypeCache::Other => match self.lookup(b"Type").map(Object::as_name_str) {
+                Some(Ok(name)) => Ok(name),
+                _ if self.has(b"Linearized") => Ok("Linearized"),
+                Some(Err(err)) => Err(err),
+                None => Err(Error::dict_key(b"Type")),
+            },
+            TypeCache::Missing => Err(Error::dict_key(b"Type")),
+        }
+    }
+
+    /// The type of the dictionary as [`Dictionary::type_name`] gives it, if it is a known name.
+    pub fn known_type(&self) -> Option<KnownName> {
+        match self.1 {
+            TypeCache::Known(name) => Some(name),
+            TypeCache::Missing if self.has(b"Linearized") => Some(KnownName::Linearized),
+            TypeCache::Missing => None,
+            TypeCache::Other => self.type_name().ok().and_then(|name| KnownName::from_bytes(name.as_bytes())),
+        }
     }
 
     pub fn type_is(&self, type_name: &[u8]) -> bool {
-        self.get(b"Type").and_then(Object::as_name).ok() == Some(type_name)
+        match self.1 {
+            TypeCache::Known(name) => name.as_bytes() == type_name,
+            TypeCache::Missing => false,
+            TypeCache::Other => self.lookup(b"Type").and_then(|name| name.as_name().ok()) == Some(type_name),
+        }
     }
 
-    pub fn iter(&self) -> Iter<'_, Vec<u8>, Object> {
-        self.0.iter()
+    pub fn iter(&self) -> DictionaryIter<'_> {
+        DictionaryIter(match self.0 {
+            DictionaryEntries::Small(ref entries) => DictionaryIterInner::Small(entries.iter()),
+            #[cfg(feature = "std")]
+            DictionaryEntries::Large(ref map) => DictionaryIterInner::Large(map.iter()),
+        })
     }
 
-    pub fn iter_mut(&mut self) -> IterMut<'_, Vec<u8>, Object> {
-        self.0.iter_mut()
+    pub fn iter_mut(&mut self) -> DictionaryIterMut<'_> {
+        self.1 = TypeCache::Other;
+        DictionaryIterMut(match self.0 {
+            DictionaryEntries::Small(ref mut entries) => DictionaryIterMutInner::Small(entries.iter_mut()),
+            #[cfg(feature = "std")]
+            DictionaryEntries::Large(ref mut map) => DictionaryIterMutInner::Large(map.iter_mut()),
+        })
     }
 
     pub fn get_font_encoding(&self) -> &str {
-        self.get(b"Encoding")
-            .and_then(Object::as_name_str)
+        self.lookup(b"Encoding")
+            .and_then(|encoding| encoding.as_name_str().ok())
             .unwrap_or("StandardEncoding")
     }
 
     pub fn extend(&mut self, other: &Dictionary) {
         let keep_both_objects =
-            |new_dict: &mut LinkedHashMap<Vec<u8>, Object>, key: &Vec<u8>, value: &Object, old_value: &Object| {
+            |new_dict: &mut Dictionary, key: &Vec<u8>, value: &Object, old_value: &Object| {
                 let mut final_array = Vec::new();
                 match value {
                     Object::Array(array) => {
@@ -363,11 +621,11 @@ impl Dictionary {
                     }
                 }
 
-                new_dict.insert(key.to_owned(), Object::Array(final_array));
+                new_dict.set(key.to_owned(), Object::Array(final_array));
             };
 
-        let mut new_dict = LinkedHashMap::new();
-        for (key, value) in other.0.iter() {
+        let mut new_dict = Dictionary::new();
+        for (key, value) in other.iter() {
             if let Some(old_value) = self.0.get(key) {
                 match old_value {
                     Object::Dictionary(old_dict) => match value {
@@ -375,7 +633,7 @@ impl Dictionary {
                             let mut replaced_dict = old_dict.to_owned();
                             replaced_dict.extend(dict);
 
-                            new_dict.insert(key.to_owned(), Object::Dictionary(replaced_dict));
+                            new_dict.set(key.to_owned(), Object::Dictionary(replaced_dict));
                         }
                         _ => keep_both_objects(&mut new_dict, key, value, old_value),
                     },
@@ -384,60 +642,55 @@ impl Dictionary {
                             let mut replaced_array = old_array.to_owned();
                             replaced_array.extend(array.to_owned());
 
-                            new_dict.insert(key.to_owned(), Object::Array(replaced_array));
+                            new_dict.set(key.to_owned(), Object::Array(replaced_array));
                         }
                         _ => keep_both_objects(&mut new_dict, key, value, old_value),
                     },
                     Object::Integer(old_id) => match value {
                         Object::Integer(id) => {
-                            let mut array = Vec::new();
-                            array.push(Object::Integer(*old_id));
-                            array.push(Object::Integer(*id));
+                            let array = vec![Object::Integer(*old_id), Object::Integer(*id)];
 
-                            new_dict.insert(key.to_owned(), Object::Array(array));
+                            new_dict.set(key.to_owned(), Object::Array(array));
                         }
                         _ => keep_both_objects(&mut new_dict, key, value, old_value),
                     },
                     Object::Real(old_id) => match value {
                         Object::Real(id) => {
-                            let mut array = Vec::new();
-                            array.push(Object::Real(*old_id));
-                            array.push(Object::Real(*id));
+                            let array = vec![Object::Real(*old_id), Object::Real(*id)];
 
-                            new_dict.insert(key.to_owned(), Object::Array(array));
+                            new_dict.set(key.to_owned(), Object::Array(array));
                         }
                         _ => keep_both_objects(&mut new_dict, key, value, old_value),
                     },
                     Object::String(old_ids, old_format) => match value {
                         Object::String(ids, format) => {
-                            let mut array = Vec::new();
-                            array.push(Object::String(old_ids.to_owned(), old_format.to_owned()));
-                            array.push(Object::String(ids.to_owned(), format.to_owned()));
+                            let array = vec![
+                                Object::String(old_ids.to_owned(), old_format.to_owned()),
+                                Object::String(ids.to_owned(), format.to_owned()),
+                            ];
 
-                            new_dict.insert(key.to_owned(), Object::Array(array));
+                            new_dict.set(key.to_owned(), Object::Array(array));
                         }
                         _ => keep_both_objects(&mut new_dict, key, value, old_value),
                     },
                     Object::Reference(old_object_id) => match value {
                         Object::Reference(object_id) => {
-                            let mut array = Vec::new();
-                            array.push(Object::Reference(*old_object_id));
-                            array.push(Object::Reference(*object_id));
+                            let array = vec![Object::Reference(*old_object_id), Object::Reference(*object_id)];
 
-                            new_dict.insert(key.to_owned(), Object::Array(array));
+                            new_dict.set(key.to_owned(), Object::Array(array));
                         }
                         _ => keep_both_objects(&mut new_dict, key, value, old_value),
                     },
                     _ => {
-                        new_dict.insert(key.to_owned(), old_value.to_owned());
+                        new_dict.set(key.to_owned(), old_value.to_owned());
                     }
                 }
             } else {
-                new_dict.insert(key.to_owned(), value.to_owned());
+                new_dict.set(key.to_owned(), value.to_owned());
             }
         }
 
-        self.0 = new_dict;
+        *self = new_dict;
     }
 }
 
@@ -470,14 +723,14 @@ impl fmt::Debug for Dictionary {
 
 impl<'a> IntoIterator for &'a Dictionary {
     type Item = (&'a Vec<u8>, &'a Object);
-    type IntoIter = linked_hash_map::Iter<'a, Vec<u8>, Object>;
+    type IntoIter = DictionaryIter<'a>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.0.iter()
+        self.iter()
     }
 }
 
-use std::iter::FromIterator;
+use core::iter::FromIterator;
 impl<K: Into<Vec<u8>>> FromIterator<(K, Object)> for Dictionary {
     fn from_iter<I: IntoIterator<Item = (K, Object)>>(iter: I) -> Self {
         let mut dict = Dictionary::new();
@@ -493,7 +746,7 @@ impl Stream {
         dict.set("Length", content.len() as i64);
         Stream {
             dict,
-            content,
+            content: Arc::new(content),
             allows_compression: true,
             start_position: None,
         }
@@ -502,7 +755,7 @@ impl Stream {
     pub fn with_position(dict: Dictionary, position: usize) -> Stream {
         Stream {
             dict,
-            content: vec![],
+            content: Arc::default(),
             allows_compression: true,
             start_position: Some(position),
         }
@@ -519,11 +772,15 @@ impl Stream {
     // Return first filter
     pub fn filter(&self) -> Result<String> {
         self.filters()
-            .and_then(|f| f.into_iter().next().ok_or(Error::ObjectNotFound))
+            .and_then(|f| f.into_iter().next().ok_or_else(|| Error::type_mismatch("Name", "empty Array").at_key(b"Filter")))
     }
 
     pub fn filters(&self) -> Result<Vec<String>> {
-        let filter = self.dict.get(b"Filter")?;
+        Self::filters_of(&self.dict)
+    }
+
+    fn filters_of(dict: &Dictionary) -> Result<Vec<String>> {
+        let filter = dict.get(b"Filter")?;
 
         if let Ok(name) = filter.as_name_str() {
             Ok(vec![name.into()])
@@ -531,18 +788,21 @@ impl Stream {
             // It is an error if a single conversion fails.
             names
                 .iter()
-                .map(|n| match Object::as_name_str(n) {
-                    Ok(n) => Ok(String::from(n)),
-                    Err(_) => Err(Error::Type),
-                })
+                .map(|n| Object::as_name_str(n).map(String::from).map_err(|err| err.at_key(b"Filter")))
                 .collect()
         } else {
-            Err(Error::Type)
+            Err(Error::type_mismatch("Name or Array", filter.enum_variant()).at_key(b"Filter"))
         }
     }
 
+    /// Contents of the stream in bytes, shared between clones of the stream until one of them changes them
+    /// through [`Stream::content_mut`] or [`Stream::set_content`].
+    pub fn content(&self) -> &[u8] {
+        &self.content
+    }
+
     pub fn set_content(&mut self, content: Vec<u8>) {
-        self.content = content;
+        self.content = Arc::new(content);
         self.dict.set("Length", self.content.len() as i64);
     }
 
@@ -550,15 +810,27 @@ impl Stream {
         self.dict.remove(b"DecodeParms");
         self.dict.remove(b"Filter");
         self.dict.set("Length", content.len() as i64);
-        self.content = content;
+        self.content = Arc::new(content);
+    }
+
+    /// The content for changing it in place, copied first if it is shared with other streams. `/Length` is not
+    /// updated.
+    pub fn content_mut(&mut self) -> &mut Vec<u8> {
+        Arc::make_mut(&mut self.content)
+    }
+
+    /// The content, without copying it unless it is shared with other streams.
+    pub fn into_content(self) -> Vec<u8> {
+        Arc::try_unwrap(self.content).unwrap_or_else(|content| content.as_ref().clone())
     }
 
+    #[cfg(feature = "std")]
     pub fn compress(&mut self) -> Result<()> {
         use flate2::write::ZlibEncoder;
         use flate2::Compression;
         use std::io::prelude::*;
 
-        if self.dict.get(b"Filter").is_err() {
+        if !self.dict.has(b"Filter") {
             let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
             encoder.write_all(self.content.as_slice())?;
             let compressed = encoder.finish()?;
@@ -570,102 +842,91 @@ impl Stream {
         Ok(())
     }
 
+    #[cfg(feature = "std")]
     pub fn decompressed_content(&self) -> Result<Vec<u8>> {
-        let params = self.dict.get(b"DecodeParms").and_then(Object::as_dict).ok();
-        let filters = self.filters()?;
-
-        if self.dict.get(b"Subtype").and_then(Object::as_name_str).ok() == Some("Image") {
-            return Err(Error::Type);
-        }
-
-        let mut input = self.content.as_slice();
-        let mut output = None;
-
-        // Filters are in decoding order.
-        for filter in filters {
-            output = Some(match filter.as_str() {
-                "FlateDecode" => Self::decompress_zlib(input, params)?,
-                "LZWDecode" => Self::decompress_lzw(input, params)?,
-                _ => {
-                    return Err(Error::Type);
-                }
-            });
-            input = output.as_ref().unwrap();
-        }
-
-        output.ok_or(Error::Type)
+        Codecs::with(|codecs| self.decompressed_content_with(codecs))
     }
 
-    fn decompress_lzw(input: &[u8], params: Option<&Dictionary>) -> Result<Vec<u8>> {
-        use weezl::{BitOrder, decode::Decoder};
-        const MIN_BITS: u8 = 9;
-
-        let early_change = params
-            .and_then(|p| p.get(b"EarlyChange").ok())
-            .and_then(|p| Object::as_i64(p).ok())
-            .map(|v| v != 0)
-            .unwrap_or(true);
-
-        let mut decoder = if early_change {
-            Decoder::with_tiff_size_switch(BitOrder::Msb, MIN_BITS - 1)
-        } else {
-            Decoder::new(BitOrder::Msb, MIN_BITS - 1)
-        };
-
-        let output = Self::decompress_lzw_loop(input, &mut decoder);
-        Self::decompress_predictor(output, params)
+    /// Decode the content as [`Stream::decompressed_content`] does, with the decoding state of `codecs`.
+    #[cfg(feature = "std")]
+    pub fn decompressed_content_with(&self, codecs: &Codecs) -> Result<Vec<u8>> {
+        codecs.decode(&self.dict, &self.content)
     }
 
-    fn decompress_lzw_loop(input: &[u8], decoder: &mut weezl::decode::Decoder) -> Vec<u8> {
-        let mut output = vec![];
-
-        let result = decoder.into_stream(&mut output).decode_all(input);
-        if let Err(err) = result.status {
-            warn!("{}", err);
-        }
-
-        output
+    #[cfg(feature = "std")]
+    pub fn decompress(&mut self) {
+        Codecs::with(|codecs| self.decompress_with(codecs))
     }
 
-    fn decompress_zlib(input: &[u8], params: Option<&Dictionary>) -> Result<Vec<u8>> {
-        use flate2::read::ZlibDecoder;
-        use std::io::prelude::*;
-
-        let mut output = Vec::with_capacity(input.len() * 2);
-        let mut decoder = ZlibDecoder::new(input);
-
-        if !input.is_empty() {
-            decoder.read_to_end(&mut output).unwrap_or_else(|err| {
-                warn!("{}", err);
-                0
-            });
+    /// Decompress the stream as [`Stream::decompress`] does, with the decoding state of `codecs`.
+    #[cfg(feature = "std")]
+    pub fn decompress_with(&mut self, codecs: &Codecs) {
+        if let Ok(data) = self.decompressed_content_with(codecs) {
+            self.dict.remove(b"DecodeParms");
+            self.dict.remove(b"Filter");
+            self.set_content(data);
         }
-        Self::decompress_predictor(output, params)
     }
+}
 
-    fn decompress_predictor(mut data: Vec<u8>, params: Option<&Dictionary>) -> Result<Vec<u8>> {
-        use crate::filters::png;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        if let Some(params) = params {
-            let predictor = params.get(b"Predictor").and_then(Object::as_i64).unwrap_or(1);
-            if (10..=15).contains(&predictor) {
-                let pixels_per_row = params.get(b"Columns").and_then(Object::as_i64).unwrap_or(1) as usize;
-                let colors = params.get(b"Colors").and_then(Object::as_i64).unwrap_or(1) as usize;
-                let bits = params.get(b"BitsPerComponent").and_then(Object::as_i64).unwrap_or(8) as usize;
-                let bytes_per_pixel = colors * bits / 8;
-                data = png::decode_frame(data.as_slice(), bytes_per_pixel, pixels_per_row)?;
-            }
-            Ok(data)
-        } else {
-            Ok(data)
+    #[test]
+    fn dictionaries_keep_their_order() {
+        let mut dict = Dictionary::new();
+        for i in 0..40 {
+            dict.set(format!("K{}", i), i);
         }
-    }
-
-    pub fn decompress(&mut self) {
-        if let Ok(data) = self.decompressed_content() {
-            self.dict.remove(b"DecodeParms");
-            self.dict.remove(b"Filter");
-            self.set_content(data);
+        // Setting a key again moves it last, in small and large dictionaries alike.
+        dict.set("K3", 3);
+        dict.remove(b"K10");
+        let keys: Vec<String> = dict.iter().map(|(key, _)| String::from_utf8_lossy(key).into_owned()).collect();
+        assert_eq!(keys.len(), 39);
+        assert_eq!(keys[..4], ["K0", "K1", "K2", "K4"]);
+        assert_eq!(keys.last().unwrap(), "K3");
+        assert_eq!(dict.get(b"K39").and_then(Object::as_i64).unwrap(), 39);
+
+        let mut small: Dictionary = vec![("A", Object::from(1)), ("B", 2.into())].into_iter().collect();
+        small.set("A", 3);
+        let keys: Vec<&[u8]> = small.iter().map(|(key, _)| key.as_slice()).collect();
+        assert_eq!(keys, [&b"B"[..], b"A"]);
+        assert!(small.iter_mut().all(|(_, value)| value.as_i64().is_ok()));
+    }
+
+    #[test]
+    fn cached_dictionary_types() {
+        let mut dict = dictionary! { "Type" => "Page" };
+        assert_eq!(dict.known_type(), Some(KnownName::Page));
+        assert!(dict.type_is(b"Page") && !dict.type_is(b"Pages"));
+
+        // Names that are not known, and types changed in place, are looked up.
+        dict.set("Type", "ProprietaryThing");
+        assert_eq!(dict.known_type(), None);
+        assert_eq!(dict.type_name().unwrap(), "ProprietaryThing");
+        *dict.get_mut(b"Type").unwrap() = Object::Name(b"Pages".to_vec());
+        assert_eq!(dict.known_type(), Some(KnownName::Pages));
+        for (_, value) in dict.iter_mut() {
+            *value = Object::Name(b"Font".to_vec());
+        }
+        assert!(dict.type_is(b"Font"));
+        assert_eq!(dict.type_name().unwrap(), "Font");
+
+        dict.remove(b"Type");
+        assert!(dict.type_name().is_err() && !dict.type_is(b"Font"));
+        dict.set("Linearized", 1);
+        assert_eq!(dict.known_type(), Some(KnownName::Linearized));
+        dict.set("Type", 12);
+        assert_eq!(dict.type_name().unwrap(), "Linearized");
+
+        // A /Type that is not a name can become one in place.
+        let mut dict = dictionary! { "Type" => 12 };
+        assert!(!dict.type_is(b"Page"));
+        for (_, value) in dict.iter_mut() {
+            *value = Object::Name(b"Page".to_vec());
         }
+        assert!(dict.type_is(b"Page"));
+        assert_eq!(dict.known_type(), Some(KnownName::Page));
     }
 }
@@ -1,9 +1,9 @@
 #![cfg(any(feature = "pom_parser", feature = "nom_parser"))]
 
-use crate::parser;
-use crate::{Error, Object, ObjectId, Result, Stream};
+use crate::{numbers, parser};
+use crate::{Codecs, Object, ObjectId, ParseWarning, ParseWarningKind, Result, Stream};
 use std::collections::BTreeMap;
-use std::str::FromStr;
+use std::convert::TryFrom;
 
 #[cfg(feature = "rayon")]
 use rayon::prelude::*;
@@ -11,43 +11,216 @@ use rayon::prelude::*;
 #[derive(Debug)]
 pub struct ObjectStream {
     pub objects: BTreeMap<ObjectId, Object>,
+    /// Where the header of the stream did not match its content.
+    pub warnings: Vec<ParseWarning>,
 }
 
 impl ObjectStream {
-    pub fn new(stream: &mut Stream) -> Result<ObjectStream> {
-        stream.decompress();
+    pub fn new(id: ObjectId, stream: &Stream) -> Result<ObjectStream> {
+        if !stream.dict.has(b"Filter") {
+            return Self::parse(id, stream, &stream.content);
+        }
+        let decoded = Codecs::with(|codecs| {
+            codecs.decode_with(&stream.dict, &stream.content, |data| Self::parse(id, stream, data))
+        });
+        // Content that cannot be decoded is read as it is.
+        decoded.unwrap_or_else(|_| Self::parse(id, stream, &stream.content))
+    }
+
+    fn parse(id: ObjectId, stream: &Stream, content: &[u8]) -> Result<ObjectStream> {
+        let mut object_stream = ObjectStream {
+            objects: BTreeMap::new(),
+            warnings: Vec::new(),
+        };
+        if content.is_empty() {
+            return Ok(object_stream);
+        }
+        let mut warn = |kind, detail: String| {
+            object_stream.warnings.push(ParseWarning {
+                kind,
+                object: Some(id),
+                detail,
+            })
+        };
+
+        let first_offset = stream
+            .dict
+            .get(b"First")?
+            .as_i64()
+            .map_err(|err| err.at_key(b"First"))? as usize;
+        let count = stream
+            .dict
+            .get(b"N")?
+            .as_i64()
+            .map_err(|err| err.at_key(b"N"))? as usize;
 
-        if stream.content.is_empty() {
-            return Ok(ObjectStream {
-                objects: BTreeMap::new(),
-            });
+        // Without a /First in the content, the header is taken to be the number of pairs given by /N.
+        let first_valid = first_offset <= content.len();
+        let header = &content[..first_offset.min(content.len())];
+        let (pairs, header_end) = header_pairs(header, if first_valid { usize::MAX } else { count });
+        if pairs.len() != count {
+            warn(
+                ParseWarningKind::ObjectStreamCount,
+                format!("/N is {} but the header has {} objects", count, pairs.len()),
+            );
         }
 
-        let first_offset = stream.dict.get(b"First").and_then(Object::as_i64)? as usize;
-        let _count = stream.dict.get(b"N").and_then(Object::as_i64)? as usize;
+        let past_end = pairs
+            .iter()
+            .filter(|(_, offset)| first_offset.saturating_add(*offset) >= content.len())
+            .count();
+        if first_valid && past_end == 0 {
+            let chunks_filter_map = |&(number, offset): &(u32, usize)| {
+                let (object, normalized) =
+                    numbers::collect_normalized(|| parser::direct_object(&content[first_offset + offset..]));
+                Some(((number, 0), object?, normalized))
+            };
+            #[cfg(feature = "rayon")]
+            let objects: Vec<_> = pairs.par_iter().filter_map(chunks_filter_map).collect();
+            #[cfg(not(feature = "rayon"))]
+            let objects: Vec<_> = pairs.iter().filter_map(chunks_filter_map).collect();
+            for (id, object, normalized) in objects {
+                object_stream.objects.insert(id, object);
+                let warnings = normalized.into_iter().map(|detail| numbers::warning(Some(id), detail));
+                object_stream.warnings.extend(warnings);
+            }
+            return Ok(object_stream);
+        }
 
-        let index_block = stream.content.get(..first_offset).ok_or(Error::Offset(first_offset))?;
+        // The objects follow one another, so those before the first that cannot be read are found by reading them
+        // in order.
+        let mut position = if first_valid { first_offset } else { header_end };
+        let mut objects = BTreeMap::new();
+        let mut number_warnings = Vec::new();
+        for &(number, _) in &pairs {
+            position += content[position..].iter().take_while(|&&byte| is_whitespace(byte)).count();
+            let (object, normalized) =
+                numbers::collect_normalized(|| parser::leading_direct_object(&content[position..]));
+            match object {
+                Some((object, length)) => {
+                    objects.insert((number, 0), object);
+                    let warnings = normalized.into_iter().map(|detail| numbers::warning(Some((number, 0)), detail));
+                    number_warnings.extend(warnings);
+                    position += length;
+                }
+                None => break,
+            }
+        }
+        let problem = if first_valid {
+            format!("{} objects are past the end of the content", past_end)
+        } else {
+            format!("/First {} is past the end of the content", first_offset)
+        };
+        warn(
+            ParseWarningKind::ObjectStreamOffsets,
+            format!(
+                "{} of {} bytes; {} of {} objects read in order",
+                problem,
+                content.len(),
+                objects.len(),
+                pairs.len()
+            ),
+        );
+        object_stream.warnings.extend(number_warnings);
+        object_stream.objects = objects;
+        Ok(object_stream)
+    }
+}
 
-        let numbers_str = std::str::from_utf8(index_block)?;
-        let numbers: Vec<_> = numbers_str
-            .split_whitespace()
-            .map(|number| u32::from_str(number).ok())
-            .collect();
-        let len = numbers.len() / 2 * 2; // Ensure only pairs.
+/// The pairs of object number and offset at the start of a header, up to the first that is not one or to a limit
+/// of pairs, and the offset where the last of them ends.
+fn header_pairs(header: &[u8], limit: usize) -> (Vec<(u32, usize)>, usize) {
+    let mut numbers = Vec::new();
+    let mut position = 0;
+    while numbers.len() / 2 < limit {
+        let start = position + header[position..].iter().take_while(|&&byte| is_whitespace(byte)).count();
+        let end = start + header[start..].iter().take_while(|byte| byte.is_ascii_digit()).count();
+        let separated = header.get(end).is_none_or(|&byte| is_whitespace(byte));
+        let number = std::str::from_utf8(&header[start..end])
+            .ok()
+            .and_then(|digits| digits.parse::<usize>().ok());
+        match number {
+            Some(number) if separated => numbers.push((number, end)),
+            _ => break,
+        }
+        position = end;
+    }
+    let pairs = numbers
+        .chunks_exact(2)
+        .map_while(|pair| Some((u32::try_from(pair[0].0).ok()?, pair[1].0)))
+        .collect::<Vec<_>>();
+    let end = pairs.len().checked_sub(1).map_or(0, |last| numbers[2 * last + 1].1);
+    (pairs, end)
+}
 
-        let chunks_filter_map = |chunk: &[_]| {
-            let id = chunk[0]?;
-            let offset = first_offset + chunk[1]? as usize;
+fn is_whitespace(byte: u8) -> bool {
+    matches!(byte, b' ' | b'\t' | b'\n' | b'\r' | b'\x0c' | b'\0')
+}
 
-            let object = parser::direct_object(&stream.content[offset..])?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Document;
 
-            Some(((id, 0), object))
+    fn object_stream(header: &str, objects: &str, count: i64) -> Stream {
+        let content = format!("{}{}", header, objects).into_bytes();
+        let dict = dictionary! {
+            "Type" => "ObjStm",
+            "N" => count,
+            "First" => header.len() as i64,
         };
-        #[cfg(feature = "rayon")]
-        let objects = numbers[..len].par_chunks(2).filter_map(chunks_filter_map).collect();
-        #[cfg(not(feature = "rayon"))]
-        let objects = numbers[..len].chunks(2).filter_map(chunks_filter_map).collect();
+        Stream::new(dict, content)
+    }
+
+    #[test]
+    fn truncated_object_stream_keeps_its_first_objects() {
+        let objects = "<</A 1>>\n[2 0 R]\n(three)\n<</D (four)>>";
+        // The stream is cut in the middle of its fourth object, the third declares an offset past the end.
+        let stream = object_stream("5 0 6 9 7 17 8 60 ", &objects[..30], 4);
+        let parsed = ObjectStream::new((4, 0), &stream).unwrap();
+        assert_eq!(parsed.objects.keys().copied().collect::<Vec<_>>(), [(5, 0), (6, 0), (7, 0)]);
+        assert_eq!(parsed.warnings.len(), 1);
+        assert_eq!(parsed.warnings[0].kind, ParseWarningKind::ObjectStreamOffsets);
+        assert_eq!(parsed.warnings[0].object, Some((4, 0)));
 
-        Ok(ObjectStream { objects })
+        // /N is one too many and /First past the content: the pairs found are used.
+        let mut stream = object_stream("5 0 6 9 ", &objects[..16], 3);
+        stream.dict.set("First", 200);
+        let parsed = ObjectStream::new((4, 0), &stream).unwrap();
+        assert_eq!(parsed.objects.len(), 2);
+        let kinds: Vec<_> = parsed.warnings.iter().map(|warning| warning.kind).collect();
+        assert_eq!(kinds, [ParseWarningKind::ObjectStreamCount, ParseWarningKind::ObjectStreamOffsets]);
+
+        // Loaded documents have the objects recovered as any others.
+        let content = format!("5 0 6 9 7 17 8 60 {}", &objects[..30]);
+        let objects = [
+            "<</Type/Catalog/Pages 2 0 R>>".to_string(),
+            "<</Type/Pages/Kids[]/Count 0>>".to_string(),
+            format!(
+                "<</Type/ObjStm/N 4/First 18/Length {}>>stream\n{}\nendstream",
+                content.len(),
+                content
+            ),
+        ];
+        let mut pdf = String::from("%PDF-1.5\n");
+        let mut xref = String::from("0 5\n0000000000 65535 f \n");
+        for (id, object) in [1, 2, 4].iter().zip(&objects) {
+            if *id == 4 {
+                xref.push_str("0000000000 65535 f \n");
+            }
+            xref.push_str(&format!("{:010} 00000 n \n", pdf.len()));
+            pdf.push_str(&format!("{} 0 obj\n{}\nendobj\n", id, object));
+        }
+        let start = pdf.len();
+        pdf.push_str(&format!(
+            "xref\n{}trailer\n<</Root 1 0 R/Size 5>>\nstartxref\n{}\n%%EOF",
+            xref, start
+        ));
+        let loaded = Document::load_mem(pdf.as_bytes()).unwrap();
+        assert!(loaded.get_dictionary((5, 0)).unwrap().has(b"A"));
+        assert_eq!(loaded.get_object((6, 0)).and_then(Object::as_array).unwrap().len(), 1);
+        assert!(loaded.get_object((8, 0)).is_err());
+        assert_eq!(loaded.parse_warnings.len(), 1);
+        assert_eq!(loaded.parse_warnings[0].object, Some((4, 0)));
     }
 }
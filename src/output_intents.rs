@@ -0,0 +1,234 @@
+use crate::encodings::{decode_text_string, encode_text_string};
+use crate::{Document, Error, Object, ObjectId, Result, Stream, StringFormat};
+
+/// The `/S` of an output intent: the standard whose requirements the intended output condition meets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutputIntentSubtype {
+    /// `GTS_PDFA1`, for PDF/A.
+    PdfA,
+    /// `GTS_PDFX`, for PDF/X.
+    PdfX,
+    Other(String),
+}
+
+impl OutputIntentSubtype {
+    fn name(&self) -> &str {
+        match self {
+            OutputIntentSubtype::PdfA => "GTS_PDFA1",
+            OutputIntentSubtype::PdfX => "GTS_PDFX",
+            OutputIntentSubtype::Other(name) => name,
+        }
+    }
+
+    fn from_name(name: &[u8]) -> OutputIntentSubtype {
+        match name {
+            b"GTS_PDFA1" => OutputIntentSubtype::PdfA,
+            b"GTS_PDFX" => OutputIntentSubtype::PdfX,
+            _ => OutputIntentSubtype::Other(String::from_utf8_lossy(name).into_owned()),
+        }
+    }
+}
+
+/// An output intent of the catalog, from [`Document::output_intents`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputIntentInfo {
+    /// The output intent dictionary, if it is an indirect object.
+    pub id: Option<ObjectId>,
+    pub subtype: OutputIntentSubtype,
+    /// `/OutputConditionIdentifier`, such as `FOGRA39` or `sRGB IEC61966-2.1`.
+    pub identifier: Option<String>,
+    /// `/OutputCondition`, a description of the output condition.
+    pub condition: Option<String>,
+    /// `/RegistryName`, the registry of the output condition identifier.
+    pub registry: Option<String>,
+    pub info: Option<String>,
+    /// The ICC profile stream of `/DestOutputProfile`.
+    pub profile: Option<ObjectId>,
+    /// The number of color components of the profile, from its `/N`.
+    pub components: Option<i64>,
+}
+
+impl Document {
+    /// The output intents of the catalog, skipping entries that are not dictionaries.
+    pub fn output_intents(&self) -> Vec<OutputIntentInfo> {
+        let intents = match self
+            .catalog()
+            .and_then(|catalog| catalog.get_deref(b"OutputIntents", self))
+            .and_then(Object::as_array)
+        {
+            Ok(intents) => intents,
+            Err(_) => return Vec::new(),
+        };
+        intents
+            .iter()
+            .filter_map(|intent| {
+                let (id, intent) = self.dereference(intent).ok()?;
+                let intent = intent.as_dict().ok()?;
+                let text = |key: &[u8]| {
+                    intent
+                        .get_deref(key, self)
+                        .and_then(Object::as_str)
+                        .ok()
+                        .map(decode_text_string)
+                };
+                let profile = intent.get(b"DestOutputProfile").and_then(Object::as_reference).ok();
+                Some(OutputIntentInfo {
+                    id,
+                    subtype: OutputIntentSubtype::from_name(
+                        intent.get(b"S").and_then(Object::as_name).unwrap_or_default(),
+                    ),
+                    identifier: text(b"OutputConditionIdentifier"),
+                    condition: text(b"OutputCondition"),
+                    registry: text(b"RegistryName"),
+                    info: text(b"Info"),
+                    profile,
+                    components: profile
+                        .and_then(|profile| self.get_object(profile).and_then(Object::as_stream).ok())
+                        .and_then(|profile| profile.dict.get(b"N").and_then(Object::as_i64).ok()),
+                })
+            })
+            .collect()
+    }
+
+    /// Set the output intent of a subtype, replacing the one the catalog has of that subtype or adding one, and
+    /// return its id.
+    ///
+    /// The identifier is given as `/OutputConditionIdentifier` and `/Info`. An ICC profile is embedded,
+    /// compressed, as the `/DestOutputProfile`, with the number of components of the color space of its header;
+    /// a profile is required unless the identifier is that of a registered output condition. The replaced output
+    /// intent and its profile are left for [`Document::prune_objects`] to delete.
+    pub fn set_output_intent(
+        &mut self, subtype: OutputIntentSubtype, identifier: &str, icc_profile: Option<&[u8]>,
+    ) -> Result<ObjectId> {
+        let root_id = self.trailer.get(b"Root")?.as_reference()?;
+        let text = || Object::String(encode_text_string(identifier), StringFormat::Literal);
+        let mut intent = dictionary! {
+            "Type" => "OutputIntent",
+            "S" => subtype.name(),
+            "OutputConditionIdentifier" => text(),
+            "Info" => text(),
+        };
+        if let Some(profile) = icc_profile {
+            let components = icc_components(profile)?;
+            let mut stream = Stream::new(dictionary! { "N" => components }, profile.to_vec());
+            stream.compress()?;
+            intent.set("DestOutputProfile", self.add_object(stream));
+        }
+        let intent_id = self.add_object(intent);
+
+        let (intents_id, mut intents) = match self
+            .catalog()?
+            .get(b"OutputIntents")
+            .map(|intents| self.dereference(intents))
+        {
+            Ok(Ok((id, Object::Array(intents)))) => (id, intents.clone()),
+            _ => (None, Vec::new()),
+        };
+        let position = intents.iter().position(|intent| {
+            let intent = self.dereference(intent).and_then(|(_, intent)| intent.as_dict());
+            intent
+                .and_then(|intent| intent.get(b"S"))
+                .and_then(Object::as_name)
+                .ok()
+                == Some(subtype.name().as_bytes())
+        });
+        match position {
+            Some(position) => intents[position] = intent_id.into(),
+            None => intents.push(intent_id.into()),
+        }
+        match intents_id {
+            Some(id) => {
+                self.objects.insert(id, Object::Array(intents));
+            }
+            None => self
+                .get_object_mut(root_id)?
+                .as_dict_mut()?
+                .set("OutputIntents", intents),
+        }
+        Ok(intent_id)
+    }
+}
+
+/// The number of color components of an ICC profile, from the data color space of its header.
+fn icc_components(profile: &[u8]) -> Result<i64> {
+    if profile.len() < 128 || &profile[36..40] != b"acsp" {
+        return Err(Error::Syntax("ICC profile has no valid header".to_string()));
+    }
+    match &profile[16..20] {
+        b"GRAY" => Ok(1),
+        b"RGB " | b"Lab " | b"XYZ " | b"YCbr" | b"Luv " | b"Yxy " | b"HSV " | b"HLS " => Ok(3),
+        b"CMYK" => Ok(4),
+        b"CMY " => Ok(3),
+        // Multichannel color spaces, `2CLR` to `FCLR`.
+        [count, b'C', b'L', b'R'] => char::from(*count)
+            .to_digit(16)
+            .filter(|&count| count >= 2)
+            .map(i64::from)
+            .ok_or_else(|| Error::Syntax("ICC profile has an unknown color space".to_string())),
+        _ => Err(Error::Syntax("ICC profile has an unknown color space".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::creator::tests::create_document;
+
+    fn icc_profile(color_space: &[u8; 4]) -> Vec<u8> {
+        let mut profile = vec![0; 512];
+        profile[16..20].copy_from_slice(color_space);
+        profile[36..40].copy_from_slice(b"acsp");
+        profile
+    }
+
+    #[test]
+    fn set_and_replace_output_intents() {
+        let mut doc = create_document();
+        assert!(doc.output_intents().is_empty());
+        let first = doc
+            .set_output_intent(
+                OutputIntentSubtype::PdfA,
+                "sRGB IEC61966-2.1",
+                Some(&icc_profile(b"RGB ")),
+            )
+            .unwrap();
+        doc.set_output_intent(OutputIntentSubtype::PdfX, "FOGRA39", None)
+            .unwrap();
+        let replaced = doc
+            .set_output_intent(OutputIntentSubtype::PdfA, "Coated FOGRA39", Some(&icc_profile(b"CMYK")))
+            .unwrap();
+        assert_ne!(first, replaced);
+
+        let intents = doc.output_intents();
+        assert_eq!(intents.len(), 2);
+        assert_eq!(intents[0].id, Some(replaced));
+        assert_eq!(intents[0].subtype, OutputIntentSubtype::PdfA);
+        assert_eq!(intents[0].identifier.as_deref(), Some("Coated FOGRA39"));
+        assert_eq!(intents[0].info.as_deref(), Some("Coated FOGRA39"));
+        assert_eq!(intents[0].components, Some(4));
+        let profile = doc
+            .get_object(intents[0].profile.unwrap())
+            .and_then(Object::as_stream)
+            .unwrap();
+        assert_eq!(
+            profile.dict.get(b"Filter").and_then(Object::as_name_str).unwrap(),
+            "FlateDecode"
+        );
+        assert_eq!(profile.decompressed_content().unwrap(), icc_profile(b"CMYK"));
+        assert_eq!(intents[1].subtype, OutputIntentSubtype::PdfX);
+        assert_eq!(intents[1].profile, None);
+        assert!(doc
+            .pdfa_precheck(2)
+            .iter()
+            .all(|issue| issue.kind != crate::PdfaIssueKind::MissingOutputIntent));
+    }
+
+    #[test]
+    fn components_of_icc_profiles() {
+        assert_eq!(icc_components(&icc_profile(b"GRAY")).unwrap(), 1);
+        assert_eq!(icc_components(&icc_profile(b"Lab ")).unwrap(), 3);
+        assert_eq!(icc_components(&icc_profile(b"6CLR")).unwrap(), 6);
+        assert!(icc_components(&icc_profile(b"????")).is_err());
+        assert!(icc_components(b"not a profile").is_err());
+    }
+}
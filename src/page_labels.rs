@@ -0,0 +1,192 @@
+use crate::encodings::decode_text_string;
+use crate::{Document, Object};
+use log::warn;
+use std::convert::TryFrom;
+
+/// The numbering style of a range of page labels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageLabelStyle {
+    /// Arabic numerals, `D`.
+    Decimal,
+    /// Roman numerals, `R` for uppercase and `r` for lowercase.
+    UpperRoman,
+    LowerRoman,
+    /// Letters, `A` to `Z`, then `AA` to `ZZ` and so on; `A` for uppercase and `a` for lowercase.
+    UpperLetters,
+    LowerLetters,
+}
+
+/// A range of pages labelled alike, from [`Document::page_labels`]. The range runs up to the first page of the
+/// next one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageLabelRange {
+    /// The number of the first page of the range, starting from 1.
+    pub first_page: u32,
+    /// The numbering style, or `None` for labels made of the prefix only.
+    pub style: Option<PageLabelStyle>,
+    pub prefix: String,
+    /// The number of the first page of the range in its numbering, `/St`.
+    pub start: i64,
+}
+
+impl PageLabelRange {
+    /// The label of a page of the range, by its number in the document.
+    pub fn label(&self, page_number: u32) -> String {
+        let number = self.start + i64::from(page_number) - i64::from(self.first_page);
+        let numbering = match self.style {
+            Some(PageLabelStyle::Decimal) => number.to_string(),
+            Some(PageLabelStyle::UpperRoman) => roman(number),
+            Some(PageLabelStyle::LowerRoman) => roman(number).to_lowercase(),
+            Some(PageLabelStyle::UpperLetters) => letters(number),
+            Some(PageLabelStyle::LowerLetters) => letters(number).to_lowercase(),
+            None => String::new(),
+        };
+        format!("{}{}", self.prefix, numbering)
+    }
+}
+
+impl Document {
+    /// The ranges of page labels of the `/PageLabels` number tree of the catalog, in page order. Ranges that are
+    /// malformed or start after the last page are skipped.
+    pub fn page_labels(&self) -> Vec<PageLabelRange> {
+        let root = match self
+            .catalog()
+            .and_then(|catalog| catalog.get_deref(b"PageLabels", self))
+            .and_then(Object::as_dict)
+        {
+            Ok(root) => root,
+            Err(_) => return Vec::new(),
+        };
+        let page_count = self.page_iter().count();
+        self.number_tree_entries(root)
+            .into_iter()
+            .filter_map(|(index, label)| {
+                let label = self.dereference(&label).and_then(|(_, label)| label.as_dict());
+                let (first_page, label) = match (u32::try_from(index), label) {
+                    (Ok(page_index), Ok(label)) if (page_index as usize) < page_count => (page_index + 1, label),
+                    _ => {
+                        warn!("Skipping page label range of page index {}", index);
+                        return None;
+                    }
+                };
+                let style = match label.get_deref(b"S", self).and_then(Object::as_name) {
+                    Ok(b"D") => Some(PageLabelStyle::Decimal),
+                    Ok(b"R") => Some(PageLabelStyle::UpperRoman),
+                    Ok(b"r") => Some(PageLabelStyle::LowerRoman),
+                    Ok(b"A") => Some(PageLabelStyle::UpperLetters),
+                    Ok(b"a") => Some(PageLabelStyle::LowerLetters),
+                    _ => None,
+                };
+                Some(PageLabelRange {
+                    first_page,
+                    style,
+                    prefix: label
+                        .get_deref(b"P", self)
+                        .and_then(Object::as_str)
+                        .map(decode_text_string)
+                        .unwrap_or_default(),
+                    start: label.get_deref(b"St", self).and_then(Object::as_i64).unwrap_or(1),
+                })
+            })
+            .collect()
+    }
+}
+
+/// A number in uppercase roman numerals, or in decimal if it is not positive.
+fn roman(mut number: i64) -> String {
+    const NUMERALS: [(i64, &str); 13] = [
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+    if number <= 0 {
+        return number.to_string();
+    }
+    let mut text = String::new();
+    for (value, numeral) in NUMERALS {
+        while number >= value {
+            text.push_str(numeral);
+            number -= value;
+        }
+    }
+    text
+}
+
+/// A number in uppercase letters, or in decimal if it is not positive: `A` to `Z` for 1 to 26, then `AA` to `ZZ`
+/// for 27 to 52, and so on.
+fn letters(number: i64) -> String {
+    if number <= 0 {
+        return number.to_string();
+    }
+    let letter = char::from(b'A' + ((number - 1) % 26) as u8);
+    letter.to_string().repeat(((number - 1) / 26 + 1) as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::creator::tests::create_document;
+    use crate::Stream;
+
+    #[test]
+    fn read_page_labels() {
+        let mut doc = create_document();
+        assert!(doc.page_labels().is_empty());
+        let pages_id = doc
+            .catalog()
+            .unwrap()
+            .get(b"Pages")
+            .and_then(Object::as_reference)
+            .unwrap();
+        let mut kids = vec![doc.page_iter().next().unwrap().into()];
+        for _ in 0..4 {
+            let content = doc.add_object(Stream::new(dictionary! {}, Vec::new()));
+            kids.push(
+                doc.add_object(dictionary! { "Type" => "Page", "Parent" => pages_id, "Contents" => content })
+                    .into(),
+            );
+        }
+        let pages = doc.get_object_mut(pages_id).unwrap().as_dict_mut().unwrap();
+        pages.set("Kids", kids);
+        pages.set("Count", 5);
+        doc.catalog_mut().unwrap().set(
+            "PageLabels",
+            dictionary! {
+                "Nums" => vec![
+                    0.into(),
+                    dictionary! { "S" => "r" }.into(),
+                    2.into(),
+                    dictionary! { "S" => "D", "P" => Object::string_literal("A-"), "St" => 8 }.into(),
+                    4.into(),
+                    dictionary! { "P" => Object::string_literal("Back cover") }.into(),
+                    9.into(),
+                    dictionary! { "S" => "D" }.into(),
+                ],
+            },
+        );
+        let labels = doc.page_labels();
+        assert_eq!(labels.len(), 3);
+        assert_eq!(labels[1].first_page, 3);
+        assert_eq!(labels[1].style, Some(PageLabelStyle::Decimal));
+        assert_eq!(labels[0].label(2), "ii");
+        assert_eq!(labels[1].label(4), "A-9");
+        assert_eq!(labels[2].label(5), "Back cover");
+    }
+
+    #[test]
+    fn numberings() {
+        assert_eq!(roman(1994), "MCMXCIV");
+        assert_eq!(letters(1), "A");
+        assert_eq!(letters(28), "BB");
+    }
+}
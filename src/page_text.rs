@@ -0,0 +1,338 @@
+//! Lines of text added to existing pages, without handling their resources and content streams.
+
+use crate::content::{Content, Operation};
+use crate::forms::appearance::{FieldFont, TextFont};
+use crate::geometry::{Matrix, Rect};
+use crate::truetype::TrueTypeFont;
+use crate::{BuiltinFont, Color, Dictionary, Document, Error, Object, ObjectId, Result, StringFormat};
+
+/// Text shown on a page from a starting point on its baseline, in PDF units.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlacedText {
+    pub x: f64,
+    pub y: f64,
+    pub size: f64,
+    pub color: Color,
+    pub text: String,
+}
+
+impl PlacedText {
+    /// Black text.
+    pub fn new(x: f64, y: f64, size: f64, text: &str) -> PlacedText {
+        PlacedText {
+            x,
+            y,
+            size,
+            color: Color::Rgb(0.0, 0.0, 0.0),
+            text: text.to_string(),
+        }
+    }
+}
+
+/// Font of the text added by [`Document::append_text`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FontChoice {
+    /// One of the standard 14 fonts, reusing a font of the page that is the same.
+    Builtin(BuiltinFont),
+    /// A font dictionary of the document, such as the font of another page. Composite fonts need an embedded
+    /// TrueType font file with the `Identity-H` encoding, as the fonts that this crate embeds have; widths are
+    /// added for the glyphs they have none for, but their `/ToUnicode` map is left as it is.
+    Embedded(ObjectId),
+}
+
+/// Options for [`Document::append_text_with`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AppendTextOptions {
+    /// Take positions from the bottom left corner of the page as it is displayed after its `/Rotate`, with the text
+    /// upright, rather than in the default user space of the page.
+    pub rotated: bool,
+}
+
+impl Document {
+    /// Add text on top of the content of a page, in the default user space of the page.
+    pub fn append_text(&mut self, page_id: ObjectId, spans: &[PlacedText], font: FontChoice) -> Result<()> {
+        self.append_text_with(page_id, spans, font, &AppendTextOptions::default())
+    }
+
+    /// Add text on top of the content of a page, with options.
+    ///
+    /// The font is added to the resources of the page, [isolated](Document::isolate_page_resources) from those of
+    /// other pages, and the text to the end of its `/Contents`, isolated from the graphics state the existing
+    /// content leaves.
+    pub fn append_text_with(
+        &mut self, page_id: ObjectId, spans: &[PlacedText], font: FontChoice, options: &AppendTextOptions,
+    ) -> Result<()> {
+        self.get_dictionary(page_id)?;
+        let (font_id, codes) = match font {
+            FontChoice::Builtin(font) => {
+                let codes = spans.iter().map(|span| font.encode(&span.text)).collect();
+                let font_id = match self.page_builtin_font(page_id, font) {
+                    Some(font_id) => font_id,
+                    None => self.add_object(font.dictionary()),
+                };
+                (font_id, codes)
+            }
+            FontChoice::Embedded(font_id) => (font_id, self.encode_in_font(font_id, spans)?),
+        };
+        let name = self.add_page_font(page_id, font_id)?;
+
+        let mut operations = Vec::new();
+        if options.rotated {
+            if let Some(matrix) = self.rotated_space(page_id) {
+                operations.push(Operation::new("cm", matrix.iter().map(|&value| value.into()).collect()));
+            }
+        }
+        operations.push(Operation::new("BT", vec![]));
+        for (span, codes) in spans.iter().zip(codes) {
+            operations.push(span.color.fill_operation());
+            operations.push(Operation::new("Tf", vec![Object::Name(name.clone()), span.size.into()]));
+            operations.push(Operation::new(
+                "Tm",
+                vec![1.into(), 0.into(), 0.into(), 1.into(), span.x.into(), span.y.into()],
+            ));
+            operations.push(Operation::new(
+                "Tj",
+                vec![Object::String(codes, StringFormat::Hexadecimal)],
+            ));
+        }
+        operations.push(Operation::new("ET", vec![]));
+        self.append_isolated_content(page_id, Content { operations }.encode()?)
+    }
+
+    /// A font of the resources of a page that is the same as a standard font.
+    fn page_builtin_font(&self, page_id: ObjectId, font: BuiltinFont) -> Option<ObjectId> {
+        let expected = font.dictionary();
+        let resources = self.page_attribute(page_id, b"Resources")?;
+        let fonts = self.dereference(resources).ok()?.1.as_dict().ok()?.get(b"Font").ok()?;
+        let fonts = self.dereference(fonts).ok()?.1.as_dict().ok()?;
+        fonts.iter().find_map(|(_, font)| {
+            let id = font.as_reference().ok()?;
+            let font = self.get_dictionary(id).ok()?;
+            let same = [&b"Subtype"[..], b"BaseFont", b"Encoding"].iter().all(|key| {
+                font.get(key).and_then(Object::as_name).ok() == expected.get(key).and_then(Object::as_name).ok()
+            });
+            (same && !font.has(b"FontDescriptor")).then_some(id)
+        })
+    }
+
+    /// The codes of the text of spans in a font of the document.
+    fn encode_in_font(&mut self, font_id: ObjectId, spans: &[PlacedText]) -> Result<Vec<Vec<u8>>> {
+        let font = self.get_dictionary(font_id)?;
+        if font.get(b"Subtype").and_then(Object::as_name)? != b"Type0" {
+            let font = FieldFont::new(self, font);
+            return Ok(spans.iter().map(|span| font.encode(&span.text)).collect());
+        }
+        let unsupported = Error::Unimplemented("text in composite fonts without an embedded TrueType font");
+        if font.get(b"Encoding").and_then(Object::as_name).ok() != Some(b"Identity-H") {
+            return Err(unsupported);
+        }
+        let descendant_id = font
+            .get(b"DescendantFonts")
+            .and_then(|fonts| self.dereference(fonts))
+            .and_then(|(_, fonts)| fonts.as_array())?
+            .first()
+            .ok_or_else(|| Error::dict_key(b"DescendantFonts"))?
+            .as_reference()?;
+        let descendant = self.get_dictionary(descendant_id)?;
+        let font_file = descendant
+            .get_deref(b"FontDescriptor", self)
+            .and_then(Object::as_dict)
+            .and_then(|descriptor| descriptor.get_deref(b"FontFile2", self))
+            .and_then(Object::as_stream)
+            .map_err(|_| unsupported)?;
+        let truetype = TrueTypeFont::parse(font_file.decompressed_content()?)?;
+        let codes: Vec<Vec<u8>> = spans.iter().map(|span| truetype.encode(&span.text)).collect();
+
+        let mut widths = descendant
+            .get_deref(b"W", self)
+            .and_then(Object::as_array)
+            .cloned()
+            .unwrap_or_default();
+        let mut added = false;
+        for code in codes.iter().flat_map(|codes| codes.chunks_exact(2)) {
+            let glyph = i64::from(u16::from_be_bytes([code[0], code[1]]));
+            if !widths_cover(&widths, glyph) {
+                widths.push(glyph.into());
+                widths.push(vec![(truetype.width(code) * 1000.0).round().into()].into());
+                added = true;
+            }
+        }
+        if added {
+            self.get_object_mut(descendant_id)?.as_dict_mut()?.set("W", widths);
+        }
+        Ok(codes)
+    }
+
+    /// Add a font to the resources of a page, under the name it already has there if any.
+    fn add_page_font(&mut self, page_id: ObjectId, font_id: ObjectId) -> Result<Vec<u8>> {
+        let resources = self.page_resources_mut(page_id)?;
+        if !resources.get(b"Font").is_ok_and(|fonts| fonts.as_dict().is_ok()) {
+            resources.set("Font", Dictionary::new());
+        }
+        let fonts = resources.get_mut(b"Font")?.as_dict_mut()?;
+        if let Some((name, _)) = fonts.iter().find(|(_, font)| font.as_reference().ok() == Some(font_id)) {
+            return Ok(name.clone());
+        }
+        let mut number = fonts.len() + 1;
+        while fonts.has(format!("F{}", number).as_bytes()) {
+            number += 1;
+        }
+        let name = format!("F{}", number).into_bytes();
+        fonts.set(name.clone(), font_id);
+        Ok(name)
+    }
+
+    /// The matrix from the space of a page as it is displayed, rotated by its `/Rotate`, to its default user space.
+    fn rotated_space(&self, page_id: ObjectId) -> Option<Matrix> {
+        let rotate = self
+            .page_attribute(page_id, b"Rotate")
+            .and_then(|rotate| rotate.as_i64().ok())
+            .unwrap_or(0)
+            .rem_euclid(360);
+        let Rect { llx, lly, urx, ury } = self
+            .page_attribute(page_id, b"CropBox")
+            .and_then(|crop_box| Rect::from_object(self.dereference(crop_box).ok()?.1).ok())
+            .or_else(|| self.get_page_media_box(page_id))?;
+        match rotate {
+            90 => Some([0.0, 1.0, -1.0, 0.0, urx, lly]),
+            180 => Some([-1.0, 0.0, 0.0, -1.0, urx, ury]),
+            270 => Some([0.0, -1.0, 1.0, 0.0, llx, ury]),
+            _ => None,
+        }
+    }
+
+    /// An entry of a page, or of the closest node of the page tree above it with one.
+    fn page_attribute(&self, page_id: ObjectId, key: &[u8]) -> Option<&Object> {
+        let mut node = self.get_dictionary(page_id).ok()?;
+        // Bounded, as page trees may have cycles.
+        for _ in 0..64 {
+            if let Ok(value) = node.get(key) {
+                return Some(value);
+            }
+            node = node
+                .get(b"Parent")
+                .and_then(Object::as_reference)
+                .and_then(|id| self.get_dictionary(id))
+                .ok()?;
+        }
+        None
+    }
+}
+
+/// Whether the `/W` array of a composite font has a width for a glyph, either as `c [w1 w2 ...]` or `first last w`.
+fn widths_cover(widths: &[Object], glyph: i64) -> bool {
+    let mut index = 0;
+    while let (Some(first), Some(next)) = (widths.get(index), widths.get(index + 1)) {
+        let first = first.as_i64().unwrap_or(-1);
+        match next {
+            Object::Array(list) => {
+                if (first..first + list.len() as i64).contains(&glyph) {
+                    return true;
+                }
+                index += 2;
+            }
+            last => {
+                if (first..=last.as_i64().unwrap_or(-1)).contains(&glyph) {
+                    return true;
+                }
+                index += 3;
+            }
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::creator::tests::create_document;
+    use crate::Stream;
+
+    fn last_content(doc: &Document, page_id: ObjectId) -> String {
+        let last = *doc.get_page_contents(page_id).last().unwrap();
+        let stream = doc.get_object(last).and_then(Object::as_stream).unwrap();
+        String::from_utf8(stream.content.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn append_text_to_pages() {
+        let mut doc = create_document();
+        let page_id = doc.page_iter().next().unwrap();
+        let mut red = PlacedText::new(72.0, 100.0, 10.0, "Approved");
+        red.color = Color::Rgb(1.0, 0.0, 0.0);
+        let spans = [red, PlacedText::new(72.0, 88.0, 8.0, "by the board")];
+        doc.append_text(page_id, &spans, FontChoice::Builtin(BuiltinFont::Courier))
+            .unwrap();
+        doc.append_text(page_id, &spans[1..], FontChoice::Builtin(BuiltinFont::Helvetica))
+            .unwrap();
+
+        // The inherited font was kept and Courier, differing in its encoding, was added alongside Helvetica.
+        let fonts = doc.get_page_fonts(page_id);
+        let names: Vec<_> = fonts
+            .keys()
+            .map(|name| String::from_utf8_lossy(name).into_owned())
+            .collect();
+        assert_eq!(names, ["F1", "F2", "F3"]);
+        assert!(doc.get_dictionary(page_id).unwrap().has(b"Resources"));
+        let contents = doc.get_page_contents(page_id);
+        assert_eq!(contents.len(), 5);
+        assert!(last_content(&doc, page_id).starts_with("Q\nBT\n0 0 0 rg\n/F3 8 Tf\n1 0 0 1 72 88 Tm\n"));
+        #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+        {
+            let text = doc.extract_text(&[1]).unwrap();
+            assert!(text.contains("Approved") && text.contains("by the board"));
+        }
+
+        // A page rotated a quarter turn, with its contents an array object, and a font it already has.
+        let array_id = doc.add_object(vec![contents[2].into()]);
+        let page = doc.get_object_mut(page_id).unwrap().as_dict_mut().unwrap();
+        page.set("Contents", array_id);
+        page.set("Rotate", 90);
+        let font_id = doc
+            .get_dictionary(page_id)
+            .unwrap()
+            .get_deref(b"Resources", &doc)
+            .and_then(Object::as_dict)
+            .unwrap();
+        let font_id = font_id
+            .get(b"Font")
+            .and_then(Object::as_dict)
+            .unwrap()
+            .get(b"F2")
+            .unwrap();
+        let font_id = font_id.as_reference().unwrap();
+        let options = AppendTextOptions { rotated: true };
+        doc.append_text_with(page_id, &spans[..1], FontChoice::Embedded(font_id), &options)
+            .unwrap();
+        assert_eq!(doc.get_page_contents(page_id).len(), 3);
+        assert!(last_content(&doc, page_id).starts_with("Q\n0 1 -1 0 595 0 cm\nBT\n1 0 0 rg\n/F2 10 Tf\n"));
+
+        // Composite fonts get widths for the glyphs they did not have any for.
+        let truetype = TrueTypeFont::parse(crate::truetype::tests::test_font()).unwrap();
+        let font_id = doc.embed_truetype(&truetype, "A").unwrap();
+        let spans = [PlacedText::new(10.0, 10.0, 12.0, "Ж")];
+        doc.append_text(page_id, &spans, FontChoice::Embedded(font_id)).unwrap();
+        let descendant = doc
+            .get_dictionary(font_id)
+            .unwrap()
+            .get(b"DescendantFonts")
+            .unwrap()
+            .as_array()
+            .unwrap()[0]
+            .as_reference()
+            .unwrap();
+        let widths = doc
+            .get_dictionary(descendant)
+            .unwrap()
+            .get(b"W")
+            .unwrap()
+            .as_array()
+            .unwrap();
+        assert_eq!(widths.len(), 4);
+        assert!(last_content(&doc, page_id).contains("/F4 12 Tf\n1 0 0 1 10 10 Tm\n<0002> Tj"));
+        let stream_id = doc.add_object(Stream::new(Dictionary::new(), Vec::new()));
+        assert!(doc
+            .append_text(page_id, &spans, FontChoice::Embedded(stream_id))
+            .is_err());
+    }
+}
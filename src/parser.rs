@@ -1,6 +1,7 @@
 use super::{Dictionary, Object, ObjectId, Stream, StringFormat};
 use crate::content::*;
 use crate::error::XrefError;
+use crate::{numbers, parser_aux};
 use crate::reader::Reader;
 use crate::xref::*;
 use crate::{Error, Result};
@@ -28,14 +29,19 @@ fn space<'a>() -> Parser<'a, u8, ()> {
 
 fn integer<'a>() -> Parser<'a, u8, i64> {
     let number = one_of(b"+-").opt() + one_of(b"0123456789").repeat(1..);
-    number.collect().convert(str::from_utf8).convert(|s| i64::from_str(&s))
+    number.collect().convert(str::from_utf8).convert(i64::from_str)
 }
 
-fn real<'a>() -> Parser<'a, u8, f64> {
-    let number = one_of(b"+-").opt()
-        + ((one_of(b"0123456789").repeat(1..) * sym(b'.') - one_of(b"0123456789").repeat(0..))
-            | (sym(b'.') - one_of(b"0123456789").repeat(1..)));
-    number.collect().convert(str::from_utf8).convert(|s| f64::from_str(&s))
+/// An integer or a real, as [`numbers::leading_number`] reads them.
+fn number<'a>() -> Parser<'a, u8, Object> {
+    Parser::new(|input: &'a [u8], start: usize| {
+        numbers::leading_number(&input[start..])
+            .map(|(number, length)| (number, start + length))
+            .ok_or_else(|| pom::Error::Mismatch {
+                message: "expect number".to_string(),
+                position: start,
+            })
+    })
 }
 
 fn hex_char<'a>() -> Parser<'a, u8, u8> {
@@ -149,10 +155,10 @@ fn stream<'a>(reader: &'a Reader) -> Parser<'a, u8, Stream> {
 fn object_id<'a>() -> Parser<'a, u8, ObjectId> {
     let id = one_of(b"0123456789")
         .repeat(1..)
-        .convert(|v| u32::from_str(&str::from_utf8(&v).unwrap()));
+        .convert(|v| u32::from_str(str::from_utf8(&v).unwrap()));
     let gen = one_of(b"0123456789")
         .repeat(1..)
-        .convert(|v| u16::from_str(&str::from_utf8(&v).unwrap()));
+        .convert(|v| u16::from_str(str::from_utf8(&v).unwrap()));
     id - space() + gen - space()
 }
 
@@ -160,13 +166,17 @@ pub fn direct_object(input: &[u8]) -> Option<Object> {
     _direct_object().parse(input).ok()
 }
 
+/// A direct object at the start of the input, with the length of it and of the whitespace after it.
+pub fn leading_direct_object(input: &[u8]) -> Option<(Object, usize)> {
+    _direct_object().parse_at(input, 0).ok()
+}
+
 fn _direct_object<'a>() -> Parser<'a, u8, Object> {
     (seq(b"null").map(|_| Object::Null)
         | seq(b"true").map(|_| Object::Boolean(true))
         | seq(b"false").map(|_| Object::Boolean(false))
         | (object_id().map(Object::Reference) - sym(b'R'))
-        | real().map(Object::Real)
-        | integer().map(Object::Integer)
+        | number()
         | name().map(Object::Name)
         | literal_string().map(Object::string_literal)
         | hexadecimal_string().map(|bytes| Object::String(bytes, StringFormat::Hexadecimal))
@@ -180,8 +190,7 @@ fn object<'a>(reader: &'a Reader) -> Parser<'a, u8, Object> {
         | seq(b"true").map(|_| Object::Boolean(true))
         | seq(b"false").map(|_| Object::Boolean(false))
         | (object_id().map(Object::Reference) - sym(b'R'))
-        | real().map(Object::Real)
-        | integer().map(Object::Integer)
+        | number()
         | name().map(Object::Name)
         | literal_string().map(Object::string_literal)
         | hexadecimal_string().map(|bytes| Object::String(bytes, StringFormat::Hexadecimal))
@@ -191,13 +200,17 @@ fn object<'a>(reader: &'a Reader) -> Parser<'a, u8, Object> {
         - space()
 }
 
+/// An indirect object at an offset, with the offset of the end of the object and of the whitespace after it.
 pub fn indirect_object(
     input: &[u8], offset: usize, expected_id: Option<ObjectId>, reader: &Reader,
-) -> Result<(ObjectId, Object)> {
+) -> Result<(ObjectId, Object, usize)> {
     _indirect_object(expected_id, reader)
         .parse_at(input, offset)
-        .map(|(out, _)| out)
-        .map_err(|_| Error::Parse { offset })
+        .map(|((id, object), end)| (id, object, end))
+        .map_err(|_| Error::Parse {
+            offset,
+            object: expected_id,
+        })
 }
 
 fn _indirect_object<'a>(expected_id: Option<ObjectId>, reader: &'a Reader) -> Parser<'a, u8, (ObjectId, Object)> {
@@ -220,9 +233,9 @@ pub fn header(input: &[u8]) -> Option<String> {
 }
 
 fn xref<'a>() -> Parser<'a, u8, Xref> {
-    let xref_entry = integer().map(|i| i as u32) - sym(b' ') + integer().map(|i| i as u16) - sym(b' ')
+    let xref_entry = integer().map(|i| i as u64) - sym(b' ') + integer().map(|i| i as u16) - sym(b' ')
         + one_of(b"nf").map(|k| k == b'n')
-        - take(2);
+        - (sym(b' ').opt() * eol());
     let xref_section =
         integer().map(|i| i as usize) - sym(b' ') + integer() - sym(b' ').opt() - eol() + xref_entry.repeat(0..);
     let xref = seq(b"xref") * eol() * xref_section.repeat(1..) - space();
@@ -272,7 +285,10 @@ pub fn xref_start(input: &[u8]) -> Option<i64> {
 // The following code create parser to parse content stream.
 
 fn content_space<'a>() -> Parser<'a, u8, ()> {
-    is_a(multispace).repeat(0..).discard()
+    // A comment may end the content without an end of line.
+    (is_a(multispace).discard() | (sym(b'%') * none_of(b"\r\n").repeat(0..).discard()))
+        .repeat(0..)
+        .discard()
 }
 
 fn operator<'a>() -> Parser<'a, u8, String> {
@@ -283,8 +299,7 @@ fn operand<'a>() -> Parser<'a, u8, Object> {
     (seq(b"null").map(|_| Object::Null)
         | seq(b"true").map(|_| Object::Boolean(true))
         | seq(b"false").map(|_| Object::Boolean(false))
-        | real().map(Object::Real)
-        | integer().map(Object::Integer)
+        | number()
         | name().map(Object::Name)
         | literal_string().map(Object::string_literal)
         | hexadecimal_string().map(|bytes| Object::String(bytes, StringFormat::Hexadecimal))
@@ -293,15 +308,62 @@ fn operand<'a>() -> Parser<'a, u8, Object> {
         - content_space()
 }
 
+/// The operands of an operation, collected as `repeat(0..)` would but without allocating a vector for them.
+fn operands<'a>() -> Parser<'a, u8, Operands> {
+    let operand = operand();
+    Parser::new(move |input: &'a [u8], start: usize| {
+        let mut operands = Operands::new();
+        let mut pos = start;
+        while let Ok((item, item_pos)) = operand.parse_at(input, pos) {
+            operands.push(item);
+            pos = item_pos;
+        }
+        Ok((operands, pos))
+    })
+}
+
 fn operation<'a>() -> Parser<'a, u8, Operation> {
-    let operation = operand().repeat(0..) + operator() - content_space();
+    let operation = operands() + operator() - content_space();
     operation.map(|(operands, operator)| Operation { operator, operands })
 }
 
-pub fn content(input: &[u8]) -> Option<Content<Vec<Operation>>> {
-    (content_space() * operation().repeat(0..).map(|operations| Content { operations }))
-        .parse(input)
-        .ok()
+/// An inline image, as [`parser_aux::inline_image`] reads it.
+fn inline_image<'a>() -> Parser<'a, u8, Operation> {
+    let entry = name() - content_space() + operand();
+    let head = seq(b"BI") * content_space() * entry.repeat(0..) - seq(b"ID") - one_of(b" \t\r\n\0\x0C");
+    let space = content_space();
+    Parser::new(move |input: &'a [u8], start: usize| {
+        let (entries, data) = head.parse_at(input, start)?;
+        let (image, length) = parser_aux::inline_image(entries, &input[data..]).ok_or_else(|| pom::Error::Mismatch {
+            message: "expect EI".to_string(),
+            position: data,
+        })?;
+        let (_, end) = space.parse_at(input, data + length)?;
+        Ok((image, end))
+    })
+}
+
+/// The content operations, with the offset parsing stopped at: the end of the input, unless an operation is cut
+/// off or cannot be parsed.
+pub fn content(input: &[u8]) -> (Content<Vec<Operation>>, usize) {
+    let (operation, inline_image) = (operation(), inline_image());
+    let mut operations = Vec::new();
+    let mut pos = content_space().parse_at(input, 0).map_or(0, |(_, pos)| pos);
+    while pos < input.len() {
+        let parsed = if parser_aux::starts_inline_image(&input[pos..]) {
+            inline_image.parse_at(input, pos)
+        } else {
+            operation.parse_at(input, pos)
+        };
+        match parsed {
+            Ok((operation, end)) => {
+                operations.push(operation);
+                pos = end;
+            }
+            Err(_) => break,
+        }
+    }
+    (Content { operations }, pos)
 }
 
 #[cfg(test)]
@@ -310,12 +372,10 @@ mod tests {
 
     #[test]
     fn parse_real_number() {
-        let r0 = real().parse(b"0.12");
-        assert_eq!(r0, Ok(0.12));
-        let r1 = real().parse(b"-.12");
-        assert_eq!(r1, Ok(-0.12));
-        let r2 = real().parse(b"10.");
-        assert_eq!(r2, Ok(10.0));
+        let real = |input| number().parse(input).ok().and_then(|number| number.as_float().ok());
+        assert_eq!(real(b"0.12"), Some(0.12));
+        assert_eq!(real(b"-.12"), Some(-0.12));
+        assert_eq!(real(b"10."), Some(10.0));
     }
 
     #[test]
@@ -365,8 +425,9 @@ BT
 [(b) 20 (ut generally tak) 10 (e more space than \\311)] TJ
 T* (encoded streams.) Tj
 		";
-        let content = content(stream);
+        let (content, offset) = content(stream);
         println!("{:?}", content);
-        assert!(content.is_some());
+        assert_eq!(offset, stream.len());
+        assert!(!content.operations.is_empty());
     }
 }
@@ -6,20 +6,174 @@ use crate::{
     error::XrefError,
     object::Object::Name,
     xref::{Xref, XrefEntry},
-    Error, Result,
+    Error, Phase, Result,
 };
-use crate::{parser, Dictionary, Object, ObjectId, Stream};
-use log::info;
+use crate::font_cache::FontCache;
+use crate::{numbers, parser, Codecs, Dictionary, Object, ObjectId, ParseWarning, ParseWarningKind, Stream};
+use log::{info, warn};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
 use std::{
-    collections::BTreeMap,
-    io::{Cursor, Read},
+    collections::{BTreeMap, BTreeSet},
+    convert::TryFrom,
+    io::{Cursor, Read, Write},
+    sync::Arc,
 };
 
+/// The order in which [`Document::extract_text_with`] extracts text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextOrder {
+    /// The order in which the content streams show the text, as [`Document::extract_text`] does.
+    #[default]
+    Geometric,
+    /// The reading order of the structure tree of a tagged document, leaving out artifacts.
+    Structure,
+}
+
+/// Options for [`Document::extract_text_with`].
+#[derive(Debug, Clone, Default)]
+pub struct TextExtractionOptions {
+    pub order: TextOrder,
+}
+
+/// Options for [`Document::extract_text_to`].
+#[derive(Debug, Clone, Default)]
+pub struct ExtractionOptions {
+    /// Written between the text of consecutive pages; nothing by default, as [`Document::extract_text`] does.
+    pub page_separator: String,
+}
+
+/// What [`Document::extract_text_to`] extracted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ExtractStats {
+    pub pages: usize,
+    /// The character codes shown that no `/ToUnicode` map or encoding gives text for.
+    pub unmapped_glyphs: usize,
+}
+
+/// A span of extracted text of the same language.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextSpan {
+    pub text: String,
+    /// The language of the text, from the innermost marked-content sequence or structure element giving one,
+    /// or else from the catalog.
+    pub lang: Option<String>,
+    /// Whether the text is the `/ActualText` or `/Alt` of a structure element rather than text shown by the content.
+    pub actual_text: bool,
+}
+
+/// Text extracted by [`Document::extract_text_with`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtractedText {
+    pub text: String,
+    /// The spans of the text in structure order, whose text is that of `text` without the line breaks between
+    /// elements; empty in geometric order.
+    pub spans: Vec<TextSpan>,
+    /// The order the text was extracted in, which is geometric when the structure order is not available.
+    pub order: TextOrder,
+    /// Why the text was not extracted in the order asked for.
+    pub note: Option<String>,
+}
+
+/// Options for [`Content::decode_with`].
+#[derive(Debug, Clone, Default)]
+pub struct ContentDecodeOptions {
+    /// Fail on content that stops in the middle of an operation, or at one that cannot be parsed, instead of
+    /// keeping the operations before it.
+    pub strict: bool,
+}
+
+/// Content decoded by [`Content::decode_with`].
+#[derive(Debug, Clone)]
+pub struct DecodedContent {
+    /// The operations parsed, up to where parsing stopped.
+    pub content: Content<Vec<Operation>>,
+    /// Whether parsing stopped before the end of the data, as for content cut off in the middle of a string,
+    /// dictionary or inline image, or between the operands of an operation and its operator.
+    pub truncated: bool,
+    /// The offset in the data where parsing stopped: the start of the first operation not parsed, or the length of
+    /// the data when it was all parsed.
+    pub offset: usize,
+}
+
 impl Content<Vec<Operation>> {
-    /// Decode content operations.
+    /// Decode content operations. Content that stops in the middle of an operation, or at one that cannot be
+    /// parsed, is decoded up to there, with a warning; [`Content::decode_with`] tells where, or fails instead.
     pub fn decode(data: &[u8]) -> Result<Self> {
-        parser::content(data).ok_or(Error::ContentDecode)
+        Ok(Content::decode_with(data, &ContentDecodeOptions::default())?.content)
+    }
+
+    /// Decode content operations, saying whether and where parsing stopped before the end of the data.
+    pub fn decode_with(data: &[u8], options: &ContentDecodeOptions) -> Result<DecodedContent> {
+        let (content, offset) = parser::content(data);
+        let decoded = DecodedContent {
+            truncated: offset < data.len(),
+            content,
+            offset,
+        };
+        if decoded.truncated {
+            if options.strict {
+                return Err(Error::Parse { offset, object: None });
+            }
+            warn!(
+                "Content stops at byte {} of {}, keeping the {} operations before",
+                offset,
+                data.len(),
+                decoded.content.operations.len()
+            );
+        }
+        Ok(decoded)
+    }
+
+    /// Decode content operations, with a warning for each number read in a form the syntax does not allow, and one
+    /// when parsing stops before the end of the data, as [`Content::decode`] does.
+    pub fn decode_with_warnings(data: &[u8]) -> Result<(Self, Vec<ParseWarning>)> {
+        let ((content, offset), normalized) = numbers::collect_normalized(|| parser::content(data));
+        let mut warnings: Vec<ParseWarning> =
+            normalized.into_iter().map(|detail| numbers::warning(None, detail)).collect();
+        if offset < data.len() {
+            warnings.push(ParseWarning {
+                kind: ParseWarningKind::TruncatedContent,
+                object: None,
+                detail: format!(
+                    "content stops at byte {} of {}, after {} operations",
+                    offset,
+                    data.len(),
+                    content.operations.len()
+                ),
+            });
+        }
+        Ok((content, warnings))
+    }
+}
+
+/// Whether content operations continue with an inline image, whose `BI` operator the parsers read with its
+/// dictionary and data rather than as an operation of its own.
+pub(crate) fn starts_inline_image(input: &[u8]) -> bool {
+    input.starts_with(b"BI") && !input.get(2).is_some_and(|&c| c.is_ascii_alphabetic() || b"*'\"".contains(&c))
+}
+
+/// The operation of an inline image, `BI` with the image as a stream operand, from the entries of its dictionary
+/// and the input its data starts at, after `ID` and one white-space byte; with the length of the input up to the end
+/// of its `EI`. The data ends at the first `EI` after white space and before white space or the end of the input.
+pub(crate) fn inline_image(entries: Vec<(Vec<u8>, Object)>, data: &[u8]) -> Option<(Operation, usize)> {
+    let white_space = |c: &u8| b" \t\r\n\0\x0C".contains(c);
+    let end = (0..data.len()).find(|&i| {
+        data[i..].starts_with(b"EI")
+            && (i == 0 || white_space(&data[i - 1]))
+            && data.get(i + 2).is_none_or(white_space)
+    })?;
+    let mut dict = Dictionary::new();
+    for (key, value) in entries {
+        dict.set(key, value);
     }
+    let image = Stream {
+        dict,
+        content: Arc::new(data[..end.saturating_sub(1)].to_vec()),
+        allows_compression: false,
+        start_position: None,
+    };
+    Some((Operation::new("BI", vec![Object::Stream(image)]), end + 2))
 }
 
 impl Stream {
@@ -37,55 +191,158 @@ impl Document {
     }
 
     pub fn extract_text(&self, page_numbers: &[u32]) -> Result<String> {
-        fn collect_text(text: &mut String, encoding: Option<&str>, operands: &[Object]) {
+        let mut text = Vec::new();
+        self.extract_text_to(page_numbers, &mut text, ExtractionOptions::default())?;
+        Ok(String::from_utf8(text).map_err(|err| err.utf8_error())?)
+    }
+
+    /// Extract the text of some pages as [`Document::extract_text`] does, writing it to a sink page by page
+    /// rather than gathering it all.
+    ///
+    /// The `/ToUnicode` maps of the fonts are parsed once for all pages. Pages before one that cannot be extracted
+    /// are written already when the error is returned.
+    pub fn extract_text_to<W: Write>(
+        &self, page_numbers: &[u32], sink: &mut W, options: ExtractionOptions,
+    ) -> Result<ExtractStats> {
+        let pages = self.get_pages();
+        let page_ids = page_numbers
+            .iter()
+            .map(|number| pages.get(number).copied().ok_or(Error::PageNumberNotFound(*number)))
+            .collect::<Result<Vec<_>>>()?;
+        let fonts = FontCache::new(self, &page_ids);
+        let mut stats = ExtractStats::default();
+        let mut text = String::new();
+        // Text objects end lines that are not ended yet, as if the pages were extracted into one string.
+        let mut line_ended = false;
+        for (index, page_id) in page_ids.into_iter().enumerate() {
+            if index > 0 && !options.page_separator.is_empty() {
+                sink.write_all(options.page_separator.as_bytes())?;
+                line_ended = options.page_separator.ends_with('\n');
+            }
+            text.clear();
+            if line_ended {
+                text.push('\n');
+            }
+            let start = text.len();
+            self.collect_page_text(page_id, &fonts, &mut text, &mut stats.unmapped_glyphs)?;
+            sink.write_all(&text.as_bytes()[start..])?;
+            line_ended = text.ends_with('\n');
+            stats.pages += 1;
+        }
+        Ok(stats)
+    }
+
+    /// Extract the text of each of some pages, as [`Document::extract_text`] does, in the order of the page numbers.
+    ///
+    /// The `/ToUnicode` maps of the fonts are parsed once for all pages, and with the `parallel` feature the pages
+    /// are extracted on several threads.
+    pub fn extract_text_pages(&self, page_numbers: &[u32]) -> Vec<Result<String>> {
+        let pages = self.get_pages();
+        let page_ids: Vec<ObjectId> = page_numbers
+            .iter()
+            .filter_map(|number| pages.get(number).copied())
+            .collect();
+        let fonts = FontCache::new(self, &page_ids);
+        let extract = |number: &u32| {
+            let page_id = *pages.get(number).ok_or(Error::PageNumberNotFound(*number))?;
+            let mut text = String::new();
+            self.collect_page_text(page_id, &fonts, &mut text, &mut 0)?;
+            Ok(text)
+        };
+        #[cfg(feature = "rayon")]
+        let texts = page_numbers.par_iter().map(extract).collect();
+        #[cfg(not(feature = "rayon"))]
+        let texts = page_numbers.iter().map(extract).collect();
+        texts
+    }
+
+    fn collect_page_text(
+        &self, page_id: ObjectId, fonts: &FontCache, text: &mut String, unmapped: &mut usize,
+    ) -> Result<()> {
+        let span = span!("extract_page_text", page = ?page_id, bytes = tracing::field::Empty);
+        let start = text.len();
+        self.collect_text_of_page(page_id, fonts, text, unmapped)?;
+        span.record("bytes", text.len() - start);
+        Ok(())
+    }
+
+    fn collect_text_of_page(
+        &self, page_id: ObjectId, fonts: &FontCache, text: &mut String, unmapped: &mut usize,
+    ) -> Result<()> {
+        fn collect_text(
+            text: &mut String, unmapped: &mut usize, fonts: &FontCache, font: Option<&Dictionary>,
+            operands: &[Object],
+        ) {
             for operand in operands.iter() {
                 match *operand {
                     Object::String(ref bytes, _) => {
-                        let decoded_text = Document::decode_text(encoding, bytes);
+                        let (decoded_text, codes) = fonts.decode(font, bytes);
                         text.push_str(&decoded_text);
+                        *unmapped += codes;
                     }
                     Object::Array(ref arr) => {
-                        collect_text(text, encoding, arr);
+                        collect_text(text, unmapped, fonts, font, arr);
                     }
                     _ => {}
                 }
             }
         }
-        let mut text = String::new();
+        let page_fonts = self.get_page_fonts(page_id);
+        let content_data = self.get_page_content(page_id)?;
+        let content = Content::decode(&content_data)?;
+        let mut current_font = None;
+        for operation in &content.operations {
+            match operation.operator.as_ref() {
+                "Tf" => {
+                    let font_name = operation
+                        .operands
+                        .first()
+                        .ok_or_else(|| Error::Syntax("missing font operand".to_string()))?
+                        .as_name()?;
+                    current_font = page_fonts.get(font_name).copied();
+                }
+                "Tj" | "TJ" => {
+                    collect_text(text, unmapped, fonts, current_font, &operation.operands);
+                }
+                "ET" if !text.ends_with('\n') => text.push('\n'),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Extract the text of some pages in the order of the options.
+    ///
+    /// The structure order is that of [`StructTree::text_in_reading_order`](crate::StructTree::text_in_reading_order),
+    /// restricted to the content of the pages. Documents without a structure tree are extracted in geometric
+    /// order instead, with a note saying so.
+    pub fn extract_text_with(&self, page_numbers: &[u32], options: &TextExtractionOptions) -> Result<ExtractedText> {
         let pages = self.get_pages();
-        for page_number in page_numbers {
-            let page_id = *pages.get(page_number).ok_or(Error::PageNumberNotFound(*page_number))?;
-            let fonts = self.get_page_fonts(page_id);
-            let encodings = fonts
-                .into_iter()
-                .map(|(name, font)| (name, font.get_font_encoding()))
-                .collect::<BTreeMap<Vec<u8>, &str>>();
-            let content_data = self.get_page_content(page_id)?;
-            let content = Content::decode(&content_data)?;
-            let mut current_encoding = None;
-            for operation in &content.operations {
-                match operation.operator.as_ref() {
-                    "Tf" => {
-                        let current_font = operation
-                            .operands
-                            .get(0)
-                            .ok_or_else(|| Error::Syntax("missing font operand".to_string()))?
-                            .as_name()?;
-                        current_encoding = encodings.get(current_font).cloned();
-                    }
-                    "Tj" | "TJ" => {
-                        collect_text(&mut text, current_encoding, &operation.operands);
-                    }
-                    "ET" => {
-                        if !text.ends_with('\n') {
-                            text.push('\n')
-                        }
-                    }
-                    _ => {}
+        let page_ids = page_numbers
+            .iter()
+            .map(|number| pages.get(number).copied().ok_or(Error::PageNumberNotFound(*number)))
+            .collect::<Result<BTreeSet<_>>>()?;
+        let mut note = None;
+        if options.order == TextOrder::Structure {
+            match self.structure_tree() {
+                Some(tree) => {
+                    let (text, spans) = tree.text_of_pages(self, Some(&page_ids));
+                    return Ok(ExtractedText {
+                        text,
+                        spans,
+                        order: TextOrder::Structure,
+                        note: None,
+                    })
                 }
+                None => note = Some("The document has no structure tree, so text is in geometric order".to_string()),
             }
         }
-        Ok(text)
+        Ok(ExtractedText {
+            text: self.extract_text(page_numbers)?,
+            spans: Vec::new(),
+            order: TextOrder::Geometric,
+            note,
+        })
     }
 
     pub fn replace_text(&mut self, page_number: u32, text: &str, other_text: &str) -> Result<()> {
@@ -106,7 +363,7 @@ impl Document {
                 "Tf" => {
                     let current_font = operation
                         .operands
-                        .get(0)
+                        .first()
                         .ok_or_else(|| Error::Syntax("missing font operand".to_string()))?
                         .as_name()?;
                     current_encoding = encodings.get(current_font).map(std::string::String::as_str);
@@ -177,10 +434,29 @@ impl Document {
     }
 }
 
-pub fn decode_xref_stream(mut stream: Stream) -> Result<(Xref, Dictionary)> {
-    stream.decompress();
-    let mut dict = stream.dict;
-    let mut reader = Cursor::new(stream.content);
+pub fn decode_xref_stream(stream: Stream) -> Result<(Xref, Dictionary)> {
+    let Stream { mut dict, content, .. } = stream;
+    let decoded = match dict.has(b"Filter") {
+        true => Codecs::with(|codecs| codecs.decode_with(&dict, &content, |data| read_xref_stream(&dict, data))).ok(),
+        false => None,
+    };
+    let xref = match decoded {
+        Some(xref) => {
+            dict.remove(b"DecodeParms");
+            dict.remove(b"Filter");
+            xref.map_err(|err| err.in_phase(Phase::Xref))?
+        }
+        // Content that cannot be decoded is read as it is.
+        None => read_xref_stream(&dict, &content).map_err(|err| err.in_phase(Phase::Xref))?,
+    };
+    dict.remove(b"Length");
+    dict.remove(b"W");
+    dict.remove(b"Index");
+    Ok((xref, dict))
+}
+
+fn read_xref_stream(dict: &Dictionary, data: &[u8]) -> Result<Xref> {
+    let mut reader = Cursor::new(data);
     let size = dict
         .get(b"Size")
         .and_then(Object::as_i64)
@@ -196,7 +472,8 @@ pub fn decode_xref_stream(mut stream: Stream) -> Result<(Xref, Dictionary)> {
             .and_then(parse_integer_array)
             .map_err(|_| Error::Xref(XrefError::Parse))?;
 
-        if field_widths.len() < 3 {
+        // The fields are read into 64-bit integers.
+        if field_widths.len() < 3 || field_widths[..3].iter().any(|width| !(0..=8).contains(width)) {
             return Err(Error::Xref(XrefError::Parse));
         }
 
@@ -233,6 +510,7 @@ pub fn decode_xref_stream(mut stream: Stream) -> Result<(Xref, Dictionary)> {
                     2 => {
                         //compressed object
                         let container = read_big_endian_integer(&mut reader, bytes2.as_mut_slice())?;
+                        let container = u32::try_from(container).map_err(|_| Error::Xref(XrefError::Parse))?;
                         let index = read_big_endian_integer(&mut reader, bytes3.as_mut_slice())? as u16;
                         xref.insert((start + j) as u32, XrefEntry::Compressed { container, index });
                     }
@@ -241,17 +519,14 @@ pub fn decode_xref_stream(mut stream: Stream) -> Result<(Xref, Dictionary)> {
             }
         }
     }
-    dict.remove(b"Length");
-    dict.remove(b"W");
-    dict.remove(b"Index");
-    Ok((xref, dict))
+    Ok(xref)
 }
 
-fn read_big_endian_integer(reader: &mut Cursor<Vec<u8>>, buffer: &mut [u8]) -> Result<u32> {
+fn read_big_endian_integer(reader: &mut Cursor<&[u8]>, buffer: &mut [u8]) -> Result<u64> {
     reader.read_exact(buffer)?;
     let mut value = 0;
     for &mut byte in buffer {
-        value = (value << 8) + u32::from(byte);
+        value = (value << 8) + u64::from(byte);
     }
     Ok(value)
 }
@@ -290,3 +565,122 @@ fn load_and_save() {
     // Clean up the saved document
     remove_document(&filename);
 }
+
+#[test]
+fn extract_text_pages_as_serially() {
+    use crate::content::Operation;
+    use crate::creator::tests::create_document;
+    use crate::StringFormat;
+
+    let mut doc = create_document();
+    let pages_id = doc.catalog().unwrap().get(b"Pages").and_then(Object::as_reference).unwrap();
+    let resources_id = doc
+        .get_dictionary(pages_id)
+        .unwrap()
+        .get(b"Resources")
+        .and_then(Object::as_reference)
+        .unwrap();
+    let to_unicode = doc.add_object(Stream::new(
+        Dictionary::new(),
+        b"1 begincodespacerange <0000> <FFFF> endcodespacerange\n\
+          1 beginbfrange <0001> <001A> <0061> endbfrange\n"
+            .to_vec(),
+    ));
+    let font_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type0",
+        "Encoding" => "Identity-H",
+        "ToUnicode" => to_unicode,
+    });
+    let resources = doc.get_object_mut(resources_id).unwrap().as_dict_mut().unwrap();
+    resources.get_mut(b"Font").and_then(Object::as_dict_mut).unwrap().set("F2", font_id);
+
+    let mut kids = vec![doc.page_iter().next().unwrap().into()];
+    for number in 2..=20u8 {
+        let content = Content {
+            operations: vec![
+                Operation::new("BT", vec![]),
+                Operation::new("Tf", vec!["F1".into(), 12.into()]),
+                Operation::new("Tj", vec![Object::string_literal(format!("Page {} ", number))]),
+                Operation::new("Tf", vec!["F2".into(), 12.into()]),
+                Operation::new("Tj", vec![Object::String(vec![0, number, 0, 1], StringFormat::Hexadecimal)]),
+                Operation::new("ET", vec![]),
+            ],
+        };
+        let content_id = doc.add_object(Stream::new(Dictionary::new(), content.encode().unwrap()));
+        kids.push(
+            doc.add_object(dictionary! { "Type" => "Page", "Parent" => pages_id, "Contents" => content_id })
+                .into(),
+        );
+    }
+    let pages = doc.get_object_mut(pages_id).unwrap().as_dict_mut().unwrap();
+    pages.set("Kids", kids);
+    pages.set("Count", 20);
+
+    let numbers: Vec<u32> = (1..=20).rev().collect();
+    let texts = doc.extract_text_pages(&numbers);
+    let serial: Vec<String> = numbers.iter().map(|&number| doc.extract_text(&[number]).unwrap()).collect();
+    assert_eq!(texts.into_iter().collect::<Result<Vec<_>>>().unwrap(), serial);
+    assert_eq!(serial[17], "Page 3 ca\n");
+    assert!(doc.extract_text_pages(&[1, 21])[1].is_err());
+}
+
+#[test]
+fn decode_truncated_content() {
+    type Row<'a> = (&'a [u8], &'a [&'a str], Option<usize>);
+
+    // The content, the operators salvaged and the offset parsing stops at, if before the end.
+    let table: &[Row] = &[
+        (b"BT /F1 12 Tf (Hello) Tj ET\n% done", &["BT", "Tf", "Tj", "ET"], None),
+        (b"BT /F1 12 Tf (Hel", &["BT", "Tf"], Some(13)),
+        (b"BT (a\\) b) Tj (c) Tj", &["BT", "Tj", "Tj"], None),
+        (b"0 0 m 100 100 l 200 200", &["m", "l"], Some(16)),
+        (b"q /GS0 gs << /MCID 1 /Lang", &["q", "gs"], Some(10)),
+        (b"q BI /W 2 /H 1 /BPC 8 /CS /G ID \x00\xff EI Q", &["q", "BI", "Q"], None),
+        (b"q BI /W 2 /H 1 /BPC 8 /CS /G ID \x00\xff", &["q"], Some(2)),
+        (b"q BI /W 2 /H 1 /BP", &["q"], Some(2)),
+        (b"q BIX Q", &["q", "BIX", "Q"], None),
+    ];
+    for (data, operators, stop) in table {
+        let decoded = Content::decode_with(data, &ContentDecodeOptions::default()).unwrap();
+        let parsed: Vec<&str> = decoded.content.operations.iter().map(|op| op.operator.as_str()).collect();
+        assert_eq!(&parsed, operators, "{:?}", String::from_utf8_lossy(data));
+        assert_eq!(decoded.truncated, stop.is_some());
+        assert_eq!(decoded.offset, stop.unwrap_or(data.len()));
+
+        let strict = Content::decode_with(data, &ContentDecodeOptions { strict: true });
+        match stop {
+            Some(stop) => assert!(matches!(strict, Err(Error::Parse { offset, .. }) if offset == *stop)),
+            None => assert!(strict.is_ok()),
+        }
+        let (_, warnings) = Content::decode_with_warnings(data).unwrap();
+        let truncated = warnings
+            .iter()
+            .filter(|warning| warning.kind == ParseWarningKind::TruncatedContent)
+            .count();
+        assert_eq!(truncated, stop.is_some() as usize);
+    }
+}
+
+#[test]
+fn decode_inline_images_and_extract_truncated_pages() {
+    use crate::creator::tests::create_document;
+
+    let data = b"q 10 0 0 10 0 0 cm BI /W 2 /H 1 /BPC 8 /CS /G /F /AHx ID 00ff>\nEI Q";
+    let content = Content::decode(data).unwrap();
+    let image = content.operations[2].operands[0].as_stream().unwrap();
+    assert_eq!(image.dict.get(b"W").and_then(Object::as_i64).unwrap(), 2);
+    assert_eq!(image.dict.get(b"F").and_then(Object::as_name).unwrap(), b"AHx");
+    assert_eq!(*image.content, b"00ff>");
+    let encoded = content.encode().unwrap();
+    let decoded = Content::decode_with(&encoded, &ContentDecodeOptions { strict: true }).unwrap();
+    assert_eq!(decoded.content.encode().unwrap(), encoded);
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    let content_id = doc.get_page_contents(page_id)[0];
+    let stream = doc.get_object_mut(content_id).and_then(Object::as_stream_mut).unwrap();
+    stream.set_plain_content(b"BT /F1 48 Tf 100 600 Td (Hello) Tj 0 -50 Td (Wor".to_vec());
+    assert_eq!(doc.get_and_decode_page_content(page_id).unwrap().operations.len(), 5);
+    assert!(doc.extract_text(&[1]).unwrap().contains("Hello"));
+}
@@ -0,0 +1,374 @@
+use crate::xmp::read_property;
+use crate::{ActionKind, Dictionary, Document, Object, ObjectId};
+use std::collections::BTreeSet;
+
+/// A kind of obvious violation of PDF/A found by [`Document::pdfa_precheck`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PdfaIssueKind {
+    /// The document is encrypted.
+    Encrypted,
+    /// A font program is not embedded.
+    FontNotEmbedded,
+    /// A JavaScript or launch action.
+    Action(ActionKind),
+    /// A stream is compressed with LZW, forbidden by PDF/A-1.
+    LzwCompression,
+    /// No PDF/A output intent with an ICC profile.
+    MissingOutputIntent,
+    /// The catalog has no XMP metadata.
+    MissingXmp,
+    /// The XMP metadata stream is filtered.
+    FilteredXmp,
+    /// An embedded file, forbidden by PDF/A-1 and allowed by PDF/A-2 only if it conforms to PDF/A.
+    EmbeddedFile,
+    /// Transparency, forbidden by PDF/A-1.
+    Transparency,
+}
+
+/// An obvious violation of PDF/A, with the object it was found in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PdfaIssue {
+    pub kind: PdfaIssueKind,
+    /// The offending object, or the closest indirect object holding it.
+    pub object: Option<ObjectId>,
+    /// What was found, such as the name of a font or the entry that uses transparency.
+    pub detail: String,
+}
+
+impl Document {
+    /// The PDF/A part and conformance level that the document claims, from the `pdfaid:part` and
+    /// `pdfaid:conformance` properties of its XMP metadata. The conformance level, such as `B`, is empty if the
+    /// metadata does not give one, as with PDF/A-4.
+    pub fn pdfa_identification(&self) -> Option<(u8, String)> {
+        let xml = String::from_utf8(self.xmp()?).ok()?;
+        let part = read_property(&xml, "pdfaid:part")?.first()?.trim().parse().ok()?;
+        let conformance = read_property(&xml, "pdfaid:conformance")
+            .and_then(|values| values.into_iter().next())
+            .map(|conformance| conformance.trim().to_uppercase())
+            .unwrap_or_default();
+        Some((part, conformance))
+    }
+
+    /// Check the document for the violations of a part of PDF/A, such as 1 for PDF/A-1, that can be found from its
+    /// structure: encryption, fonts that are not embedded, JavaScript and launch actions, LZW compression, a
+    /// missing output intent, missing or filtered XMP metadata, embedded files and, for PDF/A-1, transparency.
+    ///
+    /// This is no validation: a document without issues may still not conform, and content streams are not
+    /// looked into.
+    pub fn pdfa_precheck(&self, part: u8) -> Vec<PdfaIssue> {
+        let mut issues = Vec::new();
+        let mut issue = |kind, object, detail: String| issues.push(PdfaIssue { kind, object, detail });
+        let root_id = self.trailer.get(b"Root").and_then(Object::as_reference).ok();
+        // Graphics states shared by several resource dictionaries are reported once.
+        let mut transparency = BTreeSet::new();
+
+        if let Ok(encrypt) = self.trailer.get(b"Encrypt") {
+            issue(
+                PdfaIssueKind::Encrypted,
+                encrypt.as_reference().ok(),
+                "the trailer has /Encrypt".to_string(),
+            );
+        }
+
+        for (&id, object) in &self.objects {
+            let dict = match object {
+                Object::Dictionary(dict) => dict,
+                Object::Stream(stream) => &stream.dict,
+                _ => continue,
+            };
+            if let Some(font) = self.font_not_embedded(dict) {
+                issue(PdfaIssueKind::FontNotEmbedded, Some(id), font);
+            }
+            if part == 1 {
+                let filters = match dict.get_deref(b"Filter", self) {
+                    Ok(Object::Name(name)) => vec![name.as_slice()],
+                    Ok(Object::Array(names)) => names.iter().filter_map(|name| name.as_name().ok()).collect(),
+                    _ => Vec::new(),
+                };
+                if matches!(object, Object::Stream(_)) && filters.contains(&&b"LZWDecode"[..]) {
+                    issue(PdfaIssueKind::LzwCompression, Some(id), "/LZWDecode".to_string());
+                }
+                for (object, detail) in self.transparency(dict) {
+                    let object = object.unwrap_or(id);
+                    if transparency.insert((object, detail.clone())) {
+                        issue(PdfaIssueKind::Transparency, Some(object), detail);
+                    }
+                }
+            }
+        }
+
+        for action in self.actions_report() {
+            if !matches!(action.kind, ActionKind::JavaScript | ActionKind::Launch) {
+                continue;
+            }
+            let object = action.id.or(match action.location {
+                crate::ActionLocation::Page { page, .. } => Some(page),
+                crate::ActionLocation::Annotation { page, annotation, .. } => annotation.or(Some(page)),
+                crate::ActionLocation::Field { field, .. } => Some(field),
+                crate::ActionLocation::Outline(id) | crate::ActionLocation::Other(id) => Some(id),
+                _ => root_id,
+            });
+            issue(
+                PdfaIssueKind::Action(action.kind),
+                object,
+                format!("{:?}", action.location),
+            );
+        }
+
+        let catalog = self.catalog().ok();
+        let has_output_intent = catalog
+            .and_then(|catalog| {
+                catalog
+                    .get_deref(b"OutputIntents", self)
+                    .and_then(Object::as_array)
+                    .ok()
+            })
+            .into_iter()
+            .flatten()
+            .filter_map(|intent| self.dereference(intent).and_then(|(_, intent)| intent.as_dict()).ok())
+            .any(|intent| {
+                intent.get(b"S").and_then(Object::as_name).ok() == Some(b"GTS_PDFA1")
+                    && intent
+                        .get_deref(b"DestOutputProfile", self)
+                        .and_then(Object::as_stream)
+                        .is_ok()
+            });
+        if !has_output_intent {
+            issue(
+                PdfaIssueKind::MissingOutputIntent,
+                root_id,
+                "no /GTS_PDFA1 output intent with /DestOutputProfile".to_string(),
+            );
+        }
+
+        let metadata = catalog.and_then(|catalog| catalog.get(b"Metadata").ok());
+        match metadata.map(|metadata| self.dereference(metadata)) {
+            Some(Ok((id, Object::Stream(stream)))) => {
+                if stream.dict.has(b"Filter") {
+                    issue(
+                        PdfaIssueKind::FilteredXmp,
+                        id,
+                        "the /Metadata stream has /Filter".to_string(),
+                    );
+                }
+            }
+            _ => issue(
+                PdfaIssueKind::MissingXmp,
+                root_id,
+                "the catalog has no /Metadata stream".to_string(),
+            ),
+        }
+
+        if part <= 2 {
+            for attachment in self.attachments() {
+                // PDF/A-2 allows PDF/A files, which can only be told apart by a validator.
+                let is_pdf = attachment
+                    .data(self)
+                    .map(|data| data.starts_with(b"%PDF-"))
+                    .unwrap_or_default();
+                if part == 1 || !is_pdf {
+                    issue(
+                        PdfaIssueKind::EmbeddedFile,
+                        Some(attachment.stream),
+                        attachment.filename,
+                    );
+                }
+            }
+        }
+        issues
+    }
+
+    /// The name of a font whose program is not embedded, if the dictionary is such a font. Type 0 fonts are
+    /// checked through their descendant font, and Type 3 fonts are part of the document.
+    fn font_not_embedded(&self, dict: &Dictionary) -> Option<String> {
+        if dict.get(b"Type").and_then(Object::as_name).ok() != Some(b"Font") {
+            return None;
+        }
+        let subtype = dict.get(b"Subtype").and_then(Object::as_name).ok();
+        if subtype == Some(b"Type0") || subtype == Some(b"Type3") {
+            return None;
+        }
+        let embedded = dict
+            .get_deref(b"FontDescriptor", self)
+            .and_then(Object::as_dict)
+            .is_ok_and(|descriptor| {
+                [&b"FontFile"[..], b"FontFile2", b"FontFile3"]
+                    .iter()
+                    .any(|key| descriptor.has(key))
+            });
+        if embedded {
+            return None;
+        }
+        let name = dict.get(b"BaseFont").and_then(Object::as_name_str).unwrap_or_default();
+        Some(name.to_string())
+    }
+
+    /// The uses of transparency by a dictionary: soft masks of images, transparency groups, and the soft masks,
+    /// constant alpha and blend modes of the graphics states in its resources, with the graphics states that are
+    /// indirect objects.
+    fn transparency(&self, dict: &Dictionary) -> Vec<(Option<ObjectId>, String)> {
+        let mut found = Vec::new();
+        let soft_mask = dict
+            .get_deref(b"SMask", self)
+            .is_ok_and(|mask| mask.as_name().ok() != Some(b"None"));
+        if dict.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Image") && soft_mask {
+            found.push((None, "image /SMask".to_string()));
+        }
+        let group = dict.get_deref(b"Group", self).and_then(Object::as_dict);
+        if group.is_ok_and(|group| group.get(b"S").and_then(Object::as_name).ok() == Some(b"Transparency")) {
+            found.push((None, "/Group /S /Transparency".to_string()));
+        }
+
+        // The dictionary may be a page or form with resources, or a resource dictionary itself.
+        let resources = dict.get_deref(b"Resources", self).and_then(Object::as_dict).ok();
+        for resources in resources.into_iter().chain(Some(dict)) {
+            let states = match resources.get_deref(b"ExtGState", self).and_then(Object::as_dict) {
+                Ok(states) => states,
+                Err(_) => continue,
+            };
+            for (name, state) in states {
+                let (state_id, state) = match self.dereference(state) {
+                    Ok((id, Object::Dictionary(state))) => (id, state),
+                    _ => continue,
+                };
+                for detail in self.graphics_state_transparency(state) {
+                    found.push((state_id, format!("/{} {}", String::from_utf8_lossy(name), detail)));
+                }
+            }
+        }
+        found
+    }
+
+    fn graphics_state_transparency(&self, state: &Dictionary) -> Vec<&'static str> {
+        let mut found = Vec::new();
+        if state
+            .get_deref(b"SMask", self)
+            .is_ok_and(|mask| mask.as_name().ok() != Some(b"None"))
+        {
+            found.push("/SMask");
+        }
+        for (key, detail) in [(&b"CA"[..], "/CA"), (b"ca", "/ca")] {
+            if state
+                .get_deref(key, self)
+                .and_then(Object::as_float)
+                .is_ok_and(|alpha| alpha < 1.0)
+            {
+                found.push(detail);
+            }
+        }
+        let blend_mode = match state.get_deref(b"BM", self) {
+            Ok(Object::Name(name)) => Some(name.as_slice()),
+            Ok(Object::Array(names)) => names.first().and_then(|name| name.as_name().ok()),
+            _ => None,
+        };
+        if blend_mode.is_some_and(|mode| mode != b"Normal" && mode != b"Compatible") {
+            found.push("/BM");
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::{Content, Operation};
+    use crate::creator::tests::create_document;
+    use crate::Stream;
+
+    fn kinds(issues: &[PdfaIssue]) -> Vec<PdfaIssueKind> {
+        issues.iter().map(|issue| issue.kind).collect()
+    }
+
+    #[test]
+    fn identification_from_xmp() {
+        let mut doc = create_document();
+        assert_eq!(doc.pdfa_identification(), None);
+        let xmp = concat!(
+            "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\"><rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">",
+            "<rdf:Description rdf:about=\"\" xmlns:pdfaid=\"http://www.aiim.org/pdfa/ns/id/\" pdfaid:part=\"2\">",
+            "<pdfaid:conformance>b</pdfaid:conformance></rdf:Description></rdf:RDF></x:xmpmeta>",
+        );
+        doc.set_xmp(xmp.as_bytes()).unwrap();
+        assert_eq!(doc.pdfa_identification(), Some((2, "B".to_string())));
+        doc.set_xmp(xmp.replace("<pdfaid:conformance>b</pdfaid:conformance>", "").as_bytes())
+            .unwrap();
+        assert_eq!(doc.pdfa_identification(), Some((2, String::new())));
+    }
+
+    #[test]
+    fn precheck_structural_violations() {
+        let mut doc = create_document();
+        let font_id = doc.objects.iter().find_map(|(&id, object)| {
+            let font = object.as_dict().ok()?;
+            (font.get(b"Type").and_then(Object::as_name).ok() == Some(b"Font")).then_some(id)
+        });
+        let issues = doc.pdfa_precheck(2);
+        assert_eq!(
+            kinds(&issues),
+            vec![
+                PdfaIssueKind::FontNotEmbedded,
+                PdfaIssueKind::MissingOutputIntent,
+                PdfaIssueKind::MissingXmp
+            ]
+        );
+        assert_eq!((issues[0].object, issues[0].detail.as_str()), (font_id, "Courier"));
+
+        let profile = doc.add_object(Stream::new(dictionary! { "N" => 3 }, b"icc".to_vec()));
+        let state = doc.add_object(dictionary! { "Type" => "ExtGState", "ca" => 0.5 });
+        let lzw = content_stream(&mut doc, "LZWDecode");
+        let page_id = doc.page_iter().next().unwrap();
+        let page = doc.get_object_mut(page_id).unwrap().as_dict_mut().unwrap();
+        page.set(
+            "Resources",
+            dictionary! { "ExtGState" => dictionary! { "GS1" => state } },
+        );
+        page.set("Contents", lzw);
+        let root_id = doc.trailer.get(b"Root").and_then(Object::as_reference).unwrap();
+        let catalog = doc.get_object_mut(root_id).unwrap().as_dict_mut().unwrap();
+        catalog.set(
+            "OutputIntents",
+            vec![dictionary! { "S" => "GTS_PDFA1", "DestOutputProfile" => profile }.into()],
+        );
+        catalog.set(
+            "OpenAction",
+            dictionary! { "S" => "JavaScript", "JS" => Object::string_literal("app.alert(1)") },
+        );
+        doc.sync_metadata(crate::MetadataSync::InfoToXmp).unwrap();
+        doc.attach_file("data.csv", b"a,b".to_vec(), &Default::default())
+            .unwrap();
+
+        let issues = doc.pdfa_precheck(1);
+        assert_eq!(
+            kinds(&issues),
+            vec![
+                PdfaIssueKind::FontNotEmbedded,
+                PdfaIssueKind::Transparency,
+                PdfaIssueKind::LzwCompression,
+                PdfaIssueKind::Action(ActionKind::JavaScript),
+                PdfaIssueKind::EmbeddedFile
+            ]
+        );
+        assert_eq!((issues[1].object, issues[1].detail.as_str()), (Some(state), "/GS1 /ca"));
+        assert_eq!(issues[2].object, Some(lzw));
+        assert_eq!(issues[3].object, Some(root_id));
+        assert_eq!(issues[4].detail, "data.csv");
+        // Later parts allow transparency and LZW, and PDF/A-3 any embedded file.
+        assert_eq!(
+            kinds(&doc.pdfa_precheck(3)),
+            vec![
+                PdfaIssueKind::FontNotEmbedded,
+                PdfaIssueKind::Action(ActionKind::JavaScript)
+            ]
+        );
+    }
+
+    fn content_stream(doc: &mut Document, filter: &str) -> ObjectId {
+        let content = Content {
+            operations: vec![Operation::new("q", vec![]), Operation::new("Q", vec![])],
+        };
+        doc.add_object(Stream::new(
+            dictionary! { "Filter" => filter },
+            content.encode().unwrap(),
+        ))
+    }
+}
@@ -0,0 +1,83 @@
+use alloc::vec::Vec;
+use bitflags::bitflags;
+use core::fmt;
+
+bitflags! {
+    /// What users of an encrypted document may do without the owner password, as given by `/P`.
+    pub struct Permissions: u32 {
+        const PRINT = 1 << 2;
+        const MODIFY = 1 << 3;
+        const COPY = 1 << 4;
+        const ANNOTATE = 1 << 5;
+        const FILL_FORMS = 1 << 8;
+        const EXTRACT_ACCESSIBILITY = 1 << 9;
+        const ASSEMBLE = 1 << 10;
+        const PRINT_HIGH_RES = 1 << 11;
+    }
+}
+
+impl Permissions {
+    /// Read a `/P` value, which is a signed 32 bit integer, though some writers store it unsigned.
+    pub fn from_p(value: i64) -> Permissions {
+        Permissions::from_bits_truncate(value as i32 as u32)
+    }
+
+    /// The `/P` value, with the bits that must be set for compatibility set, as a signed integer.
+    pub fn to_p(self) -> i64 {
+        i64::from((self.bits() | 0xFFFF_F0C0) as i32)
+    }
+
+    fn names() -> [(Permissions, &'static str); 8] {
+        [
+            (Permissions::PRINT, "print"),
+            (Permissions::PRINT_HIGH_RES, "print in high resolution"),
+            (Permissions::MODIFY, "modify"),
+            (Permissions::COPY, "copy"),
+            (Permissions::ANNOTATE, "annotate"),
+            (Permissions::FILL_FORMS, "fill forms"),
+            (Permissions::EXTRACT_ACCESSIBILITY, "extract for accessibility"),
+            (Permissions::ASSEMBLE, "assemble"),
+        ]
+    }
+}
+
+impl fmt::Display for Permissions {
+    /// A summary such as `print, fill forms (denied: modify, copy, ...)`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names = |allowed: bool| {
+            Permissions::names()
+                .iter()
+                .filter(|(permission, _)| self.contains(*permission) == allowed)
+                .map(|(_, name)| *name)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        if self.is_all() {
+            write!(f, "all permissions")
+        } else if self.is_empty() {
+            write!(f, "no permissions")
+        } else {
+            write!(f, "{} (denied: {})", names(true), names(false))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn permission_values() {
+        assert_eq!(Permissions::from_p(-3900), Permissions::PRINT);
+        assert_eq!(Permissions::from_p(4_294_963_396), Permissions::PRINT);
+        assert_eq!(Permissions::PRINT.to_p(), -3900);
+        assert_eq!(Permissions::all().to_p(), -4);
+        assert_eq!(Permissions::all().to_string(), "all permissions");
+        assert_eq!(
+            (Permissions::PRINT | Permissions::FILL_FORMS).to_string(),
+            "print, fill forms (denied: print in high resolution, modify, copy, annotate, extract for \
+             accessibility, assemble)"
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use crate::datetime::PdfDate;
+use crate::{Dictionary, Document, Error, Object, ObjectId, Result};
+
+/// Where page-piece dictionaries are stored: in the catalog for data about the whole document, or in a page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PieceInfoTarget {
+    Document,
+    Page(ObjectId),
+}
+
+/// The private data of an application in a page-piece dictionary, from [`Document::get_piece_info`].
+#[derive(Debug, Clone)]
+pub struct PieceInfo {
+    /// `/LastModified`, when the application last changed the data.
+    pub last_modified: Option<PdfDate>,
+    /// `/Private`, the data itself; empty if it is missing or not a dictionary.
+    pub data: Dictionary,
+}
+
+impl Document {
+    /// Set the private data of an application in the `/PieceInfo` of the catalog or of a page, keeping the data
+    /// of other applications. The `/LastModified` of the data, and that of the page, are set to the time of the
+    /// [clock](Document::clock), as viewers compare them to find data that is out of date.
+    ///
+    /// For data of the whole document, it is the `/ModDate` set when saving that the viewers compare with.
+    pub fn set_piece_info(&mut self, target: PieceInfoTarget, app_name: &str, data: Dictionary) -> Result<()> {
+        let holder_id = self.piece_info_holder(target)?;
+        let now = self.clock.now();
+        let piece_info_id = match self.get_dictionary(holder_id)?.get(b"PieceInfo") {
+            Ok(Object::Reference(id)) if self.get_dictionary(*id).is_ok() => Some(*id),
+            _ => None,
+        };
+        let holder = self.get_object_mut(holder_id)?.as_dict_mut()?;
+        if let (PieceInfoTarget::Page(_), Some(now)) = (target, now) {
+            holder.set("LastModified", now);
+        }
+        let piece_info = match piece_info_id {
+            Some(id) => self.get_object_mut(id)?.as_dict_mut()?,
+            None => {
+                if !matches!(holder.get(b"PieceInfo"), Ok(Object::Dictionary(_))) {
+                    holder.set("PieceInfo", Dictionary::new());
+                }
+                holder.get_mut(b"PieceInfo")?.as_dict_mut()?
+            }
+        };
+
+        // Other entries of the data dictionary of the application are kept, but not one that is indirect.
+        if !matches!(piece_info.get(app_name.as_bytes()), Ok(Object::Dictionary(_))) {
+            piece_info.set(app_name, Dictionary::new());
+        }
+        let entry = piece_info.get_mut(app_name.as_bytes())?.as_dict_mut()?;
+        if let Some(now) = now {
+            entry.set("LastModified", now);
+        }
+        entry.set("Private", data);
+        Ok(())
+    }
+
+    /// The private data of an application in the `/PieceInfo` of the catalog or of a page.
+    pub fn get_piece_info(&self, target: PieceInfoTarget, app_name: &str) -> Option<PieceInfo> {
+        let holder = self.get_dictionary(self.piece_info_holder(target).ok()?).ok()?;
+        let entry = holder
+            .get_deref(b"PieceInfo", self)
+            .and_then(Object::as_dict)
+            .and_then(|piece_info| piece_info.get_deref(app_name.as_bytes(), self))
+            .and_then(Object::as_dict)
+            .ok()?;
+        Some(PieceInfo {
+            last_modified: entry
+                .get_deref(b"LastModified", self)
+                .ok()
+                .and_then(Object::as_datetime),
+            data: entry
+                .get_deref(b"Private", self)
+                .and_then(Object::as_dict)
+                .cloned()
+                .unwrap_or_default(),
+        })
+    }
+
+    /// The catalog or the page holding the page-piece dictionary of a target.
+    fn piece_info_holder(&self, target: PieceInfoTarget) -> Result<ObjectId> {
+        match target {
+            PieceInfoTarget::Document => self.trailer.get(b"Root")?.as_reference(),
+            PieceInfoTarget::Page(page_id) => {
+                let page = self.get_dictionary(page_id)?;
+                if page.get(b"Type").and_then(Object::as_name).ok() != Some(b"Page") {
+                    return Err(Error::Syntax(format!("{:?} is not a page", page_id)));
+                }
+                Ok(page_id)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::creator::tests::create_document;
+
+    #[test]
+    fn set_and_read_piece_info() {
+        let mut doc = create_document();
+        let page_id = doc.page_iter().next().unwrap();
+        let page = PieceInfoTarget::Page(page_id);
+        assert!(doc.get_piece_info(page, "Pipeline").is_none());
+        doc.get_object_mut(page_id).unwrap().as_dict_mut().unwrap().set(
+            "PieceInfo",
+            dictionary! { "Other" => dictionary! { "Private" => "kept" } },
+        );
+
+        doc.set_piece_info(page, "Pipeline", dictionary! { "Run" => 1 })
+            .unwrap();
+        doc.set_piece_info(page, "Pipeline", dictionary! { "Run" => 2 })
+            .unwrap();
+        let info = doc.get_piece_info(page, "Pipeline").unwrap();
+        assert_eq!(info.data.get(b"Run").and_then(Object::as_i64).unwrap(), 2);
+        assert!(info.last_modified.is_some());
+        let page_dict = doc.get_dictionary(page_id).unwrap();
+        assert!(page_dict
+            .get(b"LastModified")
+            .ok()
+            .and_then(Object::as_datetime)
+            .is_some());
+        let piece_info = page_dict.get(b"PieceInfo").and_then(Object::as_dict).unwrap();
+        assert!(piece_info.has(b"Other"));
+
+        assert!(doc.get_piece_info(PieceInfoTarget::Document, "Pipeline").is_none());
+        doc.set_piece_info(
+            PieceInfoTarget::Document,
+            "Pipeline",
+            dictionary! { "Source" => "scan" },
+        )
+        .unwrap();
+        assert!(doc.get_piece_info(PieceInfoTarget::Document, "Pipeline").is_some());
+        assert!(!doc.catalog().unwrap().has(b"LastModified"));
+        assert!(doc
+            .set_piece_info(PieceInfoTarget::Page((1, 0)), "Pipeline", Dictionary::new())
+            .is_err());
+    }
+}
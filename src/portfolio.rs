@@ -0,0 +1,347 @@
+//! Portfolios, or PDF packages: documents whose content is the files they embed, listed by viewers according to
+//! the `/Collection` dictionary of the catalog, with a cover sheet as their pages.
+
+use crate::encodings::{decode_text_string, encode_text_string};
+use crate::{
+    AttachmentLocation, AttachmentOptions, BuiltinFont, Dictionary, Document, DocumentBuilder, Object, PageBuilder,
+    Result, StringFormat,
+};
+use std::collections::BTreeMap;
+
+/// The `/Collection` dictionary of a portfolio, from [`Document::collection`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CollectionInfo {
+    /// The fields of the collection items of the files, in the order they are shown.
+    pub schema: Vec<CollectionField>,
+    /// The keys of the fields the files are sorted by, each with `true` for ascending order.
+    pub sort: Vec<(String, bool)>,
+    /// The name in the `/EmbeddedFiles` name tree of the file shown first.
+    pub initial_document: Option<String>,
+    pub view: CollectionView,
+}
+
+/// A field of the schema of a collection.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CollectionField {
+    /// The key of the field in the collection items.
+    pub key: String,
+    /// The name shown for the field, `/N`.
+    pub name: String,
+    pub field_type: CollectionFieldType,
+    /// The position of the field, `/O`.
+    pub order: Option<i64>,
+    /// Whether the field is shown, `/V`.
+    pub visible: bool,
+    /// Whether viewers let the field be edited, `/E`.
+    pub editable: bool,
+}
+
+/// The type of a field of a collection, `/Subtype`: data of the collection items, or a property of the files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollectionFieldType {
+    /// A text string, `S`.
+    Text,
+    /// A date, `D`.
+    Date,
+    /// A number, `N`.
+    Number,
+    /// The file name, `F`.
+    FileName,
+    /// The description of the file, `Desc`.
+    Description,
+    ModDate,
+    CreationDate,
+    Size,
+    CompressedSize,
+}
+
+/// How viewers first show the files of a collection, `/View`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollectionView {
+    /// A list with the fields of the schema, `D`.
+    Details,
+    /// Thumbnails, `T`.
+    Tile,
+    /// The cover sheet, with the files hidden, `H`.
+    Hidden,
+}
+
+/// A value of the collection item of a file, `/CI`. Dates are text strings, as they are written.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CollectionValue {
+    Text(String),
+    Number(f64),
+}
+
+/// A file of a portfolio, from [`Document::portfolio_extract_all`] or for [`Document::new_portfolio`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PortfolioMember {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+impl PortfolioMember {
+    /// Whether the file is a PDF document, from its header.
+    pub fn is_pdf(&self) -> bool {
+        // Readers accept the header anywhere in the first kilobyte.
+        let start = &self.data[..self.data.len().min(1024)];
+        start.windows(5).any(|window| window == b"%PDF-")
+    }
+
+    /// Load the file as a document, such as a portfolio within the portfolio.
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    pub fn load(&self) -> Result<Document> {
+        Document::load_mem(&self.data)
+    }
+}
+
+/// The collection item of a file specification. A value of a subitem dictionary is given without its prefix.
+pub(crate) fn collection_item(doc: &Document, file_spec: &Dictionary) -> BTreeMap<String, CollectionValue> {
+    let item = match file_spec.get_deref(b"CI", doc).and_then(Object::as_dict) {
+        Ok(item) => item,
+        Err(_) => return BTreeMap::new(),
+    };
+    let value = |object: &Object| match object {
+        Object::String(text, _) => Some(CollectionValue::Text(decode_text_string(text))),
+        Object::Integer(_) | Object::Real(_) => object.as_float().ok().map(CollectionValue::Number),
+        _ => None,
+    };
+    item.iter()
+        .filter(|(key, _)| key.as_slice() != b"Type")
+        .filter_map(|(key, object)| {
+            let object = doc.dereference(object).ok()?.1;
+            let object = match object {
+                Object::Dictionary(subitem) => subitem.get_deref(b"D", doc).ok()?,
+                object => object,
+            };
+            Some((String::from_utf8_lossy(key).into_owned(), value(object)?))
+        })
+        .collect()
+}
+
+impl Document {
+    /// Whether the document is a portfolio, its catalog having a `/Collection` dictionary.
+    pub fn is_portfolio(&self) -> bool {
+        self.collection_dictionary().is_some()
+    }
+
+    fn collection_dictionary(&self) -> Option<&Dictionary> {
+        self.catalog()
+            .and_then(|catalog| catalog.get_deref(b"Collection", self))
+            .and_then(Object::as_dict)
+            .ok()
+    }
+
+    /// The `/Collection` dictionary of a portfolio. Fields of the schema with an unknown type are skipped.
+    pub fn collection(&self) -> Option<CollectionInfo> {
+        let collection = self.collection_dictionary()?;
+        let text = |dict: &Dictionary, key: &[u8]| {
+            dict.get_deref(key, self)
+                .and_then(Object::as_str)
+                .ok()
+                .map(decode_text_string)
+        };
+
+        let mut schema = Vec::new();
+        if let Ok(fields) = collection.get_deref(b"Schema", self).and_then(Object::as_dict) {
+            for (key, field) in fields.iter().filter(|(key, _)| key.as_slice() != b"Type") {
+                let field = match self.dereference(field).and_then(|(_, field)| field.as_dict()) {
+                    Ok(field) => field,
+                    Err(_) => continue,
+                };
+                let field_type = match field.get(b"Subtype").and_then(Object::as_name) {
+                    Ok(b"S") => CollectionFieldType::Text,
+                    Ok(b"D") => CollectionFieldType::Date,
+                    Ok(b"N") => CollectionFieldType::Number,
+                    Ok(b"F") => CollectionFieldType::FileName,
+                    Ok(b"Desc") => CollectionFieldType::Description,
+                    Ok(b"ModDate") => CollectionFieldType::ModDate,
+                    Ok(b"CreationDate") => CollectionFieldType::CreationDate,
+                    Ok(b"Size") => CollectionFieldType::Size,
+                    Ok(b"CompressedSize") => CollectionFieldType::CompressedSize,
+                    _ => continue,
+                };
+                let key = String::from_utf8_lossy(key).into_owned();
+                schema.push(CollectionField {
+                    name: text(field, b"N").unwrap_or_else(|| key.clone()),
+                    key,
+                    field_type,
+                    order: field.get(b"O").and_then(Object::as_i64).ok(),
+                    visible: field.get(b"V").and_then(Object::as_bool).unwrap_or(true),
+                    editable: field.get(b"E").and_then(Object::as_bool).unwrap_or(false),
+                });
+            }
+        }
+        // Fields without an order come last, in the order they are written.
+        schema.sort_by_key(|field| field.order.unwrap_or(i64::MAX));
+
+        let mut sort = Vec::new();
+        if let Ok(order) = collection.get_deref(b"Sort", self).and_then(Object::as_dict) {
+            let one_or_many = |key: &[u8]| match order.get_deref(key, self) {
+                Ok(Object::Array(values)) => values.clone(),
+                Ok(value) => vec![value.clone()],
+                Err(_) => Vec::new(),
+            };
+            let ascending = one_or_many(b"A");
+            for (index, key) in one_or_many(b"S").iter().enumerate() {
+                if let Ok(key) = key.as_name() {
+                    // The first field is sorted in ascending order unless told otherwise, the others as the first.
+                    let ascending = ascending
+                        .get(index)
+                        .or_else(|| ascending.first())
+                        .and_then(|ascending| ascending.as_bool().ok())
+                        .unwrap_or(true);
+                    sort.push((String::from_utf8_lossy(key).into_owned(), ascending));
+                }
+            }
+        }
+
+        let view = match collection.get(b"View").and_then(Object::as_name) {
+            Ok(b"T") => CollectionView::Tile,
+            Ok(b"H") => CollectionView::Hidden,
+            _ => CollectionView::Details,
+        };
+        Some(CollectionInfo {
+            schema,
+            sort,
+            initial_document: text(collection, b"D"),
+            view,
+        })
+    }
+
+    /// The files of the `/EmbeddedFiles` name tree, by name, which are the content of a portfolio. Their file
+    /// specifications and collection items are those of [`Document::attachments`].
+    pub fn portfolio_extract_all(&self) -> Result<Vec<PortfolioMember>> {
+        let mut members = Vec::new();
+        for attachment in self.attachments() {
+            let name = match &attachment.location {
+                AttachmentLocation::EmbeddedFiles(name) => decode_text_string(name),
+                AttachmentLocation::Annotation { .. } => continue,
+            };
+            members.push(PortfolioMember {
+                name,
+                data: attachment.data(self)?,
+            });
+        }
+        Ok(members)
+    }
+
+    /// Create a portfolio of files, with a cover sheet naming them for viewers that do not show portfolios. The
+    /// files are listed in details view, with the first shown first.
+    pub fn new_portfolio(members: Vec<PortfolioMember>) -> Result<Document> {
+        let mut cover = PageBuilder::a4().text(
+            72.0,
+            760.0,
+            BuiltinFont::Helvetica,
+            16.0,
+            "This document is a portfolio of the following files:",
+        );
+        for (index, member) in members.iter().enumerate() {
+            let y = 730.0 - 18.0 * index as f64;
+            cover = cover.text(90.0, y, BuiltinFont::Helvetica, 12.0, &member.name);
+        }
+        let mut doc = DocumentBuilder::new().version("1.7").page(cover.build()).build()?;
+
+        let mut collection = dictionary! {
+            "Type" => "Collection",
+            "View" => "D",
+        };
+        if let Some(first) = members.first() {
+            collection.set(
+                "D",
+                Object::String(encode_text_string(&first.name), StringFormat::Literal),
+            );
+        }
+        for member in members {
+            doc.attach_file(&member.name, member.data, &AttachmentOptions::default())?;
+        }
+        let catalog = doc.catalog_mut()?;
+        catalog.set("Collection", collection);
+        catalog.set("PageMode", "UseAttachments");
+        Ok(doc)
+    }
+}
+
+#[cfg(all(test, any(feature = "pom_parser", feature = "nom_parser")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn portfolio_created_read_and_extracted() {
+        let inner = Document::new_portfolio(vec![PortfolioMember {
+            name: "nested.txt".to_string(),
+            data: b"Nested".to_vec(),
+        }])
+        .unwrap();
+        let mut inner_data = Vec::new();
+        inner.clone().save_to(&mut inner_data).unwrap();
+        let members = vec![
+            PortfolioMember {
+                name: "report.pdf".to_string(),
+                data: inner_data,
+            },
+            PortfolioMember {
+                name: "données.csv".to_string(),
+                data: b"a,b\n1,2\n".to_vec(),
+            },
+        ];
+        let mut doc = Document::new_portfolio(members.clone()).unwrap();
+
+        // A schema and collection items, as portfolio editors write them.
+        let collection = doc.catalog_mut().unwrap().get_mut(b"Collection").unwrap();
+        let collection = collection.as_dict_mut().unwrap();
+        collection.set(
+            "Schema",
+            dictionary! {
+                "Type" => "CollectionSchema",
+                "Pages" => dictionary! { "Subtype" => "N", "N" => Object::string_literal("Pages"), "O" => 2 },
+                "File" => dictionary! { "Subtype" => "F", "N" => Object::string_literal("Name"), "O" => 1 },
+                "Notes" => dictionary! { "Subtype" => "S", "V" => false, "E" => true },
+            },
+        );
+        collection.set(
+            "Sort",
+            dictionary! { "S" => vec!["Pages".into(), "File".into()], "A" => false },
+        );
+        let report_spec = doc.attachments()[1].file_spec.unwrap();
+        doc.get_object_mut(report_spec).unwrap().as_dict_mut().unwrap().set(
+            "CI",
+            dictionary! {
+                "Pages" => 1,
+                "Notes" => dictionary! { "D" => Object::string_literal("Draft"), "P" => Object::string_literal("v1 ") },
+            },
+        );
+
+        let mut saved = Vec::new();
+        doc.save_to(&mut saved).unwrap();
+        let doc = Document::load_mem(&saved).unwrap();
+        assert!(doc.is_portfolio() && !Document::new().is_portfolio());
+        let collection = doc.collection().unwrap();
+        let keys: Vec<&str> = collection.schema.iter().map(|field| field.key.as_str()).collect();
+        assert_eq!(keys, ["File", "Pages", "Notes"]);
+        assert_eq!(collection.schema[0].field_type, CollectionFieldType::FileName);
+        assert_eq!(collection.schema[2].name, "Notes");
+        assert!(!collection.schema[2].visible && collection.schema[2].editable);
+        assert_eq!(
+            collection.sort,
+            [("Pages".to_string(), false), ("File".to_string(), false)]
+        );
+        assert_eq!(collection.initial_document.as_deref(), Some("report.pdf"));
+        assert_eq!(collection.view, CollectionView::Details);
+
+        // The members are listed by name in the name tree.
+        let attachments = doc.attachments();
+        assert_eq!(attachments[1].filename, "report.pdf");
+        assert_eq!(attachments[1].collection_item["Pages"], CollectionValue::Number(1.0));
+        assert_eq!(
+            attachments[1].collection_item["Notes"],
+            CollectionValue::Text("Draft".to_string())
+        );
+        let extracted = doc.portfolio_extract_all().unwrap();
+        assert_eq!(extracted[0], members[1]);
+        assert!(!extracted[0].is_pdf() && extracted[1].is_pdf());
+        let nested = extracted[1].load().unwrap();
+        assert!(nested.is_portfolio());
+        assert_eq!(nested.portfolio_extract_all().unwrap()[0].data, b"Nested");
+    }
+}
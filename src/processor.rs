@@ -1,9 +1,54 @@
 use crate::Result;
-use crate::{Document, Object, ObjectId};
-use std::collections::BTreeMap;
+use crate::content::{Content, Operation};
+use crate::geometry::{transform_point, transform_rect, Matrix, Rect};
+use crate::{DestinationPolicy, Dictionary, Document, Error, KnownName, Object, ObjectId, Stream};
+use log::warn;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use std::collections::{BTreeMap, BTreeSet};
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
 use std::fs::File;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
 use std::io::Write;
 
+/// Options for [`Document::resize_pages_with`].
+#[derive(Debug, Clone, Default)]
+pub struct PageResizeOptions {
+    /// Draw the appearances of the annotations again for their new rectangles, with
+    /// [`Document::regenerate_annotation_appearances`], rather than letting viewers scale them.
+    pub regenerate_appearances: bool,
+}
+
+/// Transform the rectangle of an annotation and the points it draws with.
+fn transform_annotation_geometry(annotation: &mut Object, matrix: &Matrix) {
+    fn transform_points(points: &mut [Object], matrix: &Matrix) {
+        for point in points.chunks_exact_mut(2) {
+            if let (Ok(x), Ok(y)) = (point[0].as_float(), point[1].as_float()) {
+                let (x, y) = transform_point(matrix, x, y);
+                point[0] = Object::Real(x);
+                point[1] = Object::Real(y);
+            }
+        }
+    }
+    if let Object::Dictionary(ref mut annotation) = *annotation {
+        if let Ok(rect) = annotation.get(b"Rect").and_then(Rect::from_object) {
+            annotation.set("Rect", transform_rect(matrix, &rect));
+        }
+        for key in [&b"QuadPoints"[..], b"L", b"CL", b"Vertices"] {
+            if let Ok(Object::Array(points)) = annotation.get_mut(key) {
+                transform_points(points, matrix);
+            }
+        }
+        if let Ok(Object::Array(paths)) = annotation.get_mut(b"InkList") {
+            for path in paths.iter_mut() {
+                if let Object::Array(points) = path {
+                    transform_points(points, matrix);
+                }
+            }
+        }
+    }
+}
+
 impl Document {
     /// Change producer of document information dictionary.
     pub fn change_producer(&mut self, producer: &str) {
@@ -18,11 +63,12 @@ impl Document {
         }
     }
 
-    /// Compress PDF stream objects.
+    /// Compress PDF stream objects, except XMP metadata streams, which must stay readable without decoding.
     pub fn compress(&mut self) {
         for object in self.objects.values_mut() {
             if let Object::Stream(ref mut stream) = *object {
-                if stream.allows_compression {
+                let metadata = stream.dict.known_type() == Some(KnownName::Metadata);
+                if stream.allows_compression && !metadata {
                     // Ignore any error and continue to compress other streams.
                     let _ = stream.compress();
                 }
@@ -32,18 +78,70 @@ impl Document {
 
     /// Decompress PDF stream objects.
     pub fn decompress(&mut self) {
-        for object in self.objects.values_mut() {
-            if let Object::Stream(ref mut stream) = *object {
-                stream.decompress()
+        self.try_decompress();
+    }
+
+    /// Decompress PDF stream objects, on several threads with the `parallel` feature, and return the streams that
+    /// could not be decoded with the error, in object order. Streams with filters that are not supported fail with
+    /// [`Error::Unimplemented`]; images are left compressed and not reported.
+    pub fn try_decompress(&mut self) -> Vec<(ObjectId, Error)> {
+        // The streams are decoded apart from the document, so filters and decode parameters given as references
+        // are resolved beforehand.
+        let mut resolved = Vec::new();
+        for (&id, object) in &self.objects {
+            if let Object::Stream(ref stream) = *object {
+                for key in [&b"Filter"[..], b"DecodeParms"] {
+                    if let Ok(Object::Reference(_)) = stream.dict.get(key) {
+                        if let Ok(value) = stream.dict.get_deref(key, self) {
+                            resolved.push((id, key, value.clone()));
+                        }
+                    }
+                }
             }
         }
+        for (id, key, value) in resolved {
+            if let Some(Object::Stream(ref mut stream)) = self.objects.get_mut(&id) {
+                stream.dict.set(key, value);
+            }
+        }
+
+        let decode = |(id, object): (&ObjectId, &mut Object)| {
+            let stream = match *object {
+                Object::Stream(ref mut stream) if stream.dict.has(b"Filter") => stream,
+                _ => return None,
+            };
+            if stream.dict.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Image") {
+                return None;
+            }
+            match stream.decompressed_content() {
+                Ok(data) => {
+                    stream.dict.remove(b"DecodeParms");
+                    stream.dict.remove(b"Filter");
+                    stream.set_content(data);
+                    None
+                }
+                Err(err) => Some((*id, err)),
+            }
+        };
+        #[cfg(feature = "rayon")]
+        let errors = self.objects.par_iter_mut().filter_map(decode).collect();
+        #[cfg(not(feature = "rayon"))]
+        let errors = self.objects.iter_mut().filter_map(decode).collect();
+        errors
     }
 
-    /// Delete pages.
+    /// Delete pages, the beads of article threads on them and the destinations that go to them, as
+    /// [`DestinationPolicy::Drop`] removes them.
     pub fn delete_pages(&mut self, page_numbers: &[u32]) {
         let pages = self.get_pages();
+        let deleted: BTreeSet<ObjectId> = page_numbers.iter().filter_map(|number| pages.get(number)).copied().collect();
+        self.unlink_page_beads(&deleted);
+        let mapping = deleted.iter().map(|&page_id| (page_id, None)).collect();
+        if let Err(err) = self.remap_destinations(&mapping, DestinationPolicy::Drop) {
+            warn!("Could not remove the destinations of deleted pages: {}", err);
+        }
         for page_number in page_numbers {
-            if let Some(page) = pages.get(&page_number).and_then(|page_id| self.delete_object(*page_id)) {
+            if let Some(page) = pages.get(page_number).and_then(|page_id| self.delete_object(*page_id)) {
                 let mut page_tree_ref = page
                     .as_dict()
                     .and_then(|dict| dict.get(b"Parent"))
@@ -62,12 +160,154 @@ impl Document {
         }
     }
 
-    /// Prune all unused objects.
+    /// A document of some of the pages, numbered from 1, with the objects they use. The contents of the streams are
+    /// shared with this document rather than copied.
+    pub fn extract_pages(&self, page_numbers: &[u32]) -> Document {
+        let mut doc = self.clone();
+        let others: Vec<u32> = doc
+            .get_pages()
+            .into_keys()
+            .filter(|number| !page_numbers.contains(number))
+            .collect();
+        doc.delete_pages(&others);
+        doc.prune_objects();
+        doc
+    }
+
+    /// Copy an object of another document, with the objects it refers to, directly or not, under new ids. Returns
+    /// the id of the copy. `/Parent` entries, which would bring the page tree of the other document along with a
+    /// page, are left out. The contents of the streams are shared with the other document rather than copied.
+    pub fn import_object(&mut self, other: &Document, id: ObjectId) -> Result<ObjectId> {
+        /// Give the references of an object the ids of the copies, the objects not seen yet being queued.
+        fn renumber(
+            object: &mut Object, ids: &mut BTreeMap<ObjectId, ObjectId>, pending: &mut Vec<ObjectId>, next: &mut u32,
+        ) {
+            match object {
+                Object::Reference(id) => {
+                    *id = *ids.entry(*id).or_insert_with(|| {
+                        pending.push(*id);
+                        *next += 1;
+                        (*next, 0)
+                    });
+                }
+                Object::Array(array) => array.iter_mut().for_each(|item| renumber(item, ids, pending, next)),
+                Object::Dictionary(dict) => {
+                    dict.remove(b"Parent");
+                    dict.iter_mut().for_each(|(_, value)| renumber(value, ids, pending, next));
+                }
+                Object::Stream(stream) => stream
+                    .dict
+                    .iter_mut()
+                    .for_each(|(_, value)| renumber(value, ids, pending, next)),
+                _ => {}
+            }
+        }
+
+        other.get_object(id)?;
+        let mut ids = BTreeMap::new();
+        ids.insert(id, self.new_object_id());
+        let mut pending = vec![id];
+        let mut next = self.max_id;
+        while let Some(old_id) = pending.pop() {
+            // References to missing objects are left to resolve to `null` in the copies as well.
+            if let Ok(object) = other.get_object(old_id) {
+                let mut object = object.clone();
+                renumber(&mut object, &mut ids, &mut pending, &mut next);
+                self.objects.insert(ids[&old_id], object);
+            }
+        }
+        self.max_id = next;
+        Ok(ids[&id])
+    }
+
+    /// Scale the pages to a new size, keeping their proportions: their content is scaled to fit and centered, and
+    /// the boxes of the pages, the rectangles and points of their annotations and their
+    /// [viewports](Document::page_viewports) follow it.
+    pub fn resize_pages(&mut self, page_numbers: &[u32], width: f64, height: f64) -> Result<()> {
+        self.resize_pages_with(page_numbers, width, height, &PageResizeOptions::default())
+    }
+
+    /// Scale the pages to a new size as [`Document::resize_pages`] does, with options.
+    pub fn resize_pages_with(
+        &mut self, page_numbers: &[u32], width: f64, height: f64, options: &PageResizeOptions,
+    ) -> Result<()> {
+        if !(width > 0.0 && height > 0.0) {
+            return Err(Error::Syntax(format!("Invalid page size {} by {}", width, height)));
+        }
+        let pages = self.get_pages();
+        for page_number in page_numbers {
+            let page_id = match pages.get(page_number) {
+                Some(page_id) => *page_id,
+                None => continue,
+            };
+            let media_box = self
+                .get_page_media_box(page_id)
+                .ok_or_else(|| Error::Syntax(format!("Page {:?} has no media box", page_id)))?;
+            let scale = (width / media_box.width()).min(height / media_box.height());
+            let matrix = [
+                scale,
+                0.0,
+                0.0,
+                scale,
+                (width - media_box.width() * scale) / 2.0 - media_box.llx * scale,
+                (height - media_box.height() * scale) / 2.0 - media_box.lly * scale,
+            ];
+
+            let begin = Content {
+                operations: vec![
+                    Operation::new("q", vec![]),
+                    Operation::new("cm", matrix.iter().map(|&value| value.into()).collect()),
+                ],
+            };
+            let end = Content {
+                operations: vec![Operation::new("Q", vec![])],
+            };
+            let begin_id = self.add_object(Stream::new(Dictionary::new(), begin.encode()?));
+            let end_id = self.add_object(Stream::new(Dictionary::new(), end.encode()?));
+            let mut contents: Vec<Object> = vec![begin_id.into()];
+            contents.extend(self.get_page_contents(page_id).into_iter().map(Object::from));
+            contents.push(end_id.into());
+
+            let page = self.get_object_mut(page_id)?.as_dict_mut()?;
+            page.set("Contents", contents);
+            page.set("MediaBox", Rect::new(0.0, 0.0, width, height));
+            for key in [&b"CropBox"[..], b"BleedBox", b"TrimBox", b"ArtBox"] {
+                if let Ok(rect) = page.get(key).and_then(Rect::from_object) {
+                    page.set(key, transform_rect(&matrix, &rect));
+                }
+            }
+            let annotations: Vec<Object> = match page.get_mut(b"Annots") {
+                Ok(Object::Array(annotations)) => {
+                    for annotation in annotations.iter_mut() {
+                        transform_annotation_geometry(annotation, &matrix);
+                    }
+                    annotations.clone()
+                }
+                _ => Vec::new(),
+            };
+            for annotation in annotations {
+                if let Ok(id) = annotation.as_reference() {
+                    if let Ok(annotation) = self.get_object_mut(id) {
+                        transform_annotation_geometry(annotation, &matrix);
+                    }
+                }
+            }
+            self.transform_page_viewports(page_id, &matrix)?;
+            if options.regenerate_appearances {
+                self.regenerate_annotation_appearances(page_id, |_| true)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Prune all unused objects. The fonts that [`Document::font_usage`] finds are kept with the objects they refer
+    /// to, wherever they are used from.
     pub fn prune_objects(&mut self) -> Vec<ObjectId> {
         let mut ids = vec![];
-        let refs = self.traverse_objects(|_| {});
+        let refs: BTreeSet<ObjectId> = self.traverse_objects(|_| {}).into_iter().collect();
+        let fonts = self.used_font_objects();
         for id in self.objects.keys() {
-            if !refs.contains(id) {
+            if !refs.contains(id) && !fonts.contains(id) {
                 ids.push(*id);
             }
         }
@@ -163,6 +403,7 @@ impl Document {
     /// Renumber objects with a custom starting id, this is very useful in case of multiple
     /// document objects insertion in a single main document
     pub fn renumber_objects_with(&mut self, starting_id: u32) {
+        let _span = span!(INFO, "renumber", objects = self.objects.len(), starting_id);
         let mut replace = BTreeMap::new();
         let mut new_id = starting_id;
         let mut ids = self.objects.keys().cloned().collect::<Vec<ObjectId>>();
@@ -196,7 +437,7 @@ impl Document {
 
         let action = |object: &mut Object| {
             if let Object::Reference(ref mut id) = *object {
-                if replace.contains_key(&id) {
+                if replace.contains_key(id) {
                     *id = replace[id];
                 }
             }
@@ -205,15 +446,14 @@ impl Document {
         self.traverse_objects(action);
 
         self.max_id = new_id - 1;
+        self.id_allocator = None;
     }
 
     pub fn change_content_stream(&mut self, stream_id: ObjectId, content: Vec<u8>) {
-        if let Some(content_stream) = self.objects.get_mut(&stream_id) {
-            if let Object::Stream(ref mut stream) = *content_stream {
-                stream.set_plain_content(content);
-                // Ignore any compression error.
-                let _ = stream.compress();
-            }
+        if let Some(Object::Stream(ref mut stream)) = self.objects.get_mut(&stream_id) {
+            stream.set_plain_content(content);
+            // Ignore any compression error.
+            let _ = stream.compress();
         }
     }
 
@@ -228,10 +468,8 @@ impl Document {
                     }
                 } else {
                     let new_stream = self.add_object(super::Stream::new(dictionary! {}, content));
-                    if let Ok(page) = self.get_object_mut(page_id) {
-                        if let Object::Dictionary(ref mut dict) = *page {
-                            dict.set("Contents", new_stream);
-                        }
+                    if let Ok(Object::Dictionary(ref mut dict)) = self.get_object_mut(page_id) {
+                        dict.set("Contents", new_stream);
                     }
                 }
             }
@@ -240,21 +478,114 @@ impl Document {
         Ok(())
     }
 
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
     pub fn extract_stream(&self, stream_id: ObjectId, decompress: bool) -> Result<()> {
         let mut file = File::create(format!("{:?}.bin", stream_id))?;
-        if let Ok(stream_obj) = self.get_object(stream_id) {
-            if let Object::Stream(ref stream) = *stream_obj {
-                if decompress {
-                    if let Ok(data) = stream.decompressed_content() {
-                        file.write_all(&data)?;
-                    } else {
-                        file.write_all(&stream.content)?;
-                    }
+        if let Ok(Object::Stream(ref stream)) = self.get_object(stream_id) {
+            if decompress {
+                if let Ok(data) = stream.decompressed_content() {
+                    file.write_all(&data)?;
                 } else {
                     file.write_all(&stream.content)?;
                 }
+            } else {
+                file.write_all(&stream.content)?;
             }
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Dictionary, Stream};
+
+    #[test]
+    fn decompress_with_errors() {
+        let mut doc = Document::with_version("1.5");
+        let mut text = Stream::new(Dictionary::new(), b"BT /F1 12 Tf (Hello) Tj ET\n".repeat(20));
+        text.compress().unwrap();
+        let text = doc.add_object(text);
+
+        // Rows of three bytes without prediction, each after the byte of its PNG filter.
+        let params = doc.add_object(dictionary! { "Predictor" => 12, "Columns" => 3 });
+        let mut predicted = Stream::new(Dictionary::new(), [0, 1, 2, 3].repeat(20));
+        predicted.compress().unwrap();
+        predicted.dict.set("DecodeParms", params);
+        let predicted = doc.add_object(predicted);
+
+        let image = doc.add_object(Stream::new(
+            dictionary! { "Subtype" => "Image", "Filter" => "FlateDecode" },
+            vec![1, 2, 3],
+        ));
+        let unsupported = doc.add_object(Stream::new(dictionary! { "Filter" => "RunLengthDecode" }, vec![128]));
+
+        let errors = doc.try_decompress();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, unsupported);
+        assert!(matches!(errors[0].1, Error::Unimplemented(_)));
+        let text = doc.get_object(text).and_then(Object::as_stream).unwrap();
+        assert!(!text.dict.has(b"Filter"));
+        assert_eq!(*text.content, b"BT /F1 12 Tf (Hello) Tj ET\n".repeat(20));
+        let predicted = doc.get_object(predicted).and_then(Object::as_stream).unwrap();
+        assert_eq!(*predicted.content, [1, 2, 3].repeat(20));
+        assert!(doc
+            .get_object(image)
+            .and_then(Object::as_stream)
+            .unwrap()
+            .dict
+            .has(b"Filter"));
+    }
+
+    #[test]
+    fn extracted_pages_share_stream_content() {
+        use std::sync::Arc;
+
+        let mut source = crate::creator::tests::create_document();
+        let image = source.add_object(Stream::new(
+            dictionary! { "Type" => "XObject", "Subtype" => "Image", "Width" => 1024, "Height" => 1024 },
+            vec![0x80; 1 << 20],
+        ));
+        let pages_id = source
+            .catalog()
+            .unwrap()
+            .get(b"Pages")
+            .and_then(Object::as_reference)
+            .unwrap();
+        let first_page = source.page_iter().next().unwrap();
+        let second_page = source.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "Resources" => dictionary! { "XObject" => dictionary! { "Im1" => image } },
+        });
+        let pages = source.get_object_mut(pages_id).unwrap().as_dict_mut().unwrap();
+        pages.set("Kids", vec![first_page.into(), second_page.into()]);
+        pages.set("Count", 2);
+
+        let extracted: Vec<Document> = (0..100).map(|_| source.extract_pages(&[2])).collect();
+        assert!(extracted.iter().all(|doc| doc.get_pages().len() == 1));
+        let content = |doc: &Document, id| doc.get_object(id).and_then(Object::as_stream).unwrap().content.clone();
+        let shared = content(&source, image);
+        // The bytes are held by the source, the 100 documents and `shared`.
+        assert_eq!(Arc::strong_count(&shared), 102);
+        assert!(extracted.iter().all(|doc| Arc::ptr_eq(&content(doc, image), &shared)));
+
+        // Importing the page into another document copies the image along with it, sharing its bytes again.
+        let mut target = Document::with_version("1.5");
+        let page = target.import_object(&source, second_page).unwrap();
+        let resources = target.get_dictionary(page).unwrap().get(b"Resources").unwrap();
+        let copy = resources.as_dict().unwrap().get(b"XObject").and_then(Object::as_dict).unwrap();
+        let copy = copy.get(b"Im1").and_then(Object::as_reference).unwrap();
+        assert_ne!(copy, image);
+        assert!(Arc::ptr_eq(&content(&target, copy), &shared));
+        assert!(!target.get_dictionary(page).unwrap().has(b"Parent"));
+        assert_eq!(target.max_id, 2);
+
+        let mut changed = extracted[0].clone();
+        let stream = changed.get_object_mut(image).and_then(Object::as_stream_mut).unwrap();
+        stream.content_mut()[0] = 0;
+        assert!(!Arc::ptr_eq(&stream.content, &shared));
+        assert_eq!(shared[0], 0x80);
+    }
+}
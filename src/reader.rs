@@ -2,51 +2,193 @@
 
 use log::{error, warn};
 use std::cmp;
-use std::convert::TryInto;
+use std::collections::BTreeMap;
+use std::convert::{TryFrom, TryInto};
+use std::fmt;
+use std::ops::Range;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
 use std::fs::File;
 use std::io::Read;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
 use std::path::Path;
 use std::sync::Mutex;
 
 #[cfg(feature = "rayon")]
 use rayon::prelude::*;
 
-use super::parser;
-use super::{Document, Object, ObjectId};
+use super::{numbers, parser};
+use super::{Dictionary, Document, KnownName, Object, ObjectId};
 use crate::error::XrefError;
 use crate::object_stream::ObjectStream;
 use crate::xref::XrefEntry;
-use crate::{Error, Result};
+use crate::{Error, ParseWarning, ParseWarningKind, Phase, Result};
+
+/// What loading does with an object, as a [`ParseOptions::object_filter`] decides.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectAction {
+    Keep,
+    /// Keep the dictionary of a stream and drop its content, which is left empty.
+    SkipContent,
+    /// Replace the object by `null`, which references to it then resolve to.
+    Drop,
+}
+
+/// Settings for [`Document::load_with_options`] and the other loading methods taking options.
+#[derive(Default)]
+pub struct ParseOptions {
+    /// Called with the identifier and the dictionary of each dictionary and stream object once it is parsed,
+    /// including those of object streams, to keep it, skip its content or drop it. Other objects are always kept, as
+    /// are the object streams themselves; the objects of the object streams of encrypted documents, read when the
+    /// document is decrypted, are not filtered. With the `parallel` feature it is called on several threads.
+    pub object_filter: Option<Box<ObjectFilter>>,
+    /// Keep where each object is in the file, so that saving copies the objects left unchanged as they are instead
+    /// of writing them anew. Only the objects read directly are kept, not those of object streams.
+    pub preserve_raw: bool,
+    /// Parse on the blocking threads of the `tokio` runtime when loading with `Document::load_async` or
+    /// `Document::load_from_async` and their variants with options, so that the task loading does not hold its
+    /// worker thread meanwhile. Only with the `async` feature.
+    pub blocking_parse: bool,
+    /// Keep the file with the document, for [`Document::save_incremental`], [`Document::sign`] and
+    /// [`Document::rollback_to`]. It is kept anyway for signed documents, whose signatures cover it, and with
+    /// `preserve_raw`; otherwise the document holds nothing more than its objects.
+    pub keep_source: bool,
+}
+
+/// The callback of [`ParseOptions::object_filter`].
+pub type ObjectFilter = dyn Fn(ObjectId, &Dictionary) -> ObjectAction + Send + Sync;
+
+impl fmt::Debug for ParseOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ParseOptions")
+            .field("object_filter", &self.object_filter.as_ref().map(|_| ".."))
+            .field("preserve_raw", &self.preserve_raw)
+            .field("blocking_parse", &self.blocking_parse)
+            .field("keep_source", &self.keep_source)
+            .finish()
+    }
+}
+
+impl ParseOptions {
+    /// Apply the object filter to an object read.
+    fn filter(&self, id: ObjectId, object: &mut Object) -> ObjectAction {
+        let filter = match &self.object_filter {
+            Some(filter) => filter,
+            None => return ObjectAction::Keep,
+        };
+        let action = match object {
+            Object::Dictionary(dict) => filter(id, dict),
+            Object::Stream(stream) => filter(id, &stream.dict),
+            _ => return ObjectAction::Keep,
+        };
+        match action {
+            ObjectAction::Keep => {}
+            ObjectAction::SkipContent => {
+                if let Object::Stream(stream) = object {
+                    stream.set_content(Vec::new());
+                    stream.start_position = None;
+                }
+            }
+            ObjectAction::Drop => *object = Object::Null,
+        }
+        action
+    }
+}
 
 impl Document {
     /// Load a PDF document from a specified file path.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
     #[inline]
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Document> {
+        Self::load_with_options(path, &ParseOptions::default())
+    }
+
+    /// Load a PDF document from a specified file path, with options.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn load_with_options<P: AsRef<Path>>(path: P, options: &ParseOptions) -> Result<Document> {
         let file = File::open(path)?;
         let capacity = Some(file.metadata()?.len() as usize);
-        Self::load_internal(file, capacity)
+        Self::load_internal(file, capacity, options)
     }
 
     /// Load a PDF document from an arbitrary source.
     #[inline]
     pub fn load_from<R: Read>(source: R) -> Result<Document> {
-        Self::load_internal(source, None)
+        Self::load_internal(source, None, &ParseOptions::default())
+    }
+
+    /// Load a PDF document from an arbitrary source, with options.
+    pub fn load_from_with_options<R: Read>(source: R, options: &ParseOptions) -> Result<Document> {
+        Self::load_internal(source, None, options)
     }
 
-    fn load_internal<R: Read>(mut source: R, capacity: Option<usize>) -> Result<Document> {
-        let mut buffer = capacity.map(Vec::with_capacity).unwrap_or_else(Vec::new);
-        source.read_to_end(&mut buffer)?;
+    fn load_internal<R: Read>(mut source: R, capacity: Option<usize>, options: &ParseOptions) -> Result<Document> {
+        let mut buffer = capacity.map(Vec::with_capacity).unwrap_or_default();
+        source
+            .read_to_end(&mut buffer)
+            .map_err(|err| Error::from(err).in_phase(Phase::Read))?;
 
+        Self::load_mem_with_options(&buffer, options)
+    }
+
+    /// Load a PDF document from a memory slice.
+    pub fn load_mem(buffer: &[u8]) -> Result<Document> {
+        buffer.try_into()
+    }
+
+    /// Load a PDF document from a memory slice, with options.
+    pub fn load_mem_with_options(buffer: &[u8], options: &ParseOptions) -> Result<Document> {
         Reader {
-            buffer: &buffer,
+            buffer,
             document: Document::new(),
+            options,
+            entry_revisions: BTreeMap::new(),
         }
         .read()
     }
+}
 
-    /// Load a PDF document from a memory slice.
-    pub fn load_mem(buffer: &[u8]) -> Result<Document> {
-        buffer.try_into()
+impl Document {
+    /// The definitions of objects in the file that are not used, with the revision of each, 0 being the original
+    /// file: those that later incremental updates replace, and those that another definition of the same object is
+    /// preferred to, where the entries of several object numbers lead to it.
+    pub fn shadowed_objects(&self) -> Vec<(ObjectId, usize)> {
+        self.shadowed.clone()
+    }
+
+    /// The number of revisions of the file, the original file and its incremental updates. Each section of the
+    /// chain of cross-reference sections counts as one, except the first page section of linearized files, which
+    /// belongs to the revision of the main section that follows it in the file.
+    pub fn revision_count(&self) -> usize {
+        self.revision_ends.len()
+    }
+
+    /// Load the document again as it was in an earlier revision of the file, 0 being the original one. Changes
+    /// made since loading are lost, and the file is cut to that revision, which is then the latest. The file must
+    /// have been kept, see [`ParseOptions::keep_source`]; it is kept again, and the other options are the default
+    /// ones, see [`Document::rollback_to_with_options`] for others.
+    pub fn rollback_to(&mut self, revision: usize) -> Result<()> {
+        let options = ParseOptions {
+            keep_source: true,
+            ..ParseOptions::default()
+        };
+        self.rollback_to_with_options(revision, &options)
+    }
+
+    /// Load the document again as it was in an earlier revision of the file, as [`Document::rollback_to`] does,
+    /// with options, usually those it was loaded with.
+    pub fn rollback_to_with_options(&mut self, revision: usize, options: &ParseOptions) -> Result<()> {
+        let end = *self
+            .revision_ends
+            .get(revision)
+            .ok_or_else(|| Error::Syntax(format!("The file has no revision {}", revision)))?;
+        if self.source.is_empty() {
+            return Err(Error::SourceNotKept);
+        }
+        let mut document = Document::load_mem_with_options(&self.source[..end], options)?;
+        document.deterministic = self.deterministic;
+        document.clock = self.clock;
+        *self = document;
+        Ok(())
     }
 }
 
@@ -54,58 +196,243 @@ impl TryInto<Document> for &[u8] {
     type Error = Error;
 
     fn try_into(self) -> Result<Document> {
-        Reader {
-            buffer: self,
-            document: Document::new(),
-        }
-        .read()
+        Document::load_mem_with_options(self, &ParseOptions::default())
     }
 }
 
 pub struct Reader<'a> {
     buffer: &'a [u8],
     document: Document,
+    options: &'a ParseOptions,
+    /// The revision of the entries of object numbers that the newest revision has no entry for.
+    entry_revisions: BTreeMap<u32, usize>,
 }
 
+/// An object read directly, with the number of its entry and its offset.
+type Definition = (ObjectId, Object, u32, u64);
+
 /// Maximum allowed embedding of literal strings.
 pub const MAX_BRACKET: usize = 100;
 
 impl<'a> Reader<'a> {
     /// Read whole document.
     fn read(mut self) -> Result<Document> {
+        let span = span!(INFO, "load", bytes = self.buffer.len(), objects = tracing::field::Empty);
+        self.read_cross_references()?;
+
+        let zero_length_streams = Mutex::new(vec![]);
+        let object_streams = Mutex::new(vec![]);
+        let warnings = Mutex::new(vec![]);
+        let raw_spans = Mutex::new(vec![]);
+
+        // Object streams of encrypted documents can only be read once decrypted.
+        let encrypted = self.document.trailer.has(b"Encrypt");
+        let entries_filter_map = |(&number, entry): (&u32, &_)| {
+            if let XrefEntry::Normal { offset, generation } = *entry {
+                let (read, normalized) = numbers::collect_normalized(|| self.read_object(offset, None));
+                let (object_id, mut object, end) = read
+                    .map_err(|e| {
+                        let e = e.in_object((number, generation));
+                        repair!(object = ?(number, generation), error = %e, "skipping object that cannot be read");
+                        error!("Object load error: {}", e)
+                    })
+                    .ok()?;
+                let number_warnings = normalized.into_iter().map(|detail| numbers::warning(Some(object_id), detail));
+                warnings.lock().unwrap().extend(number_warnings);
+                let object_stream = object
+                    .as_stream()
+                    .is_ok_and(|stream| stream.dict.known_type() == Some(KnownName::ObjStm));
+                let action = if object_stream {
+                    ObjectAction::Keep
+                } else {
+                    self.options.filter(object_id, &mut object)
+                };
+                if action == ObjectAction::SkipContent {
+                    return Some((object_id, object, number, offset));
+                }
+                if self.options.preserve_raw && action == ObjectAction::Keep {
+                    if let Some(span) = raw_span(self.buffer, offset as usize..end) {
+                        raw_spans.lock().unwrap().push((object_id, span));
+                    }
+                }
+                if let Ok(ref mut stream) = object.as_stream_mut() {
+                    if object_stream && !encrypted {
+                        let span = span!(
+                            TRACE,
+                            "object_stream",
+                            object = ?object_id,
+                            bytes = stream.content.len(),
+                            objects = tracing::field::Empty
+                        );
+                        let obj_stream = ObjectStream::new(object_id, stream)
+                            .map_err(|e| {
+                                let e = e.in_object(object_id);
+                                repair!(object = ?object_id, error = %e, "skipping object stream that cannot be read");
+                                error!("Object stream load error: {}", e)
+                            })
+                            .ok()?;
+                        span.record("objects", obj_stream.objects.len());
+                        for warning in &obj_stream.warnings {
+                            let _detail = &warning.detail;
+                            repair!(object = ?object_id, detail = %_detail, "object stream read despite its header");
+                            warn!("Object stream {:?}: {}", object_id, warning.detail);
+                        }
+                        warnings.lock().unwrap().extend(obj_stream.warnings);
+                        let xref = &self.document.reference_table;
+                        let objects = obj_stream.objects.into_iter().map(|(id, mut object)| {
+                            self.options.filter(id, &mut object);
+                            (id, xref.stream_object_rank(id.0, object_id.0), object)
+                        });
+                        let mut object_streams = object_streams.lock().unwrap();
+                        object_streams.extend(objects);
+                    } else if stream.content.is_empty() {
+                        let mut zero_length_streams = zero_length_streams.lock().unwrap();
+                        zero_length_streams.push(object_id);
+                    }
+                }
+                Some((object_id, object, number, offset))
+            } else {
+                None
+            }
+        };
+        let parse_span = span!(
+            "parse_objects",
+            entries = self.document.reference_table.entries.len(),
+            objects = tracing::field::Empty
+        );
+        #[cfg(feature = "rayon")]
+        let objects: Vec<Definition> = self
+            .document
+            .reference_table
+            .entries
+            .par_iter()
+            .filter_map(entries_filter_map)
+            .collect();
+        #[cfg(not(feature = "rayon"))]
+        let objects: Vec<Definition> = self
+            .document
+            .reference_table
+            .entries
+            .iter()
+            .filter_map(entries_filter_map)
+            .collect();
+        self.document.objects = self.prefer_definitions(objects).into_iter().collect();
+        // Objects in the object stream their entry names come first, then those without an entry, and those that
+        // newer revisions free are left out.
+        let mut compressed = object_streams.into_inner().unwrap();
+        compressed.sort_by_key(|(id, rank, _)| (*id, *rank));
+        // Only add entries, but never replace entries
+        for (id, _, object) in compressed.into_iter().filter(|(_, rank, _)| *rank < 3) {
+            self.document.objects.entry(id).or_insert(object);
+        }
+        let mut warnings = warnings.into_inner().unwrap();
+        warnings.sort_by_key(|warning| warning.object);
+        self.document.parse_warnings.extend(warnings);
+        parse_span.record("objects", self.document.objects.len());
+        parse_span.exit();
+
+        for object_id in zero_length_streams.into_inner().unwrap() {
+            match self.set_stream_content(object_id) {
+                Ok(()) => repair!(object = ?object_id, "stream content read again from its /Length"),
+                Err(_err) => repair!(object = ?object_id, error = %_err, "stream left empty"),
+            }
+        }
+        // The objects are compared when saving to how they were once read, repaired streams included.
+        for (object_id, span) in raw_spans.into_inner().unwrap() {
+            self.document.preserve_object(object_id, span);
+        }
+        // Signatures and their byte ranges are only checked against the file.
+        let signed = self.document.objects.values().any(|object| match object {
+            Object::Dictionary(dict) => dict.has(b"ByteRange"),
+            _ => false,
+        });
+        if self.options.keep_source || self.options.preserve_raw || signed {
+            self.document.source = self.buffer.to_vec();
+        }
+        span.record("objects", self.document.objects.len());
+
+        Ok(self.document)
+    }
+
+    /// A document of the version, trailer and cross-reference table of a buffer, without its objects.
+    #[cfg(feature = "borrowed")]
+    pub(crate) fn cross_references(buffer: &'a [u8]) -> Result<Document> {
+        let mut reader = Reader {
+            buffer,
+            document: Document::new(),
+            options: &ParseOptions::default(),
+            entry_revisions: BTreeMap::new(),
+        };
+        reader.read_cross_references()?;
+        Ok(reader.document)
+    }
+
+    fn read_cross_references(&mut self) -> Result<()> {
         // The document structure can be expressed in PEG as:
         //   document <- header indirect_object* xref trailer xref_start
-        let version = parser::header(&self.buffer).ok_or(Error::Header)?;
+        let span = span!("xref", sections = tracing::field::Empty, entries = tracing::field::Empty);
+        let version = parser::header(self.buffer).ok_or(Error::Header)?;
 
-        let xref_start = Self::get_xref_start(&self.buffer)?;
+        let xref_start = Self::get_xref_start(self.buffer)?;
         if xref_start > self.buffer.len() {
             return Err(Error::Xref(XrefError::Start));
         }
         self.document.reference_table_start = xref_start;
 
-        let (mut xref, mut trailer) = parser::xref_and_trailer(&self.buffer[xref_start..], &self)?;
-
-        // Read previous Xrefs of linearized or incremental updated document.
+        let (mut xref, mut trailer) = parser::xref_and_trailer(&self.buffer[xref_start..], self)?;
+        let mut section_starts = vec![xref_start];
+        let mut revision_starts = vec![xref_start];
+        // Definitions that newer revisions replace, and the revision of the entries of the older ones, with the
+        // index of their revision from the newest.
+        let mut shadowed = Vec::new();
+        let mut entry_revisions = BTreeMap::new();
+
+        // Read the cross-reference streams of hybrid-reference files, whose tables win over them, and the previous
+        // sections of linearized or incrementally updated documents, which the newer ones win over.
+        let mut xref_stream_start = trailer.remove(b"XRefStm");
         let mut prev_xref_start = trailer.remove(b"Prev");
-        while let Some(prev) = prev_xref_start.and_then(|offset| offset.as_i64().ok()) {
-            let prev = prev as usize;
+        loop {
+            if let Some(start) = xref_stream_start.and_then(|offset| offset.as_i64().ok()) {
+                let start = start as usize;
+                if start > self.buffer.len() {
+                    return Err(Error::Xref(XrefError::StreamStart));
+                }
+                let (stream_xref, _) = parser::xref_and_trailer(&self.buffer[start..], self)?;
+                xref.merge(stream_xref);
+                section_starts.push(start);
+            }
+            let prev = match prev_xref_start.and_then(|offset| offset.as_i64().ok()) {
+                Some(prev) => prev as usize,
+                None => break,
+            };
             if prev > self.buffer.len() {
                 return Err(Error::Xref(XrefError::PrevStart));
             }
-            let (prev_xref, mut prev_trailer) = parser::xref_and_trailer(&self.buffer[prev..], &self)?;
-            xref.merge(prev_xref);
-
-            // Read xref stream in hybrid-reference file
-            let prev_xref_stream_start = trailer.remove(b"XRefStm");
-            if let Some(prev) = prev_xref_stream_start.and_then(|offset| offset.as_i64().ok()) {
-                let prev = prev as usize;
-                if prev > self.buffer.len() {
-                    return Err(Error::Xref(XrefError::StreamStart));
+            if section_starts.contains(&prev) {
+                warn!("Cross-reference sections link back to the one at {}", prev);
+                break;
+            }
+            let (prev_xref, mut prev_trailer) = parser::xref_and_trailer(&self.buffer[prev..], self)?;
+            // The previous section of an update is before it in the file; one after it is the main section of a
+            // linearized file, and the first page section before it is part of the same revision, which ends after
+            // the main section.
+            let last = revision_starts.len() - 1;
+            let revision = if prev > revision_starts[last] { last } else { last + 1 };
+            for (&number, entry) in &prev_xref.entries {
+                if !xref.entries.contains_key(&number) {
+                    entry_revisions.insert(number, revision);
+                } else if let Some(id) = entry.defined_id(number) {
+                    shadowed.push((id, revision));
                 }
-                let (prev_xref, _) = parser::xref_and_trailer(&self.buffer[prev..], &self)?;
-                xref.merge(prev_xref);
             }
-
+            xref.merge(prev_xref);
+            if revision == last {
+                revision_starts[last] = prev;
+            } else {
+                revision_starts.push(prev);
+            }
+            section_starts.push(prev);
+            xref_stream_start = prev_trailer.remove(b"XRefStm");
             prev_xref_start = prev_trailer.remove(b"Prev");
         }
 
@@ -115,70 +442,61 @@ impl<'a> Reader<'a> {
                 "Size entry of trailer dictionary is {}, correct value is {}.",
                 xref.size, xref_entry_count
             );
+            repair!(size = xref.size, corrected = xref_entry_count, "trailer /Size corrected");
             xref.size = xref_entry_count;
         }
+        span.record("sections", section_starts.len());
+        span.record("entries", xref.entries.len());
 
         self.document.version = version;
         self.document.max_id = xref.size - 1;
         self.document.trailer = trailer;
         self.document.reference_table = xref;
+        // Revisions are counted from the original file, and end with the end of file marker after their section.
+        let revisions = revision_starts.len();
+        self.document.revision_ends = revision_starts
+            .iter()
+            .rev()
+            .map(|&start| revision_end(self.buffer, start))
+            .collect();
+        self.document.shadowed = shadowed
+            .into_iter()
+            .map(|(id, newest_first)| (id, revisions - 1 - newest_first))
+            .collect();
+        self.document.shadowed.sort();
+        self.entry_revisions = entry_revisions
+            .into_iter()
+            .map(|(number, newest_first)| (number, revisions - 1 - newest_first))
+            .collect();
+        Ok(())
+    }
 
-        let zero_length_streams = Mutex::new(vec![]);
-        let object_streams = Mutex::new(vec![]);
-
-        let entries_filter_map = |(_, entry): (&_, &_)| {
-            if let XrefEntry::Normal { offset, .. } = *entry {
-                let (object_id, mut object) = self
-                    .read_object(offset as usize, None)
-                    .map_err(|e| error!("Object load error: {:?}", e))
-                    .ok()?;
-                if let Ok(ref mut stream) = object.as_stream_mut() {
-                    if stream.dict.type_is(b"ObjStm") {
-                        let obj_stream = ObjectStream::new(stream).ok()?;
-                        let mut object_streams = object_streams.lock().unwrap();
-                        // TODO: Is insert and replace intended behavior?
-                        // See https://github.com/J-F-Liu/lopdf/issues/160 for more info
-                        object_streams.extend(obj_stream.objects);
-                    } else if stream.content.is_empty() {
-                        let mut zero_length_streams = zero_length_streams.lock().unwrap();
-                        zero_length_streams.push(object_id);
-                    }
-                }
-                Some((object_id, object))
-            } else {
-                None
+    /// The objects read directly, one for each identifier. Where entries of several object numbers define the same
+    /// object, as when their offsets are wrong, the one of the entry of the object's own number is kept, or failing
+    /// that the one furthest in the file, which is the latest to be written; the others are shadowed.
+    fn prefer_definitions(&mut self, mut objects: Vec<Definition>) -> Vec<(ObjectId, Object)> {
+        objects.sort_by_key(|&(id, _, number, offset)| (id, number != id.0, cmp::Reverse(offset)));
+        let newest = self.document.revision_ends.len().saturating_sub(1);
+        let mut preferred: Vec<(ObjectId, Object)> = Vec::with_capacity(objects.len());
+        let mut kept_offset = 0;
+        for (id, object, number, offset) in objects {
+            if preferred.last().is_some_and(|(kept, _)| *kept == id) {
+                let revision = self.entry_revisions.get(&number).copied().unwrap_or(newest);
+                self.document.shadowed.push((id, revision));
+                repair!(object = ?id, offset, kept = kept_offset, "object defined more than once");
+                warn!("Object {:?} is defined at {} and {}", id, kept_offset, offset);
+                self.document.parse_warnings.push(ParseWarning {
+                    kind: ParseWarningKind::DuplicateObject,
+                    object: Some(id),
+                    detail: format!("defined at {} and {}, the one at {} is used", kept_offset, offset, kept_offset),
+                });
+                continue;
             }
-        };
-        #[cfg(feature = "rayon")]
-        {
-            self.document.objects = self
-                .document
-                .reference_table
-                .entries
-                .par_iter()
-                .filter_map(entries_filter_map)
-                .collect();
-        }
-        #[cfg(not(feature = "rayon"))]
-        {
-            self.document.objects = self
-                .document
-                .reference_table
-                .entries
-                .iter()
-                .filter_map(entries_filter_map)
-                .collect();
-        }
-        // Only add entries, but never replace entries
-        for (id, entry) in object_streams.into_inner().unwrap() {
-            self.document.objects.entry(id).or_insert(entry);
-        }
-
-        for object_id in zero_length_streams.into_inner().unwrap() {
-            let _ = self.set_stream_content(object_id);
+            kept_offset = offset;
+            preferred.push((id, object));
         }
-
-        Ok(self.document)
+        self.document.shadowed.sort();
+        preferred
     }
 
     fn set_stream_content(&mut self, object_id: ObjectId) -> Result<()> {
@@ -187,7 +505,7 @@ impl<'a> Reader<'a> {
             .document
             .get_object_mut(object_id)
             .and_then(Object::as_stream_mut)?;
-        let start = stream.start_position.ok_or(Error::ObjectNotFound)?;
+        let start = stream.start_position.ok_or_else(|| Error::not_found(object_id))?;
 
         if length < 0 {
             return Err(Error::Syntax("Negative stream length.".to_string()));
@@ -207,42 +525,32 @@ impl<'a> Reader<'a> {
         let object = self.document.get_object(object_id)?;
         let stream = object.as_stream()?;
 
-        stream.dict.get(b"Length").and_then(|value| {
-            if let Ok(id) = value.as_reference() {
-                return self.document.get_object(id).and_then(Object::as_i64);
-            }
-            value.as_i64()
-        })
+        let value = stream.dict.get(b"Length").map_err(|err| err.in_object(object_id))?;
+        match value.as_reference() {
+            Ok(id) => self.document.get_object(id).and_then(Object::as_i64),
+            Err(_) => value.as_i64(),
+        }
+        .map_err(|err| err.at_key(b"Length").in_object(object_id))
     }
 
     /// Get object offset by object id.
-    fn get_offset(&self, id: ObjectId) -> Result<u32> {
-        let entry = self.document.reference_table.get(id.0).ok_or(Error::ObjectNotFound)?;
-        match *entry {
-            XrefEntry::Normal { offset, generation } => {
-                if id.1 == generation {
-                    Ok(offset)
-                } else {
-                    Err(Error::ObjectNotFound)
-                }
-            }
-            _ => Err(Error::ObjectNotFound),
+    fn get_offset(&self, id: ObjectId) -> Result<u64> {
+        match self.document.reference_table.get(id.0) {
+            Some(&XrefEntry::Normal { offset, generation }) if id.1 == generation => Ok(offset),
+            _ => Err(Error::not_found(id)),
         }
     }
 
     pub fn get_object(&self, id: ObjectId) -> Result<Object> {
         let offset = self.get_offset(id)?;
-        let (_, obj) = self.read_object(offset as usize, Some(id))?;
+        let (_, obj, _) = self.read_object(offset, Some(id))?;
 
         Ok(obj)
     }
 
-    fn read_object(&self, offset: usize, expected_id: Option<ObjectId>) -> Result<(ObjectId, Object)> {
-        if offset > self.buffer.len() {
-            return Err(Error::Offset(offset));
-        }
-
-        parser::indirect_object(&self.buffer, offset, expected_id, self)
+    fn read_object(&self, offset: u64, expected_id: Option<ObjectId>) -> Result<(ObjectId, Object, usize)> {
+        let offset = buffer_offset(self.buffer, offset)?;
+        parser::indirect_object(self.buffer, offset, expected_id, self)
     }
 
     fn get_xref_start(buffer: &[u8]) -> Result<usize> {
@@ -286,6 +594,33 @@ impl<'a> Reader<'a> {
     }
 }
 
+/// The end of a revision whose cross-reference section starts at an offset, after the end of file marker that
+/// follows it and its end of line.
+fn revision_end(buffer: &[u8], start: usize) -> usize {
+    let marker = match buffer[start..].windows(5).position(|window| window == b"%%EOF") {
+        Some(position) => start + position + 5,
+        None => return buffer.len(),
+    };
+    let rest = &buffer[marker..];
+    marker + [&b"\r\n"[..], b"\n", b"\r"].iter().find(|eol| rest.starts_with(eol)).map_or(0, |eol| eol.len())
+}
+
+/// The position of an offset of the cross-reference table in the buffer read, which offsets past its end, and those
+/// a `usize` cannot hold, are not.
+pub(crate) fn buffer_offset(buffer: &[u8], offset: u64) -> Result<usize> {
+    match usize::try_from(offset) {
+        Ok(position) if position <= buffer.len() => Ok(position),
+        _ => Err(Error::Offset(usize::try_from(offset).unwrap_or(usize::MAX))),
+    }
+}
+
+/// The bytes of an indirect object, from its number to its `endobj` keyword, which is not optional here.
+fn raw_span(buffer: &[u8], span: Range<usize>) -> Option<Range<usize>> {
+    let raw = buffer.get(span.clone())?;
+    let end = raw.iter().rposition(|c| !b" \t\n\r\x0c\0".contains(c))? + 1;
+    raw[..end].ends_with(b"endobj").then(|| span.start..span.start + end)
+}
+
 #[test]
 fn load_document() {
     let mut doc = Document::load("assets/example.pdf").unwrap();
@@ -293,6 +628,31 @@ fn load_document() {
     doc.save("test_2_load.pdf").unwrap();
 }
 
+#[test]
+fn load_document_with_object_filter() {
+    let options = ParseOptions {
+        object_filter: Some(Box::new(|_id, dict| {
+            if dict.type_is(b"Font") {
+                ObjectAction::Drop
+            } else if dict.has(b"Length") {
+                ObjectAction::SkipContent
+            } else {
+                ObjectAction::Keep
+            }
+        })),
+        ..ParseOptions::default()
+    };
+    let doc = Document::load_with_options("assets/example.pdf", &options).unwrap();
+    assert_eq!(doc.objects.len(), 6);
+    // References to the font dropped resolve to null.
+    let resources = doc.get_dictionary((3, 0)).unwrap();
+    let font = resources.get(b"Font").and_then(Object::as_dict).unwrap().get(b"F1").unwrap();
+    assert!(matches!(doc.dereference(font), Ok((Some((2, 0)), Object::Null))));
+    let content = doc.get_object((4, 0)).and_then(Object::as_stream).unwrap();
+    assert!(content.content.is_empty() && content.dict.has(b"Length"));
+    assert_eq!(doc.get_pages().len(), 1);
+}
+
 #[test]
 #[should_panic(expected = "Xref(Start)")]
 fn load_short_document() {
@@ -301,10 +661,8 @@ fn load_short_document() {
 
 #[test]
 fn load_many_shallow_brackets() {
-    let content: String = std::iter::repeat("()")
-        .take(MAX_BRACKET * 10)
-        .map(|x| x.chars())
-        .flatten()
+    let content: String = std::iter::repeat_n("()", MAX_BRACKET * 10)
+        .flat_map(|x| x.chars())
         .collect();
     const STREAM_CRUFT: usize = 33;
     let doc = format!(
@@ -348,9 +706,8 @@ startxref
 
 #[test]
 fn load_too_deep_brackets() {
-    let content: Vec<u8> = std::iter::repeat(b'(')
-        .take(MAX_BRACKET + 1)
-        .chain(std::iter::repeat(b')').take(MAX_BRACKET + 1))
+    let content: Vec<u8> = std::iter::repeat_n(b'(', MAX_BRACKET + 1)
+        .chain(std::iter::repeat_n(b')', MAX_BRACKET + 1))
         .collect();
     let content = String::from_utf8(content).unwrap();
     const STREAM_CRUFT: usize = 33;
@@ -396,6 +753,92 @@ startxref
     );
 
     let doc = Document::load_mem(doc.as_bytes()).unwrap();
-    let pages = doc.get_pages().keys().map(|r| *r).collect::<Vec<_>>();
+    let pages = doc.get_pages().keys().copied().collect::<Vec<_>>();
     assert_eq!("Hello World!\n", doc.extract_text(&pages).unwrap());
 }
+
+#[test]
+fn load_redefined_objects() {
+    let mut doc = Document::with_version("1.5");
+    let pages_id = doc.add_object(dictionary! { "Type" => "Pages", "Kids" => vec![], "Count" => 0 });
+    let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+    let note_id = doc.add_object(Object::string_literal("first"));
+    doc.trailer.set("Root", catalog_id);
+    let mut original = Vec::new();
+    doc.save_to(&mut original).unwrap();
+
+    let options = ParseOptions {
+        keep_source: true,
+        ..ParseOptions::default()
+    };
+    let mut doc = Document::load_mem_with_options(&original, &options).unwrap();
+    doc.objects.insert(note_id, Object::string_literal("second"));
+    let mut updated = Vec::new();
+    doc.save_incremental_to(&mut updated).unwrap();
+    assert!(matches!(Document::load_mem(&updated).unwrap().rollback_to(0), Err(Error::SourceNotKept)));
+    let mut doc = Document::load_mem_with_options(&updated, &options).unwrap();
+    assert_eq!(doc.get_object(note_id).unwrap().as_str().unwrap(), b"second");
+    assert_eq!(doc.revision_count(), 2);
+    assert_eq!(doc.shadowed_objects(), vec![(note_id, 0)]);
+    doc.rollback_to(0).unwrap();
+    assert_eq!(doc.get_object(note_id).unwrap().as_str().unwrap(), b"first");
+    assert_eq!(doc.revision_count(), 1);
+    assert!(doc.rollback_to(1).is_err());
+
+    // Without an entry of its own, the definition of an object furthest in the file is used.
+    let objects = ["<</Type/Catalog/Pages 2 0 R>>", "<</Type/Pages/Kids[]/Count 0>>", "(old)", "(new)"];
+    let mut pdf = String::from("%PDF-1.5\n");
+    let mut xref = String::from("0 5\n0000000000 65535 f \n");
+    for (number, object) in [1, 2, 5, 5].iter().zip(&objects) {
+        xref.push_str(&format!("{:010} 00000 n \n", pdf.len()));
+        pdf.push_str(&format!("{} 0 obj\n{}\nendobj\n", number, object));
+    }
+    let start = pdf.len();
+    pdf.push_str(&format!("xref\n{}trailer\n<</Root 1 0 R/Size 5>>\nstartxref\n{}\n%%EOF", xref, start));
+    let doc = Document::load_mem(pdf.as_bytes()).unwrap();
+    assert_eq!(doc.get_object((5, 0)).unwrap().as_str().unwrap(), b"new");
+    assert_eq!(doc.shadowed_objects(), vec![((5, 0), 0)]);
+    assert_eq!(doc.parse_warnings[0].kind, ParseWarningKind::DuplicateObject);
+
+    // The first page section of a linearized file, which links to the main section after it, is part of the
+    // original revision.
+    let linearized = |main: usize| {
+        let mut pdf = String::from("%PDF-1.5\n1 0 obj\n<</Linearized 1>>\nendobj\n");
+        let catalog = pdf.len();
+        pdf.push_str("2 0 obj\n<</Type/Catalog/Pages 3 0 R>>\nendobj\n");
+        let first_page = pdf.len();
+        pdf.push_str(&format!(
+            "xref\n1 2\n{:010} 00000 n \n{:010} 00000 n \ntrailer\n<</Root 2 0 R/Size 4/Prev {:010}>>\n\
+             startxref\n0\n%%EOF\n",
+            9, catalog, main
+        ));
+        let pages = pdf.len();
+        pdf.push_str("3 0 obj\n<</Type/Pages/Kids[]/Count 0>>\nendobj\n");
+        let main = pdf.len();
+        pdf.push_str(&format!(
+            "xref\n0 1\n0000000000 65535 f \n3 1\n{:010} 00000 n \ntrailer\n<</Size 4>>\nstartxref\n{}\n%%EOF\n",
+            pages, first_page
+        ));
+        (pdf, main)
+    };
+    let (_, main) = linearized(0);
+    let (pdf, _) = linearized(main);
+    let mut doc = Document::load_mem_with_options(pdf.as_bytes(), &options).unwrap();
+    assert_eq!(doc.revision_count(), 1);
+    assert_eq!(doc.revision_ends, vec![pdf.len()]);
+    assert!(doc.get_object((3, 0)).is_ok());
+    doc.get_object_mut((3, 0)).and_then(Object::as_dict_mut).unwrap().set("Note", 1);
+    let mut updated = Vec::new();
+    doc.save_incremental_to(&mut updated).unwrap();
+    let preserving = ParseOptions {
+        preserve_raw: true,
+        ..ParseOptions::default()
+    };
+    let mut doc = Document::load_mem_with_options(&updated, &preserving).unwrap();
+    assert_eq!(doc.revision_count(), 2);
+    assert_eq!(doc.revision_ends, vec![pdf.len(), updated.len()]);
+    assert_eq!(doc.shadowed_objects(), vec![((3, 0), 0)]);
+    doc.rollback_to_with_options(0, &preserving).unwrap();
+    assert!(!doc.get_dictionary((3, 0)).unwrap().has(b"Note"));
+    assert!(!doc.raw_objects.is_empty());
+}
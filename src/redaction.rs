@@ -0,0 +1,1111 @@
+#![cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+
+use crate::annotations::AnnotationSubtype;
+use crate::content::{Content, Operation};
+use crate::forms::appearance::{FieldFont, TextFont};
+use crate::geometry::{multiply, transform_point, transform_rect, Matrix, Rect, IDENTITY_MATRIX};
+use crate::{Color, Dictionary, Document, Error, Object, ObjectId, Result, Stream};
+use log::warn;
+use std::collections::{BTreeMap, BTreeSet};
+use std::rc::Rc;
+
+/// Maximum nesting of Form XObjects redacted.
+const FORM_DEPTH_LIMIT: usize = 32;
+
+/// XObjects to add to some resources, by name.
+type NewXObjects = Vec<(Vec<u8>, ObjectId)>;
+
+/// What [`Document::redact`] does with images painted over a region.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageRedaction {
+    /// Stop painting every image that intersects a region.
+    Remove,
+    /// Paint a copy of the image in which the pixels under the regions are set to the fill color, or to black
+    /// without one. Stencil masks and images that cannot be decoded are removed instead.
+    #[cfg(feature = "embed_image")]
+    Blank,
+}
+
+/// Settings for [`Document::redact`] and [`Document::apply_redaction_annotations`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RedactionOptions {
+    /// Color painted over each region once its content is removed; `None` leaves the regions empty.
+    pub fill_color: Option<Color>,
+    pub images: ImageRedaction,
+}
+
+impl Default for RedactionOptions {
+    fn default() -> Self {
+        RedactionOptions {
+            fill_color: Some(Color::Rgb(0.0, 0.0, 0.0)),
+            images: ImageRedaction::Remove,
+        }
+    }
+}
+
+/// What was removed by a redaction.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RedactionReport {
+    pub glyphs_removed: usize,
+    pub paths_removed: usize,
+    pub images_removed: usize,
+    pub images_blanked: usize,
+    /// Form XObjects replaced by a redacted copy.
+    pub forms_redacted: usize,
+}
+
+impl RedactionReport {
+    fn add(&mut self, other: &RedactionReport) {
+        self.glyphs_removed += other.glyphs_removed;
+        self.paths_removed += other.paths_removed;
+        self.images_removed += other.images_removed;
+        self.images_blanked += other.images_blanked;
+        self.forms_redacted += other.forms_redacted;
+    }
+
+    fn is_empty(&self) -> bool {
+        *self == RedactionReport::default()
+    }
+}
+
+/// Glyph widths and extent of a font, enough to tell where its glyphs are painted.
+enum GlyphFont {
+    Simple(FieldFont),
+    /// A Type0 font, taken to use two-byte codes that are CIDs, as with the `Identity-H` encoding.
+    Composite {
+        default_width: f64,
+        widths: BTreeMap<u32, f64>,
+        ascent: f64,
+        descent: f64,
+    },
+}
+
+impl GlyphFont {
+    fn new(doc: &Document, font: &Dictionary) -> GlyphFont {
+        if font.get(b"Subtype").and_then(Object::as_name).ok() != Some(b"Type0") {
+            return GlyphFont::Simple(FieldFont::new(doc, font));
+        }
+        let descendant = font
+            .get_deref(b"DescendantFonts", doc)
+            .and_then(Object::as_array)
+            .ok()
+            .and_then(|fonts| fonts.first())
+            .and_then(|descendant| doc.dereference(descendant).ok())
+            .and_then(|(_, descendant)| descendant.as_dict().ok());
+        let mut default_width = 1000.0;
+        let mut widths = BTreeMap::new();
+        let mut ascent = 800.0;
+        let mut descent = -200.0;
+        if let Some(descendant) = descendant {
+            let number = |object: &Object| doc.dereference(object).and_then(|(_, number)| number.as_float()).ok();
+            if let Some(width) = descendant.get(b"DW").ok().and_then(number) {
+                default_width = width;
+            }
+            // Entries are either `first [w1 w2 ...]` or `first last w`.
+            let w = descendant.get_deref(b"W", doc).and_then(Object::as_array);
+            let mut entries = w.map(|w| w.iter()).into_iter().flatten();
+            while let Some(first) = entries.next().and_then(number) {
+                let first = first as u32;
+                match entries
+                    .next()
+                    .map(|entry| doc.dereference(entry).map(|(_, entry)| entry))
+                {
+                    Some(Ok(Object::Array(list))) => {
+                        for (cid, width) in (first..).zip(list) {
+                            widths.insert(cid, number(width).unwrap_or(default_width));
+                        }
+                    }
+                    Some(Ok(last)) => {
+                        let (last, width) = match (last.as_float(), entries.next().and_then(number)) {
+                            (Ok(last), Some(width)) => (last as u32, width),
+                            _ => break,
+                        };
+                        for cid in first..=last.min(first.saturating_add(0xFFFF)) {
+                            widths.insert(cid, width);
+                        }
+                    }
+                    _ => break,
+                }
+            }
+            let descriptor = descendant.get_deref(b"FontDescriptor", doc).and_then(Object::as_dict);
+            if let Ok(descriptor) = descriptor {
+                if let (Ok(top), Ok(bottom)) = (
+                    descriptor.get(b"Ascent").and_then(Object::as_float),
+                    descriptor.get(b"Descent").and_then(Object::as_float),
+                ) {
+                    if top > bottom {
+                        ascent = top;
+                        descent = bottom;
+                    }
+                }
+            }
+        }
+        GlyphFont::Composite {
+            default_width,
+            widths,
+            ascent,
+            descent,
+        }
+    }
+
+    fn code_len(&self) -> usize {
+        match self {
+            GlyphFont::Simple(font) => font.code_len(),
+            GlyphFont::Composite { .. } => 2,
+        }
+    }
+
+    /// Width of a character code at a font size of 1.
+    fn width(&self, code: &[u8]) -> f64 {
+        match self {
+            GlyphFont::Simple(font) => font.width(code),
+            GlyphFont::Composite {
+                default_width, widths, ..
+            } => {
+                let cid = code.iter().fold(0, |cid, &byte| cid << 8 | u32::from(byte));
+                widths.get(&cid).unwrap_or(default_width) / 1000.0
+            }
+        }
+    }
+
+    /// Bottom and top of the glyphs at a font size of 1.
+    fn extent(&self) -> (f64, f64) {
+        match self {
+            GlyphFont::Simple(font) => (font.descent() / 1000.0, font.ascent() / 1000.0),
+            GlyphFont::Composite { ascent, descent, .. } => (descent / 1000.0, ascent / 1000.0),
+        }
+    }
+}
+
+/// The fonts and XObjects a content stream can use.
+struct ContentResources {
+    /// The resource dictionary itself, given to copies of forms that inherit it.
+    dict: Dictionary,
+    fonts: BTreeMap<Vec<u8>, Rc<GlyphFont>>,
+    xobjects: BTreeMap<Vec<u8>, ObjectId>,
+}
+
+/// The parameters of the graphics state that decide where things are painted.
+#[derive(Clone)]
+struct GraphicsState {
+    ctm: Matrix,
+    font: Option<Rc<GlyphFont>>,
+    font_size: f64,
+    char_spacing: f64,
+    word_spacing: f64,
+    /// Horizontal scaling, as a fraction.
+    horizontal_scaling: f64,
+    leading: f64,
+    rise: f64,
+}
+
+impl Default for GraphicsState {
+    fn default() -> Self {
+        GraphicsState {
+            ctm: IDENTITY_MATRIX,
+            font: None,
+            font_size: 0.0,
+            char_spacing: 0.0,
+            word_spacing: 0.0,
+            horizontal_scaling: 1.0,
+            leading: 0.0,
+            rise: 0.0,
+        }
+    }
+}
+
+/// Follows the graphics state and the text position through content operations.
+struct Interpreter {
+    state: GraphicsState,
+    stack: Vec<GraphicsState>,
+    text_matrix: Matrix,
+    line_matrix: Matrix,
+}
+
+/// A character code of a shown string, as laid out by [`Interpreter::show`].
+struct Glyph<'a> {
+    code: &'a [u8],
+    /// Whether the glyph intersects a region.
+    redacted: bool,
+    /// Horizontal displacement in text space.
+    advance: f64,
+}
+
+impl Interpreter {
+    fn new(state: GraphicsState) -> Interpreter {
+        Interpreter {
+            state,
+            stack: Vec::new(),
+            text_matrix: IDENTITY_MATRIX,
+            line_matrix: IDENTITY_MATRIX,
+        }
+    }
+
+    /// Apply an operation that changes the graphics state or the text position. Text-showing operators other
+    /// than `Tj` and `TJ` only get their effect on the position and spacing applied.
+    fn update(&mut self, operation: &Operation, resources: &ContentResources) {
+        let operands = &operation.operands;
+        let number = |index: usize| operands.get(index).and_then(|operand| operand.as_float().ok());
+        match operation.operator.as_str() {
+            "q" => self.stack.push(self.state.clone()),
+            "Q" => {
+                if let Some(state) = self.stack.pop() {
+                    self.state = state;
+                }
+            }
+            "cm" => {
+                if let Some(matrix) = matrix_operands(operands) {
+                    self.state.ctm = multiply(&matrix, &self.state.ctm);
+                }
+            }
+            "BT" => {
+                self.text_matrix = IDENTITY_MATRIX;
+                self.line_matrix = IDENTITY_MATRIX;
+            }
+            "Tf" => {
+                self.state.font = operands
+                    .first()
+                    .and_then(|name| name.as_name().ok())
+                    .and_then(|name| resources.fonts.get(name))
+                    .cloned();
+                self.state.font_size = number(1).unwrap_or(0.0);
+            }
+            "Tc" => self.state.char_spacing = number(0).unwrap_or(0.0),
+            "Tw" => self.state.word_spacing = number(0).unwrap_or(0.0),
+            "Tz" => self.state.horizontal_scaling = number(0).unwrap_or(100.0) / 100.0,
+            "TL" => self.state.leading = number(0).unwrap_or(0.0),
+            "Ts" => self.state.rise = number(0).unwrap_or(0.0),
+            "Td" => self.next_line(number(0).unwrap_or(0.0), number(1).unwrap_or(0.0)),
+            "TD" => {
+                let ty = number(1).unwrap_or(0.0);
+                self.state.leading = -ty;
+                self.next_line(number(0).unwrap_or(0.0), ty);
+            }
+            "Tm" => {
+                if let Some(matrix) = matrix_operands(operands) {
+                    self.text_matrix = matrix;
+                    self.line_matrix = matrix;
+                }
+            }
+            "T*" | "'" => self.next_line(0.0, -self.state.leading),
+            "\"" => {
+                self.state.word_spacing = number(0).unwrap_or(0.0);
+                self.state.char_spacing = number(1).unwrap_or(0.0);
+                self.next_line(0.0, -self.state.leading);
+            }
+            _ => {}
+        }
+    }
+
+    fn next_line(&mut self, tx: f64, ty: f64) {
+        self.line_matrix = multiply(&[1.0, 0.0, 0.0, 1.0, tx, ty], &self.line_matrix);
+        self.text_matrix = self.line_matrix;
+    }
+
+    fn advance(&mut self, tx: f64) {
+        self.text_matrix = multiply(&[1.0, 0.0, 0.0, 1.0, tx, 0.0], &self.text_matrix);
+    }
+
+    /// Lay out the glyphs of a string, moving the text position past them.
+    fn show<'a>(&mut self, bytes: &'a [u8], regions: &[Rect]) -> Vec<Glyph<'a>> {
+        let font = match self.state.font.clone() {
+            Some(font) => font,
+            None => return Vec::new(),
+        };
+        let state = &self.state;
+        let size = state.font_size;
+        let scaling = state.horizontal_scaling;
+        let (bottom, top) = font.extent();
+        let code_len = font.code_len();
+        let mut glyphs = Vec::with_capacity(bytes.len() / code_len);
+        for code in bytes.chunks(code_len) {
+            let width = font.width(code);
+            let rendering = multiply(
+                &[size * scaling, 0.0, 0.0, size, 0.0, state.rise],
+                &multiply(&self.text_matrix, &state.ctm),
+            );
+            let bounds = transform_rect(&rendering, &Rect::new(0.0, bottom, width, top));
+            let word_spacing = if code == b" " { state.word_spacing } else { 0.0 };
+            let advance = (width * size + state.char_spacing + word_spacing) * scaling;
+            glyphs.push(Glyph {
+                code,
+                redacted: regions.iter().any(|region| region.intersects(&bounds)),
+                advance,
+            });
+            self.text_matrix = multiply(&[1.0, 0.0, 0.0, 1.0, advance, 0.0], &self.text_matrix);
+        }
+        glyphs
+    }
+
+    /// The `TJ` adjustment that moves the text position by a displacement, if there is one, rounded to
+    /// millionths of an em.
+    fn adjustment(&self, advance: f64) -> f64 {
+        let scale = self.state.font_size * self.state.horizontal_scaling;
+        if scale == 0.0 {
+            0.0
+        } else {
+            (-advance * 1_000_000.0 / scale).round() / 1000.0
+        }
+    }
+}
+
+/// Elements of a `TJ` array being rebuilt without some glyphs.
+#[derive(Default)]
+struct ShownText {
+    elements: Vec<Object>,
+    removed: usize,
+}
+
+impl ShownText {
+    fn push_glyphs(&mut self, interpreter: &mut Interpreter, string: &Object, regions: &[Rect]) {
+        let (bytes, format) = match string {
+            Object::String(bytes, format) => (bytes, format),
+            _ => return,
+        };
+        let mut kept = Vec::new();
+        for glyph in interpreter.show(bytes, regions) {
+            if glyph.redacted {
+                if !kept.is_empty() {
+                    self.elements
+                        .push(Object::String(std::mem::take(&mut kept), format.clone()));
+                }
+                let adjustment = interpreter.adjustment(glyph.advance);
+                self.push_adjustment(adjustment);
+                self.removed += 1;
+            } else {
+                kept.extend_from_slice(glyph.code);
+            }
+        }
+        if !kept.is_empty() {
+            self.elements.push(Object::String(kept, format.clone()));
+        }
+    }
+
+    fn push_adjustment(&mut self, adjustment: f64) {
+        if let Some(Object::Real(previous)) = self.elements.last_mut() {
+            *previous += adjustment;
+        } else {
+            self.elements.push(Object::Real(adjustment));
+        }
+    }
+}
+
+fn matrix_operands(operands: &[Object]) -> Option<Matrix> {
+    let mut matrix = IDENTITY_MATRIX;
+    if operands.len() != 6 {
+        return None;
+    }
+    for (value, operand) in matrix.iter_mut().zip(operands) {
+        *value = operand.as_float().ok()?;
+    }
+    Some(matrix)
+}
+
+fn is_path_construction(operator: &str) -> bool {
+    matches!(operator, "m" | "l" | "c" | "v" | "y" | "h" | "re")
+}
+
+fn is_path_painting(operator: &str) -> bool {
+    matches!(operator, "S" | "s" | "f" | "F" | "f*" | "B" | "B*" | "b" | "b*" | "n")
+}
+
+/// Points a path construction operation passes through or is bent towards, in user space.
+fn path_points(operation: &Operation, ctm: &Matrix) -> Vec<(f64, f64)> {
+    let numbers: Vec<f64> = operation
+        .operands
+        .iter()
+        .filter_map(|operand| operand.as_float().ok())
+        .collect();
+    let points = match (operation.operator.as_str(), numbers.as_slice()) {
+        ("re", &[x, y, width, height]) => vec![(x, y), (x + width, y), (x, y + height), (x + width, y + height)],
+        (_, numbers) => numbers.chunks_exact(2).map(|point| (point[0], point[1])).collect(),
+    };
+    points.into_iter().map(|(x, y)| transform_point(ctm, x, y)).collect()
+}
+
+impl Document {
+    /// Remove the content of a page that lies within some regions, given in default user space, and paint the
+    /// regions over.
+    ///
+    /// Glyphs intersecting a region are removed from the text-showing operations, which are rewritten so the
+    /// remaining glyphs stay in place, and `/ActualText` and `/Alt` entries of the marked content they belonged
+    /// to are dropped. Paths lying within a region are removed, and images intersecting one are handled as the
+    /// options say. Form XObjects are redacted the same way, through copies. The rewritten text is laid out
+    /// again to check that no glyph is left within a region.
+    ///
+    /// The page is given new content. Its former content streams, and the forms and images replaced, are deleted
+    /// with the objects only they referred to, unless other pages still use them. An incremental update keeps the
+    /// previous revision of the file, content included. Annotations are left alone.
+    pub fn redact(
+        &mut self, page_id: ObjectId, regions: &[Rect], options: &RedactionOptions,
+    ) -> Result<RedactionReport> {
+        let referenced = self.referenced_objects();
+        let report = self.redact_content(page_id, regions, options)?;
+        if let Some(color) = options.fill_color {
+            self.fill_regions(page_id, regions, color)?;
+        }
+        self.delete_unreferenced(&referenced);
+        Ok(report)
+    }
+
+    /// Apply the `/Redact` annotations of every page, with [`Document::redact`], and remove them.
+    ///
+    /// The regions of an annotation are its `/QuadPoints`, or its `/Rect` without them. They are filled with
+    /// the interior color (`/IC`) of the annotation if it has one, or else with the fill color of the
+    /// options. Overlay text is not drawn.
+    pub fn apply_redaction_annotations(&mut self, options: &RedactionOptions) -> Result<RedactionReport> {
+        let referenced = self.referenced_objects();
+        let mut report = RedactionReport::default();
+        let pages: Vec<ObjectId> = self.page_iter().collect();
+        for page_id in pages {
+            let mut marked = Vec::new();
+            for annotation in self.annotations(page_id) {
+                if annotation.subtype != AnnotationSubtype::Redact {
+                    continue;
+                }
+                let regions: Vec<Rect> = if annotation.quad_points.is_empty() {
+                    annotation.rect.into_iter().collect()
+                } else {
+                    annotation.quad_points.iter().map(|quad| quad.bounding_rect()).collect()
+                };
+                marked.push((regions, annotation.interior_color.or(options.fill_color)));
+            }
+            if marked.is_empty() {
+                continue;
+            }
+            let regions: Vec<Rect> = marked.iter().flat_map(|(regions, _)| regions.iter().copied()).collect();
+            report.add(&self.redact_content(page_id, &regions, options)?);
+            for (regions, color) in marked {
+                if let Some(color) = color {
+                    self.fill_regions(page_id, &regions, color)?;
+                }
+            }
+            self.remove_annotations(Some(page_id), |annotation| {
+                annotation.subtype == AnnotationSubtype::Redact
+            });
+        }
+        self.delete_unreferenced(&referenced);
+        Ok(report)
+    }
+
+    /// The objects referred to from the trailer, directly or not, and the fonts [`Document::prune_objects`] keeps.
+    fn referenced_objects(&mut self) -> BTreeSet<ObjectId> {
+        let mut referenced: BTreeSet<ObjectId> = self.traverse_objects(|_| {}).into_iter().collect();
+        referenced.extend(self.used_font_objects());
+        referenced
+    }
+
+    /// Delete the objects that were referred to before redacting and no longer are, so that the content removed
+    /// is not saved.
+    fn delete_unreferenced(&mut self, before: &BTreeSet<ObjectId>) {
+        let after = self.referenced_objects();
+        for id in before.difference(&after) {
+            self.objects.remove(id);
+        }
+    }
+
+    fn redact_content(
+        &mut self, page_id: ObjectId, regions: &[Rect], options: &RedactionOptions,
+    ) -> Result<RedactionReport> {
+        let content = self.get_and_decode_page_content(page_id)?;
+        let resources_dict = self.page_resources_mut(page_id)?.clone();
+        let resources = self.content_resources(&resources_dict);
+        let mut report = RedactionReport::default();
+        let (operations, new_xobjects, replaced) = self.redact_operations(
+            &content.operations,
+            &resources,
+            GraphicsState::default(),
+            regions,
+            options,
+            &mut Vec::new(),
+            &mut report,
+        )?;
+        if report.is_empty() {
+            return Ok(report);
+        }
+        if !new_xobjects.is_empty() || !replaced.is_empty() {
+            let xobjects = self.merged_xobjects(&resources_dict, new_xobjects, &replaced, &operations);
+            self.page_resources_mut(page_id)?.set("XObject", xobjects);
+        }
+        let content_id = self.add_object(Stream::new(Dictionary::new(), Content { operations }.encode()?));
+        self.get_object_mut(page_id)?.as_dict_mut()?.set("Contents", content_id);
+        Ok(report)
+    }
+
+    /// Paint rectangles over the content of a page.
+    fn fill_regions(&mut self, page_id: ObjectId, regions: &[Rect], color: Color) -> Result<()> {
+        let mut operations = vec![color.fill_operation()];
+        for region in regions {
+            operations.push(Operation::new(
+                "re",
+                vec![
+                    region.llx.into(),
+                    region.lly.into(),
+                    region.width().into(),
+                    region.height().into(),
+                ],
+            ));
+        }
+        operations.push(Operation::new("f", vec![]));
+        self.append_isolated_content(page_id, Content { operations }.encode()?)
+    }
+
+    fn content_resources(&self, dict: &Dictionary) -> ContentResources {
+        let mut fonts = BTreeMap::new();
+        if let Ok(font_dict) = dict.get_deref(b"Font", self).and_then(Object::as_dict) {
+            for (name, font) in font_dict {
+                if let Ok((_, Object::Dictionary(font))) = self.dereference(font) {
+                    fonts.insert(name.clone(), Rc::new(GlyphFont::new(self, font)));
+                }
+            }
+        }
+        let mut xobjects = BTreeMap::new();
+        self.collect_xobjects(dict, &mut xobjects);
+        ContentResources {
+            dict: dict.clone(),
+            fonts,
+            xobjects,
+        }
+    }
+
+    /// The `/XObject` dictionary of some resources, with more XObjects added, and without those replaced that the
+    /// operations no longer paint.
+    fn merged_xobjects(
+        &self, resources: &Dictionary, added: NewXObjects, replaced: &[Vec<u8>], operations: &[Operation],
+    ) -> Dictionary {
+        let mut xobjects = resources
+            .get_deref(b"XObject", self)
+            .and_then(Object::as_dict)
+            .cloned()
+            .unwrap_or_default();
+        for name in replaced {
+            let painted = operations.iter().any(|operation| {
+                operation.operator == "Do"
+                    && operation.operands.first().and_then(|name| name.as_name().ok()) == Some(name.as_slice())
+            });
+            if !painted {
+                xobjects.remove(name);
+            }
+        }
+        for (name, id) in added {
+            xobjects.set(name, id);
+        }
+        xobjects
+    }
+
+    /// Rewrite content operations without what lies within the regions. Returns the new operations, the XObjects
+    /// they paint that must be added to the resources, and the names of the XObjects replaced in places.
+    #[allow(clippy::too_many_arguments)]
+    fn redact_operations(
+        &mut self, operations: &[Operation], resources: &ContentResources, state: GraphicsState, regions: &[Rect],
+        options: &RedactionOptions, forms: &mut Vec<ObjectId>, report: &mut RedactionReport,
+    ) -> Result<(Vec<Operation>, NewXObjects, Vec<Vec<u8>>)> {
+        let mut interpreter = Interpreter::new(state.clone());
+        let mut output = Vec::with_capacity(operations.len());
+        let mut new_xobjects: NewXObjects = Vec::new();
+        let mut replaced = Vec::new();
+        // The path being constructed, held back until it is known whether it is painted within a region.
+        let mut path: Vec<Operation> = Vec::new();
+        let mut points = Vec::new();
+        let mut clips = false;
+        // Marked content sequences open, by the index of their operator in the output, and whether glyphs were
+        // removed from them.
+        let mut marked: Vec<(usize, bool)> = Vec::new();
+
+        for operation in operations {
+            let operator = operation.operator.as_str();
+            if is_path_construction(operator) {
+                points.extend(path_points(operation, &interpreter.state.ctm));
+                path.push(operation.clone());
+                continue;
+            }
+            if matches!(operator, "W" | "W*") && !path.is_empty() {
+                clips = true;
+                path.push(operation.clone());
+                continue;
+            }
+            if is_path_painting(operator) && !path.is_empty() {
+                let (x, y) = points.first().copied().unwrap_or_default();
+                let bounds = points.iter().fold(Rect::new(x, y, x, y), |bounds, &(x, y)| {
+                    bounds.union(&Rect::new(x, y, x, y))
+                });
+                let painted = operator != "n";
+                if painted && !clips && regions.iter().any(|region| region.contains(&bounds)) {
+                    report.paths_removed += 1;
+                } else {
+                    output.append(&mut path);
+                    output.push(operation.clone());
+                }
+                path.clear();
+                points.clear();
+                clips = false;
+                continue;
+            }
+            output.append(&mut path);
+            points.clear();
+            clips = false;
+
+            match operator {
+                "Tj" | "TJ" | "'" | "\"" => {
+                    let strings: &[Object] = match (operator, operation.operands.as_slice()) {
+                        ("TJ", [Object::Array(elements)]) => elements,
+                        ("\"", [_, _, string]) => std::slice::from_ref(string),
+                        (_, [string]) => std::slice::from_ref(string),
+                        _ => &[],
+                    };
+                    // Position the text as the operator does before showing it.
+                    interpreter.update(operation, resources);
+                    let mut shown = ShownText::default();
+                    for element in strings {
+                        match element {
+                            Object::String(..) => shown.push_glyphs(&mut interpreter, element, regions),
+                            _ => {
+                                let adjustment = element.as_float().unwrap_or(0.0);
+                                let scale = interpreter.state.font_size * interpreter.state.horizontal_scaling;
+                                interpreter.advance(-adjustment / 1000.0 * scale);
+                                shown.push_adjustment(adjustment);
+                            }
+                        }
+                    }
+                    if shown.removed == 0 {
+                        output.push(operation.clone());
+                        continue;
+                    }
+                    report.glyphs_removed += shown.removed;
+                    for (_, removed) in marked.iter_mut() {
+                        *removed = true;
+                    }
+                    match operator {
+                        "'" => output.push(Operation::new("T*", vec![])),
+                        "\"" => {
+                            output.push(Operation::new("Tw", vec![operation.operands[0].clone()]));
+                            output.push(Operation::new("Tc", vec![operation.operands[1].clone()]));
+                            output.push(Operation::new("T*", vec![]));
+                        }
+                        _ => {}
+                    }
+                    output.push(Operation::new("TJ", vec![Object::Array(shown.elements)]));
+                }
+                "BMC" | "BDC" => {
+                    marked.push((output.len(), false));
+                    output.push(operation.clone());
+                }
+                "EMC" => {
+                    if let Some((index, true)) = marked.pop() {
+                        if let Some(Object::Dictionary(properties)) = output[index].operands.get_mut(1) {
+                            properties.remove(b"ActualText");
+                            properties.remove(b"Alt");
+                        }
+                    }
+                    output.push(operation.clone());
+                }
+                "Do" => {
+                    let name = match operation.operands.first().and_then(|name| name.as_name().ok()) {
+                        Some(name) => name,
+                        None => {
+                            output.push(operation.clone());
+                            continue;
+                        }
+                    };
+                    let xobject_id = resources.xobjects.get(name).copied();
+                    let stream = xobject_id.and_then(|id| self.get_object(id).and_then(Object::as_stream).ok());
+                    let (xobject_id, stream) = match (xobject_id, stream) {
+                        (Some(id), Some(stream)) => (id, stream.clone()),
+                        _ => {
+                            output.push(operation.clone());
+                            continue;
+                        }
+                    };
+                    let ctm = interpreter.state.ctm;
+                    let replacement = match stream.dict.get(b"Subtype").and_then(Object::as_name) {
+                        Ok(b"Image") => {
+                            let bounds = transform_rect(&ctm, &Rect::new(0.0, 0.0, 1.0, 1.0));
+                            if !regions.iter().any(|region| region.intersects(&bounds)) {
+                                output.push(operation.clone());
+                                continue;
+                            }
+                            match options.images {
+                                ImageRedaction::Remove => None,
+                                #[cfg(feature = "embed_image")]
+                                ImageRedaction::Blank => {
+                                    let color = options.fill_color.unwrap_or(Color::BLACK);
+                                    self.blanked_image(xobject_id, &stream, &ctm, regions, color)
+                                        .map(|image| (self.add_object(image), "Im"))
+                                }
+                            }
+                        }
+                        Ok(b"Form") => {
+                            let matrix = stream
+                                .dict
+                                .get(b"Matrix")
+                                .and_then(Object::as_array)
+                                .ok()
+                                .and_then(|matrix| matrix_operands(matrix))
+                                .unwrap_or(IDENTITY_MATRIX);
+                            let form_ctm = multiply(&matrix, &ctm);
+                            let inside = stream
+                                .dict
+                                .get(b"BBox")
+                                .and_then(Rect::from_object)
+                                .map_or(true, |bbox| {
+                                    let bounds = transform_rect(&form_ctm, &bbox);
+                                    regions.iter().any(|region| region.intersects(&bounds))
+                                });
+                            if !inside {
+                                output.push(operation.clone());
+                                continue;
+                            }
+                            if forms.len() >= FORM_DEPTH_LIMIT || forms.contains(&xobject_id) {
+                                warn!("Not redacting form {:?}, nested too deeply", xobject_id);
+                                output.push(operation.clone());
+                                continue;
+                            }
+                            forms.push(xobject_id);
+                            let form = self.redacted_form(
+                                &stream,
+                                resources,
+                                &interpreter.state,
+                                form_ctm,
+                                regions,
+                                options,
+                                forms,
+                                report,
+                            );
+                            forms.pop();
+                            match form? {
+                                Some(form) => Some((self.add_object(form), "Fm")),
+                                None => {
+                                    output.push(operation.clone());
+                                    continue;
+                                }
+                            }
+                        }
+                        _ => {
+                            output.push(operation.clone());
+                            continue;
+                        }
+                    };
+                    if !replaced.iter().any(|replaced: &Vec<u8>| replaced == name) {
+                        replaced.push(name.to_vec());
+                    }
+                    match replacement {
+                        Some((id, prefix)) => {
+                            let mut name = format!("{}{}", prefix, id.0).into_bytes();
+                            while resources.xobjects.contains_key(&name) || new_xobjects.iter().any(|(n, _)| *n == name)
+                            {
+                                name.push(b'_');
+                            }
+                            if prefix == "Im" {
+                                report.images_blanked += 1;
+                            } else {
+                                report.forms_redacted += 1;
+                            }
+                            output.push(Operation::new("Do", vec![Object::Name(name.clone())]));
+                            new_xobjects.push((name, id));
+                        }
+                        None => report.images_removed += 1,
+                    }
+                }
+                _ => {
+                    interpreter.update(operation, resources);
+                    output.push(operation.clone());
+                }
+            }
+        }
+        output.append(&mut path);
+
+        if self.shows_text_within(&output, resources, state, regions) {
+            return Err(Error::Syntax(
+                "redacted text is still shown within a region".to_string(),
+            ));
+        }
+        Ok((output, new_xobjects, replaced))
+    }
+
+    /// A copy of a form XObject without what lies within the regions, or `None` if nothing does.
+    #[allow(clippy::too_many_arguments)]
+    fn redacted_form(
+        &mut self, stream: &Stream, resources: &ContentResources, state: &GraphicsState, ctm: Matrix, regions: &[Rect],
+        options: &RedactionOptions, forms: &mut Vec<ObjectId>, report: &mut RedactionReport,
+    ) -> Result<Option<Stream>> {
+        let data = stream.decompressed_content().unwrap_or_else(|_| stream.content.to_vec());
+        let content = Content::decode(&data)?;
+        let form_resources = stream
+            .dict
+            .get_deref(b"Resources", self)
+            .and_then(Object::as_dict)
+            .ok()
+            .map(|resources| self.content_resources(resources));
+        let inherited = form_resources.is_none();
+        let form_resources = form_resources.as_ref().unwrap_or(resources);
+        let mut form_report = RedactionReport::default();
+        let state = GraphicsState { ctm, ..state.clone() };
+        let (operations, new_xobjects, mut replaced) = self.redact_operations(
+            &content.operations,
+            form_resources,
+            state,
+            regions,
+            options,
+            forms,
+            &mut form_report,
+        )?;
+        if form_report.is_empty() {
+            return Ok(None);
+        }
+        report.add(&form_report);
+        if inherited {
+            // The resources given to the copy as its own only need the XObjects it paints, not the form itself.
+            replaced.extend(form_resources.xobjects.keys().cloned());
+        }
+        let mut form = stream.clone();
+        let mut form_resources_dict = form_resources.dict.clone();
+        if !new_xobjects.is_empty() || !replaced.is_empty() {
+            let xobjects = self.merged_xobjects(&form_resources_dict, new_xobjects, &replaced, &operations);
+            form_resources_dict.set("XObject", xobjects);
+        }
+        form.dict.set("Resources", form_resources_dict);
+        form.set_plain_content(Content { operations }.encode()?);
+        form.compress()?;
+        Ok(Some(form))
+    }
+
+    /// Whether operations show a glyph that intersects a region.
+    fn shows_text_within(
+        &self, operations: &[Operation], resources: &ContentResources, state: GraphicsState, regions: &[Rect],
+    ) -> bool {
+        let mut interpreter = Interpreter::new(state);
+        for operation in operations {
+            interpreter.update(operation, resources);
+            let strings: &[Object] = match (operation.operator.as_str(), operation.operands.as_slice()) {
+                ("TJ", [Object::Array(elements)]) => elements,
+                ("Tj", [string]) | ("'", [string]) | ("\"", [_, _, string]) => std::slice::from_ref(string),
+                _ => continue,
+            };
+            for element in strings {
+                match element {
+                    Object::String(bytes, _) => {
+                        if interpreter.show(bytes, regions).iter().any(|glyph| glyph.redacted) {
+                            return true;
+                        }
+                    }
+                    _ => {
+                        let scale = interpreter.state.font_size * interpreter.state.horizontal_scaling;
+                        interpreter.advance(-element.as_float().unwrap_or(0.0) / 1000.0 * scale);
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    /// A copy of an image in which the pixels under the regions are painted with a color, or `None` for
+    /// stencil masks and images that cannot be decoded.
+    #[cfg(feature = "embed_image")]
+    fn blanked_image(
+        &self, image_id: ObjectId, stream: &Stream, ctm: &Matrix, regions: &[Rect], color: Color,
+    ) -> Option<Stream> {
+        use crate::geometry::invert;
+        use crate::PixelFormat;
+
+        if stream.dict.get(b"ImageMask").and_then(Object::as_bool).unwrap_or(false) {
+            return None;
+        }
+        let mut image = match self.decode_image(image_id) {
+            Ok(image) => image,
+            Err(error) => {
+                warn!("Removing image {:?} which could not be decoded: {}", image_id, error);
+                return None;
+            }
+        };
+        let inverse = invert(ctm)?;
+        let fill: Vec<f32> = match image.format {
+            PixelFormat::Gray => vec![color.to_gray()],
+            PixelFormat::Rgb => color.to_rgb().to_vec(),
+            PixelFormat::Cmyk => color.to_cmyk().to_vec(),
+            PixelFormat::GrayAlpha | PixelFormat::Rgba => return None,
+        };
+        let fill: Vec<u8> = fill
+            .iter()
+            .map(|c| (c * 255.0).round().clamp(0.0, 255.0) as u8)
+            .collect();
+        let (width, height) = (f64::from(image.width), f64::from(image.height));
+        let components = fill.len();
+        for region in regions {
+            // The unit square, onto which the image is mapped with its first row at the top.
+            let area = transform_rect(&inverse, region);
+            let column = |u: f64| (u * width).clamp(0.0, width);
+            let row = |v: f64| ((1.0 - v) * height).clamp(0.0, height);
+            let (first_column, last_column) = (column(area.llx).floor() as usize, column(area.urx).ceil() as usize);
+            let (first_row, last_row) = (row(area.ury).floor() as usize, row(area.lly).ceil() as usize);
+            for y in first_row..last_row {
+                for x in first_column..last_column {
+                    let offset = (y * image.width as usize + x) * components;
+                    image.pixels[offset..offset + components].copy_from_slice(&fill);
+                }
+            }
+        }
+
+        let mut blanked = stream.clone();
+        let converted = match stream.dict.get_deref(b"ColorSpace", self) {
+            Ok(Object::Array(color_space)) => matches!(
+                color_space.first().and_then(|family| family.as_name().ok()),
+                Some(b"Indexed") | Some(b"Separation") | Some(b"DeviceN")
+            ),
+            _ => false,
+        };
+        if converted {
+            let device = match image.format {
+                PixelFormat::Gray => "DeviceGray",
+                PixelFormat::Cmyk => "DeviceCMYK",
+                _ => "DeviceRGB",
+            };
+            blanked.dict.set("ColorSpace", device);
+            blanked.dict.remove(b"Decode");
+        }
+        blanked.dict.set("BitsPerComponent", 8);
+        blanked.set_plain_content(image.pixels);
+        blanked.compress().ok()?;
+        Some(blanked)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::creator::tests::create_document;
+
+    #[test]
+    fn redact_text_and_paths() {
+        let mut doc = create_document();
+        let page_id = doc.page_iter().next().unwrap();
+        let mut content = doc.get_and_decode_page_content(page_id).unwrap();
+        // One rectangle within the region and one across its edge.
+        for x in [300, 400] {
+            content
+                .operations
+                .push(Operation::new("re", vec![x.into(), 700.into(), 50.into(), 20.into()]));
+            content.operations.push(Operation::new("f", vec![]));
+        }
+        doc.change_page_content(page_id, content.encode().unwrap()).unwrap();
+
+        // Courier glyphs are 28.8 points wide at 48 points, so "World" spans 272.8 to 416.8.
+        let region = Rect::new(275.0, 590.0, 410.0, 730.0);
+        let report = doc.redact(page_id, &[region], &RedactionOptions::default()).unwrap();
+        assert_eq!((report.glyphs_removed, report.paths_removed), (5, 1));
+        doc.prune_objects();
+
+        let mut file = Vec::new();
+        doc.save_to(&mut file).unwrap();
+        let doc = Document::load_mem(&file).unwrap();
+        let text = doc.extract_text(&[1]).unwrap();
+        assert_eq!(text, "Hello !\n");
+        let content = doc.get_and_decode_page_content(page_id).unwrap();
+        let shown = content
+            .operations
+            .iter()
+            .find(|operation| operation.operator == "TJ")
+            .unwrap();
+        let elements = shown.operands[0].as_array().unwrap();
+        assert_eq!(elements.len(), 3);
+        assert_eq!(elements[0].as_str().unwrap(), b"Hello ");
+        assert_eq!(elements[1].as_float().unwrap(), -3000.0);
+        assert_eq!(elements[2].as_str().unwrap(), b"!");
+        let rectangles = content.operations.iter().filter(|operation| operation.operator == "re");
+        assert_eq!(rectangles.count(), 2);
+    }
+
+    #[test]
+    fn replaced_content_not_saved() {
+        let mut doc = create_document();
+        let page_id = doc.page_iter().next().unwrap();
+        let form_id = doc.add_object(Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Form",
+                "BBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+            },
+            b"BT /F1 48 Tf 300 650 Td (Secret) Tj ET".to_vec(),
+        ));
+        doc.add_xobject(page_id, "Fm1", form_id).unwrap();
+        let mut content = doc.get_and_decode_page_content(page_id).unwrap();
+        content.operations.push(Operation::new("Do", vec!["Fm1".into()]));
+        doc.change_page_content(page_id, content.encode().unwrap()).unwrap();
+        let saved = |doc: &mut Document| {
+            let mut file = Vec::new();
+            doc.save_to(&mut file).unwrap();
+            let contains = |text: &[u8]| file.windows(text.len()).any(|window| window == text);
+            (contains(b"World"), contains(b"Secret"))
+        };
+        assert_eq!(saved(&mut doc), (true, true));
+
+        let region = Rect::new(275.0, 590.0, 480.0, 730.0);
+        let report = doc.redact(page_id, &[region], &RedactionOptions::default()).unwrap();
+        assert_eq!((report.glyphs_removed, report.forms_redacted), (12, 1));
+        assert!(doc.get_object(form_id).is_err());
+        assert_eq!(saved(&mut doc), (false, false));
+    }
+
+    #[test]
+    fn apply_redact_annotations() {
+        let mut doc = create_document();
+        let page_id = doc.page_iter().next().unwrap();
+        let quad = [100, 650, 230, 650, 100, 590, 230, 590];
+        doc.add_annotation(
+            page_id,
+            dictionary! {
+                "Subtype" => "Redact",
+                "Rect" => vec![100.into(), 590.into(), 230.into(), 650.into()],
+                "QuadPoints" => quad.iter().map(|&n| Object::from(n)).collect::<Vec<_>>(),
+                "IC" => vec![1.into(), 0.into(), 0.into()],
+            },
+        )
+        .unwrap();
+
+        let report = doc.apply_redaction_annotations(&RedactionOptions::default()).unwrap();
+        assert_eq!(report.glyphs_removed, 5);
+        assert!(doc.annotations(page_id).is_empty());
+        let text = doc.extract_text(&[1]).unwrap();
+        assert!(!text.contains("Hello"));
+        assert!(text.contains("World!"));
+        let content = doc.get_and_decode_page_content(page_id).unwrap();
+        let fill = content
+            .operations
+            .iter()
+            .find(|operation| operation.operator == "rg")
+            .unwrap();
+        let color: Vec<f64> = fill.operands.iter().map(|c| c.as_float().unwrap()).collect();
+        assert_eq!(color, vec![1.0, 0.0, 0.0]);
+    }
+
+    #[cfg(feature = "embed_image")]
+    #[test]
+    fn blank_image_pixels() {
+        let mut doc = create_document();
+        let page_id = doc.page_iter().next().unwrap();
+        let image = Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Image",
+                "Width" => 4,
+                "Height" => 4,
+                "ColorSpace" => "DeviceGray",
+                "BitsPerComponent" => 8,
+            },
+            vec![128; 16],
+        );
+        doc.insert_image(page_id, image, (100.0, 100.0), (40.0, 40.0)).unwrap();
+        let options = RedactionOptions {
+            images: ImageRedaction::Blank,
+            ..RedactionOptions::default()
+        };
+        let report = doc
+            .redact(page_id, &[Rect::new(90.0, 90.0, 120.0, 150.0)], &options)
+            .unwrap();
+        assert_eq!(report.images_blanked, 1);
+
+        let placement = &doc.image_placements(page_id).unwrap()[0];
+        let blanked = doc.decode_image(placement.image_id).unwrap();
+        let row = [0, 0, 128, 128];
+        assert_eq!(blanked.pixels, row.repeat(4));
+    }
+}
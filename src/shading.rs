@@ -0,0 +1,121 @@
+//! Axial and radial shadings, the gradients painted with `sh` or filled with as shading patterns.
+
+use crate::color::widened;
+use crate::function::Function;
+use crate::geometry::Matrix;
+use crate::{Color, Document, Error, Object, ObjectId, Result};
+
+impl Document {
+    /// Add an axial shading, `/ShadingType 2`, blending two colors along the line from `(x0, y0)` to
+    /// `(x1, y1)` given as `[x0, y0, x1, y1]`, and painting the colors of its ends beyond them as `extend` says.
+    pub fn add_axial_shading(
+        &mut self, c0: Color, c1: Color, coords: [f64; 4], extend: [bool; 2],
+    ) -> Result<ObjectId> {
+        self.add_shading_dictionary(2, c0, c1, &coords, extend)
+    }
+
+    /// Add a radial shading, `/ShadingType 3`, blending two colors between the circle of center `(x0, y0)` and
+    /// radius `r0` and the one of center `(x1, y1)` and radius `r1`, given as `[x0, y0, r0, x1, y1, r1]`, and
+    /// painting the colors of the circles beyond them as `extend` says.
+    pub fn add_radial_shading(
+        &mut self, c0: Color, c1: Color, coords: [f64; 6], extend: [bool; 2],
+    ) -> Result<ObjectId> {
+        if coords[2] < 0.0 || coords[5] < 0.0 {
+            return Err(Error::Syntax("Negative radius of a radial shading".to_string()));
+        }
+        self.add_shading_dictionary(3, c0, c1, &coords, extend)
+    }
+
+    /// Add a shading pattern, `/PatternType 2`, of a shading mapped to the default coordinates of the page by a
+    /// matrix, for filling shapes with the shading.
+    pub fn add_shading_pattern(&mut self, shading_id: ObjectId, matrix: Matrix) -> ObjectId {
+        self.add_object(dictionary! {
+            "Type" => "Pattern",
+            "PatternType" => 2,
+            "Shading" => shading_id,
+            "Matrix" => matrix.iter().map(|&value| value.into()).collect::<Vec<Object>>(),
+        })
+    }
+
+    /// The shading blends the colors in the color space of the first one, to which the second one is converted.
+    fn add_shading_dictionary(
+        &mut self, shading_type: i64, c0: Color, c1: Color, coords: &[f64], extend: [bool; 2],
+    ) -> Result<ObjectId> {
+        let (color_space, c1) = match c0 {
+            Color::Gray(_) => ("DeviceGray", vec![c1.to_gray()]),
+            Color::Rgb(..) => ("DeviceRGB", c1.to_rgb().to_vec()),
+            Color::Cmyk(..) => ("DeviceCMYK", c1.to_cmyk().to_vec()),
+        };
+        let components = |color: &[f32]| -> Vec<f64> { color.iter().map(|&component| widened(component)).collect() };
+        let function = Function::exponential(components(&c0.clamped().components()), components(&c1), 1.0);
+        let shading = dictionary! {
+            "ShadingType" => shading_type,
+            "ColorSpace" => color_space,
+            "Coords" => coords.iter().map(|&value| value.into()).collect::<Vec<Object>>(),
+            "Function" => function.to_object()?,
+            "Extend" => vec![extend[0].into(), extend[1].into()],
+        };
+        Ok(self.add_object(shading))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::Content;
+    use crate::geometry::IDENTITY_MATRIX;
+    use crate::{DocumentBuilder, PageBuilder};
+
+    #[test]
+    fn gradients_painted_and_kept_by_pruning() {
+        let mut content = Content { operations: vec![] };
+        content.paint_shading("Sh1");
+        content.set_fill_pattern("P1");
+        assert_eq!(content.encode().unwrap(), b"/Sh1 sh\n/Pattern cs\n/P1 scn\n");
+
+        let mut doc = DocumentBuilder::new()
+            .page(PageBuilder::a4().operations(content.operations).build())
+            .build()
+            .unwrap();
+        let page_id = doc.page_iter().next().unwrap();
+        let axial = doc
+            .add_axial_shading(
+                Color::Rgb(1.0, 0.0, 0.0),
+                Color::Cmyk(1.0, 1.0, 0.0, 0.0),
+                [0.0, 0.0, 0.0, 842.0],
+                [true, true],
+            )
+            .unwrap();
+        let radial = doc
+            .add_radial_shading(
+                Color::WHITE,
+                Color::BLACK,
+                [300.0, 400.0, 0.0, 300.0, 400.0, 200.0],
+                [false, true],
+            )
+            .unwrap();
+        assert!(doc
+            .add_radial_shading(Color::WHITE, Color::BLACK, [0.0, 0.0, -1.0, 0.0, 0.0, 1.0], [false, false])
+            .is_err());
+        let pattern = doc.add_shading_pattern(radial, IDENTITY_MATRIX);
+        doc.add_shading(page_id, "Sh1", axial).unwrap();
+        doc.add_pattern(page_id, "P1", pattern).unwrap();
+
+        let shading = doc.get_dictionary(axial).unwrap();
+        assert_eq!(shading.get(b"ShadingType").and_then(Object::as_i64).unwrap(), 2);
+        let function = Function::from_object(&doc, shading.get(b"Function").unwrap()).unwrap();
+        assert_eq!(function.evaluate(&[0.25]).unwrap(), [0.75, 0.0, 0.25]);
+        let shading = doc.get_dictionary(radial).unwrap();
+        assert_eq!(shading.get(b"ColorSpace").and_then(Object::as_name).unwrap(), b"DeviceGray");
+
+        // Shadings and patterns are resources, which pruning follows.
+        assert!(doc.prune_objects().is_empty());
+        let (_, resource_ids) = doc.get_page_resources(page_id);
+        let resources = doc.get_dictionary(resource_ids[0]).unwrap();
+        let shadings = resources.get(b"Shading").and_then(Object::as_dict).unwrap();
+        assert_eq!(shadings.get(b"Sh1").and_then(Object::as_reference).unwrap(), axial);
+        let patterns = resources.get(b"Pattern").and_then(Object::as_dict).unwrap();
+        assert_eq!(patterns.get(b"P1").and_then(Object::as_reference).unwrap(), pattern);
+        assert!(doc.objects.contains_key(&radial));
+    }
+}
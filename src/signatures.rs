@@ -0,0 +1,673 @@
+use crate::digest::{md5, sha1, sha256, sha384, sha512};
+use crate::forms::FieldType;
+use crate::{Dictionary, Document, Error, Object, ObjectId, Result, StringFormat};
+
+/// `/SigFlags` bit set when the document holds at least one signature field.
+pub(crate) const SIGNATURES_EXIST: i64 = 1;
+/// `/SigFlags` bit set when the document must only be changed by incremental updates, to keep signatures valid.
+pub(crate) const APPEND_ONLY: i64 = 2;
+
+/// Value of the last three `/ByteRange` numbers until the document is saved for signing. It is as wide as the
+/// offsets of any file below 10 GB, so the actual offsets can be written over it without moving anything.
+pub(crate) const BYTE_RANGE_PLACEHOLDER: i64 = 9_999_999_999;
+
+/// Size of the signature placeholder when signing a field that has none, enough for a CMS signature with a
+/// few certificates.
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+pub(crate) const DEFAULT_SIGNATURE_SIZE: usize = 8192;
+
+/// Computes signatures for [`Document::sign`], keeping the cryptography, and keys, outside of lopdf.
+pub trait Signer {
+    /// Sign the bytes covered by the byte range, returning a DER encoded detached CMS signature.
+    fn sign(&self, data_to_be_signed: &[u8]) -> Result<Vec<u8>>;
+}
+
+/// Obtains RFC 3161 time-stamp tokens for [`Document::add_document_timestamp`], keeping the requests to the
+/// time-stamping authority outside of lopdf.
+pub trait TimestampAuthority {
+    /// The algorithm of the digest sent to the authority.
+    fn digest_algorithm(&self) -> DigestAlgorithm {
+        DigestAlgorithm::Sha256
+    }
+
+    /// Time-stamp the digest of the bytes covered by the byte range, returning a DER encoded TimeStampToken.
+    fn timestamp(&self, digest: &[u8]) -> Result<Vec<u8>>;
+}
+
+/// A message digest algorithm of signatures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestAlgorithm {
+    Md5,
+    Sha1,
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl DigestAlgorithm {
+    pub fn digest(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            DigestAlgorithm::Md5 => md5(data).to_vec(),
+            DigestAlgorithm::Sha1 => sha1(data).to_vec(),
+            DigestAlgorithm::Sha256 => sha256(data).to_vec(),
+            DigestAlgorithm::Sha384 => sha384(data).to_vec(),
+            DigestAlgorithm::Sha512 => sha512(data).to_vec(),
+        }
+    }
+}
+
+/// A signature of a signature field, checked against the file the document was loaded from.
+#[derive(Debug, Clone)]
+pub struct SignatureInfo {
+    /// The signature field.
+    pub field: ObjectId,
+    /// Fully qualified name of the signature field.
+    pub name: String,
+    /// `/SubFilter` of the signature dictionary, such as `adbe.pkcs7.detached` or `ETSI.CAdES.detached`.
+    pub sub_filter: Option<String>,
+    /// Whether this is a document timestamp (`/Type /DocTimeStamp`), whose `/Contents` is a time-stamp token
+    /// rather than a signature.
+    pub document_timestamp: bool,
+    /// The signed parts of the file, as offsets and lengths.
+    pub byte_range: Vec<(usize, usize)>,
+    /// The signature value, usually a DER encoded CMS signed-data structure padded with zeros.
+    pub contents: Vec<u8>,
+    /// `/M`, the signing time given by the signature dictionary.
+    pub modified: Option<String>,
+    /// Whether the byte ranges cover the file from its start, except for exactly the hex string of `/Contents`.
+    pub byte_range_valid: bool,
+    /// Whether the byte ranges run to the end of the file, so that nothing, such as an incremental update, was
+    /// added after signing.
+    pub covers_whole_file: bool,
+    /// The algorithm of [`digest`](SignatureInfo::digest): that of the CMS signer if known, otherwise the one of
+    /// the sub filter, SHA-256 by default.
+    pub digest_algorithm: DigestAlgorithm,
+    /// The digest of the byte ranges, unless they lie outside the file.
+    pub digest: Option<Vec<u8>>,
+    /// The parsed CMS signature, for CMS sub filters, and the time-stamp token of document timestamps. Their
+    /// [`timestamp`](crate::CmsSignature::timestamp) gives the time the document was time-stamped.
+    #[cfg(feature = "crypto")]
+    pub cms: Option<crate::CmsSignature>,
+}
+
+impl Document {
+    /// The signatures of the signature fields, with the structural checks that need no cryptography. With the
+    /// `crypto` feature, the CMS signatures are parsed as well and their message digests compared with the
+    /// signed byte ranges; the signature values and the certificates are not verified.
+    pub fn signatures(&self) -> Vec<SignatureInfo> {
+        self.form_fields()
+            .into_iter()
+            .filter(|field| field.field_type == FieldType::Signature)
+            .filter_map(|field| {
+                let value = self.get_dictionary(field.id).ok()?.get(b"V").ok()?;
+                let signature = self.dereference(value).ok()?.1.as_dict().ok()?;
+                Some(self.signature_info(field.id, field.name, signature))
+            })
+            .collect()
+    }
+
+    fn signature_info(&self, field: ObjectId, name: String, signature: &Dictionary) -> SignatureInfo {
+        let sub_filter = signature
+            .get(b"SubFilter")
+            .and_then(Object::as_name_str)
+            .ok()
+            .map(str::to_string);
+        let numbers: Vec<usize> = signature
+            .get(b"ByteRange")
+            .and_then(Object::as_array)
+            .map(|numbers| {
+                numbers
+                    .iter()
+                    .filter_map(|number| number.as_i64().ok())
+                    .map(|number| number.max(0) as usize)
+                    .collect()
+            })
+            .unwrap_or_default();
+        let byte_range: Vec<(usize, usize)> = numbers.chunks_exact(2).map(|pair| (pair[0], pair[1])).collect();
+        let contents = signature
+            .get(b"Contents")
+            .and_then(Object::as_str)
+            .map(<[u8]>::to_vec)
+            .unwrap_or_default();
+        let modified = signature
+            .get(b"M")
+            .and_then(Object::as_str)
+            .ok()
+            .map(|date| String::from_utf8_lossy(date).into_owned());
+
+        let source = &self.source;
+        let byte_range_valid = match byte_range[..] {
+            [(0, first), (second, length)] if first < second && second.saturating_add(length) <= source.len() => {
+                if let [b'<', hex @ .., b'>'] = &source[first..second] {
+                    let digits: Vec<u8> = hex
+                        .iter()
+                        .filter(|byte| !byte.is_ascii_whitespace())
+                        .copied()
+                        .collect();
+                    digits.len() == 2 * contents.len()
+                        && digits.chunks(2).zip(&contents).all(|(pair, &byte)| {
+                            std::str::from_utf8(pair)
+                                .ok()
+                                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                                == Some(byte)
+                        })
+                } else {
+                    false
+                }
+            }
+            _ => false,
+        };
+        let end = byte_range
+            .iter()
+            .map(|&(offset, length)| offset.saturating_add(length))
+            .max();
+        let covers_whole_file = end
+            .and_then(|end| source.get(end..))
+            .is_some_and(|rest| rest.iter().all(u8::is_ascii_whitespace));
+        let signed_data: Option<Vec<u8>> = byte_range
+            .iter()
+            .map(|&(offset, length)| source.get(offset..offset.checked_add(length)?))
+            .collect::<Option<Vec<&[u8]>>>()
+            .map(|ranges| ranges.concat());
+
+        #[cfg(feature = "crypto")]
+        let cms = match sub_filter.as_deref() {
+            Some("adbe.pkcs7.detached")
+            | Some("ETSI.CAdES.detached")
+            | Some("adbe.pkcs7.sha1")
+            | Some("ETSI.RFC3161") => crate::CmsSignature::parse(&contents),
+            _ => None,
+        };
+        let digest_algorithm = match sub_filter.as_deref() {
+            Some("adbe.pkcs7.sha1") | Some("adbe.x509.rsa_sha1") => DigestAlgorithm::Sha1,
+            _ => DigestAlgorithm::Sha256,
+        };
+        #[cfg(feature = "crypto")]
+        let digest_algorithm = cms.as_ref().map_or(digest_algorithm, |cms| match &cms.timestamp {
+            Some(timestamp) => timestamp.digest_algorithm,
+            None => cms.digest_algorithm,
+        });
+        let digest = signed_data.as_ref().map(|data| digest_algorithm.digest(data));
+        #[cfg(feature = "crypto")]
+        let cms = cms.map(|mut cms| {
+            // adbe.pkcs7.sha1 signs the SHA-1 digest of the ranges, rather than the ranges themselves.
+            let signed = match (sub_filter.as_deref(), &digest) {
+                (Some("adbe.pkcs7.sha1"), Some(digest)) => Some(cms.digest_algorithm.digest(digest)),
+                _ => digest.clone(),
+            };
+            // The signer of a time-stamp token signs the token info, which holds the digest of the ranges.
+            let expected = match &cms.timestamp {
+                Some(timestamp) => Some(&timestamp.message_imprint),
+                None => cms.message_digest.as_ref(),
+            };
+            cms.digest_matches = match (expected, signed) {
+                (Some(expected), Some(signed)) => Some(*expected == signed),
+                _ => None,
+            };
+            cms
+        });
+
+        SignatureInfo {
+            field,
+            name,
+            sub_filter,
+            document_timestamp: signature.type_is(b"DocTimeStamp"),
+            byte_range,
+            contents,
+            modified,
+            byte_range_valid,
+            covers_whole_file,
+            digest_algorithm,
+            digest,
+            #[cfg(feature = "crypto")]
+            cms,
+        }
+    }
+
+    /// Attach an empty signature dictionary to a signature field, for a detached PKCS #7 signature of up to
+    /// `sig_dict_size` bytes. `/Contents` is filled with zeros, written as twice as many hex digits, and
+    /// `/ByteRange` with placeholders, both to be overwritten when the document is saved for signing.
+    ///
+    /// Returns the id of the signature dictionary.
+    pub fn prepare_signature(&mut self, field: ObjectId, sig_dict_size: usize) -> Result<ObjectId> {
+        let form_field = self
+            .form_fields()
+            .into_iter()
+            .find(|form_field| form_field.id == field)
+            .ok_or_else(|| Error::not_found(field))?;
+        if form_field.field_type != FieldType::Signature {
+            return Err(Error::Signature(format!(
+                "{} is not a signature field",
+                form_field.name
+            )));
+        }
+        if self.get_dictionary(field)?.has(b"V") {
+            return Err(Error::Signature(format!("{} is already signed", form_field.name)));
+        }
+
+        let placeholder = Object::Integer(BYTE_RANGE_PLACEHOLDER);
+        let mut signature = dictionary! {
+            "Type" => "Sig",
+            "Filter" => "Adobe.PPKLite",
+            "SubFilter" => "adbe.pkcs7.detached",
+            "ByteRange" => vec![0.into(), placeholder.clone(), placeholder.clone(), placeholder],
+            "Contents" => Object::String(vec![0; sig_dict_size], StringFormat::Hexadecimal),
+        };
+        if let Some(now) = self.clock.now() {
+            signature.set("M", now);
+        }
+        let signature_id = self.add_object(signature);
+        self.get_object_mut(field)?.as_dict_mut()?.set("V", signature_id);
+        self.set_sig_flags(SIGNATURES_EXIST | APPEND_ONLY)?;
+        Ok(signature_id)
+    }
+
+    /// Sign a signature field, saving the document to `target` as an incremental update with the signature.
+    ///
+    /// The signature dictionary prepared by [`prepare_signature`](Document::prepare_signature) is used, or one
+    /// for a signature of up to 8192 bytes is added. Its `/ByteRange` is filled in once the file is laid out, and
+    /// the bytes it covers passed to `signer`, whose signature is written into `/Contents`.
+    /// Afterwards, the document corresponds to the signed file, so that further incremental updates keep the
+    /// signature valid. A document loaded from a file must have kept it, see
+    /// [`ParseOptions::keep_source`](crate::ParseOptions::keep_source).
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    pub fn sign<W: std::io::Write>(&mut self, target: &mut W, field: ObjectId, signer: &dyn Signer) -> Result<()> {
+        let signature_id = match self.get_dictionary(field)?.get(b"V") {
+            Ok(Object::Reference(id)) => *id,
+            Ok(_) => {
+                return Err(Error::Signature(
+                    "the signature dictionary is not an indirect object".to_string(),
+                ))
+            }
+            Err(_) => self.prepare_signature(field, DEFAULT_SIGNATURE_SIZE)?,
+        };
+        self.write_signature(target, signature_id, &|data| signer.sign(data))
+    }
+
+    /// Add a document timestamp, saving the document to `target` as an incremental update with it. Returns the
+    /// id of the new signature field.
+    ///
+    /// The timestamp is the value of an invisible signature field on the first page, a signature dictionary of
+    /// type `/DocTimeStamp` with the `ETSI.RFC3161` sub filter. Once the file is laid out, the digest of the
+    /// bytes covered by its `/ByteRange` is passed to `tsa`, and the time-stamp token returned written into
+    /// `/Contents`, as with [`sign`](Document::sign).
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    pub fn add_document_timestamp<W: std::io::Write>(
+        &mut self, target: &mut W, tsa: &dyn TimestampAuthority,
+    ) -> Result<ObjectId> {
+        let page_id = self.page_iter().next().ok_or(Error::PageNumberNotFound(1))?;
+        let names: Vec<String> = self.form_fields().into_iter().map(|field| field.name).collect();
+        let name = (1..)
+            .map(|number| format!("Timestamp{}", number))
+            .find(|name| !names.contains(name))
+            .unwrap_or_default();
+        let options = crate::SignatureFieldOptions {
+            invisible: true,
+            ..Default::default()
+        };
+        let field =
+            self.add_signature_field(page_id, &name, crate::geometry::Rect::new(0.0, 0.0, 0.0, 0.0), &options)?;
+        let signature_id = self.prepare_signature(field, DEFAULT_SIGNATURE_SIZE)?;
+        let signature = self.get_object_mut(signature_id)?.as_dict_mut()?;
+        signature.set("Type", "DocTimeStamp");
+        signature.set("SubFilter", "ETSI.RFC3161");
+        signature.remove(b"M");
+        let algorithm = tsa.digest_algorithm();
+        self.write_signature(target, signature_id, &|data| tsa.timestamp(&algorithm.digest(data)))?;
+        Ok(field)
+    }
+
+    /// Save the document to `target` as an incremental update in which a prepared signature dictionary holds
+    /// the value `sign` computes from the bytes covered by its byte range.
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    fn write_signature<W: std::io::Write>(
+        &mut self, target: &mut W, signature_id: ObjectId, sign: &dyn Fn(&[u8]) -> Result<Vec<u8>>,
+    ) -> Result<()> {
+        let byte_range = self
+            .get_dictionary(signature_id)?
+            .get(b"ByteRange")
+            .and_then(Object::as_array)?;
+        if !byte_range
+            .iter()
+            .any(|number| number.as_i64().ok() == Some(BYTE_RANGE_PLACEHOLDER))
+        {
+            return Err(Error::Signature("the field is already signed".to_string()));
+        }
+
+        // Signatures and document timestamps are added whatever the modification policy, which they are checked
+        // against when validated.
+        let mut update = self.incremental_update(false)?;
+        let (start, end) = update
+            .contents
+            .get(&signature_id)
+            .map(|&(start, end)| (start as usize, end as usize))
+            .ok_or_else(|| Error::Signature("the signature dictionary has no /Contents".to_string()))?;
+        let object_start = match update.xref.get(signature_id.0) {
+            Some(crate::xref::XrefEntry::Normal { offset, .. }) => *offset as usize,
+            _ => return Err(Error::not_found(signature_id)),
+        };
+        let data = &mut update.data;
+
+        let placeholder = format!("[0 {0} {0} {0}]", BYTE_RANGE_PLACEHOLDER);
+        let position = data[object_start..]
+            .windows(placeholder.len())
+            .position(|window| window == placeholder.as_bytes())
+            .map(|position| object_start + position)
+            .ok_or_else(|| Error::Signature("the /ByteRange placeholder was not written".to_string()))?;
+        let ranges = [0, start, end, data.len() - end];
+        let byte_range = format!("[0 {} {} {}]", ranges[1], ranges[2], ranges[3]);
+        data[position..position + placeholder.len()]
+            .copy_from_slice(format!("{:width$}", byte_range, width = placeholder.len()).as_bytes());
+
+        let to_be_signed = [&data[..start], &data[end..]].concat();
+        let signature = sign(&to_be_signed)?;
+        let capacity = (end - start - 2) / 2;
+        if signature.len() > capacity {
+            return Err(Error::Signature(format!(
+                "the signature of {} bytes does not fit into /Contents of {} bytes",
+                signature.len(),
+                capacity
+            )));
+        }
+        let hex: String = signature.iter().map(|byte| format!("{:02X}", byte)).collect();
+        data[start + 1..start + 1 + hex.len()].copy_from_slice(hex.as_bytes());
+        target.write_all(data)?;
+
+        let mut contents = signature;
+        contents.resize(capacity, 0);
+        let dict = self.get_object_mut(signature_id)?.as_dict_mut()?;
+        dict.set(
+            "ByteRange",
+            ranges
+                .iter()
+                .map(|&number| Object::Integer(number as i64))
+                .collect::<Vec<_>>(),
+        );
+        dict.set("Contents", Object::String(contents, StringFormat::Hexadecimal));
+        self.reference_table_start = update.xref_start as usize;
+        self.source = update.data;
+        Ok(())
+    }
+
+    /// Add flags to the `/SigFlags` of the interactive form.
+    pub(crate) fn set_sig_flags(&mut self, flags: i64) -> Result<()> {
+        let acro_form = self.acro_form_mut()?;
+        let existing = acro_form.get(b"SigFlags").and_then(Object::as_i64).unwrap_or(0);
+        acro_form.set("SigFlags", existing | flags);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::creator::tests::create_document;
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    use crate::creator::tests::load_keeping_source;
+    use crate::geometry::Rect;
+    use crate::SignatureFieldOptions;
+
+    #[test]
+    fn add_and_prepare_signature_fields() {
+        let mut doc = create_document();
+        let page_id = doc.page_iter().next().unwrap();
+        let options = SignatureFieldOptions {
+            unsigned_appearance: true,
+            ..Default::default()
+        };
+        let rect = Rect::new(50.0, 50.0, 250.0, 100.0);
+        let visible_id = doc.add_signature_field(page_id, "approval", rect, &options).unwrap();
+        let invisible = SignatureFieldOptions {
+            invisible: true,
+            ..Default::default()
+        };
+        let invisible_id = doc.add_signature_field(page_id, "author", rect, &invisible).unwrap();
+        let acro_form = doc.acro_form().unwrap().1;
+        assert_eq!(
+            acro_form.get(b"SigFlags").and_then(Object::as_i64).unwrap(),
+            SIGNATURES_EXIST
+        );
+
+        let fields = doc.form_fields();
+        assert_eq!(fields.len(), 2);
+        assert!(fields.iter().all(|field| field.field_type == FieldType::Signature));
+        let visible = doc.get_dictionary(visible_id).unwrap();
+        let appearance = visible.get(b"AP").and_then(Object::as_dict).unwrap();
+        let appearance = doc
+            .get_object(appearance.get(b"N").unwrap().as_reference().unwrap())
+            .and_then(Object::as_stream)
+            .unwrap();
+        assert!(String::from_utf8_lossy(&appearance.content).contains("(Unsigned) Tj"));
+        let invisible = doc.get_dictionary(invisible_id).unwrap();
+        assert_eq!(
+            Rect::from_object(invisible.get(b"Rect").unwrap()).unwrap(),
+            Rect::new(0.0, 0.0, 0.0, 0.0)
+        );
+        assert!(!invisible.has(b"AP"));
+
+        let signature_id = doc.prepare_signature(visible_id, 8192).unwrap();
+        let signature = doc.get_dictionary(signature_id).unwrap();
+        assert_eq!(signature.get(b"Contents").and_then(Object::as_str).unwrap().len(), 8192);
+        assert_eq!(signature.get(b"ByteRange").and_then(Object::as_array).unwrap().len(), 4);
+        let acro_form = doc.acro_form().unwrap().1;
+        assert_eq!(acro_form.get(b"SigFlags").and_then(Object::as_i64).unwrap(), 3);
+        assert!(matches!(
+            doc.prepare_signature(visible_id, 8192),
+            Err(Error::Signature(_))
+        ));
+
+        let text_id = doc.add_text_field(page_id, "name", rect, &Default::default()).unwrap();
+        assert!(matches!(doc.prepare_signature(text_id, 8192), Err(Error::Signature(_))));
+    }
+
+    #[test]
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    fn check_signatures_of_signed_file() {
+        let doc = Document::load("assets/signed.pdf").unwrap();
+        let signatures = doc.signatures();
+        assert_eq!(signatures.len(), 1);
+        let signature = &signatures[0];
+        assert_eq!(signature.name, "Signature1");
+        assert_eq!(signature.sub_filter.as_deref(), Some("adbe.pkcs7.detached"));
+        assert_eq!(signature.contents.len(), 8192);
+        assert!(signature.byte_range_valid);
+        assert!(signature.covers_whole_file);
+        assert_eq!(signature.digest_algorithm, DigestAlgorithm::Sha256);
+        #[cfg(feature = "crypto")]
+        {
+            let cms = signature.cms.as_ref().unwrap();
+            assert_eq!(cms.digest_matches, Some(true));
+            assert_eq!(cms.signer.as_deref(), Some("C=GB, O=lopdf, CN=Test Signer"));
+            assert!(cms.signing_time.is_some());
+        }
+
+        // An incremental update after signing leaves the signed ranges intact, but no longer covering the file.
+        let mut source = doc.source.clone();
+        source.extend_from_slice(b"1 0 obj\n<< >>\nendobj\n");
+        let updated = Document { source, ..doc.clone() };
+        let signature = &updated.signatures()[0];
+        assert!(signature.byte_range_valid);
+        assert!(!signature.covers_whole_file);
+
+        // A gap of a single byte between the ranges has no room for the hex string of /Contents.
+        let mut gap = doc.clone();
+        let length = gap.source.len() as i64;
+        for object in gap.objects.values_mut() {
+            if let Ok(signature) = object.as_dict_mut() {
+                if signature.has(b"ByteRange") {
+                    let byte_range = vec![0.into(), 100.into(), 101.into(), (length - 101).into()];
+                    signature.set("ByteRange", byte_range);
+                }
+            }
+        }
+        assert!(!gap.signatures()[0].byte_range_valid);
+
+        // Changing a signed byte keeps the structure valid, but not the message digest.
+        let mut tampered = doc.source.clone();
+        let offset = tampered.windows(8).position(|window| window == b"(Signed)").unwrap();
+        tampered[offset + 1] = b's';
+        let tampered = Document {
+            source: tampered,
+            ..doc
+        };
+        let signature = &tampered.signatures()[0];
+        assert!(signature.byte_range_valid);
+        #[cfg(feature = "crypto")]
+        assert_eq!(signature.cms.as_ref().unwrap().digest_matches, Some(false));
+    }
+
+    struct FixedSigner(Vec<u8>);
+
+    impl Signer for FixedSigner {
+        fn sign(&self, data_to_be_signed: &[u8]) -> Result<Vec<u8>> {
+            assert!(data_to_be_signed.starts_with(b"%PDF-"));
+            Ok([&self.0[..], &sha256(data_to_be_signed)[..]].concat())
+        }
+    }
+
+    #[test]
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    fn sign_as_incremental_update() {
+        let mut doc = create_document();
+        let page_id = doc.page_iter().next().unwrap();
+        let rect = Rect::new(50.0, 50.0, 250.0, 100.0);
+        let field = doc
+            .add_signature_field(page_id, "approval", rect, &Default::default())
+            .unwrap();
+        let mut original = Vec::new();
+        doc.save_to(&mut original).unwrap();
+        let mut doc = load_keeping_source(&original);
+
+        let mut signed = Vec::new();
+        doc.sign(&mut signed, field, &FixedSigner(b"signature".to_vec()))
+            .unwrap();
+        assert!(signed.starts_with(&original));
+        assert_eq!(doc.source(), signed);
+        assert!(matches!(
+            doc.sign(&mut Vec::new(), field, &FixedSigner(Vec::new())),
+            Err(Error::Signature(_))
+        ));
+
+        let loaded = Document::load_mem(&signed).unwrap();
+        let signature = &loaded.signatures()[0];
+        assert!(signature.byte_range_valid);
+        assert!(signature.covers_whole_file);
+        let covered = [
+            &signed[..signature.byte_range[0].1],
+            &signed[signature.byte_range[1].0..],
+        ]
+        .concat();
+        assert!(signature.contents.starts_with(b"signature"));
+        assert_eq!(signature.contents[9..41], sha256(&covered));
+        assert_eq!(signature.contents.len(), DEFAULT_SIGNATURE_SIZE);
+
+        // Later updates keep the signed revision intact.
+        let catalog_id = doc.trailer.get(b"Root").and_then(Object::as_reference).unwrap();
+        let catalog = doc.get_object_mut(catalog_id).and_then(Object::as_dict_mut).unwrap();
+        catalog.set("Lang", Object::string_literal("en"));
+        let mut updated = Vec::new();
+        doc.save_incremental_to(&mut updated).unwrap();
+        assert!(updated.starts_with(&signed));
+        let updated = Document::load_mem(&updated).unwrap();
+        let signature = &updated.signatures()[0];
+        assert!(signature.byte_range_valid);
+        assert!(!signature.covers_whole_file);
+
+        let mut doc = load_keeping_source(&original);
+        assert!(matches!(
+            doc.sign(&mut Vec::new(), field, &FixedSigner(vec![0; DEFAULT_SIGNATURE_SIZE])),
+            Err(Error::Signature(_))
+        ));
+    }
+
+    /// A DER element with the given tag.
+    fn der(tag: u8, content: &[u8]) -> Vec<u8> {
+        let length = content.len();
+        let mut element = vec![tag];
+        if length < 0x80 {
+            element.push(length as u8);
+        } else {
+            element.extend_from_slice(&[0x82, (length >> 8) as u8, length as u8]);
+        }
+        element.extend_from_slice(content);
+        element
+    }
+
+    /// Issues time-stamp tokens with a fixed time and a meaningless signature.
+    struct FixedTimestampAuthority;
+
+    impl TimestampAuthority for FixedTimestampAuthority {
+        fn timestamp(&self, digest: &[u8]) -> Result<Vec<u8>> {
+            let sha256 = der(
+                0x30,
+                &der(0x06, &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01]),
+            );
+            let tst_info = der(
+                0x30,
+                &[
+                    der(0x02, &[1]),
+                    der(0x06, &[0x2A, 0x03]),
+                    der(0x30, &[sha256.clone(), der(0x04, digest)].concat()),
+                    der(0x02, &[7]),
+                    der(0x18, b"20260102030405Z"),
+                ]
+                .concat(),
+            );
+            let tst_info_oid = [0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x09, 0x10, 0x01, 0x04];
+            let content = der(
+                0x30,
+                &[der(0x06, &tst_info_oid), der(0xA0, &der(0x04, &tst_info))].concat(),
+            );
+            let signer_info = der(
+                0x30,
+                &[
+                    der(0x02, &[1]),
+                    der(0x30, &[der(0x30, &[]), der(0x02, &[1])].concat()),
+                    sha256.clone(),
+                    sha256.clone(),
+                    der(0x04, b"signature"),
+                ]
+                .concat(),
+            );
+            let signed_data = der(
+                0x30,
+                &[der(0x02, &[3]), der(0x31, &sha256), content, der(0x31, &signer_info)].concat(),
+            );
+            let signed_data_oid = [0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x07, 0x02];
+            Ok(der(
+                0x30,
+                &[der(0x06, &signed_data_oid), der(0xA0, &signed_data)].concat(),
+            ))
+        }
+    }
+
+    #[test]
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    fn add_document_timestamp_as_incremental_update() {
+        let mut original = Vec::new();
+        create_document().save_to(&mut original).unwrap();
+        let mut doc = load_keeping_source(&original);
+        let mut stamped = Vec::new();
+        let field = doc
+            .add_document_timestamp(&mut stamped, &FixedTimestampAuthority)
+            .unwrap();
+        assert!(stamped.starts_with(&original));
+
+        let loaded = Document::load_mem(&stamped).unwrap();
+        let signatures = loaded.signatures();
+        assert_eq!(signatures.len(), 1);
+        let timestamp = &signatures[0];
+        assert_eq!((timestamp.field, timestamp.name.as_str()), (field, "Timestamp1"));
+        assert_eq!(timestamp.sub_filter.as_deref(), Some("ETSI.RFC3161"));
+        assert!(timestamp.document_timestamp);
+        assert!(timestamp.byte_range_valid && timestamp.covers_whole_file);
+        let widget = loaded.get_dictionary(field).unwrap();
+        assert_eq!(Rect::from_object(widget.get(b"Rect").unwrap()).unwrap().width(), 0.0);
+        #[cfg(feature = "crypto")]
+        {
+            let cms = timestamp.cms.as_ref().unwrap();
+            assert_eq!(cms.digest_matches, Some(true));
+            assert_eq!(cms.timestamp.as_ref().unwrap().time, "D:20260102030405Z");
+        }
+    }
+}
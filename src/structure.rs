@@ -0,0 +1,760 @@
+use crate::encodings::decode_text_string;
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+use crate::parser_aux::TextSpan;
+use crate::{Dictionary, Document, Object, ObjectId};
+use log::warn;
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+
+/// Maximum nesting of structure elements read by [`Document::structure_tree`].
+const MAX_DEPTH: usize = 256;
+
+/// Standard structure types after which [`StructTree::text_in_reading_order`] starts a new line.
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+const BLOCK_TYPES: [&str; 19] = [
+    "P",
+    "H",
+    "H1",
+    "H2",
+    "H3",
+    "H4",
+    "H5",
+    "H6",
+    "Title",
+    "LI",
+    "TH",
+    "TD",
+    "Caption",
+    "BlockQuote",
+    "Note",
+    "Code",
+    "Formula",
+    "Figure",
+    "TOCI",
+];
+
+/// The text of a marked-content sequence, in pieces of the same language.
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+type MarkedText = Vec<(Option<String>, String)>;
+
+/// The logical structure of a tagged document, from [`Document::structure_tree`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StructTree {
+    /// `/RoleMap`, from custom structure types to the types they stand for.
+    pub role_map: BTreeMap<String, String>,
+    /// `/ParentTree`, by the `/StructParents` key of a page or the `/StructParent` key of an object.
+    pub parent_tree: BTreeMap<i64, ParentTreeEntry>,
+    /// The top-level structure elements, in reading order.
+    pub children: Vec<StructElement>,
+}
+
+/// An entry of the parent tree of a [`StructTree`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParentTreeEntry {
+    /// The structure element holding each marked-content sequence of a content stream, by MCID.
+    MarkedContent(Vec<Option<ObjectId>>),
+    /// The structure element holding an object, such as an annotation.
+    Object(ObjectId),
+}
+
+/// A structure element of a [`StructTree`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructElement {
+    /// The structure element dictionary, unless it is stored directly in its parent.
+    pub id: Option<ObjectId>,
+    /// `/S`, as given; see [`StructTree::standard_type`].
+    pub struct_type: String,
+    /// `/T`.
+    pub title: Option<String>,
+    /// `/Alt`, an alternate description, such as that of a figure.
+    pub alt: Option<String>,
+    /// `/ActualText`, the text the content of the element stands for.
+    pub actual_text: Option<String>,
+    /// `/Lang`.
+    pub lang: Option<String>,
+    /// `/Pg`, the page holding the content of the element.
+    pub page: Option<ObjectId>,
+    pub kids: Vec<StructKid>,
+}
+
+/// A kid of a [`StructElement`], in reading order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StructKid {
+    Element(StructElement),
+    /// A marked-content sequence, by MCID, of a page or of the content stream of a form XObject.
+    MarkedContent {
+        mcid: i64,
+        page: Option<ObjectId>,
+        stream: Option<ObjectId>,
+    },
+    /// An object reference (`/OBJR`), such as to an annotation.
+    Object {
+        object: ObjectId,
+        page: Option<ObjectId>,
+    },
+}
+
+impl StructTree {
+    /// The standard structure type a structure type stands for, following the role map.
+    pub fn standard_type<'a>(&'a self, struct_type: &'a str) -> &'a str {
+        let mut current = struct_type;
+        // Bounded, as role maps may have cycles.
+        for _ in 0..self.role_map.len() {
+            match self.role_map.get(current) {
+                Some(mapped) if mapped != current => current = mapped,
+                _ => break,
+            }
+        }
+        current
+    }
+
+    /// The text of the structure elements in reading order, from the marked-content sequences of the pages with
+    /// their MCIDs, or from `/ActualText` where it is given, and from `/Alt` for elements without text, such as
+    /// figures. A new line is started after block-level elements.
+    ///
+    /// Unlike [`Document::extract_text`], this follows the logical order of the document, such as that of its
+    /// columns, rather than the order in which the content is drawn, and leaves out artifacts such as running
+    /// headers. Marked content of form XObjects is not read, and MCIDs the pages lack are skipped with a warning.
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    pub fn text_in_reading_order(&self, doc: &Document) -> String {
+        self.text_of_pages(doc, None).0
+    }
+
+    /// The text in reading order of the content on some pages, or on all of them, and its spans.
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    pub(crate) fn text_of_pages(&self, doc: &Document, pages: Option<&BTreeSet<ObjectId>>) -> (String, Vec<TextSpan>) {
+        let mut text = String::new();
+        let mut spans = Vec::new();
+        let mut sections = BTreeMap::new();
+        let lang = doc.language();
+        for element in &self.children {
+            self.collect_text(
+                doc,
+                element,
+                lang.as_deref(),
+                pages,
+                &mut sections,
+                &mut text,
+                &mut spans,
+            );
+        }
+        (text, spans)
+    }
+
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    #[allow(clippy::too_many_arguments)]
+    fn collect_text(
+        &self, doc: &Document, element: &StructElement, lang: Option<&str>, pages: Option<&BTreeSet<ObjectId>>,
+        sections: &mut BTreeMap<ObjectId, BTreeMap<i64, MarkedText>>, text: &mut String, spans: &mut Vec<TextSpan>,
+    ) {
+        let lang = element.lang.as_deref().or(lang);
+        fn push(text: &mut String, spans: &mut Vec<TextSpan>, span: &str, lang: Option<&str>, actual_text: bool) {
+            text.push_str(span);
+            spans.push(TextSpan {
+                text: span.to_string(),
+                lang: lang.map(str::to_string),
+                actual_text,
+            });
+        }
+        let start = text.len();
+        if let Some(actual_text) = &element.actual_text {
+            push(text, spans, actual_text, lang, true);
+        } else {
+            for kid in &element.kids {
+                match kid {
+                    StructKid::Element(kid) => self.collect_text(doc, kid, lang, pages, sections, text, spans),
+                    StructKid::MarkedContent {
+                        mcid,
+                        page: Some(page),
+                        stream: None,
+                    } => {
+                        if pages.is_some_and(|pages| !pages.contains(page)) {
+                            continue;
+                        }
+                        let page_sections = sections.entry(*page).or_insert_with(|| {
+                            doc.marked_content_text(*page).unwrap_or_else(|err| {
+                                warn!("Cannot read the content of page {:?}: {}", page, err);
+                                BTreeMap::new()
+                            })
+                        });
+                        match page_sections.get(mcid) {
+                            Some(section) => {
+                                for (content_lang, span) in section {
+                                    push(text, spans, span, content_lang.as_deref().or(lang), false);
+                                }
+                            }
+                            None => warn!("Structure element refers to MCID {} missing from page {:?}", mcid, page),
+                        }
+                    }
+                    StructKid::MarkedContent { mcid, page: None, .. } => {
+                        warn!("Skipping MCID {} of a structure element without a page", mcid)
+                    }
+                    _ => {}
+                }
+            }
+            let on_pages = pages.is_none_or(|pages| element.page.is_none_or(|page| pages.contains(&page)));
+            if let Some(alt) = element.alt.as_ref().filter(|_| text.len() == start && on_pages) {
+                push(text, spans, alt, lang, true);
+            }
+        }
+        if BLOCK_TYPES.contains(&self.standard_type(&element.struct_type)) && !text.is_empty() && !text.ends_with('\n')
+        {
+            text.push('\n');
+        }
+    }
+
+    /// Warn about marked-content sequences the parent tree does not map to their structure element.
+    fn check_parent_tree(&self, doc: &Document, elements: &[StructElement]) {
+        for element in elements {
+            for kid in &element.kids {
+                match kid {
+                    StructKid::Element(kid) => self.check_parent_tree(doc, std::slice::from_ref(kid)),
+                    StructKid::MarkedContent {
+                        mcid,
+                        page: Some(page),
+                        stream: None,
+                    } => {
+                        let parent = doc
+                            .get_dictionary(*page)
+                            .and_then(|page| page.get(b"StructParents"))
+                            .and_then(Object::as_i64)
+                            .ok()
+                            .and_then(|key| match self.parent_tree.get(&key) {
+                                Some(ParentTreeEntry::MarkedContent(parents)) => usize::try_from(*mcid)
+                                    .ok()
+                                    .and_then(|mcid| parents.get(mcid).copied().flatten()),
+                                _ => None,
+                            });
+                        if element.id.is_some() && parent != element.id {
+                            warn!(
+                                "Parent tree does not map MCID {} of page {:?} to its structure element",
+                                mcid, page
+                            );
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+impl Document {
+    /// The logical structure of a tagged document, from the `/StructTreeRoot` of the catalog.
+    ///
+    /// Malformed elements and parent tree entries are skipped, elements reached twice are only read once, and
+    /// a parent tree that does not match the elements is reported with warnings.
+    pub fn structure_tree(&self) -> Option<StructTree> {
+        let root = self
+            .catalog()
+            .ok()?
+            .get_deref(b"StructTreeRoot", self)
+            .and_then(Object::as_dict)
+            .ok()?;
+        let name = |name: &[u8]| String::from_utf8_lossy(name).into_owned();
+
+        let role_map = match root.get_deref(b"RoleMap", self) {
+            Ok(Object::Dictionary(role_map)) => role_map
+                .iter()
+                .filter_map(|(key, value)| {
+                    let value = self.dereference(value).ok()?.1.as_name().ok()?;
+                    Some((name(key), name(value)))
+                })
+                .collect(),
+            _ => BTreeMap::new(),
+        };
+
+        let parent_tree = match root.get_deref(b"ParentTree", self) {
+            Ok(Object::Dictionary(parent_tree)) => self
+                .number_tree_entries(parent_tree)
+                .into_iter()
+                .filter_map(|(key, value)| match self.dereference(&value) {
+                    Ok((_, Object::Array(parents))) => {
+                        let parents = parents
+                            .iter()
+                            .map(|parent| match parent {
+                                Object::Reference(id) => Some(*id),
+                                Object::Null => None,
+                                _ => {
+                                    warn!("Skipping malformed parent of parent tree entry {}", key);
+                                    None
+                                }
+                            })
+                            .collect();
+                        Some((key, ParentTreeEntry::MarkedContent(parents)))
+                    }
+                    Ok((Some(id), Object::Dictionary(_))) => Some((key, ParentTreeEntry::Object(id))),
+                    _ => {
+                        warn!("Skipping malformed parent tree entry {}", key);
+                        None
+                    }
+                })
+                .collect(),
+            Ok(_) => {
+                warn!("Ignoring a structure parent tree that is not a dictionary");
+                BTreeMap::new()
+            }
+            Err(_) => BTreeMap::new(),
+        };
+
+        let children = self
+            .struct_kids(root.get(b"K").ok(), None, &mut BTreeSet::new(), 0)
+            .into_iter()
+            .filter_map(|kid| match kid {
+                StructKid::Element(element) => Some(element),
+                _ => {
+                    warn!("Skipping content item of the structure tree root");
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        let tree = StructTree {
+            role_map,
+            parent_tree,
+            children,
+        };
+        tree.check_parent_tree(self, &tree.children);
+        Some(tree)
+    }
+
+    /// The kids of a structure element, from its `/K`, whose content items are on a page unless they give theirs.
+    fn struct_kids(
+        &self, kids: Option<&Object>, page: Option<ObjectId>, visited: &mut BTreeSet<ObjectId>, depth: usize,
+    ) -> Vec<StructKid> {
+        let kids = match kids {
+            Some(kids) => match self.dereference(kids) {
+                Ok((_, Object::Array(array))) => array.iter().collect(),
+                Ok(_) => vec![kids],
+                Err(err) => {
+                    warn!("Skipping structure kids: {}", err);
+                    return Vec::new();
+                }
+            },
+            None => return Vec::new(),
+        };
+
+        let mut items = Vec::new();
+        for kid in kids {
+            if let Object::Integer(mcid) = *kid {
+                items.push(StructKid::MarkedContent {
+                    mcid,
+                    page,
+                    stream: None,
+                });
+                continue;
+            }
+            let id = kid.as_reference().ok();
+            if let Some(id) = id {
+                if !visited.insert(id) {
+                    warn!("Skipping structure element {:?} reached twice", id);
+                    continue;
+                }
+            }
+            let dict = match self.dereference(kid).and_then(|(_, kid)| kid.as_dict()) {
+                Ok(dict) => dict,
+                Err(_) => {
+                    warn!("Skipping malformed structure kid {:?}", kid);
+                    continue;
+                }
+            };
+            let kid_page = dict.get(b"Pg").and_then(Object::as_reference).ok().or(page);
+            if dict.has(b"S") {
+                if depth >= MAX_DEPTH {
+                    warn!("Skipping structure elements nested too deeply");
+                    continue;
+                }
+                items.push(StructKid::Element(self.struct_element(
+                    id,
+                    dict,
+                    kid_page,
+                    visited,
+                    depth + 1,
+                )));
+            } else if let Ok(mcid) = dict.get_deref(b"MCID", self).and_then(Object::as_i64) {
+                items.push(StructKid::MarkedContent {
+                    mcid,
+                    page: kid_page,
+                    stream: dict.get(b"Stm").and_then(Object::as_reference).ok(),
+                });
+            } else if let Ok(object) = dict.get(b"Obj").and_then(Object::as_reference) {
+                items.push(StructKid::Object { object, page: kid_page });
+            } else {
+                warn!("Skipping malformed structure kid {:?}", kid);
+            }
+        }
+        items
+    }
+
+    fn struct_element(
+        &self, id: Option<ObjectId>, dict: &Dictionary, page: Option<ObjectId>, visited: &mut BTreeSet<ObjectId>,
+        depth: usize,
+    ) -> StructElement {
+        let text = |key: &[u8]| {
+            dict.get_deref(key, self)
+                .and_then(Object::as_str)
+                .ok()
+                .map(decode_text_string)
+        };
+        StructElement {
+            id,
+            struct_type: dict
+                .get_deref(b"S", self)
+                .and_then(Object::as_name)
+                .map(|name| String::from_utf8_lossy(name).into_owned())
+                .unwrap_or_default(),
+            title: text(b"T"),
+            alt: text(b"Alt"),
+            actual_text: text(b"ActualText"),
+            lang: text(b"Lang"),
+            page: dict.get(b"Pg").and_then(Object::as_reference).ok(),
+            kids: self.struct_kids(dict.get(b"K").ok(), page, visited, depth),
+        }
+    }
+
+    /// The text shown in each marked-content sequence with an MCID on a page, by MCID, including that of the
+    /// sequences nested inside it but not that of artifacts, with the language its property lists give it.
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    fn marked_content_text(&self, page_id: ObjectId) -> crate::Result<BTreeMap<i64, MarkedText>> {
+        let encodings = self
+            .get_page_fonts(page_id)
+            .into_iter()
+            .map(|(name, font)| (name, font.get_font_encoding()))
+            .collect::<BTreeMap<Vec<u8>, &str>>();
+        let (direct, inherited) = self.get_page_resources(page_id);
+        let properties = direct
+            .into_iter()
+            .chain(inherited.iter().filter_map(|id| self.get_dictionary(*id).ok()))
+            .filter_map(|resources| resources.get_deref(b"Properties", self).and_then(Object::as_dict).ok())
+            .collect::<Vec<_>>();
+        let content = self.get_and_decode_page_content(page_id)?;
+
+        let mut sections = BTreeMap::new();
+        // The MCID of each open marked-content sequence, whether it marks an artifact, and its language.
+        let mut open: Vec<(Option<i64>, bool, Option<String>)> = Vec::new();
+        let mut encoding = None;
+        for operation in &content.operations {
+            match operation.operator.as_str() {
+                "BMC" | "BDC" => {
+                    let artifact = operation.operands.first().and_then(|tag| tag.as_name().ok()) == Some(b"Artifact");
+                    let list = match operation.operands.get(1) {
+                        Some(Object::Name(name)) => properties
+                            .iter()
+                            .find_map(|properties| properties.get_deref(name, self).and_then(Object::as_dict).ok()),
+                        Some(Object::Dictionary(list)) => Some(list),
+                        _ => None,
+                    };
+                    let mcid = list.and_then(|list| list.get(b"MCID").and_then(Object::as_i64).ok());
+                    let lang = list
+                        .and_then(|list| list.get_deref(b"Lang", self).and_then(Object::as_str).ok())
+                        .map(decode_text_string);
+                    if let Some(mcid) = mcid {
+                        sections.entry(mcid).or_insert_with(Vec::new);
+                    }
+                    open.push((mcid, artifact, lang));
+                }
+                "EMC" => {
+                    open.pop();
+                }
+                "Tf" => {
+                    encoding = operation
+                        .operands
+                        .first()
+                        .and_then(|font| font.as_name().ok())
+                        .and_then(|font| encodings.get(font))
+                        .copied();
+                }
+                "Tj" | "TJ" | "'" | "\"" => {
+                    if open.iter().any(|(_, artifact, _)| *artifact) {
+                        continue;
+                    }
+                    let mcid = match open.iter().rev().find_map(|(mcid, _, _)| *mcid) {
+                        Some(mcid) => mcid,
+                        None => continue,
+                    };
+                    let lang = open.iter().rev().find_map(|(_, _, lang)| lang.clone());
+                    let section: &mut MarkedText = sections.entry(mcid).or_default();
+                    if section.last().is_none_or(|(last, _)| *last != lang) {
+                        section.push((lang, String::new()));
+                    }
+                    if let Some((_, text)) = section.last_mut() {
+                        show_text(text, encoding, &operation.operands);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(sections)
+    }
+}
+
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+fn show_text(text: &mut String, encoding: Option<&str>, operands: &[Object]) {
+    for operand in operands {
+        match operand {
+            Object::String(bytes, _) => text.push_str(&Document::decode_text(encoding, bytes)),
+            Object::Array(array) => show_text(text, encoding, array),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::{Content, Operation};
+    use crate::creator::tests::create_document;
+    use crate::Stream;
+
+    /// A page of two columns drawn right first, tagged left first, with a heading of a custom type.
+    fn tagged_document() -> (Document, ObjectId) {
+        let mut doc = create_document();
+        let page_id = doc.page_iter().next().unwrap();
+        let section = |mcid: i64, text: &str| {
+            vec![
+                Operation::new("BDC", vec!["P".into(), dictionary! { "MCID" => mcid }.into()]),
+                Operation::new("BT", vec![]),
+                Operation::new("Tf", vec!["F1".into(), 12.into()]),
+                Operation::new("Tj", vec![Object::string_literal(text)]),
+                Operation::new("ET", vec![]),
+                Operation::new("EMC", vec![]),
+            ]
+        };
+        let mut operations = vec![
+            Operation::new(
+                "BDC",
+                vec!["Artifact".into(), dictionary! { "Type" => "Pagination" }.into()],
+            ),
+            Operation::new("BT", vec![]),
+            Operation::new("Tf", vec!["F1".into(), 10.into()]),
+            Operation::new("Tj", vec![Object::string_literal("Page 1")]),
+            Operation::new("ET", vec![]),
+            Operation::new("EMC", vec![]),
+        ];
+        operations.extend(section(2, "right column"));
+        operations.extend(section(1, "left column"));
+        operations.extend(section(0, "Heading"));
+        let content = Content { operations }.encode().unwrap();
+        let content_id = doc.add_object(Stream::new(Dictionary::new(), content));
+        let page = doc.get_object_mut(page_id).unwrap().as_dict_mut().unwrap();
+        page.set("Contents", content_id);
+        page.set("StructParents", 0);
+
+        let root_id = doc.new_object_id();
+        let document_id = doc.new_object_id();
+        let heading = doc.add_object(dictionary! {
+            "S" => "Heading",
+            "P" => document_id,
+            "Pg" => page_id,
+            "K" => 0,
+            "T" => Object::string_literal("Introduction"),
+        });
+        let left = doc.add_object(dictionary! { "S" => "P", "P" => document_id, "Pg" => page_id, "K" => 1 });
+        let right = doc.add_object(dictionary! {
+            "S" => "P",
+            "P" => document_id,
+            "K" => vec![dictionary! { "Type" => "MCR", "Pg" => page_id, "MCID" => 2 }.into()],
+        });
+        let missing = doc.add_object(dictionary! { "S" => "P", "P" => document_id, "Pg" => page_id, "K" => 7 });
+        let figure = doc.add_object(dictionary! {
+            "S" => "Figure",
+            "P" => document_id,
+            "Alt" => Object::string_literal("A chart"),
+            "ActualText" => Object::string_literal("1 2 3"),
+        });
+        let photo = doc.add_object(dictionary! {
+            "S" => "Figure",
+            "P" => document_id,
+            "Alt" => Object::string_literal("A photo"),
+        });
+        let kids: Vec<Object> = vec![
+            heading.into(),
+            left.into(),
+            right.into(),
+            missing.into(),
+            figure.into(),
+            photo.into(),
+        ];
+        doc.objects.insert(
+            document_id,
+            Object::Dictionary(dictionary! { "S" => "Document", "P" => root_id, "K" => kids }),
+        );
+        doc.objects.insert(
+            root_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "StructTreeRoot",
+                "K" => document_id,
+                "RoleMap" => dictionary! { "Heading" => "H1" },
+                "ParentTree" => dictionary! {
+                    "Nums" => vec![
+                        0.into(),
+                        vec![heading.into(), left.into(), right.into()].into(),
+                        1.into(),
+                        Object::string_literal("junk"),
+                    ],
+                },
+            }),
+        );
+        let catalog_id = doc.trailer.get(b"Root").unwrap().as_reference().unwrap();
+        let catalog = doc.get_object_mut(catalog_id).unwrap().as_dict_mut().unwrap();
+        catalog.set("StructTreeRoot", root_id);
+        catalog.set("MarkInfo", dictionary! { "Marked" => true });
+        (doc, page_id)
+    }
+
+    #[test]
+    fn read_structure_tree() {
+        let (doc, page_id) = tagged_document();
+        assert!(create_document().structure_tree().is_none());
+        let tree = doc.structure_tree().unwrap();
+        assert_eq!(tree.parent_tree.len(), 1);
+        assert_eq!(tree.children.len(), 1);
+        let document = &tree.children[0];
+        assert_eq!(document.struct_type, "Document");
+        assert_eq!(document.kids.len(), 6);
+
+        let heading = match &document.kids[0] {
+            StructKid::Element(heading) => heading,
+            kid => panic!("unexpected kid {:?}", kid),
+        };
+        assert_eq!(heading.title.as_deref(), Some("Introduction"));
+        assert_eq!(tree.standard_type(&heading.struct_type), "H1");
+        assert_eq!(
+            heading.kids,
+            vec![StructKid::MarkedContent {
+                mcid: 0,
+                page: Some(page_id),
+                stream: None
+            }]
+        );
+        match &document.kids[4] {
+            StructKid::Element(figure) => {
+                assert_eq!(figure.alt.as_deref(), Some("A chart"));
+                assert_eq!(figure.actual_text.as_deref(), Some("1 2 3"));
+            }
+            kid => panic!("unexpected kid {:?}", kid),
+        }
+    }
+
+    #[test]
+    fn role_map_cycles() {
+        let mut tree = StructTree::default();
+        tree.role_map.insert("A".to_string(), "B".to_string());
+        tree.role_map.insert("B".to_string(), "A".to_string());
+        tree.role_map.insert("Chapter".to_string(), "Sect".to_string());
+        assert_eq!(tree.standard_type("Chapter"), "Sect");
+        assert_eq!(tree.standard_type("P"), "P");
+        let _ = tree.standard_type("A");
+    }
+
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    #[test]
+    fn text_in_reading_order() {
+        let (doc, _) = tagged_document();
+        let tree = doc.structure_tree().unwrap();
+        assert_eq!(
+            tree.text_in_reading_order(&doc),
+            "Heading\nleft column\nright column\n1 2 3\nA photo\n"
+        );
+        assert_eq!(
+            doc.extract_text(&[1]).unwrap(),
+            "Page 1\nright column\nleft column\nHeading\n"
+        );
+    }
+
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    #[test]
+    fn extract_text_in_structure_order() {
+        use crate::{TextExtractionOptions, TextOrder};
+        let options = TextExtractionOptions {
+            order: TextOrder::Structure,
+        };
+        let (doc, _) = tagged_document();
+        let extracted = doc.extract_text_with(&[1], &options).unwrap();
+        assert_eq!(extracted.order, TextOrder::Structure);
+        assert_eq!(extracted.note, None);
+        assert_eq!(
+            extracted.text,
+            doc.structure_tree().unwrap().text_in_reading_order(&doc)
+        );
+
+        let extracted = create_document().extract_text_with(&[1], &options).unwrap();
+        assert_eq!(extracted.order, TextOrder::Geometric);
+        assert!(extracted.note.is_some());
+        assert_eq!(extracted.text, "Hello World!\n");
+        assert!(doc.extract_text_with(&[2], &options).is_err());
+    }
+
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    #[test]
+    fn languages_of_spans() {
+        use crate::TextSpan;
+        let mut doc = create_document();
+        let page_id = doc.page_iter().next().unwrap();
+        let show = |text: &str| Operation::new("Tj", vec![Object::string_literal(text)]);
+        let operations = vec![
+            Operation::new("BT", vec![]),
+            Operation::new("Tf", vec!["F1".into(), 12.into()]),
+            Operation::new("BDC", vec!["P".into(), dictionary! { "MCID" => 0 }.into()]),
+            show("The sign read "),
+            Operation::new(
+                "BDC",
+                vec![
+                    "Span".into(),
+                    dictionary! { "Lang" => Object::string_literal("fr-FR") }.into(),
+                ],
+            ),
+            show("Entree interdite"),
+            Operation::new("EMC", vec![]),
+            show("."),
+            Operation::new("EMC", vec![]),
+            Operation::new("BDC", vec!["Span".into(), dictionary! { "MCID" => 1 }.into()]),
+            show("Guten Tag"),
+            Operation::new("EMC", vec![]),
+            Operation::new("ET", vec![]),
+        ];
+        let content = Content { operations }.encode().unwrap();
+        let content_id = doc.add_object(Stream::new(Dictionary::new(), content));
+        doc.get_object_mut(page_id)
+            .unwrap()
+            .as_dict_mut()
+            .unwrap()
+            .set("Contents", content_id);
+
+        let dropcap = dictionary! { "S" => "Span", "ActualText" => Object::string_literal("T") };
+        let quote = doc.add_object(dictionary! { "S" => "P", "Pg" => page_id, "K" => vec![dropcap.into(), 0.into()] });
+        let german =
+            dictionary! { "S" => "Span", "Lang" => Object::string_literal("de-DE"), "Pg" => page_id, "K" => 1 };
+        let greeting = doc.add_object(dictionary! {
+            "S" => "P",
+            "Lang" => Object::string_literal("en-GB"),
+            "K" => vec![german.into()],
+        });
+        let root = doc.add_object(dictionary! {
+            "Type" => "StructTreeRoot",
+            "K" => vec![quote.into(), greeting.into()],
+        });
+        doc.catalog_mut().unwrap().set("StructTreeRoot", root);
+        doc.set_language("en-US").unwrap();
+
+        let options = crate::TextExtractionOptions {
+            order: crate::TextOrder::Structure,
+        };
+        let extracted = doc.extract_text_with(&[1], &options).unwrap();
+        assert_eq!(extracted.text, "TThe sign read Entree interdite.\nGuten Tag\n");
+        let span = |text: &str, lang: &str, actual_text: bool| TextSpan {
+            text: text.to_string(),
+            lang: Some(lang.to_string()),
+            actual_text,
+        };
+        assert_eq!(
+            extracted.spans,
+            vec![
+                span("T", "en-US", true),
+                span("The sign read ", "en-US", false),
+                span("Entree interdite", "fr-FR", false),
+                span(".", "en-US", false),
+                span("Guten Tag", "de-DE", false),
+            ]
+        );
+    }
+}
@@ -0,0 +1,185 @@
+#![cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+
+//! Page thumbnails, the images of the `/Thumb` entry of pages that viewers show without rendering the pages.
+
+use crate::{Document, Error, ExtractedImage, ImageExtractionOptions, Object, ObjectId, Result, Stream};
+
+/// The filters a thumbnail may be compressed with; the others are not meant for such small images.
+const THUMBNAIL_FILTERS: [&[u8]; 3] = [b"FlateDecode", b"LZWDecode", b"DCTDecode"];
+
+impl Document {
+    /// Set the thumbnail of a page to an image, such as one made with `xobject::image_from`, added as a new object
+    /// whose id is returned. The image is in the DeviceGray or DeviceRGB color space or in an Indexed color space
+    /// based on one of them, the color spaces the specification allows for thumbnails, and compressed with
+    /// FlateDecode, LZWDecode or DCTDecode if at all.
+    pub fn set_page_thumbnail(&mut self, page_id: ObjectId, image: Stream) -> Result<ObjectId> {
+        self.get_dictionary(page_id)?;
+        self.check_thumbnail(&image)?;
+        let thumbnail_id = self.add_object(image);
+        self.get_object_mut(page_id)?.as_dict_mut()?.set("Thumb", thumbnail_id);
+        Ok(thumbnail_id)
+    }
+
+    /// The thumbnail of a page decoded to pixels, or `None` if the page has none. Indexed thumbnails are converted
+    /// to their base color space; JPEG thumbnails need the `embed_image` feature.
+    pub fn get_page_thumbnail(&self, page_id: ObjectId) -> Result<Option<ExtractedImage>> {
+        let thumbnail = match self.get_dictionary(page_id)?.get(b"Thumb") {
+            Ok(thumbnail) => thumbnail,
+            Err(_) => return Ok(None),
+        };
+        let (thumbnail_id, thumbnail) = self.dereference(thumbnail).map_err(|err| err.at_key(b"Thumb"))?;
+        let stream = thumbnail.as_stream().map_err(|err| err.at_key(b"Thumb"))?;
+        let options = ImageExtractionOptions {
+            decode: true,
+            apply_mask: false,
+        };
+        let image = self.extract_image_stream(thumbnail_id.unwrap_or(page_id), stream, &options, [0, 0, 0])?;
+        Ok(Some(image))
+    }
+
+    /// Remove the thumbnails of all pages, with the objects of the thumbnails and of their color palettes that
+    /// nothing else refers to. Returns the number of pages that had a thumbnail.
+    pub fn remove_thumbnails(&mut self) -> usize {
+        let mut removed = Vec::new();
+        let page_ids: Vec<ObjectId> = self.page_iter().collect();
+        for page_id in page_ids {
+            let page = match self.objects.get_mut(&page_id).and_then(|page| page.as_dict_mut().ok()) {
+                Some(page) => page,
+                None => continue,
+            };
+            removed.extend(page.remove(b"Thumb"));
+        }
+
+        // Palettes of Acrobat thumbnails are streams, which the thumbnails of a document often share.
+        let mut unused = Vec::new();
+        for thumbnail in &removed {
+            if let Ok(id) = thumbnail.as_reference() {
+                unused.push(id);
+                let palette = self
+                    .get_object(id)
+                    .and_then(Object::as_stream)
+                    .and_then(|stream| stream.dict.get_deref(b"ColorSpace", self))
+                    .and_then(Object::as_array)
+                    .ok()
+                    .and_then(|color_space| color_space.get(3)?.as_reference().ok());
+                unused.extend(palette);
+            }
+        }
+        let referenced = self.traverse_objects(|_| {});
+        for id in unused {
+            if !referenced.contains(&id) {
+                self.objects.remove(&id);
+            }
+        }
+        removed.len()
+    }
+
+    fn check_thumbnail(&self, image: &Stream) -> Result<()> {
+        let color_space = image.dict.get_deref(b"ColorSpace", self)?;
+        let device = |name: &[u8]| name == b"DeviceGray" || name == b"DeviceRGB";
+        let valid = match color_space {
+            Object::Name(name) => device(name),
+            Object::Array(array) => match array.as_slice() {
+                [Object::Name(family), base, _, _] if family == b"Indexed" => {
+                    let (_, base) = self.dereference(base)?;
+                    base.as_name().is_ok_and(device)
+                }
+                _ => false,
+            },
+            _ => false,
+        };
+        if !valid {
+            return Err(Error::Syntax(
+                "Thumbnail color space is not DeviceGray, DeviceRGB or Indexed on one of them".to_string(),
+            ));
+        }
+        let filters = match image.dict.get(b"Filter") {
+            Ok(Object::Array(filters)) => filters.iter().collect(),
+            Ok(filter) => vec![filter],
+            Err(_) => vec![],
+        };
+        if filters
+            .iter()
+            .any(|filter| filter.as_name().map_or(true, |name| !THUMBNAIL_FILTERS.contains(&name)))
+        {
+            return Err(Error::Syntax("Thumbnail filter is not allowed".to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Dictionary, ImageDataFormat, PixelFormat};
+
+    fn image(color_space: Object, pixels: Vec<u8>, width: i64) -> Stream {
+        let height = pixels.len() as i64 / width;
+        let dict = dictionary! {
+            "Width" => width,
+            "Height" => height,
+            "ColorSpace" => color_space,
+            "BitsPerComponent" => 8,
+        };
+        let mut stream = Stream::new(dict, pixels);
+        stream.compress().unwrap();
+        stream
+    }
+
+    #[test]
+    fn thumbnails_set_read_and_removed() {
+        let mut doc = Document::load("assets/example.pdf").unwrap();
+        let page_id = doc.page_iter().next().unwrap();
+        assert!(doc.get_page_thumbnail(page_id).unwrap().is_none());
+
+        // An Indexed thumbnail as Acrobat writes them, with its palette in a stream.
+        let mut palette = Stream::new(Dictionary::new(), vec![0, 0, 0, 255, 0, 0, 0, 0, 255]);
+        palette.compress().unwrap();
+        let palette_id = doc.add_object(palette);
+        let color_space = vec!["Indexed".into(), "DeviceRGB".into(), 2.into(), palette_id.into()];
+        let indices = [0, 1, 2, 1].repeat(40);
+        let thumbnail_id = doc
+            .set_page_thumbnail(page_id, image(color_space.into(), indices.clone(), 8))
+            .unwrap();
+
+        let mut saved = Vec::new();
+        doc.save_to(&mut saved).unwrap();
+        let mut doc = Document::load_mem(&saved).unwrap();
+        let thumbnail = doc.get_page_thumbnail(page_id).unwrap().unwrap();
+        assert_eq!((thumbnail.id, thumbnail.width, thumbnail.height), (thumbnail_id, 8, 20));
+        assert_eq!(thumbnail.format, ImageDataFormat::Pixels(PixelFormat::Rgb));
+        let palette = [[0, 0, 0], [255, 0, 0], [0, 0, 255]];
+        let expected: Vec<u8> = indices.iter().flat_map(|&index| palette[usize::from(index)]).collect();
+        assert_eq!(thumbnail.data, expected);
+
+        // Only the color spaces and filters of thumbnails are accepted.
+        assert!(doc
+            .set_page_thumbnail(page_id, image("DeviceCMYK".into(), vec![0; 16], 2))
+            .is_err());
+        let mut jpx = image("DeviceRGB".into(), vec![0; 12], 2);
+        jpx.dict.set("Filter", "JPXDecode");
+        assert!(doc.set_page_thumbnail(page_id, jpx).is_err());
+
+        assert_eq!(doc.remove_thumbnails(), 1);
+        assert!(doc.get_page_thumbnail(page_id).unwrap().is_none());
+        assert!(!doc.objects.contains_key(&thumbnail_id) && !doc.objects.contains_key(&palette_id));
+    }
+
+    #[cfg(feature = "embed_image")]
+    #[test]
+    fn png_thumbnail_read_back() {
+        let pixels = image::RgbImage::from_fn(6, 4, |x, y| image::Rgb([x as u8 * 40, y as u8 * 60, 200]));
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgb8(pixels.clone())
+            .write_to(&mut png, image::ImageOutputFormat::Png)
+            .unwrap();
+
+        let mut doc = Document::load("assets/example.pdf").unwrap();
+        let page_id = doc.page_iter().next().unwrap();
+        doc.set_page_thumbnail(page_id, crate::xobject::image_from(png).unwrap())
+            .unwrap();
+        let thumbnail = doc.get_page_thumbnail(page_id).unwrap().unwrap();
+        assert_eq!((thumbnail.width, thumbnail.height), (6, 4));
+        assert_eq!(thumbnail.data, pixels.into_raw());
+    }
+}
@@ -0,0 +1,81 @@
+//! Spans and events of the `tracing` feature. Without the feature, [`span!`] gives a span that does nothing and
+//! [`repair!`] expands to nothing, the fields being neither evaluated nor recorded.
+
+/// Enter a span of the `lopdf` target until the end of the scope of the span returned, at debug level unless a
+/// level is given first. The span has a `duration_us` field, recorded when it is dropped; other fields are given
+/// as `tracing` takes them, `tracing::field::Empty` for those recorded later with [`Span::record`].
+macro_rules! span {
+    ($level:ident, $name:literal $(, $($fields:tt)*)?) => {{
+        #[cfg(feature = "tracing")]
+        let span = $crate::trace::Span::enter(tracing::span!(
+            target: "lopdf",
+            tracing::Level::$level,
+            $name,
+            duration_us = tracing::field::Empty
+            $(, $($fields)*)?
+        ));
+        #[cfg(not(feature = "tracing"))]
+        let span = $crate::trace::Span;
+        span
+    }};
+    ($name:literal $(, $($fields:tt)*)?) => {
+        span!(DEBUG, $name $(, $($fields)*)?)
+    };
+}
+
+/// A debug event of the `lopdf::repair` target, for a document read though it is malformed: an object skipped or
+/// a value corrected.
+macro_rules! repair {
+    ($($arg:tt)+) => {{
+        #[cfg(feature = "tracing")]
+        tracing::debug!(target: "lopdf::repair", $($arg)+);
+    }};
+}
+
+/// A span entered by [`span!`], exited when it is dropped.
+#[cfg(feature = "tracing")]
+pub(crate) struct Span {
+    span: tracing::span::EnteredSpan,
+    // There is no clock to read in WebAssembly without JavaScript.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    start: std::time::Instant,
+}
+
+#[cfg(feature = "tracing")]
+impl Span {
+    pub(crate) fn enter(span: tracing::Span) -> Span {
+        Span {
+            span: span.entered(),
+            #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+            start: std::time::Instant::now(),
+        }
+    }
+
+    /// Record a count of the span, of objects or bytes.
+    pub(crate) fn record(&self, field: &'static str, value: usize) {
+        self.span.record(field, value as u64);
+    }
+
+    /// Exit the span before the end of its scope.
+    pub(crate) fn exit(self) {}
+}
+
+#[cfg(feature = "tracing")]
+impl Drop for Span {
+    fn drop(&mut self) {
+        #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+        self.span.record("duration_us", self.start.elapsed().as_micros() as u64);
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct Span;
+
+#[cfg(not(feature = "tracing"))]
+impl Span {
+    #[inline(always)]
+    pub(crate) fn record(&self, _field: &'static str, _value: usize) {}
+
+    #[inline(always)]
+    pub(crate) fn exit(self) {}
+}
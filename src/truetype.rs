@@ -0,0 +1,488 @@
+//! Reading TrueType fonts, enough to lay out text and embed the font as a composite font.
+
+use crate::forms::appearance::TextFont;
+use crate::{Document, Error, Object, ObjectId, Result, Stream};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
+/// Most character codes that the groups or segments of a character map cover altogether, as many as there are
+/// Unicode code points.
+const MAPPED_CODE_LIMIT: u64 = 0x11_0000;
+
+/// Metrics and character map of a TrueType font file.
+#[derive(Debug, Clone)]
+pub(crate) struct TrueTypeFont {
+    data: Vec<u8>,
+    postscript_name: String,
+    units_per_em: f64,
+    /// Bounding box of all glyphs, in font units.
+    bbox: [i16; 4],
+    ascender: i16,
+    descender: i16,
+    cap_height: Option<i16>,
+    italic_angle: f64,
+    /// Advance width of each glyph, in font units.
+    advances: Vec<u16>,
+    glyphs: BTreeMap<char, u16>,
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Result<u16> {
+    data.get(offset..offset + 2)
+        .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+        .ok_or_else(|| Error::Syntax("Truncated TrueType font".to_string()))
+}
+
+fn i16_at(data: &[u8], offset: usize) -> Result<i16> {
+    u16_at(data, offset).map(|value| value as i16)
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Result<u32> {
+    Ok(u32::from(u16_at(data, offset)?) << 16 | u32::from(u16_at(data, offset + 2)?))
+}
+
+impl TrueTypeFont {
+    pub fn parse(data: Vec<u8>) -> Result<TrueTypeFont> {
+        match data.get(0..4) {
+            Some(b"\x00\x01\x00\x00") | Some(b"true") => {}
+            Some(b"OTTO") => return Err(Error::Unimplemented("OpenType fonts with CFF outlines")),
+            _ => return Err(Error::Syntax("Not a TrueType font".to_string())),
+        }
+        let mut tables = BTreeMap::new();
+        for i in 0..usize::from(u16_at(&data, 4)?) {
+            let record = 12 + 16 * i;
+            let tag = data.get(record..record + 4).ok_or(Error::Offset(record))?.to_vec();
+            let offset = u32_at(&data, record + 8)? as usize;
+            let length = u32_at(&data, record + 12)? as usize;
+            if offset.checked_add(length).is_none_or(|end| end > data.len()) {
+                return Err(Error::Offset(offset));
+            }
+            tables.insert(tag, &data[offset..offset + length]);
+        }
+        let table = |tag: &[u8]| {
+            tables
+                .get(tag)
+                .copied()
+                .ok_or_else(|| Error::Syntax(format!("TrueType font without {} table", String::from_utf8_lossy(tag))))
+        };
+
+        let head = table(b"head")?;
+        let units_per_em = f64::from(u16_at(head, 18)?.max(1));
+        let bbox = [
+            i16_at(head, 36)?,
+            i16_at(head, 38)?,
+            i16_at(head, 40)?,
+            i16_at(head, 42)?,
+        ];
+        let hhea = table(b"hhea")?;
+        let metrics = usize::from(u16_at(hhea, 34)?);
+        let glyph_count = match tables.get(&b"maxp"[..]) {
+            Some(maxp) => usize::from(u16_at(maxp, 4)?),
+            None => metrics,
+        };
+        let hmtx = table(b"hmtx")?;
+        let mut advances = Vec::with_capacity(glyph_count);
+        for glyph in 0..glyph_count.max(metrics) {
+            // Glyphs after the last metric share its advance width.
+            let advance = match advances.last() {
+                Some(&last) if glyph >= metrics => last,
+                _ => u16_at(hmtx, 4 * glyph)?,
+            };
+            advances.push(advance);
+        }
+        table(b"glyf")?;
+
+        let cap_height = tables
+            .get(&b"OS/2"[..])
+            .filter(|os2| u16_at(os2, 0).is_ok_and(|version| version >= 2))
+            .and_then(|os2| i16_at(os2, 88).ok());
+        let italic_angle = tables
+            .get(&b"post"[..])
+            .and_then(|post| u32_at(post, 4).ok())
+            .map_or(0.0, |angle| f64::from(angle as i32) / 65536.0);
+        let postscript_name = tables
+            .get(&b"name"[..])
+            .and_then(|name| postscript_name(name))
+            .unwrap_or_else(|| "EmbeddedFont".to_string());
+
+        Ok(TrueTypeFont {
+            postscript_name,
+            units_per_em,
+            bbox,
+            ascender: i16_at(hhea, 4)?,
+            descender: i16_at(hhea, 6)?,
+            cap_height,
+            italic_angle,
+            advances,
+            glyphs: character_map(table(b"cmap")?)?,
+            data,
+        })
+    }
+
+    /// The glyph for a character, 0 being the glyph shown for missing characters.
+    pub fn glyph(&self, c: char) -> u16 {
+        self.glyphs.get(&c).copied().unwrap_or(0)
+    }
+
+    /// Advance width of a glyph, in thousandths of the font size.
+    fn advance(&self, glyph: u16) -> f64 {
+        let advance = self.advances.get(usize::from(glyph)).or_else(|| self.advances.last());
+        f64::from(advance.copied().unwrap_or(0)) * 1000.0 / self.units_per_em
+    }
+
+    fn scale(&self, units: i16) -> f64 {
+        (f64::from(units) * 1000.0 / self.units_per_em).round()
+    }
+}
+
+impl TextFont for TrueTypeFont {
+    /// Glyph identifiers as two-byte codes, matching the `Identity-H` encoding of the embedded font.
+    fn encode(&self, text: &str) -> Vec<u8> {
+        text.chars()
+            .flat_map(|c| self.glyph(c).to_be_bytes().to_vec())
+            .collect()
+    }
+
+    fn width(&self, bytes: &[u8]) -> f64 {
+        let width: f64 = bytes
+            .chunks_exact(2)
+            .map(|code| self.advance(u16::from_be_bytes([code[0], code[1]])))
+            .sum();
+        width / 1000.0
+    }
+
+    fn code_len(&self) -> usize {
+        2
+    }
+
+    fn ascent(&self) -> f64 {
+        self.scale(self.ascender)
+    }
+
+    fn descent(&self) -> f64 {
+        self.scale(self.descender)
+    }
+}
+
+/// The PostScript name from a `name` table, without the characters a PDF font name should not contain.
+fn postscript_name(table: &[u8]) -> Option<String> {
+    let count = usize::from(u16_at(table, 2).ok()?);
+    let storage = usize::from(u16_at(table, 4).ok()?);
+    for i in 0..count {
+        let record = 6 + 12 * i;
+        let platform = u16_at(table, record).ok()?;
+        if u16_at(table, record + 6).ok()? != 6 {
+            continue;
+        }
+        let length = usize::from(u16_at(table, record + 8).ok()?);
+        let offset = storage + usize::from(u16_at(table, record + 10).ok()?);
+        let bytes = table.get(offset..offset + length)?;
+        let name = match platform {
+            1 => String::from_utf8_lossy(bytes).into_owned(),
+            0 | 3 => {
+                let code_units: Vec<u16> = bytes
+                    .chunks_exact(2)
+                    .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                    .collect();
+                String::from_utf16_lossy(&code_units)
+            }
+            _ => continue,
+        };
+        let name: String = name
+            .chars()
+            .filter(|c| c.is_ascii_graphic() && !"()<>[]{}/%#".contains(*c))
+            .collect();
+        if !name.is_empty() {
+            return Some(name);
+        }
+    }
+    None
+}
+
+/// Read the Unicode subtable of a `cmap` table, in format 12 or 4.
+fn character_map(table: &[u8]) -> Result<BTreeMap<char, u16>> {
+    let mut subtables = Vec::new();
+    for i in 0..usize::from(u16_at(table, 2)?) {
+        let record = 4 + 8 * i;
+        let platform = u16_at(table, record)?;
+        let encoding = u16_at(table, record + 2)?;
+        let offset = u32_at(table, record + 4)? as usize;
+        let format = u16_at(table, offset)?;
+        // Full Unicode subtables come first, then those limited to the basic multilingual plane.
+        let priority = match (platform, encoding, format) {
+            (3, 10, 12) | (0, _, 12) => 0,
+            (3, 1, 4) | (0, _, 4) => 1,
+            _ => continue,
+        };
+        subtables.push((priority, offset, format));
+    }
+    subtables.sort_unstable();
+    let (_, offset, format) = *subtables
+        .first()
+        .ok_or_else(|| Error::Syntax("TrueType font without a Unicode character map".to_string()))?;
+    let subtable = &table[offset..];
+
+    let mut glyphs = BTreeMap::new();
+    let mut mapped = 0;
+    let mut map_codes = |count: u64| {
+        mapped += count;
+        if mapped > MAPPED_CODE_LIMIT {
+            return Err(Error::Syntax("TrueType character map covers too many codes".to_string()));
+        }
+        Ok(())
+    };
+    if format == 12 {
+        for group in 0..u32_at(subtable, 12)? as usize {
+            let record = 16 + 12 * group;
+            let (start, end, first_glyph) = (
+                u32_at(subtable, record)?,
+                u32_at(subtable, record + 4)?,
+                u32_at(subtable, record + 8)?,
+            );
+            let end = end.min(0x10FFFF);
+            map_codes((u64::from(end) + 1).saturating_sub(u64::from(start)))?;
+            for code in start..=end {
+                let glyph = first_glyph.checked_add(code - start).and_then(|glyph| u16::try_from(glyph).ok());
+                if let (Some(c), Some(glyph)) = (char::from_u32(code), glyph) {
+                    glyphs.insert(c, glyph);
+                }
+            }
+        }
+    } else {
+        let segments = usize::from(u16_at(subtable, 6)? / 2);
+        let ends = 14;
+        let starts = ends + 2 * segments + 2;
+        let deltas = starts + 2 * segments;
+        let range_offsets = deltas + 2 * segments;
+        for segment in 0..segments {
+            let end = u16_at(subtable, ends + 2 * segment)?;
+            let start = u16_at(subtable, starts + 2 * segment)?;
+            let delta = u16_at(subtable, deltas + 2 * segment)?;
+            let range_offset = usize::from(u16_at(subtable, range_offsets + 2 * segment)?);
+            map_codes((u64::from(end) + 1).saturating_sub(u64::from(start)))?;
+            for code in start..=end {
+                let glyph = if range_offset == 0 {
+                    code.wrapping_add(delta)
+                } else {
+                    // The offset is relative to its own position in the subtable.
+                    let index = range_offsets + 2 * segment + range_offset + 2 * usize::from(code - start);
+                    match u16_at(subtable, index)? {
+                        0 => 0,
+                        glyph => glyph.wrapping_add(delta),
+                    }
+                };
+                if let (Some(c), true) = (char::from_u32(u32::from(code)), glyph != 0) {
+                    glyphs.insert(c, glyph);
+                }
+                if code == u16::MAX {
+                    break;
+                }
+            }
+        }
+    }
+    Ok(glyphs)
+}
+
+impl Document {
+    /// Embed a whole TrueType font as a composite font with the `Identity-H` encoding, so that text encoded with
+    /// [`TextFont::encode`] can be shown with it. Widths and the `/ToUnicode` map cover the characters of `text`.
+    pub(crate) fn embed_truetype(&mut self, font: &TrueTypeFont, text: &str) -> Result<ObjectId> {
+        let mut used: BTreeMap<u16, char> = BTreeMap::new();
+        for c in text.chars() {
+            used.entry(font.glyph(c)).or_insert(c);
+        }
+
+        let mut font_file = Stream::new(dictionary! { "Length1" => font.data.len() as i64 }, font.data.clone());
+        font_file.compress()?;
+        let font_file_id = self.add_object(font_file);
+        let name = font.postscript_name.as_str();
+        let [x_min, y_min, x_max, y_max] = font.bbox;
+        let descriptor_id = self.add_object(dictionary! {
+            "Type" => "FontDescriptor",
+            "FontName" => name,
+            // Nonsymbolic.
+            "Flags" => 32,
+            "FontBBox" => vec![
+                font.scale(x_min).into(),
+                font.scale(y_min).into(),
+                font.scale(x_max).into(),
+                font.scale(y_max).into(),
+            ],
+            "ItalicAngle" => font.italic_angle,
+            "Ascent" => font.ascent(),
+            "Descent" => font.descent(),
+            "CapHeight" => font.scale(font.cap_height.unwrap_or(font.ascender)),
+            "StemV" => 80,
+            "FontFile2" => font_file_id,
+        });
+
+        let mut widths = Vec::new();
+        for &glyph in used.keys() {
+            widths.push(Object::Integer(i64::from(glyph)));
+            widths.push(Object::Array(vec![font.advance(glyph).round().into()]));
+        }
+        let descendant_id = self.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "CIDFontType2",
+            "BaseFont" => name,
+            "CIDSystemInfo" => dictionary! {
+                "Registry" => Object::string_literal("Adobe"),
+                "Ordering" => Object::string_literal("Identity"),
+                "Supplement" => 0,
+            },
+            "FontDescriptor" => descriptor_id,
+            "W" => widths,
+            "CIDToGIDMap" => "Identity",
+        });
+
+        let mut to_unicode = Stream::new(dictionary! {}, to_unicode_cmap(&used).into_bytes());
+        to_unicode.compress()?;
+        let to_unicode_id = self.add_object(to_unicode);
+        Ok(self.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type0",
+            "BaseFont" => name,
+            "Encoding" => "Identity-H",
+            "DescendantFonts" => vec![descendant_id.into()],
+            "ToUnicode" => to_unicode_id,
+        }))
+    }
+}
+
+/// A CMap mapping two-byte glyph codes back to the characters they show, for text extraction.
+fn to_unicode_cmap(glyphs: &BTreeMap<u16, char>) -> String {
+    let mut cmap = String::from(
+        "/CIDInit /ProcSet findresource begin\n12 dict begin\nbegincmap\n\
+         /CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n\
+         /CMapName /Adobe-Identity-UCS def\n/CMapType 2 def\n\
+         1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n",
+    );
+    let glyphs: Vec<(&u16, &char)> = glyphs.iter().filter(|(&glyph, _)| glyph != 0).collect();
+    // A bfchar block holds at most 100 mappings.
+    for block in glyphs.chunks(100) {
+        cmap.push_str(&format!("{} beginbfchar\n", block.len()));
+        for (glyph, c) in block {
+            let unicode: String = c
+                .encode_utf16(&mut [0; 2])
+                .iter()
+                .map(|unit| format!("{:04X}", unit))
+                .collect();
+            cmap.push_str(&format!("<{:04X}> <{}>\n", glyph, unicode));
+        }
+        cmap.push_str("endbfchar\n");
+    }
+    cmap.push_str("endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend\n");
+    cmap
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A font with an empty glyph for `A` and one for `Ж`, 2048 units per em.
+    pub(crate) fn test_font() -> Vec<u8> {
+        fn words(values: &[u16]) -> Vec<u8> {
+            values.iter().flat_map(|value| value.to_be_bytes().to_vec()).collect()
+        }
+        let mut head = vec![0; 54];
+        head[18..20].copy_from_slice(&2048u16.to_be_bytes());
+        head[36..44].copy_from_slice(&words(&[0, (-400i16) as u16, 1800, 1900]));
+        let mut hhea = vec![0; 36];
+        hhea[4..8].copy_from_slice(&words(&[1900, (-480i16) as u16]));
+        hhea[34..36].copy_from_slice(&3u16.to_be_bytes());
+        let maxp = words(&[0, 0x5000, 3]);
+        let hmtx = words(&[1024, 0, 1400, 0, 1600, 0]);
+        let cmap = [
+            words(&[0, 1, 3, 1, 0, 12]),
+            // Segments for A, Ж and the final 0xFFFF one.
+            words(&[4, 40, 0, 6, 4, 1, 2]),
+            words(&[0x41, 0x416, 0xFFFF, 0]),
+            words(&[0x41, 0x416, 0xFFFF]),
+            words(&[1u16.wrapping_sub(0x41), 2u16.wrapping_sub(0x416), 1]),
+            words(&[0, 0, 0]),
+        ]
+        .concat();
+        let name = [
+            words(&[0, 1, 18, 3, 1, 0x409, 6, 18, 0]),
+            words(&"Test Sans".encode_utf16().collect::<Vec<_>>()),
+        ]
+        .concat();
+        let tables: Vec<(&[u8], Vec<u8>)> = vec![
+            (b"cmap", cmap),
+            (b"glyf", Vec::new()),
+            (b"head", head),
+            (b"hhea", hhea),
+            (b"hmtx", hmtx),
+            (b"maxp", maxp),
+            (b"name", name),
+        ];
+        let mut font = words(&[1, 0, tables.len() as u16, 0, 0, 0]);
+        let mut offset = 12 + 16 * tables.len();
+        let mut data = Vec::new();
+        for (tag, table) in &tables {
+            font.extend_from_slice(tag);
+            font.extend(words(&[0, 0]));
+            font.extend((offset as u32).to_be_bytes().iter());
+            font.extend((table.len() as u32).to_be_bytes().iter());
+            offset += table.len();
+            data.extend_from_slice(table);
+        }
+        font.extend(data);
+        font
+    }
+
+    #[test]
+    fn read_truetype_font() {
+        let font = TrueTypeFont::parse(test_font()).unwrap();
+        assert_eq!(font.postscript_name, "TestSans");
+        assert_eq!(font.glyph('A'), 1);
+        assert_eq!(font.glyph('Ж'), 2);
+        assert_eq!(font.glyph('B'), 0);
+        let text = font.encode("AЖ");
+        assert_eq!(text, vec![0, 1, 0, 2]);
+        assert_eq!(font.width(&text), 1.46484375);
+        assert_eq!((font.ascent(), font.descent()), (928.0, -234.0));
+        assert!(TrueTypeFont::parse(b"OTTO\x00\x00".to_vec()).is_err());
+
+        let mut doc = Document::with_version("1.5");
+        let font_id = doc.embed_truetype(&font, "AЖA").unwrap();
+        let type0 = doc.get_dictionary(font_id).unwrap();
+        let descendant = type0.get(b"DescendantFonts").and_then(Object::as_array).unwrap()[0]
+            .as_reference()
+            .unwrap();
+        let widths = doc
+            .get_dictionary(descendant)
+            .unwrap()
+            .get(b"W")
+            .and_then(Object::as_array)
+            .unwrap();
+        assert_eq!(widths.len(), 4);
+        assert_eq!(widths[2].as_i64().unwrap(), 2);
+        let to_unicode = type0.get(b"ToUnicode").and_then(Object::as_reference).unwrap();
+        let cmap = doc
+            .get_object(to_unicode)
+            .and_then(Object::as_stream)
+            .unwrap()
+            .decompressed_content()
+            .unwrap();
+        assert!(String::from_utf8(cmap).unwrap().contains("<0002> <0416>"));
+    }
+
+    #[test]
+    fn bound_character_map_groups() {
+        // A format 12 Unicode subtable with the given groups of start, end and first glyph.
+        let cmap = |groups: &[[u32; 3]]| {
+            let mut table = [&[0, 0, 0, 1, 0, 3, 0, 10][..], &12u32.to_be_bytes(), &[0, 12, 0, 0]].concat();
+            table.extend_from_slice(&(16 + 12 * groups.len() as u32).to_be_bytes());
+            table.extend_from_slice(&0u32.to_be_bytes());
+            table.extend_from_slice(&(groups.len() as u32).to_be_bytes());
+            for value in groups.iter().flatten() {
+                table.extend_from_slice(&value.to_be_bytes());
+            }
+            table
+        };
+        let glyphs = character_map(&cmap(&[[0x41, 0x42, 7], [0x43, 0x44, u32::MAX]])).unwrap();
+        assert_eq!(glyphs.into_iter().collect::<Vec<_>>(), vec![('A', 7), ('B', 8)]);
+        assert!(character_map(&cmap(&[[0, 0x10FFFF, 0]])).is_ok());
+        assert!(character_map(&cmap(&[[0, 0x10FFFF, 0]; 2])).is_err());
+    }
+}
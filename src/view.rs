@@ -0,0 +1,1257 @@
+use crate::content::Content;
+use crate::font_cache::FontCache;
+use crate::reader::{buffer_offset, Reader, MAX_BRACKET};
+use crate::xref::{Xref, XrefEntry};
+use crate::{Codecs, Dictionary, Document, Error, Object, ObjectId, Result, Stream, StringFormat};
+use log::warn;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+use std::str;
+use std::sync::{Arc, RwLock};
+use std::thread::{self, ThreadId};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// The deepest nesting of arrays and dictionaries parsed.
+const MAX_DEPTH: usize = 256;
+
+/// A PDF object read by a [`DocumentView`], borrowing from the buffer of the file. Names and literal strings
+/// without escapes, and the contents of streams, are slices of the buffer; other names and strings are decoded
+/// into owned bytes.
+#[derive(Debug, Clone)]
+pub enum ObjectRef<'a> {
+    Null,
+    Boolean(bool),
+    Integer(i64),
+    Real(f64),
+    Name(Cow<'a, [u8]>),
+    String(Cow<'a, [u8]>, StringFormat),
+    Array(Vec<ObjectRef<'a>>),
+    Dictionary(DictionaryRef<'a>),
+    Stream(StreamRef<'a>),
+    Reference(ObjectId),
+}
+
+/// A dictionary of a [`DocumentView`], with its entries in the order of the file.
+#[derive(Debug, Clone, Default)]
+pub struct DictionaryRef<'a>(Vec<(Cow<'a, [u8]>, ObjectRef<'a>)>);
+
+/// A stream of a [`DocumentView`], whose content is a slice of the buffer of the file.
+#[derive(Debug, Clone)]
+pub struct StreamRef<'a> {
+    pub dict: DictionaryRef<'a>,
+    pub content: &'a [u8],
+}
+
+impl<'a> ObjectRef<'a> {
+    /// The name of the type of the object, as [`Object::enum_variant`] gives it.
+    pub fn enum_variant(&self) -> &'static str {
+        match *self {
+            ObjectRef::Null => "Null",
+            ObjectRef::Boolean(_) => "Boolean",
+            ObjectRef::Integer(_) => "Integer",
+            ObjectRef::Real(_) => "Real",
+            ObjectRef::Name(_) => "Name",
+            ObjectRef::String(..) => "String",
+            ObjectRef::Array(_) => "Array",
+            ObjectRef::Dictionary(_) => "Dictionary",
+            ObjectRef::Stream(_) => "Stream",
+            ObjectRef::Reference(_) => "Reference",
+        }
+    }
+
+    pub fn as_bool(&self) -> Result<bool> {
+        match *self {
+            ObjectRef::Boolean(value) => Ok(value),
+            _ => Err(Error::type_mismatch("Boolean", self.enum_variant())),
+        }
+    }
+
+    pub fn as_i64(&self) -> Result<i64> {
+        match *self {
+            ObjectRef::Integer(value) => Ok(value),
+            _ => Err(Error::type_mismatch("Integer", self.enum_variant())),
+        }
+    }
+
+    /// The value of an integer or a real number.
+    pub fn as_f64(&self) -> Result<f64> {
+        match *self {
+            ObjectRef::Integer(value) => Ok(value as f64),
+            ObjectRef::Real(value) => Ok(value),
+            _ => Err(Error::type_mismatch("Integer or Real", self.enum_variant())),
+        }
+    }
+
+    pub fn as_name(&self) -> Result<&[u8]> {
+        match self {
+            ObjectRef::Name(name) => Ok(name),
+            _ => Err(Error::type_mismatch("Name", self.enum_variant())),
+        }
+    }
+
+    pub fn as_str(&self) -> Result<&[u8]> {
+        match self {
+            ObjectRef::String(text, _) => Ok(text),
+            _ => Err(Error::type_mismatch("String", self.enum_variant())),
+        }
+    }
+
+    pub fn as_array(&self) -> Result<&[ObjectRef<'a>]> {
+        match self {
+            ObjectRef::Array(array) => Ok(array),
+            _ => Err(Error::type_mismatch("Array", self.enum_variant())),
+        }
+    }
+
+    pub fn as_dict(&self) -> Result<&DictionaryRef<'a>> {
+        match self {
+            ObjectRef::Dictionary(dict) => Ok(dict),
+            _ => Err(Error::type_mismatch("Dictionary", self.enum_variant())),
+        }
+    }
+
+    pub fn as_stream(&self) -> Result<&StreamRef<'a>> {
+        match self {
+            ObjectRef::Stream(stream) => Ok(stream),
+            _ => Err(Error::type_mismatch("Stream", self.enum_variant())),
+        }
+    }
+
+    pub fn as_reference(&self) -> Result<ObjectId> {
+        match *self {
+            ObjectRef::Reference(id) => Ok(id),
+            _ => Err(Error::type_mismatch("Reference", self.enum_variant())),
+        }
+    }
+
+    /// An owned copy of the object.
+    pub fn to_object(&self) -> Object {
+        match self {
+            ObjectRef::Null => Object::Null,
+            ObjectRef::Boolean(value) => Object::Boolean(*value),
+            ObjectRef::Integer(value) => Object::Integer(*value),
+            ObjectRef::Real(value) => Object::Real(*value),
+            ObjectRef::Name(name) => Object::Name(name.to_vec()),
+            ObjectRef::String(text, format) => Object::String(text.to_vec(), format.clone()),
+            ObjectRef::Array(array) => Object::Array(array.iter().map(ObjectRef::to_object).collect()),
+            ObjectRef::Dictionary(dict) => Object::Dictionary(dict.to_dictionary()),
+            ObjectRef::Stream(stream) => Object::Stream(stream.to_stream()),
+            ObjectRef::Reference(id) => Object::Reference(*id),
+        }
+    }
+
+    /// The object with all its bytes owned, for the objects of object streams, which are parsed from decoded
+    /// content. Streams, which object streams cannot hold, become null.
+    fn into_owned(self) -> ObjectRef<'static> {
+        match self {
+            ObjectRef::Null => ObjectRef::Null,
+            ObjectRef::Boolean(value) => ObjectRef::Boolean(value),
+            ObjectRef::Integer(value) => ObjectRef::Integer(value),
+            ObjectRef::Real(value) => ObjectRef::Real(value),
+            ObjectRef::Name(name) => ObjectRef::Name(Cow::Owned(name.into_owned())),
+            ObjectRef::String(text, format) => ObjectRef::String(Cow::Owned(text.into_owned()), format),
+            ObjectRef::Array(array) => ObjectRef::Array(array.into_iter().map(ObjectRef::into_owned).collect()),
+            ObjectRef::Dictionary(dict) => ObjectRef::Dictionary(DictionaryRef(
+                dict.0
+                    .into_iter()
+                    .map(|(key, value)| (Cow::Owned(key.into_owned()), value.into_owned()))
+                    .collect(),
+            )),
+            ObjectRef::Stream(_) => ObjectRef::Null,
+            ObjectRef::Reference(id) => ObjectRef::Reference(id),
+        }
+    }
+}
+
+impl From<ObjectRef<'_>> for Object {
+    fn from(object: ObjectRef<'_>) -> Self {
+        object.to_object()
+    }
+}
+
+impl<'a> DictionaryRef<'a> {
+    /// The value of a key, the last one if the key is repeated.
+    pub fn get(&self, key: &[u8]) -> Result<&ObjectRef<'a>> {
+        self.0
+            .iter()
+            .rev()
+            .find(|(name, _)| name.as_ref() == key)
+            .map(|(_, value)| value)
+            .ok_or_else(|| Error::dict_key(key))
+    }
+
+    pub fn has(&self, key: &[u8]) -> bool {
+        self.get(key).is_ok()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&[u8], &ObjectRef<'a>)> {
+        self.0.iter().map(|(key, value)| (key.as_ref(), value))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// An owned copy of the dictionary.
+    pub fn to_dictionary(&self) -> Dictionary {
+        self.0
+            .iter()
+            .map(|(key, value)| (key.to_vec(), value.to_object()))
+            .collect()
+    }
+}
+
+impl<'a> StreamRef<'a> {
+    /// The content decoded by its filters, borrowed if it has none. Images and filters that lopdf cannot decode
+    /// fail as with [`Stream::decompressed_content`].
+    pub fn decoded_content(&self) -> Result<Cow<'a, [u8]>> {
+        if !self.dict.has(b"Filter") {
+            return Ok(Cow::Borrowed(self.content));
+        }
+        Codecs::with(|codecs| codecs.decode(&self.dict.to_dictionary(), self.content)).map(Cow::Owned)
+    }
+
+    /// An owned copy of the stream.
+    pub fn to_stream(&self) -> Stream {
+        Stream::new(self.dict.to_dictionary(), self.content.to_vec())
+    }
+}
+
+/// A read-only view of a PDF document borrowing from the buffer of the file, for tools that only read documents.
+/// Objects are parsed when they are asked for, without copying their names, strings and stream contents.
+///
+/// The document cannot be changed or saved through the view; [`ObjectRef::to_object`] gives owned objects for
+/// that. Encrypted documents are not supported.
+///
+/// Object streams are decoded the first time one of their objects is asked for, and kept decoded for the other
+/// objects, by all the threads sharing the view.
+pub struct DocumentView<'a> {
+    buffer: &'a [u8],
+    pub version: String,
+    pub trailer: Dictionary,
+    pub reference_table: Xref,
+    object_streams: RwLock<BTreeMap<u32, CachedObjectStream>>,
+}
+
+/// An object stream in the cache of a view.
+enum CachedObjectStream {
+    /// Being decoded by a thread, which finds the stream in its own way if its length is one of its objects.
+    Decoding(ThreadId),
+    Decoded(Arc<DecodedObjectStream>),
+    Failed,
+}
+
+/// The decoded content of an object stream, and the offsets of its objects in it by object number.
+struct DecodedObjectStream {
+    data: Vec<u8>,
+    offsets: BTreeMap<u32, usize>,
+}
+
+impl<'a> DocumentView<'a> {
+    /// Read the cross-reference table of a file.
+    pub fn load(buffer: &'a [u8]) -> Result<Self> {
+        let document = Reader::cross_references(buffer)?;
+        if document.trailer.has(b"Encrypt") {
+            return Err(Error::Unimplemented("views of encrypted documents"));
+        }
+        Ok(DocumentView {
+            buffer,
+            version: document.version,
+            trailer: document.trailer,
+            reference_table: document.reference_table,
+            object_streams: RwLock::new(BTreeMap::new()),
+        })
+    }
+
+    /// Decode all the object streams at once, on several threads with the `parallel` feature, rather than as
+    /// their objects are asked for.
+    pub fn preload_object_streams(&self) {
+        let containers: Vec<u32> = self
+            .reference_table
+            .entries
+            .values()
+            .filter_map(|entry| match *entry {
+                XrefEntry::Compressed { container, .. } => Some(container),
+                _ => None,
+            })
+            .collect::<BTreeSet<u32>>()
+            .into_iter()
+            .collect();
+        #[cfg(feature = "rayon")]
+        containers.par_iter().for_each(|&container| {
+            let _ = self.object_stream(container);
+        });
+        #[cfg(not(feature = "rayon"))]
+        containers.iter().for_each(|&container| {
+            let _ = self.object_stream(container);
+        });
+    }
+
+    /// Parse an object of the file.
+    pub fn get_object(&self, id: ObjectId) -> Result<ObjectRef<'a>> {
+        match self.reference_table.get(id.0) {
+            Some(XrefEntry::Normal { offset, generation }) if *generation == id.1 => {
+                let offset = buffer_offset(self.buffer, *offset)?;
+                let mut parser = Parser {
+                    input: self.buffer,
+                    pos: offset,
+                };
+                let (object_id, object) = parser.indirect_object(self).map_err(|err| err.in_object(id))?;
+                if object_id != id {
+                    return Err(Error::ObjectIdMismatch);
+                }
+                Ok(object)
+            }
+            Some(XrefEntry::Compressed { container, .. }) if id.1 == 0 => {
+                let stream = self.object_stream(*container)?;
+                let offset = *stream.offsets.get(&id.0).ok_or_else(|| Error::not_found(id))?;
+                let mut parser = Parser {
+                    input: &stream.data,
+                    pos: offset,
+                };
+                parser.object(0).map(ObjectRef::into_owned)
+            }
+            _ => Err(Error::not_found(id)),
+        }
+    }
+
+    /// The object a reference points to, following chains of references, or the object itself if it is direct.
+    pub fn dereference(&self, object: &ObjectRef<'a>) -> Result<ObjectRef<'a>> {
+        let mut object = object.clone();
+        let mut chain = Vec::new();
+        for _ in 0..32 {
+            match object {
+                ObjectRef::Reference(id) => {
+                    object = self.get_object(id).map_err(|err| err.after_references(&chain))?;
+                    chain.push(id);
+                }
+                _ => return Ok(object),
+            }
+        }
+        Err(Error::ReferenceLimit { chain })
+    }
+
+    pub fn get_dictionary(&self, id: ObjectId) -> Result<DictionaryRef<'a>> {
+        match self.get_object(id)? {
+            ObjectRef::Dictionary(dict) => Ok(dict),
+            object => Err(Error::type_mismatch("Dictionary", object.enum_variant())),
+        }
+    }
+
+    pub fn catalog(&self) -> Result<DictionaryRef<'a>> {
+        self.get_dictionary(self.trailer.get(b"Root").and_then(Object::as_reference)?)
+    }
+
+    /// The pages of the document by their number, starting from 1, as [`Document::get_pages`](crate::Document::get_pages)
+    /// gives them.
+    pub fn get_pages(&self) -> BTreeMap<u32, ObjectId> {
+        let mut pages = BTreeMap::new();
+        if let Ok(root_id) = self.catalog().and_then(|catalog| catalog.get(b"Pages")?.as_reference()) {
+            self.collect_pages(root_id, 0, &mut BTreeSet::new(), &mut pages);
+        }
+        pages
+    }
+
+    fn collect_pages(
+        &self, node_id: ObjectId, depth: usize, visited: &mut BTreeSet<ObjectId>, pages: &mut BTreeMap<u32, ObjectId>,
+    ) {
+        if depth > MAX_DEPTH || !visited.insert(node_id) {
+            return;
+        }
+        let node = match self.get_dictionary(node_id) {
+            Ok(node) => node,
+            Err(_) => return,
+        };
+        match node.get(b"Type").and_then(ObjectRef::as_name) {
+            Ok(b"Page") => {
+                pages.insert(pages.len() as u32 + 1, node_id);
+            }
+            Ok(b"Pages") => {
+                let kids = node.get(b"Kids").and_then(|kids| self.dereference(kids));
+                for kid in kids.iter().flat_map(|kids| kids.as_array().unwrap_or_default()) {
+                    if let Ok(kid_id) = kid.as_reference() {
+                        self.collect_pages(kid_id, depth + 1, visited, pages);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The decoded content of a page, as [`Document::get_page_content`](crate::Document::get_page_content)
+    /// gives it.
+    pub fn get_page_content(&self, page_id: ObjectId) -> Result<Vec<u8>> {
+        let page = self.get_dictionary(page_id)?;
+        let stream_ids: Vec<ObjectId> = match page.get(b"Contents") {
+            Ok(ObjectRef::Reference(id)) => vec![*id],
+            Ok(ObjectRef::Array(contents)) => contents.iter().filter_map(|id| id.as_reference().ok()).collect(),
+            _ => Vec::new(),
+        };
+        let mut content = Vec::new();
+        for stream_id in stream_ids {
+            if let Ok(ObjectRef::Stream(stream)) = self.get_object(stream_id) {
+                match stream.decoded_content() {
+                    Ok(data) => content.extend_from_slice(&data),
+                    Err(_) => content.extend_from_slice(stream.content),
+                }
+            }
+        }
+        Ok(content)
+    }
+
+    /// Extract the text of some pages, as [`Document::extract_text`](crate::Document::extract_text) does.
+    pub fn extract_text(&self, page_numbers: &[u32]) -> Result<String> {
+        let pages = self.get_pages();
+        let mut fonts = FontCache::default();
+        let mut text = String::new();
+        for number in page_numbers {
+            let page_id = *pages.get(number).ok_or(Error::PageNumberNotFound(*number))?;
+            let page_fonts = self.page_fonts(page_id);
+            for to_unicode in page_fonts.values().filter_map(|(_, to_unicode)| *to_unicode) {
+                if fonts.contains(to_unicode) {
+                    continue;
+                }
+                if let Ok(ObjectRef::Stream(stream)) = self.get_object(to_unicode) {
+                    if let Ok(data) = stream.decoded_content() {
+                        fonts.insert(to_unicode, &data);
+                    }
+                }
+            }
+
+            let content = Content::decode(&self.get_page_content(page_id)?)?;
+            let mut current_font = None;
+            for operation in &content.operations {
+                match operation.operator.as_ref() {
+                    "Tf" => {
+                        let font_name = operation
+                            .operands
+                            .first()
+                            .ok_or_else(|| Error::Syntax("missing font operand".to_string()))?
+                            .as_name()?;
+                        current_font = page_fonts.get(font_name);
+                    }
+                    "Tj" | "TJ" => {
+                        let (encoding, to_unicode) = match current_font {
+                            Some((encoding, to_unicode)) => (Some(encoding.as_str()), *to_unicode),
+                            None => (None, None),
+                        };
+                        collect_text(&mut text, &fonts, encoding, to_unicode, &operation.operands);
+                    }
+                    "ET" if !text.ends_with('\n') => text.push('\n'),
+                    _ => {}
+                }
+            }
+        }
+        Ok(text)
+    }
+
+    /// The encodings and `/ToUnicode` maps of the fonts of a page by their resource name.
+    fn page_fonts(&self, page_id: ObjectId) -> BTreeMap<Vec<u8>, (String, Option<ObjectId>)> {
+        let mut fonts = BTreeMap::new();
+        for (name, font) in self.page_font_entries(page_id) {
+            if let Ok(ObjectRef::Dictionary(font)) = self.dereference(&font) {
+                let encoding = font
+                    .get(b"Encoding")
+                    .and_then(ObjectRef::as_name)
+                    .ok()
+                    .and_then(|name| str::from_utf8(name).ok())
+                    .unwrap_or("StandardEncoding");
+                let to_unicode = font.get(b"ToUnicode").and_then(ObjectRef::as_reference).ok();
+                fonts.insert(name, (encoding.to_string(), to_unicode));
+            }
+        }
+        fonts
+    }
+
+    /// The entries of the fonts of a page by their resource name, from the resources of the page and those it
+    /// inherits, as [`Document::get_page_fonts`](crate::Document::get_page_fonts) finds them.
+    fn page_font_entries(&self, page_id: ObjectId) -> BTreeMap<Vec<u8>, ObjectRef<'a>> {
+        let mut resources = Vec::new();
+        let mut node = self.get_dictionary(page_id).ok();
+        if let Some(Ok(ObjectRef::Dictionary(page_resources))) = node.as_ref().map(|page| page.get(b"Resources")) {
+            resources.push(page_resources.clone());
+        }
+        for _ in 0..MAX_DEPTH {
+            let current = match node {
+                Some(ref current) => current,
+                None => break,
+            };
+            if let Ok(id) = current.get(b"Resources").and_then(ObjectRef::as_reference) {
+                resources.extend(self.get_dictionary(id));
+            }
+            node = current
+                .get(b"Parent")
+                .and_then(ObjectRef::as_reference)
+                .and_then(|id| self.get_dictionary(id))
+                .ok();
+        }
+
+        let mut fonts = BTreeMap::new();
+        for resources in resources {
+            if let Ok(ObjectRef::Dictionary(font_dict)) =
+                resources.get(b"Font").and_then(|fonts| self.dereference(fonts))
+            {
+                for (name, font) in font_dict.iter() {
+                    fonts.entry(name.to_vec()).or_insert_with(|| font.clone());
+                }
+            }
+        }
+        fonts
+    }
+
+    /// Run a closure on a page loaded alone, without the other pages of the document, for documents too large to
+    /// load at once. Only the page, the attributes it inherits, its content streams and its fonts with their
+    /// `/ToUnicode` maps are copied out of the file, and they are dropped once the closure returns.
+    ///
+    /// The page is found by the `/Count` of the page tree nodes, so that only the nodes on the way to it are read.
+    pub fn with_page<R>(&self, page_number: u32, f: impl FnOnce(PageContext) -> R) -> Result<R> {
+        let mut remaining = page_number;
+        let page_id = self
+            .catalog()
+            .and_then(|catalog| catalog.get(b"Pages")?.as_reference())
+            .ok()
+            .filter(|_| page_number > 0)
+            .and_then(|root_id| self.find_page(root_id, &mut remaining, 0, &mut BTreeSet::new()))
+            .ok_or(Error::PageNumberNotFound(page_number))?;
+        Ok(f(self.load_page(page_id)?))
+    }
+
+    /// The page of a number, counted from 1 within a page tree node, skipping the nodes it is not in.
+    fn find_page(
+        &self, node_id: ObjectId, remaining: &mut u32, depth: usize, visited: &mut BTreeSet<ObjectId>,
+    ) -> Option<ObjectId> {
+        if depth > MAX_DEPTH || !visited.insert(node_id) {
+            return None;
+        }
+        let node = self.get_dictionary(node_id).ok()?;
+        match node.get(b"Type").and_then(ObjectRef::as_name) {
+            Ok(b"Page") if *remaining == 1 => Some(node_id),
+            Ok(b"Page") => {
+                *remaining -= 1;
+                None
+            }
+            Ok(b"Pages") => {
+                if let Ok(count) = node.get(b"Count").and_then(ObjectRef::as_i64) {
+                    if count >= 0 && count < i64::from(*remaining) {
+                        *remaining -= count as u32;
+                        return None;
+                    }
+                }
+                let kids = node.get(b"Kids").and_then(|kids| self.dereference(kids)).ok()?;
+                kids.as_array()
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|kid| kid.as_reference().ok())
+                    .find_map(|kid_id| self.find_page(kid_id, remaining, depth + 1, visited))
+            }
+            _ => None,
+        }
+    }
+
+    /// A document of a page alone, with its inherited attributes set in the page dictionary, its fonts in its
+    /// `/Resources`, and its objects under the same ids as in the file.
+    fn load_page(&self, page_id: ObjectId) -> Result<PageContext> {
+        let mut page = self.get_dictionary(page_id)?.to_dictionary();
+        let mut ancestor_id = page.get(b"Parent").and_then(Object::as_reference).ok();
+        for _ in 0..MAX_DEPTH {
+            let ancestor = match ancestor_id.map(|id| self.get_dictionary(id)) {
+                Some(Ok(ancestor)) => ancestor,
+                _ => break,
+            };
+            for key in [&b"Resources"[..], b"MediaBox", b"CropBox", b"Rotate"] {
+                if !page.has(key) {
+                    if let Ok(value) = ancestor.get(key) {
+                        page.set(key, value.to_object());
+                    }
+                }
+            }
+            ancestor_id = ancestor.get(b"Parent").and_then(ObjectRef::as_reference).ok();
+        }
+
+        let mut document = Document::with_version(self.version.clone());
+        let mut resources = match page.get(b"Resources") {
+            Ok(Object::Reference(id)) => self.get_dictionary(*id)?.to_dictionary(),
+            Ok(Object::Dictionary(resources)) => resources.clone(),
+            _ => Dictionary::new(),
+        };
+        let mut fonts = Dictionary::new();
+        for (name, font) in self.page_font_entries(page_id) {
+            if let ObjectRef::Reference(font_id) = font {
+                let font = self.get_dictionary(font_id)?;
+                if let Ok(to_unicode_id) = font.get(b"ToUnicode").and_then(ObjectRef::as_reference) {
+                    if let Ok(to_unicode) = self.get_object(to_unicode_id) {
+                        document.objects.insert(to_unicode_id, to_unicode.to_object());
+                    }
+                }
+                document
+                    .objects
+                    .insert(font_id, Object::Dictionary(font.to_dictionary()));
+            }
+            fonts.set(name, font.to_object());
+        }
+        resources.set("Font", fonts);
+        page.set("Resources", resources);
+
+        let content_ids = match page.get(b"Contents") {
+            Ok(Object::Reference(id)) => vec![*id],
+            Ok(Object::Array(contents)) => contents.iter().filter_map(|id| id.as_reference().ok()).collect(),
+            _ => Vec::new(),
+        };
+        for content_id in content_ids {
+            if let Ok(content) = self.get_object(content_id) {
+                document.objects.insert(content_id, content.to_object());
+            }
+        }
+
+        document.max_id = document
+            .objects
+            .keys()
+            .map(|id| id.0)
+            .chain(Some(page_id.0))
+            .max()
+            .unwrap_or(0);
+        let pages_id = document.new_object_id();
+        page.set("Parent", pages_id);
+        document.objects.insert(page_id, Object::Dictionary(page));
+        document.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! { "Type" => "Pages", "Kids" => vec![page_id.into()], "Count" => 1 }),
+        );
+        let catalog_id = document.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        document.trailer.set("Root", catalog_id);
+        Ok(PageContext { document, page_id })
+    }
+
+    /// A decoded object stream, from the cache or decoded and put in it.
+    fn object_stream(&self, container: u32) -> Result<Arc<DecodedObjectStream>> {
+        let current_thread = thread::current().id();
+        match self.object_streams.read().unwrap().get(&container) {
+            Some(CachedObjectStream::Decoded(stream)) => return Ok(stream.clone()),
+            Some(CachedObjectStream::Failed) => return Err(Error::not_found((container, 0))),
+            Some(CachedObjectStream::Decoding(thread_id)) if *thread_id == current_thread => {
+                return Err(Error::ReferenceLimit {
+                    chain: vec![(container, 0)],
+                });
+            }
+            _ => {}
+        }
+        self.object_streams
+            .write()
+            .unwrap()
+            .entry(container)
+            .or_insert(CachedObjectStream::Decoding(current_thread));
+
+        let span = span!(TRACE, "object_stream", object = ?(container, 0), objects = tracing::field::Empty);
+        let decoded = self.decode_object_stream(container).map(Arc::new);
+        if let Ok(ref stream) = decoded {
+            span.record("objects", stream.offsets.len());
+        }
+        span.exit();
+        let mut object_streams = self.object_streams.write().unwrap();
+        let cached = match decoded {
+            Ok(ref stream) => CachedObjectStream::Decoded(stream.clone()),
+            Err(ref err) => {
+                repair!(object = ?(container, 0), error = %err, "skipping object stream that cannot be read");
+                warn!("Object stream {} could not be read: {}", container, err);
+                CachedObjectStream::Failed
+            }
+        };
+        // Another thread may have decoded the stream meanwhile, which is then kept.
+        match object_streams.get(&container) {
+            Some(CachedObjectStream::Decoded(stream)) => return Ok(stream.clone()),
+            _ => object_streams.insert(container, cached),
+        };
+        decoded
+    }
+
+    fn decode_object_stream(&self, container: u32) -> Result<DecodedObjectStream> {
+        let object = self.get_object((container, 0))?;
+        let stream = object.as_stream()?;
+        let data = stream.decoded_content()?.into_owned();
+        let first = stream.dict.get(b"First").and_then(ObjectRef::as_i64)? as usize;
+        let header = str::from_utf8(data.get(..first).ok_or(Error::Offset(first))?)?;
+        let numbers: Vec<usize> = header
+            .split_whitespace()
+            .filter_map(|number| number.parse().ok())
+            .collect();
+        let mut offsets = BTreeMap::new();
+        for pair in numbers.chunks_exact(2) {
+            offsets.entry(pair[0] as u32).or_insert(first + pair[1]);
+        }
+        Ok(DecodedObjectStream { data, offsets })
+    }
+}
+
+fn collect_text(
+    text: &mut String, fonts: &FontCache, encoding: Option<&str>, to_unicode: Option<ObjectId>, operands: &[Object],
+) {
+    for operand in operands {
+        match operand {
+            Object::String(bytes, _) => text.push_str(&fonts.decode_with(encoding, to_unicode, bytes).0),
+            Object::Array(array) => collect_text(text, fonts, encoding, to_unicode, array),
+            _ => {}
+        }
+    }
+}
+
+/// A page loaded alone by [`DocumentView::with_page`].
+pub struct PageContext {
+    document: Document,
+    page_id: ObjectId,
+}
+
+impl PageContext {
+    /// The id of the page in the file.
+    pub fn page_id(&self) -> ObjectId {
+        self.page_id
+    }
+
+    /// The page dictionary, with the `/Resources`, `/MediaBox`, `/CropBox` and `/Rotate` it inherits.
+    pub fn page(&self) -> &Dictionary {
+        self.document.get_dictionary(self.page_id).unwrap()
+    }
+
+    /// A document of the objects loaded for the page, as its only page. References to other objects of the file,
+    /// such as images and annotations, are not resolved in it.
+    pub fn document(&self) -> &Document {
+        &self.document
+    }
+
+    pub fn fonts(&self) -> BTreeMap<Vec<u8>, &Dictionary> {
+        self.document.get_page_fonts(self.page_id)
+    }
+
+    /// The decoded content of the page.
+    pub fn get_content(&self) -> Result<Vec<u8>> {
+        self.document.get_page_content(self.page_id)
+    }
+
+    pub fn decode_content(&self) -> Result<Content> {
+        self.document.get_and_decode_page_content(self.page_id)
+    }
+
+    /// The text of the page, as [`Document::extract_text`](crate::Document::extract_text) gives it.
+    pub fn extract_text(&self) -> Result<String> {
+        self.document.extract_text(&[1])
+    }
+}
+
+/// A parser of objects borrowing from the input.
+struct Parser<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+fn is_white_space(byte: u8) -> bool {
+    matches!(byte, b' ' | b'\t' | b'\n' | b'\r' | b'\x0C' | b'\0')
+}
+
+fn is_regular(byte: u8) -> bool {
+    !is_white_space(byte) && !b"()<>[]{}/%".contains(&byte)
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self) -> Error {
+        Error::Parse {
+            offset: self.pos,
+            object: None,
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn skip_space(&mut self) {
+        while let Some(byte) = self.peek() {
+            if is_white_space(byte) {
+                self.pos += 1;
+            } else if byte == b'%' {
+                while self.peek().is_some_and(|byte| byte != b'\n' && byte != b'\r') {
+                    self.pos += 1;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// The regular characters up to the next delimiter, such as a keyword.
+    fn token(&mut self) -> &'a [u8] {
+        let start = self.pos;
+        while self.peek().is_some_and(is_regular) {
+            self.pos += 1;
+        }
+        &self.input[start..self.pos]
+    }
+
+    fn unsigned(&mut self) -> Option<u64> {
+        let start = self.pos;
+        while self.peek().is_some_and(|byte| byte.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        str::from_utf8(&self.input[start..self.pos]).ok()?.parse().ok()
+    }
+
+    fn object(&mut self, depth: usize) -> Result<ObjectRef<'a>> {
+        if depth > MAX_DEPTH {
+            return Err(Error::BracketLimit);
+        }
+        self.skip_space();
+        match self.peek().ok_or_else(|| self.error())? {
+            b'/' => Ok(ObjectRef::Name(self.name())),
+            b'(' => self.literal_string(),
+            b'<' if self.input.get(self.pos + 1) == Some(&b'<') => self.dictionary(depth).map(ObjectRef::Dictionary),
+            b'<' => self.hexadecimal_string(),
+            b'[' => {
+                self.pos += 1;
+                let mut array = Vec::new();
+                loop {
+                    self.skip_space();
+                    match self.peek() {
+                        Some(b']') => {
+                            self.pos += 1;
+                            return Ok(ObjectRef::Array(array));
+                        }
+                        Some(_) => array.push(self.object(depth + 1)?),
+                        None => return Err(self.error()),
+                    }
+                }
+            }
+            b'+' | b'-' | b'.' | b'0'..=b'9' => self.number_or_reference(),
+            _ => match self.token() {
+                b"true" => Ok(ObjectRef::Boolean(true)),
+                b"false" => Ok(ObjectRef::Boolean(false)),
+                b"null" => Ok(ObjectRef::Null),
+                _ => Err(self.error()),
+            },
+        }
+    }
+
+    fn name(&mut self) -> Cow<'a, [u8]> {
+        self.pos += 1;
+        let name = self.token();
+        if !name.contains(&b'#') {
+            return Cow::Borrowed(name);
+        }
+        let mut decoded = Vec::with_capacity(name.len());
+        let mut i = 0;
+        while i < name.len() {
+            let escaped = name
+                .get(i + 1..i + 3)
+                .filter(|_| name[i] == b'#')
+                .and_then(|digits| u8::from_str_radix(str::from_utf8(digits).ok()?, 16).ok());
+            match escaped {
+                Some(byte) => {
+                    decoded.push(byte);
+                    i += 3;
+                }
+                None => {
+                    decoded.push(name[i]);
+                    i += 1;
+                }
+            }
+        }
+        Cow::Owned(decoded)
+    }
+
+    fn literal_string(&mut self) -> Result<ObjectRef<'a>> {
+        let start = self.pos + 1;
+        let mut depth = 0;
+        let mut escaped = false;
+        while let Some(byte) = self.peek() {
+            match byte {
+                b'\\' => {
+                    escaped = true;
+                    self.pos += 1;
+                }
+                b'(' => {
+                    depth += 1;
+                    if depth > MAX_BRACKET {
+                        return Err(Error::BracketLimit);
+                    }
+                }
+                b')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        let raw = &self.input[start..self.pos];
+                        self.pos += 1;
+                        let text = if escaped {
+                            Cow::Owned(unescape(raw))
+                        } else {
+                            Cow::Borrowed(raw)
+                        };
+                        return Ok(ObjectRef::String(text, StringFormat::Literal));
+                    }
+                }
+                _ => {}
+            }
+            self.pos += 1;
+        }
+        Err(self.error())
+    }
+
+    fn hexadecimal_string(&mut self) -> Result<ObjectRef<'a>> {
+        let start = self.pos + 1;
+        let end = self.input[start..]
+            .iter()
+            .position(|&byte| byte == b'>')
+            .map(|end| start + end)
+            .ok_or_else(|| self.error())?;
+        self.pos = end + 1;
+        let digits: Vec<u8> = self.input[start..end]
+            .iter()
+            .filter_map(|&digit| char::from(digit).to_digit(16).map(|digit| digit as u8))
+            .collect();
+        let bytes = digits
+            .chunks(2)
+            .map(|pair| pair[0] << 4 | pair.get(1).copied().unwrap_or(0))
+            .collect();
+        Ok(ObjectRef::String(Cow::Owned(bytes), StringFormat::Hexadecimal))
+    }
+
+    fn dictionary(&mut self, depth: usize) -> Result<DictionaryRef<'a>> {
+        self.pos += 2;
+        let mut entries = Vec::new();
+        loop {
+            self.skip_space();
+            match self.peek() {
+                Some(b'>') if self.input.get(self.pos + 1) == Some(&b'>') => {
+                    self.pos += 2;
+                    return Ok(DictionaryRef(entries));
+                }
+                Some(b'/') => {
+                    let key = self.name();
+                    entries.push((key, self.object(depth + 1)?));
+                }
+                _ => return Err(self.error()),
+            }
+        }
+    }
+
+    fn number_or_reference(&mut self) -> Result<ObjectRef<'a>> {
+        let start = self.pos;
+        if matches!(self.peek(), Some(b'+') | Some(b'-')) {
+            self.pos += 1;
+        }
+        while self.peek().is_some_and(|byte| byte.is_ascii_digit() || byte == b'.') {
+            self.pos += 1;
+        }
+        let number = str::from_utf8(&self.input[start..self.pos]).map_err(|_| self.error())?;
+        if number.contains('.') {
+            return number.parse().map(ObjectRef::Real).map_err(|_| self.error());
+        }
+        let integer: i64 = number.parse().map_err(|_| self.error())?;
+
+        // An object number is followed by its generation and `R`.
+        let after_integer = self.pos;
+        if let (Ok(number), true) = (u32::try_from(integer), self.input[start].is_ascii_digit()) {
+            self.skip_space();
+            if let Some(generation) = self.unsigned().and_then(|generation| u16::try_from(generation).ok()) {
+                self.skip_space();
+                if self.token() == b"R" {
+                    return Ok(ObjectRef::Reference((number, generation)));
+                }
+            }
+        }
+        self.pos = after_integer;
+        Ok(ObjectRef::Integer(integer))
+    }
+
+    /// An indirect object, whose stream content is as long as its `/Length`, which may be an object of the view.
+    fn indirect_object(&mut self, view: &DocumentView<'a>) -> Result<(ObjectId, ObjectRef<'a>)> {
+        self.skip_space();
+        let number = self.unsigned().and_then(|number| u32::try_from(number).ok());
+        self.skip_space();
+        let generation = self.unsigned().and_then(|generation| u16::try_from(generation).ok());
+        self.skip_space();
+        let id = match (number, generation, self.token()) {
+            (Some(number), Some(generation), b"obj") => (number, generation),
+            _ => return Err(self.error()),
+        };
+        let object = self.object(0)?;
+        let dict = match object {
+            ObjectRef::Dictionary(dict) => dict,
+            object => return Ok((id, object)),
+        };
+
+        let after_dict = self.pos;
+        self.skip_space();
+        if self.token() != b"stream" {
+            self.pos = after_dict;
+            return Ok((id, ObjectRef::Dictionary(dict)));
+        }
+        if self.peek() == Some(b'\r') {
+            self.pos += 1;
+        }
+        if self.peek() == Some(b'\n') {
+            self.pos += 1;
+        }
+        let length = match dict.get(b"Length")? {
+            ObjectRef::Reference(length_id) if *length_id != id => view.get_object(*length_id)?.as_i64()?,
+            length => length.as_i64()?,
+        };
+        let end = usize::try_from(length)
+            .ok()
+            .and_then(|length| self.pos.checked_add(length))
+            .filter(|&end| end <= self.input.len())
+            .ok_or_else(|| Error::Syntax("Stream extends after document end.".to_string()))?;
+        let content = &self.input[self.pos..end];
+        self.pos = end;
+        Ok((id, ObjectRef::Stream(StreamRef { dict, content })))
+    }
+}
+
+/// The bytes of a literal string between its parentheses, with its escape sequences decoded.
+fn unescape(raw: &[u8]) -> Vec<u8> {
+    let mut text = Vec::with_capacity(raw.len());
+    let mut i = 0;
+    while i < raw.len() {
+        if raw[i] != b'\\' {
+            text.push(raw[i]);
+            i += 1;
+            continue;
+        }
+        i += 1;
+        match raw.get(i) {
+            Some(b'n') => text.push(b'\n'),
+            Some(b'r') => text.push(b'\r'),
+            Some(b't') => text.push(b'\t'),
+            Some(b'b') => text.push(b'\x08'),
+            Some(b'f') => text.push(b'\x0C'),
+            Some(b'0'..=b'7') => {
+                let digits = raw[i..]
+                    .iter()
+                    .take(3)
+                    .take_while(|digit| (b'0'..=b'7').contains(digit))
+                    .count();
+                let value = raw[i..i + digits]
+                    .iter()
+                    .fold(0u16, |value, digit| value * 8 + u16::from(digit - b'0'));
+                text.push(value as u8);
+                i += digits;
+                continue;
+            }
+            // A backslash at the end of a line continues the string on the next line.
+            Some(b'\r') if raw.get(i + 1) == Some(&b'\n') => i += 1,
+            Some(b'\r') | Some(b'\n') => {}
+            Some(&byte) => text.push(byte),
+            None => {}
+        }
+        i += 1;
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::creator::tests::create_document;
+
+    #[test]
+    fn view_saved_document() {
+        let mut doc = create_document();
+        let page_id = doc.page_iter().next().unwrap();
+        let content_id = doc.get_page_contents(page_id)[0];
+        doc.get_object_mut(content_id)
+            .and_then(Object::as_stream_mut)
+            .unwrap()
+            .compress()
+            .unwrap();
+        let note = doc.add_object(dictionary! {
+            "A B" => Object::string_literal("Tab\tand (nested) \\ backslash"),
+            "Plain" => Object::string_literal("Created (here)"),
+            "Values" => vec![1.into(), (-2.5).into(), Object::Reference(page_id), true.into(), Object::Null],
+        });
+        let mut buffer = Vec::new();
+        doc.save_to(&mut buffer).unwrap();
+        let loaded = Document::load_mem(&buffer).unwrap();
+
+        let view = DocumentView::load(&buffer).unwrap();
+        assert_eq!(view.get_pages(), loaded.get_pages());
+        assert_eq!(view.extract_text(&[1]).unwrap(), loaded.extract_text(&[1]).unwrap());
+        assert!(view.extract_text(&[2]).is_err());
+
+        let note = view.get_dictionary(note).unwrap();
+        assert_eq!(
+            note.get(b"A B").and_then(ObjectRef::as_str).unwrap(),
+            b"Tab\tand (nested) \\ backslash"
+        );
+        assert!(matches!(
+            note.get(b"Plain"),
+            Ok(ObjectRef::String(Cow::Borrowed(b"Created (here)"), _))
+        ));
+        let values = note.get(b"Values").and_then(ObjectRef::as_array).unwrap();
+        assert_eq!(values[1].as_f64().unwrap(), -2.5);
+        assert_eq!(values[2].as_reference().unwrap(), page_id);
+        assert!(values[3].as_bool().unwrap());
+
+        // The content of streams is a slice of the buffer, and decoding it gives that of the loaded document.
+        let content = view.get_object(content_id).unwrap();
+        let stream = content.as_stream().unwrap();
+        assert!(buffer.as_ptr_range().contains(&stream.content.as_ptr()));
+        assert_eq!(
+            *stream.decoded_content().unwrap(),
+            loaded.get_page_content(page_id).unwrap()[..]
+        );
+        assert_eq!(
+            Object::from(content).as_stream().unwrap().content,
+            loaded
+                .get_object(content_id)
+                .and_then(Object::as_stream)
+                .unwrap()
+                .content
+        );
+    }
+
+    #[test]
+    fn page_loaded_alone() {
+        let mut buffer = Vec::new();
+        create_document().save_to(&mut buffer).unwrap();
+        let loaded = Document::load_mem(&buffer).unwrap();
+        let view = DocumentView::load(&buffer).unwrap();
+
+        let page_id = loaded.page_iter().next().unwrap();
+        let (text, media_box, fonts, objects) = view
+            .with_page(1, |page| {
+                assert_eq!(page.page_id(), page_id);
+                let media_box = page.page().get(b"MediaBox").and_then(Object::as_array).unwrap().len();
+                let fonts: Vec<Vec<u8>> = page.fonts().keys().cloned().collect();
+                (
+                    page.extract_text().unwrap(),
+                    media_box,
+                    fonts,
+                    page.document().objects.len(),
+                )
+            })
+            .unwrap();
+        assert_eq!(text, loaded.extract_text(&[1]).unwrap());
+        assert_eq!(media_box, 4);
+        assert_eq!(fonts, vec![b"F1".to_vec()]);
+        // The page, its content, its font, and the page tree node and catalog of the page alone.
+        assert_eq!(objects, 5);
+        assert!(view.with_page(0, |_| ()).is_err());
+        assert!(view.with_page(2, |_| ()).is_err());
+    }
+
+    /// A file whose catalog and page tree node are in the object stream 1, of which the /Length is the page tree
+    /// node itself if `length_in_itself`.
+    fn object_stream_file(length_in_itself: bool) -> Vec<u8> {
+        let objects = [
+            &b"<< /Type /Catalog /Pages 3 0 R >>"[..],
+            b"<< /Type /Pages /Kids [] /Count 0 >>",
+        ];
+        let header = format!("2 0 3 {} ", objects[0].len() + 1);
+        let content = [header.as_bytes(), objects[0], b" ", objects[1]].concat();
+        let length = if length_in_itself {
+            "3 0 R".to_string()
+        } else {
+            content.len().to_string()
+        };
+        let mut file = b"%PDF-1.5\n".to_vec();
+        let object_stream = file.len();
+        file.extend_from_slice(
+            format!(
+                "1 0 obj\n<< /Type /ObjStm /N 2 /First {} /Length {} >>\nstream\n",
+                header.len(),
+                length
+            )
+            .as_bytes(),
+        );
+        file.extend_from_slice(&content);
+        file.extend_from_slice(b"\nendstream\nendobj\n");
+        let xref = file.len();
+        let entry = |kind: u8, value: usize, index: u8| vec![kind, (value >> 8) as u8, value as u8, index];
+        let entries = [
+            entry(0, 0, 255),
+            entry(1, object_stream, 0),
+            entry(2, 1, 0),
+            entry(2, 1, 1),
+            entry(1, xref, 0),
+        ]
+        .concat();
+        file.extend_from_slice(
+            format!(
+                "4 0 obj\n<< /Type /XRef /Size 5 /W [1 2 1] /Root 2 0 R /Length {} >>\nstream\n",
+                entries.len()
+            )
+            .as_bytes(),
+        );
+        file.extend_from_slice(&entries);
+        file.extend_from_slice(format!("\nendstream\nendobj\nstartxref\n{}\n%%EOF\n", xref).as_bytes());
+        file
+    }
+
+    #[test]
+    fn view_object_streams() {
+        let file = object_stream_file(false);
+        let view = DocumentView::load(&file).unwrap();
+        let catalog = view.catalog().unwrap();
+        assert_eq!(catalog.get(b"Type").and_then(ObjectRef::as_name).unwrap(), b"Catalog");
+        assert_eq!(
+            view.get_dictionary((3, 0))
+                .unwrap()
+                .get(b"Count")
+                .unwrap()
+                .as_i64()
+                .unwrap(),
+            0
+        );
+        assert!(view.get_pages().is_empty());
+
+        // The object stream is decoded once for both objects, and kept for all threads.
+        fn shared<T: Sync>(_: &T) {}
+        shared(&view);
+        let object_streams = view.object_streams.read().unwrap();
+        assert_eq!(object_streams.len(), 1);
+        let stream = match object_streams.get(&1) {
+            Some(CachedObjectStream::Decoded(stream)) => stream,
+            _ => panic!("object stream not decoded"),
+        };
+        assert_eq!(Arc::strong_count(stream), 1);
+        assert_eq!(stream.offsets.keys().copied().collect::<Vec<u32>>(), [2, 3]);
+        drop(object_streams);
+        let preloaded = DocumentView::load(&file).unwrap();
+        preloaded.preload_object_streams();
+        assert_eq!(preloaded.object_streams.read().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn object_stream_cache() {
+        let file = object_stream_file(false);
+        let view = DocumentView::load(&file).unwrap();
+        let first = view.object_stream(1).unwrap();
+        assert!(Arc::ptr_eq(&first, &view.object_stream(1).unwrap()));
+        // Threads looking objects up at the same time share the stream decoded first.
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    assert_eq!(view.catalog().unwrap().len(), 2);
+                    assert!(Arc::ptr_eq(&first, &view.object_stream(1).unwrap()));
+                });
+            }
+        });
+        assert!(view.object_stream(4).is_err());
+        assert!(matches!(
+            view.object_streams.read().unwrap().get(&4),
+            Some(CachedObjectStream::Failed)
+        ));
+
+        // The length of the stream is one of its objects, so decoding it asks for itself, and fails once for all.
+        let file = object_stream_file(true);
+        let view = DocumentView::load(&file).unwrap();
+        assert!(matches!(view.get_object((2, 0)), Err(Error::ReferenceLimit { .. })));
+        assert!(matches!(
+            view.object_streams.read().unwrap().get(&1),
+            Some(CachedObjectStream::Failed)
+        ));
+        assert!(matches!(view.get_object((3, 0)), Err(Error::ObjectNotFound { .. })));
+    }
+}
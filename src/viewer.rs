@@ -0,0 +1,339 @@
+use crate::{Dictionary, Document, Error, Object, Result, StringFormat};
+use log::warn;
+
+/// Implement the conversion of an enum from and to the names it stands for.
+macro_rules! names {
+    ($type:ident { $($variant:ident => $name:literal,)* }) => {
+        impl $type {
+            fn name(self) -> &'static str {
+                match self {
+                    $($type::$variant => $name,)*
+                }
+            }
+
+            fn from_name(name: &[u8]) -> Option<$type> {
+                $(if name == $name.as_bytes() {
+                    return Some($type::$variant);
+                })*
+                None
+            }
+        }
+    };
+}
+
+/// `/PageMode` of the catalog: how the document is shown when opened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageMode {
+    UseNone,
+    /// With the bookmarks panel open.
+    UseOutlines,
+    /// With the thumbnails panel open.
+    UseThumbs,
+    FullScreen,
+    /// With the layers panel open.
+    UseOC,
+    /// With the attachments panel open.
+    UseAttachments,
+}
+
+names!(PageMode {
+    UseNone => "UseNone",
+    UseOutlines => "UseOutlines",
+    UseThumbs => "UseThumbs",
+    FullScreen => "FullScreen",
+    UseOC => "UseOC",
+    UseAttachments => "UseAttachments",
+});
+
+/// `/PageLayout` of the catalog: how pages are arranged when the document is opened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageLayout {
+    SinglePage,
+    OneColumn,
+    /// Two columns, odd pages on the left.
+    TwoColumnLeft,
+    /// Two columns, odd pages on the right.
+    TwoColumnRight,
+    /// Two pages at a time, odd pages on the left.
+    TwoPageLeft,
+    /// Two pages at a time, odd pages on the right.
+    TwoPageRight,
+}
+
+names!(PageLayout {
+    SinglePage => "SinglePage",
+    OneColumn => "OneColumn",
+    TwoColumnLeft => "TwoColumnLeft",
+    TwoColumnRight => "TwoColumnRight",
+    TwoPageLeft => "TwoPageLeft",
+    TwoPageRight => "TwoPageRight",
+});
+
+/// `/Duplex` of the viewer preferences, for the print dialog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Duplex {
+    Simplex,
+    FlipShortEdge,
+    FlipLongEdge,
+}
+
+names!(Duplex {
+    Simplex => "Simplex",
+    FlipShortEdge => "DuplexFlipShortEdge",
+    FlipLongEdge => "DuplexFlipLongEdge",
+});
+
+/// `/PrintScaling` of the viewer preferences, for the print dialog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrintScaling {
+    /// Print at the actual size.
+    None,
+    AppDefault,
+}
+
+names!(PrintScaling {
+    None => "None",
+    AppDefault => "AppDefault",
+});
+
+/// `/Direction` of the viewer preferences: the reading order of the text, which sets that of pages side by side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    LeftToRight,
+    RightToLeft,
+}
+
+names!(Direction {
+    LeftToRight => "L2R",
+    RightToLeft => "R2L",
+});
+
+/// The `/ViewerPreferences` of the catalog, from [`Document::viewer_preferences`]. Entries that are missing or of
+/// the wrong type are `None`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ViewerPreferences {
+    pub hide_toolbar: Option<bool>,
+    /// Whether to resize the window to fit the first page.
+    pub fit_window: Option<bool>,
+    /// Whether the window shows the title of the document rather than its file name.
+    pub display_doc_title: Option<bool>,
+    pub duplex: Option<Duplex>,
+    pub print_scaling: Option<PrintScaling>,
+    pub direction: Option<Direction>,
+}
+
+impl Document {
+    /// The `/ViewerPreferences` of the catalog.
+    pub fn viewer_preferences(&self) -> ViewerPreferences {
+        let preferences = match self
+            .catalog()
+            .and_then(|catalog| catalog.get_deref(b"ViewerPreferences", self))
+            .and_then(Object::as_dict)
+        {
+            Ok(preferences) => preferences,
+            Err(_) => return ViewerPreferences::default(),
+        };
+        let flag = |key: &[u8]| preferences.get_deref(key, self).and_then(Object::as_bool).ok();
+        let name = |key: &[u8]| preferences.get_deref(key, self).and_then(Object::as_name).ok();
+        ViewerPreferences {
+            hide_toolbar: flag(b"HideToolbar"),
+            fit_window: flag(b"FitWindow"),
+            display_doc_title: flag(b"DisplayDocTitle"),
+            duplex: name(b"Duplex").and_then(Duplex::from_name),
+            print_scaling: name(b"PrintScaling").and_then(PrintScaling::from_name),
+            direction: name(b"Direction").and_then(Direction::from_name),
+        }
+    }
+
+    /// Set the entries of the `/ViewerPreferences` of the catalog that are not `None`, keeping the others. The
+    /// dictionary is added if the catalog has none.
+    pub fn set_viewer_preferences(&mut self, preferences: ViewerPreferences) -> Result<()> {
+        let dict = self.viewer_preferences_mut()?;
+        let flags = [
+            ("HideToolbar", preferences.hide_toolbar),
+            ("FitWindow", preferences.fit_window),
+            ("DisplayDocTitle", preferences.display_doc_title),
+        ];
+        for (key, flag) in flags {
+            if let Some(flag) = flag {
+                dict.set(key, flag);
+            }
+        }
+        let names = [
+            ("Duplex", preferences.duplex.map(Duplex::name)),
+            ("PrintScaling", preferences.print_scaling.map(PrintScaling::name)),
+            ("Direction", preferences.direction.map(Direction::name)),
+        ];
+        for (key, name) in names {
+            if let Some(name) = name {
+                dict.set(key, name);
+            }
+        }
+        Ok(())
+    }
+
+    /// The `/PageMode` of the catalog.
+    pub fn page_mode(&self) -> Option<PageMode> {
+        let mode = self.catalog().ok()?.get_deref(b"PageMode", self).ok()?;
+        PageMode::from_name(mode.as_name().ok()?)
+    }
+
+    pub fn set_page_mode(&mut self, mode: PageMode) -> Result<()> {
+        self.catalog_mut()?.set("PageMode", mode.name());
+        Ok(())
+    }
+
+    /// The `/PageLayout` of the catalog.
+    pub fn page_layout(&self) -> Option<PageLayout> {
+        let layout = self.catalog().ok()?.get_deref(b"PageLayout", self).ok()?;
+        PageLayout::from_name(layout.as_name().ok()?)
+    }
+
+    pub fn set_page_layout(&mut self, layout: PageLayout) -> Result<()> {
+        self.catalog_mut()?.set("PageLayout", layout.name());
+        Ok(())
+    }
+
+    /// The `/Lang` of the catalog, the natural language of the text of the document.
+    pub fn language(&self) -> Option<String> {
+        let language = self.catalog().ok()?.get_deref(b"Lang", self).ok()?;
+        Some(crate::encodings::decode_text_string(language.as_str().ok()?))
+    }
+
+    /// Set the `/Lang` of the catalog to a language tag, such as `de-DE`, checking it has the shape of a BCP 47
+    /// tag: a language of letters, then subtags of one to eight letters or digits, separated by hyphens.
+    pub fn set_language(&mut self, language: &str) -> Result<()> {
+        if !is_language_tag(language) {
+            return Err(Error::Syntax(format!("invalid language tag {:?}", language)));
+        }
+        self.catalog_mut()?.set(
+            "Lang",
+            Object::String(language.as_bytes().to_vec(), StringFormat::Literal),
+        );
+        Ok(())
+    }
+
+    /// Set what PDF/UA requires of the catalog for a tagged document: `/Lang`, `/DisplayDocTitle` in the viewer
+    /// preferences, and `/Marked` in `/MarkInfo`.
+    ///
+    /// The document should also have a title in its metadata, which is warned about if it is missing, and a
+    /// structure tree, which is left to the caller.
+    pub fn prepare_for_accessibility(&mut self, language: &str) -> Result<()> {
+        self.set_language(language)?;
+        self.set_viewer_preferences(ViewerPreferences {
+            display_doc_title: Some(true),
+            ..Default::default()
+        })?;
+        self.set_mark_info(crate::MarkInfo {
+            marked: Some(true),
+            ..Default::default()
+        })?;
+        if self.metadata().title.is_none() {
+            warn!("Document has no title to display");
+        }
+        Ok(())
+    }
+
+    /// The `/ViewerPreferences` of the catalog, added if missing or of the wrong type.
+    fn viewer_preferences_mut(&mut self) -> Result<&mut Dictionary> {
+        let preferences_id = match self.catalog()?.get(b"ViewerPreferences") {
+            Ok(Object::Reference(id)) if self.get_dictionary(*id).is_ok() => Some(*id),
+            Ok(Object::Dictionary(_)) => None,
+            _ => {
+                self.catalog_mut()?.set("ViewerPreferences", Dictionary::new());
+                None
+            }
+        };
+        match preferences_id {
+            Some(id) => self.get_object_mut(id)?.as_dict_mut(),
+            None => self.catalog_mut()?.get_mut(b"ViewerPreferences")?.as_dict_mut(),
+        }
+    }
+}
+
+/// Whether a language tag has the shape of a BCP 47 tag, including private use (`x-…`) and grandfathered
+/// (`i-…`) tags.
+fn is_language_tag(tag: &str) -> bool {
+    let mut subtags = tag.split('-');
+    let language = subtags.next().unwrap_or_default();
+    let language_valid = (2..=8).contains(&language.len()) && language.bytes().all(|b| b.is_ascii_alphabetic())
+        || language.eq_ignore_ascii_case("x")
+        || language.eq_ignore_ascii_case("i");
+    language_valid
+        && subtags.all(|subtag| (1..=8).contains(&subtag.len()) && subtag.bytes().all(|b| b.is_ascii_alphanumeric()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::creator::tests::create_document;
+
+    #[test]
+    fn merge_viewer_preferences() {
+        let mut doc = create_document();
+        assert_eq!(doc.viewer_preferences(), ViewerPreferences::default());
+        doc.catalog_mut().unwrap().set(
+            "ViewerPreferences",
+            dictionary! { "NonFullScreenPageMode" => "UseOutlines", "FitWindow" => true },
+        );
+        doc.set_viewer_preferences(ViewerPreferences {
+            hide_toolbar: Some(true),
+            duplex: Some(Duplex::FlipLongEdge),
+            direction: Some(Direction::RightToLeft),
+            ..Default::default()
+        })
+        .unwrap();
+        let preferences = doc.viewer_preferences();
+        assert_eq!(preferences.hide_toolbar, Some(true));
+        assert_eq!(preferences.fit_window, Some(true));
+        assert_eq!(preferences.duplex, Some(Duplex::FlipLongEdge));
+        assert_eq!(preferences.direction, Some(Direction::RightToLeft));
+        let dict = doc
+            .catalog()
+            .unwrap()
+            .get(b"ViewerPreferences")
+            .and_then(Object::as_dict)
+            .unwrap();
+        assert_eq!(
+            dict.get(b"Duplex").and_then(Object::as_name_str).unwrap(),
+            "DuplexFlipLongEdge"
+        );
+        assert!(dict.has(b"NonFullScreenPageMode"));
+
+        doc.set_page_mode(PageMode::UseOutlines).unwrap();
+        doc.set_page_layout(PageLayout::TwoPageRight).unwrap();
+        assert_eq!(doc.page_mode(), Some(PageMode::UseOutlines));
+        assert_eq!(doc.page_layout(), Some(PageLayout::TwoPageRight));
+    }
+
+    #[test]
+    fn language_tags() {
+        for tag in [
+            "de-DE",
+            "en",
+            "zh-Hant-TW",
+            "sr-Latn-RS",
+            "x-klingon",
+            "i-navajo",
+            "es-419",
+        ] {
+            assert!(is_language_tag(tag), "{}", tag);
+        }
+        for tag in ["", "d", "de_DE", "de-", "german language", "12-DE", "de-toolongsubtag"] {
+            assert!(!is_language_tag(tag), "{}", tag);
+        }
+        let mut doc = create_document();
+        assert!(doc.set_language("en_US").is_err());
+        assert_eq!(doc.language(), None);
+        doc.prepare_for_accessibility("de-DE").unwrap();
+        assert_eq!(doc.language().as_deref(), Some("de-DE"));
+        assert_eq!(doc.viewer_preferences().display_doc_title, Some(true));
+        let mark_info = doc
+            .catalog()
+            .unwrap()
+            .get(b"MarkInfo")
+            .and_then(Object::as_dict)
+            .unwrap();
+        assert!(mark_info.get(b"Marked").and_then(Object::as_bool).unwrap());
+    }
+}
@@ -1,55 +1,217 @@
+#[cfg(all(feature = "std", not(all(target_arch = "wasm32", target_os = "unknown"))))]
 use std::fs::File;
-use std::io::{BufWriter, Result, Write};
+#[cfg(all(feature = "std", not(all(target_arch = "wasm32", target_os = "unknown"))))]
+use std::io::BufWriter;
+#[cfg(all(feature = "std", not(all(target_arch = "wasm32", target_os = "unknown"))))]
 use std::path::Path;
 
 use super::Object::*;
-use super::{Dictionary, Document, Object, Stream, StringFormat};
+use super::{Dictionary, Object, Stream, StringFormat};
+#[cfg(feature = "std")]
+use super::{Document, KnownName, ObjectId};
+use crate::io::{Error, ErrorKind, Result, Write};
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
 use crate::xref::*;
-use byteorder::{BigEndian, WriteBytesExt};
 
+#[cfg(feature = "std")]
 impl Document {
     /// Save PDF document to specified file path.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
     #[inline]
     pub fn save<P: AsRef<Path>>(&mut self, path: P) -> Result<File> {
-        let mut file = BufWriter::new(File::create(path)?);
-        self.save_internal(&mut file)?;
-        Ok(file.into_inner()?)
+        self.save_with_options(path, SaveOptions::default())
     }
 
     /// Save PDF to arbitrary target
     #[inline]
     pub fn save_to<W: Write>(&mut self, target: &mut W) -> Result<()> {
-        self.save_internal(target)
+        self.save_to_with_options(target, SaveOptions::default())
+    }
+
+    /// Save the document to the specified file path, calling the [hooks](SaveHooks) of the options.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn save_with_options<P: AsRef<Path>>(&mut self, path: P, mut options: SaveOptions) -> Result<File> {
+        let mut file = BufWriter::new(File::create(path)?);
+        self.save_internal(&mut file, &mut options.hooks)?;
+        Ok(file.into_inner()?)
+    }
+
+    /// Save the document to an arbitrary target, calling the [hooks](SaveHooks) of the options.
+    pub fn save_to_with_options<W: Write>(&mut self, target: &mut W, mut options: SaveOptions) -> Result<()> {
+        self.save_internal(target, &mut options.hooks).map(|_| ())
+    }
+
+    /// Save the document as an incremental update of the file it was loaded from: the file unchanged, followed
+    /// by the objects that were added or changed and a cross-reference section linked to the previous ones, so
+    /// that existing signatures stay valid. Removed objects are left in the earlier revision. A document
+    /// created in memory is saved in full; a document loaded without keeping its file (see
+    /// [`ParseOptions::keep_source`](crate::ParseOptions::keep_source)) is refused with [`Error::SourceNotKept`].
+    ///
+    /// The changes are checked against the [modification policy](Document::modification_policy) of the file:
+    /// an update that its certification or field locks forbid is refused with [`Error::Signature`], and one that
+    /// viewers report, or that invalidates usage rights, is logged.
+    ///
+    /// [`Error::Signature`]: crate::Error::Signature
+    /// [`Error::SourceNotKept`]: crate::Error::SourceNotKept
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    pub fn save_incremental_to<W: Write>(&mut self, target: &mut W) -> crate::Result<()> {
+        let update = self.incremental_update(true)?;
+        target.write_all(&update.data)?;
+        Ok(())
+    }
+
+    /// Save the document as an incremental update to the specified file path, see
+    /// [`save_incremental_to`](Document::save_incremental_to).
+    #[cfg(all(any(feature = "pom_parser", feature = "nom_parser"), not(all(target_arch = "wasm32", target_os = "unknown"))))]
+    pub fn save_incremental<P: AsRef<Path>>(&mut self, path: P) -> crate::Result<File> {
+        let mut file = BufWriter::new(File::create(path)?);
+        self.save_incremental_to(&mut file)?;
+        Ok(file.into_inner().map_err(|err| err.into_error())?)
+    }
+
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    pub(crate) fn incremental_update(&mut self, check_policy: bool) -> crate::Result<FileUpdate> {
+        let span = span!(INFO, "save_incremental", objects = tracing::field::Empty, bytes = tracing::field::Empty);
+        let mut data = Vec::new();
+        if self.source.is_empty() && !self.revision_ends.is_empty() {
+            return Err(crate::Error::SourceNotKept);
+        }
+        if self.source.is_empty() {
+            let (xref, contents, xref_start) = self.save_internal(&mut data, &mut SaveHooks::default())?;
+            return Ok(FileUpdate {
+                data,
+                xref,
+                contents,
+                xref_start,
+            });
+        }
+
+        let original = Document::load_mem(&self.source)?;
+        if check_policy {
+            self.check_update(&original)?;
+        }
+        self.update_mod_date();
+        self.update_file_id(&original.trailer)?;
+        // The objects are compared by their serialization, into buffers reused from one object to the next.
+        let (mut original_bytes, mut bytes) = (Vec::new(), Vec::new());
+        let serialize = |object: &Object, bytes: &mut Vec<u8>| -> Result<()> {
+            bytes.clear();
+            let mut target = CountingWrite {
+                inner: bytes,
+                bytes_written: 0,
+            };
+            Writer::write_object(&mut target, object, None, None)?;
+            Ok(())
+        };
+        data.extend_from_slice(&self.source);
+        if !data.ends_with(b"\n") {
+            data.push(b'\n');
+        }
+        let start = data.len();
+        let mut target = CountingWrite {
+            inner: &mut data,
+            bytes_written: start as u64,
+        };
+        let mut xref = Xref::new(self.max_id + 1);
+        let mut contents_map = Some(BTreeMap::new());
+        for (&oid, object) in &self.objects {
+            if is_cross_reference_object(object) {
+                continue;
+            }
+            let changed = match original.objects.get(&oid) {
+                Some(original) => {
+                    serialize(original, &mut original_bytes)?;
+                    serialize(object, &mut bytes)?;
+                    original_bytes != bytes
+                }
+                None => true,
+            };
+            if changed {
+                contents_map = Writer::write_indirect_object(&mut target, oid, object, &mut xref, contents_map)?;
+            }
+        }
+
+        let xref_start = target.bytes_written;
+        Writer::write_xref(&mut target, &xref)?;
+        let mut trailer = self.trailer.clone();
+        // The keys describing a cross-reference stream do not carry over to a cross-reference table.
+        for key in [&b"Type"[..], b"W", b"Index", b"Length", b"Filter", b"DecodeParms", b"XRefStm"] {
+            trailer.remove(key);
+        }
+        trailer.set("Size", i64::from(self.max_id.max(original.max_id) + 1));
+        trailer.set("Prev", self.reference_table_start as i64);
+        target.write_all(b"trailer\n")?;
+        Writer::write_dictionary(&mut target, &trailer, None, None)?;
+        write!(target, "\nstartxref\n{}\n%%EOF", xref_start)?;
+        span.record("objects", xref.entries.len());
+        span.record("bytes", data.len() - start);
+        Ok(FileUpdate {
+            data,
+            xref,
+            contents: contents_map.unwrap_or_default(),
+            xref_start,
+        })
     }
 
-    fn save_internal<W: Write>(&mut self, target: &mut W) -> Result<()> {
+    fn save_internal<W: Write>(
+        &mut self, target: &mut W, hooks: &mut SaveHooks,
+    ) -> Result<(Xref, ContentOffsets, u64)> {
+        let span = span!(INFO, "save", objects = self.objects.len(), bytes = tracing::field::Empty);
         let mut target = CountingWrite {
             inner: target,
             bytes_written: 0,
         };
 
+        self.update_mod_date();
+        self.permanent_file_id();
         let mut xref = Xref::new(self.max_id + 1);
         writeln!(target, "%PDF-{}", self.version)?;
 
-        let mut contents_map = Some(std::collections::btree_map::BTreeMap::<crate::ObjectId, (u32, u32)>::new());
+        let mut contents_map = Some(BTreeMap::<ObjectId, (u64, u64)>::new());
 
+        let objects_span = span!("write_objects", bytes = tracing::field::Empty);
         for (&oid, object) in &self.objects {
-            if object
-                .type_name()
-                .map(|name| ["ObjStm", "XRef", "Linearized"].contains(&name))
-                .ok()
-                != Some(true)
-            {
-                contents_map = Writer::write_indirect_object(&mut target, oid, object, &mut xref, contents_map)?;
+            if is_cross_reference_object(object) {
+                continue;
+            }
+            // Signature dictionaries are written anew, for the offsets of their /Contents.
+            let signature = object.as_dict().is_ok_and(|dict| dict.has(b"ByteRange"));
+            if let Some(comment) = hooks.before_object.as_mut().and_then(|hook| hook(oid, object)) {
+                Writer::write_comment(&mut target, &comment)?;
+            }
+            let offset = target.bytes_written;
+            match self.raw_object(oid).filter(|_| !signature) {
+                Some(raw) => {
+                    xref.insert(oid.0, XrefEntry::Normal { offset, generation: oid.1 });
+                    target.write_all(raw)?;
+                    target.write_all(b"\n")?;
+                }
+                None => {
+                    contents_map = Writer::write_indirect_object(&mut target, oid, object, &mut xref, contents_map)?;
+                }
+            }
+            if let Some(hook) = hooks.after_object.as_mut() {
+                hook(oid, offset, target.bytes_written - offset);
             }
         }
+        objects_span.record("bytes", target.bytes_written as usize);
+        objects_span.exit();
 
         let xref_start = target.bytes_written;
+        let xref_span = span!("write_xref", entries = xref.entries.len());
         Writer::write_xref(&mut target, &xref)?;
+        xref_span.exit();
+        if let Some(hook) = hooks.after_xref.as_mut() {
+            hook(xref_start);
+        }
+        let _trailer_span = span!("write_trailer");
         self.write_trailer(&mut target)?;
         write!(target, "\nstartxref\n{}\n%%EOF", xref_start)?;
+        span.record("bytes", target.bytes_written as usize);
 
-        Ok(())
+        Ok((xref, contents_map.unwrap_or_default(), xref_start))
     }
 
     fn write_trailer<W: Write>(&mut self, file: &mut CountingWrite<&mut W>) -> Result<()> {
@@ -60,6 +222,66 @@ impl Document {
     }
 }
 
+/// Options of [`Document::save_with_options`] and [`Document::save_to_with_options`].
+#[cfg(feature = "std")]
+#[derive(Default)]
+pub struct SaveOptions<'a> {
+    pub hooks: SaveHooks<'a>,
+}
+
+/// Callbacks made while a document is saved, for tools that work on the file written. None are set by default.
+///
+/// They are given the offsets of what is written, and cannot write to the file themselves, so the offsets of the
+/// cross-reference table stay right.
+#[cfg(feature = "std")]
+#[derive(Default)]
+pub struct SaveHooks<'a> {
+    /// Called before each object is written, with its id; the bytes returned are written as a comment line
+    /// before the object, with line breaks replaced by spaces.
+    pub before_object: Option<BeforeObjectHook<'a>>,
+    /// Called after each object is written, with its id, the offset it starts at and its length in bytes, which
+    /// ends with the line break after `endobj`.
+    pub after_object: Option<AfterObjectHook<'a>>,
+    /// Called after the cross-reference table is written, with the offset it starts at.
+    pub after_xref: Option<AfterXrefHook<'a>>,
+}
+
+/// The callback of [`SaveHooks::before_object`].
+#[cfg(feature = "std")]
+pub type BeforeObjectHook<'a> = Box<dyn FnMut(ObjectId, &Object) -> Option<Vec<u8>> + 'a>;
+/// The callback of [`SaveHooks::after_object`].
+#[cfg(feature = "std")]
+pub type AfterObjectHook<'a> = Box<dyn FnMut(ObjectId, u64, u64) + 'a>;
+/// The callback of [`SaveHooks::after_xref`].
+#[cfg(feature = "std")]
+pub type AfterXrefHook<'a> = Box<dyn FnMut(u64) + 'a>;
+
+/// Start and end offsets of the `/Contents` values of the objects written, by object.
+#[cfg(feature = "std")]
+type ContentOffsets = BTreeMap<ObjectId, (u64, u64)>;
+
+/// Objects that only describe the layout of the file they were read from, and are not written again.
+#[cfg(feature = "std")]
+fn is_cross_reference_object(object: &Object) -> bool {
+    matches!(
+        object.known_type(),
+        Some(KnownName::ObjStm) | Some(KnownName::XRef) | Some(KnownName::Linearized)
+    )
+}
+
+/// A saved file, with where its objects and their `/Contents` entries were written.
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+pub(crate) struct FileUpdate {
+    pub data: Vec<u8>,
+    /// Offsets of the objects written, which for an incremental update are only those that changed.
+    pub xref: Xref,
+    pub contents: ContentOffsets,
+    pub xref_start: u64,
+}
+
+/// The largest offset the ten digits of a cross-reference table entry hold.
+const MAX_XREF_TABLE_OFFSET: u64 = 9_999_999_999;
+
 pub struct Writer;
 
 impl Writer {
@@ -74,6 +296,8 @@ impl Writer {
         )
     }
 
+    /// Write a cross-reference table. Its entries give offsets in ten digits, which files of 10 GB or more
+    /// overflow: an error is returned for them instead, and such files need a cross-reference stream.
     pub fn write_xref(file: &mut dyn Write, xref: &Xref) -> Result<()> {
         let mut start = 0;
         let mut current = 1;
@@ -89,8 +313,15 @@ impl Writer {
             };
             writeln!(file, "{} {}", start, len)?;
 
-            let mut write_xref_entry =
-                |offset: u32, generation: u16, kind: char| writeln!(file, "{:>010} {:>05} {} ", offset, generation, kind);
+            let mut write_xref_entry = |offset: u64, generation: u16, kind: char| {
+                if offset > MAX_XREF_TABLE_OFFSET {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        "offset beyond the ten digits of a cross-reference table entry",
+                    ));
+                }
+                writeln!(file, "{:>010} {:>05} {} ", offset, generation, kind)
+            };
 
             if start == 0 {
                 write_xref_entry(0, 65535, 'f')?;
@@ -122,7 +353,20 @@ impl Writer {
         Ok(())
     }
 
-    pub fn write_xref_stream(xref: &Xref) -> (Vec<u8>, Vec<(i64, i64)>) {
+    /// The entries of a cross-reference stream, with its `/Index` sections and its `/W` field widths: the second
+    /// field is as wide as the largest offset or object stream number needs.
+    pub fn write_xref_stream(xref: &Xref) -> (Vec<u8>, Vec<(i64, i64)>, [usize; 3]) {
+        let largest = xref
+            .entries
+            .values()
+            .map(|entry| match *entry {
+                XrefEntry::Normal { offset, .. } => offset,
+                XrefEntry::Compressed { container, .. } => u64::from(container),
+                XrefEntry::Free => 0,
+            })
+            .max()
+            .unwrap_or(0);
+        let width = (8 - largest.leading_zeros() as usize / 8).max(1);
         let mut start = 0;
         let mut current = 1;
         let mut entries: Vec<Option<&super::xref::XrefEntry>> = vec![];
@@ -137,10 +381,10 @@ impl Writer {
             };
             indices.push((start as i64, len as i64));
 
-            let mut write_xref_entry = |offset: u32, generation: u16, kind: u8| {
-                out.write_u8(kind).unwrap();
-                out.write_u32::<BigEndian>(offset).unwrap();
-                out.write_u16::<BigEndian>(generation).unwrap();
+            let mut write_xref_entry = |offset: u64, generation: u16, kind: u8| {
+                out.push(kind);
+                out.extend_from_slice(&offset.to_be_bytes()[8 - width..]);
+                out.extend_from_slice(&generation.to_be_bytes());
             };
 
             if start == 0 {
@@ -153,7 +397,7 @@ impl Writer {
                             write_xref_entry(*offset, *generation, 1);
                         }
                         XrefEntry::Compressed { container, index } => {
-                            write_xref_entry(*container, *index, 2);
+                            write_xref_entry(u64::from(*container), *index, 2);
                         }
                         XrefEntry::Free => {}
                     }
@@ -176,14 +420,25 @@ impl Writer {
         }
         output_entries(start, &mut entries);
 
-        (out, indices)
+        (out, indices, [1, width, 2])
+    }
+
+    /// Write a comment line, replacing line breaks in the text, which would end the comment, by spaces.
+    pub fn write_comment(file: &mut dyn Write, text: &[u8]) -> Result<()> {
+        let text: Vec<u8> = text
+            .iter()
+            .map(|&byte| if byte == b'\r' || byte == b'\n' { b' ' } else { byte })
+            .collect();
+        file.write_all(b"%")?;
+        file.write_all(&text)?;
+        file.write_all(b"\n")
     }
 
     pub fn write_indirect_object<W: Write>(
         file: &mut CountingWrite<&mut W>, oid: crate::ObjectId, object: &Object, xref: &mut Xref,
-        contents_map: Option<std::collections::btree_map::BTreeMap<crate::ObjectId, (u32, u32)>>
-    ) -> Result<Option<std::collections::btree_map::BTreeMap<crate::ObjectId, (u32, u32)>>> {
-        let offset = file.bytes_written as u32;
+        contents_map: Option<BTreeMap<crate::ObjectId, (u64, u64)>>
+    ) -> Result<Option<BTreeMap<crate::ObjectId, (u64, u64)>>> {
+        let offset = file.bytes_written;
         xref.insert(oid.0, XrefEntry::Normal { offset, generation: oid.1 });
         write!(
             file,
@@ -203,8 +458,8 @@ impl Writer {
 
     pub fn write_object<W: Write>(
         file: &mut CountingWrite<&mut W>, object: &Object, oid: Option<crate::ObjectId>,
-        contents_map: Option<std::collections::btree_map::BTreeMap<crate::ObjectId, (u32, u32)>>
-    ) -> Result<Option<std::collections::btree_map::BTreeMap<crate::ObjectId, (u32, u32)>>> {
+        contents_map: Option<BTreeMap<crate::ObjectId, (u64, u64)>>
+    ) -> Result<Option<BTreeMap<crate::ObjectId, (u64, u64)>>> {
         match *object {
             Null => {
                 file.write_all(b"null")?;
@@ -250,7 +505,7 @@ impl Writer {
         for &byte in name {
             // white-space and delimiter chars are encoded to # sequences
             // also encode bytes outside of the range 33 (!) to 126 (~)
-            if b" \t\n\r\x0C()<>[]{}/%#".contains(&byte) || byte < 33 || byte > 126 {
+            if b" \t\n\r\x0C()<>[]{}/%#".contains(&byte) || !(33..=126).contains(&byte) {
                 write!(file, "#{:02X}", byte)?;
             } else {
                 file.write_all(&[byte])?;
@@ -267,45 +522,60 @@ impl Writer {
             // If an end-of-line marker appears within a literal string without a preceding backslash, the result is equivalent to \n.
             // So \r also need be escaped.
             StringFormat::Literal => {
-                let mut escape_indice = Vec::new();
-                let mut parentheses = Vec::new();
-                for (index, &byte) in text.iter().enumerate() {
-                    match byte {
-                        b'(' => parentheses.push(index),
-                        b')' => {
-                            if !parentheses.is_empty() {
-                                parentheses.pop();
-                            } else {
-                                escape_indice.push(index);
+                // Opening parentheses left unbalanced at the end are escaped, so they are found beforehand.
+                let mut unbalanced = Vec::new();
+                if text.contains(&b'(') {
+                    for (index, &byte) in text.iter().enumerate() {
+                        match byte {
+                            b'(' => unbalanced.push(index),
+                            b')' => {
+                                unbalanced.pop();
                             }
+                            _ => {}
                         }
-                        b'\\' | b'\r' => escape_indice.push(index),
-                        _ => continue,
                     }
                 }
-                escape_indice.append(&mut parentheses);
+                let mut unbalanced = unbalanced.into_iter().peekable();
 
                 file.write_all(b"(")?;
-                if !escape_indice.is_empty() {
-                    for (index, &byte) in text.iter().enumerate() {
-                        if escape_indice.contains(&index) {
-                            file.write_all(b"\\")?;
-                            file.write_all(&[if byte == b'\r' { b'r' } else { byte }])?;
-                        } else {
-                            file.write_all(&[byte])?;
+                let mut depth = 0usize;
+                let mut run_start = 0;
+                for (index, &byte) in text.iter().enumerate() {
+                    let escape = match byte {
+                        b'(' if unbalanced.peek() == Some(&index) => {
+                            unbalanced.next();
+                            true
+                        }
+                        b'(' => {
+                            depth += 1;
+                            false
                         }
+                        b')' if depth > 0 => {
+                            depth -= 1;
+                            false
+                        }
+                        b')' | b'\\' | b'\r' => true,
+                        _ => false,
+                    };
+                    if escape {
+                        file.write_all(&text[run_start..index])?;
+                        file.write_all(&[b'\\', if byte == b'\r' { b'r' } else { byte }])?;
+                        run_start = index + 1;
                     }
-                } else {
-                    file.write_all(text)?;
                 }
+                file.write_all(&text[run_start..])?;
                 file.write_all(b")")?;
             }
             StringFormat::Hexadecimal => {
-                file.write_all(b"<")?;
+                const DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+                let mut hex = Vec::with_capacity(text.len() * 2 + 2);
+                hex.push(b'<');
                 for &byte in text {
-                    write!(file, "{:02X}", byte)?;
+                    hex.push(DIGITS[usize::from(byte >> 4)]);
+                    hex.push(DIGITS[usize::from(byte & 0x0F)]);
                 }
-                file.write_all(b">")?;
+                hex.push(b'>');
+                file.write_all(&hex)?;
             }
         }
         Ok(())
@@ -313,8 +583,8 @@ impl Writer {
 
     pub fn write_array<W: Write>(
         file: &mut CountingWrite<&mut W>, array: &[Object], oid: Option<crate::ObjectId>,
-        mut contents_map: Option<std::collections::btree_map::BTreeMap<crate::ObjectId, (u32, u32)>>
-    ) -> Result<Option<std::collections::btree_map::BTreeMap<crate::ObjectId, (u32, u32)>>> {
+        mut contents_map: Option<BTreeMap<crate::ObjectId, (u64, u64)>>
+    ) -> Result<Option<BTreeMap<crate::ObjectId, (u64, u64)>>> {
         file.write_all(b"[")?;
         let mut first = true;
         for object in array {
@@ -331,22 +601,19 @@ impl Writer {
 
     pub fn write_dictionary<W: Write>(
         file: &mut CountingWrite<&mut W>, dictionary: &Dictionary, oid: Option<crate::ObjectId>,
-        mut contents_map: Option<std::collections::btree_map::BTreeMap<crate::ObjectId, (u32, u32)>>
-    ) -> Result<Option<std::collections::btree_map::BTreeMap<crate::ObjectId, (u32, u32)>>> {
+        mut contents_map: Option<BTreeMap<crate::ObjectId, (u64, u64)>>
+    ) -> Result<Option<BTreeMap<crate::ObjectId, (u64, u64)>>> {
         file.write_all(b"<<")?;
         for (key, value) in dictionary {
             Writer::write_name(file, key)?;
             if Writer::need_separator(value) {
                 file.write_all(b" ")?;
             }
-            let start = file.bytes_written as u32;
+            let start = file.bytes_written;
             contents_map = Writer::write_object(file, value, oid, contents_map)?;
             if key == b"Contents" {
-                match (oid, &mut contents_map) {
-                    (Some(oid), Some(ref mut contents_map)) => {
-                        contents_map.insert(oid, (start, file.bytes_written as u32));
-                    },
-                    _ => {}
+                if let (Some(oid), Some(ref mut contents_map)) = (oid, &mut contents_map) {
+                    contents_map.insert(oid, (start, file.bytes_written));
                 }
             }
         }
@@ -356,8 +623,8 @@ impl Writer {
 
     pub fn write_stream<W: Write>(
         file: &mut CountingWrite<&mut W>, stream: &Stream, oid: Option<crate::ObjectId>,
-        mut contents_map: Option<std::collections::btree_map::BTreeMap<crate::ObjectId, (u32, u32)>>
-    ) -> Result<Option<std::collections::btree_map::BTreeMap<crate::ObjectId, (u32, u32)>>> {
+        mut contents_map: Option<BTreeMap<crate::ObjectId, (u64, u64)>>
+    ) -> Result<Option<BTreeMap<crate::ObjectId, (u64, u64)>>> {
         contents_map = Writer::write_dictionary(file, &stream.dict, oid, contents_map)?;
         file.write_all(b"stream\n")?;
         file.write_all(&stream.content)?;
@@ -368,7 +635,7 @@ impl Writer {
 
 pub struct CountingWrite<W: Write> {
     pub inner: W,
-    pub bytes_written: usize,
+    pub bytes_written: u64,
 }
 
 impl<W: Write> Write for CountingWrite<W> {
@@ -376,14 +643,14 @@ impl<W: Write> Write for CountingWrite<W> {
     fn write(&mut self, buffer: &[u8]) -> Result<usize> {
         let result = self.inner.write(buffer);
         if let Ok(bytes) = result {
-            self.bytes_written += bytes;
+            self.bytes_written += bytes as u64;
         }
         result
     }
 
     #[inline]
     fn write_all(&mut self, buffer: &[u8]) -> Result<()> {
-        self.bytes_written += buffer.len();
+        self.bytes_written += buffer.len() as u64;
         // If this returns `Err` we can’t know how many bytes were actually written (if any)
         // but that doesn’t matter since we’re gonna abort the entire PDF generation anyway.
         self.inner.write_all(buffer)
@@ -395,6 +662,7 @@ impl<W: Write> Write for CountingWrite<W> {
     }
 }
 
+#[cfg(feature = "std")]
 #[test]
 fn save_document() {
     let mut doc = Document::with_version("1.5");
@@ -423,3 +691,287 @@ fn save_document() {
 
     doc.save("test_0_save.pdf").unwrap();
 }
+
+#[cfg(all(feature = "std", any(feature = "pom_parser", feature = "nom_parser")))]
+#[test]
+fn save_hooks_see_the_objects_written() {
+    use crate::digest::sha256;
+    use std::cell::RefCell;
+
+    let serialized = |object: &Object| {
+        let mut bytes = Vec::new();
+        let mut target = CountingWrite {
+            inner: &mut bytes,
+            bytes_written: 0,
+        };
+        Writer::write_object(&mut target, object, None, None).unwrap();
+        bytes
+    };
+    let mut doc = Document::load("assets/example.pdf").unwrap();
+    let digests = RefCell::new(BTreeMap::new());
+    let spans = RefCell::new(BTreeMap::new());
+    let mut xref_start = 0;
+    let mut saved = Vec::new();
+    let options = SaveOptions {
+        hooks: SaveHooks {
+            before_object: Some(Box::new(|id, object| {
+                digests.borrow_mut().insert(id, sha256(&serialized(object)));
+                // The line break cannot end the comment early.
+                Some(format!("object {}\r\n{}", id.0, id.1).into_bytes())
+            })),
+            after_object: Some(Box::new(|id, offset, len| {
+                spans.borrow_mut().insert(id, offset as usize..(offset + len) as usize);
+            })),
+            after_xref: Some(Box::new(|offset| xref_start = offset as usize)),
+        },
+    };
+    doc.save_to_with_options(&mut saved, options).unwrap();
+
+    let resaved = Document::load_mem(&saved).unwrap();
+    let (digests, spans) = (digests.into_inner(), spans.into_inner());
+    assert_eq!(digests.len(), resaved.objects.len());
+    for (id, object) in &resaved.objects {
+        assert_eq!(sha256(&serialized(object)), digests[id], "{:?}", id);
+        let span = spans[id].clone();
+        assert!(saved[span.clone()].starts_with(format!("{} {} obj", id.0, id.1).as_bytes()));
+        assert!(saved[..span.start].ends_with(format!("%object {}  {}\n", id.0, id.1).as_bytes()));
+        assert!(saved[span].ends_with(b"endobj\n"));
+    }
+    assert!(saved[xref_start..].starts_with(b"xref\n"));
+}
+
+#[test]
+fn write_strings() {
+    let cases: [(&[u8], &[u8]); 8] = [
+        (b"", b"()"),
+        (b"plain text", b"(plain text)"),
+        (b"a(b)c", b"(a(b)c)"),
+        (b"a)b(c", b"(a\\)b\\(c)"),
+        (b"((x)", b"(\\((x))"),
+        (b"())(", b"(()\\)\\()"),
+        (b"(a(b)c))((", b"((a(b)c)\\)\\(\\()"),
+        (b"back\\slash\r\n", b"(back\\\\slash\\r\n)"),
+    ];
+    for (text, written) in cases.iter() {
+        let mut buffer = Vec::new();
+        Writer::write_string(&mut buffer, text, &StringFormat::Literal).unwrap();
+        assert_eq!(buffer, *written, "{:?}", alloc::string::String::from_utf8_lossy(text));
+    }
+    let mut buffer = Vec::new();
+    Writer::write_string(&mut buffer, b"\x00\xABz", &StringFormat::Hexadecimal).unwrap();
+    assert_eq!(buffer, b"<00AB7A>");
+}
+
+#[cfg(all(feature = "std", any(feature = "pom_parser", feature = "nom_parser")))]
+#[test]
+fn save_untouched_objects_as_read() {
+    let options = crate::ParseOptions {
+        preserve_raw: true,
+        ..crate::ParseOptions::default()
+    };
+    let source = std::fs::read("assets/example.pdf").unwrap();
+    let mut doc = Document::load_mem_with_options(&source, &options).unwrap();
+    let ids: Vec<ObjectId> = doc.objects.keys().copied().collect();
+    let body = |doc: &Document, id| doc.raw_object(id).unwrap().to_vec();
+    let bodies: Vec<Vec<u8>> = ids.iter().map(|&id| body(&doc, id)).collect();
+    assert!(bodies[3].starts_with(b"4 0 obj<</Length 45>>stream\n") && bodies[3].ends_with(b"endstream endobj"));
+
+    let mut saved = Vec::new();
+    doc.save_to(&mut saved).unwrap();
+    let resaved = Document::load_mem_with_options(&saved, &options).unwrap();
+    for (&id, body_read) in ids.iter().zip(&bodies) {
+        assert_eq!(&body(&resaved, id), body_read, "{:?}", id);
+    }
+
+    // Only the dictionary edited is written anew, and changes made through the objects are noticed.
+    doc.catalog_mut().unwrap().set("PageMode", "UseOutlines");
+    assert!(doc.is_dirty((6, 0)) && !doc.is_dirty((2, 0)));
+    let set_font = |doc: &mut Document, font: &str| {
+        let font_object = doc.objects.get_mut(&(2, 0)).unwrap();
+        font_object.as_dict_mut().unwrap().set("BaseFont", font);
+    };
+    set_font(&mut doc, "Helvetica");
+    assert!(doc.is_dirty((2, 0)));
+    set_font(&mut doc, "Courier");
+    assert!(!doc.is_dirty((2, 0)));
+    saved.clear();
+    doc.save_to(&mut saved).unwrap();
+    let resaved = Document::load_mem_with_options(&saved, &options).unwrap();
+    for (&id, body_read) in ids.iter().zip(&bodies) {
+        assert_eq!(&body(&resaved, id) == body_read, id != (6, 0), "{:?}", id);
+    }
+    assert!(resaved.catalog().unwrap().has(b"PageMode"));
+}
+
+#[cfg(all(feature = "std", any(feature = "pom_parser", feature = "nom_parser")))]
+#[test]
+fn save_annotation_contents_as_read() {
+    use crate::creator::tests::create_document;
+
+    // The text string in the /Contents of an annotation does not make it a signature dictionary, written anew.
+    let mut doc = create_document();
+    let annotation_id = doc.add_object(dictionary! {
+        "Type" => "Annot",
+        "Subtype" => "Text",
+        "Contents" => Object::string_literal("Note"),
+    });
+    let mut source = Vec::new();
+    doc.save_to(&mut source).unwrap();
+    // A hex string of the same length in lowercase, which the writer does not write.
+    let offset = source.windows(6).position(|window| window == b"(Note)").unwrap();
+    source[offset..offset + 6].copy_from_slice(b"<4e6f>");
+    let options = crate::ParseOptions {
+        preserve_raw: true,
+        ..crate::ParseOptions::default()
+    };
+    let mut doc = Document::load_mem_with_options(&source, &options).unwrap();
+    let body = doc.raw_object(annotation_id).unwrap().to_vec();
+
+    let mut saved = Vec::new();
+    doc.save_to(&mut saved).unwrap();
+    let resaved = Document::load_mem_with_options(&saved, &options).unwrap();
+    assert_eq!(resaved.raw_object(annotation_id).unwrap(), &body[..]);
+    assert!(saved.windows(6).any(|window| window == b"<4e6f>"));
+}
+
+#[cfg(all(feature = "std", any(feature = "pom_parser", feature = "nom_parser")))]
+#[test]
+fn write_and_read_offsets_beyond_4_gib() {
+    use crate::creator::tests::create_document;
+
+    // Objects written after 5 GiB of other data, which the sink does not keep.
+    const START: u64 = 5 << 30;
+    let mut tail = Vec::new();
+    let mut target = CountingWrite {
+        inner: &mut tail,
+        bytes_written: START,
+    };
+    let mut xref = Xref::new(3);
+    Writer::write_indirect_object(&mut target, (1, 0), &Object::Integer(1), &mut xref, None).unwrap();
+    let second = target.bytes_written;
+    Writer::write_indirect_object(&mut target, (2, 0), &Object::Integer(2), &mut xref, None).unwrap();
+    let offsets = |xref: &Xref| -> Vec<u64> {
+        (1..=2)
+            .map(|number| match xref.get(number) {
+                Some(XrefEntry::Normal { offset, .. }) => *offset,
+                _ => panic!("no entry for object {}", number),
+            })
+            .collect()
+    };
+    assert_eq!(offsets(&xref), [START, second]);
+
+    let mut table = Vec::new();
+    Writer::write_xref(&mut table, &xref).unwrap();
+    assert!(table.ends_with(format!("5368709120 00000 n \n{:>010} 00000 n \n", second).as_bytes()));
+    let mut too_far = xref.clone();
+    too_far.insert(3, XrefEntry::Normal { offset: 10_000_000_000, generation: 0 });
+    let error = Writer::write_xref(&mut Vec::new(), &too_far).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+    // The stream gives the offsets in five bytes.
+    let (data, indices, widths) = Writer::write_xref_stream(&too_far);
+    assert_eq!(widths, [1, 5, 2]);
+    assert_eq!(data.len(), 4 * 8);
+    let stream = Stream::new(
+        dictionary! {
+            "Type" => "XRef",
+            "Size" => 4,
+            "W" => widths.iter().map(|&width| Object::Integer(width as i64)).collect::<Vec<_>>(),
+            "Index" => indices.iter().flat_map(|&(start, len)| vec![start.into(), len.into()]).collect::<Vec<_>>(),
+        },
+        data,
+    );
+    let (read, _) = crate::xref::decode_xref_stream(stream).unwrap();
+    assert_eq!(offsets(&read), [START, second]);
+    assert!(matches!(read.get(3), Some(XrefEntry::Normal { offset: 10_000_000_000, .. })));
+
+    // A table read with an offset beyond 4 GiB keeps it, and the object it places past the end of the file is
+    // not read.
+    let mut saved = Vec::new();
+    create_document().save_to(&mut saved).unwrap();
+    let table = saved.windows(6).position(|window| window == b"\nxref\n").unwrap();
+    let entry = table + saved[table..].windows(20).position(|window| window.ends_with(b"65535 f \n")).unwrap() + 20;
+    saved[entry..entry + 10].copy_from_slice(b"5368709120");
+    let doc = Document::load_mem(&saved).unwrap();
+    assert!(matches!(doc.reference_table.get(1), Some(XrefEntry::Normal { offset: START, .. })));
+    assert!(doc.get_object((1, 0)).is_err());
+    assert_eq!(doc.get_pages().len(), 1);
+}
+
+#[test]
+fn xref_stream_widths_of_offsets() {
+    let width = |offset: u64| {
+        let mut xref = Xref::new(2);
+        xref.insert(1, XrefEntry::Normal { offset, generation: 0 });
+        Writer::write_xref_stream(&xref).2[1]
+    };
+    assert_eq!(width(0), 1);
+    assert_eq!(width(u64::from(u32::MAX)), 4);
+    assert_eq!(width(u64::from(u32::MAX) + 1), 5);
+    assert_eq!(width(1 << 40), 6);
+    assert_eq!(width(u64::MAX), 8);
+}
+
+#[cfg(all(
+    feature = "std",
+    any(feature = "pom_parser", feature = "nom_parser"),
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+#[test]
+#[ignore = "reads a file of more than 4 GiB into memory and writes another"]
+fn load_and_save_files_beyond_4_gib() {
+    use crate::creator::tests::create_document;
+    use std::io::{Read, Seek, SeekFrom};
+
+    // The objects of a sparse file follow a hole of null bytes, which are white-space, up to past 4 GiB.
+    const START: u64 = (4 << 30) + (1 << 20);
+    let sparse = std::env::temp_dir().join("lopdf_sparse_beyond_4_gib.pdf");
+    let mut doc = create_document();
+    {
+        let mut file = File::create(&sparse).unwrap();
+        file.write_all(b"%PDF-1.5\n").unwrap();
+        file.seek(SeekFrom::Start(START)).unwrap();
+        let mut target = CountingWrite {
+            inner: &mut file,
+            bytes_written: START,
+        };
+        let mut xref = Xref::new(doc.max_id + 1);
+        for (&id, object) in &doc.objects {
+            Writer::write_indirect_object(&mut target, id, object, &mut xref, None).unwrap();
+        }
+        let xref_start = target.bytes_written;
+        Writer::write_xref(&mut target, &xref).unwrap();
+        doc.write_trailer(&mut target).unwrap();
+        write!(target, "\nstartxref\n{}\n%%EOF", xref_start).unwrap();
+    }
+    let loaded = Document::load(&sparse);
+    std::fs::remove_file(&sparse).unwrap();
+    let mut doc = loaded.unwrap();
+    assert!(matches!(doc.reference_table.get(1), Some(XrefEntry::Normal { offset, .. }) if *offset >= START));
+    assert_eq!(doc.get_pages().len(), 1);
+
+    // Saved after a stream of zeros, which stay unread in memory, the object added last is written past 4 GiB.
+    doc.add_object(Stream::new(Dictionary::new(), vec![0; START as usize]));
+    let note_id = doc.add_object(Object::string_literal("beyond 4 GiB"));
+    let saved = std::env::temp_dir().join("lopdf_saved_beyond_4_gib.pdf");
+    let mut tail = Vec::new();
+    let written = doc.save(&saved).and_then(|_| {
+        let mut file = File::open(&saved)?;
+        file.seek(SeekFrom::Start(START))?;
+        file.read_to_end(&mut tail)
+    });
+    std::fs::remove_file(&saved).unwrap();
+    written.unwrap();
+    let at = |offset: u64| &tail[(offset - START) as usize..];
+    let number = |bytes: &[u8]| -> u64 { core::str::from_utf8(bytes).unwrap().trim().parse().unwrap() };
+    let startxref = tail.windows(10).rposition(|window| window == b"startxref\n").unwrap() + 10;
+    let xref_start = number(&tail[startxref..tail.len() - "\n%%EOF".len()]);
+    // A single section, from object 0, of entries of 20 bytes.
+    let table = at(xref_start);
+    let section = format!("xref\n0 {}\n", doc.max_id + 1);
+    assert!(table.starts_with(section.as_bytes()));
+    let entry = section.len() + 20 * note_id.0 as usize;
+    let offset = number(&table[entry..entry + 10]);
+    assert!(offset > u64::from(u32::MAX));
+    assert!(at(offset).starts_with(format!("{} 0 obj", note_id.0).as_bytes()));
+}
@@ -0,0 +1,515 @@
+use crate::encodings::{decode_text_string, encode_text_string};
+use crate::{Document, Error, Object, Result, Stream, StringFormat};
+
+/// The direction in which [`Document::sync_metadata`] copies the core properties.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataSync {
+    /// Update the XMP metadata from the document information dictionary.
+    InfoToXmp,
+    /// Update the document information dictionary from the XMP metadata.
+    XmpToInfo,
+}
+
+/// How an entry of the document information dictionary is stored in XMP.
+#[derive(Clone, Copy)]
+enum Kind {
+    Text,
+    /// A language alternative, whose default language is used.
+    Alternative,
+    /// An ordered array, whose items are joined by semicolons in the information dictionary.
+    Sequence,
+    Date,
+}
+
+/// The entries of the document information dictionary and the XMP properties they correspond to.
+const PROPERTIES: [(&str, &str, Kind); 6] = [
+    ("Title", "dc:title", Kind::Alternative),
+    ("Author", "dc:creator", Kind::Sequence),
+    ("Producer", "pdf:Producer", Kind::Text),
+    ("Keywords", "pdf:Keywords", Kind::Text),
+    ("CreationDate", "xmp:CreateDate", Kind::Date),
+    ("ModDate", "xmp:ModifyDate", Kind::Date),
+];
+
+const NAMESPACES: &str = concat!(
+    r#"xmlns:dc="http://purl.org/dc/elements/1.1/" "#,
+    r#"xmlns:pdf="http://ns.adobe.com/pdf/1.3/" "#,
+    r#"xmlns:xmp="http://ns.adobe.com/xap/1.0/""#,
+);
+
+const EMPTY_PACKET: &str = concat!(
+    "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n",
+    "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n",
+    "<rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n",
+    "</rdf:RDF>\n",
+    "</x:xmpmeta>\n",
+    "<?xpacket end=\"w\"?>",
+);
+
+impl Document {
+    /// The XMP metadata of the document, the decoded content of the `/Metadata` stream of the catalog.
+    pub fn xmp(&self) -> Option<Vec<u8>> {
+        let metadata = self.catalog().ok()?.get(b"Metadata").ok()?;
+        let stream = self.dereference(metadata).ok()?.1.as_stream().ok()?;
+        Some(stream.decompressed_content().unwrap_or_else(|_| stream.content.to_vec()))
+    }
+
+    /// Store XMP metadata in the `/Metadata` stream of the catalog, replacing the content of an existing stream
+    /// or adding one.
+    ///
+    /// The stream is left unfiltered, as PDF/A requires, and [`Document::compress`] does not compress it.
+    pub fn set_xmp(&mut self, xml: &[u8]) -> Result<()> {
+        let root_id = self.trailer.get(b"Root")?.as_reference()?;
+        let mut stream =
+            Stream::new(dictionary! { "Type" => "Metadata", "Subtype" => "XML" }, xml.to_vec()).with_compression(false);
+        let existing = match self
+            .catalog()?
+            .get(b"Metadata")
+            .map(|metadata| self.dereference(metadata))
+        {
+            Ok(Ok((Some(id), Object::Stream(existing)))) => Some((id, existing)),
+            _ => None,
+        };
+        match existing {
+            Some((id, existing)) => {
+                // Keep other entries, such as those of associated files, but not the filters.
+                let mut dict = existing.dict.clone();
+                for key in [&b"Filter"[..], b"DecodeParms", b"DL"] {
+                    dict.remove(key);
+                }
+                dict.extend(&stream.dict);
+                stream.dict = dict;
+                self.objects.insert(id, Object::Stream(stream));
+            }
+            None => {
+                let id = self.add_object(stream);
+                self.get_object_mut(root_id)?.as_dict_mut()?.set("Metadata", id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Copy the core properties between the document information dictionary and the XMP metadata: the title
+    /// (`dc:title`), author (`dc:creator`), producer (`pdf:Producer`), keywords (`pdf:Keywords`) and dates
+    /// (`xmp:CreateDate` and `xmp:ModifyDate`). Properties missing from the source are removed from the
+    /// destination.
+    ///
+    /// The XMP metadata is read and updated as text, with the usual namespace prefixes of these properties;
+    /// everything else in it is kept as it is. A new packet is created if the document has no XMP metadata.
+    pub fn sync_metadata(&mut self, direction: MetadataSync) -> Result<()> {
+        let xml = match self.xmp() {
+            Some(xml) => String::from_utf8(xml).map_err(|_| Error::UTF8)?,
+            None if direction == MetadataSync::XmpToInfo => return Ok(()),
+            None => EMPTY_PACKET.to_string(),
+        };
+        match direction {
+            MetadataSync::InfoToXmp => {
+                let info = self.info();
+                let mut xml = xml;
+                let mut elements = String::new();
+                for &(key, property, kind) in &PROPERTIES {
+                    remove_property(&mut xml, property);
+                    let value = match info.and_then(|info| info.get_deref(key.as_bytes(), self).ok()) {
+                        Some(Object::String(value, _)) => value,
+                        _ => continue,
+                    };
+                    let value = match kind {
+                        Kind::Date => match pdf_date_to_xmp(value) {
+                            Some(date) => date,
+                            None => continue,
+                        },
+                        _ => decode_text_string(value),
+                    };
+                    elements.push_str(&property_element(property, kind, &value));
+                }
+                // Drop the description of an earlier synchronization, now empty.
+                let description_start = format!("<rdf:Description rdf:about=\"\" {}>", NAMESPACES);
+                let mut position = 0;
+                while let Some(found) = xml[position..].find(&description_start) {
+                    let start = position + found;
+                    let content_start = start + description_start.len();
+                    let rest = &xml[content_start..];
+                    match rest.trim_start().strip_prefix("</rdf:Description>") {
+                        Some(after) => {
+                            let end = xml.len() - after.len();
+                            let end = if xml[end..].starts_with('\n') { end + 1 } else { end };
+                            xml.replace_range(start..end, "");
+                        }
+                        None => position = content_start,
+                    }
+                }
+                if !elements.is_empty() {
+                    let description = format!("{}\n{}</rdf:Description>\n", description_start, elements);
+                    if !xml.contains("</rdf:RDF>") {
+                        xml = EMPTY_PACKET.to_string();
+                    }
+                    let end = xml.rfind("</rdf:RDF>").unwrap_or_default();
+                    xml.insert_str(end, &description);
+                }
+                self.set_xmp(xml.as_bytes())
+            }
+            MetadataSync::XmpToInfo => {
+                let info = self.info_mut()?;
+                for &(key, property, kind) in &PROPERTIES {
+                    let value = read_property(&xml, property).and_then(|values| match kind {
+                        Kind::Date => values.first().and_then(|date| xmp_date_to_pdf(date)),
+                        Kind::Sequence => Some(values.join("; ")),
+                        _ => values.into_iter().next(),
+                    });
+                    match value {
+                        Some(value) if matches!(kind, Kind::Date) => info.set(key, Object::string_literal(value)),
+                        Some(value) => info.set(key, Object::String(encode_text_string(&value), StringFormat::Literal)),
+                        None => {
+                            info.remove(key.as_bytes());
+                        }
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The XMP element of a property.
+fn property_element(property: &str, kind: Kind, value: &str) -> String {
+    match kind {
+        Kind::Text | Kind::Date => format!("<{0}>{1}</{0}>\n", property, escape(value)),
+        Kind::Alternative => format!(
+            "<{0}><rdf:Alt><rdf:li xml:lang=\"x-default\">{1}</rdf:li></rdf:Alt></{0}>\n",
+            property,
+            escape(value)
+        ),
+        Kind::Sequence => {
+            let items: String = value
+                .split(';')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(|item| format!("<rdf:li>{}</rdf:li>", escape(item)))
+                .collect();
+            format!("<{0}><rdf:Seq>{1}</rdf:Seq></{0}>\n", property, items)
+        }
+    }
+}
+
+/// The position of the start tag of an element, `<name` followed by the end of the name.
+fn find_start_tag(xml: &str, name: &str, from: usize) -> Option<usize> {
+    let pattern = format!("<{}", name);
+    let mut position = from;
+    while let Some(found) = xml[position..].find(&pattern) {
+        let start = position + found;
+        let next = xml[start + pattern.len()..].chars().next();
+        if next.is_none_or(|next| next.is_whitespace() || next == '>' || next == '/') {
+            return Some(start);
+        }
+        position = start + pattern.len();
+    }
+    None
+}
+
+/// The range of an element, and that of its content, starting at its start tag.
+fn element_range(xml: &str, name: &str, start: usize) -> Option<((usize, usize), (usize, usize))> {
+    let tag_end = start + xml[start..].find('>')? + 1;
+    if xml[..tag_end].ends_with("/>") {
+        return Some(((start, tag_end), (tag_end, tag_end)));
+    }
+    let end_tag = format!("</{}>", name);
+    let content_end = tag_end + xml[tag_end..].find(&end_tag)?;
+    Some(((start, content_end + end_tag.len()), (tag_end, content_end)))
+}
+
+/// The position of the value of an attribute, after its opening quote, and the quote.
+fn find_attribute(xml: &str, name: &str, from: usize) -> Option<(usize, usize, char)> {
+    let mut position = from;
+    while let Some(found) = xml[position..].find(name) {
+        let start = position + found;
+        position = start + name.len();
+        if !xml[..start].ends_with(char::is_whitespace) {
+            continue;
+        }
+        let rest = xml[position..].trim_start();
+        let rest = match rest.strip_prefix('=') {
+            Some(rest) => rest.trim_start(),
+            None => continue,
+        };
+        let quote = match rest.chars().next() {
+            Some(quote) if quote == '"' || quote == '\'' => quote,
+            _ => continue,
+        };
+        let value_start = xml.len() - rest.len() + 1;
+        return Some((start, value_start, quote));
+    }
+    None
+}
+
+/// The values of a property, in element or attribute form: the items of an array, the default language first,
+/// or the text of a simple property.
+pub(crate) fn read_property(xml: &str, property: &str) -> Option<Vec<String>> {
+    if let Some(start) = find_start_tag(xml, property, 0) {
+        let (_, (content_start, content_end)) = element_range(xml, property, start)?;
+        let content = &xml[content_start..content_end];
+        if find_start_tag(content, "rdf:li", 0).is_none() {
+            return Some(vec![unescape(content.trim())]);
+        }
+        let mut values = Vec::new();
+        let mut position = 0;
+        while let Some(start) = find_start_tag(content, "rdf:li", position) {
+            let ((_, end), (item_start, item_end)) = element_range(content, "rdf:li", start)?;
+            let value = unescape(content[item_start..item_end].trim());
+            if content[start..item_start].contains("x-default") {
+                values.insert(0, value);
+            } else {
+                values.push(value);
+            }
+            position = end;
+        }
+        return Some(values);
+    }
+    let (_, value_start, quote) = find_attribute(xml, property, 0)?;
+    let value_end = value_start + xml[value_start..].find(quote)?;
+    Some(vec![unescape(&xml[value_start..value_end])])
+}
+
+/// Remove a property, in element and attribute form.
+fn remove_property(xml: &mut String, property: &str) {
+    while let Some(start) = find_start_tag(xml, property, 0) {
+        let ((start, mut end), _) = match element_range(xml, property, start) {
+            Some(range) => range,
+            None => break,
+        };
+        if xml[end..].starts_with('\n') {
+            end += 1;
+        }
+        xml.replace_range(start..end, "");
+    }
+    while let Some((start, value_start, quote)) = find_attribute(xml, property, 0) {
+        let value_end = match xml[value_start..].find(quote) {
+            Some(length) => value_start + length + 1,
+            None => break,
+        };
+        let start = xml[..start].trim_end().len();
+        xml.replace_range(start..value_end, "");
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = match rest.find(';') {
+            Some(end) => end,
+            None => break,
+        };
+        let character = match &rest[1..end] {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            entity => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse))
+                .and_then(|code| code.ok())
+                .and_then(char::from_u32),
+        };
+        match character {
+            Some(character) => {
+                unescaped.push(character);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+/// Convert a PDF date, `D:YYYYMMDDHHmmSSOHH'mm'` with everything after the year optional, to an XMP date.
+pub(crate) fn pdf_date_to_xmp(date: &[u8]) -> Option<String> {
+    let date = std::str::from_utf8(date).ok()?;
+    let date = date.strip_prefix("D:").unwrap_or(date);
+    let digits = date.bytes().take_while(u8::is_ascii_digit).count();
+    if digits < 4 || digits % 2 != 0 || digits > 14 {
+        return None;
+    }
+    let field = |index: usize, default: &'static str| {
+        date.get(2 + 2 * index..4 + 2 * index)
+            .filter(|_| 4 + 2 * index <= digits)
+            .unwrap_or(default)
+    };
+    let mut xmp = format!(
+        "{}-{}-{}T{}:{}:{}",
+        &date[..4],
+        field(1, "01"),
+        field(2, "01"),
+        field(3, "00"),
+        field(4, "00"),
+        field(5, "00")
+    );
+    let offset: String = date[digits..].chars().filter(|c| *c != '\'').collect();
+    match offset.as_bytes() {
+        [] => {}
+        [b'Z', ..] => xmp.push('Z'),
+        [sign @ (b'+' | b'-'), hours @ ..] if hours.len() >= 2 && hours.iter().all(u8::is_ascii_digit) => {
+            let minutes = offset.get(3..5).unwrap_or("00");
+            xmp.push_str(&format!("{}{}:{}", *sign as char, &offset[1..3], minutes));
+        }
+        _ => return None,
+    }
+    Some(xmp)
+}
+
+/// Convert an XMP date, ISO 8601 with everything after the year optional, to a PDF date.
+fn xmp_date_to_pdf(date: &str) -> Option<String> {
+    let date = date.trim();
+    // The offset follows the time, whose separator is the first `T`.
+    let offset_start = date
+        .find('T')
+        .and_then(|time| date[time..].find(['Z', '+', '-']).map(|offset| time + offset));
+    let (date_time, offset) = date.split_at(offset_start.unwrap_or(date.len()));
+    let date_time = date_time.split('.').next().unwrap_or_default();
+    let digits: String = date_time.chars().filter(char::is_ascii_digit).collect();
+    if digits.len() < 4 || date_time.chars().any(|c| !c.is_ascii_digit() && !"-T:".contains(c)) {
+        return None;
+    }
+    let mut pdf = format!("D:{}", digits);
+    match offset.as_bytes() {
+        [] => {}
+        [b'Z'] => pdf.push('Z'),
+        [sign @ (b'+' | b'-'), b'0'..=b'9', b'0'..=b'9', b':', b'0'..=b'9', b'0'..=b'9'] => {
+            pdf.push_str(&format!("{}{}'{}'", *sign as char, &offset[1..3], &offset[4..6]));
+        }
+        _ => return None,
+    }
+    Some(pdf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::creator::tests::create_document;
+
+    const PDFA_PACKET: &str = concat!(
+        "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n",
+        "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n",
+        "<rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n",
+        "<rdf:Description rdf:about=\"\" xmlns:pdfaid=\"http://www.aiim.org/pdfa/ns/id/\" pdfaid:part=\"2\"/>\n",
+        "<rdf:Description rdf:about=\"\" xmlns:pdf=\"http://ns.adobe.com/pdf/1.3/\" ",
+        "xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\" ",
+        "pdf:Producer=\"Tool &amp; Co\" xmp:CreateDate=\"2024-02-29T10:30:00.25+01:00\">\n",
+        "<dc:title><rdf:Alt><rdf:li xml:lang=\"fr\">Rapport</rdf:li>",
+        "<rdf:li xml:lang=\"x-default\">Report</rdf:li></rdf:Alt></dc:title>\n",
+        "<dc:creator><rdf:Seq><rdf:li>Ann</rdf:li><rdf:li>Bob &#x4E2D;</rdf:li></rdf:Seq></dc:creator>\n",
+        "</rdf:Description>\n",
+        "</rdf:RDF>\n",
+        "</x:xmpmeta>\n",
+        "<?xpacket end=\"w\"?>",
+    );
+
+    #[test]
+    fn xmp_to_info() {
+        let mut doc = create_document();
+        doc.set_xmp(PDFA_PACKET.as_bytes()).unwrap();
+        assert_eq!(doc.xmp().unwrap(), PDFA_PACKET.as_bytes());
+        doc.sync_metadata(MetadataSync::XmpToInfo).unwrap();
+        let metadata = doc.metadata();
+        assert_eq!(metadata.title.as_deref(), Some("Report"));
+        assert_eq!(metadata.author.as_deref(), Some("Ann; Bob 中"));
+        assert_eq!(metadata.producer.as_deref(), Some("Tool & Co"));
+        assert_eq!(metadata.keywords, None);
+        let created = doc
+            .info()
+            .unwrap()
+            .get(b"CreationDate")
+            .and_then(Object::as_str)
+            .unwrap();
+        assert_eq!(created, b"D:20240229103000+01'00'");
+        assert!(metadata.creation_date.is_some());
+        assert!(metadata.mod_date.is_none());
+    }
+
+    #[test]
+    fn info_to_xmp_keeps_other_properties() {
+        let mut doc = create_document();
+        doc.set_xmp(PDFA_PACKET.as_bytes()).unwrap();
+        let mut metadata = doc.metadata();
+        metadata.title = Some("Summary <draft>".to_string());
+        metadata.author = Some("Ann; Carl".to_string());
+        metadata.keywords = Some("pdf, xmp".to_string());
+        doc.set_metadata(&metadata).unwrap();
+        doc.sync_metadata(MetadataSync::InfoToXmp).unwrap();
+        // A second synchronization replaces the properties written by the first one.
+        doc.sync_metadata(MetadataSync::InfoToXmp).unwrap();
+
+        let xml = String::from_utf8(doc.xmp().unwrap()).unwrap();
+        assert!(xml.contains("pdfaid:part=\"2\""));
+        assert!(!xml.contains("pdf:Producer"));
+        assert!(!xml.contains("Rapport"));
+        assert_eq!(xml.matches("<dc:title>").count(), 1);
+        assert!(xml.contains("<rdf:li xml:lang=\"x-default\">Summary &lt;draft&gt;</rdf:li>"));
+        assert!(xml.contains("<rdf:Seq><rdf:li>Ann</rdf:li><rdf:li>Carl</rdf:li></rdf:Seq>"));
+        assert!(xml.contains("<pdf:Keywords>pdf, xmp</pdf:Keywords>"));
+        let created = xml.split("<xmp:CreateDate>").nth(1).unwrap();
+        assert!(created.starts_with("20"));
+        assert!(xml.ends_with("<?xpacket end=\"w\"?>"));
+
+        doc.set_metadata(&Default::default()).unwrap();
+        doc.sync_metadata(MetadataSync::XmpToInfo).unwrap();
+        assert_eq!(doc.metadata().title.as_deref(), Some("Summary <draft>"));
+        assert_eq!(doc.metadata().author.as_deref(), Some("Ann; Carl"));
+    }
+
+    #[test]
+    fn metadata_stream_stays_unfiltered() {
+        let mut doc = create_document();
+        doc.sync_metadata(MetadataSync::InfoToXmp).unwrap();
+        doc.compress();
+        let metadata = doc
+            .catalog()
+            .unwrap()
+            .get(b"Metadata")
+            .and_then(Object::as_reference)
+            .unwrap();
+        let stream = doc.get_object(metadata).and_then(Object::as_stream).unwrap();
+        assert!(!stream.dict.has(b"Filter"));
+        assert_eq!(
+            stream.dict.get(b"Subtype").and_then(Object::as_name_str).unwrap(),
+            "XML"
+        );
+        assert!(String::from_utf8_lossy(&stream.content).contains("<xmp:CreateDate>"));
+    }
+
+    #[test]
+    fn convert_dates() {
+        assert_eq!(
+            pdf_date_to_xmp(b"D:20240229103000+01'00'").unwrap(),
+            "2024-02-29T10:30:00+01:00"
+        );
+        assert_eq!(pdf_date_to_xmp(b"D:2024").unwrap(), "2024-01-01T00:00:00");
+        assert_eq!(
+            pdf_date_to_xmp(b"D:199812231952-08'00'").unwrap(),
+            "1998-12-23T19:52:00-08:00"
+        );
+        assert_eq!(pdf_date_to_xmp(b"D:20240229103000Z").unwrap(), "2024-02-29T10:30:00Z");
+        assert_eq!(pdf_date_to_xmp(b"yesterday"), None);
+        assert_eq!(
+            xmp_date_to_pdf("2024-02-29T10:30:00.25-05:00").unwrap(),
+            "D:20240229103000-05'00'"
+        );
+        assert_eq!(xmp_date_to_pdf("2024-02-29").unwrap(), "D:20240229");
+        assert_eq!(xmp_date_to_pdf("2024-02-29T10:30Z").unwrap(), "D:202402291030Z");
+        assert_eq!(xmp_date_to_pdf("soon"), None);
+    }
+}
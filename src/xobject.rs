@@ -2,11 +2,14 @@ use crate::*;
 use crate::{Dictionary, Stream};
 
 #[cfg(feature = "embed_image")]
-use image::{self, ColorType, GenericImageView, ImageFormat};
+use image::{self, ColorType, GenericImageView};
 
-#[cfg(feature = "embed_image")]
+#[cfg(all(feature = "embed_image", not(all(target_arch = "wasm32", target_os = "unknown"))))]
 use std::path::Path;
 
+#[cfg(feature = "embed_image")]
+use crate::filters::jpeg::{self, JpegInfo};
+
 #[cfg(feature = "embed_image")]
 use crate::Result;
 
@@ -25,7 +28,23 @@ pub fn form(boundingbox: Vec<f64>, matrix: Vec<f64>, content: Vec<u8>) -> Stream
     xobject
 }
 
-#[cfg(feature = "embed_image")]
+/// Build an image XObject from JPEG 2000 data, either a JP2 file or a raw codestream.
+///
+/// The color space is left to the JPEG 2000 data.
+pub fn jp2_image(buffer: Vec<u8>) -> Result<Stream> {
+    let info =
+        crate::filters::jpx::read_info(&buffer).ok_or_else(|| Error::Syntax("Invalid JPEG 2000 data".to_string()))?;
+
+    let mut dict = Dictionary::new();
+    dict.set("Type", Object::Name(b"XObject".to_vec()));
+    dict.set("Subtype", Object::Name(b"Image".to_vec()));
+    dict.set("Width", info.width);
+    dict.set("Height", info.height);
+    dict.set("Filter", Object::Name(b"JPXDecode".to_vec()));
+    Ok(Stream::new(dict, buffer))
+}
+
+#[cfg(all(feature = "embed_image", not(all(target_arch = "wasm32", target_os = "unknown"))))]
 pub fn image<P: AsRef<Path>>(path: P) -> Result<Stream> {
     use std::fs::File;
     use std::io::prelude::*;
@@ -39,6 +58,10 @@ pub fn image<P: AsRef<Path>>(path: P) -> Result<Stream> {
 
 #[cfg(feature = "embed_image")]
 pub fn image_from(buffer: Vec<u8>) -> Result<Stream> {
+    if let Some(info) = jpeg::read_info(&buffer) {
+        return jpeg_image(buffer, info);
+    }
+
     let img = image::load_from_memory(buffer.as_ref())?;
 
     let (width, height) = img.dimensions();
@@ -69,23 +92,40 @@ pub fn image_from(buffer: Vec<u8>) -> Result<Stream> {
     dict.set("ColorSpace", Object::Name(color_space));
     dict.set("BitsPerComponent", bits);
 
-    let is_jpeg = match image::guess_format(buffer.as_ref()) {
-        Ok(format) => match format {
-            ImageFormat::Jpeg => true,
-            _ => false,
-        },
-        Err(_) => false,
+    let mut img_object = Stream::new(dict, img.to_bytes());
+    // Ignore any compression error.
+    let _ = img_object.compress();
+    Ok(img_object)
+}
+
+/// Embed JPEG data as is, taking the image properties from its header.
+#[cfg(feature = "embed_image")]
+fn jpeg_image(buffer: Vec<u8>, info: JpegInfo) -> Result<Stream> {
+    let color_space = match info.components {
+        1 => "DeviceGray",
+        3 => "DeviceRGB",
+        4 => "DeviceCMYK",
+        _ => return Err(Error::Unimplemented("JPEG component count")),
     };
 
-    if is_jpeg {
-        dict.set("Filter", Object::Name(b"DCTDecode".to_vec()));
-        Ok(Stream::new(dict, buffer))
-    } else {
-        let mut img_object = Stream::new(dict, img.to_bytes());
-        // Ignore any compression error.
-        let _ = img_object.compress();
-        Ok(img_object)
+    let mut dict = Dictionary::new();
+    dict.set("Type", Object::Name(b"XObject".to_vec()));
+    dict.set("Subtype", Object::Name(b"Image".to_vec()));
+    dict.set("Width", info.width);
+    dict.set("Height", info.height);
+    dict.set("ColorSpace", Object::Name(color_space.as_bytes().to_vec()));
+    dict.set("BitsPerComponent", info.bits_per_component);
+    if info.is_inverted_cmyk() {
+        dict.set(
+            "Decode",
+            [1, 0, 1, 0, 1, 0, 1, 0]
+                .iter()
+                .map(|&v| Object::Integer(v))
+                .collect::<Vec<_>>(),
+        );
     }
+    dict.set("Filter", Object::Name(b"DCTDecode".to_vec()));
+    Ok(Stream::new(dict, buffer))
 }
 
 #[cfg(feature = "embed_image")]
@@ -94,8 +134,21 @@ fn insert_image() {
     use super::xobject;
     let mut doc = Document::load("assets/example.pdf").unwrap();
     let pages = doc.get_pages();
-    let page_id = *pages.get(&1).expect(&format!("Page {} not exist.", 1));
+    let page_id = *pages.get(&1).expect("Page 1 not exist.");
     let img = xobject::image("assets/pdf_icon.jpg").unwrap();
     doc.insert_image(page_id, img, (100.0, 210.0), (400.0, 225.0)).unwrap();
     doc.save("test_5_image.pdf").unwrap();
 }
+
+#[cfg(feature = "embed_image")]
+#[test]
+fn inverted_cmyk_jpeg_gets_decode_array() {
+    let img = image("assets/cmyk_adobe.jpg").unwrap();
+    assert_eq!(
+        img.dict.get(b"ColorSpace").and_then(Object::as_name_str).unwrap(),
+        "DeviceCMYK"
+    );
+    let decode = img.dict.get(b"Decode").and_then(Object::as_array).unwrap();
+    assert_eq!(decode.len(), 8);
+    assert_eq!(decode[0].as_i64().unwrap(), 1);
+}
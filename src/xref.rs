@@ -1,4 +1,6 @@
-use std::collections::BTreeMap;
+use alloc::collections::BTreeMap;
+
+use crate::ObjectId;
 
 #[derive(Debug, Clone)]
 pub struct Xref {
@@ -12,7 +14,7 @@ pub struct Xref {
 #[derive(Debug, Clone)]
 pub enum XrefEntry {
     Free,
-    Normal { offset: u32, generation: u16 },
+    Normal { offset: u64, generation: u16 },
     Compressed { container: u32, index: u16 },
 }
 
@@ -44,6 +46,18 @@ impl Xref {
         self.entries.clear()
     }
 
+    /// How an object found in an object stream stands against the entries: 0 if its entry places it in that
+    /// stream, 1 if it has no entry, 2 if its entry places it elsewhere and 3 if its entry is free.
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    pub(crate) fn stream_object_rank(&self, number: u32, container: u32) -> u8 {
+        match self.entries.get(&number) {
+            Some(Compressed { container: placed, .. }) if *placed == container => 0,
+            None => 1,
+            Some(Free) => 3,
+            Some(_) => 2,
+        }
+    }
+
     pub fn max_id(&self) -> u32 {
         match self.entries.keys().max() {
             Some(&id) => id,
@@ -61,6 +75,15 @@ impl XrefEntry {
     pub fn is_compressed(&self) -> bool {
         matches!(*self, Compressed { .. })
     }
+
+    /// The identifier of the object the entry of an object number defines, if it is not free.
+    pub fn defined_id(&self, number: u32) -> Option<ObjectId> {
+        match *self {
+            Free => None,
+            Normal { generation, .. } => Some((number, generation)),
+            Compressed { .. } => Some((number, 0)),
+        }
+    }
 }
 
 #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
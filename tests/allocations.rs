@@ -0,0 +1,42 @@
+use lopdf::content::{Content, Operands, Operation};
+use lopdf::{Dictionary, Object};
+
+mod common;
+use common::{allocation_count, CountingAllocator};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[test]
+fn small_operands_and_dictionaries_share_allocations() {
+    // Up to two operands are held in the operation itself.
+    let (operands, allocations) = allocation_count(|| {
+        let mut operands = Operands::new();
+        operands.push(100.into());
+        operands.push(600.into());
+        operands
+    });
+    assert_eq!(allocations, 0);
+    assert_eq!(operands.iter().map(|operand| operand.as_i64().unwrap()).sum::<i64>(), 700);
+
+    // Copying a content takes one allocation for its operations and one for each operator.
+    let content = Content {
+        operations: (0..1000)
+            .map(|i| Operation::new("Td", vec![i.into(), 12.into()]))
+            .collect::<Vec<_>>(),
+    };
+    let (copy, allocations) = allocation_count(|| content.clone());
+    assert_eq!(allocations, 1001);
+    assert_eq!(copy.encode().unwrap(), content.encode().unwrap());
+
+    // The entries of a small dictionary take one allocation besides their keys.
+    let (dict, allocations) = allocation_count(|| {
+        let mut dict = Dictionary::new();
+        dict.set("MCID", 3);
+        dict.set("W", 1);
+        dict.set("H", 2);
+        dict
+    });
+    assert_eq!(allocations, 4);
+    assert_eq!(dict.get(b"MCID").and_then(Object::as_i64).unwrap(), 3);
+}
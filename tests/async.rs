@@ -0,0 +1,55 @@
+#![cfg(all(feature = "async", any(feature = "pom_parser", feature = "nom_parser")))]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use lopdf::{Document, ObjectAction, ParseOptions};
+use tokio::io::AsyncWriteExt;
+
+/// A reader of the fixture written a few bytes at a time, the writer waiting for each write to be read.
+fn throttled_fixture() -> tokio::io::DuplexStream {
+    let source = std::fs::read("assets/example.pdf").unwrap();
+    let (mut writer, reader) = tokio::io::duplex(16);
+    tokio::spawn(async move {
+        for chunk in source.chunks(7) {
+            writer.write_all(chunk).await.unwrap();
+            tokio::task::yield_now().await;
+        }
+    });
+    reader
+}
+
+#[tokio::test]
+async fn load_from_throttled_reader() {
+    let options = ParseOptions {
+        keep_source: true,
+        ..ParseOptions::default()
+    };
+    let doc = Document::load_from_async_with_options(throttled_fixture(), options).await.unwrap();
+    assert_eq!(doc.version, "1.5");
+    assert_eq!(doc.objects.len(), 6);
+    assert_eq!(doc.source(), std::fs::read("assets/example.pdf").unwrap());
+
+    let doc = Document::load_async("assets/example.pdf").await.unwrap();
+    assert_eq!(doc.get_pages().len(), 1);
+}
+
+#[tokio::test]
+async fn parse_on_blocking_thread() {
+    let filtered = Arc::new(AtomicUsize::new(0));
+    let counter = filtered.clone();
+    let options = ParseOptions {
+        object_filter: Some(Box::new(move |_id, _dict| {
+            counter.fetch_add(1, Ordering::Relaxed);
+            ObjectAction::Keep
+        })),
+        blocking_parse: true,
+        ..ParseOptions::default()
+    };
+    let doc = Document::load_from_async_with_options(throttled_fixture(), options)
+        .await
+        .unwrap();
+    assert_eq!(doc.objects.len(), 6);
+    // Every object is a dictionary or a stream.
+    assert_eq!(filtered.load(Ordering::Relaxed), 6);
+}
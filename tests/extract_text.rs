@@ -0,0 +1,102 @@
+#![cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+use std::io::{self, Write};
+
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Document, ExtractionOptions, Object, Stream, StringFormat};
+
+/// A document of pages of a few lines each, each page showing a code that the standard encoding has no text for.
+fn document_of_pages(page_count: i64) -> Document {
+    let mut doc = Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+    let font_id = doc.add_object(dictionary! { "Type" => "Font", "Subtype" => "Type1", "BaseFont" => "Courier" });
+    let mut kids = Vec::new();
+    for number in 1..=page_count {
+        let mut operations = vec![Operation::new("Tf", vec!["F1".into(), 12.into()])];
+        for line in 0..20 {
+            operations.push(Operation::new("BT", vec![]));
+            operations.push(Operation::new(
+                "Tj",
+                vec![Object::string_literal(format!("Page {} line {}", number, line))],
+            ));
+            operations.push(Operation::new("ET", vec![]));
+        }
+        operations.push(Operation::new("BT", vec![]));
+        operations.push(Operation::new(
+            "Tj",
+            vec![Object::String(vec![0x80], StringFormat::Hexadecimal)],
+        ));
+        operations.push(Operation::new("ET", vec![]));
+        let content = Content { operations }.encode().unwrap();
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content));
+        kids.push(Object::from(doc.add_object(
+            dictionary! { "Type" => "Page", "Parent" => pages_id, "Contents" => content_id },
+        )));
+    }
+    let count = kids.len() as i64;
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => kids,
+            "Count" => count,
+            "Resources" => dictionary! { "Font" => dictionary! { "F1" => font_id } },
+            "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+    doc.trailer.set("Root", catalog_id);
+    let mut buffer = Vec::new();
+    doc.save_to(&mut buffer).unwrap();
+    Document::load_mem(&buffer).unwrap()
+}
+
+/// A sink that keeps nothing but checks what is written against the text expected, and counts the writes.
+struct CountingSink<'a> {
+    expected: &'a [u8],
+    written: usize,
+    writes: usize,
+}
+
+impl Write for CountingSink<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        assert_eq!(buf, &self.expected[self.written..self.written + buf.len()]);
+        self.written += buf.len();
+        self.writes += 1;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn extract_text_page_by_page() {
+    let doc = document_of_pages(1500);
+    let pages: Vec<u32> = doc.get_pages().keys().copied().collect();
+    let text = doc.extract_text(&pages).unwrap();
+    assert!(text.starts_with("Page 1 line 0\nPage 1 line 1\n"));
+
+    let mut sink = CountingSink {
+        expected: text.as_bytes(),
+        written: 0,
+        writes: 0,
+    };
+    let stats = doc
+        .extract_text_to(&pages, &mut sink, ExtractionOptions::default())
+        .unwrap();
+    assert_eq!(sink.written, text.len());
+    // Each page is written at once.
+    assert_eq!(sink.writes, pages.len());
+    assert_eq!(stats.pages, pages.len());
+    assert_eq!(stats.unmapped_glyphs, pages.len());
+
+    let options = ExtractionOptions {
+        page_separator: "\x0c".to_string(),
+    };
+    let mut separated = Vec::new();
+    doc.extract_text_to(&pages[..3], &mut separated, options).unwrap();
+    let separated = String::from_utf8(separated).unwrap();
+    assert_eq!(separated.split('\x0c').count(), 3);
+    assert!(separated.split('\x0c').nth(2).unwrap().starts_with("Page 3 line 0\n"));
+}
@@ -0,0 +1,77 @@
+#![cfg(all(feature = "borrowed", any(feature = "pom_parser", feature = "nom_parser")))]
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Document, DocumentView, Object, Stream};
+
+mod common;
+use common::{peak_allocation, CountingAllocator};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// A document of pages each with a long content stream.
+fn document_of_pages(page_count: i64) -> Vec<u8> {
+    let mut doc = Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+    let font_id = doc.add_object(dictionary! { "Type" => "Font", "Subtype" => "Type1", "BaseFont" => "Courier" });
+    let mut kids = Vec::new();
+    for number in 1..=page_count {
+        let mut operations = vec![
+            Operation::new("BT", vec![]),
+            Operation::new("Tf", vec!["F1".into(), 12.into()]),
+            Operation::new("Tj", vec![Object::string_literal(format!("Page {}", number))]),
+            Operation::new("ET", vec![]),
+        ];
+        for line in 0..1000 {
+            operations.push(Operation::new("m", vec![line.into(), number.into()]));
+        }
+        let content = Content { operations }.encode().unwrap();
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content));
+        kids.push(Object::from(
+            doc.add_object(dictionary! { "Type" => "Page", "Parent" => pages_id, "Contents" => content_id }),
+        ));
+    }
+    let count = kids.len() as i64;
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => kids,
+            "Count" => count,
+            "Resources" => dictionary! { "Font" => dictionary! { "F1" => font_id } },
+            "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+    doc.trailer.set("Root", catalog_id);
+    let mut buffer = Vec::new();
+    doc.save_to(&mut buffer).unwrap();
+    buffer
+}
+
+/// The most bytes allocated at once to extract the text of a page through a view, and to load the document.
+fn page_and_document_peaks(page_count: i64) -> (usize, usize) {
+    let buffer = document_of_pages(page_count);
+    let (text, page_peak) = peak_allocation(|| {
+        let view = DocumentView::load(&buffer).unwrap();
+        view.with_page(7, |page| page.extract_text().unwrap()).unwrap()
+    });
+    assert_eq!(text.trim(), "Page 7");
+    let (_, document_peak) = peak_allocation(|| Document::load_mem(&buffer).unwrap());
+    (page_peak, document_peak)
+}
+
+#[test]
+fn page_memory_is_bounded() {
+    let (small_page_peak, small_document_peak) = page_and_document_peaks(20);
+    let (large_page_peak, large_document_peak) = page_and_document_peaks(400);
+
+    // The memory taken by a page alone hardly grows with the document, unlike that of loading it.
+    assert!(
+        large_page_peak < small_page_peak + small_page_peak / 2,
+        "{} bytes for a page of 20, {} for a page of 400",
+        small_page_peak,
+        large_page_peak
+    );
+    assert!(large_document_peak > small_document_peak * 10);
+    assert!(large_page_peak * 10 < large_document_peak);
+}
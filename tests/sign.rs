@@ -0,0 +1,131 @@
+#![cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+
+use std::str::FromStr;
+use std::time::Duration;
+
+use cms::builder::{SignedDataBuilder, SignerInfoBuilder};
+use cms::cert::x509::der::{Decode, Encode, SliceReader};
+use cms::cert::x509::spki::{AlgorithmIdentifierOwned, SubjectPublicKeyInfoOwned};
+use cms::cert::{CertificateChoices, IssuerAndSerialNumber};
+use cms::content_info::ContentInfo;
+use cms::signed_data::{EncapsulatedContentInfo, SignedData, SignerIdentifier};
+use lopdf::geometry::Rect;
+use lopdf::{DigestAlgorithm, Document, Error, ParseOptions, Result, Signer};
+use p256::ecdsa::signature::Verifier;
+use p256::ecdsa::{DerSignature, SigningKey, VerifyingKey};
+use x509_cert::builder::{Builder, CertificateBuilder, Profile};
+use x509_cert::name::Name;
+use x509_cert::serial_number::SerialNumber;
+use x509_cert::time::Validity;
+use x509_cert::Certificate;
+
+const ID_DATA: &str = "1.2.840.113549.1.7.1";
+const ID_SHA_256: &str = "2.16.840.1.101.3.4.2.1";
+
+/// Signs with a P-256 key and its self-signed certificate, producing detached CMS signatures.
+struct CmsSigner {
+    key: SigningKey,
+    certificate: Certificate,
+}
+
+impl CmsSigner {
+    fn new() -> CmsSigner {
+        let key = SigningKey::from_bytes(&[7; 32].into()).unwrap();
+        let public_key = SubjectPublicKeyInfoOwned::from_key(*key.verifying_key()).unwrap();
+        let subject = Name::from_str("CN=Test Signer,O=lopdf,C=GB").unwrap();
+        let validity = Validity::from_now(Duration::from_secs(3600)).unwrap();
+        let certificate = CertificateBuilder::new(
+            Profile::Root,
+            SerialNumber::from(1_u32),
+            validity,
+            subject,
+            public_key,
+            &key,
+        )
+        .unwrap()
+        .build::<DerSignature>()
+        .unwrap();
+        CmsSigner { key, certificate }
+    }
+}
+
+impl Signer for CmsSigner {
+    fn sign(&self, data_to_be_signed: &[u8]) -> Result<Vec<u8>> {
+        let error = |err: &dyn std::fmt::Display| Error::Signature(err.to_string());
+        let digest = DigestAlgorithm::Sha256.digest(data_to_be_signed);
+        let content = EncapsulatedContentInfo {
+            econtent_type: ID_DATA.parse().unwrap(),
+            econtent: None,
+        };
+        let digest_algorithm = AlgorithmIdentifierOwned {
+            oid: ID_SHA_256.parse().unwrap(),
+            parameters: None,
+        };
+        let tbs_certificate = &self.certificate.tbs_certificate;
+        let signer_identifier = SignerIdentifier::IssuerAndSerialNumber(IssuerAndSerialNumber {
+            issuer: tbs_certificate.issuer.clone(),
+            serial_number: tbs_certificate.serial_number.clone(),
+        });
+        let signer_info = SignerInfoBuilder::new(
+            &self.key,
+            signer_identifier,
+            digest_algorithm.clone(),
+            &content,
+            Some(&digest),
+        )
+        .map_err(|err| error(&err))?;
+        SignedDataBuilder::new(&content)
+            .add_digest_algorithm(digest_algorithm)
+            .and_then(|builder| builder.add_certificate(CertificateChoices::Certificate(self.certificate.clone())))
+            .and_then(|builder| builder.add_signer_info::<_, DerSignature>(signer_info))
+            .and_then(|builder| builder.build())
+            .map_err(|err| error(&err))?
+            .to_der()
+            .map_err(|err| error(&err))
+    }
+}
+
+#[test]
+fn sign_with_self_signed_certificate() {
+    let options = ParseOptions {
+        keep_source: true,
+        ..ParseOptions::default()
+    };
+    let mut doc = Document::load_with_options("assets/example.pdf", &options).unwrap();
+    let page_id = doc.page_iter().next().unwrap();
+    let rect = Rect::new(50.0, 50.0, 250.0, 100.0);
+    let field = doc
+        .add_signature_field(page_id, "approval", rect, &Default::default())
+        .unwrap();
+    let signer = CmsSigner::new();
+    let mut signed = Vec::new();
+    doc.sign(&mut signed, field, &signer).unwrap();
+
+    let doc = Document::load_mem(&signed).unwrap();
+    let signature = &doc.signatures()[0];
+    assert!(signature.byte_range_valid);
+    assert!(signature.covers_whole_file);
+    #[cfg(feature = "crypto")]
+    {
+        let cms = signature.cms.as_ref().unwrap();
+        assert_eq!(cms.digest_algorithm, DigestAlgorithm::Sha256);
+        assert_eq!(cms.digest_matches, Some(true));
+        assert_eq!(cms.signer.as_deref(), Some("C=GB, O=lopdf, CN=Test Signer"));
+    }
+
+    // The signature in /Contents, followed by the zeros of the rest of the placeholder, verifies with the key of
+    // the certificate it carries.
+    let content_info = ContentInfo::decode(&mut SliceReader::new(&signature.contents).unwrap()).unwrap();
+    let signed_data = content_info.content.decode_as::<SignedData>().unwrap();
+    let certificate = match signed_data.certificates.as_ref().unwrap().0.get(0) {
+        Some(CertificateChoices::Certificate(certificate)) => certificate,
+        _ => panic!("the signature holds no certificate"),
+    };
+    assert_eq!(certificate, &signer.certificate);
+    let public_key = &certificate.tbs_certificate.subject_public_key_info.subject_public_key;
+    let verifying_key = VerifyingKey::from_sec1_bytes(public_key.raw_bytes()).unwrap();
+    let signer_info = signed_data.signer_infos.0.get(0).unwrap();
+    let signed_attributes = signer_info.signed_attrs.as_ref().unwrap().to_der().unwrap();
+    let value = DerSignature::from_bytes(signer_info.signature.as_bytes()).unwrap();
+    verifying_key.verify(&signed_attributes, &value).unwrap();
+}
@@ -0,0 +1,50 @@
+#![cfg(feature = "std")]
+
+use lopdf::{dictionary, Codecs, Stream};
+
+mod common;
+use common::{allocation_count, CountingAllocator};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Small streams such as `/ToUnicode` maps and cross-reference streams, the latter with a PNG predictor.
+fn small_streams() -> Vec<Stream> {
+    (0..1000)
+        .map(|i| {
+            let mut stream = if i % 2 == 0 {
+                let map = format!("<{:04X}> <{:04X}>\n", i, i + 0x41).repeat(20);
+                Stream::new(dictionary! {}, map.into_bytes())
+            } else {
+                // Rows of entries of 1, 3 and 1 bytes, each added to the row above.
+                let rows = (0..100_u32)
+                    .flat_map(|j| [2, 1, 0, (j >> 8) as u8, j as u8, 0])
+                    .collect();
+                let params = dictionary! { "Predictor" => 12, "Columns" => 5 };
+                Stream::new(dictionary! { "Type" => "XRef", "DecodeParms" => params }, rows)
+            };
+            stream.compress().unwrap();
+            stream
+        })
+        .collect()
+}
+
+#[test]
+fn small_streams_decode_in_one_allocation() {
+    let streams = small_streams();
+    assert!(streams.iter().all(|stream| stream.dict.has(b"Filter")));
+
+    let codecs = Codecs::new();
+    streams[1].decompressed_content_with(&codecs).unwrap();
+    let (decoded, allocations) = allocation_count(|| {
+        streams
+            .iter()
+            .map(|stream| stream.decompressed_content_with(&codecs).unwrap())
+            .collect::<Vec<_>>()
+    });
+    // One allocation for each decoded content, besides the vector holding them.
+    assert_eq!(allocations, streams.len() + 1);
+    assert!(decoded[0].starts_with(b"<0000> <0041>\n"));
+    assert_eq!(decoded[1].len(), 500);
+    assert_eq!(decoded[1][5..10], [2, 0, 0, 1, 0]);
+}
@@ -0,0 +1,125 @@
+#![cfg(all(feature = "tracing", any(feature = "pom_parser", feature = "nom_parser")))]
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use lopdf::{Dictionary, Document, Stream};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+/// The fields of a span or an event, formatted.
+type Fields = BTreeMap<String, String>;
+
+struct FieldsVisitor<'a>(&'a mut Fields);
+
+impl Visit for FieldsVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name().to_string(), format!("{:?}", value));
+    }
+}
+
+/// A subscriber keeping every span, with the fields recorded on it, and every event.
+#[derive(Default, Clone)]
+struct Collector {
+    spans: Arc<Mutex<Vec<(&'static str, Fields)>>>,
+    events: Arc<Mutex<Vec<(&'static str, Fields)>>>,
+}
+
+impl Collector {
+    fn spans(&self, name: &str) -> Vec<Fields> {
+        let spans = self.spans.lock().unwrap();
+        spans
+            .iter()
+            .filter(|(span, _)| *span == name)
+            .map(|(_, fields)| fields.clone())
+            .collect()
+    }
+
+    fn count(fields: &Fields, name: &str) -> usize {
+        fields[name].parse().unwrap()
+    }
+}
+
+impl Subscriber for Collector {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut fields = Fields::new();
+        span.record(&mut FieldsVisitor(&mut fields));
+        let mut spans = self.spans.lock().unwrap();
+        spans.push((span.metadata().name(), fields));
+        Id::from_u64(spans.len() as u64)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        let mut spans = self.spans.lock().unwrap();
+        values.record(&mut FieldsVisitor(&mut spans[span.into_u64() as usize - 1].1));
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields::new();
+        event.record(&mut FieldsVisitor(&mut fields));
+        self.events.lock().unwrap().push((event.metadata().target(), fields));
+    }
+
+    fn enter(&self, _span: &Id) {}
+
+    fn exit(&self, _span: &Id) {}
+}
+
+#[test]
+fn spans_of_load_save_and_extraction() {
+    // A trailer with a wrong /Size, which loading corrects.
+    let source = std::fs::read("assets/example.pdf").unwrap();
+    let position = source.windows(7).position(|window| window == b"/Size 7").unwrap();
+    let mut malformed = source.clone();
+    malformed[position + 6] = b'9';
+
+    let collector = Collector::default();
+    let mut saved = Vec::new();
+    tracing::subscriber::with_default(collector.clone(), || {
+        let mut doc = Document::load_mem(&malformed).unwrap();
+        let mut stream = Stream::new(Dictionary::new(), b"0 0 m 10 10 l S\n".repeat(50));
+        stream.compress().unwrap();
+        stream.decompressed_content().unwrap();
+        assert!(doc.extract_text(&[1]).unwrap().contains("Hello World!"));
+        doc.save_to(&mut saved).unwrap();
+        doc.renumber_objects();
+    });
+
+    let load = &collector.spans("load")[0];
+    assert_eq!(Collector::count(load, "bytes"), malformed.len());
+    assert_eq!(Collector::count(load, "objects"), 6);
+    assert!(load.contains_key("duration_us"));
+    let xref = &collector.spans("xref")[0];
+    assert_eq!(
+        (Collector::count(xref, "sections"), Collector::count(xref, "entries")),
+        (1, 6)
+    );
+    assert_eq!(Collector::count(&collector.spans("parse_objects")[0], "objects"), 6);
+
+    let page = &collector.spans("extract_page_text")[0];
+    assert_eq!(page["page"], "(5, 0)");
+    assert!(Collector::count(page, "bytes") > 0);
+    let decompress = &collector.spans("decompress")[0];
+    assert_eq!(Collector::count(decompress, "filters"), 1);
+    assert_eq!(Collector::count(decompress, "decoded_bytes"), 800);
+    assert!(Collector::count(decompress, "bytes") < 800);
+
+    let save = &collector.spans("save")[0];
+    assert_eq!(Collector::count(save, "bytes"), saved.len());
+    for phase in &["write_objects", "write_xref", "write_trailer"] {
+        assert_eq!(collector.spans(phase).len(), 1, "{}", phase);
+    }
+    assert_eq!(Collector::count(&collector.spans("renumber")[0], "objects"), 6);
+
+    let events = collector.events.lock().unwrap();
+    let repair = events.iter().find(|(target, _)| *target == "lopdf::repair").unwrap();
+    assert_eq!((repair.1["size"].as_str(), repair.1["corrected"].as_str()), ("9", "7"));
+}